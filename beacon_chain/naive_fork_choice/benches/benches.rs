@@ -0,0 +1,42 @@
+#[macro_use]
+extern crate criterion;
+extern crate db;
+extern crate naive_fork_choice;
+extern crate ssz;
+extern crate types;
+
+use criterion::{black_box, Benchmark, Criterion};
+use db::stores::BeaconBlockStore;
+use db::MemoryDB;
+use naive_fork_choice::naive_fork_choice;
+use ssz::ssz_encode;
+use std::sync::Arc;
+use types::{BeaconBlock, Hash256};
+
+/// Chooses between `count` head blocks, each of which is loaded and decoded from the store.
+fn head(c: &mut Criterion) {
+    for &count in &[1, 16, 256] {
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        let heads: Vec<Hash256> = (0..count)
+            .map(|i| {
+                let mut block = BeaconBlock::zero();
+                block.slot = 100 + i % 4;
+                block.graffiti = Hash256::from(i);
+                let root = Hash256::from(i + 1);
+                store
+                    .put_serialized_block(&root, &ssz_encode(&block))
+                    .unwrap();
+                root
+            })
+            .collect();
+        c.bench(
+            "fork_choice",
+            Benchmark::new(format!("{}_heads", count), move |b| {
+                b.iter(|| black_box(naive_fork_choice(&heads, store.clone()).is_ok()))
+            }),
+        );
+    }
+}
+
+criterion_group!(benches, head);
+criterion_main!(benches);
@@ -37,11 +37,11 @@ where
     /*
      * Loop through all the head blocks and find the highest slot.
      */
-    let highest_slot: Option<u64> = None;
+    let mut highest_slot: Option<u64> = None;
     for (_, block) in &head_blocks {
         let slot = block.slot;
 
-        match highest_slot {
+        highest_slot = match highest_slot {
             None => Some(slot),
             Some(winning_slot) => {
                 if slot > winning_slot {
@@ -90,8 +90,32 @@ impl From<DBError> for ForkChoiceError {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use db::MemoryDB;
+    use ssz::ssz_encode;
+
+    fn store_block(store: &BeaconBlockStore<MemoryDB>, hash: Hash256, slot: u64) {
+        let mut block = BeaconBlock::zero();
+        block.slot = slot;
+        store
+            .put_serialized_block(&hash, &ssz_encode(&block))
+            .unwrap();
+    }
+
     #[test]
     fn test_naive_fork_choice() {
-        assert_eq!(2 + 2, 4);
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        store_block(&store, Hash256::from(1), 5);
+        store_block(&store, Hash256::from(3), 7);
+        store_block(&store, Hash256::from(2), 7);
+
+        /*
+         * The highest slot wins, with ties broken by the lowest hash.
+         */
+        let heads = vec![Hash256::from(1), Hash256::from(3), Hash256::from(2)];
+        assert_eq!(naive_fork_choice(&heads, store.clone()).ok(), Some(Some(2)));
+
+        assert_eq!(naive_fork_choice(&vec![], store.clone()).ok(), Some(None));
+        assert!(naive_fork_choice(&vec![Hash256::from(4)], store).is_err());
     }
 }
@@ -1,7 +1,8 @@
+use super::ssz::{Decodable, DecodeError, Encodable, SszStream};
 use super::Hash256;
 use super::{Attestation, SpecialRecord};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct ActiveState {
     pub pending_attestations: Vec<Attestation>,
     pub pending_specials: Vec<SpecialRecord>,
@@ -15,3 +16,49 @@ impl ActiveState {
         Hash256::zero()
     }
 }
+
+impl Encodable for ActiveState {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append_vec(&self.pending_attestations);
+        s.append_vec(&self.pending_specials);
+        s.append_vec(&self.recent_block_hashes);
+        s.append(&self.randao_mix);
+    }
+}
+
+impl Decodable for ActiveState {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (pending_attestations, i) = Decodable::ssz_decode(bytes, i)?;
+        let (pending_specials, i) = Decodable::ssz_decode(bytes, i)?;
+        let (recent_block_hashes, i) = Decodable::ssz_decode(bytes, i)?;
+        let (randao_mix, i) = Hash256::ssz_decode(bytes, i)?;
+        let state = ActiveState {
+            pending_attestations,
+            pending_specials,
+            recent_block_hashes,
+            randao_mix,
+        };
+        Ok((state, i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_state_ssz_encode_decode() {
+        let state = ActiveState {
+            pending_attestations: vec![Attestation::zero()],
+            pending_specials: vec![SpecialRecord::logout(&[1, 2])],
+            recent_block_hashes: vec![Hash256::zero(), Hash256::from("block".as_bytes())],
+            randao_mix: Hash256::from("mix".as_bytes()),
+        };
+
+        let mut ssz_stream = SszStream::new();
+        ssz_stream.append(&state);
+        let ssz = ssz_stream.drain();
+
+        assert_eq!(ActiveState::ssz_decode(&ssz, 0), Ok((state, ssz.len())));
+    }
+}
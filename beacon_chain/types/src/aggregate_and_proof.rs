@@ -0,0 +1,90 @@
+use super::bls::Signature;
+use super::hashing::canonical_hash;
+use super::ssz::{decode_ssz_list, Decodable, DecodeError, Encodable, SszStream};
+use super::Attestation;
+
+/// The number of validators of each committee expected to be selected as aggregators.
+pub const TARGET_AGGREGATORS_PER_COMMITTEE: usize = 16;
+
+/// An aggregate attestation, together with the proof that its aggregator was selected to
+/// aggregate for the committee.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateAndProof {
+    pub aggregator_index: u64,
+    pub aggregate: Attestation,
+    /// The aggregator's signature over the slot of the aggregate.
+    pub selection_proof: Signature,
+}
+
+/// Returns true if the validator with `selection_proof` is selected to aggregate for a committee
+/// of `committee_len` validators.
+///
+/// The hash of the proof selects about `TARGET_AGGREGATORS_PER_COMMITTEE` validators of each
+/// committee, without revealing which until each publishes its proof.
+pub fn is_aggregator(committee_len: usize, selection_proof: &Signature) -> bool {
+    let modulo = (committee_len / TARGET_AGGREGATORS_PER_COMMITTEE).max(1) as u64;
+    let hash = canonical_hash(&selection_proof.as_bytes());
+    let value = hash[..8]
+        .iter()
+        .rev()
+        .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte));
+    value % modulo == 0
+}
+
+impl Encodable for AggregateAndProof {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.aggregator_index);
+        s.append(&self.aggregate);
+        s.append_vec(&self.selection_proof.as_bytes());
+    }
+}
+
+impl Decodable for AggregateAndProof {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (aggregator_index, i) = u64::ssz_decode(bytes, i)?;
+        let (aggregate, i) = Attestation::ssz_decode(bytes, i)?;
+        let (proof_bytes, i) = decode_ssz_list(bytes, i)?;
+        let selection_proof =
+            Signature::from_bytes(&proof_bytes).map_err(|_| DecodeError::TooShort)?;
+        Ok((
+            Self {
+                aggregator_index,
+                aggregate,
+                selection_proof,
+            },
+            i,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bls::Keypair;
+    use super::super::ssz::ssz_encode;
+    use super::*;
+
+    #[test]
+    fn test_is_aggregator() {
+        let keypair = Keypair::random();
+        let proofs: Vec<Signature> = (0..256u64)
+            .map(|slot| Signature::new(&ssz_encode(&slot), &keypair.sk))
+            .collect();
+        assert!(proofs.iter().all(|proof| is_aggregator(16, proof)));
+        let selected = proofs.iter().filter(|proof| is_aggregator(64, proof)).count();
+        assert!(selected > 32 && selected < 96);
+    }
+
+    #[test]
+    fn test_ssz_round_trip() {
+        let keypair = Keypair::random();
+        let original = AggregateAndProof {
+            aggregator_index: 7,
+            aggregate: Attestation::zero(),
+            selection_proof: Signature::new(&[1, 2, 3], &keypair.sk),
+        };
+        let ssz = ssz_encode(&original);
+        let (decoded, i) = AggregateAndProof::ssz_decode(&ssz, 0).unwrap();
+        assert_eq!(decoded, original);
+        assert_eq!(i, ssz.len());
+    }
+}
@@ -1,6 +1,7 @@
 use super::attestation::Attestation;
+use super::hashing::canonical_hash;
 use super::special_record::SpecialRecord;
-use super::ssz::{Decodable, DecodeError, Encodable, SszStream};
+use super::ssz::{ssz_encode, Decodable, DecodeError, Encodable, SszStream};
 use super::Hash256;
 
 pub const MIN_SSZ_BLOCK_LENGTH: usize = {
@@ -11,7 +12,8 @@ pub const MIN_SSZ_BLOCK_LENGTH: usize = {
     32 +                // active_state_root
     32 +                // crystallized_state_root
     4 +                 // attestations (assuming empty)
-    4 // specials (assuming empty)
+    4 +                 // specials (assuming empty)
+    32 // graffiti
 };
 pub const MAX_SSZ_BLOCK_LENGTH: usize = MIN_SSZ_BLOCK_LENGTH + (1 << 24);
 
@@ -25,6 +27,8 @@ pub struct BeaconBlock {
     pub crystallized_state_root: Hash256,
     pub attestations: Vec<Attestation>,
     pub specials: Vec<SpecialRecord>,
+    /// Arbitrary bytes chosen by the proposer.
+    pub graffiti: Hash256,
 }
 
 impl BeaconBlock {
@@ -38,6 +42,7 @@ impl BeaconBlock {
             crystallized_state_root: Hash256::zero(),
             attestations: vec![],
             specials: vec![],
+            graffiti: Hash256::zero(),
         }
     }
 
@@ -47,6 +52,12 @@ impl BeaconBlock {
     pub fn parent_hash(&self) -> Option<&Hash256> {
         self.ancestor_hashes.get(0)
     }
+
+    /// Returns the root by which the block is referenced by its children, which is also the
+    /// object root signed by its proposer.
+    pub fn canonical_root(&self) -> Hash256 {
+        Hash256::from(&canonical_hash(&ssz_encode(self))[..])
+    }
 }
 
 impl Encodable for BeaconBlock {
@@ -59,6 +70,7 @@ impl Encodable for BeaconBlock {
         s.append(&self.crystallized_state_root);
         s.append_vec(&self.attestations);
         s.append_vec(&self.specials);
+        s.append(&self.graffiti);
     }
 }
 
@@ -72,6 +84,7 @@ impl Decodable for BeaconBlock {
         let (crystallized_state_root, i) = Hash256::ssz_decode(bytes, i)?;
         let (attestations, i) = Decodable::ssz_decode(bytes, i)?;
         let (specials, i) = Decodable::ssz_decode(bytes, i)?;
+        let (graffiti, i) = Hash256::ssz_decode(bytes, i)?;
         let block = BeaconBlock {
             slot,
             randao_reveal,
@@ -81,6 +94,7 @@ impl Decodable for BeaconBlock {
             crystallized_state_root,
             attestations,
             specials,
+            graffiti,
         };
         Ok((block, i))
     }
@@ -101,12 +115,14 @@ mod tests {
         assert!(b.crystallized_state_root.is_zero());
         assert_eq!(b.attestations.len(), 0);
         assert_eq!(b.specials.len(), 0);
+        assert!(b.graffiti.is_zero());
     }
 
     #[test]
     pub fn test_block_ssz_encode_decode() {
         let mut b = BeaconBlock::zero();
         b.ancestor_hashes = vec![Hash256::zero(); 32];
+        b.graffiti = Hash256::from("graffiti".as_bytes());
 
         let mut ssz_stream = SszStream::new();
         ssz_stream.append(&b);
@@ -139,4 +155,14 @@ mod tests {
 
         assert_eq!(b.parent_hash().unwrap(), &Hash256::from("cats".as_bytes()));
     }
+
+    #[test]
+    pub fn test_block_canonical_root() {
+        let mut b = BeaconBlock::zero();
+        let root = b.canonical_root();
+        assert_eq!(root, Hash256::from(&canonical_hash(&ssz_encode(&b))[..]));
+
+        b.graffiti = Hash256::from("graffiti".as_bytes());
+        assert_ne!(b.canonical_root(), root);
+    }
 }
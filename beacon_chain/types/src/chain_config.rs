@@ -40,6 +40,19 @@ impl ChainConfig {
         }
     }
 
+    /// The "minimal" preset of the spec, with small committees and short cycles, for spec tests
+    /// and local devnets.
+    pub fn minimal() -> Self {
+        Self {
+            cycle_length: 4,
+            shard_count: 4,
+            min_committee_size: 2,
+            epoch_length: 4,
+            min_attestation_inclusion_delay: 1,
+            ..Self::standard()
+        }
+    }
+
     pub fn validate(&self) -> bool {
         // criteria that ensure the config is valid
 
@@ -70,3 +83,14 @@ impl ChainConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_valid() {
+        assert!(ChainConfig::standard().validate());
+        assert!(ChainConfig::minimal().validate());
+    }
+}
@@ -1,9 +1,11 @@
 extern crate bls;
 extern crate boolean_bitfield;
 extern crate ethereum_types;
+extern crate hashing;
 extern crate ssz;
 
 pub mod active_state;
+pub mod aggregate_and_proof;
 pub mod attestation_data;
 pub mod attestation;
 pub mod beacon_block;
@@ -16,14 +18,19 @@ pub mod fork_data;
 pub mod pending_attestation_record;
 pub mod shard_and_committee;
 pub mod shard_reassignment_record;
+pub mod signing;
 pub mod special_record;
 pub mod validator_record;
 pub mod validator_registration;
 
+#[cfg(test)]
+mod ssz_static;
+
 use self::ethereum_types::{H160, H256, U256};
 use std::collections::HashMap;
 
 pub use active_state::ActiveState;
+pub use aggregate_and_proof::{is_aggregator, AggregateAndProof, TARGET_AGGREGATORS_PER_COMMITTEE};
 pub use attestation_data::AttestationData;
 pub use attestation::Attestation;
 pub use beacon_block::BeaconBlock;
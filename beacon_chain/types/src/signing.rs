@@ -0,0 +1,61 @@
+use super::hashing::canonical_hash;
+use super::ssz::ssz_encode;
+use super::{AttestationData, Hash256};
+
+/*
+ * Each kind of message is signed in its own domain, so that a signature over one kind of message
+ * is never valid as another. The domain also commits to the fork digest, so that signatures are
+ * not valid on other chains.
+ */
+
+pub const DOMAIN_PROPOSAL: u32 = 0;
+pub const DOMAIN_RANDAO: u32 = 1;
+pub const DOMAIN_ATTESTATION: u32 = 2;
+pub const DOMAIN_SELECTION_PROOF: u32 = 3;
+pub const DOMAIN_AGGREGATE_AND_PROOF: u32 = 4;
+
+/// Returns the domain of `domain_type` on the chain of `fork_digest`: the little-endian domain
+/// type followed by the fork digest.
+pub fn domain(domain_type: u32, fork_digest: [u8; 4]) -> [u8; 8] {
+    let mut domain = [0; 8];
+    for (i, byte) in domain[..4].iter_mut().enumerate() {
+        *byte = (domain_type >> (8 * i)) as u8;
+    }
+    domain[4..].copy_from_slice(&fork_digest);
+    domain
+}
+
+/// Returns the root which is signed for an object with `object_root` in `domain`.
+pub fn signing_root(object_root: &Hash256, domain: [u8; 8]) -> Hash256 {
+    let mut bytes = object_root.to_vec();
+    bytes.extend_from_slice(&domain);
+    Hash256::from(&canonical_hash(&bytes)[..])
+}
+
+/// Returns the root of `data`, by which the beacon node looks up its aggregate.
+pub fn attestation_data_root(data: &AttestationData) -> Hash256 {
+    Hash256::from(&canonical_hash(&ssz_encode(data))[..])
+}
+
+/// Returns the root which is signed for `data` on the chain of `fork_digest`.
+pub fn attestation_signing_root(data: &AttestationData, fork_digest: [u8; 4]) -> Hash256 {
+    signing_root(
+        &attestation_data_root(data),
+        domain(DOMAIN_ATTESTATION, fork_digest),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain() {
+        assert_eq!(domain(0x0102, [5, 6, 7, 8]), [2, 1, 0, 0, 5, 6, 7, 8]);
+        let data = AttestationData::zero();
+        assert_ne!(
+            attestation_signing_root(&data, [0; 4]),
+            attestation_signing_root(&data, [1; 4])
+        );
+    }
+}
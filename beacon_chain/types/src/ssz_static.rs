@@ -0,0 +1,246 @@
+/*
+ * Runs `ssz_static` vectors against each of the consensus types: every serialization is decoded,
+ * encoded again and hashed, and must reproduce the serialization and root of the vector.
+ *
+ * The bundled vectors, in `src/specs/ssz_static`, are regression vectors of this tree's own
+ * encoding rather than vectors of the spec: their serializations are those of the encoders here,
+ * and their roots are the `canonical_hash` of the serialization, by which the chain identifies
+ * blocks and states, as this tree has no merkleization. The signatures of the `Attestation` and
+ * `AggregateAndProof` cases are points of G1, so that they decode.
+ *
+ * Another directory of vectors may be given by the `SSZ_STATIC_VECTORS` variable, laid out as
+ * `<fork>/<Type>/<case>/serialized.ssz` and `roots.yaml`. A type without vectors in any fork
+ * fails.
+ */
+extern crate hex;
+extern crate yaml_rust;
+
+use self::yaml_rust::YamlLoader;
+use super::hashing::canonical_hash;
+use super::ssz::{ssz_encode, Decodable, Encodable};
+use super::{
+    ActiveState, AggregateAndProof, Attestation, AttestationData, BeaconBlock, SpecialRecord,
+};
+use std::env;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const VECTORS_VAR: &str = "SSZ_STATIC_VECTORS";
+const BUNDLED_VECTORS: &str = "./src/specs/ssz_static";
+const SERIALIZED_FILE: &str = "serialized.ssz";
+const ROOTS_FILE: &str = "roots.yaml";
+
+/// A case of the vectors, named `<fork>/<Type>/<case>`.
+struct Case {
+    name: String,
+    serialized: Vec<u8>,
+    root: Vec<u8>,
+}
+
+fn vectors_dir() -> PathBuf {
+    env::var_os(VECTORS_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(BUNDLED_VECTORS))
+}
+
+/// Returns the directories within `dir`, sorted by name.
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    };
+    dirs.sort();
+    dirs
+}
+
+/// Returns the cases of `type_name` across every fork.
+fn cases(dir: &Path, type_name: &str) -> Vec<Case> {
+    let mut cases = vec![];
+    for fork in sub_dirs(dir) {
+        for case in sub_dirs(&fork.join(type_name)) {
+            let name = format!("{}", case.strip_prefix(dir).unwrap().display());
+            let serialized = fs::read(case.join(SERIALIZED_FILE))
+                .unwrap_or_else(|e| panic!("{}: unable to read {}: {}", name, SERIALIZED_FILE, e));
+            let roots = fs::read_to_string(case.join(ROOTS_FILE))
+                .unwrap_or_else(|e| panic!("{}: unable to read {}: {}", name, ROOTS_FILE, e));
+            let root = YamlLoader::load_from_str(&roots)
+                .ok()
+                .and_then(|docs| docs.into_iter().next())
+                .and_then(|doc| doc["root"].as_str().map(|root| root.to_string()))
+                .and_then(|root| hex::decode(root.trim_start_matches("0x")).ok())
+                .unwrap_or_else(|| panic!("{}: {} has no hex root", name, ROOTS_FILE));
+            cases.push(Case {
+                name,
+                serialized,
+                root,
+            });
+        }
+    }
+    cases
+}
+
+/// Describes the first line at which the debug representations of `expected` and `actual`
+/// differ, which names the first mismatching field.
+fn first_difference<T: Debug>(expected: &T, actual: &T) -> String {
+    let expected = format!("{:#?}", expected);
+    let actual = format!("{:#?}", actual);
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => continue,
+            (None, None) => return "no difference".to_string(),
+            (e, a) => {
+                return format!(
+                    "expected `{}`, got `{}`",
+                    e.unwrap_or("").trim(),
+                    a.unwrap_or("").trim()
+                )
+            }
+        }
+    }
+}
+
+/// Decodes, encodes and hashes the serialization of `case`.
+fn run_case<T: Encodable + Decodable + Debug + PartialEq>(case: &Case) -> Result<(), String> {
+    let (value, i) =
+        T::ssz_decode(&case.serialized, 0).map_err(|e| format!("unable to decode: {:?}", e))?;
+    if i != case.serialized.len() {
+        return Err(format!(
+            "decoding read {} of {} bytes",
+            i,
+            case.serialized.len()
+        ));
+    }
+
+    let encoded = ssz_encode(&value);
+    if encoded != case.serialized {
+        /*
+         * Encoding lost or changed something, which decoding the result again locates.
+         */
+        return Err(match T::ssz_decode(&encoded, 0) {
+            Ok((ref round_tripped, _)) if *round_tripped != value => format!(
+                "the encoding differs at {}",
+                first_difference(&value, round_tripped)
+            ),
+            Ok(_) => {
+                let offset = encoded
+                    .iter()
+                    .zip(&case.serialized)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or_else(|| encoded.len().min(case.serialized.len()));
+                format!(
+                    "the encoding of {} bytes differs from the {} bytes of the vector at byte {}",
+                    encoded.len(),
+                    case.serialized.len(),
+                    offset
+                )
+            }
+            Err(e) => format!("unable to decode the encoding: {:?}", e),
+        });
+    }
+
+    let root = canonical_hash(&encoded);
+    if root != case.root {
+        return Err(format!(
+            "expected root 0x{}, got 0x{}",
+            hex::encode(&case.root),
+            hex::encode(&root)
+        ));
+    }
+    Ok(())
+}
+
+/// Runs every case of `type_name`, failing with each which does not round trip, or if there are
+/// none.
+fn run_type<T: Encodable + Decodable + Debug + PartialEq>(type_name: &str) {
+    let dir = vectors_dir();
+    let cases = cases(&dir, type_name);
+    assert!(
+        !cases.is_empty(),
+        "No ssz_static vectors for {} in {}",
+        type_name,
+        dir.display()
+    );
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|case| {
+            run_case::<T>(case)
+                .err()
+                .map(|e| format!("{}: {}", case.name, e))
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
+
+/// Generates a test running the vectors of each type.
+macro_rules! ssz_static_tests {
+    ($($test:ident: $type:ident),*) => {
+        $(
+            #[test]
+            fn $test() {
+                run_type::<$type>(stringify!($type));
+            }
+        )*
+    };
+}
+
+ssz_static_tests!(
+    test_active_state: ActiveState,
+    test_aggregate_and_proof: AggregateAndProof,
+    test_attestation: Attestation,
+    test_attestation_data: AttestationData,
+    test_beacon_block: BeaconBlock,
+    test_special_record: SpecialRecord
+);
+
+#[test]
+fn test_reports_first_mismatching_field() {
+    let mut block = BeaconBlock::zero();
+    block.slot = 3;
+    let serialized = ssz_encode(&block);
+    let case = Case {
+        name: "phase0/BeaconBlock/case_0".to_string(),
+        serialized: serialized.clone(),
+        root: canonical_hash(&serialized),
+    };
+    assert_eq!(run_case::<BeaconBlock>(&case), Ok(()));
+
+    /*
+     * Trailing bytes are not decoded.
+     */
+    let mut trailing = serialized.clone();
+    trailing.push(0);
+    let case = Case {
+        serialized: trailing,
+        ..case
+    };
+    assert!(run_case::<BeaconBlock>(&case)
+        .unwrap_err()
+        .starts_with("decoding read"));
+
+    let case = Case {
+        serialized,
+        root: vec![0; 32],
+        ..case
+    };
+    assert!(run_case::<BeaconBlock>(&case)
+        .unwrap_err()
+        .starts_with("expected root 0x0000"));
+
+    let mut other = block.clone();
+    other.graffiti = block.randao_reveal;
+    other.pow_chain_reference = super::Hash256::from(1);
+    assert_eq!(first_difference(&block, &block), "no difference");
+    assert!(first_difference(&block, &other).starts_with("expected `pow_chain_reference: "));
+}
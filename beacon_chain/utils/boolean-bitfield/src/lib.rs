@@ -123,7 +123,7 @@ impl ssz::Encodable for BooleanBitfield {
 impl ssz::Decodable for BooleanBitfield {
     fn ssz_decode(bytes: &[u8], index: usize) -> Result<(Self, usize), ssz::DecodeError> {
         let len = ssz::decode::decode_length(bytes, index, ssz::LENGTH_BYTES)?;
-        if (ssz::LENGTH_BYTES + len) > bytes.len() {
+        if (index + ssz::LENGTH_BYTES + len) > bytes.len() {
             return Err(ssz::DecodeError::TooShort);
         }
 
@@ -352,6 +352,15 @@ mod tests {
         assert_eq!(field, expected);
     }
 
+    #[test]
+    fn test_ssz_decode_at_index_too_short() {
+        let encoded = vec![9, 9, 0, 0, 0, 3, 255, 255];
+        assert_eq!(
+            BooleanBitfield::ssz_decode(&encoded, 2),
+            Err(ssz::DecodeError::TooShort)
+        );
+    }
+
     #[test]
     fn test_ssz_round_trip() {
         let original = BooleanBitfield::from_bytes(&vec![18; 12][..]);
@@ -0,0 +1,262 @@
+//! Metrics in the global Prometheus registry, shared by every crate of the node.
+//!
+//! Metrics are declared with `lazy_static!` in a `metrics` module of the crate which records
+//! them, e.g.
+//!
+//! ```ignore
+//! lazy_static! {
+//!     pub static ref BLOCK_IMPORTS: Result<IntCounter> =
+//!         try_create_int_counter("beacon_block_imports_total", "Count of blocks imported");
+//! }
+//!
+//! inc_counter(&BLOCK_IMPORTS);
+//! ```
+//!
+//! A metric which fails to register, e.g. because its name is taken, is ignored rather than
+//! stopping the node, so every helper takes the `Result` of creating the metric.
+extern crate prometheus;
+
+use prometheus::{HistogramOpts, Opts};
+
+pub use prometheus::{
+    Gauge, Histogram, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Result,
+};
+
+/// The content type of `encode_text`.
+pub const TEXT_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Creates a counter in the global registry.
+pub fn try_create_int_counter(name: &str, help: &str) -> Result<IntCounter> {
+    let counter = IntCounter::with_opts(Opts::new(name, help))?;
+    prometheus::register(Box::new(counter.clone()))?;
+    Ok(counter)
+}
+
+/// Creates a gauge in the global registry.
+pub fn try_create_int_gauge(name: &str, help: &str) -> Result<IntGauge> {
+    let gauge = IntGauge::with_opts(Opts::new(name, help))?;
+    prometheus::register(Box::new(gauge.clone()))?;
+    Ok(gauge)
+}
+
+/// Creates a gauge of a fractional value, e.g. seconds of CPU time, in the global registry.
+pub fn try_create_float_gauge(name: &str, help: &str) -> Result<Gauge> {
+    let gauge = Gauge::with_opts(Opts::new(name, help))?;
+    prometheus::register(Box::new(gauge.clone()))?;
+    Ok(gauge)
+}
+
+/// Creates a counter with a value for each combination of the labels `label_names`, in the
+/// global registry.
+pub fn try_create_int_counter_vec(
+    name: &str,
+    help: &str,
+    label_names: &[&str],
+) -> Result<IntCounterVec> {
+    let counter = IntCounterVec::new(Opts::new(name, help), label_names)?;
+    prometheus::register(Box::new(counter.clone()))?;
+    Ok(counter)
+}
+
+/// Creates a gauge with a value for each combination of the labels `label_names`, in the global
+/// registry.
+pub fn try_create_int_gauge_vec(
+    name: &str,
+    help: &str,
+    label_names: &[&str],
+) -> Result<IntGaugeVec> {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), label_names)?;
+    prometheus::register(Box::new(gauge.clone()))?;
+    Ok(gauge)
+}
+
+/// Creates a histogram with the default buckets, suited to durations in seconds, in the global
+/// registry.
+pub fn try_create_histogram(name: &str, help: &str) -> Result<Histogram> {
+    let histogram = Histogram::with_opts(HistogramOpts::new(name, help))?;
+    prometheus::register(Box::new(histogram.clone()))?;
+    Ok(histogram)
+}
+
+/// Creates a histogram with the upper bounds `buckets`, for values other than durations, in the
+/// global registry.
+pub fn try_create_histogram_with_buckets(
+    name: &str,
+    help: &str,
+    buckets: Vec<f64>,
+) -> Result<Histogram> {
+    let histogram = Histogram::with_opts(HistogramOpts::new(name, help).buckets(buckets))?;
+    prometheus::register(Box::new(histogram.clone()))?;
+    Ok(histogram)
+}
+
+/// Creates a histogram with the default buckets and a value for each combination of the labels
+/// `label_names`, in the global registry.
+pub fn try_create_histogram_vec(
+    name: &str,
+    help: &str,
+    label_names: &[&str],
+) -> Result<HistogramVec> {
+    let histogram = HistogramVec::new(HistogramOpts::new(name, help), label_names)?;
+    prometheus::register(Box::new(histogram.clone()))?;
+    Ok(histogram)
+}
+
+pub fn inc_counter(counter: &Result<IntCounter>) {
+    if let Ok(counter) = counter {
+        counter.inc();
+    }
+}
+
+pub fn inc_counter_by(counter: &Result<IntCounter>, value: i64) {
+    if let Ok(counter) = counter {
+        counter.inc_by(value);
+    }
+}
+
+/// Increments the counter of `counter` with the label values `labels`.
+pub fn inc_counter_vec(counter: &Result<IntCounterVec>, labels: &[&str]) {
+    if let Ok(counter) = counter {
+        counter.with_label_values(labels).inc();
+    }
+}
+
+pub fn set_gauge(gauge: &Result<IntGauge>, value: i64) {
+    if let Ok(gauge) = gauge {
+        gauge.set(value);
+    }
+}
+
+pub fn inc_gauge(gauge: &Result<IntGauge>) {
+    if let Ok(gauge) = gauge {
+        gauge.inc();
+    }
+}
+
+pub fn dec_gauge(gauge: &Result<IntGauge>) {
+    if let Ok(gauge) = gauge {
+        gauge.dec();
+    }
+}
+
+pub fn set_float_gauge(gauge: &Result<Gauge>, value: f64) {
+    if let Ok(gauge) = gauge {
+        gauge.set(value);
+    }
+}
+
+/// Sets the gauge of `gauge` with the label values `labels`.
+pub fn set_gauge_vec(gauge: &Result<IntGaugeVec>, labels: &[&str], value: i64) {
+    if let Ok(gauge) = gauge {
+        gauge.with_label_values(labels).set(value);
+    }
+}
+
+/// Increments the gauge of `gauge` with the label values `labels`.
+pub fn inc_gauge_vec(gauge: &Result<IntGaugeVec>, labels: &[&str]) {
+    if let Ok(gauge) = gauge {
+        gauge.with_label_values(labels).inc();
+    }
+}
+
+/// Decrements the gauge of `gauge` with the label values `labels`.
+pub fn dec_gauge_vec(gauge: &Result<IntGaugeVec>, labels: &[&str]) {
+    if let Ok(gauge) = gauge {
+        gauge.with_label_values(labels).dec();
+    }
+}
+
+pub fn observe(histogram: &Result<Histogram>, value: f64) {
+    if let Ok(histogram) = histogram {
+        histogram.observe(value);
+    }
+}
+
+/// Observes `value` in the histogram of `histogram` with the label values `labels`.
+pub fn observe_vec(histogram: &Result<HistogramVec>, labels: &[&str], value: f64) {
+    if let Ok(histogram) = histogram {
+        histogram.with_label_values(labels).observe(value);
+    }
+}
+
+/// Starts timing, to be observed by `histogram` in seconds when the timer is stopped or dropped.
+pub fn start_timer(histogram: &Result<Histogram>) -> Option<HistogramTimer> {
+    match histogram {
+        Ok(histogram) => Some(histogram.start_timer()),
+        Err(_) => None,
+    }
+}
+
+pub fn stop_timer(timer: Option<HistogramTimer>) {
+    if let Some(timer) = timer {
+        timer.observe_duration();
+    }
+}
+
+/// Returns every metric in the global registry in the Prometheus text format.
+pub fn encode_text() -> Vec<u8> {
+    use prometheus::Encoder;
+
+    let mut buffer = vec![];
+    prometheus::TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("Metrics are encodable as text");
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_are_registered_once() {
+        let counter = try_create_int_counter("test_counter_total", "A test counter");
+        inc_counter_by(&counter, 2);
+        inc_counter(&counter);
+        assert_eq!(counter.as_ref().unwrap().get(), 3);
+        assert!(try_create_int_counter("test_counter_total", "A test counter").is_err());
+
+        let gauge = try_create_int_gauge("test_gauge", "A test gauge");
+        set_gauge(&gauge, -5);
+        inc_gauge(&gauge);
+        inc_gauge(&gauge);
+        dec_gauge(&gauge);
+        let float_gauge = try_create_float_gauge("test_float_gauge", "A test gauge");
+        set_float_gauge(&float_gauge, 1.5);
+        let histogram = try_create_histogram("test_histogram_seconds", "A test histogram");
+        stop_timer(start_timer(&histogram));
+        observe(&histogram, 0.5);
+        let buckets = try_create_histogram_with_buckets(
+            "test_histogram_slots",
+            "A test histogram",
+            vec![1.0, 4.0],
+        );
+        observe(&buckets, 2.0);
+
+        let counter_vec =
+            try_create_int_counter_vec("test_counter_vec_total", "A test counter", &["label"]);
+        inc_counter_vec(&counter_vec, &["a"]);
+        inc_counter_vec(&counter_vec, &["a"]);
+        let gauge_vec = try_create_int_gauge_vec("test_gauge_vec", "A test gauge", &["label"]);
+        set_gauge_vec(&gauge_vec, &["b"], 7);
+        inc_gauge_vec(&gauge_vec, &["c"]);
+        inc_gauge_vec(&gauge_vec, &["c"]);
+        dec_gauge_vec(&gauge_vec, &["c"]);
+        let histogram_vec =
+            try_create_histogram_vec("test_histogram_vec_seconds", "A test histogram", &["label"]);
+        observe_vec(&histogram_vec, &["d"], 0.5);
+
+        let text = String::from_utf8(encode_text()).unwrap();
+        assert!(text.contains("test_counter_total 3"));
+        assert!(text.contains("test_gauge -4"));
+        assert!(text.contains("test_float_gauge 1.5"));
+        assert!(text.contains("test_histogram_seconds_count 2"));
+        assert!(text.contains("test_histogram_slots_bucket{le=\"1\"} 0"));
+        assert!(text.contains("test_histogram_slots_bucket{le=\"4\"} 1"));
+        assert!(text.contains("test_counter_vec_total{label=\"a\"} 2"));
+        assert!(text.contains("test_gauge_vec{label=\"b\"} 7"));
+        assert!(text.contains("test_gauge_vec{label=\"c\"} 1"));
+        assert!(text.contains("test_histogram_vec_seconds_count{label=\"d\"} 1"));
+    }
+}
@@ -0,0 +1,168 @@
+use slog::{BorrowedKV, Drain, Level, OwnedKVList, Record, RecordLocation, RecordStatic};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The number of distinct warnings and errors remembered, beyond which those not logged within
+/// the interval are forgotten.
+const MAX_TRACKED: usize = 1_024;
+
+/// When a warning or error with a message was last logged, and how many repeats of it have been
+/// suppressed since.
+struct Seen {
+    logged_at: Instant,
+    suppressed: u64,
+}
+
+/// Passes on the first of repeated warnings and errors, suppressing repeats of the same level,
+/// module and message for `interval` after it. The first repeat logged after the interval carries
+/// the number suppressed as `suppressed`.
+///
+/// Records below warning are always passed on.
+pub struct DedupDrain<D> {
+    drain: D,
+    interval: Duration,
+    seen: Mutex<HashMap<(Level, &'static str, String), Seen>>,
+}
+
+impl<D> DedupDrain<D> {
+    /// Deduplicates within `interval`, or not at all if it is zero.
+    pub fn new(drain: D, interval: Duration) -> Self {
+        Self {
+            drain,
+            interval,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the number of repeats suppressed since the record was last logged, or `None` if it
+    /// should be suppressed.
+    fn check(&self, record: &Record) -> Option<u64> {
+        let key = (record.level(), record.module(), record.msg().to_string());
+        let now = Instant::now();
+        let mut seen = match self.seen.lock() {
+            Ok(seen) => seen,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(entry) = seen.get_mut(&key) {
+            if now.duration_since(entry.logged_at) < self.interval {
+                entry.suppressed += 1;
+                return None;
+            }
+            let suppressed = entry.suppressed;
+            entry.logged_at = now;
+            entry.suppressed = 0;
+            return Some(suppressed);
+        }
+        if seen.len() >= MAX_TRACKED {
+            let interval = self.interval;
+            seen.retain(|_, entry| now.duration_since(entry.logged_at) < interval);
+        }
+        seen.insert(
+            key,
+            Seen {
+                logged_at: now,
+                suppressed: 0,
+            },
+        );
+        Some(0)
+    }
+}
+
+impl<D: Drain<Ok = ()>> Drain for DedupDrain<D> {
+    type Ok = ();
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<(), D::Err> {
+        if self.interval == Duration::from_secs(0) || !record.level().is_at_least(Level::Warning) {
+            return self.drain.log(record, values);
+        }
+        match self.check(record) {
+            None => Ok(()),
+            Some(0) => self.drain.log(record, values),
+            Some(suppressed) => {
+                /*
+                 * The record is rebuilt with the count of suppressed repeats among its key-values.
+                 */
+                let location = RecordLocation {
+                    file: record.file(),
+                    line: record.line(),
+                    column: record.column(),
+                    function: record.function(),
+                    module: record.module(),
+                };
+                let record_static = RecordStatic {
+                    location: &location,
+                    tag: record.tag(),
+                    level: record.level(),
+                };
+                let kv = (record.kv(), kv!("suppressed" => suppressed));
+                let record = Record::new(&record_static, record.msg(), BorrowedKV(&kv));
+                self.drain.log(&record, values)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::json::{JsonDrain, TestBuffer};
+    use super::*;
+    use slog::Logger;
+    use std::thread;
+
+    #[test]
+    fn test_dedup() {
+        let buffer = TestBuffer::default();
+        let drain = DedupDrain::new(
+            JsonDrain::new(buffer.clone()).fuse(),
+            Duration::from_millis(200),
+        );
+        let log = Logger::root(drain, o!());
+        for peer in 0..3 {
+            warn!(log, "Peer disconnected"; "peer" => peer);
+            error!(log, "Peer disconnected");
+            info!(log, "Peer disconnected");
+        }
+        warn!(log, "Database slow");
+
+        let records = buffer.records();
+        let messages: Vec<(&str, &str)> = records
+            .iter()
+            .map(|r| (r["level"].as_str().unwrap(), r["msg"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("WARNING", "Peer disconnected"),
+                ("ERROR", "Peer disconnected"),
+                ("INFO", "Peer disconnected"),
+                ("INFO", "Peer disconnected"),
+                ("INFO", "Peer disconnected"),
+                ("WARNING", "Database slow"),
+            ]
+        );
+        assert_eq!(records[0]["peer"], 0);
+        assert!(records[0].get("suppressed").is_none());
+
+        thread::sleep(Duration::from_millis(250));
+        warn!(log, "Peer disconnected"; "peer" => 9);
+        let records = buffer.records();
+        assert_eq!(records.len(), 7);
+        assert_eq!(records[6]["peer"], 9);
+        assert_eq!(records[6]["suppressed"], 2);
+    }
+
+    #[test]
+    fn test_dedup_disabled() {
+        let buffer = TestBuffer::default();
+        let drain = DedupDrain::new(
+            JsonDrain::new(buffer.clone()).fuse(),
+            Duration::from_secs(0),
+        );
+        let log = Logger::root(drain, o!());
+        warn!(log, "Peer disconnected");
+        warn!(log, "Peer disconnected");
+        assert_eq!(buffer.records().len(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::{self, Map, Value};
+use slog::{self, Drain, Key, OwnedKVList, Record, Serializer, KV};
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Writes each record as a JSON object on its own line, with the time, level, module and message
+/// of the record alongside its key-values, e.g.
+///
+/// `{"level":"INFO","module":"beacon_node::node","msg":"Imported block","slot":7,"ts":"..."}`
+///
+/// Numbers and booleans keep their types, and other values are formatted as strings.
+pub struct JsonDrain<W: Write> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> JsonDrain<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write> Drain for JsonDrain<W> {
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> io::Result<()> {
+        let mut object = Map::new();
+        {
+            let mut serializer = JsonSerializer(&mut object);
+            values.serialize(record, &mut serializer)?;
+            record.kv().serialize(record, &mut serializer)?;
+        }
+        /*
+         * The fields of the record are inserted last, so a key-value of the same name cannot hide
+         * them.
+         */
+        object.insert(
+            "ts".to_string(),
+            Value::String(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+        object.insert(
+            "level".to_string(),
+            Value::String(record.level().as_str().to_string()),
+        );
+        object.insert(
+            "module".to_string(),
+            Value::String(record.module().to_string()),
+        );
+        object.insert("msg".to_string(), Value::String(record.msg().to_string()));
+
+        let mut line = serde_json::to_vec(&Value::Object(object))?;
+        line.push(b'\n');
+        let mut writer = match self.writer.lock() {
+            Ok(writer) => writer,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        writer.write_all(&line)?;
+        writer.flush()
+    }
+}
+
+struct JsonSerializer<'a>(&'a mut Map<String, Value>);
+
+impl<'a> JsonSerializer<'a> {
+    fn insert<V: Into<Value>>(&mut self, key: Key, value: V) -> slog::Result {
+        self.0.insert(key.to_string(), value.into());
+        Ok(())
+    }
+}
+
+macro_rules! emit_number {
+    ($name: ident, $type: ty) => {
+        fn $name(&mut self, key: Key, value: $type) -> slog::Result {
+            self.insert(key, value)
+        }
+    };
+}
+
+impl<'a> Serializer for JsonSerializer<'a> {
+    fn emit_arguments(&mut self, key: Key, value: &fmt::Arguments) -> slog::Result {
+        self.insert(key, value.to_string())
+    }
+
+    fn emit_str(&mut self, key: Key, value: &str) -> slog::Result {
+        self.insert(key, value)
+    }
+
+    fn emit_bool(&mut self, key: Key, value: bool) -> slog::Result {
+        self.insert(key, value)
+    }
+
+    fn emit_none(&mut self, key: Key) -> slog::Result {
+        self.insert(key, Value::Null)
+    }
+
+    emit_number!(emit_u8, u8);
+    emit_number!(emit_u16, u16);
+    emit_number!(emit_u32, u32);
+    emit_number!(emit_u64, u64);
+    emit_number!(emit_usize, usize);
+    emit_number!(emit_i8, i8);
+    emit_number!(emit_i16, i16);
+    emit_number!(emit_i32, i32);
+    emit_number!(emit_i64, i64);
+    emit_number!(emit_isize, isize);
+    emit_number!(emit_f32, f32);
+    emit_number!(emit_f64, f64);
+}
+
+/// A writer whose lines can be read back by tests, while a drain owns it.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct TestBuffer(::std::sync::Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl TestBuffer {
+    /// Returns the JSON objects written, one per line.
+    pub fn records(&self) -> Vec<Value> {
+        let bytes = self.0.lock().unwrap();
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl Write for TestBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::Logger;
+
+    #[test]
+    fn test_json_drain() {
+        let buffer = TestBuffer::default();
+        let log = Logger::root(
+            JsonDrain::new(buffer.clone()).fuse(),
+            o!("service" => "test"),
+        );
+        info!(log, "Imported block"; "slot" => 7u64, "root" => "0xab", "valid" => true);
+        warn!(log, "Peer {} disconnected", 3; "msg" => "hidden", "delay" => -1i64);
+
+        let records = buffer.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["level"], "INFO");
+        assert_eq!(records[0]["msg"], "Imported block");
+        assert_eq!(records[0]["module"], module_path!());
+        assert_eq!(records[0]["service"], "test");
+        assert_eq!(records[0]["slot"], 7);
+        assert_eq!(records[0]["root"], "0xab");
+        assert_eq!(records[0]["valid"], true);
+        assert!(records[0]["ts"].as_str().unwrap().ends_with('Z'));
+
+        assert_eq!(records[1]["level"], "WARNING");
+        assert_eq!(records[1]["msg"], "Peer 3 disconnected");
+        assert_eq!(records[1]["delay"], -1);
+    }
+}
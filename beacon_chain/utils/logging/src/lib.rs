@@ -0,0 +1,195 @@
+//! The root logger of the node, built from a `LoggerConfig`.
+//!
+//! Records are written to the terminal or to a rotated file, as text or as one JSON object per
+//! line for log aggregation. Each module may have its own level, and repeats of a warning or
+//! error within the dedup interval are counted rather than logged, e.g.
+//!
+//! ```ignore
+//! let config = LoggerConfig {
+//!     format: LogFormat::Json,
+//!     module_levels: parse_module_levels("network=debug")?,
+//!     ..LoggerConfig::default()
+//! };
+//! let (log, _guard) = build_logger(&config)?;
+//! ```
+#[macro_use]
+extern crate slog;
+extern crate chrono;
+extern crate serde_json;
+extern crate slog_async;
+extern crate slog_term;
+
+mod dedup;
+mod json;
+mod module_filter;
+mod rotating_file;
+
+pub use self::dedup::DedupDrain;
+pub use self::json::JsonDrain;
+pub use self::module_filter::ModuleLevelFilter;
+pub use self::rotating_file::RotatingFile;
+
+use slog::{Drain, Level, Logger, Never};
+use slog_async::AsyncGuard;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The size at which the log file is rotated, by default.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+/// The number of rotated log files kept, by default.
+pub const DEFAULT_MAX_FILES: usize = 5;
+/// The interval within which repeated warnings and errors are counted rather than logged, by
+/// default.
+pub const DEFAULT_DEDUP_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogFormat {
+    /// Human-readable text.
+    Terminal,
+    /// One JSON object per record, per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = LoggingError;
+
+    fn from_str(format: &str) -> Result<Self, LoggingError> {
+        match format {
+            "terminal" => Ok(LogFormat::Terminal),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(LoggingError::InvalidFormat(format.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggerConfig {
+    pub format: LogFormat,
+    /// The level of modules without a level of their own.
+    pub level: Level,
+    /// The levels of modules and their submodules, by module path, e.g. `network::discovery`.
+    pub module_levels: Vec<(String, Level)>,
+    /// The file to write to instead of the terminal.
+    pub file: Option<PathBuf>,
+    /// The size in bytes beyond which the file is rotated.
+    pub max_file_size: u64,
+    /// The number of rotated files kept, from `FILE.1`, the newest, to `FILE.<max_files>`.
+    pub max_files: usize,
+    /// Repeats of a warning or error within this interval of it being logged are counted rather
+    /// than logged. Zero disables deduplication.
+    pub dedup_interval: Duration,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Terminal,
+            level: Level::Info,
+            module_levels: vec![],
+            file: None,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_files: DEFAULT_MAX_FILES,
+            dedup_interval: DEFAULT_DEDUP_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoggingError {
+    InvalidFormat(String),
+    InvalidLevel(String),
+    Io(io::Error),
+}
+
+impl From<io::Error> for LoggingError {
+    fn from(e: io::Error) -> Self {
+        LoggingError::Io(e)
+    }
+}
+
+/// Parses a level name, e.g. `info` or `warn`.
+pub fn parse_level(level: &str) -> Result<Level, LoggingError> {
+    Level::from_str(level).map_err(|_| LoggingError::InvalidLevel(level.to_string()))
+}
+
+/// Parses a comma-separated list of module levels, e.g. `network=debug,http_api::server=warn`.
+pub fn parse_module_levels(list: &str) -> Result<Vec<(String, Level)>, LoggingError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let mut parts = entry.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(module), Some(level)) if !module.trim().is_empty() => {
+                    Ok((module.trim().to_string(), parse_level(level.trim())?))
+                }
+                _ => Err(LoggingError::InvalidLevel(entry.to_string())),
+            }
+        })
+        .collect()
+}
+
+/// Waits, when dropped, for the records logged before to be written.
+///
+/// Clones of the logger may outlive `main` on other threads, so the guard is what ensures the
+/// last records are written before the process exits.
+pub struct LoggerGuard {
+    _guard: AsyncGuard,
+}
+
+/// Builds the root logger, whose records are written by a background thread until the guard is
+/// dropped.
+pub fn build_logger(config: &LoggerConfig) -> Result<(Logger, LoggerGuard), LoggingError> {
+    let output: Box<dyn Drain<Ok = (), Err = Never> + Send> = match (config.format, &config.file) {
+        (LogFormat::Terminal, None) => {
+            let decorator = slog_term::TermDecorator::new().build();
+            Box::new(slog_term::CompactFormat::new(decorator).build().fuse())
+        }
+        (LogFormat::Terminal, Some(path)) => {
+            let file = RotatingFile::open(path, config.max_file_size, config.max_files)?;
+            let decorator = slog_term::PlainSyncDecorator::new(file);
+            Box::new(slog_term::FullFormat::new(decorator).build().fuse())
+        }
+        (LogFormat::Json, None) => Box::new(JsonDrain::new(io::stdout()).fuse()),
+        (LogFormat::Json, Some(path)) => {
+            let file = RotatingFile::open(path, config.max_file_size, config.max_files)?;
+            Box::new(JsonDrain::new(file).fuse())
+        }
+    };
+    let drain = DedupDrain::new(output, config.dedup_interval);
+    let drain = ModuleLevelFilter::new(drain, config.level, config.module_levels.clone());
+    let (drain, guard) = slog_async::Async::new(drain).build_with_guard();
+    let log = Logger::root(drain.fuse(), o!());
+    Ok((log, LoggerGuard { _guard: guard }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_module_levels() {
+        assert_eq!(
+            parse_module_levels(" network=debug, http_api::server = warn,").unwrap(),
+            vec![
+                ("network".to_string(), Level::Debug),
+                ("http_api::server".to_string(), Level::Warning),
+            ]
+        );
+        assert!(parse_module_levels("").unwrap().is_empty());
+        assert!(parse_module_levels("network").is_err());
+        assert!(parse_module_levels("=debug").is_err());
+        assert!(parse_module_levels("network=loud").is_err());
+    }
+
+    #[test]
+    fn test_parse_format_and_level() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert_eq!(parse_level("error").unwrap(), Level::Error);
+        assert_eq!(parse_level("CRIT").unwrap(), Level::Critical);
+        assert!(parse_level("").is_err());
+    }
+}
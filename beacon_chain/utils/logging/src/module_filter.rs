@@ -0,0 +1,85 @@
+use slog::{Drain, Level, OwnedKVList, Record};
+
+/// Passes on the records at least as severe as the level of their module: the level of the
+/// longest module path which is the module or one of its parents, or else the default level.
+pub struct ModuleLevelFilter<D> {
+    drain: D,
+    level: Level,
+    module_levels: Vec<(String, Level)>,
+}
+
+impl<D> ModuleLevelFilter<D> {
+    pub fn new(drain: D, level: Level, module_levels: Vec<(String, Level)>) -> Self {
+        Self {
+            drain,
+            level,
+            module_levels,
+        }
+    }
+
+    /// Returns the level of records of `module`.
+    pub fn level_of(&self, module: &str) -> Level {
+        self.module_levels
+            .iter()
+            .filter(|(path, _)| {
+                module == path
+                    || (module.starts_with(path.as_str()) && module[path.len()..].starts_with("::"))
+            })
+            .max_by_key(|(path, _)| path.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+}
+
+impl<D: Drain<Ok = ()>> Drain for ModuleLevelFilter<D> {
+    type Ok = ();
+    type Err = D::Err;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<(), D::Err> {
+        if record.level().is_at_least(self.level_of(record.module())) {
+            self.drain.log(record, values)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::json::{JsonDrain, TestBuffer};
+    use super::*;
+    use slog::Logger;
+
+    #[test]
+    fn test_level_of() {
+        let filter = ModuleLevelFilter::new(
+            (),
+            Level::Info,
+            vec![
+                ("network".to_string(), Level::Debug),
+                ("network::discovery".to_string(), Level::Error),
+            ],
+        );
+        assert_eq!(filter.level_of("network"), Level::Debug);
+        assert_eq!(filter.level_of("network::rpc"), Level::Debug);
+        assert_eq!(filter.level_of("network::discovery::enr"), Level::Error);
+        assert_eq!(filter.level_of("network_extra"), Level::Info);
+        assert_eq!(filter.level_of("beacon_node"), Level::Info);
+    }
+
+    #[test]
+    fn test_filter() {
+        let buffer = TestBuffer::default();
+        let module = module_path!().to_string();
+        let drain = ModuleLevelFilter::new(
+            JsonDrain::new(buffer.clone()).fuse(),
+            Level::Error,
+            vec![(module, Level::Debug)],
+        );
+        let log = Logger::root(drain, o!());
+        debug!(log, "Shown");
+        trace!(log, "Hidden");
+        assert_eq!(buffer.records().len(), 1);
+        assert_eq!(buffer.records()[0]["msg"], "Shown");
+    }
+}
@@ -0,0 +1,150 @@
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A log file which, once larger than `max_size` bytes, is renamed to `FILE.1` and replaced by
+/// an empty file, with older files renamed from `FILE.n` to `FILE.n+1` and those beyond
+/// `max_files` removed.
+///
+/// The file is only rotated on flush, so a record is never split across files.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Opens the file at `path` for appending, creating it and its directory if need be.
+    pub fn open(path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    /// Returns the path of the `n`th newest rotated file.
+    pub fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = OsString::from(&self.path);
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files > 0 {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+            for n in (1..self.max_files).rev() {
+                let path = self.rotated_path(n);
+                if path.exists() {
+                    fs::rename(path, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.size > self.max_size {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("lighthouse_logging_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("logs").join("beacon.log")
+    }
+
+    fn write_line(file: &mut RotatingFile, line: &str) {
+        file.write_all(line.as_bytes()).unwrap();
+        file.flush().unwrap();
+    }
+
+    #[test]
+    fn test_rotation() {
+        let path = temp_path("rotation");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        write_line(&mut file, "first\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\n");
+
+        /*
+         * Writing beyond the maximum size rotates the file once the record is complete.
+         */
+        write_line(&mut file, "second\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        assert_eq!(
+            fs::read_to_string(file.rotated_path(1)).unwrap(),
+            "first\nsecond\n"
+        );
+
+        for line in &["third 12345\n", "fourth 1234\n", "fifth 12345\n"] {
+            write_line(&mut file, line);
+        }
+        assert_eq!(
+            fs::read_to_string(file.rotated_path(1)).unwrap(),
+            "fifth 12345\n"
+        );
+        assert_eq!(
+            fs::read_to_string(file.rotated_path(2)).unwrap(),
+            "fourth 1234\n"
+        );
+        assert!(!file.rotated_path(3).exists());
+    }
+
+    #[test]
+    fn test_reopen_appends() {
+        let path = temp_path("reopen");
+        write_line(&mut RotatingFile::open(&path, 100, 1).unwrap(), "first\n");
+        let mut file = RotatingFile::open(&path, 100, 1).unwrap();
+        write_line(&mut file, "second\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+        assert_eq!(file.size, 13);
+    }
+
+    #[test]
+    fn test_no_rotated_files() {
+        let path = temp_path("no_rotated");
+        let mut file = RotatingFile::open(&path, 4, 0).unwrap();
+        write_line(&mut file, "first\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        assert!(!file.rotated_path(1).exists());
+    }
+}
@@ -0,0 +1,26 @@
+#[macro_use]
+extern crate criterion;
+extern crate ethereum_types;
+extern crate merkle_proof;
+
+use criterion::{black_box, Benchmark, Criterion};
+use ethereum_types::H256;
+use merkle_proof::MerkleTree;
+
+/// Builds trees of as many leaves as a large validator set, as done for the validators tree of
+/// a state summary.
+fn tree_root(c: &mut Criterion) {
+    for &count in &[1 << 14, 1 << 17, 1 << 20] {
+        let leaves: Vec<H256> = (0..count).map(|i| H256::from(i as u64)).collect();
+        c.bench(
+            "merkle_tree",
+            Benchmark::new(format!("root_{}_leaves", count), move |b| {
+                b.iter(|| black_box(MerkleTree::new(&leaves).root()))
+            })
+            .sample_size(10),
+        );
+    }
+}
+
+criterion_group!(benches, tree_root);
+criterion_main!(benches);
@@ -0,0 +1,159 @@
+//! Binary Merkle trees addressed by generalized index, with proofs of their nodes.
+//!
+//! The root has generalized index `1` and the children of node `i` are `2i` and `2i + 1`, so the
+//! leaves of a tree of depth `d` are `2^d..2^(d + 1)`.
+extern crate ethereum_types;
+extern crate hashing;
+
+use ethereum_types::H256;
+use hashing::canonical_hash;
+
+/// A complete binary tree, with leaves padded with zero hashes up to a power of two.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MerkleTree {
+    /// Every node, indexed by generalized index. Index `0` is unused.
+    nodes: Vec<H256>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: &[H256]) -> Self {
+        let width = leaves.len().next_power_of_two();
+        let mut nodes = vec![H256::zero(); 2 * width];
+        nodes[width..width + leaves.len()].copy_from_slice(leaves);
+        for i in (1..width).rev() {
+            nodes[i] = hash_pair(&nodes[2 * i], &nodes[2 * i + 1]);
+        }
+        Self { nodes }
+    }
+
+    pub fn root(&self) -> H256 {
+        self.nodes[1]
+    }
+
+    /// The number of levels below the root.
+    pub fn depth(&self) -> u32 {
+        (self.nodes.len() / 2).trailing_zeros()
+    }
+
+    /// Returns the generalized index of the leaf at `index`.
+    pub fn leaf_gindex(&self, index: usize) -> usize {
+        self.nodes.len() / 2 + index
+    }
+
+    /// Returns the node at `gindex`, or `None` if it is outside the tree.
+    pub fn node(&self, gindex: usize) -> Option<H256> {
+        match gindex {
+            0 => None,
+            _ => self.nodes.get(gindex).cloned(),
+        }
+    }
+
+    /// Returns the siblings of the nodes from `gindex` up to, but excluding, the root, or `None`
+    /// if `gindex` is outside the tree.
+    pub fn branch(&self, gindex: usize) -> Option<Vec<H256>> {
+        self.node(gindex)?;
+        let mut branch = vec![];
+        let mut i = gindex;
+        while i > 1 {
+            branch.push(self.nodes[i ^ 1]);
+            i /= 2;
+        }
+        Some(branch)
+    }
+}
+
+/// Returns `true` if `branch` proves that `leaf` is at `gindex` of the tree with `root`.
+pub fn verify_merkle_proof(leaf: H256, branch: &[H256], gindex: usize, root: H256) -> bool {
+    if gindex == 0 || gindex_depth(gindex) != branch.len() {
+        return false;
+    }
+    let mut node = leaf;
+    let mut i = gindex;
+    for sibling in branch {
+        node = if i & 1 == 0 {
+            hash_pair(&node, sibling)
+        } else {
+            hash_pair(sibling, &node)
+        };
+        i /= 2;
+    }
+    node == root
+}
+
+/// Returns the generalized index of the node at `gindex` of a subtree whose root is at
+/// `subtree_root` of a larger tree.
+pub fn concat_gindices(subtree_root: usize, gindex: usize) -> usize {
+    let depth = gindex_depth(gindex);
+    (subtree_root << depth) | (gindex - (1 << depth))
+}
+
+/// The number of levels between `gindex` and the root.
+pub fn gindex_depth(gindex: usize) -> usize {
+    (0usize.leading_zeros() - gindex.leading_zeros()) as usize - 1
+}
+
+fn hash_pair(left: &H256, right: &H256) -> H256 {
+    let mut preimage = left.to_vec();
+    preimage.extend_from_slice(right);
+    H256::from(&canonical_hash(&preimage)[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: u64) -> Vec<H256> {
+        (1..=n).map(H256::from).collect()
+    }
+
+    #[test]
+    fn test_proves_every_node() {
+        let tree = MerkleTree::new(&leaves(5));
+        assert_eq!(tree.depth(), 3);
+        assert_eq!(tree.leaf_gindex(4), 12);
+        assert_eq!(tree.node(12), Some(H256::from(5)));
+        assert_eq!(tree.node(13), Some(H256::zero()));
+
+        for gindex in 1..16 {
+            let branch = tree.branch(gindex).unwrap();
+            let node = tree.node(gindex).unwrap();
+            assert!(verify_merkle_proof(node, &branch, gindex, tree.root()));
+            assert!(!verify_merkle_proof(
+                H256::from(99),
+                &branch,
+                gindex,
+                tree.root()
+            ));
+        }
+        assert!(!verify_merkle_proof(H256::from(1), &[], 8, tree.root()));
+        assert_eq!(tree.branch(16), None);
+        assert_eq!(tree.branch(0), None);
+    }
+
+    #[test]
+    fn test_subtree_proofs_compose() {
+        let subtree = MerkleTree::new(&leaves(3));
+        let mut top = leaves(4);
+        top[2] = subtree.root();
+        let tree = MerkleTree::new(&top);
+
+        let gindex = concat_gindices(6, subtree.leaf_gindex(1));
+        assert_eq!(gindex, 25);
+        let mut branch = subtree.branch(subtree.leaf_gindex(1)).unwrap();
+        branch.extend(tree.branch(6).unwrap());
+        assert!(verify_merkle_proof(
+            H256::from(2),
+            &branch,
+            gindex,
+            tree.root()
+        ));
+    }
+
+    #[test]
+    fn test_single_leaf() {
+        let tree = MerkleTree::new(&leaves(1));
+        assert_eq!(tree.depth(), 0);
+        assert_eq!(tree.root(), H256::from(1));
+        assert_eq!(tree.branch(1), Some(vec![]));
+    }
+}
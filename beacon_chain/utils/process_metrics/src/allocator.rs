@@ -0,0 +1,209 @@
+use std::io;
+
+#[cfg(all(not(feature = "jemalloc"), target_os = "linux", target_env = "gnu"))]
+pub use self::glibc::{dump_heap_profile, scrape, HEAP_PROFILE_EXTENSION};
+#[cfg(feature = "jemalloc")]
+pub use self::jemalloc::{dump_heap_profile, scrape, HEAP_PROFILE_EXTENSION};
+#[cfg(not(any(feature = "jemalloc", all(target_os = "linux", target_env = "gnu"))))]
+pub use self::unsupported::{dump_heap_profile, scrape, HEAP_PROFILE_EXTENSION};
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(io::Error),
+    /// The allocator refused to dump a profile, e.g. because jemalloc was started without
+    /// profiling.
+    Allocator(String),
+    /// The allocator of this build cannot dump profiles.
+    Unsupported,
+}
+
+impl From<io::Error> for ProfileError {
+    fn from(e: io::Error) -> Self {
+        ProfileError::Io(e)
+    }
+}
+
+#[cfg(any(feature = "jemalloc", all(target_os = "linux", target_env = "gnu")))]
+fn c_path(path: &::std::path::Path) -> Result<::std::ffi::CString, ProfileError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    ::std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| ProfileError::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))
+}
+
+/// The statistics of glibc's malloc, the system allocator.
+#[cfg(all(not(feature = "jemalloc"), target_os = "linux", target_env = "gnu"))]
+mod glibc {
+    use super::{c_path, ProfileError};
+    use libc;
+    use lighthouse_metrics::{self, set_gauge, try_create_int_gauge, IntGauge};
+    use std::io;
+    use std::path::Path;
+
+    /// `malloc_info` describes the heap as XML.
+    pub const HEAP_PROFILE_EXTENSION: &str = "xml";
+
+    lazy_static! {
+        pub static ref MALLOC_ARENA: lighthouse_metrics::Result<IntGauge> = try_create_int_gauge(
+            "malloc_arena_bytes",
+            "Bytes of the heap obtained by malloc other than with mmap"
+        );
+        pub static ref MALLOC_MMAP: lighthouse_metrics::Result<IntGauge> = try_create_int_gauge(
+            "malloc_mmap_bytes",
+            "Bytes of the heap obtained by malloc with mmap"
+        );
+        pub static ref MALLOC_IN_USE: lighthouse_metrics::Result<IntGauge> = try_create_int_gauge(
+            "malloc_in_use_bytes",
+            "Bytes of the heap allocated by the application"
+        );
+        pub static ref MALLOC_FREE: lighthouse_metrics::Result<IntGauge> =
+            try_create_int_gauge("malloc_free_bytes", "Bytes of the heap free for allocation");
+        pub static ref MALLOC_RELEASABLE: lighthouse_metrics::Result<IntGauge> =
+            try_create_int_gauge(
+                "malloc_releasable_bytes",
+                "Bytes at the top of the heap which could be released to the OS"
+            );
+    }
+
+    /// Records the statistics of `mallinfo`, whose fields are C ints which wrap at 4 GiB.
+    pub fn scrape() {
+        let info = unsafe { libc::mallinfo() };
+        set_gauge(&MALLOC_ARENA, i64::from(info.arena as u32));
+        set_gauge(&MALLOC_MMAP, i64::from(info.hblkhd as u32));
+        set_gauge(&MALLOC_IN_USE, i64::from(info.uordblks as u32));
+        set_gauge(&MALLOC_FREE, i64::from(info.fordblks as u32));
+        set_gauge(&MALLOC_RELEASABLE, i64::from(info.keepcost as u32));
+    }
+
+    /// Writes the state of every arena of the heap to `path`, with `malloc_info`.
+    pub fn dump_heap_profile(path: &Path) -> Result<(), ProfileError> {
+        let path = c_path(path)?;
+        unsafe {
+            let file = libc::fopen(path.as_ptr(), b"w\0".as_ptr() as *const libc::c_char);
+            if file.is_null() {
+                return Err(io::Error::last_os_error().into());
+            }
+            let result = libc::malloc_info(0, file);
+            libc::fclose(file);
+            if result != 0 {
+                return Err(ProfileError::Allocator("malloc_info failed".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The statistics of jemalloc, which replaces the system allocator.
+#[cfg(feature = "jemalloc")]
+mod jemalloc {
+    use super::{c_path, ProfileError};
+    use lighthouse_metrics::{self, set_gauge, try_create_int_gauge, IntGauge};
+    use std::path::Path;
+    use tikv_jemalloc_ctl::{epoch, raw, stats};
+
+    /// The heap profiles of jemalloc are read with `jeprof`.
+    pub const HEAP_PROFILE_EXTENSION: &str = "heap";
+
+    lazy_static! {
+        pub static ref JEMALLOC_ALLOCATED: lighthouse_metrics::Result<IntGauge> =
+            try_create_int_gauge(
+                "jemalloc_allocated_bytes",
+                "Bytes allocated by the application"
+            );
+        pub static ref JEMALLOC_ACTIVE: lighthouse_metrics::Result<IntGauge> = try_create_int_gauge(
+            "jemalloc_active_bytes",
+            "Bytes of the pages holding allocations"
+        );
+        pub static ref JEMALLOC_RESIDENT: lighthouse_metrics::Result<IntGauge> =
+            try_create_int_gauge(
+                "jemalloc_resident_bytes",
+                "Bytes of the pages mapped by jemalloc which are resident"
+            );
+        pub static ref JEMALLOC_MAPPED: lighthouse_metrics::Result<IntGauge> = try_create_int_gauge(
+            "jemalloc_mapped_bytes",
+            "Bytes of the active extents mapped by jemalloc"
+        );
+        pub static ref JEMALLOC_RETAINED: lighthouse_metrics::Result<IntGauge> =
+            try_create_int_gauge(
+                "jemalloc_retained_bytes",
+                "Bytes of virtual memory retained by jemalloc rather than returned to the OS"
+            );
+    }
+
+    /// Records the statistics of jemalloc, which are cached until its epoch is advanced.
+    pub fn scrape() {
+        if epoch::advance().is_err() {
+            return;
+        }
+        let gauges = [
+            (&*JEMALLOC_ALLOCATED, stats::allocated::read()),
+            (&*JEMALLOC_ACTIVE, stats::active::read()),
+            (&*JEMALLOC_RESIDENT, stats::resident::read()),
+            (&*JEMALLOC_MAPPED, stats::mapped::read()),
+            (&*JEMALLOC_RETAINED, stats::retained::read()),
+        ];
+        for (gauge, bytes) in gauges.iter() {
+            if let Ok(bytes) = bytes {
+                set_gauge(gauge, *bytes as i64);
+            }
+        }
+    }
+
+    /// Writes a heap profile to `path`, which requires jemalloc to have been started with
+    /// `_RJEM_MALLOC_CONF=prof:true`.
+    pub fn dump_heap_profile(path: &Path) -> Result<(), ProfileError> {
+        let path = c_path(path)?;
+        unsafe { raw::write(b"prof.dump\0", path.as_ptr()) }
+            .map_err(|e| ProfileError::Allocator(format!("{}", e)))
+    }
+}
+
+/// Allocators without statistics.
+#[cfg(not(any(feature = "jemalloc", all(target_os = "linux", target_env = "gnu"))))]
+mod unsupported {
+    use super::ProfileError;
+    use std::path::Path;
+
+    pub const HEAP_PROFILE_EXTENSION: &str = "txt";
+
+    pub fn scrape() {}
+
+    pub fn dump_heap_profile(_path: &Path) -> Result<(), ProfileError> {
+        Err(ProfileError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[cfg(any(feature = "jemalloc", all(target_os = "linux", target_env = "gnu")))]
+    #[test]
+    fn test_scrape_and_dump_heap_profile() {
+        let allocation = vec![1u8; 1 << 20];
+        scrape();
+        let text = String::from_utf8(::lighthouse_metrics::encode_text()).unwrap();
+        if cfg!(feature = "jemalloc") {
+            assert!(text.contains("jemalloc_allocated_bytes"));
+        } else {
+            assert!(text.contains("malloc_in_use_bytes"));
+        }
+        drop(allocation);
+
+        let path = env::temp_dir().join(format!(
+            "lighthouse_heap_profile_test.{}",
+            HEAP_PROFILE_EXTENSION
+        ));
+        match dump_heap_profile(&path) {
+            Ok(()) => assert!(fs::metadata(&path).unwrap().len() > 0),
+            // jemalloc dumps only when profiling was enabled at startup.
+            Err(ProfileError::Allocator(_)) if cfg!(feature = "jemalloc") => {}
+            Err(e) => panic!("{:?}", e),
+        }
+        let _ = fs::remove_file(&path);
+
+        assert!(dump_heap_profile(&env::temp_dir().join("no_such_dir").join("profile")).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+//! Metrics of the process as a whole, read from the OS and the allocator each time the metrics
+//! are scraped, to diagnose the growth of the node's memory, e.g.
+//!
+//! ```ignore
+//! process_metrics::scrape();
+//! let text = lighthouse_metrics::encode_text();
+//! ```
+//!
+//! With the `jemalloc` feature, jemalloc replaces the system allocator, and its statistics are
+//! recorded instead of those of glibc's malloc.
+#[macro_use]
+extern crate lazy_static;
+extern crate libc;
+extern crate lighthouse_metrics;
+#[cfg(feature = "jemalloc")]
+extern crate tikv_jemalloc_ctl;
+#[cfg(feature = "jemalloc")]
+extern crate tikv_jemallocator;
+
+mod allocator;
+mod process;
+
+pub use self::allocator::{dump_heap_profile, ProfileError, HEAP_PROFILE_EXTENSION};
+
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static ALLOCATOR: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+/// Updates the process and allocator metrics, to be called before the registry is encoded.
+pub fn scrape() {
+    process::scrape();
+    allocator::scrape();
+}
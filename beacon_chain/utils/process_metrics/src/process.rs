@@ -0,0 +1,161 @@
+use lighthouse_metrics::{
+    set_float_gauge, set_gauge, try_create_float_gauge, try_create_int_gauge, Gauge, IntGauge,
+    Result,
+};
+use std::fs;
+
+lazy_static! {
+    pub static ref PROCESS_RESIDENT_MEMORY: Result<IntGauge> = try_create_int_gauge(
+        "process_resident_memory_bytes",
+        "Resident memory size in bytes"
+    );
+    pub static ref PROCESS_VIRTUAL_MEMORY: Result<IntGauge> = try_create_int_gauge(
+        "process_virtual_memory_bytes",
+        "Virtual memory size in bytes"
+    );
+    pub static ref PROCESS_THREADS: Result<IntGauge> =
+        try_create_int_gauge("process_threads", "Number of OS threads of the process");
+    pub static ref PROCESS_OPEN_FDS: Result<IntGauge> =
+        try_create_int_gauge("process_open_fds", "Number of open file descriptors");
+    pub static ref PROCESS_MAX_FDS: Result<IntGauge> =
+        try_create_int_gauge("process_max_fds", "Maximum number of open file descriptors");
+    pub static ref PROCESS_CPU_SECONDS: Result<Gauge> = try_create_float_gauge(
+        "process_cpu_seconds_total",
+        "Total user and system CPU time spent in seconds"
+    );
+}
+
+/// The memory and threads of the process, from `/proc/self/status`.
+#[derive(Debug, PartialEq, Default)]
+struct Status {
+    resident_bytes: Option<i64>,
+    virtual_bytes: Option<i64>,
+    threads: Option<i64>,
+}
+
+/// Reads the metrics of the process from `/proc`, leaving those which cannot be read unchanged.
+#[cfg(target_os = "linux")]
+pub fn scrape() {
+    if let Ok(status) = fs::read_to_string("/proc/self/status") {
+        let status = parse_status(&status);
+        if let Some(bytes) = status.resident_bytes {
+            set_gauge(&PROCESS_RESIDENT_MEMORY, bytes);
+        }
+        if let Some(bytes) = status.virtual_bytes {
+            set_gauge(&PROCESS_VIRTUAL_MEMORY, bytes);
+        }
+        if let Some(threads) = status.threads {
+            set_gauge(&PROCESS_THREADS, threads);
+        }
+    }
+    if let Ok(fds) = fs::read_dir("/proc/self/fd") {
+        set_gauge(&PROCESS_OPEN_FDS, fds.count() as i64);
+    }
+    if let Some(max_fds) = fs::read_to_string("/proc/self/limits")
+        .ok()
+        .and_then(|limits| parse_max_fds(&limits))
+    {
+        set_gauge(&PROCESS_MAX_FDS, max_fds);
+    }
+    let ticks_per_second = unsafe { ::libc::sysconf(::libc::_SC_CLK_TCK) };
+    if let Some(ticks) = fs::read_to_string("/proc/self/stat")
+        .ok()
+        .and_then(|stat| parse_cpu_ticks(&stat))
+    {
+        if ticks_per_second > 0 {
+            set_float_gauge(&PROCESS_CPU_SECONDS, ticks as f64 / ticks_per_second as f64);
+        }
+    }
+}
+
+/// Other platforms have no `/proc`, so the metrics are not recorded.
+#[cfg(not(target_os = "linux"))]
+pub fn scrape() {}
+
+/// Parses the lines of the form `VmRSS:   1234 kB` of `/proc/self/status`.
+fn parse_status(status: &str) -> Status {
+    let mut parsed = Status::default();
+    for line in status.lines() {
+        let mut parts = line.split_whitespace();
+        let (key, value) = match (
+            parts.next(),
+            parts.next().and_then(|v| v.parse::<i64>().ok()),
+        ) {
+            (Some(key), Some(value)) => (key, value),
+            _ => continue,
+        };
+        match key {
+            "VmRSS:" => parsed.resident_bytes = Some(value * 1024),
+            "VmSize:" => parsed.virtual_bytes = Some(value * 1024),
+            "Threads:" => parsed.threads = Some(value),
+            _ => {}
+        }
+    }
+    parsed
+}
+
+/// Returns the soft limit of the `Max open files` line of `/proc/self/limits`, unless it is
+/// unlimited.
+fn parse_max_fds(limits: &str) -> Option<i64> {
+    limits
+        .lines()
+        .find(|line| line.starts_with("Max open files"))
+        .and_then(|line| line["Max open files".len()..].split_whitespace().next())
+        .and_then(|soft_limit| soft_limit.parse().ok())
+}
+
+/// Returns the user and system CPU time of `/proc/self/stat` in clock ticks.
+///
+/// The fields are counted from the end of the executable name, which is in parentheses and may
+/// itself hold spaces.
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+    Some(utime + stime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let status = "Name:\tlighthouse\nVmSize:\t  2048 kB\nVmRSS:\t   512 kB\nThreads:\t7\n";
+        assert_eq!(
+            parse_status(status),
+            Status {
+                resident_bytes: Some(512 * 1024),
+                virtual_bytes: Some(2048 * 1024),
+                threads: Some(7),
+            }
+        );
+        assert_eq!(parse_status(""), Status::default());
+    }
+
+    #[test]
+    fn test_parse_max_fds() {
+        let limits = "Limit                     Soft Limit           Hard Limit           Units\n\
+                      Max open files            1024                 4096                 files\n";
+        assert_eq!(parse_max_fds(limits), Some(1024));
+        let unlimited =
+            "Max open files            unlimited            unlimited            files\n";
+        assert_eq!(parse_max_fds(unlimited), None);
+    }
+
+    #[test]
+    fn test_parse_cpu_ticks() {
+        let stat = "42 (light house) S 1 42 42 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 9 0";
+        assert_eq!(parse_cpu_ticks(stat), Some(300));
+        assert_eq!(parse_cpu_ticks("42 (lighthouse) S 1"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_scrape() {
+        scrape();
+        assert!(PROCESS_RESIDENT_MEMORY.as_ref().unwrap().get() > 0);
+        assert!(PROCESS_THREADS.as_ref().unwrap().get() > 0);
+        assert!(PROCESS_OPEN_FDS.as_ref().unwrap().get() > 0);
+    }
+}
@@ -1,5 +1,14 @@
+mod system_time_slot_clock;
+mod testing_slot_clock;
+
+pub use self::system_time_slot_clock::SystemTimeSlotClock;
+pub use self::testing_slot_clock::TestingSlotClock;
+
 use std::time::{Duration, SystemTime, SystemTimeError};
 
+/// The default tolerance for the clocks of peers being ahead of or behind our own.
+pub const MAXIMUM_CLOCK_DISPARITY: Duration = Duration::from_millis(500);
+
 pub fn slot_now(
     genesis_seconds: u64,
     slot_duration_seconds: u64,
@@ -14,6 +23,85 @@ pub fn slot_now(
     }
 }
 
+/// Divides time since genesis into slots, with millisecond precision.
+///
+/// Implementors provide only the genesis, the slot duration and the present time; the slot
+/// arithmetic is shared.
+pub trait SlotClock: Send + Sync {
+    /// The start of slot zero, as a duration since the unix epoch.
+    fn genesis(&self) -> Duration;
+
+    /// Never zero.
+    fn slot_duration(&self) -> Duration;
+
+    /// The present time, as a duration since the unix epoch.
+    fn now_duration(&self) -> Duration;
+
+    /// Returns the present slot, or `None` before genesis.
+    fn now(&self) -> Option<u64> {
+        self.slot_at(self.now_duration())
+    }
+
+    /// Returns the slot of a clock running `tolerance` ahead of this one: the latest slot a peer
+    /// may have started.
+    fn now_with_future_tolerance(&self, tolerance: Duration) -> Option<u64> {
+        self.slot_at(self.now_duration() + tolerance)
+    }
+
+    /// Returns the slot of a clock running `tolerance` behind this one: the earliest slot a peer
+    /// may still be in.
+    fn now_with_past_tolerance(&self, tolerance: Duration) -> Option<u64> {
+        self.slot_at(self.now_duration().checked_sub(tolerance)?)
+    }
+
+    /// Returns the time from now until the start of `slot`, or `None` if it has started.
+    fn duration_to_slot(&self, slot: u64) -> Option<Duration> {
+        self.duration_to(self.start_of(slot))
+    }
+
+    /// Returns the time from now until `instant`, a duration since the unix epoch, or `None` if
+    /// it has passed.
+    fn duration_to(&self, instant: Duration) -> Option<Duration> {
+        instant.checked_sub(self.now_duration())
+    }
+
+    /// Returns the time from now until the start of the next slot.
+    fn duration_to_next_slot(&self) -> Duration {
+        let next_slot = self.now().map_or(0, |slot| slot + 1);
+        self.duration_to_slot(next_slot)
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+
+    /// Returns the time since the start of the present slot, or `None` before genesis.
+    fn duration_into_slot(&self) -> Option<Duration> {
+        let now = self.now_duration();
+        let slot = self.slot_at(now)?;
+        now.checked_sub(self.start_of(slot))
+    }
+
+    /// Returns the time since the start of `slot`, or zero if it has not started.
+    fn duration_since_start_of(&self, slot: u64) -> Duration {
+        self.now_duration()
+            .checked_sub(self.start_of(slot))
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+
+    /// Returns the start of `slot` as a duration since the unix epoch.
+    fn start_of(&self, slot: u64) -> Duration {
+        self.genesis() + Duration::from_millis(as_millis(self.slot_duration()).saturating_mul(slot))
+    }
+
+    /// Returns the slot containing `since_epoch`, or `None` if it is before genesis.
+    fn slot_at(&self, since_epoch: Duration) -> Option<u64> {
+        let since_genesis = since_epoch.checked_sub(self.genesis())?;
+        Some(as_millis(since_genesis) / as_millis(self.slot_duration()))
+    }
+}
+
+fn as_millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
+}
+
 fn slot_from_duration(slot_duration_seconds: u64, duration: Duration) -> Option<u64> {
     duration.as_secs().checked_div(slot_duration_seconds)
 }
@@ -0,0 +1,69 @@
+use super::SlotClock;
+use std::time::{Duration, SystemTime};
+
+/// A `SlotClock` reading the system time.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SystemTimeSlotClock {
+    genesis: Duration,
+    slot_duration: Duration,
+}
+
+impl SystemTimeSlotClock {
+    /// Returns `None` if `slot_duration_millis` is zero.
+    pub fn new(genesis_seconds: u64, slot_duration_millis: u64) -> Option<Self> {
+        if slot_duration_millis == 0 {
+            return None;
+        }
+        Some(Self {
+            genesis: Duration::from_secs(genesis_seconds),
+            slot_duration: Duration::from_millis(slot_duration_millis),
+        })
+    }
+}
+
+impl SlotClock for SystemTimeSlotClock {
+    fn genesis(&self) -> Duration {
+        self.genesis
+    }
+
+    fn slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+
+    fn now_duration(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::from_secs(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_time_slot_clock() {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        let clock = SystemTimeSlotClock::new(now.as_secs() - 10, 4_000).unwrap();
+        let slot = clock.now().unwrap();
+        assert!(slot == 2 || slot == 3);
+        assert_eq!(
+            clock.start_of(3) - clock.start_of(2),
+            Duration::from_secs(4)
+        );
+        assert!(clock.duration_to_next_slot() <= Duration::from_secs(4));
+        assert!(clock.duration_into_slot().unwrap() < Duration::from_secs(4));
+        assert_eq!(clock.duration_to_slot(0), None);
+        assert!(clock.duration_to_slot(slot + 2).unwrap() > Duration::from_secs(4));
+        assert_eq!(clock.duration_to(now), None);
+        assert!(clock.duration_to(now + Duration::from_secs(10)).unwrap() > Duration::from_secs(9));
+
+        let future = SystemTimeSlotClock::new(now.as_secs() + 100, 4_000).unwrap();
+        assert_eq!(future.now(), None);
+        assert_eq!(future.duration_into_slot(), None);
+        assert!(future.duration_to_next_slot() > Duration::from_secs(99));
+        assert_eq!(SystemTimeSlotClock::new(0, 0), None);
+    }
+}
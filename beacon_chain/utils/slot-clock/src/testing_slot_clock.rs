@@ -0,0 +1,128 @@
+use super::SlotClock;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// A `SlotClock` which only moves when told to, for deterministic tests and simulations.
+///
+/// It starts at genesis.
+#[derive(Debug)]
+pub struct TestingSlotClock {
+    genesis: Duration,
+    slot_duration: Duration,
+    now: RwLock<Duration>,
+}
+
+impl TestingSlotClock {
+    /// Returns `None` if `slot_duration_millis` is zero.
+    pub fn new(genesis_seconds: u64, slot_duration_millis: u64) -> Option<Self> {
+        if slot_duration_millis == 0 {
+            return None;
+        }
+        let genesis = Duration::from_secs(genesis_seconds);
+        Some(Self {
+            genesis,
+            slot_duration: Duration::from_millis(slot_duration_millis),
+            now: RwLock::new(genesis),
+        })
+    }
+
+    /// Sets the present time, as a duration since the unix epoch.
+    pub fn set_now(&self, now: Duration) {
+        *self.now.write().expect("Clock lock is not poisoned") = now;
+    }
+
+    /// Moves the present time to the start of `slot`.
+    pub fn set_slot(&self, slot: u64) {
+        self.set_now(self.start_of(slot));
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let now = self.now_duration();
+        self.set_now(now + duration);
+    }
+
+    /// Moves the present time to the start of the next slot.
+    pub fn advance_slot(&self) {
+        let next_slot = self.now().map_or(0, |slot| slot + 1);
+        self.set_slot(next_slot);
+    }
+}
+
+impl SlotClock for TestingSlotClock {
+    fn genesis(&self) -> Duration {
+        self.genesis
+    }
+
+    fn slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+
+    fn now_duration(&self) -> Duration {
+        *self.now.read().expect("Clock lock is not poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_testing_slot_clock() {
+        let clock = TestingSlotClock::new(100, 6_000).unwrap();
+        assert_eq!(clock.now(), Some(0));
+        assert_eq!(clock.duration_into_slot(), Some(Duration::from_secs(0)));
+        assert_eq!(clock.duration_to_next_slot(), Duration::from_secs(6));
+
+        clock.advance(Duration::from_millis(6_500));
+        assert_eq!(clock.now(), Some(1));
+        assert_eq!(clock.duration_into_slot(), Some(Duration::from_millis(500)));
+        assert_eq!(clock.duration_to_next_slot(), Duration::from_millis(5_500));
+
+        clock.advance_slot();
+        assert_eq!(clock.now(), Some(2));
+        assert_eq!(clock.now_duration(), Duration::from_secs(112));
+
+        clock.set_slot(10);
+        assert_eq!(clock.now(), Some(10));
+        assert_eq!(clock.duration_to_slot(10), Some(Duration::from_secs(0)));
+        assert_eq!(clock.duration_to_slot(9), None);
+        assert_eq!(clock.duration_since_start_of(10), Duration::from_secs(0));
+        assert_eq!(clock.duration_since_start_of(9), Duration::from_secs(6));
+        assert_eq!(clock.duration_since_start_of(11), Duration::from_secs(0));
+
+        clock.set_now(Duration::from_secs(50));
+        assert_eq!(clock.now(), None);
+        assert_eq!(clock.duration_to_next_slot(), Duration::from_secs(50));
+        clock.advance_slot();
+        assert_eq!(clock.now(), Some(0));
+
+        assert!(TestingSlotClock::new(0, 0).is_none());
+    }
+
+    #[test]
+    fn test_clock_disparity_tolerance() {
+        let clock = TestingSlotClock::new(100, 6_000).unwrap();
+        let tolerance = Duration::from_millis(500);
+
+        clock.set_slot(5);
+        clock.advance(Duration::from_millis(5_600));
+        assert_eq!(clock.now(), Some(5));
+        assert_eq!(clock.now_with_future_tolerance(tolerance), Some(6));
+        assert_eq!(clock.now_with_past_tolerance(tolerance), Some(5));
+
+        clock.set_slot(5);
+        clock.advance(Duration::from_millis(400));
+        assert_eq!(clock.now_with_future_tolerance(tolerance), Some(5));
+        assert_eq!(clock.now_with_past_tolerance(tolerance), Some(4));
+
+        clock.set_slot(0);
+        assert_eq!(clock.now_with_past_tolerance(tolerance), None);
+        clock.set_now(Duration::from_millis(99_600));
+        assert_eq!(clock.now(), None);
+        assert_eq!(clock.now_with_future_tolerance(tolerance), Some(0));
+        assert_eq!(
+            clock.now_with_past_tolerance(Duration::from_secs(200)),
+            None
+        );
+    }
+}
@@ -18,6 +18,7 @@ const RANDAO_REVEAL_BYTES: usize = HASH_SIZE;
 const POW_CHAIN_REF_BYTES: usize = HASH_SIZE;
 const ACTIVE_STATE_BYTES: usize = HASH_SIZE;
 const CRYSTALLIZED_STATE_BYTES: usize = HASH_SIZE;
+const GRAFFITI_BYTES: usize = HASH_SIZE;
 
 /// Allows for reading of block values directly from serialized ssz bytes.
 ///
@@ -210,6 +211,12 @@ impl<'a> SszBeaconBlock<'a> {
         let start = self.specials_position;
         &self.ssz[start..(start + self.specials_len + LENGTH_PREFIX_BYTES)]
     }
+
+    /// Return the `graffiti` field.
+    pub fn graffiti(&self) -> &[u8] {
+        let start = self.specials_position + LENGTH_PREFIX_BYTES + self.specials_len;
+        &self.ssz[start..(start + GRAFFITI_BYTES)]
+    }
 }
 
 #[cfg(test)]
@@ -293,8 +300,8 @@ mod tests {
         // will tell us if the hash changes, not that it matches some
         // canonical reference.
         let expected_hash = [
-            254, 192, 124, 164, 240, 137, 162, 126, 50, 255, 118, 88, 189, 151, 221, 4, 40, 121,
-            198, 33, 248, 221, 104, 255, 46, 234, 146, 161, 202, 140, 109, 175,
+            69, 217, 167, 100, 105, 190, 126, 102, 12, 58, 113, 128, 118, 131, 190, 251, 223, 177,
+            197, 46, 127, 229, 14, 104, 215, 108, 3, 63, 227, 159, 60, 237,
         ];
         assert_eq!(hash, expected_hash);
 
@@ -477,4 +484,17 @@ mod tests {
 
         assert_eq!(ssz_block.cry_state_root(), &reference_hash.to_vec()[..]);
     }
+
+    #[test]
+    fn test_ssz_block_graffiti() {
+        let mut block = BeaconBlock::zero();
+        block.specials.push(SpecialRecord::logout(&[1]));
+        let reference_hash = Hash256::from([42_u8; 32]);
+        block.graffiti = reference_hash.clone();
+
+        let serialized = get_block_ssz(&block);
+        let ssz_block = SszBeaconBlock::from_slice(&serialized).unwrap();
+
+        assert_eq!(ssz_block.graffiti(), &reference_hash.to_vec()[..]);
+    }
 }
@@ -0,0 +1,230 @@
+//! Runs the background tasks of the node, each on its own named thread.
+//!
+//! A task which panics would otherwise end its thread silently, leaving the node running without
+//! it, e.g. importing no more blocks. Instead, the panic is logged and counted, and the node is
+//! asked to shut down in an orderly way, through the receiver returned by `TaskExecutor::new`.
+#[macro_use]
+extern crate lazy_static;
+extern crate lighthouse_metrics;
+#[macro_use]
+extern crate slog;
+
+mod metrics;
+
+use lighthouse_metrics::{dec_gauge_vec, inc_counter_vec, inc_gauge_vec};
+use slog::Logger;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Why the node is shutting down.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ShutdownReason {
+    /// SIGINT or SIGTERM was received.
+    Signal,
+    /// The task of this name panicked.
+    Panic(String),
+}
+
+impl ShutdownReason {
+    /// Whether the node is shutting down because something went wrong, rather than on request.
+    pub fn is_failure(&self) -> bool {
+        match self {
+            ShutdownReason::Signal => false,
+            ShutdownReason::Panic(_) => true,
+        }
+    }
+}
+
+/// The signal to the tasks of an executor that the node is shutting down, and why.
+#[derive(Clone, Default)]
+pub struct Exit {
+    reason: Arc<(Mutex<Option<ShutdownReason>>, Condvar)>,
+}
+
+impl Exit {
+    pub fn is_exiting(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// The first reason given to shut down, if the node is shutting down.
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        self.reason.0.lock().expect("Exit lock poisoned").clone()
+    }
+
+    /// Waits up to `timeout` for the node to shut down, returning `true` if it is shutting down.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (ref lock, ref condvar) = *self.reason;
+        let mut reason = lock.lock().expect("Exit lock poisoned");
+        while reason.is_none() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            reason = condvar
+                .wait_timeout(reason, deadline - now)
+                .expect("Exit lock poisoned")
+                .0;
+        }
+        reason.is_some()
+    }
+
+    fn trigger(&self, reason: ShutdownReason) {
+        let (ref lock, ref condvar) = *self.reason;
+        lock.lock()
+            .expect("Exit lock poisoned")
+            .get_or_insert(reason);
+        condvar.notify_all();
+    }
+}
+
+/// Spawns the background tasks of the node, shutting the node down should any of them panic.
+#[derive(Clone)]
+pub struct TaskExecutor {
+    exit: Exit,
+    shutdown: Sender<ShutdownReason>,
+    log: Logger,
+}
+
+impl TaskExecutor {
+    /// Returns an executor, with the receiver of its requests to shut the node down.
+    ///
+    /// Dropping the receiver is harmless, e.g. in tests, as requests are then ignored.
+    pub fn new(log: Logger) -> (Self, Receiver<ShutdownReason>) {
+        let (shutdown, shutdown_rx) = channel();
+        let executor = Self {
+            exit: Exit::default(),
+            shutdown,
+            log,
+        };
+        (executor, shutdown_rx)
+    }
+
+    /// The signal given to each task, which is triggered once the node starts shutting down.
+    pub fn exit(&self) -> Exit {
+        self.exit.clone()
+    }
+
+    pub fn log(&self) -> &Logger {
+        &self.log
+    }
+
+    /// Asks the node to shut down for `reason`, signalling the tasks to exit.
+    pub fn shut_down(&self, reason: ShutdownReason) {
+        self.exit.trigger(reason.clone());
+        let _ = self.shutdown.send(reason);
+    }
+
+    /// Runs `task` on a new thread named `name`.
+    ///
+    /// Should the task panic, the panic is logged, the node is asked to shut down and the thread
+    /// ends normally, so that joining it does not propagate the panic.
+    pub fn spawn<F>(&self, name: &'static str, task: F) -> JoinHandle<()>
+    where
+        F: FnOnce(Exit) + Send + 'static,
+    {
+        let executor = self.clone();
+        inc_counter_vec(&metrics::TASKS_SPAWNED, &[name]);
+        thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                inc_gauge_vec(&metrics::TASKS_RUNNING, &[name]);
+                let result = panic::catch_unwind(AssertUnwindSafe(|| task(executor.exit())));
+                dec_gauge_vec(&metrics::TASKS_RUNNING, &[name]);
+                if let Err(payload) = result {
+                    inc_counter_vec(&metrics::TASK_PANICS, &[name]);
+                    crit!(executor.log, "Task panicked, shutting down"; "error" => panic_message(&*payload), "task" => name);
+                    executor.shut_down(ShutdownReason::Panic(name.to_string()));
+                }
+            })
+            .expect("Unable to spawn thread")
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::Discard;
+
+    #[test]
+    fn test_spawn() {
+        let (executor, shutdown) = TaskExecutor::new(Logger::root(Discard, o!()));
+        let (tx, rx) = channel();
+        executor
+            .spawn("test_task", move |exit| {
+                tx.send(thread::current().name().map(String::from)).unwrap();
+                assert!(!exit.is_exiting());
+            })
+            .join()
+            .unwrap();
+        assert_eq!(rx.recv().unwrap(), Some("test_task".to_string()));
+        assert!(shutdown.try_recv().is_err());
+        assert_eq!(executor.exit().reason(), None);
+        assert!(!ShutdownReason::Signal.is_failure());
+    }
+
+    #[test]
+    fn test_panic_shuts_down() {
+        let (executor, shutdown) = TaskExecutor::new(Logger::root(Discard, o!()));
+        let exit = executor.exit();
+        let waiting = executor.spawn("test_waiting_task", |exit| {
+            assert!(exit.wait_timeout(Duration::from_secs(60)));
+        });
+        assert!(!exit.wait_timeout(Duration::from_millis(10)));
+
+        /*
+         * The panic is caught, so the thread is joined without error, while the node is asked to
+         * shut down and the other tasks are told to exit.
+         */
+        assert!(executor
+            .spawn("test_panicking_task", |_| panic!("Task failed"))
+            .join()
+            .is_ok());
+        assert_eq!(
+            shutdown.recv_timeout(Duration::from_secs(5)),
+            Ok(ShutdownReason::Panic("test_panicking_task".to_string()))
+        );
+        assert!(exit.is_exiting());
+        waiting.join().unwrap();
+
+        /*
+         * The first reason is kept.
+         */
+        executor.shut_down(ShutdownReason::Signal);
+        assert_eq!(
+            exit.reason(),
+            Some(ShutdownReason::Panic("test_panicking_task".to_string()))
+        );
+        assert!(exit.reason().unwrap().is_failure());
+        assert_eq!(
+            metrics::TASK_PANICS
+                .as_ref()
+                .unwrap()
+                .with_label_values(&["test_panicking_task"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics::TASKS_RUNNING
+                .as_ref()
+                .unwrap()
+                .with_label_values(&["test_waiting_task"])
+                .get(),
+            0
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use lighthouse_metrics::{
+    try_create_int_counter_vec, try_create_int_gauge_vec, IntCounterVec, IntGaugeVec, Result,
+};
+
+lazy_static! {
+    pub static ref TASKS_SPAWNED: Result<IntCounterVec> = try_create_int_counter_vec(
+        "task_executor_tasks_spawned_total",
+        "Count of tasks spawned, by task name",
+        &["task"]
+    );
+    pub static ref TASKS_RUNNING: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "task_executor_tasks_running",
+        "Number of tasks running, by task name",
+        &["task"]
+    );
+    pub static ref TASK_PANICS: Result<IntCounterVec> = try_create_int_counter_vec(
+        "task_executor_task_panics_total",
+        "Count of tasks which panicked, by task name",
+        &["task"]
+    );
+}
@@ -0,0 +1,43 @@
+#[macro_use]
+extern crate criterion;
+extern crate types;
+extern crate validator_shuffling;
+
+use criterion::{black_box, Benchmark, Criterion};
+use types::{ChainConfig, ValidatorRecord, ValidatorStatus};
+use validator_shuffling::shard_and_committees_for_cycle;
+
+/// Returns `count` active validators. They share a keypair, as shuffling ignores keys.
+fn validators(count: usize) -> Vec<ValidatorRecord> {
+    let (mut validator, _) = ValidatorRecord::zero_with_thread_rand_keypair();
+    validator.status = ValidatorStatus::Active as u8;
+    vec![validator; count]
+}
+
+/// Assigns validators to the committees of a cycle, the work done at each cycle boundary.
+fn cycle_shuffling(c: &mut Criterion) {
+    let config = ChainConfig::standard();
+    for &count in &[16_384, 131_072, 1_048_576] {
+        let validators = validators(count);
+        let config = config.clone();
+        shard_and_committees_for_cycle(&[0; 32], &validators, 0, &config)
+            .expect("Validators must be assignable");
+        c.bench(
+            "cycle_shuffling",
+            Benchmark::new(format!("{}_validators", count), move |b| {
+                b.iter(|| {
+                    black_box(shard_and_committees_for_cycle(
+                        &[0; 32],
+                        &validators,
+                        0,
+                        &config,
+                    ))
+                })
+            })
+            .sample_size(10),
+        );
+    }
+}
+
+criterion_group!(benches, cycle_shuffling);
+criterion_main!(benches);
@@ -0,0 +1,174 @@
+//! Writes seed inputs for each fuzz target to `corpus/<target>`, so that fuzzing starts from
+//! well-formed messages rather than having to discover the SSZ layouts.
+//!
+//! Run from the `fuzz` directory with `cargo run --example write_corpus`.
+extern crate beacon_node;
+extern crate bls;
+extern crate network;
+extern crate ssz;
+extern crate types;
+
+use bls::{AggregateSignature, Keypair, Signature};
+use network::rpc::codec::{encode_request, encode_response_chunk};
+use network::rpc::{
+    BlocksByRangeRequest, BlocksByRootRequest, ForkDigest, MetaData, Ping, ResponseCode,
+    StatusMessage,
+};
+use network::Enr;
+use ssz::{ssz_encode, Encodable};
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use types::{
+    AggregateAndProof, Attestation, AttestationData, BeaconBlock, Bitfield, Hash256, SpecialRecord,
+};
+
+fn attestation(slot: u64) -> Attestation {
+    let mut participation_bitfield = Bitfield::from_elem(2, false);
+    participation_bitfield.set(1, true);
+    Attestation {
+        data: AttestationData {
+            slot,
+            shard: 1,
+            beacon_block_hash: Hash256::from([1; 32]),
+            justified_slot: slot.saturating_sub(1),
+            ..AttestationData::zero()
+        },
+        participation_bitfield,
+        custody_bitfield: Bitfield::from_elem(2, false),
+        aggregate_sig: AggregateSignature::new(),
+    }
+}
+
+fn block(slot: u64, parent: Hash256) -> BeaconBlock {
+    BeaconBlock {
+        slot,
+        ancestor_hashes: vec![parent],
+        attestations: vec![attestation(slot - 1)],
+        specials: vec![SpecialRecord::logout(&[0; 8])],
+        graffiti: Hash256::from([7; 32]),
+        ..BeaconBlock::zero()
+    }
+}
+
+/// Returns an `rpc_codec` input of the request on `protocol` with the SSZ payload `ssz`.
+fn request(protocol: u8, ssz: Vec<u8>) -> Vec<u8> {
+    let mut bytes = vec![protocol, 0];
+    bytes.append(&mut encode_request(&ssz));
+    bytes
+}
+
+/// Returns an `rpc_codec` input of a successful response on `protocol` with the SSZ payloads of
+/// `chunks`, delivered in pieces of 16 bytes.
+fn response(protocol: u8, chunks: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut bytes = vec![0x80 | protocol, 15];
+    for chunk in chunks {
+        bytes.append(&mut encode_response_chunk(ResponseCode::Success, &chunk));
+    }
+    bytes
+}
+
+/// Returns the SSZ of `item`, prefixed by `kind`.
+fn tagged<T: Encodable>(kind: u8, item: &T) -> Vec<u8> {
+    let mut bytes = vec![kind];
+    bytes.append(&mut ssz_encode(item));
+    bytes
+}
+
+fn write(target: &str, seeds: Vec<Vec<u8>>) {
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir).expect("Unable to create corpus directory");
+    for (i, seed) in seeds.iter().enumerate() {
+        fs::write(dir.join(format!("seed-{}", i)), seed).expect("Unable to write seed");
+    }
+    println!("Wrote {} seeds to {}", seeds.len(), dir.display());
+}
+
+fn main() {
+    let keypair = Keypair::random();
+    let first = block(1, Hash256::zero());
+    let second = block(2, first.canonical_root());
+    let status = StatusMessage {
+        fork_digest: ForkDigest([1, 2, 3, 4]),
+        finalized_root: Hash256::from([2; 32]),
+        finalized_slot: 8,
+        head_root: Hash256::from([3; 32]),
+        head_slot: 12,
+    };
+    let metadata = MetaData {
+        seq_number: 3,
+        attnets: Bitfield::from_elem(64, true),
+    };
+    let range = BlocksByRangeRequest {
+        start_slot: 1,
+        count: 64,
+        step: 1,
+    };
+    let roots = BlocksByRootRequest {
+        block_roots: vec![first.canonical_root(), second.canonical_root()],
+    };
+    let enr = Enr::new(
+        &keypair,
+        Some(Ipv4Addr::new(127, 0, 0, 1)),
+        Some(9000),
+        Some(9000),
+    );
+
+    /*
+     * The first byte of each `ssz_decode` seed selects the type, in the order of the target.
+     */
+    write(
+        "ssz_decode",
+        vec![
+            tagged(0, &first),
+            tagged(1, &attestation(3)),
+            tagged(2, &attestation(3).data),
+            tagged(3, &SpecialRecord::randao_change(&[5; 32])),
+            tagged(
+                4,
+                &AggregateAndProof {
+                    aggregator_index: 4,
+                    aggregate: attestation(3),
+                    selection_proof: Signature::new(&[3], &keypair.sk),
+                },
+            ),
+            tagged(5, &status),
+            tagged(6, &metadata),
+            tagged(7, &range),
+            tagged(8, &roots),
+            tagged(9, &enr),
+        ],
+    );
+
+    /*
+     * The first byte of each `rpc_codec` seed selects the protocol, with the high bit set for a
+     * response, and the second the size of the pieces in which a response is delivered.
+     */
+    let request_seeds = vec![
+        request(0, ssz_encode(&status)),
+        request(2, ssz_encode(&Ping { seq_number: 3 })),
+        request(3, vec![]),
+        request(4, ssz_encode(&range)),
+        request(5, ssz_encode(&roots)),
+    ];
+    let mut rpc_seeds = request_seeds;
+    rpc_seeds.push(response(0, vec![ssz_encode(&status)]));
+    rpc_seeds.push(response(3, vec![ssz_encode(&metadata)]));
+    rpc_seeds.push(response(4, vec![ssz_encode(&first), ssz_encode(&second)]));
+    let mut error = vec![0x85, 255];
+    error.append(&mut encode_response_chunk(
+        ResponseCode::InvalidRequest,
+        b"Unknown block",
+    ));
+    rpc_seeds.push(error);
+    write("rpc_codec", rpc_seeds);
+
+    /*
+     * Seeds for `block_import` set the low bit of the first byte, so that each block is made a
+     * child of the last imported.
+     */
+    let mut chain = vec![1];
+    chain.append(&mut ssz_encode(&first));
+    chain.append(&mut ssz_encode(&second));
+    write("block_import", vec![chain, tagged(0, &first)]);
+}
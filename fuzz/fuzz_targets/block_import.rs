@@ -0,0 +1,77 @@
+//! Decodes arbitrary bytes as a sequence of SSZ blocks and imports each into a fresh node, as
+//! for blocks received by gossip or sync, along with the attestations they include.
+//!
+//! Blocks with random parents are almost always rejected before reaching fork choice, so if the
+//! low bit of the first byte is set each block is made a child of the last block imported.
+//!
+//! Block and attestation import do not verify signatures until the state transition is
+//! restored, so no fake crypto is required for the fuzzer to reach fork choice.
+#![no_main]
+extern crate beacon_node;
+extern crate db;
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate ssz;
+extern crate types;
+
+use beacon_node::{BeaconNode, BlockProcessingOutcome};
+use db::stores::BeaconBlockStore;
+use db::MemoryDB;
+use ssz::Decodable;
+use std::sync::Arc;
+use types::{BeaconBlock, ChainConfig, ValidatorRegistration};
+
+/// The present slot, far enough ahead that no block is rejected as being from the future.
+const PRESENT_SLOT: u64 = 1 << 32;
+
+lazy_static! {
+    /// A config with two slots per cycle and one committee per slot.
+    ///
+    /// The validators are random, but as the genesis shuffling depends only on their number,
+    /// any input is processed identically from one run to the next.
+    static ref CONFIG: ChainConfig = {
+        let mut config = ChainConfig::standard();
+        config.cycle_length = 2;
+        config.shard_count = 2;
+        config.min_committee_size = 2;
+        config.min_attestation_inclusion_delay = 1;
+        config.initial_validators = (0..8).map(|_| ValidatorRegistration::random()).collect();
+        config
+    };
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (flags, bytes) = match data.split_first() {
+        Some((flags, bytes)) => (*flags, bytes),
+        None => return,
+    };
+    let link_parents = flags & 1 == 1;
+
+    let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+    let mut node = BeaconNode::new(CONFIG.clone(), store).expect("Genesis must be valid");
+    let mut parent = node.genesis_root();
+
+    let mut i = 0;
+    while let Ok((mut block, next)) = BeaconBlock::ssz_decode(bytes, i) {
+        i = next;
+        if link_parents {
+            match block.ancestor_hashes.first_mut() {
+                Some(hash) => *hash = parent,
+                None => block.ancestor_hashes.push(parent),
+            }
+        }
+        let outcome = node
+            .process_block(&block, PRESENT_SLOT)
+            .expect("Block import must not fail on a valid store");
+        if outcome == BlockProcessingOutcome::Imported {
+            parent = block.canonical_root();
+            assert_eq!(node.block(&parent), Ok(block.clone()));
+        }
+        for attestation in block.attestations {
+            node.process_attestation(attestation, PRESENT_SLOT)
+                .expect("Attestation import must not fail");
+        }
+    }
+});
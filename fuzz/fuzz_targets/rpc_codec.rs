@@ -0,0 +1,54 @@
+//! Decodes arbitrary bytes as a request or as a stream of response chunks, as read from an RPC
+//! stream, along with the SSZ message of each decoded payload.
+//!
+//! The first byte selects the protocol and direction. For responses, the second byte gives the
+//! size of the pieces in which the stream is delivered, so that partial chunks are exercised.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate network;
+
+use network::rpc::codec::{decode_request, encode_request, ResponseDecoder};
+use network::rpc::{Protocol, RPCRequest, RPCResponse, ResponseCode};
+
+const PROTOCOLS: [Protocol; 6] = [
+    Protocol::Status,
+    Protocol::Goodbye,
+    Protocol::Ping,
+    Protocol::MetaData,
+    Protocol::BeaconBlocksByRange,
+    Protocol::BeaconBlocksByRoot,
+];
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+    let protocol = PROTOCOLS[data[0] as usize % PROTOCOLS.len()];
+    let is_request = data[0] & 0x80 == 0;
+    let piece_len = data[1] as usize + 1;
+    let bytes = &data[2..];
+
+    if is_request {
+        if let Ok(ssz) = decode_request(bytes) {
+            assert_eq!(decode_request(&encode_request(&ssz)), Ok(ssz.clone()));
+            let _ = RPCRequest::from_ssz(protocol, &ssz);
+        }
+        return;
+    }
+
+    let mut decoder = ResponseDecoder::new();
+    for piece in bytes.chunks(piece_len) {
+        decoder.push(piece);
+        loop {
+            match decoder.next_chunk() {
+                Ok(Some((ResponseCode::Success, ssz))) => {
+                    let _ = RPCResponse::from_ssz(protocol, &ssz);
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(_) => return,
+            }
+        }
+    }
+});
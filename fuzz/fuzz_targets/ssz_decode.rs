@@ -0,0 +1,46 @@
+//! Decodes arbitrary bytes as each of the SSZ objects received from peers.
+//!
+//! The first byte selects the type. Any object which decodes must survive being encoded and
+//! decoded again unchanged.
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate network;
+extern crate ssz;
+extern crate types;
+
+use network::rpc::{BlocksByRangeRequest, BlocksByRootRequest, MetaData, StatusMessage};
+use network::Enr;
+use ssz::{ssz_encode, Decodable, Encodable};
+use std::fmt::Debug;
+use types::{AggregateAndProof, Attestation, AttestationData, BeaconBlock, SpecialRecord};
+
+fn round_trip<T>(bytes: &[u8])
+where
+    T: Decodable + Encodable + PartialEq + Debug,
+{
+    if let Ok((item, _)) = T::ssz_decode(bytes, 0) {
+        let (decoded, i) = T::ssz_decode(&ssz_encode(&item), 0).expect("Re-encoding must decode");
+        assert_eq!(decoded, item);
+        assert_eq!(i, ssz_encode(&item).len());
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (kind, bytes) = match data.split_first() {
+        Some((kind, bytes)) => (*kind, bytes),
+        None => return,
+    };
+    match kind % 10 {
+        0 => round_trip::<BeaconBlock>(bytes),
+        1 => round_trip::<Attestation>(bytes),
+        2 => round_trip::<AttestationData>(bytes),
+        3 => round_trip::<SpecialRecord>(bytes),
+        4 => round_trip::<AggregateAndProof>(bytes),
+        5 => round_trip::<StatusMessage>(bytes),
+        6 => round_trip::<MetaData>(bytes),
+        7 => round_trip::<BlocksByRangeRequest>(bytes),
+        8 => round_trip::<BlocksByRootRequest>(bytes),
+        _ => round_trip::<Enr>(bytes),
+    }
+});
@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::Path;
+
+use bls::PublicKey;
+use clap::ArgMatches;
+use hex;
+use rpassword;
+use serde_json::{self, Value};
+use slog::Logger;
+use validator_client::{
+    find_keystores, generate_mnemonic, mnemonic_seed, Kdf, Keystore, KeystorePassword,
+    ValidatorDefinitions, ValidatorDefinitionsError, ValidatorKeys,
+};
+
+/// Runs the account management subcommands.
+pub fn run(matches: &ArgMatches, validators_dir: &Path, log: &Logger) {
+    if let Some(matches) = matches
+        .subcommand_matches("validator")
+        .and_then(|matches| matches.subcommand_matches("import"))
+    {
+        import(matches, validators_dir, log);
+        return;
+    }
+    if let Some(matches) = matches
+        .subcommand_matches("validator")
+        .and_then(|matches| matches.subcommand_matches("deposit"))
+    {
+        deposit(matches, log);
+        return;
+    }
+    error!(log, "No account command given, see --help");
+}
+
+/// Imports the EIP-2335 keystores of a directory into the validator definitions, so the validator
+/// client performs their duties.
+///
+/// The password of each keystore is read from `--password-file`, which is then used whenever the
+/// keystore is decrypted, or else prompted for and stored in the definitions.
+fn import(matches: &ArgMatches, validators_dir: &Path, log: &Logger) {
+    let dir = matches
+        .value_of("directory")
+        .map(Path::new)
+        .expect("directory is required");
+    let keystores = match find_keystores(dir) {
+        Ok(keystores) => keystores,
+        Err(e) => {
+            error!(log, "Unable to read keystore directory";
+                   "dir" => format!("{}", dir.display()),
+                   "error" => format!("{}", e));
+            return;
+        }
+    };
+    if keystores.is_empty() {
+        warn!(log, "No keystores found"; "dir" => format!("{}", dir.display()));
+        return;
+    }
+    let password_file = match matches.value_of("password-file").map(Path::new) {
+        Some(path) => match path.canonicalize() {
+            Ok(path) => Some(path),
+            Err(e) => {
+                error!(log, "Unable to read password file";
+                       "path" => format!("{}", path.display()),
+                       "error" => format!("{}", e));
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let mut definitions = match ValidatorDefinitions::open(validators_dir) {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            error!(log, "Unable to read validator definitions"; "error" => format!("{:?}", e));
+            return;
+        }
+    };
+    let mut imported = 0;
+    for path in keystores {
+        let password = match password_file {
+            Some(ref password_file) => KeystorePassword::File(password_file.clone()),
+            None => {
+                let pubkey = Keystore::from_file(&path)
+                    .map(|keystore| hex_pubkey(&keystore.pubkey))
+                    .unwrap_or_default();
+                let prompt = format!("Password of {} {}: ", path.display(), pubkey);
+                match rpassword::read_password_from_tty(Some(&prompt)) {
+                    Ok(password) => KeystorePassword::Inline(password),
+                    Err(e) => {
+                        error!(log, "Unable to read password"; "error" => format!("{}", e));
+                        return;
+                    }
+                }
+            }
+        };
+        match definitions.import_keystore(validators_dir, &path, password) {
+            Ok(pubkey) => {
+                imported += 1;
+                info!(log, "Imported keystore";
+                      "path" => format!("{}", path.display()),
+                      "voting_pubkey" => hex_pubkey(&pubkey));
+            }
+            Err(ValidatorDefinitionsError::DuplicateValidator(_)) => {
+                info!(log, "Skipped keystore already imported"; "path" => format!("{}", path.display()));
+            }
+            Err(e) => {
+                error!(log, "Unable to import keystore";
+                       "path" => format!("{}", path.display()),
+                       "error" => format!("{:?}", e));
+            }
+        }
+    }
+
+    if let Err(e) = definitions.save(validators_dir) {
+        error!(log, "Unable to save validator definitions"; "error" => format!("{:?}", e));
+        return;
+    }
+    info!(log, "Saved validator definitions";
+          "imported" => imported,
+          "validators" => definitions.0.len(),
+          "dir" => format!("{}", validators_dir.display()));
+}
+
+/// Derives the keys of validators from a mnemonic, writing an EIP-2335 keystore of each voting
+/// key and a `deposit_data.json` of their signed deposits, in the format of the deposit CLI which
+/// the launchpad reads.
+///
+/// If no `--mnemonic-file` is given, a new mnemonic is generated and printed, which must be
+/// written down as it is the only backup of the withdrawal keys.
+fn deposit(matches: &ArgMatches, log: &Logger) {
+    let output_dir = matches
+        .value_of("output-dir")
+        .map(Path::new)
+        .expect("output-dir is required");
+    let (count, first_index, amount) = match (
+        matches.value_of("count").map(str::parse::<u32>),
+        matches.value_of("first-index").map(str::parse::<u32>),
+        matches.value_of("amount").map(str::parse::<u64>),
+    ) {
+        (Some(Ok(count)), Some(Ok(first_index)), Some(Ok(amount))) => (count, first_index, amount),
+        _ => {
+            error!(log, "Invalid --count, --first-index or --amount");
+            return;
+        }
+    };
+    let fork_version = match matches.value_of("fork-version").map(parse_fork_version) {
+        Some(Some(fork_version)) => fork_version,
+        _ => {
+            error!(log, "Invalid --fork-version, expected 4 bytes of hex");
+            return;
+        }
+    };
+    let network_name = matches.value_of("network-name").unwrap_or("mainnet");
+
+    let phrase = match matches.value_of("mnemonic-file") {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(phrase) => phrase,
+            Err(e) => {
+                error!(log, "Unable to read mnemonic file"; "path" => path, "error" => format!("{}", e));
+                return;
+            }
+        },
+        None => {
+            let phrase = generate_mnemonic();
+            println!("Write down this mnemonic, which is the only backup of the withdrawal keys:");
+            println!("\n{}\n", phrase);
+            phrase
+        }
+    };
+    let seed = match mnemonic_seed(&phrase, "") {
+        Ok(seed) => seed,
+        Err(e) => {
+            error!(log, "Invalid mnemonic"; "error" => format!("{:?}", e));
+            return;
+        }
+    };
+    let password = match matches.value_of("password-file") {
+        Some(path) => KeystorePassword::File(path.into()).read(),
+        None => rpassword::read_password_from_tty(Some("Password of the keystores: ")),
+    };
+    let password = match password {
+        Ok(password) => password,
+        Err(e) => {
+            error!(log, "Unable to read password"; "error" => format!("{}", e));
+            return;
+        }
+    };
+
+    if let Err(e) = fs::create_dir_all(output_dir) {
+        error!(log, "Unable to create output dir"; "error" => format!("{}", e));
+        return;
+    }
+    let mut deposits = vec![];
+    for index in first_index..first_index.saturating_add(count) {
+        let keys = match ValidatorKeys::derive(&seed, index) {
+            Ok(keys) => keys,
+            Err(e) => {
+                error!(log, "Unable to derive keys"; "index" => index, "error" => format!("{:?}", e));
+                return;
+            }
+        };
+        let keystore = match keys.voting_keystore(&password, Kdf::scrypt()) {
+            Ok(keystore) => keystore,
+            Err(e) => {
+                error!(log, "Unable to encrypt keystore"; "error" => format!("{:?}", e));
+                return;
+            }
+        };
+        let path = output_dir.join(keys.keystore_file_name());
+        if let Err(e) = fs::write(&path, keystore.to_json().to_string()) {
+            error!(log, "Unable to write keystore";
+                   "path" => format!("{}", path.display()),
+                   "error" => format!("{}", e));
+            return;
+        }
+        info!(log, "Wrote keystore";
+              "path" => format!("{}", path.display()),
+              "voting_pubkey" => hex_pubkey(&keys.voting.pk));
+        deposits.push(
+            keys.deposit_data(amount, fork_version)
+                .to_launchpad_json(fork_version, network_name),
+        );
+    }
+
+    let path = output_dir.join("deposit_data.json");
+    let json = serde_json::to_string_pretty(&Value::Array(deposits)).expect("JSON is valid");
+    if let Err(e) = fs::write(&path, json) {
+        error!(log, "Unable to write deposit data";
+               "path" => format!("{}", path.display()),
+               "error" => format!("{}", e));
+        return;
+    }
+    info!(log, "Wrote deposit data";
+          "path" => format!("{}", path.display()),
+          "validators" => count,
+          "network" => network_name);
+}
+
+/// Parses a fork version of 4 bytes of hex, with or without a `0x` prefix.
+fn parse_fork_version(hex_version: &str) -> Option<[u8; 4]> {
+    let bytes = hex::decode(hex_version.trim_start_matches("0x")).ok()?;
+    if bytes.len() != 4 {
+        return None;
+    }
+    let mut version = [0; 4];
+    version.copy_from_slice(&bytes);
+    Some(version)
+}
+
+fn hex_pubkey(pubkey: &PublicKey) -> String {
+    format!("0x{}", hex::encode(pubkey.as_bytes()))
+}
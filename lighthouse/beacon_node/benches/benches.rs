@@ -0,0 +1,127 @@
+#[macro_use]
+extern crate criterion;
+extern crate beacon_node;
+extern crate db;
+extern crate types;
+
+use beacon_node::{
+    pack_attestations, BeaconNode, BlockProcessingOutcome, MAX_ATTESTATIONS_PER_BLOCK,
+};
+use criterion::{Benchmark, Criterion};
+use db::stores::BeaconBlockStore;
+use db::MemoryDB;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use types::{Attestation, BeaconBlock, Bitfield, ChainConfig, ValidatorRegistration};
+
+const VALIDATOR_COUNT: usize = 256;
+
+fn node() -> BeaconNode<MemoryDB> {
+    let mut config = ChainConfig::standard();
+    config.cycle_length = 8;
+    config.shard_count = 8;
+    config.min_committee_size = 16;
+    config.min_attestation_inclusion_delay = 1;
+    config.initial_validators = (0..VALIDATOR_COUNT)
+        .map(|_| ValidatorRegistration::random())
+        .collect();
+    let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+    BeaconNode::new(config, store).unwrap()
+}
+
+/// Returns an attestation from every member of each committee at `slot`.
+fn attestations(node: &BeaconNode<MemoryDB>, slot: u64) -> Vec<Attestation> {
+    node.committees(slot)
+        .iter()
+        .map(|committee| {
+            let mut attestation = Attestation::zero();
+            attestation.data = node
+                .produce_attestation_data(slot, u64::from(committee.shard))
+                .unwrap();
+            attestation.participation_bitfield =
+                Bitfield::from_elem(committee.committee.len(), true);
+            attestation
+        })
+        .collect()
+}
+
+/// Imports a block at each successive slot, each a child of the last. If `attest`, the block
+/// includes attestations from every committee of its parent's slot.
+fn bench_import(c: &mut Criterion, name: &str, attest: bool) {
+    let node = RefCell::new(node());
+    let parent = Cell::new((0, node.borrow().genesis_root()));
+    c.bench(
+        "process_block",
+        Benchmark::new(name, move |b| {
+            b.iter_with_setup(
+                || {
+                    let (slot, parent_root) = parent.get();
+                    let mut block = BeaconBlock::zero();
+                    block.slot = slot + 1;
+                    block.ancestor_hashes.push(parent_root);
+                    if attest && slot > 0 {
+                        block.attestations = attestations(&node.borrow(), slot);
+                    }
+                    parent.set((block.slot, block.canonical_root()));
+                    block
+                },
+                |block| {
+                    let outcome = node.borrow_mut().process_block(&block, block.slot).unwrap();
+                    assert_eq!(outcome, BlockProcessingOutcome::Imported);
+                },
+            )
+        }),
+    );
+}
+
+fn process_block(c: &mut Criterion) {
+    bench_import(c, "empty", false);
+    bench_import(c, "with_attestations", true);
+}
+
+/// Packs a block from a cycle of attestations, each committee attesting once individually and
+/// once in an aggregate of the first half of its members.
+fn pack(c: &mut Criterion) {
+    let node = node();
+    let mut candidates = vec![];
+    for slot in 0..u64::from(node.config().cycle_length) {
+        for committee in node.committees(slot) {
+            let data = node
+                .produce_attestation_data(slot, u64::from(committee.shard))
+                .unwrap();
+            let len = committee.committee.len();
+            for (i, index) in committee.committee.iter().enumerate() {
+                let mut attestation = Attestation::zero();
+                attestation.data = data.clone();
+                attestation.participation_bitfield = Bitfield::from_elem(len, false);
+                attestation.participation_bitfield.set(i, true);
+                candidates.push((attestation, vec![*index]));
+            }
+            let mut aggregate = Attestation::zero();
+            aggregate.data = data;
+            aggregate.participation_bitfield = Bitfield::from_elem(len, false);
+            for i in 0..len / 2 {
+                aggregate.participation_bitfield.set(i, true);
+            }
+            candidates.push((aggregate, committee.committee[..len / 2].to_vec()));
+        }
+    }
+    let validators = node.validators().to_vec();
+    c.bench(
+        "pack_attestations",
+        Benchmark::new(format!("{}_candidates", candidates.len()), move |b| {
+            b.iter(|| {
+                pack_attestations(
+                    candidates.clone(),
+                    &BTreeSet::new(),
+                    &validators,
+                    MAX_ATTESTATIONS_PER_BLOCK,
+                )
+            })
+        }),
+    );
+}
+
+criterion_group!(benches, process_block, pack);
+criterion_main!(benches);
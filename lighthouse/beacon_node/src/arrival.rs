@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The fraction of a slot after which a block arrives too late to be attested to on time, as
+/// attesters vote a third of the way into the slot.
+pub const LATE_BLOCK_SLOT_FRACTION: u32 = 3;
+
+/// When a gossiped block arrived, relative to the start of its slot.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BlockArrival {
+    pub slot: u64,
+    pub proposer: usize,
+    pub delay: Duration,
+    /// The longest delay of any block of the same proposer, and its slot.
+    pub worst_delay: Duration,
+    pub worst_slot: u64,
+    /// Whether the block arrived after attesters voted.
+    pub late: bool,
+}
+
+/// Remembers the latest-arriving block of each proposer seen since the node started.
+#[derive(Debug, Default)]
+pub struct ArrivalTracker {
+    worst: HashMap<usize, (u64, Duration)>,
+}
+
+impl ArrivalTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the block of `proposer` for `slot` arrived `delay` after the slot started.
+    pub fn observe_block(
+        &mut self,
+        slot: u64,
+        proposer: usize,
+        delay: Duration,
+        slot_duration: Duration,
+    ) -> BlockArrival {
+        let worst = self.worst.entry(proposer).or_insert((slot, delay));
+        if delay > worst.1 {
+            *worst = (slot, delay);
+        }
+        BlockArrival {
+            slot,
+            proposer,
+            delay,
+            worst_delay: worst.1,
+            worst_slot: worst.0,
+            late: delay > slot_duration / LATE_BLOCK_SLOT_FRACTION,
+        }
+    }
+
+    /// Returns the longest delay of any block of `proposer`, and its slot.
+    pub fn worst_delay(&self, proposer: usize) -> Option<(u64, Duration)> {
+        self.worst.get(&proposer).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_block() {
+        let slot_duration = Duration::from_secs(6);
+        let mut tracker = ArrivalTracker::new();
+        assert_eq!(tracker.worst_delay(1), None);
+
+        let arrival = tracker.observe_block(4, 1, Duration::from_millis(2_500), slot_duration);
+        assert_eq!(arrival.worst_delay, Duration::from_millis(2_500));
+        assert_eq!(arrival.worst_slot, 4);
+        assert!(arrival.late);
+
+        /*
+         * A quicker block leaves the worst case as it was.
+         */
+        let arrival = tracker.observe_block(9, 1, Duration::from_millis(300), slot_duration);
+        assert_eq!(arrival.delay, Duration::from_millis(300));
+        assert_eq!(arrival.worst_delay, Duration::from_millis(2_500));
+        assert_eq!(arrival.worst_slot, 4);
+        assert!(!arrival.late);
+
+        tracker.observe_block(12, 1, Duration::from_secs(3), slot_duration);
+        tracker.observe_block(13, 2, Duration::from_secs(1), slot_duration);
+        assert_eq!(tracker.worst_delay(1), Some((12, Duration::from_secs(3))));
+        assert_eq!(tracker.worst_delay(2), Some((13, Duration::from_secs(1))));
+    }
+}
@@ -0,0 +1,207 @@
+use super::events::{EventBus, EventHandler};
+use super::fork_choice::{ForkChoice, ProtoArrayForkChoice};
+use super::node::{BeaconNode, BeaconNodeError};
+use db::stores::BeaconBlockStore;
+use db::{ClientDB, MemoryDB};
+use eth1::{Eth1Backend, NoEth1Backend};
+use slot_clock::SlotClock;
+use std::sync::Arc;
+use types::ChainConfig;
+
+/// Assembles a `BeaconNode` from its components, any of which may be replaced, e.g. by tests.
+///
+/// Only the store is required. Otherwise, the node reads the system clock, votes for no eth1
+/// block, chooses its head with `ProtoArrayForkChoice` and publishes its events on an `EventBus`.
+pub struct BeaconNodeBuilder<T: ClientDB> {
+    pub(super) config: ChainConfig,
+    pub(super) store: Option<Arc<BeaconBlockStore<T>>>,
+    /// If `None`, a system clock for the genesis time and slot duration of the config.
+    pub(super) slot_clock: Option<Arc<dyn SlotClock>>,
+    pub(super) eth1_backend: Arc<dyn Eth1Backend>,
+    pub(super) fork_choice: Box<dyn ForkChoice<T>>,
+    pub(super) event_handler: Arc<dyn EventHandler>,
+}
+
+impl<T: ClientDB> BeaconNodeBuilder<T> {
+    /// Starts a node whose genesis block has the `initial_validators` of `config`.
+    pub fn new(config: ChainConfig) -> Self {
+        Self {
+            config,
+            store: None,
+            slot_clock: None,
+            eth1_backend: Arc::new(NoEth1Backend),
+            fork_choice: Box::new(ProtoArrayForkChoice::default()),
+            event_handler: Arc::new(EventBus::default()),
+        }
+    }
+
+    pub fn store(mut self, store: Arc<BeaconBlockStore<T>>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn slot_clock(mut self, clock: Arc<dyn SlotClock>) -> Self {
+        self.slot_clock = Some(clock);
+        self
+    }
+
+    pub fn eth1_backend(mut self, backend: Arc<dyn Eth1Backend>) -> Self {
+        self.eth1_backend = backend;
+        self
+    }
+
+    pub fn fork_choice(mut self, fork_choice: Box<dyn ForkChoice<T>>) -> Self {
+        self.fork_choice = fork_choice;
+        self
+    }
+
+    pub fn event_handler(mut self, handler: Arc<dyn EventHandler>) -> Self {
+        self.event_handler = handler;
+        self
+    }
+
+    pub fn build(self) -> Result<BeaconNode<T>, BeaconNodeError> {
+        BeaconNode::from_builder(self)
+    }
+}
+
+impl BeaconNodeBuilder<MemoryDB> {
+    /// Stores the blocks in memory, e.g. for tests.
+    pub fn memory_store(self) -> Self {
+        self.store(Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::events::{BeaconNodeEvent, NullEventHandler};
+    use super::super::node::tests::test_config;
+    use super::super::BlockProcessingOutcome;
+    use super::*;
+    use eth1::Eth1Block;
+    use slot_clock::TestingSlotClock;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::Mutex;
+    use types::{BeaconBlock, Hash256};
+
+    struct FixedEth1Backend(Eth1Block);
+
+    impl Eth1Backend for FixedEth1Backend {
+        fn voting_block(&self) -> Option<Eth1Block> {
+            Some(self.0.clone())
+        }
+    }
+
+    /// Keeps the first of the tips as the head.
+    struct FirstForkChoice;
+
+    impl ForkChoice<MemoryDB> for FirstForkChoice {
+        fn find_head(
+            &self,
+            _heads: &[Hash256],
+            _store: &Arc<BeaconBlockStore<MemoryDB>>,
+        ) -> Result<usize, BeaconNodeError> {
+            Ok(0)
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingEventHandler {
+        events: Mutex<Vec<BeaconNodeEvent>>,
+    }
+
+    impl EventHandler for RecordingEventHandler {
+        fn publish(&self, event: BeaconNodeEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn subscribe(&self) -> Receiver<BeaconNodeEvent> {
+            channel().1
+        }
+    }
+
+    #[test]
+    fn test_build_requires_store() {
+        assert_eq!(
+            BeaconNodeBuilder::<MemoryDB>::new(test_config(4))
+                .build()
+                .err(),
+            Some(BeaconNodeError::MissingStore)
+        );
+    }
+
+    #[test]
+    fn test_build_with_components() {
+        let mut config = test_config(4);
+        config.slot_duration_millis = 0;
+        let clock = Arc::new(TestingSlotClock::new(0, 1_000).unwrap());
+        clock.set_slot(5);
+        let eth1_block = Eth1Block {
+            number: 10,
+            hash: Hash256::from(10),
+            parent_hash: Hash256::from(9),
+            timestamp: 100,
+        };
+        let events = Arc::new(RecordingEventHandler::default());
+
+        /*
+         * A clock given in place of the system clock is used even if the config has no valid
+         * slot duration.
+         */
+        let mut node = BeaconNodeBuilder::new(config)
+            .memory_store()
+            .slot_clock(clock.clone())
+            .eth1_backend(Arc::new(FixedEth1Backend(eth1_block)))
+            .fork_choice(Box::new(FirstForkChoice))
+            .event_handler(events.clone())
+            .build()
+            .unwrap();
+        assert_eq!(node.present_slot(), 5);
+
+        let block = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        assert_eq!(block.pow_chain_reference, Hash256::from(10));
+        assert_eq!(
+            node.process_block(&block, 5),
+            Ok(BlockProcessingOutcome::Imported)
+        );
+        assert_eq!(
+            *events.events.lock().unwrap(),
+            vec![
+                BeaconNodeEvent::Block {
+                    slot: 1,
+                    root: block.canonical_root(),
+                    proposer: node.block_proposer(1),
+                    attestations: vec![],
+                },
+                BeaconNodeEvent::Head {
+                    slot: 1,
+                    root: block.canonical_root(),
+                    state_root: Hash256::zero(),
+                    cycle_transition: false,
+                },
+            ]
+        );
+
+        /*
+         * The fork choice keeps the first tip, so a block on another chain does not become the
+         * head, however high its slot.
+         */
+        let mut fork = BeaconBlock::zero();
+        fork.slot = 3;
+        fork.ancestor_hashes.push(node.genesis_root());
+        node.process_block(&fork, 5).unwrap();
+        assert_eq!(node.head().1, block.canonical_root());
+    }
+
+    #[test]
+    fn test_null_event_handler() {
+        let node = BeaconNodeBuilder::new(test_config(4))
+            .memory_store()
+            .event_handler(Arc::new(NullEventHandler))
+            .build()
+            .unwrap();
+        assert!(node.events().subscribe().recv().is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use super::metrics;
+use super::node::BeaconNode;
+use super::regen::StateRegenerator;
+use db::{available_space, ClientDB};
+use lighthouse_metrics::set_gauge;
+use slog::Logger;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use task_executor::TaskExecutor;
+
+/// How often the free space is checked by default.
+pub const DEFAULT_DISK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// The free space below which snapshots are pruned by default, in megabytes.
+pub const DEFAULT_DISK_PRUNE_THRESHOLD_MB: u64 = 5_120;
+/// The free space below which block import is halted by default, in megabytes.
+pub const DEFAULT_DISK_HALT_THRESHOLD_MB: u64 = 1_024;
+/// How often the guard's thread checks whether it is stopping.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const MB: u64 = 1 << 20;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DiskGuardConfig {
+    pub check_interval: Duration,
+    /// Below this many megabytes free, snapshots are pruned aggressively.
+    pub prune_threshold_mb: u64,
+    /// Below this many megabytes free, block import is halted.
+    pub halt_threshold_mb: u64,
+}
+
+impl Default for DiskGuardConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: DEFAULT_DISK_CHECK_INTERVAL,
+            prune_threshold_mb: DEFAULT_DISK_PRUNE_THRESHOLD_MB,
+            halt_threshold_mb: DEFAULT_DISK_HALT_THRESHOLD_MB,
+        }
+    }
+}
+
+impl DiskGuardConfig {
+    pub fn classify(&self, available_bytes: u64) -> DiskSpace {
+        if available_bytes < self.halt_threshold_mb * MB {
+            DiskSpace::Critical
+        } else if available_bytes < self.prune_threshold_mb * MB {
+            DiskSpace::Low
+        } else {
+            DiskSpace::Sufficient
+        }
+    }
+}
+
+/// How the free space compares to the thresholds of a `DiskGuardConfig`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DiskSpace {
+    Sufficient,
+    /// Snapshots are pruned, but blocks are still imported.
+    Low,
+    /// Blocks are no longer imported, as a database which runs out of space mid-write may be
+    /// left corrupt.
+    Critical,
+}
+
+/// Watches the free space of the filesystem holding the database, pruning state snapshots when it
+/// runs low and halting block import before it runs out. Import resumes once space is freed.
+pub struct DiskGuard<T: ClientDB> {
+    config: DiskGuardConfig,
+    path: PathBuf,
+    node: Arc<RwLock<BeaconNode<T>>>,
+    regenerator: StateRegenerator<T>,
+    log: Logger,
+}
+
+impl<T: ClientDB> DiskGuard<T> {
+    /// Creates a guard of the filesystem holding `path`, pruning with `regenerator`.
+    pub fn new(
+        config: DiskGuardConfig,
+        path: PathBuf,
+        node: Arc<RwLock<BeaconNode<T>>>,
+        regenerator: StateRegenerator<T>,
+        log: Logger,
+    ) -> Self {
+        Self {
+            config,
+            path,
+            node,
+            regenerator,
+            log,
+        }
+    }
+
+    /// Checks the free space once, responding as it calls for.
+    pub fn check(&self) -> io::Result<DiskSpace> {
+        let available = available_space(&self.path)?;
+        Ok(self.respond(available))
+    }
+
+    /// Prunes if `available_bytes` is low, and halts or resumes block import. Space freed by
+    /// pruning is only seen by the next check.
+    fn respond(&self, available_bytes: u64) -> DiskSpace {
+        set_gauge(&metrics::DISK_AVAILABLE_BYTES, available_bytes as i64);
+        let space = self.config.classify(available_bytes);
+        let available_mb = available_bytes / MB;
+
+        if space != DiskSpace::Sufficient {
+            let finalized_root = self
+                .node
+                .read()
+                .expect("Beacon node lock poisoned")
+                .finalized_root();
+            match self.regenerator.prune_aggressively(finalized_root) {
+                Ok(pruned) => {
+                    warn!(self.log, "Disk space low, pruned state snapshots"; "available_mb" => available_mb, "pruned" => pruned)
+                }
+                Err(e) => {
+                    warn!(self.log, "Disk space low, unable to prune state snapshots"; "available_mb" => available_mb, "error" => format!("{:?}", e))
+                }
+            }
+        }
+
+        let mut node = self.node.write().expect("Beacon node lock poisoned");
+        if space == DiskSpace::Critical {
+            if node.block_import_halted().is_none() {
+                crit!(self.log, "Disk space critically low, halting block import"; "available_mb" => available_mb, "threshold_mb" => self.config.halt_threshold_mb, "help" => "free disk space, and import resumes");
+                node.halt_block_import(format!(
+                    "{} MB of disk space available, below the threshold of {} MB",
+                    available_mb, self.config.halt_threshold_mb
+                ));
+            }
+        } else if node.resume_block_import() {
+            info!(self.log, "Disk space recovered, resuming block import"; "available_mb" => available_mb);
+        }
+        set_gauge(
+            &metrics::BLOCK_IMPORT_HALTED,
+            node.block_import_halted().is_some() as i64,
+        );
+        space
+    }
+}
+
+/// Runs a `DiskGuard` on a task of the node's executor until dropped or the node shuts down.
+pub struct DiskGuardService {
+    worker: Option<JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl DiskGuardService {
+    pub fn start<T: ClientDB + 'static>(guard: DiskGuard<T>, executor: &TaskExecutor) -> Self {
+        let stopping = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let stopping = stopping.clone();
+            executor.spawn("disk_guard", move |exit| {
+                let mut next_check = Instant::now();
+                while !stopping.load(Ordering::Relaxed) && !exit.is_exiting() {
+                    if Instant::now() < next_check {
+                        thread::sleep(STOP_POLL_INTERVAL);
+                        continue;
+                    }
+                    next_check = Instant::now() + guard.config.check_interval;
+                    if let Err(e) = guard.check() {
+                        warn!(guard.log, "Unable to check disk space"; "path" => format!("{}", guard.path.display()), "error" => format!("{}", e));
+                    }
+                }
+            })
+        };
+        Self {
+            worker: Some(worker),
+            stopping,
+        }
+    }
+}
+
+impl Drop for DiskGuardService {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::node::tests::test_node;
+    use super::super::node::BeaconNodeError;
+    use super::*;
+    use db::stores::{StatePruning, StateStore};
+    use db::MemoryDB;
+    use slog::Discard;
+    use std::env;
+    use types::BeaconBlock;
+
+    #[test]
+    fn test_classify() {
+        let config = DiskGuardConfig::default();
+        assert_eq!(config.classify(u64::MAX), DiskSpace::Sufficient);
+        assert_eq!(
+            config.classify(DEFAULT_DISK_PRUNE_THRESHOLD_MB * MB - 1),
+            DiskSpace::Low
+        );
+        assert_eq!(
+            config.classify(DEFAULT_DISK_HALT_THRESHOLD_MB * MB - 1),
+            DiskSpace::Critical
+        );
+    }
+
+    #[test]
+    fn test_halt_and_resume_import() {
+        let node = test_node(4);
+        let regenerator = StateRegenerator::new(
+            node.store().clone(),
+            StateStore::new(Arc::new(MemoryDB::open())),
+            StatePruning::Archive,
+            node.genesis_root(),
+            node.config().cycle_length,
+            0,
+        );
+        let node = Arc::new(RwLock::new(node));
+        let guard = DiskGuard::new(
+            DiskGuardConfig::default(),
+            env::temp_dir(),
+            node.clone(),
+            regenerator,
+            Logger::root(Discard, o!()),
+        );
+        guard.check().unwrap();
+
+        assert_eq!(
+            guard.respond(DEFAULT_DISK_HALT_THRESHOLD_MB * MB - 1),
+            DiskSpace::Critical
+        );
+        let mut block = BeaconBlock::zero();
+        block.slot = 1;
+        block
+            .ancestor_hashes
+            .push(node.read().unwrap().genesis_root());
+        match node.write().unwrap().process_block(&block, 1) {
+            Err(BeaconNodeError::BlockImportHalted(reason)) => assert!(reason.contains("1023 MB")),
+            other => panic!("Unexpected outcome: {:?}", other),
+        }
+
+        /*
+         * Import resumes as soon as the space is no longer critical.
+         */
+        assert_eq!(
+            guard.respond(DEFAULT_DISK_HALT_THRESHOLD_MB * MB),
+            DiskSpace::Low
+        );
+        assert_eq!(node.read().unwrap().block_import_halted(), None);
+        assert!(node.write().unwrap().process_block(&block, 1).is_ok());
+    }
+}
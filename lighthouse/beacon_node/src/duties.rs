@@ -0,0 +1,98 @@
+use super::node::{proposer, BeaconNode, BeaconNodeError};
+use db::ClientDB;
+
+/// The blocks and attestation a validator must produce during a cycle.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidatorDuties {
+    pub validator_index: usize,
+    /// The slots at which the validator proposes a block.
+    pub block_production_slots: Vec<u64>,
+    /// The slot at which the validator attests, if it is in a committee.
+    pub attestation_slot: Option<u64>,
+    pub attestation_shard: Option<u64>,
+    /// The position of the validator in its committee, used to set its participation bit.
+    pub committee_index: Option<usize>,
+}
+
+impl<T: ClientDB> BeaconNode<T> {
+    /// Returns the duties of the validator at `validator_index` during `cycle`.
+    ///
+    /// The committees of the cycle are taken from the shuffling cache, where those of the next
+    /// cycle are usually pre-computed.
+    pub fn validator_duties(
+        &self,
+        validator_index: usize,
+        cycle: u64,
+    ) -> Result<ValidatorDuties, BeaconNodeError> {
+        if validator_index >= self.validators().len() {
+            return Err(BeaconNodeError::UnknownValidator);
+        }
+        let cycle_length = u64::from(self.config().cycle_length);
+        let start_slot = cycle
+            .checked_mul(cycle_length)
+            .filter(|start_slot| start_slot.checked_add(cycle_length).is_some())
+            .ok_or(BeaconNodeError::CycleOutOfRange)?;
+        let shuffling = self.shuffling(cycle)?;
+        let mut duties = ValidatorDuties {
+            validator_index,
+            block_production_slots: vec![],
+            attestation_slot: None,
+            attestation_shard: None,
+            committee_index: None,
+        };
+
+        for (slot, committees) in (start_slot..).zip(shuffling.iter()) {
+            if proposer(committees, slot) == Some(validator_index) {
+                duties.block_production_slots.push(slot);
+            }
+            for shard_and_committee in committees {
+                let position = shard_and_committee
+                    .committee
+                    .iter()
+                    .position(|i| *i == validator_index);
+                if let Some(position) = position {
+                    duties.attestation_slot = Some(slot);
+                    duties.attestation_shard = Some(u64::from(shard_and_committee.shard));
+                    duties.committee_index = Some(position);
+                }
+            }
+        }
+        Ok(duties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::node::tests::test_node;
+    use super::*;
+
+    #[test]
+    fn test_every_validator_attests_once_per_cycle() {
+        let node = test_node(8);
+        let mut proposals = 0;
+        for validator_index in 0..8 {
+            let duties = node.validator_duties(validator_index, 1).unwrap();
+            let slot = duties.attestation_slot.unwrap();
+            assert!((2..4).contains(&slot));
+            let committee = node
+                .committee(slot, duties.attestation_shard.unwrap())
+                .unwrap();
+            assert_eq!(committee[duties.committee_index.unwrap()], validator_index);
+
+            for slot in &duties.block_production_slots {
+                assert_eq!(node.block_proposer(*slot), Some(validator_index));
+            }
+            proposals += duties.block_production_slots.len();
+        }
+        assert_eq!(proposals, 2);
+        assert!(node.shuffling_cache().contains(1));
+        assert_eq!(
+            node.validator_duties(8, 0),
+            Err(BeaconNodeError::UnknownValidator)
+        );
+        assert_eq!(
+            node.validator_duties(0, u64::MAX),
+            Err(BeaconNodeError::CycleOutOfRange)
+        );
+    }
+}
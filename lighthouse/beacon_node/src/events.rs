@@ -0,0 +1,157 @@
+use super::registry::RegistryDelta;
+use super::slashing::ProposerSlashing;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use types::{Attestation, Hash256};
+
+/// A change to the chain, delivered to the metrics, the validator monitor and the event handler
+/// of the beacon node.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BeaconNodeEvent {
+    /// A block was imported, whether or not it became the head.
+    Block {
+        slot: u64,
+        root: Hash256,
+        proposer: Option<usize>,
+        /// The slot and attesting validators of each attestation included in the block.
+        attestations: Vec<(u64, Vec<usize>)>,
+    },
+    /// An attestation was added to the pool, to be included in a block.
+    Attestation {
+        attestation: Attestation,
+        participants: Vec<usize>,
+    },
+    /// The head changed. Follows any `ChainReorg` caused by the same block.
+    Head {
+        slot: u64,
+        root: Hash256,
+        state_root: Hash256,
+        /// The head is the first block of a new cycle.
+        cycle_transition: bool,
+    },
+    /// The new head does not descend from the old head.
+    ChainReorg {
+        slot: u64,
+        /// The number of slots from the common ancestor to the old head.
+        depth: u64,
+        old_head_root: Hash256,
+        new_head_root: Hash256,
+        old_head_state_root: Hash256,
+        new_head_state_root: Hash256,
+    },
+    /// The head entered a new cycle, so the changes to the registry over the cycle before are
+    /// known. Precedes the `Head` of the new cycle.
+    RegistryDelta(RegistryDelta),
+    /// Two blocks for the same slot and proposer were seen. Blocks are unsigned, so the
+    /// equivocation is unverified.
+    ProposerSlashing(ProposerSlashing),
+    /// A new block was finalized.
+    FinalizedCheckpoint {
+        root: Hash256,
+        state_root: Hash256,
+        cycle: u64,
+    },
+}
+
+/// Receives the changes to the chain published by the beacon node.
+pub trait EventHandler: Send + Sync {
+    fn publish(&self, event: BeaconNodeEvent);
+
+    /// Returns a receiver of every event handled from now on, which may be disconnected if the
+    /// handler does not deliver events.
+    fn subscribe(&self) -> Receiver<BeaconNodeEvent>;
+}
+
+/// Delivers `BeaconNodeEvent`s to any number of subscribers.
+///
+/// Subscribers are removed once their receiver is dropped.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<BeaconNodeEvent>>>,
+}
+
+impl EventBus {
+    /// Returns a receiver of every event published from now on.
+    pub fn subscribe(&self) -> Receiver<BeaconNodeEvent> {
+        let (tx, rx) = channel();
+        self.subscribers
+            .lock()
+            .expect("Event bus lock poisoned")
+            .push(tx);
+        rx
+    }
+
+    pub fn publish(&self, event: BeaconNodeEvent) {
+        self.subscribers
+            .lock()
+            .expect("Event bus lock poisoned")
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers
+            .lock()
+            .expect("Event bus lock poisoned")
+            .len()
+    }
+}
+
+impl EventHandler for EventBus {
+    fn publish(&self, event: BeaconNodeEvent) {
+        EventBus::publish(self, event)
+    }
+
+    fn subscribe(&self) -> Receiver<BeaconNodeEvent> {
+        EventBus::subscribe(self)
+    }
+}
+
+/// Discards every event, e.g. for tests and benchmarks which do not subscribe.
+pub struct NullEventHandler;
+
+impl EventHandler for NullEventHandler {
+    fn publish(&self, _event: BeaconNodeEvent) {}
+
+    fn subscribe(&self) -> Receiver<BeaconNodeEvent> {
+        channel().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dropped_subscribers_are_removed() {
+        let bus = EventBus::default();
+        let a = bus.subscribe();
+        let b = bus.subscribe();
+        let event = BeaconNodeEvent::Block {
+            slot: 1,
+            root: Hash256::from(1),
+            proposer: None,
+            attestations: vec![],
+        };
+        bus.publish(event.clone());
+        assert_eq!(a.try_recv(), Ok(event.clone()));
+        assert_eq!(b.try_recv(), Ok(event.clone()));
+
+        drop(b);
+        bus.publish(event.clone());
+        assert_eq!(bus.subscriber_count(), 1);
+        assert_eq!(a.try_recv(), Ok(event));
+    }
+
+    #[test]
+    fn test_null_event_handler() {
+        let handler = NullEventHandler;
+        let rx = handler.subscribe();
+        handler.publish(BeaconNodeEvent::Block {
+            slot: 1,
+            root: Hash256::from(1),
+            proposer: None,
+            attestations: vec![],
+        });
+        assert!(rx.recv().is_err());
+    }
+}
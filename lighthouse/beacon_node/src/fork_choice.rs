@@ -0,0 +1,299 @@
+use super::node::BeaconNodeError;
+use super::persisted::{LatestMessage, PersistedForkChoice, ProtoNode};
+use db::stores::BeaconBlockStore;
+use db::ClientDB;
+use naive_fork_choice::naive_fork_choice;
+use ssz::{ssz_encode, Decodable};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use types::{AttestationData, BeaconBlock, Hash256};
+
+/// Chooses the head of the canonical chain from the tips of all known chains.
+pub trait ForkChoice<T: ClientDB>: Send + Sync {
+    /// Returns the index of the canonical head in `heads`, which is never empty.
+    fn find_head(
+        &self,
+        heads: &[Hash256],
+        store: &Arc<BeaconBlockStore<T>>,
+    ) -> Result<usize, BeaconNodeError>;
+
+    /// Whether the fork choice keeps its own view of the blocks and votes, which is persisted
+    /// across restarts, or else replayed from the blocks.
+    fn is_stateful(&self) -> bool {
+        false
+    }
+
+    /// Records the block with `root`, whose parent was recorded before it.
+    fn process_block(&self, _root: Hash256, _block: &BeaconBlock) {}
+
+    /// Records the votes of the `validators` who attested to `data`.
+    fn process_attestation(&self, _validators: &[usize], _data: &AttestationData) {}
+
+    /// Returns the view of the fork choice to be persisted, if it is stateful.
+    fn as_ssz(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Replaces the view of the fork choice with one returned by `as_ssz`.
+    fn restore(&self, _ssz: &[u8]) -> Result<(), BeaconNodeError> {
+        Ok(())
+    }
+}
+
+/// Chooses the tip of the highest slot, breaking ties by the highest root.
+pub struct NaiveForkChoice;
+
+impl<T: ClientDB> ForkChoice<T> for NaiveForkChoice {
+    fn find_head(
+        &self,
+        heads: &[Hash256],
+        store: &Arc<BeaconBlockStore<T>>,
+    ) -> Result<usize, BeaconNodeError> {
+        naive_fork_choice(&heads.to_vec(), store.clone())
+            .map_err(|_| BeaconNodeError::ForkChoiceFailed)?
+            .ok_or(BeaconNodeError::ForkChoiceFailed)
+    }
+}
+
+/// LMD GHOST: from the genesis block, repeatedly follows the child with the most validators whose
+/// latest attestation is to a block descending from it, to a tip.
+///
+/// Ties, including between children without votes, are broken as by `NaiveForkChoice`, so the
+/// two agree until votes are seen. The blocks and votes are kept in memory, so that the head is
+/// found without reading blocks from the store. Every validator's vote weighs the same until
+/// balances are tracked.
+#[derive(Default)]
+pub struct ProtoArrayForkChoice {
+    array: RwLock<ProtoArray>,
+}
+
+#[derive(Default)]
+struct ProtoArray {
+    /// Parents before their children.
+    nodes: Vec<ProtoNode>,
+    /// The index of each node's parent, if known.
+    parents: Vec<Option<usize>>,
+    indices: HashMap<Hash256, usize>,
+    latest_messages: BTreeMap<usize, LatestMessage>,
+}
+
+impl ProtoArray {
+    fn insert(&mut self, node: ProtoNode) {
+        if self.indices.contains_key(&node.root) {
+            return;
+        }
+        self.indices.insert(node.root, self.nodes.len());
+        self.parents
+            .push(self.indices.get(&node.parent_root).cloned());
+        self.nodes.push(node);
+    }
+
+    /// Returns the index of the tip chosen by LMD GHOST.
+    fn find_head(&self) -> Option<usize> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut weights = vec![0u64; self.nodes.len()];
+        for message in self.latest_messages.values() {
+            if let Some(&i) = self.indices.get(&message.root) {
+                weights[i] += 1;
+            }
+        }
+
+        /*
+         * Children come after their parents, so a backward pass completes each node's weight and
+         * best child before it is compared with its siblings.
+         */
+        let mut best_child: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut best_tip: Vec<usize> = (0..self.nodes.len()).collect();
+        for i in (0..self.nodes.len()).rev() {
+            if let Some(child) = best_child[i] {
+                best_tip[i] = best_tip[child];
+            }
+            let parent = match self.parents[i] {
+                Some(parent) => parent,
+                None => continue,
+            };
+            weights[parent] += weights[i];
+            let better = match best_child[parent] {
+                None => true,
+                Some(other) => self.beats(i, other, &weights, &best_tip),
+            };
+            if better {
+                best_child[parent] = Some(i);
+            }
+        }
+
+        let mut head = 0;
+        while let Some(child) = best_child[head] {
+            head = child;
+        }
+        Some(head)
+    }
+
+    /// Whether the subtree of `a` is preferred to that of its sibling `b`: by weight, then by the
+    /// highest slot of a tip, then by the lowest root of that tip.
+    fn beats(&self, a: usize, b: usize, weights: &[u64], best_tip: &[usize]) -> bool {
+        let (tip_a, tip_b) = (&self.nodes[best_tip[a]], &self.nodes[best_tip[b]]);
+        (weights[a], tip_a.slot, tip_b.root) > (weights[b], tip_b.slot, tip_a.root)
+    }
+}
+
+impl<T: ClientDB> ForkChoice<T> for ProtoArrayForkChoice {
+    fn find_head(
+        &self,
+        heads: &[Hash256],
+        _store: &Arc<BeaconBlockStore<T>>,
+    ) -> Result<usize, BeaconNodeError> {
+        let array = self.array.read().expect("Fork choice lock poisoned");
+        let head = array.find_head().ok_or(BeaconNodeError::ForkChoiceFailed)?;
+        heads
+            .iter()
+            .position(|root| *root == array.nodes[head].root)
+            .ok_or(BeaconNodeError::ForkChoiceFailed)
+    }
+
+    fn is_stateful(&self) -> bool {
+        true
+    }
+
+    fn process_block(&self, root: Hash256, block: &BeaconBlock) {
+        self.array
+            .write()
+            .expect("Fork choice lock poisoned")
+            .insert(ProtoNode {
+                root,
+                parent_root: block.parent_hash().cloned().unwrap_or_else(Hash256::zero),
+                slot: block.slot,
+            });
+    }
+
+    fn process_attestation(&self, validators: &[usize], data: &AttestationData) {
+        let mut array = self.array.write().expect("Fork choice lock poisoned");
+        for validator in validators {
+            let message = LatestMessage {
+                validator: *validator as u64,
+                slot: data.slot,
+                root: data.beacon_block_hash,
+            };
+            let newer = match array.latest_messages.get(validator) {
+                Some(latest) => latest.slot < message.slot,
+                None => true,
+            };
+            if newer {
+                array.latest_messages.insert(*validator, message);
+            }
+        }
+    }
+
+    fn as_ssz(&self) -> Option<Vec<u8>> {
+        let array = self.array.read().expect("Fork choice lock poisoned");
+        Some(ssz_encode(&PersistedForkChoice {
+            nodes: array.nodes.clone(),
+            latest_messages: array.latest_messages.values().cloned().collect(),
+        }))
+    }
+
+    fn restore(&self, ssz: &[u8]) -> Result<(), BeaconNodeError> {
+        let (persisted, _) = PersistedForkChoice::ssz_decode(ssz, 0)
+            .map_err(|_| BeaconNodeError::DBError("Invalid persisted fork choice".to_string()))?;
+        let mut array = ProtoArray::default();
+        for node in persisted.nodes {
+            array.insert(node);
+        }
+        for message in persisted.latest_messages {
+            array
+                .latest_messages
+                .insert(message.validator as usize, message);
+        }
+        *self.array.write().expect("Fork choice lock poisoned") = array;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::MemoryDB;
+
+    #[test]
+    fn test_naive_fork_choice() {
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        let roots: Vec<Hash256> = [3, 5, 4]
+            .iter()
+            .map(|slot| {
+                let mut block = BeaconBlock::zero();
+                block.slot = *slot;
+                let root = block.canonical_root();
+                store
+                    .put_serialized_block(&root, &ssz_encode(&block))
+                    .unwrap();
+                root
+            })
+            .collect();
+
+        assert_eq!(NaiveForkChoice.find_head(&roots, &store), Ok(1));
+        assert_eq!(
+            NaiveForkChoice.find_head(&[Hash256::from(9)], &store),
+            Err(BeaconNodeError::ForkChoiceFailed)
+        );
+    }
+
+    #[test]
+    fn test_proto_array_fork_choice() {
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        let fork_choice = ProtoArrayForkChoice::default();
+        let block = |parent: Option<Hash256>, slot| {
+            let mut block = BeaconBlock::zero();
+            block.slot = slot;
+            block.ancestor_hashes.extend(parent);
+            let root = block.canonical_root();
+            ForkChoice::<MemoryDB>::process_block(&fork_choice, root, &block);
+            root
+        };
+        let vote = |validator: usize, slot, root| {
+            let data = AttestationData {
+                slot,
+                beacon_block_hash: root,
+                ..AttestationData::zero()
+            };
+            ForkChoice::<MemoryDB>::process_attestation(&fork_choice, &[validator], &data);
+        };
+
+        let genesis = block(None, 0);
+        let a = block(Some(genesis), 1);
+        let b = block(Some(genesis), 2);
+        let b_child = block(Some(b), 3);
+        let heads = [a, b_child];
+
+        /*
+         * Without votes, the tip of the highest slot is the head.
+         */
+        assert_eq!(fork_choice.find_head(&heads, &store), Ok(1));
+        vote(0, 1, a);
+        assert_eq!(fork_choice.find_head(&heads, &store), Ok(0));
+        vote(1, 3, b_child);
+        vote(2, 3, b);
+        assert_eq!(fork_choice.find_head(&heads, &store), Ok(1));
+
+        /*
+         * Only a later vote replaces a validator's latest message.
+         */
+        vote(1, 2, a);
+        assert_eq!(fork_choice.find_head(&heads, &store), Ok(1));
+        vote(1, 4, a);
+        vote(2, 4, a);
+        assert_eq!(fork_choice.find_head(&heads, &store), Ok(0));
+        assert_eq!(
+            fork_choice.find_head(&[b_child], &store),
+            Err(BeaconNodeError::ForkChoiceFailed)
+        );
+
+        let ssz = ForkChoice::<MemoryDB>::as_ssz(&fork_choice).unwrap();
+        let restored = ProtoArrayForkChoice::default();
+        ForkChoice::<MemoryDB>::restore(&restored, &ssz).unwrap();
+        assert_eq!(restored.find_head(&heads, &store), Ok(0));
+        assert_eq!(ForkChoice::<MemoryDB>::as_ssz(&restored), Some(ssz));
+        assert!(ForkChoice::<MemoryDB>::restore(&restored, &[1]).is_err());
+    }
+}
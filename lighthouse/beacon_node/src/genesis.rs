@@ -0,0 +1,113 @@
+use slog::Logger;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The interval at which the time to genesis is logged while waiting for it.
+pub const GENESIS_COUNTDOWN_INTERVAL: Duration = Duration::from_secs(60);
+/// How long before genesis networking starts, so that peers are found by genesis.
+pub const NETWORK_START_OFFSET: Duration = Duration::from_secs(120);
+
+/// Returns the time from now until `offset` before `genesis_time`, in seconds since the unix
+/// epoch, or `None` if that has passed.
+pub fn duration_to_genesis(genesis_time: u64, offset: Duration) -> Option<Duration> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Duration::from_secs(genesis_time)
+        .checked_sub(offset)?
+        .checked_sub(now)
+}
+
+/// Waits until `offset` before `genesis_time`, logging the time to genesis every
+/// `GENESIS_COUNTDOWN_INTERVAL`. Returns at once if that has passed.
+///
+/// Returns `false` if `shutdown` was sent to, or disconnected, meanwhile.
+pub fn wait_for_genesis<S>(
+    genesis_time: u64,
+    offset: Duration,
+    shutdown: &Receiver<S>,
+    log: &Logger,
+) -> bool {
+    while let Some(remaining) = duration_to_genesis(genesis_time, offset) {
+        info!(log, "Waiting for genesis";
+              "seconds" => (remaining + offset).as_secs(),
+              "genesis_time" => genesis_time);
+        match shutdown.recv_timeout(remaining.min(GENESIS_COUNTDOWN_INTERVAL)) {
+            Err(RecvTimeoutError::Timeout) => {}
+            Ok(_) | Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::Discard;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_duration_to_genesis() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let genesis_time = now.as_secs() + 100;
+        let remaining = duration_to_genesis(genesis_time, Duration::from_secs(0)).unwrap();
+        assert!(remaining > Duration::from_secs(98) && remaining <= Duration::from_secs(100));
+        let remaining = duration_to_genesis(genesis_time, Duration::from_secs(50)).unwrap();
+        assert!(remaining <= Duration::from_secs(50));
+        assert_eq!(
+            duration_to_genesis(genesis_time, Duration::from_secs(101)),
+            None
+        );
+        assert_eq!(
+            duration_to_genesis(now.as_secs() - 1, Duration::from_secs(0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_wait_for_genesis() {
+        let log = Logger::root(Discard, o!());
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let genesis_time = now.as_secs() + 1;
+        let (shutdown_tx, shutdown) = mpsc::channel();
+        assert!(wait_for_genesis(
+            genesis_time,
+            Duration::from_secs(0),
+            &shutdown,
+            &log
+        ));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        assert!(now >= Duration::from_secs(genesis_time));
+        /*
+         * Once genesis has passed, there is no wait, even after shutdown.
+         */
+        shutdown_tx.send(()).unwrap();
+        assert!(wait_for_genesis(
+            genesis_time,
+            Duration::from_secs(0),
+            &shutdown,
+            &log
+        ));
+    }
+
+    #[test]
+    fn test_wait_for_genesis_shutdown() {
+        let log = Logger::root(Discard, o!());
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let (shutdown_tx, shutdown) = mpsc::channel();
+        shutdown_tx.send(()).unwrap();
+        assert!(!wait_for_genesis(
+            now.as_secs() + 3_600,
+            Duration::from_secs(0),
+            &shutdown,
+            &log
+        ));
+        drop(shutdown_tx);
+        assert!(!wait_for_genesis(
+            now.as_secs() + 3_600,
+            Duration::from_secs(0),
+            &shutdown,
+            &log
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+use super::node::{BeaconNode, BeaconNodeError};
+use db::stores::ChainStore;
+use db::ClientDB;
+use ssz::{ssz_encode, Decodable};
+use types::Hash256;
+
+/// The number of slots imported between each time the chain is persisted during sync, by
+/// default.
+pub const DEFAULT_IMPORT_CHECKPOINT_SLOTS: u64 = 256;
+
+/// Limits the writes of the chain while it is imported in batches during initial sync.
+///
+/// Only the root of the latest block imported is written after each batch. The heads, fork
+/// choice and the pooled operations are persisted once the head has advanced `interval_slots`
+/// past the last checkpoint. After a crash, `recover_imports` re-imports the blocks stored since
+/// the last checkpoint, rather than downloading them again.
+pub struct ImportCheckpointer<T: ClientDB> {
+    store: ChainStore<T>,
+    interval_slots: u64,
+    /// The slot of the head when the chain was last persisted.
+    checkpoint_slot: u64,
+}
+
+impl<T: ClientDB> ImportCheckpointer<T> {
+    /// Creates a checkpointer which persists to `store` every `interval_slots`, counting from the
+    /// present head of `node`.
+    pub fn new(store: ChainStore<T>, interval_slots: u64, node: &BeaconNode<T>) -> Self {
+        Self {
+            store,
+            interval_slots: interval_slots.max(1),
+            checkpoint_slot: node.head().0,
+        }
+    }
+
+    /// Records that a batch ending with the block with `last_root` was imported, persisting the
+    /// chain if a checkpoint is due. Returns whether it was persisted.
+    pub fn on_batch_imported(
+        &mut self,
+        node: &BeaconNode<T>,
+        last_root: Hash256,
+    ) -> Result<bool, BeaconNodeError> {
+        self.store
+            .put_serialized_import_tip(&ssz_encode(&last_root))?;
+        if node.head().0 < self.checkpoint_slot + self.interval_slots {
+            return Ok(false);
+        }
+        self.checkpoint(node)?;
+        Ok(true)
+    }
+
+    /// Persists the chain now, e.g. once sync has completed.
+    pub fn checkpoint(&mut self, node: &BeaconNode<T>) -> Result<(), BeaconNodeError> {
+        node.persist(&self.store)?;
+        self.checkpoint_slot = node.head().0;
+        Ok(())
+    }
+
+    pub fn checkpoint_slot(&self) -> u64 {
+        self.checkpoint_slot
+    }
+}
+
+/// Re-imports the blocks which sync stored after the chain restored from `store` was persisted,
+/// returning the number re-imported. To be called after `BeaconNode::restore`.
+pub fn recover_imports<T: ClientDB>(
+    node: &mut BeaconNode<T>,
+    store: &ChainStore<T>,
+) -> Result<usize, BeaconNodeError> {
+    let tip = match store.get_serialized_import_tip()? {
+        Some(ssz) => Hash256::ssz_decode(&ssz, 0)
+            .map(|(tip, _)| tip)
+            .map_err(|_| BeaconNodeError::DBError("Invalid import tip".to_string()))?,
+        None => return Ok(0),
+    };
+    node.replay_to(tip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::node::tests::test_config;
+    use super::super::node::BlockProcessingOutcome;
+    use super::*;
+    use db::stores::BeaconBlockStore;
+    use db::MemoryDB;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_checkpoint_and_recover() {
+        let db = Arc::new(MemoryDB::open());
+        let config = test_config(8);
+        let new_node = || {
+            let store = Arc::new(BeaconBlockStore::new(db.clone()));
+            BeaconNode::new(config.clone(), store).unwrap()
+        };
+        let mut node = new_node();
+        let mut checkpointer = ImportCheckpointer::new(ChainStore::new(db.clone()), 4, &node);
+
+        /*
+         * Batches of two blocks are imported, so the chain is persisted after every other batch.
+         */
+        let mut persisted = vec![];
+        for batch in 0..3 {
+            let mut last_root = Hash256::zero();
+            for slot in batch * 2 + 1..=batch * 2 + 2 {
+                let block = node
+                    .produce_block(slot, Hash256::zero(), Hash256::zero())
+                    .unwrap();
+                node.process_block(&block, slot).unwrap();
+                last_root = block.canonical_root();
+            }
+            persisted.push(checkpointer.on_batch_imported(&node, last_root).unwrap());
+        }
+        assert_eq!(persisted, vec![false, true, false]);
+        assert_eq!(checkpointer.checkpoint_slot(), 4);
+        let head = node.head();
+        assert_eq!(head.0, 6);
+
+        /*
+         * After a crash, the chain is restored at the checkpoint and the blocks since replayed.
+         */
+        let chain_store = ChainStore::new(db.clone());
+        let mut restarted = new_node();
+        assert_eq!(restarted.restore(&chain_store), Ok(true));
+        assert_eq!(restarted.head().0, 4);
+        assert_eq!(recover_imports(&mut restarted, &chain_store), Ok(2));
+        assert_eq!(restarted.head(), head);
+        assert_eq!(restarted.heads(), node.heads());
+        assert_eq!(recover_imports(&mut restarted, &chain_store), Ok(0));
+
+        /*
+         * Once persisted, there is nothing to recover.
+         */
+        checkpointer.checkpoint(&node).unwrap();
+        let mut restarted = new_node();
+        assert_eq!(restarted.restore(&chain_store), Ok(true));
+        assert_eq!(recover_imports(&mut restarted, &chain_store), Ok(0));
+        assert_eq!(restarted.head(), head);
+    }
+
+    #[test]
+    fn test_process_chain_segment() {
+        let db = Arc::new(MemoryDB::open());
+        let config = test_config(8);
+        let new_node = || {
+            let store = Arc::new(BeaconBlockStore::new(db.clone()));
+            BeaconNode::new(config.clone(), store).unwrap()
+        };
+
+        /*
+         * The blocks are produced by another node, as sync downloads them.
+         */
+        let mut producer = BeaconNode::new(
+            config.clone(),
+            Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open()))),
+        )
+        .unwrap();
+        let mut blocks = vec![];
+        for slot in 1..=6 {
+            let block = producer
+                .produce_block(slot, Hash256::zero(), Hash256::zero())
+                .unwrap();
+            producer.process_block(&block, slot).unwrap();
+            blocks.push(block);
+        }
+        let mut node = new_node();
+        let chain_store = ChainStore::new(db.clone());
+        node.checkpoint_imports(ChainStore::new(db.clone()), 4);
+
+        /*
+         * The chain is persisted once the head is four slots past genesis, not after each batch.
+         */
+        let imported = || {
+            vec![
+                BlockProcessingOutcome::Imported,
+                BlockProcessingOutcome::Imported,
+            ]
+        };
+        assert_eq!(node.process_chain_segment(&blocks[..2], 6), Ok(imported()));
+        assert_eq!(new_node().restore(&chain_store), Ok(false));
+        assert_eq!(node.process_chain_segment(&blocks[2..4], 6), Ok(imported()));
+        let mut restarted = new_node();
+        assert_eq!(restarted.restore(&chain_store), Ok(true));
+        assert_eq!(restarted.head().0, 4);
+
+        /*
+         * Processing stops at the first block which cannot be imported.
+         */
+        let outcomes = node.process_chain_segment(&[blocks[3].clone(), blocks[5].clone()], 6);
+        assert_eq!(
+            outcomes,
+            Ok(vec![
+                BlockProcessingOutcome::AlreadyKnown,
+                BlockProcessingOutcome::UnknownParent
+            ])
+        );
+        assert_eq!(node.head().0, 4);
+    }
+}
@@ -0,0 +1,95 @@
+extern crate bls;
+extern crate db;
+extern crate eth1;
+extern crate hashing;
+#[macro_use]
+extern crate lazy_static;
+extern crate lighthouse_metrics;
+extern crate merkle_proof;
+extern crate naive_fork_choice;
+#[macro_use]
+extern crate slog;
+extern crate slot_clock;
+extern crate ssz;
+extern crate state_transition;
+extern crate task_executor;
+extern crate types;
+extern crate validator_induction;
+extern crate validator_shuffling;
+
+mod arrival;
+mod builder;
+mod disk_guard;
+mod duties;
+mod events;
+mod fork_choice;
+mod genesis;
+mod import_checkpoint;
+mod light_client;
+mod metrics;
+mod node;
+mod packing;
+mod participation;
+mod persisted;
+mod regen;
+mod registry;
+mod replay;
+mod rewards;
+mod shuffling_cache;
+mod slashing;
+mod summary;
+mod transition_pool;
+mod validator_index;
+mod validator_monitor;
+mod withdrawals;
+
+pub use arrival::{ArrivalTracker, BlockArrival, LATE_BLOCK_SLOT_FRACTION};
+pub use builder::BeaconNodeBuilder;
+pub use disk_guard::{
+    DiskGuard, DiskGuardConfig, DiskGuardService, DiskSpace, DEFAULT_DISK_CHECK_INTERVAL,
+    DEFAULT_DISK_HALT_THRESHOLD_MB, DEFAULT_DISK_PRUNE_THRESHOLD_MB,
+};
+pub use duties::ValidatorDuties;
+pub use events::{BeaconNodeEvent, EventBus, EventHandler, NullEventHandler};
+pub use fork_choice::{ForkChoice, NaiveForkChoice, ProtoArrayForkChoice};
+pub use genesis::{
+    duration_to_genesis, wait_for_genesis, GENESIS_COUNTDOWN_INTERVAL, NETWORK_START_OFFSET,
+};
+pub use import_checkpoint::{recover_imports, ImportCheckpointer, DEFAULT_IMPORT_CHECKPOINT_SLOTS};
+pub use light_client::{LightClientHeader, LightClientUpdate, LIGHT_CLIENT_PERIOD_CYCLES};
+pub use node::{
+    AttestationOutcome, BeaconNode, BeaconNodeError, BlockProcessingOutcome,
+    WeakSubjectivityOutcome, LIVENESS_CYCLES, REGISTRY_DELTA_CYCLES,
+};
+pub use packing::{pack_attestations, Vote, MAX_ATTESTATIONS_PER_BLOCK};
+pub use participation::{
+    AccountedCycle, CycleParticipation, ParticipationTracker, ValidatorParticipation,
+    DEFAULT_PARTICIPATION_RETENTION_CYCLES,
+};
+pub use persisted::{PersistedHead, WeakSubjectivityCheckpoint};
+pub use regen::{
+    RegenError, RegeneratedState, StateRegenService, StateRegenerator, DEFAULT_REGEN_CACHE_SIZE,
+    DEFAULT_REGEN_WORKERS, SNAPSHOT_INTERVAL,
+};
+pub use registry::RegistryDelta;
+pub use replay::{replay_blocks, ImportStep, ReplayError, ReplayReport, ReplayedBlock};
+pub use rewards::{
+    attestation_rewards, includer_reward, AttestationReward, BlockReward, BASE_REWARD_QUOTIENT,
+    INCLUDER_REWARD_QUOTIENT,
+};
+pub use shuffling_cache::{
+    compute_shuffling, CommitteePrecomputeService, CycleShuffling, ShufflingCache,
+    DEFAULT_SHUFFLING_CACHE_SIZE,
+};
+pub use slashing::ProposerSlashing;
+pub use slot_clock::{SlotClock, SystemTimeSlotClock, TestingSlotClock, MAXIMUM_CLOCK_DISPARITY};
+pub use summary::{
+    state_summary, validator_leaf, validators_tree, BLOCK_ROOT_GINDEX, FINALIZED_ROOT_GINDEX,
+    VALIDATORS_GINDEX,
+};
+pub use transition_pool::{StateTransitionPool, DEFAULT_STATE_TRANSITION_THREADS};
+pub use validator_index::ValidatorIndexCache;
+pub use validator_monitor::{
+    MonitoredAttestation, MonitoredValidator, ValidatorId, ValidatorMonitor,
+};
+pub use withdrawals::{WithdrawalCredentials, WithdrawalReport, WITHDRAWABILITY_DELAY_EPOCHS};
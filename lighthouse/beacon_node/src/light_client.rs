@@ -0,0 +1,87 @@
+use ssz::{decode_ssz_list, Decodable, DecodeError, Encodable, SszStream};
+use types::Hash256;
+
+/// The number of cycles in each period of the light client, for which one update is stored.
+pub const LIGHT_CLIENT_PERIOD_CYCLES: u64 = 256;
+
+/// The header of a block, as followed by light clients.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LightClientHeader {
+    pub slot: u64,
+    pub root: Hash256,
+    pub parent_root: Hash256,
+    pub state_root: Hash256,
+}
+
+impl Encodable for LightClientHeader {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.slot);
+        s.append(&self.root);
+        s.append(&self.parent_root);
+        s.append(&self.state_root);
+    }
+}
+
+impl Decodable for LightClientHeader {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (slot, i) = u64::ssz_decode(bytes, i)?;
+        let (root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (parent_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (state_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let header = Self {
+            slot,
+            root,
+            parent_root,
+            state_root,
+        };
+        Ok((header, i))
+    }
+}
+
+/// The latest finalized header of a period, with the proofs from which a light client learns the
+/// validators the committees of the next period are shuffled from.
+///
+/// Both branches are against `summary_root`, the root of the `state_summary` of the state after
+/// the finalized block.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LightClientUpdate {
+    pub period: u64,
+    pub finalized_header: LightClientHeader,
+    pub summary_root: Hash256,
+    /// Proves the root of the finalized header at `BLOCK_ROOT_GINDEX`.
+    pub header_branch: Vec<Hash256>,
+    pub validators_root: Hash256,
+    /// Proves `validators_root` at `VALIDATORS_GINDEX`.
+    pub validators_branch: Vec<Hash256>,
+}
+
+impl Encodable for LightClientUpdate {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.period);
+        s.append(&self.finalized_header);
+        s.append(&self.summary_root);
+        s.append_vec(&self.header_branch);
+        s.append(&self.validators_root);
+        s.append_vec(&self.validators_branch);
+    }
+}
+
+impl Decodable for LightClientUpdate {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (period, i) = u64::ssz_decode(bytes, i)?;
+        let (finalized_header, i) = LightClientHeader::ssz_decode(bytes, i)?;
+        let (summary_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (header_branch, i) = decode_ssz_list(bytes, i)?;
+        let (validators_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (validators_branch, i) = decode_ssz_list(bytes, i)?;
+        let update = Self {
+            period,
+            finalized_header,
+            summary_root,
+            header_branch,
+            validators_root,
+            validators_branch,
+        };
+        Ok((update, i))
+    }
+}
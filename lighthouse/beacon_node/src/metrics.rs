@@ -0,0 +1,192 @@
+use super::events::BeaconNodeEvent;
+use lighthouse_metrics::{
+    inc_counter, set_gauge, try_create_float_gauge, try_create_histogram,
+    try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
+    try_create_int_gauge, try_create_int_gauge_vec, Gauge, Histogram, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec, Result,
+};
+
+lazy_static! {
+    /*
+     * Block import
+     */
+    pub static ref BLOCK_PROCESSING_REQUESTS: Result<IntCounter> = try_create_int_counter(
+        "beacon_block_processing_requests_total",
+        "Count of blocks submitted for import"
+    );
+    pub static ref BLOCK_PROCESSING_SUCCESSES: Result<IntCounter> = try_create_int_counter(
+        "beacon_block_processing_successes_total",
+        "Count of blocks imported"
+    );
+    pub static ref BLOCK_PROCESSING_TIMES: Result<Histogram> = try_create_histogram(
+        "beacon_block_processing_seconds",
+        "Time taken to import a block, including fork choice"
+    );
+    pub static ref STATE_TRANSITION_QUEUE_DEPTH: Result<IntGauge> = try_create_int_gauge(
+        "beacon_state_transition_queue_depth",
+        "Number of block imports queued for the state transition threads"
+    );
+
+    /*
+     * Gossip arrival
+     */
+    pub static ref BLOCK_ARRIVAL_DELAY: Result<Histogram> = try_create_histogram_with_buckets(
+        "beacon_block_arrival_delay_seconds",
+        "Time between the start of the slot of each gossiped block and its arrival",
+        vec![0.25, 0.5, 1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0]
+    );
+    pub static ref AGGREGATE_ARRIVAL_DELAY: Result<Histogram> = try_create_histogram_with_buckets(
+        "beacon_aggregate_arrival_delay_seconds",
+        "Time between the start of the slot of each gossiped aggregate and its arrival",
+        vec![0.25, 0.5, 1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0]
+    );
+    pub static ref LATE_BLOCKS: Result<IntCounter> = try_create_int_counter(
+        "beacon_late_blocks_total",
+        "Count of gossiped blocks which arrived after attesters of their slot voted"
+    );
+
+    /*
+     * Fork choice
+     */
+    pub static ref FORK_CHOICE_TIMES: Result<Histogram> =
+        try_create_histogram("beacon_fork_choice_seconds", "Time taken to find the head");
+    pub static ref FORK_CHOICE_CHANGED_HEAD: Result<IntCounter> = try_create_int_counter(
+        "beacon_fork_choice_changed_head_total",
+        "Count of times the head changed"
+    );
+    pub static ref FORK_CHOICE_REORGS: Result<IntCounter> = try_create_int_counter(
+        "beacon_fork_choice_reorg_total",
+        "Count of times the new head did not descend from the old head"
+    );
+    pub static ref FORK_CHOICE_PERSIST_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "beacon_fork_choice_persist_failures_total",
+        "Count of times fork choice could not be persisted on finalization"
+    );
+    pub static ref LIGHT_CLIENT_UPDATE_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "beacon_light_client_update_failures_total",
+        "Count of times the light client update of a finalized checkpoint could not be stored"
+    );
+    pub static ref HEAD_SLOT: Result<IntGauge> =
+        try_create_int_gauge("beacon_head_slot", "Slot of the head block");
+
+    /*
+     * Attestations
+     */
+    pub static ref ATTESTATIONS_POOLED: Result<IntCounter> = try_create_int_counter(
+        "beacon_attestations_pooled_total",
+        "Count of attestations added to the pool"
+    );
+
+    /*
+     * Participation
+     */
+    pub static ref ATTESTATION_INCLUSION_DISTANCE: Result<Histogram> =
+        try_create_histogram_with_buckets(
+            "beacon_attestation_inclusion_distance_slots",
+            "Slots between each attestation of a cycle accounted and the block which included it",
+            vec![1.0, 2.0, 3.0, 4.0, 8.0, 16.0, 32.0, 64.0]
+        );
+    pub static ref PARTICIPATION_RATE: Result<Gauge> = try_create_float_gauge(
+        "beacon_participation_rate",
+        "Fraction of the committee members of the latest cycle accounted whose attestations were included"
+    );
+    pub static ref MISSED_ATTESTATIONS: Result<IntGauge> = try_create_int_gauge(
+        "beacon_participation_missed_attestations",
+        "Count of committee members of the latest cycle accounted whose attestations were not included"
+    );
+    pub static ref PARTICIPATION_PERSIST_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "beacon_participation_persist_failures_total",
+        "Count of times the participation of validators could not be persisted"
+    );
+
+    /*
+     * Slashings
+     */
+    pub static ref PROPOSER_SLASHINGS_POOLED: Result<IntCounter> = try_create_int_counter(
+        "beacon_proposer_slashings_pooled_total",
+        "Count of unverified proposer equivocations detected and added to the pool"
+    );
+
+    /*
+     * State regeneration
+     */
+    pub static ref STATE_REGEN_TIMES: Result<Histogram> = try_create_histogram(
+        "beacon_state_regen_seconds",
+        "Time taken to regenerate a state not in the cache"
+    );
+    pub static ref STATE_REGEN_CACHE_HITS: Result<IntCounter> = try_create_int_counter(
+        "beacon_state_regen_cache_hits_total",
+        "Count of requested states found in the regenerated state cache"
+    );
+    pub static ref STATE_REGEN_BLOCKS_REPLAYED: Result<IntCounter> = try_create_int_counter(
+        "beacon_state_regen_blocks_replayed_total",
+        "Count of blocks replayed to regenerate states"
+    );
+
+    /*
+     * Committees
+     */
+    pub static ref SHUFFLING_CACHE_HITS: Result<IntCounter> = try_create_int_counter(
+        "beacon_shuffling_cache_hits_total",
+        "Count of cycles whose committees were found in the shuffling cache"
+    );
+    pub static ref SHUFFLING_CACHE_MISSES: Result<IntCounter> = try_create_int_counter(
+        "beacon_shuffling_cache_misses_total",
+        "Count of cycles whose committees were shuffled on request, as they were not cached"
+    );
+    pub static ref COMMITTEE_PRECOMPUTE_TIMES: Result<Histogram> = try_create_histogram(
+        "beacon_committee_precompute_seconds",
+        "Time taken to pre-compute the committees of the next cycle"
+    );
+
+    /*
+     * Disk space
+     */
+    pub static ref DISK_AVAILABLE_BYTES: Result<IntGauge> = try_create_int_gauge(
+        "beacon_disk_available_bytes",
+        "Bytes available on the filesystem holding the database, as last checked"
+    );
+    pub static ref BLOCK_IMPORT_HALTED: Result<IntGauge> = try_create_int_gauge(
+        "beacon_block_import_halted",
+        "Whether block import is halted as the disk is nearly full"
+    );
+
+    /*
+     * Validator monitor
+     */
+    pub static ref MONITOR_ATTESTATIONS_INCLUDED: Result<IntCounterVec> = try_create_int_counter_vec(
+        "validator_monitor_attestations_included_total",
+        "Count of attestations of a monitored validator included in imported blocks",
+        &["validator"]
+    );
+    pub static ref MONITOR_INCLUSION_DELAY: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "validator_monitor_inclusion_delay_slots",
+        "Slots between the latest attestation of a monitored validator and its inclusion",
+        &["validator"]
+    );
+    pub static ref MONITOR_BLOCKS_PROPOSED: Result<IntCounterVec> = try_create_int_counter_vec(
+        "validator_monitor_blocks_proposed_total",
+        "Count of imported blocks proposed by a monitored validator",
+        &["validator"]
+    );
+    pub static ref MONITOR_BALANCE: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "validator_monitor_balance_gwei",
+        "Balance of a monitored validator",
+        &["validator"]
+    );
+}
+
+/// Updates the metrics of the chain from one of its events.
+pub fn observe_event(event: &BeaconNodeEvent) {
+    match event {
+        BeaconNodeEvent::Block { .. } => inc_counter(&BLOCK_PROCESSING_SUCCESSES),
+        BeaconNodeEvent::Attestation { .. } => inc_counter(&ATTESTATIONS_POOLED),
+        BeaconNodeEvent::Head { slot, .. } => {
+            inc_counter(&FORK_CHOICE_CHANGED_HEAD);
+            set_gauge(&HEAD_SLOT, *slot as i64);
+        }
+        BeaconNodeEvent::ChainReorg { .. } => inc_counter(&FORK_CHOICE_REORGS),
+        BeaconNodeEvent::ProposerSlashing(_) => inc_counter(&PROPOSER_SLASHINGS_POOLED),
+        BeaconNodeEvent::RegistryDelta(_) | BeaconNodeEvent::FinalizedCheckpoint { .. } => {}
+    }
+}
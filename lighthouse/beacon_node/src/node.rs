@@ -0,0 +1,2406 @@
+use super::arrival::{ArrivalTracker, BlockArrival};
+use super::builder::BeaconNodeBuilder;
+use super::events::{BeaconNodeEvent, EventHandler};
+use super::fork_choice::ForkChoice;
+use super::import_checkpoint::ImportCheckpointer;
+use super::light_client::{LightClientHeader, LightClientUpdate, LIGHT_CLIENT_PERIOD_CYCLES};
+use super::metrics;
+use super::packing::{pack_attestations, Vote, MAX_ATTESTATIONS_PER_BLOCK};
+use super::participation::{
+    AccountedCycle, ParticipationTracker, ValidatorParticipation,
+    DEFAULT_PARTICIPATION_RETENTION_CYCLES,
+};
+use super::persisted::{PersistedHead, PersistedOpPool, WeakSubjectivityCheckpoint};
+use super::registry::RegistryDelta;
+use super::replay::ImportStep;
+use super::rewards::{attestation_rewards, BlockReward};
+use super::shuffling_cache::{
+    compute_shuffling, CycleShuffling, ShufflingCache, DEFAULT_SHUFFLING_CACHE_SIZE,
+};
+use super::slashing::ProposerSlashing;
+use super::summary::{state_summary, validators_tree, BLOCK_ROOT_GINDEX, VALIDATORS_GINDEX};
+use super::validator_index::ValidatorIndexCache;
+use super::validator_monitor::{ValidatorId, ValidatorMonitor};
+use super::withdrawals::WithdrawalReport;
+use bls::PublicKey;
+use db::stores::{
+    BeaconBlockStore, ChainStore, LightClientStore, ParticipationStore, ValidatorStore,
+    ValidatorStoreError,
+};
+use db::{ClientDB, DBError};
+use eth1::Eth1Backend;
+use lighthouse_metrics::{inc_counter, observe, set_gauge, start_timer, stop_timer};
+use slog::Logger;
+use slot_clock::{SlotClock, SystemTimeSlotClock, MAXIMUM_CLOCK_DISPARITY};
+use ssz::{ssz_encode, Decodable, SszStream};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use types::{
+    Attestation, AttestationData, BeaconBlock, ChainConfig, Hash256, ShardAndCommittee,
+    SpecialRecord, ValidatorRecord, ValidatorStatus,
+};
+use validator_induction::ValidatorInductor;
+use validator_shuffling::ValidatorAssignmentError;
+
+/// The number of recent cycles for which the validators seen attesting are remembered.
+pub const LIVENESS_CYCLES: usize = 4;
+
+/// The number of recent cycles whose changes to the validator registry are remembered.
+pub const REGISTRY_DELTA_CYCLES: usize = 64;
+
+/// The maximum number of proposer slashings pooled. Beyond it, the oldest are dropped.
+pub const MAX_POOLED_PROPOSER_SLASHINGS: usize = 1_024;
+
+#[derive(Debug, PartialEq)]
+pub enum BeaconNodeError {
+    InsufficientValidators,
+    /// The slot duration of the config is zero.
+    InvalidSlotDuration,
+    ValidatorAssignmentError(ValidatorAssignmentError),
+    /// No validator has the given index or public key.
+    UnknownValidator,
+    /// No committee is assigned to the shard at the slot.
+    UnknownCommittee,
+    /// A block was requested for a slot which is not after the head.
+    SlotNotAfterHead,
+    /// The slots of the cycle are beyond a `u64`.
+    CycleOutOfRange,
+    /// The node was built without a block store.
+    MissingStore,
+    ForkChoiceFailed,
+    DBError(String),
+    /// Blocks are not imported until the reason given is resolved, e.g. the disk is nearly full.
+    BlockImportHalted(String),
+}
+
+impl From<ValidatorAssignmentError> for BeaconNodeError {
+    fn from(e: ValidatorAssignmentError) -> BeaconNodeError {
+        BeaconNodeError::ValidatorAssignmentError(e)
+    }
+}
+
+impl From<DBError> for BeaconNodeError {
+    fn from(e: DBError) -> BeaconNodeError {
+        BeaconNodeError::DBError(e.message)
+    }
+}
+
+impl From<ValidatorStoreError> for BeaconNodeError {
+    fn from(e: ValidatorStoreError) -> BeaconNodeError {
+        match e {
+            ValidatorStoreError::DBError(message) => BeaconNodeError::DBError(message),
+            ValidatorStoreError::DecodeError => {
+                BeaconNodeError::DBError("Invalid validator index".to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BlockProcessingOutcome {
+    Imported,
+    AlreadyKnown,
+    /// The parent of the block is not in the store, so it cannot be imported yet.
+    UnknownParent,
+    /// The block is from a slot which has not yet started.
+    FutureSlot,
+    /// The block is not from a later slot than its parent.
+    InvalidSlot,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AttestationOutcome {
+    /// The attestation was added to the pool, to be included in a block.
+    Pooled,
+    AlreadyKnown,
+    /// The attestation is from a slot which has not yet started.
+    FutureSlot,
+    /// No committee is assigned to the shard at the slot.
+    UnknownCommittee,
+    /// No participants are set, or participants outside of the committee are set.
+    InvalidParticipants,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WeakSubjectivityOutcome {
+    /// The canonical chain includes the checkpoint block.
+    Verified,
+    /// The head is before the slot of the checkpoint, so the chain cannot yet conflict with it.
+    Pending,
+    /// The canonical chain has another block, or no block, at the slot of the checkpoint.
+    Conflict(Option<Hash256>),
+}
+
+/// The beacon node's view of the chain: its blocks, head and validators, together with the
+/// attestations waiting to be included in a block.
+///
+/// Blocks are imported without state transition and the head is chosen by the `ForkChoice` it
+/// is built with. Until the state transition is restored, the validator set is fixed at
+/// genesis and every cycle uses the genesis shuffling.
+//
+// TODO: process blocks once block processing is restored.
+// https://github.com/sigp/lighthouse/issues/98
+pub struct BeaconNode<T: ClientDB> {
+    config: ChainConfig,
+    store: Arc<BeaconBlockStore<T>>,
+    validators: Vec<ValidatorRecord>,
+    /// The committees of each slot of a cycle.
+    shard_and_committee_for_slots: Vec<Vec<ShardAndCommittee>>,
+    /// The committees of the latest cycles for which duties were requested or pre-computed.
+    shufflings: Arc<ShufflingCache>,
+    genesis_root: Hash256,
+    /// The tips of all known chains.
+    head_block_hashes: Vec<Hash256>,
+    head_root: Hash256,
+    head_slot: u64,
+    /// The latest cycle accounted in which two thirds of the committee members attested.
+    justified_cycle: Option<u64>,
+    finalized_cycle: u64,
+    finalized_root: Hash256,
+    attestations: Vec<Attestation>,
+    /// Exits and slashings waiting to be included in a block.
+    specials: Vec<SpecialRecord>,
+    /// Unverified proposer equivocations at or after the finalized slot, oldest first.
+    proposer_slashings: Vec<ProposerSlashing>,
+    /// The indices of the validators seen attesting in each recent cycle.
+    live_validators: BTreeMap<u64, BTreeSet<usize>>,
+    /// The validators as they were at the start of the cycle of the head.
+    cycle_start_validators: Vec<ValidatorRecord>,
+    /// The index of each validator, by public key.
+    validator_indices: ValidatorIndexCache<T>,
+    /// The changes to the registry over each recent cycle, oldest first.
+    registry_deltas: Vec<RegistryDelta>,
+    events: Arc<dyn EventHandler>,
+    eth1: Arc<dyn Eth1Backend>,
+    fork_choice: Box<dyn ForkChoice<T>>,
+    validator_monitor: Option<ValidatorMonitor>,
+    participation: ParticipationTracker,
+    clock: Arc<dyn SlotClock>,
+    /// How far the clocks of peers may be ahead of or behind our own.
+    clock_disparity: Duration,
+    weak_subjectivity_checkpoint: Option<WeakSubjectivityCheckpoint>,
+    /// Where fork choice is persisted when a checkpoint is finalized.
+    finalization_store: Option<ChainStore<T>>,
+    /// Where the participation of each validator is written as cycles are accounted.
+    participation_store: Option<ParticipationStore<T>>,
+    /// The number of the latest cycles accounted whose participation is kept in the store.
+    participation_retention_cycles: u64,
+    /// Where an update for light clients is written when a checkpoint is finalized.
+    light_client_store: Option<LightClientStore<T>>,
+    /// Persists the chain as batches are imported by `process_chain_segment`.
+    import_checkpointer: Option<ImportCheckpointer<T>>,
+    /// Why block import is halted, if it is.
+    import_halted: Option<String>,
+    arrivals: ArrivalTracker,
+    /// The steps of the import of the block being traced, if any.
+    import_trace: Option<Vec<ImportStep>>,
+}
+
+impl<T: ClientDB> BeaconNode<T> {
+    /// Starts the chain from a genesis block, with the `initial_validators` of `config`, and the
+    /// default components of `BeaconNodeBuilder`.
+    pub fn new(
+        config: ChainConfig,
+        store: Arc<BeaconBlockStore<T>>,
+    ) -> Result<Self, BeaconNodeError> {
+        BeaconNodeBuilder::new(config).store(store).build()
+    }
+
+    pub(super) fn from_builder(builder: BeaconNodeBuilder<T>) -> Result<Self, BeaconNodeError> {
+        let BeaconNodeBuilder {
+            config,
+            store,
+            slot_clock,
+            eth1_backend,
+            fork_choice,
+            event_handler,
+        } = builder;
+        let store = store.ok_or(BeaconNodeError::MissingStore)?;
+        /*
+         * Induct the initial validators, ignoring any invalid registrations.
+         */
+        let validators = {
+            let mut inductor = ValidatorInductor::new(0, config.shard_count, vec![]);
+            for registration in &config.initial_validators {
+                let _ = inductor.induct(registration, ValidatorStatus::Active);
+            }
+            inductor.to_vec()
+        };
+        if validators.is_empty() {
+            return Err(BeaconNodeError::InsufficientValidators);
+        }
+        let mut validator_indices = ValidatorIndexCache::new();
+        validator_indices.update(&validators)?;
+        let shard_and_committee_for_slots = compute_shuffling(&validators, &config)?;
+        let shufflings = Arc::new(ShufflingCache::new(DEFAULT_SHUFFLING_CACHE_SIZE));
+        shufflings.insert(0, Arc::new(shard_and_committee_for_slots.clone()));
+        let clock: Arc<dyn SlotClock> = match slot_clock {
+            Some(clock) => clock,
+            None => Arc::new(
+                SystemTimeSlotClock::new(config.genesis_time, config.slot_duration_millis)
+                    .ok_or(BeaconNodeError::InvalidSlotDuration)?,
+            ),
+        };
+
+        let genesis = BeaconBlock::zero();
+        let genesis_root = genesis.canonical_root();
+        store.put_serialized_block(&genesis_root, &ssz_encode(&genesis))?;
+        fork_choice.process_block(genesis_root, &genesis);
+
+        Ok(Self {
+            config,
+            store,
+            cycle_start_validators: validators.clone(),
+            validator_indices,
+            registry_deltas: vec![],
+            validators,
+            shard_and_committee_for_slots,
+            shufflings,
+            genesis_root,
+            head_block_hashes: vec![genesis_root],
+            head_root: genesis_root,
+            head_slot: genesis.slot,
+            justified_cycle: None,
+            finalized_cycle: 0,
+            finalized_root: genesis_root,
+            attestations: vec![],
+            specials: vec![],
+            proposer_slashings: vec![],
+            live_validators: BTreeMap::new(),
+            events: event_handler,
+            eth1: eth1_backend,
+            fork_choice,
+            validator_monitor: None,
+            participation: ParticipationTracker::default(),
+            clock,
+            clock_disparity: MAXIMUM_CLOCK_DISPARITY,
+            weak_subjectivity_checkpoint: None,
+            finalization_store: None,
+            participation_store: None,
+            participation_retention_cycles: DEFAULT_PARTICIPATION_RETENTION_CYCLES,
+            light_client_store: None,
+            import_checkpointer: None,
+            import_halted: None,
+            arrivals: ArrivalTracker::new(),
+            import_trace: None,
+        })
+    }
+
+    pub fn config(&self) -> &ChainConfig {
+        &self.config
+    }
+
+    pub fn store(&self) -> &Arc<BeaconBlockStore<T>> {
+        &self.store
+    }
+
+    pub fn genesis_root(&self) -> Hash256 {
+        self.genesis_root
+    }
+
+    /// Replaces the system clock, e.g. with a `TestingSlotClock`.
+    pub fn set_slot_clock(&mut self, clock: Arc<dyn SlotClock>) {
+        self.clock = clock;
+    }
+
+    pub fn slot_clock(&self) -> &Arc<dyn SlotClock> {
+        &self.clock
+    }
+
+    /// Sets how far the clocks of peers may be ahead of or behind our own, for the slots of
+    /// gossiped blocks and attestations.
+    pub fn set_clock_disparity(&mut self, clock_disparity: Duration) {
+        self.clock_disparity = clock_disparity;
+    }
+
+    /// Records the arrival over gossip of the block of `proposer` for `slot`, as of now.
+    pub fn observe_block_arrival(&mut self, slot: u64, proposer: usize) -> BlockArrival {
+        let delay = self.clock.duration_since_start_of(slot);
+        observe(&metrics::BLOCK_ARRIVAL_DELAY, duration_as_secs_f64(delay));
+        let arrival =
+            self.arrivals
+                .observe_block(slot, proposer, delay, self.clock.slot_duration());
+        if arrival.late {
+            inc_counter(&metrics::LATE_BLOCKS);
+        }
+        arrival
+    }
+
+    /// Records the arrival over gossip of an aggregate for `slot` as of now, returning its delay.
+    pub fn observe_aggregate_arrival(&self, slot: u64) -> Duration {
+        let delay = self.clock.duration_since_start_of(slot);
+        observe(
+            &metrics::AGGREGATE_ARRIVAL_DELAY,
+            duration_as_secs_f64(delay),
+        );
+        delay
+    }
+
+    /// Refuses to import blocks until `resume_block_import` is called, failing with `reason`.
+    pub fn halt_block_import(&mut self, reason: String) {
+        self.import_halted = Some(reason);
+    }
+
+    /// Imports blocks again, returning whether import was halted.
+    pub fn resume_block_import(&mut self) -> bool {
+        self.import_halted.take().is_some()
+    }
+
+    /// Returns why block import is halted, if it is.
+    pub fn block_import_halted(&self) -> Option<&str> {
+        self.import_halted.as_deref()
+    }
+
+    /// Returns the slot of the clock, or zero if the chain has not yet started.
+    pub fn present_slot(&self) -> u64 {
+        self.clock.now().unwrap_or(0)
+    }
+
+    /// Returns the latest slot which a peer may be in, allowing for the clock disparity.
+    ///
+    /// Objects from later slots are from the future.
+    pub fn present_slot_with_future_tolerance(&self) -> u64 {
+        self.clock
+            .now_with_future_tolerance(self.clock_disparity)
+            .unwrap_or(0)
+    }
+
+    /// Returns the earliest slot which a peer may still be in, allowing for the clock disparity.
+    pub fn present_slot_with_past_tolerance(&self) -> u64 {
+        self.clock
+            .now_with_past_tolerance(self.clock_disparity)
+            .unwrap_or(0)
+    }
+
+    /// Sets the checkpoint which the canonical chain must include, replacing any restored.
+    pub fn set_weak_subjectivity_checkpoint(&mut self, checkpoint: WeakSubjectivityCheckpoint) {
+        self.weak_subjectivity_checkpoint = Some(checkpoint);
+    }
+
+    pub fn weak_subjectivity_checkpoint(&self) -> Option<WeakSubjectivityCheckpoint> {
+        self.weak_subjectivity_checkpoint
+    }
+
+    /// Checks that the canonical chain includes the weak subjectivity checkpoint, returning `None`
+    /// if there is no checkpoint.
+    ///
+    /// The finalized block is not persisted, so the canonical chain is checked rather than the
+    /// finalized chain.
+    pub fn verify_weak_subjectivity(
+        &self,
+    ) -> Result<Option<WeakSubjectivityOutcome>, BeaconNodeError> {
+        let checkpoint = match self.weak_subjectivity_checkpoint {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(None),
+        };
+        if self.head_slot < checkpoint.slot {
+            return Ok(Some(WeakSubjectivityOutcome::Pending));
+        }
+        let found = self
+            .store
+            .block_at_slot(&self.head_root, checkpoint.slot)
+            .map_err(|e| BeaconNodeError::DBError(format!("{:?}", e)))?
+            .map(|(root, _)| Hash256::from(&root[..]));
+        if found == Some(checkpoint.root) {
+            Ok(Some(WeakSubjectivityOutcome::Verified))
+        } else {
+            Ok(Some(WeakSubjectivityOutcome::Conflict(found)))
+        }
+    }
+
+    /// Returns the slot and root of the canonical head.
+    pub fn head(&self) -> (u64, Hash256) {
+        (self.head_slot, self.head_root)
+    }
+
+    /// Returns the tips of all known chains, including the canonical head.
+    pub fn heads(&self) -> &[Hash256] {
+        &self.head_block_hashes
+    }
+
+    /// The handler to which changes to the chain are published.
+    pub fn events(&self) -> &dyn EventHandler {
+        &*self.events
+    }
+
+    /// Returns the root of the finalized block, which is the genesis block until a later cycle is
+    /// finalized by `update_finality`.
+    pub fn finalized_root(&self) -> Hash256 {
+        self.finalized_root
+    }
+
+    pub fn finalized_cycle(&self) -> u64 {
+        self.finalized_cycle
+    }
+
+    pub fn validators(&self) -> &[ValidatorRecord] {
+        &self.validators
+    }
+
+    pub fn validator_index(&self, pubkey: &PublicKey) -> Option<usize> {
+        self.validator_indices.get(pubkey)
+    }
+
+    /// The attestations waiting to be included in a block.
+    pub fn pooled_attestations(&self) -> &[Attestation] {
+        &self.attestations
+    }
+
+    /// Returns the best aggregate of the pooled attestations to `data`.
+    ///
+    /// Attestations are combined starting from those with the most participants, in the order
+    /// they were pooled among equals, skipping any with participants already in the aggregate.
+    pub fn aggregate_attestation(&self, data: &AttestationData) -> Option<Attestation> {
+        let mut attestations: Vec<&Attestation> = self
+            .attestations
+            .iter()
+            .filter(|a| a.data == *data)
+            .collect();
+        attestations.sort_by_key(|a| Reverse(a.participation_bitfield.num_set_bits()));
+        let mut attestations = attestations.into_iter();
+        let mut aggregate = attestations.next()?.clone();
+        for attestation in attestations {
+            let participants: Vec<usize> = (0..attestation.participation_bitfield.len())
+                .filter(|i| attestation.participation_bitfield.get(*i) == Ok(true))
+                .collect();
+            if participants
+                .iter()
+                .any(|i| aggregate.participation_bitfield.get(*i) == Ok(true))
+            {
+                continue;
+            }
+            for i in participants {
+                aggregate.participation_bitfield.set(i, true);
+            }
+            aggregate
+                .aggregate_sig
+                .add_aggregate(&attestation.aggregate_sig);
+        }
+        Some(aggregate)
+    }
+
+    /// Returns whether the validator with `index` was seen attesting in `cycle`, in a block or
+    /// a pooled attestation.
+    ///
+    /// Only the last `LIVENESS_CYCLES` cycles seen are remembered.
+    pub fn is_live(&self, index: usize, cycle: u64) -> bool {
+        match self.live_validators.get(&cycle) {
+            Some(live) => live.contains(&index),
+            None => false,
+        }
+    }
+
+    /// Returns the changes to the validator registry over each of the last
+    /// `REGISTRY_DELTA_CYCLES` cycles the head has left, oldest first.
+    ///
+    /// Until the state transition is restored the registry is fixed at genesis, so every delta
+    /// is empty.
+    pub fn registry_deltas(&self) -> &[RegistryDelta] {
+        &self.registry_deltas
+    }
+
+    /// Records and publishes the changes to the registry over `cycle`, which the head has left.
+    fn record_registry_delta(&mut self, cycle: u64) {
+        let delta = RegistryDelta::between(cycle, &self.cycle_start_validators, &self.validators);
+        self.cycle_start_validators = self.validators.clone();
+        self.registry_deltas.push(delta.clone());
+        if self.registry_deltas.len() > REGISTRY_DELTA_CYCLES {
+            self.registry_deltas.remove(0);
+        }
+        self.publish(BeaconNodeEvent::RegistryDelta(delta));
+    }
+
+    /// Accounts the participation of the validators in each cycle which can no longer be
+    /// included now that the head is in `head_cycle`, writing it to the participation store.
+    ///
+    /// The records of each cycle accounted are written too, and those of the cycle leaving the
+    /// retention window are deleted.
+    fn account_participation(
+        &mut self,
+        head_cycle: u64,
+        head_root: Hash256,
+    ) -> Result<(), BeaconNodeError> {
+        let committee_members = self
+            .shard_and_committee_for_slots
+            .iter()
+            .flat_map(|committees| committees.iter())
+            .flat_map(|committee| committee.committee.iter().cloned())
+            .collect();
+        let changed = self.participation.account(head_cycle, &committee_members);
+        let accounted = self.participation.take_accounted();
+        self.update_finality(&accounted, head_root)?;
+        let store = match self.participation_store.as_ref() {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+        for index in changed {
+            let participation = self
+                .participation
+                .validator(index)
+                .expect("Changed participation is known");
+            let ssz = ssz_encode(participation);
+            if store
+                .put_serialized_participation(index as u64, &ssz)
+                .is_err()
+            {
+                inc_counter(&metrics::PARTICIPATION_PERSIST_FAILURES);
+                return Ok(());
+            }
+        }
+        for accounted in &accounted {
+            let mut result =
+                store.put_serialized_cycle(accounted.cycle, &ssz_encode(&accounted.participation));
+            if let Some(monitor) = self.validator_monitor.as_ref() {
+                let mut ssz = SszStream::new();
+                ssz.append_vec(&monitor.cycle_attestations(accounted, &committee_members));
+                result = result.and_then(|()| {
+                    store.put_serialized_monitor_cycle(accounted.cycle, &ssz.drain())
+                });
+            }
+            if let Some(stale) = accounted
+                .cycle
+                .checked_sub(self.participation_retention_cycles)
+            {
+                result = result.and_then(|()| store.delete_cycle(stale));
+            }
+            if result.is_err() {
+                inc_counter(&metrics::PARTICIPATION_PERSIST_FAILURES);
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Justifies each of the `accounted` cycles in which the attestations of at least two thirds
+    /// of the committee members were included. When two consecutive cycles are justified, the
+    /// earlier is finalized and its checkpoint published: the latest block of the chain of
+    /// `head_root` at or before the start of the cycle.
+    ///
+    /// Until the state transition is restored, the inclusion of attestations stands in for the
+    /// balances voting for each checkpoint.
+    fn update_finality(
+        &mut self,
+        accounted: &[AccountedCycle],
+        head_root: Hash256,
+    ) -> Result<(), BeaconNodeError> {
+        let cycle_length = u64::from(self.config.cycle_length.max(1));
+        for accounted in accounted {
+            let participation = &accounted.participation;
+            if participation.committee_members == 0
+                || participation.attestations_included * 3 < participation.committee_members * 2
+            {
+                continue;
+            }
+            let previous = self.justified_cycle.replace(accounted.cycle);
+            let finalized = match previous {
+                Some(justified) if justified + 1 == accounted.cycle => justified,
+                _ => continue,
+            };
+            if finalized <= self.finalized_cycle {
+                continue;
+            }
+            let mut root = head_root;
+            let mut block = self.block(&root)?;
+            while block.slot > finalized * cycle_length {
+                root = *block
+                    .parent_hash()
+                    .ok_or(BeaconNodeError::ForkChoiceFailed)?;
+                block = self.block(&root)?;
+            }
+            self.finalized_cycle = finalized;
+            self.finalized_root = root;
+            self.proposer_slashings.retain(|s| s.slot >= block.slot);
+            self.publish(BeaconNodeEvent::FinalizedCheckpoint {
+                root,
+                state_root: block.crystallized_state_root,
+                cycle: finalized,
+            });
+        }
+        Ok(())
+    }
+
+    /// Records the participants of `attestation` as live in its cycle.
+    fn record_liveness(&mut self, attestation: &Attestation) {
+        let participants = self.participants(attestation);
+        let cycle = attestation.data.slot / u64::from(self.config.cycle_length.max(1));
+        self.live_validators
+            .entry(cycle)
+            .or_default()
+            .extend(participants);
+        while self.live_validators.len() > LIVENESS_CYCLES {
+            let oldest = *self.live_validators.keys().next().expect("Not empty");
+            self.live_validators.remove(&oldest);
+        }
+    }
+
+    /// Returns the indices of the validators whose participation is set in `attestation`, or
+    /// none if it is from an unknown committee.
+    fn participants(&self, attestation: &Attestation) -> Vec<usize> {
+        let data = &attestation.data;
+        match self.committee(data.slot, data.shard) {
+            Some(committee) => committee
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| attestation.participation_bitfield.get(*i) == Ok(true))
+                .map(|(_, index)| *index)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Logs and records metrics for the validators of `ids` as blocks are imported, replacing any
+    /// validators monitored before.
+    pub fn monitor_validators(&mut self, ids: &[ValidatorId], log: Logger) {
+        self.validator_monitor = Some(ValidatorMonitor::new(ids, &self.validators, log));
+    }
+
+    pub fn validator_monitor(&self) -> Option<&ValidatorMonitor> {
+        self.validator_monitor.as_ref()
+    }
+
+    /// Reports the withdrawal credentials and exit of each monitored validator, from the head
+    /// state.
+    pub fn monitored_withdrawals(&self) -> Vec<WithdrawalReport> {
+        let indices = match self.validator_monitor.as_ref() {
+            Some(monitor) => monitor.indices(),
+            None => return vec![],
+        };
+        indices
+            .into_iter()
+            .filter_map(|i| self.validators.get(i).map(|v| (i, v)))
+            .map(|(i, v)| WithdrawalReport::new(i, v, self.config.epoch_length))
+            .collect()
+    }
+
+    /// The exits and slashings waiting to be included in a block.
+    pub fn pooled_specials(&self) -> &[SpecialRecord] {
+        &self.specials
+    }
+
+    /// Adds `special` to the pool, returning `false` if it is already pooled.
+    pub fn pool_special(&mut self, special: SpecialRecord) -> bool {
+        if self.specials.contains(&special) {
+            return false;
+        }
+        self.specials.push(special);
+        true
+    }
+
+    /// The unverified proposer equivocations detected from gossip since the finalized slot.
+    pub fn pooled_proposer_slashings(&self) -> &[ProposerSlashing] {
+        &self.proposer_slashings
+    }
+
+    /// Adds `slashing` to the pool, publishing it, returning `false` if a slashing of the same
+    /// proposer for the same slot is already pooled.
+    ///
+    /// Slashings are pruned once their slot is finalized, and at most
+    /// `MAX_POOLED_PROPOSER_SLASHINGS` are kept.
+    pub fn pool_proposer_slashing(&mut self, slashing: ProposerSlashing) -> bool {
+        let pooled = self
+            .proposer_slashings
+            .iter()
+            .any(|s| s.proposer_index == slashing.proposer_index && s.slot == slashing.slot);
+        if pooled {
+            return false;
+        }
+        self.proposer_slashings.push(slashing);
+        if self.proposer_slashings.len() > MAX_POOLED_PROPOSER_SLASHINGS {
+            self.proposer_slashings.remove(0);
+        }
+        self.publish(BeaconNodeEvent::ProposerSlashing(slashing));
+        true
+    }
+
+    /// Returns the committees assigned at `slot`.
+    pub fn committees(&self, slot: u64) -> &[ShardAndCommittee] {
+        let i = slot % u64::from(self.config.cycle_length.max(1));
+        self.shard_and_committee_for_slots
+            .get(i as usize)
+            .map(|committees| &committees[..])
+            .unwrap_or(&[])
+    }
+
+    /// The cache of the committees of recent cycles, filled ahead of each cycle by the
+    /// `CommitteePrecomputeService`.
+    pub fn shuffling_cache(&self) -> &Arc<ShufflingCache> {
+        &self.shufflings
+    }
+
+    /// Returns the committees of each slot of `cycle`, shuffling the validators if they are not
+    /// cached.
+    pub fn shuffling(&self, cycle: u64) -> Result<Arc<CycleShuffling>, BeaconNodeError> {
+        if let Some(shuffling) = self.shufflings.get(cycle) {
+            inc_counter(&metrics::SHUFFLING_CACHE_HITS);
+            return Ok(shuffling);
+        }
+        inc_counter(&metrics::SHUFFLING_CACHE_MISSES);
+        let shuffling = Arc::new(compute_shuffling(&self.validators, &self.config)?);
+        self.shufflings.insert(cycle, shuffling.clone());
+        Ok(shuffling)
+    }
+
+    /// Returns the committee assigned to `shard` at `slot`.
+    pub fn committee(&self, slot: u64, shard: u64) -> Option<&[usize]> {
+        self.committees(slot)
+            .iter()
+            .find(|c| u64::from(c.shard) == shard)
+            .map(|c| &c.committee[..])
+    }
+
+    /// Returns the index of the validator which proposes the block at `slot`.
+    ///
+    /// The proposer is taken from the first committee of the slot, in turn. Slot zero has no
+    /// proposer, as the genesis block fills it.
+    pub fn block_proposer(&self, slot: u64) -> Option<usize> {
+        proposer(self.committees(slot), slot)
+    }
+
+    /// Builds an unsigned block for `slot` on the canonical head, voting for the block of the eth1
+    /// backend and packing the pooled attestations which are old enough to include the most new
+    /// votes.
+    pub fn produce_block(
+        &self,
+        slot: u64,
+        randao_reveal: Hash256,
+        graffiti: Hash256,
+    ) -> Result<BeaconBlock, BeaconNodeError> {
+        if slot <= self.head_slot {
+            return Err(BeaconNodeError::SlotNotAfterHead);
+        }
+        let candidates: Vec<(Attestation, Vec<usize>)> = self
+            .attestations
+            .iter()
+            .filter(|a| a.data.slot + self.config.min_attestation_inclusion_delay <= slot)
+            .map(|a| (a.clone(), self.participants(a)))
+            .collect();
+        let min_slot = candidates
+            .iter()
+            .map(|(a, _)| a.data.slot)
+            .min()
+            .unwrap_or(slot);
+        let included = self.included_votes(self.head_root, min_slot)?;
+        let attestations = pack_attestations(
+            candidates,
+            &included,
+            &self.validators,
+            MAX_ATTESTATIONS_PER_BLOCK,
+        );
+
+        let mut block = BeaconBlock::zero();
+        block.slot = slot;
+        block.randao_reveal = randao_reveal;
+        block.ancestor_hashes.push(self.head_root);
+        if let Some(eth1_block) = self.eth1.voting_block() {
+            block.pow_chain_reference = eth1_block.hash;
+        }
+        block.attestations = attestations;
+        block.specials = self.specials.clone();
+        block.graffiti = graffiti;
+        Ok(block)
+    }
+
+    /// Estimates the reward of the proposer of `block`, and the marginal value of each of its
+    /// attestations, from the votes which it includes first. The block need not be imported, but
+    /// its parent must be.
+    ///
+    /// Committees and balances are taken from the head state, so the estimate of a block from
+    /// before the latest registry change is approximate.
+    pub fn block_reward(&self, block: &BeaconBlock) -> Result<BlockReward, BeaconNodeError> {
+        let attestations: Vec<(Attestation, Vec<usize>)> = block
+            .attestations
+            .iter()
+            .map(|a| (a.clone(), self.participants(a)))
+            .collect();
+        let included = match block.parent_hash() {
+            Some(parent) => {
+                let min_slot = block
+                    .attestations
+                    .iter()
+                    .map(|a| a.data.slot)
+                    .min()
+                    .unwrap_or(block.slot);
+                self.included_votes(*parent, min_slot)?
+            }
+            None => BTreeSet::new(),
+        };
+        Ok(BlockReward {
+            slot: block.slot,
+            root: block.canonical_root(),
+            proposer: self.block_proposer(block.slot),
+            attestations: attestation_rewards(&attestations, &included, &self.validators),
+        })
+    }
+
+    /// Returns the votes, of slots from `min_slot`, of the attestations included in the chain
+    /// ending with the block with `root`.
+    fn included_votes(
+        &self,
+        root: Hash256,
+        min_slot: u64,
+    ) -> Result<BTreeSet<Vote>, BeaconNodeError> {
+        let mut votes = BTreeSet::new();
+        let mut root = root;
+        while root != self.genesis_root {
+            let block = self.block(&root)?;
+            if block.slot < min_slot {
+                break;
+            }
+            for attestation in &block.attestations {
+                let data = &attestation.data;
+                if data.slot >= min_slot {
+                    for i in self.participants(attestation) {
+                        votes.insert((data.slot, data.shard, i));
+                    }
+                }
+            }
+            root = match block.parent_hash() {
+                Some(parent) => *parent,
+                None => break,
+            };
+        }
+        Ok(votes)
+    }
+
+    /// Stores `block` and runs fork choice, if its parent is known.
+    pub fn process_block(
+        &mut self,
+        block: &BeaconBlock,
+        present_slot: u64,
+    ) -> Result<BlockProcessingOutcome, BeaconNodeError> {
+        inc_counter(&metrics::BLOCK_PROCESSING_REQUESTS);
+        if let Some(ref reason) = self.import_halted {
+            return Err(BeaconNodeError::BlockImportHalted(reason.clone()));
+        }
+        let _timer = start_timer(&metrics::BLOCK_PROCESSING_TIMES);
+        let started = Instant::now();
+        let root = block.canonical_root();
+        if self.store.block_exists(&root)? {
+            return Ok(BlockProcessingOutcome::AlreadyKnown);
+        }
+        if block.slot > present_slot {
+            return Ok(BlockProcessingOutcome::FutureSlot);
+        }
+        let parent = match block.parent_hash() {
+            Some(parent) if self.store.block_exists(parent)? => *parent,
+            _ => return Ok(BlockProcessingOutcome::UnknownParent),
+        };
+        if block.slot <= self.block(&parent)?.slot {
+            return Ok(BlockProcessingOutcome::InvalidSlot);
+        }
+        self.trace("verify", started, || format!("parent {:?}", parent));
+        let started = Instant::now();
+        let ssz = ssz_encode(block);
+        self.store.put_serialized_block(&root, &ssz)?;
+        self.trace("store", started, || format!("{} bytes", ssz.len()));
+        self.import_block(root, parent, block)?;
+        Ok(BlockProcessingOutcome::Imported)
+    }
+
+    /// Imports a batch of blocks downloaded by sync, in ascending slot order, returning the
+    /// outcome of each block processed. Processing stops at the first block which is neither
+    /// imported nor already known.
+    ///
+    /// If imports are checkpointed, the batch is recorded with the checkpointer, which persists the
+    /// chain once a checkpoint is due.
+    pub fn process_chain_segment(
+        &mut self,
+        blocks: &[BeaconBlock],
+        present_slot: u64,
+    ) -> Result<Vec<BlockProcessingOutcome>, BeaconNodeError> {
+        let mut outcomes = Vec::with_capacity(blocks.len());
+        let mut last_imported = None;
+        for block in blocks {
+            let outcome = self.process_block(block, present_slot)?;
+            let processed = match outcome {
+                BlockProcessingOutcome::Imported => {
+                    last_imported = Some(block.canonical_root());
+                    true
+                }
+                BlockProcessingOutcome::AlreadyKnown => true,
+                _ => false,
+            };
+            outcomes.push(outcome);
+            if !processed {
+                break;
+            }
+        }
+        if let Some(root) = last_imported {
+            if let Some(mut checkpointer) = self.import_checkpointer.take() {
+                let checkpointed = checkpointer.on_batch_imported(self, root);
+                self.import_checkpointer = Some(checkpointer);
+                checkpointed?;
+            }
+        }
+        Ok(outcomes)
+    }
+
+    /// Imports `block` as `process_block` does, also returning each step of the import and the
+    /// time it took, in order.
+    pub fn process_block_traced(
+        &mut self,
+        block: &BeaconBlock,
+        present_slot: u64,
+    ) -> (
+        Result<BlockProcessingOutcome, BeaconNodeError>,
+        Vec<ImportStep>,
+    ) {
+        self.import_trace = Some(vec![]);
+        let outcome = self.process_block(block, present_slot);
+        (outcome, self.import_trace.take().unwrap_or_default())
+    }
+
+    /// Records a step of the import of a block which began at `started`, if it is traced.
+    fn trace<F: FnOnce() -> String>(
+        &mut self,
+        operation: &'static str,
+        started: Instant,
+        detail: F,
+    ) {
+        if let Some(trace) = self.import_trace.as_mut() {
+            trace.push(ImportStep {
+                operation,
+                duration: started.elapsed(),
+                detail: detail(),
+            });
+        }
+    }
+
+    /// Re-imports the stored blocks between the heads and `tip`, e.g. those imported after the
+    /// chain was last persisted and before a crash, returning the number re-imported.
+    ///
+    /// Nothing is re-imported if `tip` does not descend from one of the heads.
+    pub fn replay_to(&mut self, tip: Hash256) -> Result<usize, BeaconNodeError> {
+        let mut min_head_slot = u64::MAX;
+        for head in &self.head_block_hashes {
+            min_head_slot = min_head_slot.min(self.block(head)?.slot);
+        }
+        let mut to_replay = vec![];
+        let mut root = tip;
+        while !self.head_block_hashes.contains(&root) {
+            if !self.store.block_exists(&root)? {
+                return Ok(0);
+            }
+            let block = self.block(&root)?;
+            let parent = match block.parent_hash() {
+                Some(parent) if block.slot > min_head_slot => *parent,
+                _ => return Ok(0),
+            };
+            to_replay.push((root, parent, block));
+            root = parent;
+        }
+        for (root, parent, block) in to_replay.iter().rev() {
+            self.import_block(*root, *parent, block)?;
+        }
+        Ok(to_replay.len())
+    }
+
+    /// Runs fork choice and the accounting of the chain over `block`, which is stored and whose
+    /// parent is known.
+    fn import_block(
+        &mut self,
+        root: Hash256,
+        parent: Hash256,
+        block: &BeaconBlock,
+    ) -> Result<(), BeaconNodeError> {
+        let started = Instant::now();
+        self.fork_choice.process_block(root, block);
+        self.trace("fork_choice_block", started, || format!("root {:?}", root));
+        let mut attestations = Vec::with_capacity(block.attestations.len());
+        for attestation in &block.attestations {
+            let started = Instant::now();
+            self.record_liveness(attestation);
+            let participants = self.participants(attestation);
+            self.fork_choice
+                .process_attestation(&participants, &attestation.data);
+            let data = &attestation.data;
+            let count = participants.len();
+            self.trace("attestation", started, || {
+                format!(
+                    "slot {} shard {} block {:?} participants {}",
+                    data.slot, data.shard, data.beacon_block_hash, count
+                )
+            });
+            attestations.push((attestation.data.slot, participants));
+        }
+        let started = Instant::now();
+        self.participation.record(
+            block.slot,
+            &attestations,
+            u64::from(self.config.cycle_length.max(1)),
+        );
+        self.trace("participation", started, String::new);
+
+        /*
+         * The block replaces its parent as the tip of its chain.
+         */
+        self.head_block_hashes.retain(|hash| *hash != parent);
+        self.head_block_hashes.push(root);
+        let started = Instant::now();
+        let fork_choice_timer = start_timer(&metrics::FORK_CHOICE_TIMES);
+        let index = self
+            .fork_choice
+            .find_head(&self.head_block_hashes, &self.store)?;
+        stop_timer(fork_choice_timer);
+        let head_root = self.head_block_hashes[index];
+        let heads = self.head_block_hashes.len();
+        self.trace("find_head", started, || {
+            format!("head {:?} of {} heads", head_root, heads)
+        });
+        let started = Instant::now();
+        let event = BeaconNodeEvent::Block {
+            slot: block.slot,
+            root,
+            proposer: self.block_proposer(block.slot),
+            attestations,
+        };
+        self.publish(event);
+        self.trace("publish", started, String::new);
+        if head_root != self.head_root {
+            let started = Instant::now();
+            let old_head_root = self.head_root;
+            self.update_head(head_root)?;
+            self.trace("update_head", started, || {
+                format!("{:?} to {:?}", old_head_root, head_root)
+            });
+        }
+
+        /*
+         * Attestations included in the block, or too old to be included in the next block, are
+         * removed from the pool.
+         */
+        let min_slot = self
+            .head_slot
+            .saturating_sub(u64::from(self.config.cycle_length));
+        let started = Instant::now();
+        self.attestations
+            .retain(|a| a.data.slot >= min_slot && !block.attestations.contains(a));
+        self.specials.retain(|s| !block.specials.contains(s));
+        let pooled = self.attestations.len();
+        self.trace("op_pool", started, || {
+            format!("{} attestations pooled", pooled)
+        });
+        Ok(())
+    }
+
+    /// Sets the head to `head_root`, publishing the change and any reorg.
+    fn update_head(&mut self, head_root: Hash256) -> Result<(), BeaconNodeError> {
+        let old_head = self.block(&self.head_root)?;
+        let head = self.block(&head_root)?;
+        let ancestor_slot = self
+            .block(&self.common_ancestor(self.head_root, head_root)?)?
+            .slot;
+        if ancestor_slot != old_head.slot {
+            self.publish(BeaconNodeEvent::ChainReorg {
+                slot: head.slot,
+                depth: old_head.slot - ancestor_slot,
+                old_head_root: self.head_root,
+                new_head_root: head_root,
+                old_head_state_root: old_head.crystallized_state_root,
+                new_head_state_root: head.crystallized_state_root,
+            });
+        }
+
+        let cycle_length = u64::from(self.config.cycle_length.max(1));
+        if head.slot / cycle_length > old_head.slot / cycle_length {
+            self.record_registry_delta(old_head.slot / cycle_length);
+            self.account_participation(head.slot / cycle_length, head_root)?;
+        }
+        self.publish(BeaconNodeEvent::Head {
+            slot: head.slot,
+            root: head_root,
+            state_root: head.crystallized_state_root,
+            cycle_transition: head.slot / cycle_length != old_head.slot / cycle_length,
+        });
+        self.head_slot = head.slot;
+        self.head_root = head_root;
+        Ok(())
+    }
+
+    /// Delivers `event` to the metrics and the validator monitor, then to the event handler.
+    ///
+    /// Fork choice is persisted and a light client update is stored when a checkpoint is
+    /// finalized, if stores were given.
+    fn publish(&mut self, event: BeaconNodeEvent) {
+        metrics::observe_event(&event);
+        if let BeaconNodeEvent::FinalizedCheckpoint { root, cycle, .. } = event {
+            if let Some(store) = self.finalization_store.as_ref() {
+                if self.persist_fork_choice(store).is_err() {
+                    inc_counter(&metrics::FORK_CHOICE_PERSIST_FAILURES);
+                }
+            }
+            if self.store_light_client_update(root, cycle).is_err() {
+                inc_counter(&metrics::LIGHT_CLIENT_UPDATE_FAILURES);
+            }
+        }
+        if let Some(monitor) = self.validator_monitor.as_mut() {
+            monitor.process_event(&event, &self.validators);
+        }
+        self.events.publish(event);
+    }
+
+    /// Returns the latest block from which both `a` and `b` descend.
+    fn common_ancestor(&self, mut a: Hash256, mut b: Hash256) -> Result<Hash256, BeaconNodeError> {
+        let (mut a_block, mut b_block) = (self.block(&a)?, self.block(&b)?);
+        while a != b {
+            /*
+             * Step back along whichever chain is at the later slot, or both if level.
+             */
+            let (step_a, step_b) = (a_block.slot >= b_block.slot, b_block.slot >= a_block.slot);
+            if step_a {
+                a = *a_block
+                    .parent_hash()
+                    .ok_or(BeaconNodeError::ForkChoiceFailed)?;
+                a_block = self.block(&a)?;
+            }
+            if step_b {
+                b = *b_block
+                    .parent_hash()
+                    .ok_or(BeaconNodeError::ForkChoiceFailed)?;
+                b_block = self.block(&b)?;
+            }
+        }
+        Ok(a)
+    }
+
+    /// Returns the data to be signed by members of the committee of `shard` at `slot`.
+    ///
+    /// Only the cycle of the latest justified checkpoint is tracked, so the finalized checkpoint
+    /// is given as the justified checkpoint.
+    pub fn produce_attestation_data(
+        &self,
+        slot: u64,
+        shard: u64,
+    ) -> Result<AttestationData, BeaconNodeError> {
+        if self.committee(slot, shard).is_none() {
+            return Err(BeaconNodeError::UnknownCommittee);
+        }
+        Ok(AttestationData {
+            slot,
+            shard,
+            beacon_block_hash: self.head_root,
+            justified_slot: self.finalized_cycle * u64::from(self.config.cycle_length),
+            justified_block_hash: self.finalized_root(),
+            ..AttestationData::zero()
+        })
+    }
+
+    /// Adds `attestation` to the pool, if it is from a known committee.
+    ///
+    /// Signatures are not verified until the state transition is restored.
+    pub fn process_attestation(
+        &mut self,
+        attestation: Attestation,
+        present_slot: u64,
+    ) -> Result<AttestationOutcome, BeaconNodeError> {
+        if attestation.data.slot > present_slot {
+            return Ok(AttestationOutcome::FutureSlot);
+        }
+        let committee_len = match self.committee(attestation.data.slot, attestation.data.shard) {
+            Some(committee) => committee.len(),
+            None => return Ok(AttestationOutcome::UnknownCommittee),
+        };
+        match attestation.participation_bitfield.highest_set_bit() {
+            Some(i) if i < committee_len => {}
+            _ => return Ok(AttestationOutcome::InvalidParticipants),
+        }
+        if self.attestations.contains(&attestation) {
+            return Ok(AttestationOutcome::AlreadyKnown);
+        }
+        self.record_liveness(&attestation);
+        let participants = self.participants(&attestation);
+        self.fork_choice
+            .process_attestation(&participants, &attestation.data);
+        self.attestations.push(attestation.clone());
+        self.publish(BeaconNodeEvent::Attestation {
+            attestation,
+            participants,
+        });
+        Ok(AttestationOutcome::Pooled)
+    }
+
+    pub fn block(&self, root: &Hash256) -> Result<BeaconBlock, BeaconNodeError> {
+        let ssz = self
+            .store
+            .get_serialized_block(root)?
+            .ok_or_else(|| BeaconNodeError::DBError("Missing block".to_string()))?;
+        BeaconBlock::ssz_decode(&ssz, 0)
+            .map(|(block, _)| block)
+            .map_err(|_| BeaconNodeError::DBError("Invalid block".to_string()))
+    }
+
+    /// What is known of the attestations of each validator from the blocks imported.
+    pub fn participation(&self) -> &ParticipationTracker {
+        &self.participation
+    }
+
+    /// Restores the participation of the validators persisted in `store`, and writes it to
+    /// `store` as each cycle is accounted. Returns the number of validators restored.
+    ///
+    /// The records of the cycles accounted are kept for `retention_cycles` cycles; those older,
+    /// e.g. written by an earlier run with a longer retention, are pruned now.
+    pub fn persist_participation(
+        &mut self,
+        store: ParticipationStore<T>,
+        retention_cycles: u64,
+    ) -> Result<usize, BeaconNodeError> {
+        let persisted = store.all_serialized_participation()?;
+        for (index, ssz) in &persisted {
+            let (participation, _) = ValidatorParticipation::ssz_decode(ssz, 0)
+                .map_err(|_| BeaconNodeError::DBError("Invalid participation".to_string()))?;
+            self.participation.restore(*index as usize, participation);
+        }
+        let head_cycle = self.head_slot / u64::from(self.config.cycle_length.max(1));
+        store.prune_cycles_before(head_cycle.saturating_sub(retention_cycles))?;
+        self.participation_retention_cycles = retention_cycles;
+        self.participation_store = Some(store);
+        Ok(persisted.len())
+    }
+
+    /// Restores the index of each validator by public key from `store`, and writes the indices
+    /// of validators added to the registry to it. Returns the number of indices restored.
+    pub fn persist_validator_indices(
+        &mut self,
+        store: ValidatorStore<T>,
+    ) -> Result<usize, BeaconNodeError> {
+        Ok(self.validator_indices.load(store, &self.validators)?)
+    }
+
+    /// Persists fork choice to `store` each time a checkpoint is finalized, as well as on
+    /// `persist`.
+    pub fn persist_on_finalization(&mut self, store: ChainStore<T>) {
+        self.finalization_store = Some(store);
+    }
+
+    /// Persists the chain to `store` each time the head advances `interval_slots` past the last
+    /// checkpoint while batches are imported by `process_chain_segment`, rather than after every
+    /// batch.
+    pub fn checkpoint_imports(&mut self, store: ChainStore<T>, interval_slots: u64) {
+        self.import_checkpointer = Some(ImportCheckpointer::new(store, interval_slots, self));
+    }
+
+    /// Writes a `LightClientUpdate` to `store` each time a checkpoint is finalized, replacing
+    /// that of any earlier checkpoint of the same period.
+    pub fn store_light_client_updates(&mut self, store: LightClientStore<T>) {
+        self.light_client_store = Some(store);
+    }
+
+    /// Returns the update from which light clients learn of the finalized block with `root`, of
+    /// `cycle`.
+    ///
+    /// The proofs are against the same summary of the state after the block as the proofs of the
+    /// HTTP API.
+    pub fn light_client_update(
+        &self,
+        root: Hash256,
+        cycle: u64,
+    ) -> Result<LightClientUpdate, BeaconNodeError> {
+        let block = self.block(&root)?;
+        let validators = validators_tree(&self.validators);
+        let summary = state_summary(block.slot, root, self.finalized_root(), &validators);
+        Ok(LightClientUpdate {
+            period: cycle / LIGHT_CLIENT_PERIOD_CYCLES,
+            finalized_header: LightClientHeader {
+                slot: block.slot,
+                root,
+                parent_root: block.parent_hash().cloned().unwrap_or_else(Hash256::zero),
+                state_root: block.crystallized_state_root,
+            },
+            summary_root: summary.root(),
+            header_branch: summary
+                .branch(BLOCK_ROOT_GINDEX)
+                .expect("Summary has a block root"),
+            validators_root: validators.root(),
+            validators_branch: summary
+                .branch(VALIDATORS_GINDEX)
+                .expect("Summary has a validators root"),
+        })
+    }
+
+    /// Returns the stored light client updates of at most `count` periods from `start_period`,
+    /// by ascending period, skipping periods in which nothing was finalized.
+    pub fn light_client_updates(
+        &self,
+        start_period: u64,
+        count: u64,
+    ) -> Result<Vec<LightClientUpdate>, BeaconNodeError> {
+        let store = self
+            .light_client_store
+            .as_ref()
+            .ok_or(BeaconNodeError::MissingStore)?;
+        store
+            .serialized_updates(start_period, count)?
+            .into_iter()
+            .map(|(_, ssz)| {
+                LightClientUpdate::ssz_decode(&ssz, 0)
+                    .map(|(update, _)| update)
+                    .map_err(|_| {
+                        BeaconNodeError::DBError("Invalid light client update".to_string())
+                    })
+            })
+            .collect()
+    }
+
+    /// Writes the update of the finalized block with `root` to the light client store, if any.
+    fn store_light_client_update(&self, root: Hash256, cycle: u64) -> Result<(), BeaconNodeError> {
+        if let Some(store) = self.light_client_store.as_ref() {
+            let update = self.light_client_update(root, cycle)?;
+            store.put_serialized_update(update.period, &ssz_encode(&update))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the blocks and votes known to fork choice to `store`, if it has its own view of
+    /// them.
+    pub fn persist_fork_choice(&self, store: &ChainStore<T>) -> Result<(), BeaconNodeError> {
+        if let Some(ssz) = self.fork_choice.as_ssz() {
+            store.put_serialized_fork_choice(&ssz)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the heads of the chain, fork choice, the pooled operations and the weak
+    /// subjectivity checkpoint to `store`, to be restored on the next start.
+    pub fn persist(&self, store: &ChainStore<T>) -> Result<(), BeaconNodeError> {
+        let head = PersistedHead {
+            head_slot: self.head_slot,
+            head_root: self.head_root,
+            heads: self.head_block_hashes.clone(),
+        };
+        let op_pool = PersistedOpPool {
+            attestations: self.attestations.clone(),
+            specials: self.specials.clone(),
+        };
+        store.put_serialized_head(&ssz_encode(&head))?;
+        store.put_serialized_op_pool(&ssz_encode(&op_pool))?;
+        self.persist_fork_choice(store)?;
+        if let Some(checkpoint) = self.weak_subjectivity_checkpoint {
+            store.put_serialized_weak_subjectivity(&ssz_encode(&checkpoint))?;
+        }
+        Ok(())
+    }
+
+    /// Restores the heads of the chain, fork choice and the pooled operations persisted in
+    /// `store`, returning `false` if nothing was persisted.
+    ///
+    /// The heads are only restored if their blocks are in the block store, and the participants
+    /// of the pooled attestations are recorded as live. If fork choice was not persisted, it is
+    /// replayed from the blocks of the restored chains. The weak subjectivity checkpoint is
+    /// restored first, so that it is kept even if the heads cannot be.
+    pub fn restore(&mut self, store: &ChainStore<T>) -> Result<bool, BeaconNodeError> {
+        if let Some(ssz) = store.get_serialized_weak_subjectivity()? {
+            let (checkpoint, _) =
+                WeakSubjectivityCheckpoint::ssz_decode(&ssz, 0).map_err(|_| {
+                    BeaconNodeError::DBError("Invalid weak subjectivity checkpoint".to_string())
+                })?;
+            self.weak_subjectivity_checkpoint = Some(checkpoint);
+        }
+        let head = match store.get_serialized_head()? {
+            Some(ssz) => PersistedHead::ssz_decode(&ssz, 0)
+                .map(|(head, _)| head)
+                .map_err(|_| BeaconNodeError::DBError("Invalid persisted head".to_string()))?,
+            None => return Ok(false),
+        };
+        for root in &head.heads {
+            if !self.store.block_exists(root)? {
+                return Err(BeaconNodeError::DBError("Missing head block".to_string()));
+            }
+        }
+        if !head.heads.contains(&head.head_root) {
+            return Err(BeaconNodeError::DBError(
+                "Invalid persisted head".to_string(),
+            ));
+        }
+        self.head_slot = head.head_slot;
+        self.head_root = head.head_root;
+        self.head_block_hashes = head.heads;
+        set_gauge(&metrics::HEAD_SLOT, self.head_slot as i64);
+
+        if self.fork_choice.is_stateful() {
+            let restored = match store.get_serialized_fork_choice()? {
+                Some(ssz) => self.fork_choice.restore(&ssz).is_ok(),
+                None => false,
+            };
+            if !restored {
+                self.replay_fork_choice()?;
+            }
+        }
+
+        if let Some(ssz) = store.get_serialized_op_pool()? {
+            let op_pool = PersistedOpPool::ssz_decode(&ssz, 0)
+                .map(|(op_pool, _)| op_pool)
+                .map_err(|_| BeaconNodeError::DBError("Invalid persisted op pool".to_string()))?;
+            for attestation in &op_pool.attestations {
+                self.record_liveness(attestation);
+                self.fork_choice
+                    .process_attestation(&self.participants(attestation), &attestation.data);
+            }
+            self.attestations = op_pool.attestations;
+            self.specials = op_pool.specials;
+        }
+        Ok(true)
+    }
+
+    /// Records the blocks of every known chain with fork choice, parents first, together with the
+    /// votes they include.
+    ///
+    /// Every block back to genesis is read from the store, which persisting fork choice avoids.
+    fn replay_fork_choice(&self) -> Result<(), BeaconNodeError> {
+        let mut blocks = HashMap::new();
+        for head in &self.head_block_hashes {
+            let mut root = *head;
+            while root != self.genesis_root && !blocks.contains_key(&root) {
+                let block = self.block(&root)?;
+                let parent = *block
+                    .parent_hash()
+                    .ok_or(BeaconNodeError::ForkChoiceFailed)?;
+                blocks.insert(root, block);
+                root = parent;
+            }
+        }
+        let mut blocks: Vec<(Hash256, BeaconBlock)> = blocks.into_iter().collect();
+        blocks.sort_by_key(|(_, block)| block.slot);
+        for (root, block) in &blocks {
+            self.fork_choice.process_block(*root, block);
+            for attestation in &block.attestations {
+                self.fork_choice
+                    .process_attestation(&self.participants(attestation), &attestation.data);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the index of the validator which proposes the block at `slot`, given the committees of
+/// the slot.
+pub(super) fn proposer(committees: &[ShardAndCommittee], slot: u64) -> Option<usize> {
+    let committee = &committees.first()?.committee;
+    if committee.is_empty() || slot == 0 {
+        return None;
+    }
+    Some(committee[slot as usize % committee.len()])
+}
+
+fn duration_as_secs_f64(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::participation::CycleParticipation;
+    use super::super::rewards::includer_reward;
+    use super::super::validator_monitor::MonitoredAttestation;
+    use super::*;
+    use bls::Keypair;
+    use db::MemoryDB;
+    use merkle_proof::verify_merkle_proof;
+    use slot_clock::TestingSlotClock;
+    use ssz::decode_ssz_list;
+    use types::{Bitfield, ValidatorRegistration};
+
+    /// A config with two slots per cycle and one committee per slot.
+    pub fn test_config(validator_count: usize) -> ChainConfig {
+        let mut config = ChainConfig::standard();
+        config.cycle_length = 2;
+        config.shard_count = 2;
+        config.min_committee_size = 2;
+        config.min_attestation_inclusion_delay = 1;
+        config.initial_validators = (0..validator_count)
+            .map(|_| ValidatorRegistration::random())
+            .collect();
+        config
+    }
+
+    pub fn test_node(validator_count: usize) -> BeaconNode<MemoryDB> {
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        BeaconNode::new(test_config(validator_count), store).unwrap()
+    }
+
+    fn attestation(node: &BeaconNode<MemoryDB>, slot: u64, participant: usize) -> Attestation {
+        let shard = u64::from(node.committees(slot)[0].shard);
+        let mut attestation = Attestation::zero();
+        attestation.data = node.produce_attestation_data(slot, shard).unwrap();
+        attestation.participation_bitfield = Bitfield::from_elem(8, false);
+        attestation.participation_bitfield.set(participant, true);
+        attestation
+    }
+
+    #[test]
+    fn test_new_requires_validators() {
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        assert_eq!(
+            BeaconNode::new(test_config(0), store).err(),
+            Some(BeaconNodeError::InsufficientValidators)
+        );
+    }
+
+    #[test]
+    fn test_slot_clock() {
+        let mut config = test_config(4);
+        config.slot_duration_millis = 0;
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        assert_eq!(
+            BeaconNode::new(config, store).err(),
+            Some(BeaconNodeError::InvalidSlotDuration)
+        );
+
+        let mut node = test_node(4);
+        let clock = Arc::new(TestingSlotClock::new(100, 6_000).unwrap());
+        node.set_slot_clock(clock.clone());
+        clock.set_now(Duration::from_secs(99));
+        assert_eq!(node.present_slot(), 0);
+
+        clock.set_slot(3);
+        clock.advance(Duration::from_millis(5_700));
+        assert_eq!(node.present_slot(), 3);
+        assert_eq!(node.present_slot_with_future_tolerance(), 4);
+        assert_eq!(node.present_slot_with_past_tolerance(), 3);
+
+        node.set_clock_disparity(Duration::from_secs(0));
+        assert_eq!(node.present_slot_with_future_tolerance(), 3);
+        node.set_clock_disparity(Duration::from_secs(6));
+        assert_eq!(node.present_slot_with_past_tolerance(), 2);
+    }
+
+    #[test]
+    fn test_arrival_delays() {
+        let mut node = test_node(4);
+        let clock = Arc::new(TestingSlotClock::new(100, 6_000).unwrap());
+        node.set_slot_clock(clock.clone());
+        clock.set_slot(3);
+        clock.advance(Duration::from_millis(1_500));
+
+        let arrival = node.observe_block_arrival(3, 1);
+        assert_eq!(arrival.delay, Duration::from_millis(1_500));
+        assert!(!arrival.late);
+        clock.advance(Duration::from_secs(1));
+        assert!(node.observe_block_arrival(3, 2).late);
+        assert_eq!(
+            node.observe_aggregate_arrival(3),
+            Duration::from_millis(2_500)
+        );
+
+        /*
+         * Blocks from a slot which has not yet started arrive with no delay.
+         */
+        let arrival = node.observe_block_arrival(4, 1);
+        assert_eq!(arrival.delay, Duration::from_secs(0));
+        assert_eq!(arrival.worst_slot, 3);
+        assert_eq!(arrival.worst_delay, Duration::from_millis(1_500));
+    }
+
+    #[test]
+    fn test_produce_and_process_blocks() {
+        let mut node = test_node(8);
+        let genesis_root = node.genesis_root();
+        assert_eq!(node.block_proposer(0), None);
+        assert!(node.block_proposer(1).is_some());
+
+        let first = node
+            .produce_block(1, Hash256::from(1), Hash256::from(2))
+            .unwrap();
+        assert_eq!(first.parent_hash(), Some(&genesis_root));
+        assert_eq!(first.graffiti, Hash256::from(2));
+        assert_eq!(
+            node.process_block(&first, 0),
+            Ok(BlockProcessingOutcome::FutureSlot)
+        );
+        assert_eq!(
+            node.process_block(&first, 1),
+            Ok(BlockProcessingOutcome::Imported)
+        );
+        assert_eq!(node.head(), (1, first.canonical_root()));
+        assert_eq!(
+            node.process_block(&first, 1),
+            Ok(BlockProcessingOutcome::AlreadyKnown)
+        );
+        assert_eq!(
+            node.produce_block(1, Hash256::zero(), Hash256::zero())
+                .err(),
+            Some(BeaconNodeError::SlotNotAfterHead)
+        );
+
+        /*
+         * A competing block at a later slot on genesis becomes the head.
+         */
+        let mut fork = BeaconBlock::zero();
+        fork.slot = 2;
+        fork.ancestor_hashes.push(genesis_root);
+        assert_eq!(
+            node.process_block(&fork, 2),
+            Ok(BlockProcessingOutcome::Imported)
+        );
+        assert_eq!(node.head(), (2, fork.canonical_root()));
+
+        let mut orphan = BeaconBlock::zero();
+        orphan.slot = 3;
+        orphan.ancestor_hashes.push(Hash256::from(42));
+        assert_eq!(
+            node.process_block(&orphan, 3),
+            Ok(BlockProcessingOutcome::UnknownParent)
+        );
+        let mut stale = BeaconBlock::zero();
+        stale.ancestor_hashes.push(fork.canonical_root());
+        assert_eq!(
+            node.process_block(&stale, 3),
+            Ok(BlockProcessingOutcome::InvalidSlot)
+        );
+    }
+
+    #[test]
+    fn test_head_and_reorg_events() {
+        let mut node = test_node(8);
+        let events = node.events().subscribe();
+        let genesis_root = node.genesis_root();
+
+        let first = node
+            .produce_block(1, Hash256::from(1), Hash256::zero())
+            .unwrap();
+        node.process_block(&first, 1).unwrap();
+        let first_root = first.canonical_root();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                BeaconNodeEvent::Block {
+                    slot: 1,
+                    root: first_root,
+                    proposer: node.block_proposer(1),
+                    attestations: vec![],
+                },
+                BeaconNodeEvent::Head {
+                    slot: 1,
+                    root: first_root,
+                    state_root: first.crystallized_state_root,
+                    cycle_transition: false,
+                },
+            ]
+        );
+
+        let mut fork = BeaconBlock::zero();
+        fork.slot = 2;
+        fork.ancestor_hashes.push(genesis_root);
+        node.process_block(&fork, 2).unwrap();
+        let fork_root = fork.canonical_root();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                BeaconNodeEvent::Block {
+                    slot: 2,
+                    root: fork_root,
+                    proposer: node.block_proposer(2),
+                    attestations: vec![],
+                },
+                BeaconNodeEvent::ChainReorg {
+                    slot: 2,
+                    depth: 1,
+                    old_head_root: first_root,
+                    new_head_root: fork_root,
+                    old_head_state_root: first.crystallized_state_root,
+                    new_head_state_root: fork.crystallized_state_root,
+                },
+                BeaconNodeEvent::RegistryDelta(RegistryDelta::default()),
+                BeaconNodeEvent::Head {
+                    slot: 2,
+                    root: fork_root,
+                    state_root: fork.crystallized_state_root,
+                    cycle_transition: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_registry_deltas() {
+        let mut node = test_node(8);
+        let cycle_length = u64::from(node.config().cycle_length);
+        let events = node.events().subscribe();
+
+        /*
+         * The registry is fixed until the state transition is restored, so it is changed here
+         * as a block would change it.
+         */
+        node.validators[1].balance += 5;
+        node.validators[2].status = ValidatorStatus::PendingExit as u8;
+        let block = node
+            .produce_block(cycle_length - 1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&block, cycle_length - 1).unwrap();
+        assert!(node.registry_deltas().is_empty());
+
+        let block = node
+            .produce_block(cycle_length, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&block, cycle_length).unwrap();
+        let delta = RegistryDelta {
+            cycle: 0,
+            exits: vec![2],
+            top_ups: vec![(1, 5)],
+            ..RegistryDelta::default()
+        };
+        assert_eq!(node.registry_deltas(), &[delta.clone()][..]);
+        assert!(events
+            .try_iter()
+            .any(|event| event == BeaconNodeEvent::RegistryDelta(delta.clone())));
+
+        /*
+         * Each delta is of a single cycle, and only the recent cycles are remembered.
+         */
+        for cycle in 2..(2 + REGISTRY_DELTA_CYCLES as u64) {
+            let slot = cycle * cycle_length;
+            let block = node
+                .produce_block(slot, Hash256::zero(), Hash256::zero())
+                .unwrap();
+            node.process_block(&block, slot).unwrap();
+        }
+        let deltas = node.registry_deltas();
+        assert_eq!(deltas.len(), REGISTRY_DELTA_CYCLES);
+        assert_eq!(deltas[0].cycle, 1);
+        assert!(deltas.iter().all(|delta| delta.is_empty()));
+    }
+
+    #[test]
+    fn test_validator_indices() {
+        let db = Arc::new(MemoryDB::open());
+        let config = test_config(8);
+        let mut node =
+            BeaconNode::new(config.clone(), Arc::new(BeaconBlockStore::new(db.clone()))).unwrap();
+        let pubkey = node.validators()[5].pubkey.clone();
+        assert_eq!(node.validator_index(&pubkey), Some(5));
+        assert_eq!(node.validator_index(&Keypair::random().pk), None);
+        assert_eq!(
+            node.persist_validator_indices(ValidatorStore::new(db.clone())),
+            Ok(0)
+        );
+        assert_eq!(
+            ValidatorStore::new(db.clone()).get_index_by_public_key(&pubkey),
+            Ok(Some(5))
+        );
+
+        let mut restarted =
+            BeaconNode::new(config, Arc::new(BeaconBlockStore::new(db.clone()))).unwrap();
+        assert_eq!(
+            restarted.persist_validator_indices(ValidatorStore::new(db)),
+            Ok(8)
+        );
+        assert_eq!(restarted.validator_index(&pubkey), Some(5));
+    }
+
+    #[test]
+    fn test_participation() {
+        let db = Arc::new(MemoryDB::open());
+        let mut node = test_node(8);
+        assert_eq!(
+            node.persist_participation(ParticipationStore::new(db.clone()), 2),
+            Ok(0)
+        );
+        let attestation = attestation(&node, 0, 0);
+        let attester = node.committee(0, attestation.data.shard).unwrap()[0];
+        node.monitor_validators(
+            &[ValidatorId::Index(attester)],
+            Logger::root(slog::Discard, o!()),
+        );
+        node.process_attestation(attestation, 0).unwrap();
+        for slot in &[1, 2, 4] {
+            let block = node
+                .produce_block(*slot, Hash256::zero(), Hash256::zero())
+                .unwrap();
+            node.process_block(&block, *slot).unwrap();
+        }
+
+        /*
+         * Cycle 0 is accounted once the head leaves cycle 1, and every other committee member
+         * missed it.
+         */
+        let members: BTreeSet<usize> = (0..2)
+            .flat_map(|slot| node.committees(slot))
+            .flat_map(|committee| committee.committee.iter().cloned())
+            .collect();
+        assert_eq!(
+            node.participation().participation_rate(),
+            Some((0, 1.0 / members.len() as f64))
+        );
+        let participation = node.participation().validator(attester).unwrap().clone();
+        assert_eq!(participation.attestations_included, 1);
+        assert_eq!(participation.total_inclusion_distance, 1);
+        let other = *members.iter().find(|index| **index != attester).unwrap();
+        assert_eq!(
+            node.participation().validator(other).unwrap().missed_streak,
+            1
+        );
+
+        let mut restored = test_node(8);
+        assert_eq!(
+            restored.persist_participation(ParticipationStore::new(db.clone()), 2),
+            Ok(members.len())
+        );
+        assert_eq!(
+            restored.participation().validator(attester),
+            Some(&participation)
+        );
+
+        /*
+         * The records of each cycle accounted are kept for the two latest cycles.
+         */
+        let store = ParticipationStore::new(db);
+        let (cycle, _) =
+            CycleParticipation::ssz_decode(&store.get_serialized_cycle(0).unwrap().unwrap(), 0)
+                .unwrap();
+        assert_eq!(cycle.committee_members, members.len() as u64);
+        assert_eq!(cycle.attestations_included, 1);
+        let (monitored, _) = decode_ssz_list::<MonitoredAttestation>(
+            &store.get_serialized_monitor_cycle(0).unwrap().unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(monitored[0].inclusion_distance, Some(1));
+        for slot in &[6, 8] {
+            let block = node
+                .produce_block(*slot, Hash256::zero(), Hash256::zero())
+                .unwrap();
+            node.process_block(&block, *slot).unwrap();
+        }
+        assert_eq!(store.get_serialized_cycle(0).unwrap(), None);
+        assert_eq!(store.get_serialized_monitor_cycle(0).unwrap(), None);
+        assert!(store.get_serialized_cycle(2).unwrap().is_some());
+        let (monitored, _) = decode_ssz_list::<MonitoredAttestation>(
+            &store.get_serialized_monitor_cycle(1).unwrap().unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(monitored[0].inclusion_distance, None);
+    }
+
+    #[test]
+    fn test_attestations_pooled_and_included() {
+        let mut node = test_node(8);
+        let events = node.events().subscribe();
+        let attestation = attestation(&node, 0, 0);
+        let attester = node.committee(0, attestation.data.shard).unwrap()[0];
+        assert_eq!(
+            node.process_attestation(attestation.clone(), 0),
+            Ok(AttestationOutcome::Pooled)
+        );
+        assert_eq!(
+            node.process_attestation(attestation.clone(), 0),
+            Ok(AttestationOutcome::AlreadyKnown)
+        );
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![BeaconNodeEvent::Attestation {
+                attestation: attestation.clone(),
+                participants: vec![attester],
+            }]
+        );
+
+        let mut outsider = attestation.clone();
+        outsider.participation_bitfield.set(7, true);
+        assert_eq!(
+            node.process_attestation(outsider, 0),
+            Ok(AttestationOutcome::InvalidParticipants)
+        );
+        let mut unknown = attestation.clone();
+        unknown.data.shard = 1_000;
+        assert_eq!(
+            node.process_attestation(unknown, 0),
+            Ok(AttestationOutcome::UnknownCommittee)
+        );
+
+        /*
+         * The attestation is included once the inclusion delay has passed, and is then removed
+         * from the pool.
+         */
+        let block = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        assert_eq!(block.attestations, vec![attestation]);
+        node.process_block(&block, 1).unwrap();
+        assert!(node.pooled_attestations().is_empty());
+        match events.try_recv() {
+            Ok(BeaconNodeEvent::Block { attestations, .. }) => {
+                assert_eq!(attestations, vec![(0, vec![attester])])
+            }
+            other => panic!("Expected block event, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_produce_block_packs_new_votes() {
+        let mut node = test_node(8);
+        let first = attestation(&node, 0, 0);
+        let second = attestation(&node, 0, 1);
+        let mut overlapping = attestation(&node, 0, 1);
+        overlapping.participation_bitfield.set(0, true);
+        node.process_attestation(first.clone(), 0).unwrap();
+        let block = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&block, 1).unwrap();
+
+        /*
+         * Both attestations add only the vote of the second participant, as that of the first is
+         * already on chain, so only the one pooled first is included.
+         */
+        node.process_attestation(overlapping.clone(), 1).unwrap();
+        node.process_attestation(second, 1).unwrap();
+        let block = node
+            .produce_block(2, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        assert_eq!(block.attestations, vec![overlapping]);
+    }
+
+    #[test]
+    fn test_aggregate_attestation() {
+        let mut node = test_node(8);
+        let first = attestation(&node, 0, 0);
+        let second = attestation(&node, 0, 1);
+        let mut overlapping = attestation(&node, 0, 1);
+        overlapping.participation_bitfield.set(0, true);
+        assert_eq!(node.aggregate_attestation(&first.data), None);
+        for attestation in &[first.clone(), overlapping, second] {
+            node.process_attestation(attestation.clone(), 0).unwrap();
+        }
+
+        let aggregate = node.aggregate_attestation(&first.data).unwrap();
+        assert_eq!(aggregate.data, first.data);
+        assert_eq!(aggregate.participation_bitfield.num_set_bits(), 2);
+        assert_eq!(aggregate.participation_bitfield.get(1), Ok(true));
+
+        /*
+         * The attestation with the most participants is chosen over one pooled before it.
+         */
+        let mut node = test_node(8);
+        let first = attestation(&node, 0, 0);
+        let mut overlapping = attestation(&node, 0, 1);
+        overlapping.participation_bitfield.set(0, true);
+        node.process_attestation(first, 0).unwrap();
+        node.process_attestation(overlapping.clone(), 0).unwrap();
+        assert_eq!(
+            node.aggregate_attestation(&overlapping.data),
+            Some(overlapping)
+        );
+    }
+
+    #[test]
+    fn test_block_reward() {
+        let mut node = test_node(8);
+        let first = attestation(&node, 0, 0);
+        node.process_attestation(first.clone(), 0).unwrap();
+        let block = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        let attester = node.participants(&first)[0];
+        let expected = includer_reward(node.validators()[attester].balance);
+
+        let reward = node.block_reward(&block).unwrap();
+        assert_eq!(reward.root, block.canonical_root());
+        assert_eq!(reward.proposer, node.block_proposer(1));
+        assert_eq!(reward.attestations.len(), 1);
+        assert_eq!(reward.attestations[0].new_votes, 1);
+        assert_eq!(reward.attestations_reward(), expected);
+
+        /*
+         * Once imported, the block is valued as before, while a child including the same
+         * attestation again earns nothing for it.
+         */
+        node.process_block(&block, 1).unwrap();
+        assert_eq!(node.block_reward(&block), Ok(reward));
+        let mut child = node
+            .produce_block(2, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        child.attestations = vec![first];
+        let reward = node.block_reward(&child).unwrap();
+        assert_eq!(reward.attestations_reward(), 0);
+        assert_eq!(reward.redundant_votes(), 1);
+    }
+
+    #[test]
+    fn test_specials_pooled_and_included() {
+        let mut node = test_node(8);
+        let exit = SpecialRecord::logout(&[1]);
+        assert!(node.pool_special(exit.clone()));
+        assert!(!node.pool_special(exit.clone()));
+
+        let block = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        assert_eq!(block.specials, vec![exit]);
+        node.process_block(&block, 1).unwrap();
+        assert!(node.pooled_specials().is_empty());
+    }
+
+    #[test]
+    fn test_proposer_slashings_pooled() {
+        let mut node = test_node(8);
+        let events = node.events().subscribe();
+        let slashing = ProposerSlashing {
+            proposer_index: 3,
+            slot: 1,
+            block_root_1: Hash256::from(1),
+            block_root_2: Hash256::from(2),
+        };
+        assert!(node.pool_proposer_slashing(slashing));
+        assert!(!node.pool_proposer_slashing(ProposerSlashing {
+            block_root_2: Hash256::from(3),
+            ..slashing
+        }));
+        assert_eq!(node.pooled_proposer_slashings(), &[slashing][..]);
+        assert_eq!(
+            events.try_recv(),
+            Ok(BeaconNodeEvent::ProposerSlashing(slashing))
+        );
+        assert!(events.try_recv().is_err());
+
+        for slot in 2..=MAX_POOLED_PROPOSER_SLASHINGS as u64 + 1 {
+            assert!(node.pool_proposer_slashing(ProposerSlashing { slot, ..slashing }));
+        }
+        let pooled = node.pooled_proposer_slashings();
+        assert_eq!(pooled.len(), MAX_POOLED_PROPOSER_SLASHINGS);
+        assert_eq!(pooled[0].slot, 2);
+    }
+
+    #[test]
+    fn test_validator_monitor() {
+        let mut node = test_node(8);
+        let attester = node.committees(0)[0].committee[0];
+        let proposer = node.block_proposer(1).unwrap();
+        node.monitor_validators(
+            &[ValidatorId::Index(attester), ValidatorId::Index(proposer)],
+            Logger::root(slog::Discard, o!()),
+        );
+        let attestation = attestation(&node, 0, 0);
+        node.process_attestation(attestation, 0).unwrap();
+        let block = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&block, 1).unwrap();
+
+        let monitor = node.validator_monitor().unwrap();
+        let monitored = monitor.validator(attester).unwrap();
+        assert_eq!(monitored.attestations_included, 1);
+        assert_eq!(monitored.latest_attestation_slot, Some(0));
+        assert_eq!(monitor.validator(proposer).unwrap().blocks_proposed, 1);
+    }
+
+    #[test]
+    fn test_monitored_withdrawals() {
+        let mut node = test_node(4);
+        assert!(node.monitored_withdrawals().is_empty());
+
+        node.monitor_validators(
+            &[ValidatorId::Index(2), ValidatorId::Index(0)],
+            Logger::root(slog::Discard, o!()),
+        );
+        let reports = node.monitored_withdrawals();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].index, 0);
+        assert_eq!(reports[1].pubkey, node.validators()[2].pubkey);
+        assert_eq!(reports[1].exit_epoch, None);
+    }
+
+    #[test]
+    fn test_liveness() {
+        let mut node = test_node(8);
+        let first = attestation(&node, 0, 0);
+        let second = attestation(&node, 2, 1);
+        let (first_index, second_index) = (
+            node.committees(0)[0].committee[0],
+            node.committees(2)[0].committee[1],
+        );
+        node.process_attestation(first, 0).unwrap();
+        assert!(node.is_live(first_index, 0));
+        assert!(!node.is_live(first_index, 1));
+        assert!(!node.is_live(second_index, 1));
+
+        /*
+         * Attestations first seen in a block are recorded too.
+         */
+        let mut block = BeaconBlock::zero();
+        block.slot = 3;
+        block.ancestor_hashes.push(node.genesis_root());
+        block.attestations.push(second);
+        node.process_block(&block, 3).unwrap();
+        assert!(node.is_live(second_index, 1));
+
+        /*
+         * Only the most recent cycles are remembered.
+         */
+        for cycle in 2..(1 + LIVENESS_CYCLES as u64) {
+            let attestation = attestation(&node, cycle * 2, 0);
+            node.process_attestation(attestation, cycle * 2).unwrap();
+        }
+        assert!(!node.is_live(first_index, 0));
+        assert!(node.is_live(second_index, 1));
+    }
+
+    #[test]
+    fn test_persist_and_restore() {
+        let db = Arc::new(MemoryDB::open());
+        let chain_store = ChainStore::new(db.clone());
+        let config = test_config(8);
+        let new_node = || {
+            let store = Arc::new(BeaconBlockStore::new(db.clone()));
+            BeaconNode::new(config.clone(), store).unwrap()
+        };
+
+        let mut node = new_node();
+        assert_eq!(node.restore(&chain_store), Ok(false));
+        let block = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&block, 1).unwrap();
+        let attestation = attestation(&node, 1, 0);
+        let attester = node.committees(1)[0].committee[0];
+        node.process_attestation(attestation.clone(), 1).unwrap();
+        node.pool_special(SpecialRecord::logout(&[1; 32]));
+        let checkpoint = WeakSubjectivityCheckpoint {
+            slot: 1,
+            root: block.canonical_root(),
+        };
+        node.set_weak_subjectivity_checkpoint(checkpoint);
+        node.persist(&chain_store).unwrap();
+
+        let mut restarted = new_node();
+        assert_eq!(restarted.head(), (0, node.genesis_root()));
+        assert!(!restarted.is_live(attester, 0));
+        assert_eq!(restarted.restore(&chain_store), Ok(true));
+        assert_eq!(restarted.head(), (1, block.canonical_root()));
+        assert_eq!(restarted.heads(), node.heads());
+        assert_eq!(restarted.pooled_attestations(), &[attestation][..]);
+        assert_eq!(restarted.pooled_specials(), node.pooled_specials());
+        assert!(restarted.is_live(attester, 0));
+        assert_eq!(restarted.weak_subjectivity_checkpoint(), Some(checkpoint));
+
+        /*
+         * Heads whose blocks are missing from the store are not restored, but the checkpoint is.
+         */
+        let mut other = test_node(8);
+        assert!(other.restore(&chain_store).is_err());
+        assert_eq!(other.head(), (0, other.genesis_root()));
+        assert_eq!(other.weak_subjectivity_checkpoint(), Some(checkpoint));
+    }
+
+    #[test]
+    fn test_finality() {
+        let mut node = test_node(8);
+        let events = node.events().subscribe();
+        let attest = |node: &mut BeaconNode<MemoryDB>, slot: u64| {
+            let committees: Vec<(u64, usize)> = node
+                .committees(slot)
+                .iter()
+                .map(|c| (u64::from(c.shard), c.committee.len()))
+                .collect();
+            for (shard, len) in committees {
+                let mut attestation = Attestation::zero();
+                attestation.data = node.produce_attestation_data(slot, shard).unwrap();
+                attestation.participation_bitfield = Bitfield::from_elem(len, true);
+                node.process_attestation(attestation, slot).unwrap();
+            }
+        };
+
+        /*
+         * Every committee member attests at every slot, so each cycle is justified once
+         * accounted. Cycle 1 is finalized when cycle 2 is accounted, as the head enters cycle 4.
+         */
+        attest(&mut node, 0);
+        let slashing = |slot: u64| ProposerSlashing {
+            proposer_index: 0,
+            slot,
+            block_root_1: Hash256::from(1),
+            block_root_2: Hash256::from(2),
+        };
+        node.pool_proposer_slashing(slashing(1));
+        node.pool_proposer_slashing(slashing(5));
+        let mut blocks = vec![];
+        for slot in 1..=8 {
+            assert_eq!(node.finalized_root(), node.genesis_root());
+            let block = node
+                .produce_block(slot, Hash256::zero(), Hash256::zero())
+                .unwrap();
+            node.process_block(&block, slot).unwrap();
+            attest(&mut node, slot);
+            blocks.push(block);
+        }
+        let checkpoint = &blocks[1];
+        assert_eq!(node.finalized_cycle(), 1);
+        assert_eq!(node.finalized_root(), checkpoint.canonical_root());
+        assert_eq!(node.pooled_proposer_slashings(), &[slashing(5)][..]);
+        let finalized: Vec<BeaconNodeEvent> = events
+            .try_iter()
+            .filter(|event| match event {
+                BeaconNodeEvent::FinalizedCheckpoint { .. } => true,
+                _ => false,
+            })
+            .collect();
+        assert_eq!(
+            finalized,
+            vec![BeaconNodeEvent::FinalizedCheckpoint {
+                root: checkpoint.canonical_root(),
+                state_root: checkpoint.crystallized_state_root,
+                cycle: 1,
+            }]
+        );
+        let shard = u64::from(node.committees(8)[0].shard);
+        let data = node.produce_attestation_data(8, shard).unwrap();
+        assert_eq!(data.justified_slot, 2);
+        assert_eq!(data.justified_block_hash, checkpoint.canonical_root());
+    }
+
+    #[test]
+    fn test_persist_and_restore_fork_choice() {
+        let db = Arc::new(MemoryDB::open());
+        let chain_store = ChainStore::new(db.clone());
+        let new_node = || {
+            let store = Arc::new(BeaconBlockStore::new(db.clone()));
+            BeaconNode::new(test_config(8), store).unwrap()
+        };
+        let child = |parent: &BeaconBlock, slot| {
+            let mut block = BeaconBlock::zero();
+            block.slot = slot;
+            block.ancestor_hashes.push(parent.canonical_root());
+            block
+        };
+
+        /*
+         * Votes for the first block keep it the head over a later block on another chain.
+         */
+        let mut node = new_node();
+        let genesis = BeaconBlock::zero();
+        let first = child(&genesis, 1);
+        node.process_block(&first, 1).unwrap();
+        for participant in 0..2 {
+            node.process_attestation(attestation(&node, 1, participant), 1)
+                .unwrap();
+        }
+        let fork = child(&genesis, 2);
+        node.process_block(&fork, 2).unwrap();
+        assert_eq!(node.head(), (1, first.canonical_root()));
+        node.persist(&chain_store).unwrap();
+
+        let mut restarted = new_node();
+        assert_eq!(restarted.restore(&chain_store), Ok(true));
+        assert_eq!(restarted.fork_choice.as_ssz(), node.fork_choice.as_ssz());
+        restarted.process_block(&child(&fork, 3), 3).unwrap();
+        assert_eq!(restarted.head(), (1, first.canonical_root()));
+
+        /*
+         * Without a valid persisted fork choice, it is replayed from the blocks and the pool.
+         */
+        chain_store.put_serialized_fork_choice(&[0]).unwrap();
+        let mut replayed = new_node();
+        assert_eq!(replayed.restore(&chain_store), Ok(true));
+        assert_eq!(replayed.fork_choice.as_ssz(), node.fork_choice.as_ssz());
+
+        /*
+         * Fork choice is persisted on finalization, if a store is given.
+         */
+        let finalization_db = Arc::new(MemoryDB::open());
+        node.persist_on_finalization(ChainStore::new(finalization_db.clone()));
+        node.publish(BeaconNodeEvent::FinalizedCheckpoint {
+            root: node.genesis_root(),
+            state_root: Hash256::zero(),
+            cycle: 0,
+        });
+        assert_eq!(
+            ChainStore::new(finalization_db)
+                .get_serialized_fork_choice()
+                .unwrap(),
+            node.fork_choice.as_ssz()
+        );
+    }
+
+    #[test]
+    fn test_light_client_updates() {
+        let mut node = test_node(8);
+        let first = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&first, 1).unwrap();
+        assert_eq!(
+            node.light_client_updates(0, 1),
+            Err(BeaconNodeError::MissingStore)
+        );
+
+        /*
+         * The update of a later checkpoint of the same period replaces the earlier one.
+         */
+        node.store_light_client_updates(LightClientStore::new(Arc::new(MemoryDB::open())));
+        for (root, cycle) in &[
+            (node.genesis_root(), 0),
+            (first.canonical_root(), 1),
+            (first.canonical_root(), LIGHT_CLIENT_PERIOD_CYCLES * 2),
+        ] {
+            node.publish(BeaconNodeEvent::FinalizedCheckpoint {
+                root: *root,
+                state_root: Hash256::zero(),
+                cycle: *cycle,
+            });
+        }
+        let updates = node.light_client_updates(0, 3).unwrap();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(
+            updates[0],
+            node.light_client_update(first.canonical_root(), 1).unwrap()
+        );
+        assert_eq!(updates[1].period, 2);
+        assert_eq!(node.light_client_updates(1, 1).unwrap(), vec![]);
+
+        let update = &updates[0];
+        assert_eq!(update.finalized_header.slot, 1);
+        assert_eq!(update.finalized_header.parent_root, node.genesis_root());
+        assert!(verify_merkle_proof(
+            update.finalized_header.root,
+            &update.header_branch,
+            BLOCK_ROOT_GINDEX,
+            update.summary_root
+        ));
+        assert!(verify_merkle_proof(
+            update.validators_root,
+            &update.validators_branch,
+            VALIDATORS_GINDEX,
+            update.summary_root
+        ));
+        assert_eq!(
+            update.validators_root,
+            validators_tree(node.validators()).root()
+        );
+    }
+
+    #[test]
+    fn test_weak_subjectivity() {
+        let mut node = test_node(8);
+        assert_eq!(node.verify_weak_subjectivity(), Ok(None));
+
+        let first = node
+            .produce_block(1, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&first, 1).unwrap();
+        let second = node
+            .produce_block(3, Hash256::zero(), Hash256::zero())
+            .unwrap();
+        node.process_block(&second, 3).unwrap();
+
+        let mut verify = |slot, root| {
+            node.set_weak_subjectivity_checkpoint(WeakSubjectivityCheckpoint { slot, root });
+            node.verify_weak_subjectivity().unwrap().unwrap()
+        };
+        assert_eq!(
+            verify(1, first.canonical_root()),
+            WeakSubjectivityOutcome::Verified
+        );
+        assert_eq!(
+            verify(3, second.canonical_root()),
+            WeakSubjectivityOutcome::Verified
+        );
+        assert_eq!(verify(4, Hash256::zero()), WeakSubjectivityOutcome::Pending);
+        assert_eq!(
+            verify(1, Hash256::zero()),
+            WeakSubjectivityOutcome::Conflict(Some(first.canonical_root()))
+        );
+        assert_eq!(
+            verify(2, first.canonical_root()),
+            WeakSubjectivityOutcome::Conflict(None)
+        );
+    }
+}
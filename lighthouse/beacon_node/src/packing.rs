@@ -0,0 +1,180 @@
+use std::collections::BTreeSet;
+use types::{Attestation, ValidatorRecord};
+
+/// The most attestations included in a produced block.
+pub const MAX_ATTESTATIONS_PER_BLOCK: usize = 128;
+
+/// The vote of a validator, by the slot and shard attested to and the validator's index.
+pub type Vote = (u64, u64, usize);
+
+/// Chooses up to `max` of `candidates`, each given with the validators attesting to it, to
+/// include in a block.
+///
+/// This is the greedy approximation of maximum coverage: each attestation chosen is the one which
+/// adds the most weight of votes not yet in `included` or in the attestations already chosen,
+/// where a vote weighs the balance of its validator. Ties go to the attestation with the most new
+/// votes, then to the one pooled first. Attestations which add no new votes are never chosen.
+pub fn pack_attestations(
+    candidates: Vec<(Attestation, Vec<usize>)>,
+    included: &BTreeSet<Vote>,
+    validators: &[ValidatorRecord],
+    max: usize,
+) -> Vec<Attestation> {
+    let mut candidates: Vec<(Attestation, Vec<Vote>)> = candidates
+        .into_iter()
+        .map(|(attestation, participants)| {
+            let votes = participants
+                .into_iter()
+                .map(|i| (attestation.data.slot, attestation.data.shard, i))
+                .collect();
+            (attestation, votes)
+        })
+        .collect();
+    let mut covered = included.clone();
+    let mut packed = vec![];
+
+    while packed.len() < max {
+        let mut best: Option<(usize, (u64, usize))> = None;
+        for (i, (_, votes)) in candidates.iter().enumerate() {
+            let score = new_votes_score(votes, &covered, validators);
+            if score.1 == 0 {
+                continue;
+            }
+            match best {
+                Some((_, best_score)) if best_score >= score => {}
+                _ => best = Some((i, score)),
+            }
+        }
+        let (attestation, votes) = match best {
+            Some((i, _)) => candidates.remove(i),
+            None => break,
+        };
+        covered.extend(votes);
+        packed.push(attestation);
+    }
+    packed
+}
+
+/// Returns the total balance and the number of validators of the votes not in `covered`.
+fn new_votes_score(
+    votes: &[Vote],
+    covered: &BTreeSet<Vote>,
+    validators: &[ValidatorRecord],
+) -> (u64, usize) {
+    votes.iter().filter(|vote| !covered.contains(vote)).fold(
+        (0, 0),
+        |(weight, count), (_, _, i)| {
+            let balance = validators.get(*i).map(|v| v.balance).unwrap_or(0);
+            (weight.saturating_add(balance), count + 1)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::AttestationData;
+
+    fn validators(balances: &[u64]) -> Vec<ValidatorRecord> {
+        balances
+            .iter()
+            .map(|balance| {
+                let (mut validator, _) = ValidatorRecord::zero_with_thread_rand_keypair();
+                validator.balance = *balance;
+                validator
+            })
+            .collect()
+    }
+
+    fn attestation(slot: u64, marker: u8) -> Attestation {
+        let mut attestation = Attestation::zero();
+        attestation.data = AttestationData {
+            slot,
+            ..AttestationData::zero()
+        };
+        attestation
+            .participation_bitfield
+            .set(usize::from(marker), true);
+        attestation
+    }
+
+    fn weight(
+        packed: &[Attestation],
+        candidates: &[(Attestation, Vec<usize>)],
+        v: &[ValidatorRecord],
+    ) -> u64 {
+        let mut covered = BTreeSet::new();
+        for attestation in packed {
+            let (_, participants) = candidates.iter().find(|(a, _)| a == attestation).unwrap();
+            for i in participants {
+                covered.insert((attestation.data.slot, attestation.data.shard, *i));
+            }
+        }
+        covered.iter().map(|(_, _, i)| v[*i].balance).sum()
+    }
+
+    #[test]
+    fn test_pack_attestations_max_coverage() {
+        let validators = validators(&[10, 10, 10, 10, 10, 10]);
+        let candidates = vec![
+            (attestation(1, 0), vec![0, 1]),
+            (attestation(1, 1), vec![2, 3]),
+            (attestation(1, 2), vec![0, 1, 2, 3]),
+            (attestation(1, 3), vec![1, 4]),
+            (attestation(2, 4), vec![0]),
+        ];
+
+        /*
+         * With room for two attestations, first-come-first-served packing covers four votes,
+         * where the aggregate and one other cover five.
+         */
+        let packed = pack_attestations(candidates.clone(), &BTreeSet::new(), &validators, 2);
+        assert_eq!(
+            packed,
+            vec![candidates[2].0.clone(), candidates[3].0.clone()]
+        );
+        let first_come: Vec<Attestation> = candidates[..2].iter().map(|(a, _)| a.clone()).collect();
+        assert!(
+            weight(&packed, &candidates, &validators)
+                > weight(&first_come, &candidates, &validators)
+        );
+
+        /*
+         * Attestations whose votes are all covered are left out, however much room remains.
+         */
+        let packed = pack_attestations(candidates.clone(), &BTreeSet::new(), &validators, 10);
+        assert_eq!(
+            packed,
+            vec![
+                candidates[2].0.clone(),
+                candidates[3].0.clone(),
+                candidates[4].0.clone(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pack_attestations_scores() {
+        let validators = validators(&[5, 40, 5, 5, 0, 0]);
+        let candidates = vec![
+            (attestation(1, 0), vec![0, 2, 3]),
+            (attestation(1, 1), vec![1]),
+            (attestation(1, 2), vec![4]),
+            (attestation(1, 3), vec![4, 5]),
+        ];
+
+        /*
+         * Votes are weighed by balance, then counted, and votes already included on chain add
+         * nothing.
+         */
+        let packed = pack_attestations(candidates.clone(), &BTreeSet::new(), &validators, 1);
+        assert_eq!(packed, vec![candidates[1].0.clone()]);
+
+        let included: BTreeSet<Vote> = vec![(1, 0, 1)].into_iter().collect();
+        let packed = pack_attestations(candidates.clone(), &included, &validators, 4);
+        assert_eq!(
+            packed,
+            vec![candidates[0].0.clone(), candidates[3].0.clone()]
+        );
+    }
+}
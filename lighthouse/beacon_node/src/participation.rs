@@ -0,0 +1,317 @@
+use super::metrics;
+use lighthouse_metrics::{observe, set_float_gauge, set_gauge};
+use ssz::{Decodable, DecodeError, Encodable, SszStream};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The number of the latest cycles accounted whose participation is kept in the database.
+pub const DEFAULT_PARTICIPATION_RETENTION_CYCLES: u64 = 1_024;
+
+/// What is known of the attestations of a validator from the blocks imported, over the cycles
+/// accounted.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ValidatorParticipation {
+    /// The number of cycles in which an attestation of the validator was included.
+    pub attestations_included: u64,
+    /// The sum of the inclusion distances of those attestations, in slots.
+    pub total_inclusion_distance: u64,
+    /// The number of consecutive cycles, up to the latest accounted, in which the validator was
+    /// in a committee but no attestation of it was included.
+    pub missed_streak: u64,
+    pub longest_missed_streak: u64,
+}
+
+impl ValidatorParticipation {
+    /// The mean number of slots between an attestation of the validator and the block which
+    /// first included it.
+    pub fn mean_inclusion_distance(&self) -> Option<f64> {
+        if self.attestations_included == 0 {
+            return None;
+        }
+        Some(self.total_inclusion_distance as f64 / self.attestations_included as f64)
+    }
+}
+
+impl Encodable for ValidatorParticipation {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.attestations_included);
+        s.append(&self.total_inclusion_distance);
+        s.append(&self.missed_streak);
+        s.append(&self.longest_missed_streak);
+    }
+}
+
+impl Decodable for ValidatorParticipation {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (attestations_included, i) = u64::ssz_decode(bytes, i)?;
+        let (total_inclusion_distance, i) = u64::ssz_decode(bytes, i)?;
+        let (missed_streak, i) = u64::ssz_decode(bytes, i)?;
+        let (longest_missed_streak, i) = u64::ssz_decode(bytes, i)?;
+        let participation = Self {
+            attestations_included,
+            total_inclusion_distance,
+            missed_streak,
+            longest_missed_streak,
+        };
+        Ok((participation, i))
+    }
+}
+
+/// The participation of the committees of an accounted cycle.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CycleParticipation {
+    pub committee_members: u64,
+    /// The number of committee members whose attestations were included.
+    pub attestations_included: u64,
+    /// The sum of the inclusion distances of those attestations, in slots.
+    pub total_inclusion_distance: u64,
+}
+
+impl CycleParticipation {
+    /// The fraction of the committee members whose attestations were included.
+    pub fn participation_rate(&self) -> f64 {
+        if self.committee_members == 0 {
+            return 0.0;
+        }
+        self.attestations_included as f64 / self.committee_members as f64
+    }
+}
+
+impl Encodable for CycleParticipation {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.committee_members);
+        s.append(&self.attestations_included);
+        s.append(&self.total_inclusion_distance);
+    }
+}
+
+impl Decodable for CycleParticipation {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (committee_members, i) = u64::ssz_decode(bytes, i)?;
+        let (attestations_included, i) = u64::ssz_decode(bytes, i)?;
+        let (total_inclusion_distance, i) = u64::ssz_decode(bytes, i)?;
+        let participation = Self {
+            committee_members,
+            attestations_included,
+            total_inclusion_distance,
+        };
+        Ok((participation, i))
+    }
+}
+
+/// A cycle accounted by `ParticipationTracker::account`, with the least inclusion distance of
+/// each committee member whose attestation was included.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AccountedCycle {
+    pub cycle: u64,
+    pub participation: CycleParticipation,
+    pub inclusion_distances: BTreeMap<usize, u64>,
+}
+
+/// Records how long the attestations of each validator took to be included as blocks are
+/// imported, and accounts each cycle once its attestations are too old to be included: a cycle
+/// is accounted when the head leaves the cycle after it.
+///
+/// An attestation included by several blocks, e.g. on competing chains, is counted once, at its
+/// least inclusion distance.
+#[derive(Default)]
+pub struct ParticipationTracker {
+    validators: BTreeMap<usize, ValidatorParticipation>,
+    /// The least inclusion distance of each attester of each cycle not yet accounted.
+    included: BTreeMap<u64, BTreeMap<usize, u64>>,
+    /// The earliest cycle not yet accounted, from the cycle of the first block imported, as
+    /// earlier cycles may have been included by blocks imported before the node started.
+    next_cycle: Option<u64>,
+    /// The latest cycle accounted, with the fraction of its committee members whose attestations
+    /// were included.
+    participation_rate: Option<(u64, f64)>,
+    /// The cycles accounted since they were last taken.
+    accounted: Vec<AccountedCycle>,
+}
+
+impl ParticipationTracker {
+    /// Returns what is known of the attestations of the validator with `index`.
+    pub fn validator(&self, index: usize) -> Option<&ValidatorParticipation> {
+        self.validators.get(&index)
+    }
+
+    /// The latest cycle accounted, with the fraction of its committee members whose attestations
+    /// were included.
+    pub fn participation_rate(&self) -> Option<(u64, f64)> {
+        self.participation_rate
+    }
+
+    /// Returns the cycles accounted since this was last called, oldest first.
+    pub fn take_accounted(&mut self) -> Vec<AccountedCycle> {
+        std::mem::take(&mut self.accounted)
+    }
+
+    /// Replaces what is known of the validator with `index`, e.g. with what was persisted.
+    pub fn restore(&mut self, index: usize, participation: ValidatorParticipation) {
+        self.validators.insert(index, participation);
+    }
+
+    /// Records the attestations, of the slots and attesting validators of `attestations`,
+    /// included by an imported block of `slot`.
+    pub fn record(&mut self, slot: u64, attestations: &[(u64, Vec<usize>)], cycle_length: u64) {
+        let next_cycle = *self.next_cycle.get_or_insert(slot / cycle_length);
+        for (attestation_slot, attesters) in attestations {
+            let cycle = attestation_slot / cycle_length;
+            if cycle < next_cycle || *attestation_slot > slot {
+                continue;
+            }
+            let distance = slot - attestation_slot;
+            let included = self.included.entry(cycle).or_default();
+            for index in attesters {
+                let least = included.entry(*index).or_insert(distance);
+                *least = distance.min(*least);
+            }
+        }
+    }
+
+    /// Accounts each cycle before the one before `head_cycle` which is not yet accounted, in
+    /// which the validators of `committee_members` were expected to attest. Returns the indices
+    /// of the validators whose participation changed.
+    pub fn account(&mut self, head_cycle: u64, committee_members: &BTreeSet<usize>) -> Vec<usize> {
+        let first = match self.next_cycle {
+            Some(cycle) => cycle,
+            None => return vec![],
+        };
+        let end = head_cycle.saturating_sub(1);
+        if first >= end || committee_members.is_empty() {
+            return vec![];
+        }
+        for cycle in first..end {
+            let included = self.included.remove(&cycle).unwrap_or_default();
+            let mut missed: u32 = 0;
+            let mut participation = CycleParticipation {
+                committee_members: committee_members.len() as u64,
+                ..CycleParticipation::default()
+            };
+            for index in committee_members {
+                let validator = self.validators.entry(*index).or_default();
+                match included.get(index) {
+                    Some(distance) => {
+                        validator.attestations_included += 1;
+                        validator.total_inclusion_distance += distance;
+                        validator.missed_streak = 0;
+                        participation.attestations_included += 1;
+                        participation.total_inclusion_distance += distance;
+                        observe(&metrics::ATTESTATION_INCLUSION_DISTANCE, *distance as f64);
+                    }
+                    None => {
+                        missed += 1;
+                        validator.missed_streak += 1;
+                        validator.longest_missed_streak =
+                            validator.longest_missed_streak.max(validator.missed_streak);
+                    }
+                }
+            }
+            let rate = 1.0 - f64::from(missed) / committee_members.len() as f64;
+            self.participation_rate = Some((cycle, rate));
+            set_float_gauge(&metrics::PARTICIPATION_RATE, rate);
+            set_gauge(&metrics::MISSED_ATTESTATIONS, i64::from(missed));
+            self.accounted.push(AccountedCycle {
+                cycle,
+                participation,
+                inclusion_distances: included
+                    .into_iter()
+                    .filter(|(index, _)| committee_members.contains(index))
+                    .collect(),
+            });
+        }
+        self.next_cycle = Some(end);
+        committee_members.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssz::ssz_encode;
+
+    #[test]
+    fn test_inclusion_distance_and_missed_streaks() {
+        let mut tracker = ParticipationTracker::default();
+        let members: BTreeSet<usize> = (0..4).collect();
+        assert!(tracker.account(5, &members).is_empty());
+
+        /*
+         * Accounting begins with the cycle of the first block, so the attestation of cycle 1 is
+         * ignored. Validator 1 is included twice in cycle 2, and counted at the lesser distance.
+         */
+        tracker.record(9, &[(5, vec![0]), (8, vec![1])], 4);
+        tracker.record(11, &[(8, vec![1, 2])], 4);
+        assert!(tracker.account(3, &members).is_empty());
+        assert_eq!(tracker.participation_rate(), None);
+
+        assert_eq!(tracker.account(4, &members), vec![0, 1, 2, 3]);
+        assert_eq!(tracker.participation_rate(), Some((2, 0.5)));
+        let validator = tracker.validator(1).unwrap();
+        assert_eq!(validator.attestations_included, 1);
+        assert_eq!(validator.total_inclusion_distance, 1);
+        assert_eq!(validator.missed_streak, 0);
+        assert_eq!(validator.mean_inclusion_distance(), Some(1.0));
+        assert_eq!(tracker.validator(2).unwrap().total_inclusion_distance, 3);
+        let validator = tracker.validator(0).unwrap();
+        assert_eq!(validator.attestations_included, 0);
+        assert_eq!(validator.missed_streak, 1);
+        assert_eq!(validator.mean_inclusion_distance(), None);
+
+        /*
+         * Cycles without blocks are missed by every committee member.
+         */
+        tracker.record(25, &[(20, vec![0])], 4);
+        tracker.account(7, &members);
+        assert_eq!(tracker.participation_rate(), Some((5, 0.25)));
+        assert_eq!(tracker.validator(0).unwrap().missed_streak, 0);
+        assert_eq!(tracker.validator(0).unwrap().longest_missed_streak, 3);
+        assert_eq!(tracker.validator(3).unwrap().missed_streak, 4);
+        assert_eq!(tracker.validator(1).unwrap().missed_streak, 3);
+
+        /*
+         * Each cycle accounted is kept until taken.
+         */
+        let accounted = tracker.take_accounted();
+        assert_eq!(
+            accounted.iter().map(|c| c.cycle).collect::<Vec<_>>(),
+            vec![2, 3, 4, 5]
+        );
+        assert_eq!(
+            accounted[0].participation,
+            CycleParticipation {
+                committee_members: 4,
+                attestations_included: 2,
+                total_inclusion_distance: 4,
+            }
+        );
+        assert_eq!(accounted[0].participation.participation_rate(), 0.5);
+        assert_eq!(accounted[0].inclusion_distances.get(&2), Some(&3));
+        assert!(accounted[1].inclusion_distances.is_empty());
+        assert!(tracker.take_accounted().is_empty());
+    }
+
+    #[test]
+    fn test_ssz_round_trip() {
+        let participation = ValidatorParticipation {
+            attestations_included: 3,
+            total_inclusion_distance: 5,
+            missed_streak: 1,
+            longest_missed_streak: 2,
+        };
+        let ssz = ssz_encode(&participation);
+        let (decoded, i) = ValidatorParticipation::ssz_decode(&ssz, 0).unwrap();
+        assert_eq!(decoded, participation);
+        assert_eq!(i, ssz.len());
+
+        let cycle = CycleParticipation {
+            committee_members: 4,
+            attestations_included: 3,
+            total_inclusion_distance: 7,
+        };
+        let ssz = ssz_encode(&cycle);
+        assert_eq!(
+            CycleParticipation::ssz_decode(&ssz, 0),
+            Ok((cycle, ssz.len()))
+        );
+    }
+}
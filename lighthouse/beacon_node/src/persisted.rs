@@ -0,0 +1,226 @@
+use ssz::{decode_ssz_list, Decodable, DecodeError, Encodable, SszStream};
+use types::{Attestation, Hash256, SpecialRecord};
+
+/// The heads of the chain as chosen by fork choice, as persisted on shutdown.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PersistedHead {
+    pub head_slot: u64,
+    pub head_root: Hash256,
+    /// The tips of all known chains, including the canonical head.
+    pub heads: Vec<Hash256>,
+}
+
+impl Encodable for PersistedHead {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.head_slot);
+        s.append(&self.head_root);
+        s.append_vec(&self.heads);
+    }
+}
+
+impl Decodable for PersistedHead {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (head_slot, i) = u64::ssz_decode(bytes, i)?;
+        let (head_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (heads, i) = decode_ssz_list(bytes, i)?;
+        let head = Self {
+            head_slot,
+            head_root,
+            heads,
+        };
+        Ok((head, i))
+    }
+}
+
+/// The operations waiting to be included in a block, as persisted on shutdown.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PersistedOpPool {
+    pub attestations: Vec<Attestation>,
+    pub specials: Vec<SpecialRecord>,
+}
+
+impl Encodable for PersistedOpPool {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append_vec(&self.attestations);
+        s.append_vec(&self.specials);
+    }
+}
+
+impl Decodable for PersistedOpPool {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (attestations, i) = decode_ssz_list(bytes, i)?;
+        let (specials, i) = decode_ssz_list(bytes, i)?;
+        let op_pool = Self {
+            attestations,
+            specials,
+        };
+        Ok((op_pool, i))
+    }
+}
+
+/// A block known to `ProtoArrayForkChoice`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProtoNode {
+    pub root: Hash256,
+    /// Zero if the parent is not known to fork choice.
+    pub parent_root: Hash256,
+    pub slot: u64,
+}
+
+impl Encodable for ProtoNode {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.root);
+        s.append(&self.parent_root);
+        s.append(&self.slot);
+    }
+}
+
+impl Decodable for ProtoNode {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (parent_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (slot, i) = u64::ssz_decode(bytes, i)?;
+        let node = Self {
+            root,
+            parent_root,
+            slot,
+        };
+        Ok((node, i))
+    }
+}
+
+/// The block most recently attested to by a validator.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LatestMessage {
+    pub validator: u64,
+    pub slot: u64,
+    pub root: Hash256,
+}
+
+impl Encodable for LatestMessage {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.validator);
+        s.append(&self.slot);
+        s.append(&self.root);
+    }
+}
+
+impl Decodable for LatestMessage {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (validator, i) = u64::ssz_decode(bytes, i)?;
+        let (slot, i) = u64::ssz_decode(bytes, i)?;
+        let (root, i) = Hash256::ssz_decode(bytes, i)?;
+        let message = Self {
+            validator,
+            slot,
+            root,
+        };
+        Ok((message, i))
+    }
+}
+
+/// The blocks and latest messages of fork choice, as persisted on finalization and shutdown.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PersistedForkChoice {
+    /// Parents before their children.
+    pub nodes: Vec<ProtoNode>,
+    pub latest_messages: Vec<LatestMessage>,
+}
+
+impl Encodable for PersistedForkChoice {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append_vec(&self.nodes);
+        s.append_vec(&self.latest_messages);
+    }
+}
+
+impl Decodable for PersistedForkChoice {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (nodes, i) = decode_ssz_list(bytes, i)?;
+        let (latest_messages, i) = decode_ssz_list(bytes, i)?;
+        let fork_choice = Self {
+            nodes,
+            latest_messages,
+        };
+        Ok((fork_choice, i))
+    }
+}
+
+/// A block which the canonical chain must include, as given by a trusted source when the node was
+/// started, protecting it from long-range attacks.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct WeakSubjectivityCheckpoint {
+    pub slot: u64,
+    pub root: Hash256,
+}
+
+impl Encodable for WeakSubjectivityCheckpoint {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.slot);
+        s.append(&self.root);
+    }
+}
+
+impl Decodable for WeakSubjectivityCheckpoint {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (slot, i) = u64::ssz_decode(bytes, i)?;
+        let (root, i) = Hash256::ssz_decode(bytes, i)?;
+        Ok((Self { slot, root }, i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssz::ssz_encode;
+
+    #[test]
+    fn test_ssz_round_trip() {
+        let head = PersistedHead {
+            head_slot: 7,
+            head_root: Hash256::from(&[1; 32][..]),
+            heads: vec![Hash256::from(&[1; 32][..]), Hash256::from(&[2; 32][..])],
+        };
+        let (decoded, _) = PersistedHead::ssz_decode(&ssz_encode(&head), 0).unwrap();
+        assert_eq!(decoded, head);
+
+        let op_pool = PersistedOpPool {
+            attestations: vec![Attestation::zero(), Attestation::zero()],
+            specials: vec![SpecialRecord::logout(&[3; 32])],
+        };
+        let (decoded, _) = PersistedOpPool::ssz_decode(&ssz_encode(&op_pool), 0).unwrap();
+        assert_eq!(decoded, op_pool);
+
+        let fork_choice = PersistedForkChoice {
+            nodes: vec![
+                ProtoNode {
+                    root: Hash256::from(&[1; 32][..]),
+                    parent_root: Hash256::zero(),
+                    slot: 0,
+                },
+                ProtoNode {
+                    root: Hash256::from(&[2; 32][..]),
+                    parent_root: Hash256::from(&[1; 32][..]),
+                    slot: 3,
+                },
+            ],
+            latest_messages: vec![LatestMessage {
+                validator: 4,
+                slot: 3,
+                root: Hash256::from(&[2; 32][..]),
+            }],
+        };
+        let (decoded, _) = PersistedForkChoice::ssz_decode(&ssz_encode(&fork_choice), 0).unwrap();
+        assert_eq!(decoded, fork_choice);
+
+        let checkpoint = WeakSubjectivityCheckpoint {
+            slot: 9,
+            root: Hash256::from(&[3; 32][..]),
+        };
+        let ssz = ssz_encode(&checkpoint);
+        assert_eq!(
+            WeakSubjectivityCheckpoint::ssz_decode(&ssz, 0),
+            Ok((checkpoint, 40))
+        );
+    }
+}
@@ -0,0 +1,615 @@
+use super::events::BeaconNodeEvent;
+use super::metrics;
+use db::stores::{BeaconBlockStore, StatePruning, StateStore};
+use db::{ClientDB, DBError};
+use lighthouse_metrics::{inc_counter, inc_counter_by, start_timer};
+use slog::Logger;
+use ssz::{Decodable, SszStream};
+use state_transition::extend_active_state;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use task_executor::TaskExecutor;
+use types::{ActiveState, BeaconBlock, Hash256};
+
+/// The number of states regenerated at once by default.
+pub const DEFAULT_REGEN_WORKERS: usize = 2;
+/// The number of regenerated states kept in memory by default.
+pub const DEFAULT_REGEN_CACHE_SIZE: usize = 16;
+/// When archiving, a snapshot is stored of the state after the first block replayed in each span
+/// of this many slots, so no state is more than about this many blocks from a snapshot once
+/// regenerated.
+pub const SNAPSHOT_INTERVAL: u64 = 64;
+/// How often the pruning thread checks whether the service is stopping.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum RegenError {
+    DBError(String),
+    /// The block, or one of its ancestors, is not stored.
+    UnknownBlock(Hash256),
+    /// A stored block or snapshot cannot be decoded.
+    InvalidRecord(Hash256),
+    /// The state transition failed.
+    Transition(String),
+    /// The service stopped before answering.
+    Stopped,
+}
+
+impl From<DBError> for RegenError {
+    fn from(e: DBError) -> Self {
+        RegenError::DBError(e.message)
+    }
+}
+
+/// The state after a block, as regenerated from blocks.
+///
+/// Until the crystallized state transition is restored, only the active state is regenerated.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RegeneratedState {
+    pub slot: u64,
+    pub block_root: Hash256,
+    pub active_state: ActiveState,
+}
+
+/// Regenerates the state after any stored block by replaying blocks onto the nearest state known:
+/// one recently regenerated, a stored snapshot or the genesis state.
+pub struct StateRegenerator<T: ClientDB> {
+    blocks: Arc<BeaconBlockStore<T>>,
+    snapshots: StateStore<T>,
+    pruning: StatePruning,
+    genesis: Arc<RegeneratedState>,
+    cache: Mutex<StateCache>,
+}
+
+impl<T: ClientDB> StateRegenerator<T> {
+    /// Creates a regenerator for the chain starting at `genesis_root`, keeping the last
+    /// `cache_size` states regenerated and the snapshots `pruning` keeps.
+    pub fn new(
+        blocks: Arc<BeaconBlockStore<T>>,
+        snapshots: StateStore<T>,
+        pruning: StatePruning,
+        genesis_root: Hash256,
+        cycle_length: u8,
+        cache_size: usize,
+    ) -> Self {
+        Self {
+            blocks,
+            snapshots,
+            pruning,
+            genesis: Arc::new(genesis_state(genesis_root, cycle_length)),
+            cache: Mutex::new(StateCache::new(cache_size)),
+        }
+    }
+
+    /// Returns the state after the block with `block_root`, if it was recently regenerated.
+    pub fn cached(&self, block_root: &Hash256) -> Option<Arc<RegeneratedState>> {
+        if *block_root == self.genesis.block_root {
+            return Some(self.genesis.clone());
+        }
+        self.cache
+            .lock()
+            .expect("State cache lock poisoned")
+            .get(block_root)
+    }
+
+    /// Returns the state after the block with `block_root`, regenerating it if not cached.
+    pub fn state(&self, block_root: Hash256) -> Result<Arc<RegeneratedState>, RegenError> {
+        if let Some(state) = self.cached(&block_root) {
+            inc_counter(&metrics::STATE_REGEN_CACHE_HITS);
+            return Ok(state);
+        }
+        let _timer = start_timer(&metrics::STATE_REGEN_TIMES);
+
+        /*
+         * Walk back from the block to the nearest state known, then replay the blocks after it.
+         */
+        let mut to_replay = vec![];
+        let mut root = block_root;
+        let mut state = loop {
+            if let Some(state) = self.cached(&root) {
+                break (*state).clone();
+            }
+            if let Some(state) = self.snapshot(&root)? {
+                break state;
+            }
+            let block = self.block(&root)?;
+            let parent = *block.parent_hash().ok_or(RegenError::InvalidRecord(root))?;
+            to_replay.push((root, block));
+            root = parent;
+        };
+        inc_counter_by(
+            &metrics::STATE_REGEN_BLOCKS_REPLAYED,
+            to_replay.len() as i64,
+        );
+        for (root, block) in to_replay.into_iter().rev() {
+            let active_state = extend_active_state(&state.active_state, &block, &root)
+                .map_err(|e| RegenError::Transition(format!("{:?}", e)))?;
+            let snapshot = self.pruning == StatePruning::Archive
+                && state.slot / SNAPSHOT_INTERVAL != block.slot / SNAPSHOT_INTERVAL;
+            state = RegeneratedState {
+                slot: block.slot,
+                block_root: root,
+                active_state,
+            };
+            if snapshot {
+                self.snapshots
+                    .put_serialized_snapshot(&root, &encode_snapshot(&state))?;
+            }
+        }
+
+        let state = Arc::new(state);
+        self.cache
+            .lock()
+            .expect("State cache lock poisoned")
+            .insert(block_root, state.clone());
+        Ok(state)
+    }
+
+    /// Stores a snapshot of the state after the block with `finalized_root`, then deletes the
+    /// snapshots the pruning mode does not keep, returning the number deleted.
+    pub fn prune(&self, finalized_root: Hash256) -> Result<usize, RegenError> {
+        self.prune_with(self.pruning, finalized_root)
+    }
+
+    /// Deletes every snapshot but that of the finalized state, whatever the pruning mode, and
+    /// reclaims their space, e.g. when the disk is nearly full.
+    pub fn prune_aggressively(&self, finalized_root: Hash256) -> Result<usize, RegenError> {
+        let pruned = self.prune_with(StatePruning::Minimal, finalized_root)?;
+        self.snapshots.compact()?;
+        Ok(pruned)
+    }
+
+    fn prune_with(
+        &self,
+        pruning: StatePruning,
+        finalized_root: Hash256,
+    ) -> Result<usize, RegenError> {
+        if pruning == StatePruning::Minimal && finalized_root != self.genesis.block_root {
+            let state = self.state(finalized_root)?;
+            self.snapshots
+                .put_serialized_snapshot(&finalized_root, &encode_snapshot(&state))?;
+        }
+        Ok(self.snapshots.prune(pruning, &finalized_root)?)
+    }
+
+    fn block(&self, root: &Hash256) -> Result<BeaconBlock, RegenError> {
+        let ssz = self
+            .blocks
+            .get_serialized_block(root)?
+            .ok_or(RegenError::UnknownBlock(*root))?;
+        BeaconBlock::ssz_decode(&ssz, 0)
+            .map(|(block, _)| block)
+            .map_err(|_| RegenError::InvalidRecord(*root))
+    }
+
+    fn snapshot(&self, root: &Hash256) -> Result<Option<RegeneratedState>, RegenError> {
+        let ssz = match self.snapshots.get_serialized_snapshot(root)? {
+            Some(ssz) => ssz,
+            None => return Ok(None),
+        };
+        let decoded = u64::ssz_decode(&ssz, 0)
+            .and_then(|(slot, i)| ActiveState::ssz_decode(&ssz, i).map(|(s, _)| (slot, s)));
+        match decoded {
+            Ok((slot, active_state)) => Ok(Some(RegeneratedState {
+                slot,
+                block_root: *root,
+                active_state,
+            })),
+            Err(_) => Err(RegenError::InvalidRecord(*root)),
+        }
+    }
+}
+
+/// Regenerates states on a fixed number of worker threads, so that however many are requested at
+/// once, only that many are regenerated at a time and the rest wait their turn.
+///
+/// The workers are tasks of the node's executor, so a panic while regenerating shuts the node
+/// down. They stop when the service is dropped.
+pub struct StateRegenService<T: ClientDB> {
+    regenerator: Arc<StateRegenerator<T>>,
+    requests: Option<Mutex<Sender<Request>>>,
+    workers: Vec<JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+}
+
+type Request = (Hash256, Sender<Result<Arc<RegeneratedState>, RegenError>>);
+
+impl<T: ClientDB + 'static> StateRegenService<T> {
+    pub fn start(
+        regenerator: StateRegenerator<T>,
+        workers: usize,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> Self {
+        let regenerator = Arc::new(regenerator);
+        let (tx, rx) = channel();
+        let rx = Arc::new(Mutex::new(rx));
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let (regenerator, rx, log) = (regenerator.clone(), rx.clone(), log.clone());
+                executor.spawn("state_regen", move |_| run(&regenerator, &rx, &log))
+            })
+            .collect();
+        Self {
+            regenerator,
+            requests: Some(Mutex::new(tx)),
+            workers,
+            stopping: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Prunes the snapshots each time a `FinalizedCheckpoint` is received from `events`, on a
+    /// task of `executor`, until the service is dropped, the node shuts down or the events end.
+    pub fn prune_on_finalization(
+        &mut self,
+        events: Receiver<BeaconNodeEvent>,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) {
+        let (regenerator, stopping) = (self.regenerator.clone(), self.stopping.clone());
+        self.workers.push(executor.spawn("state_regen_prune", move |exit| {
+            while !stopping.load(Ordering::Relaxed) && !exit.is_exiting() {
+                let root = match events.recv_timeout(STOP_POLL_INTERVAL) {
+                    Ok(BeaconNodeEvent::FinalizedCheckpoint { root, .. }) => root,
+                    Ok(_) | Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                match regenerator.prune(root) {
+                    Ok(pruned) => {
+                        debug!(log, "Pruned state snapshots"; "finalized_root" => format!("{:?}", root), "pruned" => pruned)
+                    }
+                    Err(e) => {
+                        warn!(log, "Unable to prune state snapshots"; "finalized_root" => format!("{:?}", root), "error" => format!("{:?}", e))
+                    }
+                }
+            }
+        }));
+    }
+}
+
+impl<T: ClientDB> StateRegenService<T> {
+    /// Returns the state after the block with `block_root`, waiting for a worker to regenerate it
+    /// if it is not cached.
+    pub fn state(&self, block_root: Hash256) -> Result<Arc<RegeneratedState>, RegenError> {
+        if let Some(state) = self.regenerator.cached(&block_root) {
+            inc_counter(&metrics::STATE_REGEN_CACHE_HITS);
+            return Ok(state);
+        }
+        let (tx, rx) = channel();
+        self.requests
+            .as_ref()
+            .ok_or(RegenError::Stopped)?
+            .lock()
+            .expect("Regen requests lock poisoned")
+            .send((block_root, tx))
+            .map_err(|_| RegenError::Stopped)?;
+        rx.recv().map_err(|_| RegenError::Stopped)?
+    }
+}
+
+impl<T: ClientDB> Drop for StateRegenService<T> {
+    fn drop(&mut self) {
+        self.requests = None;
+        self.stopping.store(true, Ordering::Relaxed);
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run<T: ClientDB>(
+    regenerator: &StateRegenerator<T>,
+    requests: &Mutex<Receiver<Request>>,
+    log: &Logger,
+) {
+    loop {
+        let request = requests
+            .lock()
+            .expect("Regen requests lock poisoned")
+            .recv();
+        let (block_root, response) = match request {
+            Ok(request) => request,
+            Err(_) => break,
+        };
+        let result = regenerator.state(block_root);
+        match result {
+            Ok(ref state) => {
+                debug!(log, "Regenerated state"; "slot" => state.slot, "block_root" => format!("{:?}", block_root))
+            }
+            Err(ref e) => {
+                debug!(log, "Unable to regenerate state"; "block_root" => format!("{:?}", block_root), "error" => format!("{:?}", e))
+            }
+        }
+        let _ = response.send(result);
+    }
+}
+
+/// Keeps the most recently used states, up to a capacity.
+struct StateCache {
+    capacity: usize,
+    /// Least recently used first.
+    states: VecDeque<(Hash256, Arc<RegeneratedState>)>,
+}
+
+impl StateCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            states: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, block_root: &Hash256) -> Option<Arc<RegeneratedState>> {
+        let i = self
+            .states
+            .iter()
+            .position(|(root, _)| root == block_root)?;
+        let entry = self.states.remove(i)?;
+        let state = entry.1.clone();
+        self.states.push_back(entry);
+        Some(state)
+    }
+
+    fn insert(&mut self, block_root: Hash256, state: Arc<RegeneratedState>) {
+        self.states.retain(|(root, _)| *root != block_root);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back((block_root, state));
+    }
+}
+
+/// The state after the genesis block, whose recent block hashes span two cycles.
+fn genesis_state(genesis_root: Hash256, cycle_length: u8) -> RegeneratedState {
+    let mut recent_block_hashes = vec![Hash256::zero(); 2 * usize::from(cycle_length.max(1))];
+    if let Some(last) = recent_block_hashes.last_mut() {
+        *last = genesis_root;
+    }
+    RegeneratedState {
+        slot: 0,
+        block_root: genesis_root,
+        active_state: ActiveState {
+            pending_attestations: vec![],
+            pending_specials: vec![],
+            recent_block_hashes,
+            randao_mix: Hash256::zero(),
+        },
+    }
+}
+
+fn encode_snapshot(state: &RegeneratedState) -> Vec<u8> {
+    let mut s = SszStream::new();
+    s.append(&state.slot);
+    s.append(&state.active_state);
+    s.drain()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::node::tests::test_node;
+    use super::super::BeaconNode;
+    use super::*;
+    use db::MemoryDB;
+    use slog::Discard;
+    use std::thread;
+    use std::time::Instant;
+
+    fn executor() -> TaskExecutor {
+        TaskExecutor::new(Logger::root(Discard, o!())).0
+    }
+
+    /// Imports a block at each of `slots` on the head, each with a distinct randao reveal,
+    /// returning their roots.
+    fn import_chain(node: &mut BeaconNode<MemoryDB>, slots: &[u64]) -> Vec<Hash256> {
+        let mut roots = vec![];
+        for slot in slots {
+            let mut block = BeaconBlock::zero();
+            block.slot = *slot;
+            block.randao_reveal = Hash256::from(*slot);
+            block.ancestor_hashes.push(node.head().1);
+            node.process_block(&block, *slot).unwrap();
+            roots.push(block.canonical_root());
+        }
+        roots
+    }
+
+    fn regenerator(node: &BeaconNode<MemoryDB>, cache_size: usize) -> StateRegenerator<MemoryDB> {
+        regenerator_with_pruning(node, cache_size, StatePruning::Archive)
+    }
+
+    fn regenerator_with_pruning(
+        node: &BeaconNode<MemoryDB>,
+        cache_size: usize,
+        pruning: StatePruning,
+    ) -> StateRegenerator<MemoryDB> {
+        StateRegenerator::new(
+            node.store().clone(),
+            StateStore::new(Arc::new(MemoryDB::open())),
+            pruning,
+            node.genesis_root(),
+            node.config().cycle_length,
+            cache_size,
+        )
+    }
+
+    #[test]
+    fn test_regenerate() {
+        let mut node = test_node(4);
+        let roots = import_chain(&mut node, &[1, 2, 70, 71]);
+        let regen = regenerator(&node, 2);
+
+        let genesis = regen.state(node.genesis_root()).unwrap();
+        assert_eq!(genesis.slot, 0);
+        assert!(genesis.active_state.randao_mix.is_zero());
+
+        let state = regen.state(roots[3]).unwrap();
+        assert_eq!(state.slot, 71);
+        assert_eq!(state.block_root, roots[3]);
+        assert_eq!(
+            state.active_state.randao_mix,
+            Hash256::from(1) ^ Hash256::from(2) ^ Hash256::from(70) ^ Hash256::from(71)
+        );
+        assert_eq!(
+            state.active_state.recent_block_hashes.last(),
+            Some(&roots[3])
+        );
+        assert_eq!(state.active_state.recent_block_hashes.len(), 4);
+
+        /*
+         * Only the first block of each span of slots is snapshotted, and a state regenerated from
+         * a snapshot is the same as one regenerated from genesis.
+         */
+        assert!(regen.snapshot(&roots[0]).unwrap().is_none());
+        assert!(regen.snapshot(&roots[3]).unwrap().is_none());
+        assert_eq!(
+            regen.snapshot(&roots[2]).unwrap().unwrap(),
+            (*regen.state(roots[2]).unwrap()).clone()
+        );
+        let fresh = regenerator(&node, 0);
+        fresh
+            .snapshots
+            .put_serialized_snapshot(&roots[2], &encode_snapshot(&regen.state(roots[2]).unwrap()))
+            .unwrap();
+        assert_eq!(fresh.state(roots[3]).unwrap(), state);
+
+        assert_eq!(
+            regen.state(Hash256::from(5)),
+            Err(RegenError::UnknownBlock(Hash256::from(5)))
+        );
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut node = test_node(4);
+        let roots = import_chain(&mut node, &[1, 70, 140]);
+
+        let archive = regenerator(&node, 0);
+        archive.state(roots[2]).unwrap();
+        assert_eq!(archive.prune(roots[0]).unwrap(), 0);
+        assert!(archive.snapshot(&roots[1]).unwrap().is_some());
+        assert!(archive.snapshot(&roots[2]).unwrap().is_some());
+
+        /*
+         * Pruning aggressively disregards the mode, keeping only the finalized snapshot.
+         */
+        assert_eq!(archive.prune_aggressively(roots[1]).unwrap(), 1);
+        assert!(archive.snapshot(&roots[1]).unwrap().is_some());
+        assert!(archive.snapshot(&roots[2]).unwrap().is_none());
+
+        /*
+         * Only the finalized state is snapshotted, and later states are replayed from it.
+         */
+        let minimal = regenerator_with_pruning(&node, 0, StatePruning::Minimal);
+        let expected = minimal.state(roots[2]).unwrap();
+        assert!(minimal.snapshot(&roots[1]).unwrap().is_none());
+        assert_eq!(minimal.prune(roots[1]).unwrap(), 0);
+        assert!(minimal.snapshot(&roots[1]).unwrap().is_some());
+        assert_eq!(minimal.state(roots[2]).unwrap(), expected);
+
+        assert_eq!(minimal.prune(roots[2]).unwrap(), 1);
+        assert!(minimal.snapshot(&roots[1]).unwrap().is_none());
+        assert!(minimal.snapshot(&roots[2]).unwrap().is_some());
+        assert_eq!(minimal.prune(node.genesis_root()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_state_cache() {
+        let state = |root: u64| {
+            Arc::new(RegeneratedState {
+                slot: root,
+                ..genesis_state(Hash256::from(root), 1)
+            })
+        };
+        let mut cache = StateCache::new(2);
+        cache.insert(Hash256::from(1), state(1));
+        cache.insert(Hash256::from(2), state(2));
+        assert_eq!(cache.get(&Hash256::from(1)), Some(state(1)));
+
+        /*
+         * The least recently used state is evicted.
+         */
+        cache.insert(Hash256::from(3), state(3));
+        assert_eq!(cache.get(&Hash256::from(2)), None);
+        assert_eq!(cache.get(&Hash256::from(1)), Some(state(1)));
+        assert_eq!(cache.get(&Hash256::from(3)), Some(state(3)));
+
+        let mut disabled = StateCache::new(0);
+        disabled.insert(Hash256::from(1), state(1));
+        assert_eq!(disabled.get(&Hash256::from(1)), None);
+    }
+
+    #[test]
+    fn test_service() {
+        let mut node = test_node(4);
+        let roots = import_chain(&mut node, &[1, 2, 3]);
+        let service = StateRegenService::start(
+            regenerator(&node, 4),
+            2,
+            &executor(),
+            Logger::root(Discard, o!()),
+        );
+
+        let handles: Vec<_> = roots
+            .iter()
+            .map(|root| {
+                let root = *root;
+                let regen = service.regenerator.clone();
+                thread::spawn(move || regen.state(root).unwrap().slot)
+            })
+            .collect();
+        let slots: Vec<u64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(slots, vec![1, 2, 3]);
+
+        assert_eq!(service.state(roots[1]).unwrap().slot, 2);
+        assert_eq!(
+            service.state(Hash256::from(5)),
+            Err(RegenError::UnknownBlock(Hash256::from(5)))
+        );
+        drop(service);
+    }
+
+    #[test]
+    fn test_prune_on_finalization() {
+        let mut node = test_node(4);
+        let roots = import_chain(&mut node, &[1, 70]);
+        let mut service = StateRegenService::start(
+            regenerator_with_pruning(&node, 0, StatePruning::Minimal),
+            1,
+            &executor(),
+            Logger::root(Discard, o!()),
+        );
+        let (tx, rx) = channel();
+        service.prune_on_finalization(rx, &executor(), Logger::root(Discard, o!()));
+
+        /*
+         * Other events are ignored, and a finalized checkpoint snapshots the finalized state.
+         */
+        tx.send(BeaconNodeEvent::Block {
+            slot: 70,
+            root: roots[1],
+            proposer: None,
+            attestations: vec![],
+        })
+        .unwrap();
+        tx.send(BeaconNodeEvent::FinalizedCheckpoint {
+            root: roots[0],
+            state_root: Hash256::zero(),
+            cycle: 0,
+        })
+        .unwrap();
+        let regen = service.regenerator.clone();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while regen.snapshot(&roots[0]).unwrap().is_none() {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(10));
+        }
+        drop(service);
+    }
+}
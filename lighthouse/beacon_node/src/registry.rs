@@ -0,0 +1,116 @@
+use types::{ValidatorRecord, ValidatorStatus};
+
+/// The changes to the validator registry over a cycle, so that accounting need not compare whole
+/// registries.
+///
+/// Each list holds validator indices in ascending order.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RegistryDelta {
+    pub cycle: u64,
+    /// Validators added to the registry by a deposit.
+    pub deposits: Vec<usize>,
+    /// Validators which became active, including those added active.
+    pub activations: Vec<usize>,
+    /// Validators which began to exit or were withdrawn.
+    pub exits: Vec<usize>,
+    /// Validators which were penalized.
+    pub slashings: Vec<usize>,
+    /// Validators already in the registry whose balance increased, with the increase in gwei.
+    pub top_ups: Vec<(usize, u64)>,
+}
+
+impl RegistryDelta {
+    /// Returns the changes from `before`, the registry at the start of `cycle`, to `after`, the
+    /// registry at its end. Validators are never removed from the registry.
+    pub fn between(cycle: u64, before: &[ValidatorRecord], after: &[ValidatorRecord]) -> Self {
+        let mut delta = Self {
+            cycle,
+            ..Self::default()
+        };
+        for (index, validator) in after.iter().enumerate() {
+            let was_exited = match before.get(index) {
+                Some(old) => {
+                    if validator.balance > old.balance {
+                        delta.top_ups.push((index, validator.balance - old.balance));
+                    }
+                    if old.status == validator.status {
+                        continue;
+                    }
+                    is_exited(old.status)
+                }
+                None => {
+                    delta.deposits.push(index);
+                    false
+                }
+            };
+            if validator.status == ValidatorStatus::Active as u8 {
+                delta.activations.push(index);
+            } else if validator.status == ValidatorStatus::Penalized as u8 {
+                delta.slashings.push(index);
+            } else if is_exited(validator.status) && !was_exited {
+                delta.exits.push(index);
+            }
+        }
+        delta
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deposits.is_empty()
+            && self.activations.is_empty()
+            && self.exits.is_empty()
+            && self.slashings.is_empty()
+            && self.top_ups.is_empty()
+    }
+}
+
+fn is_exited(status: u8) -> bool {
+    status == ValidatorStatus::PendingExit as u8
+        || status == ValidatorStatus::PendingWithdraw as u8
+        || status == ValidatorStatus::Withdrawn as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator(status: ValidatorStatus, balance: u64) -> ValidatorRecord {
+        let (mut validator, _) = ValidatorRecord::zero_with_thread_rand_keypair();
+        validator.status = status as u8;
+        validator.balance = balance;
+        validator
+    }
+
+    #[test]
+    fn test_registry_delta() {
+        let before = vec![
+            validator(ValidatorStatus::Active, 32),
+            validator(ValidatorStatus::PendingActivation, 32),
+            validator(ValidatorStatus::Active, 32),
+            validator(ValidatorStatus::Active, 32),
+            validator(ValidatorStatus::PendingExit, 32),
+        ];
+        assert!(RegistryDelta::between(3, &before, &before).is_empty());
+
+        let mut after = before.clone();
+        after[0].balance = 40;
+        after[1].status = ValidatorStatus::Active as u8;
+        after[2].status = ValidatorStatus::PendingExit as u8;
+        after[2].balance = 31;
+        after[3].status = ValidatorStatus::Penalized as u8;
+        after[4].status = ValidatorStatus::Withdrawn as u8;
+        after.push(validator(ValidatorStatus::PendingActivation, 32));
+        after.push(validator(ValidatorStatus::Active, 32));
+
+        assert_eq!(
+            RegistryDelta::between(3, &before, &after),
+            RegistryDelta {
+                cycle: 3,
+                deposits: vec![5, 6],
+                activations: vec![1, 6],
+                exits: vec![2],
+                slashings: vec![3],
+                top_ups: vec![(0, 8)],
+            }
+        );
+    }
+}
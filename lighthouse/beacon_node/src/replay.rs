@@ -0,0 +1,178 @@
+use super::node::{BeaconNode, BeaconNodeError, BlockProcessingOutcome};
+use db::stores::BeaconBlockStore;
+use db::{ClientDB, DBError, MemoryDB};
+use ssz::Decodable;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use types::{BeaconBlock, ChainConfig, Hash256};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReplayError {
+    DBError(String),
+    /// A block of the chain being replayed is not stored.
+    UnknownBlock(Hash256),
+    /// A stored block cannot be decoded.
+    InvalidRecord(Hash256),
+    /// The node to replay onto could not be built.
+    Node(String),
+}
+
+impl From<DBError> for ReplayError {
+    fn from(e: DBError) -> Self {
+        ReplayError::DBError(e.message)
+    }
+}
+
+/// One step of the import of a block, as traced by `BeaconNode::process_block_traced`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportStep {
+    pub operation: &'static str,
+    pub duration: Duration,
+    /// What the step found or did, e.g. the participants of an attestation.
+    pub detail: String,
+}
+
+/// The import of one block of the range replayed.
+#[derive(Debug, PartialEq)]
+pub struct ReplayedBlock {
+    pub slot: u64,
+    pub root: Hash256,
+    pub proposer: Option<usize>,
+    pub outcome: Result<BlockProcessingOutcome, BeaconNodeError>,
+    pub duration: Duration,
+    pub steps: Vec<ImportStep>,
+    /// The head after the block was imported.
+    pub head_slot: u64,
+    pub head_root: Hash256,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ReplayReport {
+    pub from_slot: u64,
+    pub to_slot: u64,
+    /// The number of blocks imported, without tracing, before the range.
+    pub blocks_before: usize,
+    pub blocks: Vec<ReplayedBlock>,
+}
+
+/// Re-imports the chain ending at `head_root` from `blocks` onto a new node built from `config`,
+/// tracing the import of each block from `from_slot` to `to_slot` inclusive.
+///
+/// The blocks before the range are imported first, as every block must be imported after its
+/// parent. The node keeps its store in memory, so the database is only read, and the outcome of
+/// a replay of the same blocks is always the same; only the timings vary.
+pub fn replay_blocks<T: ClientDB>(
+    blocks: &BeaconBlockStore<T>,
+    config: ChainConfig,
+    head_root: Hash256,
+    from_slot: u64,
+    to_slot: u64,
+) -> Result<ReplayReport, ReplayError> {
+    let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+    let mut node =
+        BeaconNode::new(config, store).map_err(|e| ReplayError::Node(format!("{:?}", e)))?;
+    let genesis_root = node.genesis_root();
+
+    /*
+     * Walk back from the head to genesis, keeping the blocks up to the end of the range.
+     */
+    let mut chain = vec![];
+    let mut root = head_root;
+    while root != genesis_root {
+        let ssz = blocks
+            .get_serialized_block(&root)?
+            .ok_or(ReplayError::UnknownBlock(root))?;
+        let (block, _) =
+            BeaconBlock::ssz_decode(&ssz, 0).map_err(|_| ReplayError::InvalidRecord(root))?;
+        let parent = *block
+            .parent_hash()
+            .ok_or(ReplayError::InvalidRecord(root))?;
+        if block.slot <= to_slot {
+            chain.push((root, block));
+        }
+        root = parent;
+    }
+
+    let mut report = ReplayReport {
+        from_slot,
+        to_slot,
+        blocks_before: 0,
+        blocks: vec![],
+    };
+    for (root, block) in chain.into_iter().rev() {
+        if block.slot < from_slot {
+            node.process_block(&block, block.slot)
+                .map_err(|e| ReplayError::Node(format!("{:?}", e)))?;
+            report.blocks_before += 1;
+            continue;
+        }
+        let started = Instant::now();
+        let (outcome, steps) = node.process_block_traced(&block, block.slot);
+        let duration = started.elapsed();
+        let (head_slot, head_root) = node.head();
+        report.blocks.push(ReplayedBlock {
+            slot: block.slot,
+            root,
+            proposer: node.block_proposer(block.slot),
+            outcome,
+            duration,
+            steps,
+            head_slot,
+            head_root,
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::node::tests::{test_config, test_node};
+    use super::*;
+
+    #[test]
+    fn test_replay_blocks() {
+        let mut node = test_node(8);
+        for slot in 1..=4 {
+            let block = node
+                .produce_block(slot, Hash256::zero(), Hash256::zero())
+                .unwrap();
+            node.process_block(&block, slot).unwrap();
+        }
+        let head = node.head();
+
+        let report = replay_blocks(node.store(), test_config(8), head.1, 2, 3).unwrap();
+        assert_eq!(report.blocks_before, 1);
+        assert_eq!(report.blocks.len(), 2);
+        let replayed = &report.blocks[1];
+        assert_eq!(replayed.slot, 3);
+        assert_eq!(replayed.outcome, Ok(BlockProcessingOutcome::Imported));
+        assert_eq!(replayed.head_slot, 3);
+        assert_eq!(replayed.proposer, node.block_proposer(3));
+        let operations: Vec<&str> = replayed.steps.iter().map(|s| s.operation).collect();
+        assert_eq!(operations[..3], ["verify", "store", "fork_choice_block"]);
+        assert!(operations.contains(&"find_head"));
+        assert!(operations.contains(&"update_head"));
+
+        /*
+         * The replay is the same each time.
+         */
+        let again = replay_blocks(node.store(), test_config(8), head.1, 2, 3).unwrap();
+        let roots = |report: &ReplayReport| -> Vec<Hash256> {
+            report.blocks.iter().map(|b| b.head_root).collect()
+        };
+        assert_eq!(roots(&report), roots(&again));
+        assert_eq!(replayed.head_root, replayed.root);
+
+        assert_eq!(
+            replay_blocks(
+                node.store(),
+                test_config(8),
+                Hash256::from(&[1; 32][..]),
+                0,
+                4
+            )
+            .err(),
+            Some(ReplayError::UnknownBlock(Hash256::from(&[1; 32][..])))
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use super::packing::Vote;
+use std::collections::BTreeSet;
+use types::{Attestation, Hash256, ValidatorRecord};
+
+/// The divisor of the balance of a validator giving its base reward, as in the spec.
+pub const BASE_REWARD_QUOTIENT: u64 = 2_048;
+/// The divisor of the base reward of an attester giving the reward of the proposer which first
+/// includes its vote, as in the spec.
+pub const INCLUDER_REWARD_QUOTIENT: u64 = 8;
+
+/// Returns the reward of the proposer which first includes a vote of a validator with `balance`.
+pub fn includer_reward(balance: u64) -> u64 {
+    balance / BASE_REWARD_QUOTIENT / INCLUDER_REWARD_QUOTIENT
+}
+
+/// The marginal value of one attestation of a block to its proposer.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AttestationReward {
+    /// The position of the attestation in the block.
+    pub index: usize,
+    pub slot: u64,
+    pub shard: u64,
+    pub participants: usize,
+    /// The participants whose votes were included neither by an ancestor of the block nor by an
+    /// earlier attestation of it.
+    pub new_votes: usize,
+    /// The reward of the new votes to the proposer, in Gwei.
+    pub reward: u64,
+}
+
+/// The estimated reward of the proposer of a block.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockReward {
+    pub slot: u64,
+    pub root: Hash256,
+    pub proposer: Option<usize>,
+    pub attestations: Vec<AttestationReward>,
+}
+
+impl BlockReward {
+    /// The reward for the votes first included by the block, in Gwei.
+    pub fn attestations_reward(&self) -> u64 {
+        self.attestations
+            .iter()
+            .fold(0, |total, a| total.saturating_add(a.reward))
+    }
+
+    /// The votes of the block which were already included, and so earn nothing.
+    pub fn redundant_votes(&self) -> usize {
+        self.attestations
+            .iter()
+            .map(|a| a.participants - a.new_votes)
+            .sum()
+    }
+}
+
+/// Returns the marginal value of each of `attestations`, given with their participants in the
+/// order of the block, where `included` are the votes included by the ancestors of the block.
+pub fn attestation_rewards(
+    attestations: &[(Attestation, Vec<usize>)],
+    included: &BTreeSet<Vote>,
+    validators: &[ValidatorRecord],
+) -> Vec<AttestationReward> {
+    let mut covered = included.clone();
+    attestations
+        .iter()
+        .enumerate()
+        .map(|(index, (attestation, participants))| {
+            let data = &attestation.data;
+            let mut reward = AttestationReward {
+                index,
+                slot: data.slot,
+                shard: data.shard,
+                participants: participants.len(),
+                new_votes: 0,
+                reward: 0,
+            };
+            for i in participants {
+                if covered.insert((data.slot, data.shard, *i)) {
+                    let balance = validators.get(*i).map(|v| v.balance).unwrap_or(0);
+                    reward.new_votes += 1;
+                    reward.reward = reward.reward.saturating_add(includer_reward(balance));
+                }
+            }
+            reward
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::AttestationData;
+
+    fn attestation(slot: u64) -> Attestation {
+        let mut attestation = Attestation::zero();
+        attestation.data = AttestationData {
+            slot,
+            ..AttestationData::zero()
+        };
+        attestation
+    }
+
+    #[test]
+    fn test_attestation_rewards() {
+        let validators: Vec<ValidatorRecord> = [32_000_000_000, 16_000_000_000, 32_000_000_000]
+            .iter()
+            .map(|balance| {
+                let (mut validator, _) = ValidatorRecord::zero_with_thread_rand_keypair();
+                validator.balance = *balance;
+                validator
+            })
+            .collect();
+        assert_eq!(includer_reward(32_000_000_000), 1_953_125);
+
+        /*
+         * Votes included by an ancestor or by an earlier attestation earn nothing.
+         */
+        let included: BTreeSet<Vote> = vec![(1, 0, 2)].into_iter().collect();
+        let attestations = vec![
+            (attestation(1), vec![0, 2]),
+            (attestation(1), vec![0, 1]),
+            (attestation(1), vec![1]),
+            (attestation(2), vec![2]),
+        ];
+        let rewards = attestation_rewards(&attestations, &included, &validators);
+        let new_votes: Vec<usize> = rewards.iter().map(|r| r.new_votes).collect();
+        assert_eq!(new_votes, vec![1, 1, 0, 1]);
+        let values: Vec<u64> = rewards.iter().map(|r| r.reward).collect();
+        assert_eq!(values, vec![1_953_125, 976_562, 0, 1_953_125]);
+        assert_eq!(rewards[3].index, 3);
+        assert_eq!(rewards[3].slot, 2);
+
+        let block_reward = BlockReward {
+            slot: 3,
+            root: Hash256::zero(),
+            proposer: None,
+            attestations: rewards,
+        };
+        assert_eq!(block_reward.attestations_reward(), 4_882_812);
+        assert_eq!(block_reward.redundant_votes(), 3);
+    }
+}
@@ -0,0 +1,236 @@
+use super::events::BeaconNodeEvent;
+use super::metrics;
+use super::node::BeaconNode;
+use db::ClientDB;
+use lighthouse_metrics::start_timer;
+use slog::Logger;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use task_executor::TaskExecutor;
+use types::{ChainConfig, ShardAndCommittee, ValidatorRecord};
+use validator_shuffling::{shard_and_committees_for_cycle, ValidatorAssignmentError};
+
+/// The number of cycles whose committees are kept in memory by default.
+pub const DEFAULT_SHUFFLING_CACHE_SIZE: usize = 4;
+/// How often the pre-computation thread checks whether the service is stopping.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The committees of each slot of a cycle.
+pub type CycleShuffling = Vec<Vec<ShardAndCommittee>>;
+
+/// Computes the committees of each slot of a cycle from `validators`.
+///
+/// Until the state transition is restored, every cycle is shuffled with the genesis seed.
+pub fn compute_shuffling(
+    validators: &[ValidatorRecord],
+    config: &ChainConfig,
+) -> Result<CycleShuffling, ValidatorAssignmentError> {
+    shard_and_committees_for_cycle(&[0; 32], validators, 0, config)
+}
+
+/// Keeps the committees of the latest cycles, up to a capacity, so that duties are answered
+/// without shuffling the validators again.
+pub struct ShufflingCache {
+    capacity: usize,
+    shufflings: Mutex<BTreeMap<u64, Arc<CycleShuffling>>>,
+}
+
+impl ShufflingCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            shufflings: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the committees of `cycle`, if they are cached.
+    pub fn get(&self, cycle: u64) -> Option<Arc<CycleShuffling>> {
+        self.shufflings
+            .lock()
+            .expect("Shuffling cache lock poisoned")
+            .get(&cycle)
+            .cloned()
+    }
+
+    pub fn contains(&self, cycle: u64) -> bool {
+        self.shufflings
+            .lock()
+            .expect("Shuffling cache lock poisoned")
+            .contains_key(&cycle)
+    }
+
+    /// Caches the committees of `cycle`, evicting those of the earliest cycle once full.
+    pub fn insert(&self, cycle: u64, shuffling: Arc<CycleShuffling>) {
+        let mut shufflings = self
+            .shufflings
+            .lock()
+            .expect("Shuffling cache lock poisoned");
+        shufflings.insert(cycle, shuffling);
+        while shufflings.len() > self.capacity {
+            let earliest = *shufflings.keys().next().expect("Not empty");
+            shufflings.remove(&earliest);
+        }
+    }
+}
+
+/// Computes the committees of the next cycle as soon as the head enters a new cycle, storing them
+/// in the shuffling cache of the node, so that the duties requested by validator clients at the
+/// start of the cycle are answered without holding up block import.
+///
+/// The thread is a task of the node's executor, so a panic while shuffling shuts the node down.
+/// It stops when the service is dropped, the node shuts down or the events end.
+pub struct CommitteePrecomputeService {
+    worker: Option<JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl CommitteePrecomputeService {
+    pub fn start<T: ClientDB + 'static>(
+        node: Arc<RwLock<BeaconNode<T>>>,
+        events: Receiver<BeaconNodeEvent>,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> Self {
+        let stopping = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let stopping = stopping.clone();
+            executor.spawn("committee_precompute", move |exit| {
+                while !stopping.load(Ordering::Relaxed) && !exit.is_exiting() {
+                    let slot = match events.recv_timeout(STOP_POLL_INTERVAL) {
+                        Ok(BeaconNodeEvent::Head {
+                            slot,
+                            cycle_transition: true,
+                            ..
+                        }) => slot,
+                        Ok(_) | Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    precompute_next_cycle(&node, slot, &log);
+                }
+            })
+        };
+        Self {
+            worker: Some(worker),
+            stopping,
+        }
+    }
+}
+
+impl Drop for CommitteePrecomputeService {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Caches the committees of the cycle after that of `head_slot`, unless already cached.
+///
+/// The node is only locked to copy the validators, so block import continues while the
+/// validators are shuffled.
+fn precompute_next_cycle<T: ClientDB>(node: &RwLock<BeaconNode<T>>, head_slot: u64, log: &Logger) {
+    let (cycle, validators, config, cache) = {
+        let node = node.read().expect("Beacon node lock poisoned");
+        let cycle = head_slot / u64::from(node.config().cycle_length.max(1)) + 1;
+        if node.shuffling_cache().contains(cycle) {
+            return;
+        }
+        (
+            cycle,
+            node.validators().to_vec(),
+            node.config().clone(),
+            node.shuffling_cache().clone(),
+        )
+    };
+    let timer = start_timer(&metrics::COMMITTEE_PRECOMPUTE_TIMES);
+    match compute_shuffling(&validators, &config) {
+        Ok(shuffling) => {
+            drop(timer);
+            cache.insert(cycle, Arc::new(shuffling));
+            debug!(log, "Pre-computed committees"; "cycle" => cycle, "validators" => validators.len());
+        }
+        Err(e) => {
+            warn!(log, "Unable to pre-compute committees"; "cycle" => cycle, "error" => format!("{:?}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::node::tests::test_node;
+    use super::*;
+    use slog::Discard;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Instant;
+    use types::{BeaconBlock, Hash256};
+
+    #[test]
+    fn test_cache_evicts_earliest_cycle() {
+        let shuffling = |shard: u16| {
+            Arc::new(vec![vec![ShardAndCommittee {
+                shard,
+                committee: vec![],
+            }]])
+        };
+        let cache = ShufflingCache::new(2);
+        cache.insert(3, shuffling(3));
+        cache.insert(1, shuffling(1));
+        cache.insert(2, shuffling(2));
+        assert_eq!(cache.get(1), None);
+        assert_eq!(cache.get(2), Some(shuffling(2)));
+        assert_eq!(cache.get(3), Some(shuffling(3)));
+
+        let disabled = ShufflingCache::new(0);
+        disabled.insert(1, shuffling(1));
+        assert!(!disabled.contains(1));
+    }
+
+    #[test]
+    fn test_precompute_on_cycle_transition() {
+        let mut node = test_node(8);
+        let mut block = BeaconBlock::zero();
+        block.slot = 2;
+        block.ancestor_hashes.push(node.genesis_root());
+        node.process_block(&block, 2).unwrap();
+        let cache = node.shuffling_cache().clone();
+        let expected = compute_shuffling(node.validators(), node.config()).unwrap();
+        let node = Arc::new(RwLock::new(node));
+        assert!(!cache.contains(2));
+
+        /*
+         * Heads within a cycle are ignored, while a head entering cycle 1 caches cycle 2.
+         */
+        let (tx, rx) = channel();
+        let log = Logger::root(Discard, o!());
+        let (executor, _) = TaskExecutor::new(log.clone());
+        let service = CommitteePrecomputeService::start(node.clone(), rx, &executor, log);
+        tx.send(BeaconNodeEvent::Head {
+            slot: 5,
+            root: Hash256::from(5),
+            state_root: Hash256::zero(),
+            cycle_transition: false,
+        })
+        .unwrap();
+        tx.send(BeaconNodeEvent::Head {
+            slot: 2,
+            root: block.canonical_root(),
+            state_root: Hash256::zero(),
+            cycle_transition: true,
+        })
+        .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !cache.contains(2) {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!cache.contains(3));
+        assert_eq!(cache.get(2).unwrap().as_ref(), &expected);
+        drop(service);
+    }
+}
@@ -0,0 +1,16 @@
+use types::Hash256;
+
+/// Unverified evidence that a proposer published two different blocks for the same slot, as
+/// detected from gossip.
+///
+/// Blocks are not yet signed, so anyone can forge a second block for a proposer and the evidence
+/// is only the roots of the two blocks. These slashings are pooled for inspection but never
+/// included in blocks or acted upon.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ProposerSlashing {
+    pub proposer_index: u64,
+    pub slot: u64,
+    /// The root of the first block seen.
+    pub block_root_1: Hash256,
+    pub block_root_2: Hash256,
+}
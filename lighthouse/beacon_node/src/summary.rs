@@ -0,0 +1,69 @@
+use hashing::canonical_hash;
+use merkle_proof::MerkleTree;
+use ssz::SszStream;
+use types::{Hash256, ValidatorRecord};
+
+/*
+ * States are not tree-hashed, so proofs are of a summary of the state: a tree of depth 3 whose
+ * leaves, from generalized index 8, are:
+ *
+ * 8. the slot of the state's block,
+ * 9. the root of the state's block,
+ * 10. the justified slot,
+ * 11. the justified root,
+ * 12. the finalized slot,
+ * 13. the finalized root,
+ * 14. the root of the tree of validators,
+ * 15. zero.
+ *
+ * Slots are big-endian in the last eight bytes of their leaf and each validator's leaf is the hash
+ * of its SSZ encoding, so the validator at index `i` is at `concat_gindices(14, 2^d + i)` for a
+ * validator tree of depth `d`.
+ */
+
+/// The generalized index of the root of the state's block.
+pub const BLOCK_ROOT_GINDEX: usize = 9;
+/// The generalized index of the finalized root.
+pub const FINALIZED_ROOT_GINDEX: usize = 13;
+/// The generalized index of the root of the validators tree.
+pub const VALIDATORS_GINDEX: usize = 14;
+
+/// Returns the summary of the state after the block with `block_root` at `slot`.
+///
+/// Nothing is justified or finalized beyond `finalized_root` until the state transition is
+/// restored, so the justified checkpoint is the finalized one.
+pub fn state_summary(
+    slot: u64,
+    block_root: Hash256,
+    finalized_root: Hash256,
+    validators: &MerkleTree,
+) -> MerkleTree {
+    MerkleTree::new(&[
+        Hash256::from(slot),
+        block_root,
+        Hash256::from(0),
+        finalized_root,
+        Hash256::from(0),
+        finalized_root,
+        validators.root(),
+        Hash256::zero(),
+    ])
+}
+
+/// Returns the tree whose leaves are the hashes of `validators`.
+pub fn validators_tree(validators: &[ValidatorRecord]) -> MerkleTree {
+    MerkleTree::new(&validators.iter().map(validator_leaf).collect::<Vec<_>>())
+}
+
+pub fn validator_leaf(validator: &ValidatorRecord) -> Hash256 {
+    let mut ssz = SszStream::new();
+    ssz.append_encoded_raw(&validator.pubkey.as_bytes());
+    ssz.append(&validator.withdrawal_shard);
+    ssz.append_encoded_raw(&validator.withdrawal_address);
+    ssz.append(&validator.randao_commitment);
+    ssz.append(&validator.randao_last_change);
+    ssz.append(&validator.balance);
+    ssz.append(&validator.status);
+    ssz.append(&validator.exit_slot);
+    Hash256::from(&canonical_hash(&ssz.drain())[..])
+}
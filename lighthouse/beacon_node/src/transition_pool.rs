@@ -0,0 +1,147 @@
+use super::metrics;
+use lighthouse_metrics::{dec_gauge, inc_gauge};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use task_executor::TaskExecutor;
+
+/// The number of threads running state transitions by default.
+pub const DEFAULT_STATE_TRANSITION_THREADS: usize = 1;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs the imports of blocks published to the HTTP API, and the cycle transitions within them,
+/// on dedicated threads, so that a slow transition does not hold up the threads serving the API.
+///
+/// The threads are tasks of the node's executor: a job which panics shuts the node down, rather
+/// than leaving the pool without the thread, and later jobs unanswered.
+///
+/// Jobs are run in the order they are queued, and the number queued but not yet started is
+/// recorded in the `beacon_state_transition_queue_depth` metric. Jobs still queued when the pool
+/// is dropped are run before `drop` returns.
+pub struct StateTransitionPool {
+    jobs: Option<Mutex<Sender<Job>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl StateTransitionPool {
+    /// Starts a pool of `threads` threads, of which there is at least one, on `executor`.
+    pub fn start(threads: usize, executor: &TaskExecutor) -> Self {
+        let (tx, rx) = channel();
+        let rx = Arc::new(Mutex::new(rx));
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let rx = rx.clone();
+                executor.spawn("state_transition", move |_| run(&rx))
+            })
+            .collect();
+        Self {
+            jobs: Some(Mutex::new(tx)),
+            workers,
+        }
+    }
+
+    /// Queues `job` to run on the pool.
+    pub fn spawn<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if let Some(ref jobs) = self.jobs {
+            inc_gauge(&metrics::STATE_TRANSITION_QUEUE_DEPTH);
+            let sent = jobs
+                .lock()
+                .expect("State transition jobs lock poisoned")
+                .send(Box::new(job));
+            if sent.is_err() {
+                dec_gauge(&metrics::STATE_TRANSITION_QUEUE_DEPTH);
+            }
+        }
+    }
+}
+
+impl Drop for StateTransitionPool {
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run(jobs: &Mutex<Receiver<Job>>) {
+    loop {
+        /*
+         * The lock is released before the job is run, so other threads may take jobs.
+         */
+        let job = jobs
+            .lock()
+            .expect("State transition jobs lock poisoned")
+            .recv();
+        match job {
+            Ok(job) => {
+                dec_gauge(&metrics::STATE_TRANSITION_QUEUE_DEPTH);
+                job();
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::{Discard, Logger};
+    use std::thread;
+    use std::time::Duration;
+    use task_executor::ShutdownReason;
+
+    fn executor() -> (TaskExecutor, Receiver<ShutdownReason>) {
+        TaskExecutor::new(Logger::root(Discard, o!()))
+    }
+
+    #[test]
+    fn test_jobs_run_on_pool() {
+        let (executor, _) = executor();
+        let pool = StateTransitionPool::start(2, &executor);
+        let (tx, rx) = channel();
+        for i in 0..4 {
+            let tx = tx.clone();
+            pool.spawn(move || {
+                let name = thread::current().name().map(String::from).unwrap();
+                tx.send((i, name)).unwrap();
+            });
+        }
+        let mut results: Vec<(u64, String)> = (0..4)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        results.sort();
+        assert_eq!(
+            results.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert!(results.iter().all(|(_, name)| name == "state_transition"));
+
+        /*
+         * Queued jobs are completed before the pool is dropped.
+         */
+        let (tx, rx) = channel();
+        pool.spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(()).unwrap();
+        });
+        drop(pool);
+        assert_eq!(rx.try_recv(), Ok(()));
+    }
+
+    #[test]
+    fn test_panicking_job_shuts_down() {
+        let (executor, shutdown) = executor();
+        let pool = StateTransitionPool::start(1, &executor);
+        pool.spawn(|| panic!("Transition failed"));
+        assert_eq!(
+            shutdown.recv_timeout(Duration::from_secs(5)),
+            Ok(ShutdownReason::Panic("state_transition".to_string()))
+        );
+        assert!(executor.exit().is_exiting());
+    }
+}
@@ -0,0 +1,150 @@
+use bls::PublicKey;
+use db::stores::{ValidatorStore, ValidatorStoreError};
+use db::ClientDB;
+use std::collections::HashMap;
+use types::ValidatorRecord;
+
+/// Maps the public key of each validator to its index in the registry, so that validators are
+/// found by key without scanning the registry.
+///
+/// The registry only grows, as deposits are processed, so the map is extended from the last
+/// index it knows. If it is given a store, each entry added is written to it, and the entries
+/// persisted are restored from it on the next start.
+pub struct ValidatorIndexCache<T: ClientDB> {
+    indices: HashMap<Vec<u8>, usize>,
+    /// The number of validators of the registry indexed, counting any which reuse a key.
+    len: usize,
+    store: Option<ValidatorStore<T>>,
+}
+
+impl<T: ClientDB> ValidatorIndexCache<T> {
+    pub fn new() -> Self {
+        Self {
+            indices: HashMap::new(),
+            len: 0,
+            store: None,
+        }
+    }
+
+    /// The number of validators indexed.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, pubkey: &PublicKey) -> Option<usize> {
+        self.indices.get(&pubkey.as_bytes()).cloned()
+    }
+
+    /// Indexes the validators of `validators` which were added to the registry since the last
+    /// update, writing them to the store, if any. Returns the number added.
+    pub fn update(&mut self, validators: &[ValidatorRecord]) -> Result<usize, ValidatorStoreError> {
+        let start = self.len;
+        for (index, validator) in validators.iter().enumerate().skip(start) {
+            if let Some(ref store) = self.store {
+                store.put_public_key_by_index(index, &validator.pubkey)?;
+                store.put_index_by_public_key(&validator.pubkey, index)?;
+            }
+            self.indices.insert(validator.pubkey.as_bytes(), index);
+            self.len = index + 1;
+        }
+        Ok(self.len.saturating_sub(start))
+    }
+
+    /// Replaces the map with the entries persisted in `store` which agree with `validators`, then
+    /// indexes the rest of `validators`, writing them and all later entries to `store`. Returns
+    /// the number of entries restored.
+    ///
+    /// Entries are restored in order of index up to the first which is missing or does not agree
+    /// with the registry, e.g. as it was written by a node of another chain.
+    pub fn load(
+        &mut self,
+        store: ValidatorStore<T>,
+        validators: &[ValidatorRecord],
+    ) -> Result<usize, ValidatorStoreError> {
+        self.indices.clear();
+        self.len = 0;
+        for (index, validator) in validators.iter().enumerate() {
+            if store.get_public_key_by_index(index)?.as_ref() != Some(&validator.pubkey)
+                || store.get_index_by_public_key(&validator.pubkey)? != Some(index)
+            {
+                break;
+            }
+            self.indices.insert(validator.pubkey.as_bytes(), index);
+            self.len = index + 1;
+        }
+        let restored = self.len;
+        self.store = Some(store);
+        self.update(validators)?;
+        Ok(restored)
+    }
+}
+
+impl<T: ClientDB> Default for ValidatorIndexCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::MemoryDB;
+    use std::sync::Arc;
+
+    fn validators(count: usize) -> Vec<ValidatorRecord> {
+        (0..count)
+            .map(|_| ValidatorRecord::zero_with_thread_rand_keypair().0)
+            .collect()
+    }
+
+    #[test]
+    fn test_validator_index_cache() {
+        let db = Arc::new(MemoryDB::open());
+        let mut registry = validators(3);
+        let mut cache = ValidatorIndexCache::<MemoryDB>::new();
+        assert_eq!(cache.update(&registry), Ok(3));
+        assert_eq!(cache.get(&registry[2].pubkey), Some(2));
+        assert_eq!(
+            cache.load(ValidatorStore::new(db.clone()), &registry),
+            Ok(0)
+        );
+
+        /*
+         * Deposits extend the registry, and the entries are written through to the store.
+         */
+        registry.extend(validators(2));
+        assert_eq!(cache.update(&registry), Ok(2));
+        assert_eq!(cache.update(&registry), Ok(0));
+        assert_eq!(cache.get(&registry[4].pubkey), Some(4));
+        assert_eq!(cache.get(&validators(1)[0].pubkey), None);
+        let store = ValidatorStore::new(db.clone());
+        assert_eq!(
+            store.get_index_by_public_key(&registry[3].pubkey),
+            Ok(Some(3))
+        );
+
+        let mut restarted = ValidatorIndexCache::new();
+        assert_eq!(restarted.load(store, &registry), Ok(5));
+        assert_eq!(restarted.len(), 5);
+        assert_eq!(restarted.get(&registry[1].pubkey), Some(1));
+
+        /*
+         * Entries of another registry are not restored, but replaced.
+         */
+        let other = validators(4);
+        let mut other_cache = ValidatorIndexCache::new();
+        assert_eq!(
+            other_cache.load(ValidatorStore::new(db.clone()), &other),
+            Ok(0)
+        );
+        assert_eq!(other_cache.get(&other[3].pubkey), Some(3));
+        assert_eq!(
+            ValidatorStore::new(db).get_index_by_public_key(&other[0].pubkey),
+            Ok(Some(0))
+        );
+    }
+}
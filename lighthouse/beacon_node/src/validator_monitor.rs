@@ -0,0 +1,363 @@
+use super::events::BeaconNodeEvent;
+use super::metrics;
+use super::participation::AccountedCycle;
+use bls::PublicKey;
+use lighthouse_metrics::{inc_counter_vec, set_gauge_vec};
+use slog::Logger;
+use ssz::{Decodable, DecodeError, Encodable, SszStream};
+use std::collections::{BTreeMap, BTreeSet};
+use types::ValidatorRecord;
+
+/// A validator to be monitored, by public key or by index.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValidatorId {
+    Pubkey(PublicKey),
+    Index(usize),
+}
+
+/// What has been seen of a monitored validator in the blocks imported since the node started.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MonitoredValidator {
+    pub pubkey: PublicKey,
+    pub balance: u64,
+    pub attestations_included: u64,
+    /// The slot of the latest attestation included, counted once however many blocks include it.
+    pub latest_attestation_slot: Option<u64>,
+    pub blocks_proposed: u64,
+}
+
+/// Whether the attestation of a monitored validator in an accounted cycle was included, and at
+/// what inclusion distance.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MonitoredAttestation {
+    pub index: u64,
+    pub inclusion_distance: Option<u64>,
+}
+
+impl Encodable for MonitoredAttestation {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.index);
+        s.append(&u8::from(self.inclusion_distance.is_some()));
+        s.append(&self.inclusion_distance.unwrap_or(0));
+    }
+}
+
+impl Decodable for MonitoredAttestation {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (index, i) = u64::ssz_decode(bytes, i)?;
+        let (included, i) = u8::ssz_decode(bytes, i)?;
+        let (distance, i) = u64::ssz_decode(bytes, i)?;
+        let attestation = Self {
+            index,
+            inclusion_distance: if included == 1 { Some(distance) } else { None },
+        };
+        Ok((attestation, i))
+    }
+}
+
+/// Logs and records metrics for the attestations, block proposals and balance changes of chosen
+/// validators, from the events of the chain.
+///
+/// The metrics are labelled by validator index.
+pub struct ValidatorMonitor {
+    validators: BTreeMap<usize, MonitoredValidator>,
+    log: Logger,
+}
+
+impl ValidatorMonitor {
+    /// Monitors the validators of `ids` which are in `validators`, warning of any which are not.
+    pub fn new(ids: &[ValidatorId], validators: &[ValidatorRecord], log: Logger) -> Self {
+        let mut monitored = BTreeMap::new();
+        for id in ids {
+            let index = match id {
+                ValidatorId::Pubkey(pubkey) => validators.iter().position(|v| v.pubkey == *pubkey),
+                ValidatorId::Index(index) if *index < validators.len() => Some(*index),
+                ValidatorId::Index(_) => None,
+            };
+            let index = match index {
+                Some(index) => index,
+                None => {
+                    warn!(log, "Monitored validator unknown"; "validator" => format!("{:?}", id));
+                    continue;
+                }
+            };
+            let validator = &validators[index];
+            set_gauge_vec(
+                &metrics::MONITOR_BALANCE,
+                &[&index.to_string()],
+                validator.balance as i64,
+            );
+            monitored.insert(
+                index,
+                MonitoredValidator {
+                    pubkey: validator.pubkey.clone(),
+                    balance: validator.balance,
+                    attestations_included: 0,
+                    latest_attestation_slot: None,
+                    blocks_proposed: 0,
+                },
+            );
+        }
+        info!(log, "Validator monitor started"; "validators" => monitored.len());
+        Self {
+            validators: monitored,
+            log,
+        }
+    }
+
+    /// Returns the monitored validator with `index`.
+    pub fn validator(&self, index: usize) -> Option<&MonitoredValidator> {
+        self.validators.get(&index)
+    }
+
+    /// Returns the indices of the monitored validators, in ascending order.
+    pub fn indices(&self) -> Vec<usize> {
+        self.validators.keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.validators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+
+    /// Returns the attestations of the monitored validators among `committee_members` in the
+    /// cycle of `accounted`, by ascending index.
+    pub fn cycle_attestations(
+        &self,
+        accounted: &AccountedCycle,
+        committee_members: &BTreeSet<usize>,
+    ) -> Vec<MonitoredAttestation> {
+        self.validators
+            .keys()
+            .filter(|index| committee_members.contains(index))
+            .map(|index| MonitoredAttestation {
+                index: *index as u64,
+                inclusion_distance: accounted.inclusion_distances.get(index).cloned(),
+            })
+            .collect()
+    }
+
+    /// Records a block imported or an attestation pooled. Balances are compared with those of
+    /// `validators`.
+    pub fn process_event(&mut self, event: &BeaconNodeEvent, validators: &[ValidatorRecord]) {
+        match event {
+            BeaconNodeEvent::Block {
+                slot,
+                proposer,
+                attestations,
+                ..
+            } => self.process_block(*slot, *proposer, attestations, validators),
+            BeaconNodeEvent::Attestation {
+                attestation,
+                participants,
+            } => {
+                for index in participants {
+                    if self.validators.contains_key(index) {
+                        info!(self.log, "Monitored validator attestation seen";
+                              "index" => index,
+                              "slot" => attestation.data.slot,
+                              "shard" => attestation.data.shard);
+                    }
+                }
+            }
+            BeaconNodeEvent::ProposerSlashing(slashing) => {
+                let index = slashing.proposer_index as usize;
+                if self.validators.contains_key(&index) {
+                    warn!(self.log, "Unverified equivocation by monitored validator";
+                          "index" => index,
+                          "slot" => slashing.slot,
+                          "first_root" => format!("{:?}", slashing.block_root_1),
+                          "second_root" => format!("{:?}", slashing.block_root_2));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Records an imported block of `slot`, proposed by `proposer`, which includes attestations
+    /// of the slots and attesting validators of `attestations`. Balances are compared with
+    /// those of `validators`.
+    pub fn process_block(
+        &mut self,
+        slot: u64,
+        proposer: Option<usize>,
+        attestations: &[(u64, Vec<usize>)],
+        validators: &[ValidatorRecord],
+    ) {
+        if let Some(proposer) = proposer {
+            if let Some(monitored) = self.validators.get_mut(&proposer) {
+                monitored.blocks_proposed += 1;
+                inc_counter_vec(&metrics::MONITOR_BLOCKS_PROPOSED, &[&proposer.to_string()]);
+                info!(self.log, "Monitored validator proposed block"; "index" => proposer, "slot" => slot);
+            }
+        }
+
+        for (attestation_slot, attesters) in attestations {
+            for index in attesters {
+                let monitored = match self.validators.get_mut(index) {
+                    Some(monitored) => monitored,
+                    None => continue,
+                };
+                match monitored.latest_attestation_slot {
+                    Some(latest) if latest >= *attestation_slot => continue,
+                    _ => {}
+                }
+                let delay = slot.saturating_sub(*attestation_slot);
+                monitored.attestations_included += 1;
+                monitored.latest_attestation_slot = Some(*attestation_slot);
+                let label = index.to_string();
+                inc_counter_vec(&metrics::MONITOR_ATTESTATIONS_INCLUDED, &[&label]);
+                set_gauge_vec(&metrics::MONITOR_INCLUSION_DELAY, &[&label], delay as i64);
+                info!(self.log, "Monitored validator attestation included";
+                      "index" => index,
+                      "attestation_slot" => attestation_slot,
+                      "block_slot" => slot,
+                      "inclusion_delay" => delay);
+            }
+        }
+
+        for (index, monitored) in &mut self.validators {
+            let balance = match validators.get(*index) {
+                Some(validator) => validator.balance,
+                None => continue,
+            };
+            if balance != monitored.balance {
+                info!(self.log, "Monitored validator balance changed";
+                      "index" => index,
+                      "balance" => balance,
+                      "change" => balance as i64 - monitored.balance as i64);
+                monitored.balance = balance;
+                set_gauge_vec(
+                    &metrics::MONITOR_BALANCE,
+                    &[&index.to_string()],
+                    balance as i64,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::participation::CycleParticipation;
+    use super::*;
+    use slog::Discard;
+    use ssz::ssz_encode;
+    use types::{Attestation, Hash256};
+
+    fn validators(count: usize) -> Vec<ValidatorRecord> {
+        (0..count)
+            .map(|_| ValidatorRecord::zero_with_thread_rand_keypair().0)
+            .collect()
+    }
+
+    #[test]
+    fn test_new_resolves_validators() {
+        let validators = validators(4);
+        let ids = vec![
+            ValidatorId::Index(1),
+            ValidatorId::Pubkey(validators[3].pubkey.clone()),
+            ValidatorId::Index(4),
+            ValidatorId::Pubkey(ValidatorRecord::zero_with_thread_rand_keypair().0.pubkey),
+        ];
+        let monitor = ValidatorMonitor::new(&ids, &validators, Logger::root(Discard, o!()));
+        assert_eq!(monitor.len(), 2);
+        assert_eq!(monitor.validator(3).unwrap().pubkey, validators[3].pubkey);
+        assert!(monitor.validator(1).is_some());
+        assert!(monitor.validator(0).is_none());
+    }
+
+    #[test]
+    fn test_process_block() {
+        let mut validators = validators(4);
+        let ids = vec![ValidatorId::Index(0), ValidatorId::Index(2)];
+        let mut monitor = ValidatorMonitor::new(&ids, &validators, Logger::root(Discard, o!()));
+
+        monitor.process_block(3, Some(2), &[(1, vec![0, 1]), (2, vec![2])], &validators);
+        let first = monitor.validator(0).unwrap().clone();
+        assert_eq!(first.attestations_included, 1);
+        assert_eq!(first.latest_attestation_slot, Some(1));
+        assert_eq!(first.blocks_proposed, 0);
+        assert_eq!(monitor.validator(2).unwrap().blocks_proposed, 1);
+
+        /*
+         * An attestation included again, e.g. in an aggregate, is only counted once.
+         */
+        validators[0].balance = 5;
+        monitor.process_block(4, Some(1), &[(1, vec![0]), (3, vec![2])], &validators);
+        assert_eq!(monitor.validator(0).unwrap().attestations_included, 1);
+        assert_eq!(monitor.validator(0).unwrap().balance, 5);
+        assert_eq!(monitor.validator(2).unwrap().attestations_included, 2);
+        assert_eq!(monitor.validator(2).unwrap().blocks_proposed, 1);
+    }
+
+    #[test]
+    fn test_process_event() {
+        let validators = validators(4);
+        let ids = vec![ValidatorId::Index(1)];
+        let mut monitor = ValidatorMonitor::new(&ids, &validators, Logger::root(Discard, o!()));
+
+        let block = BeaconNodeEvent::Block {
+            slot: 3,
+            root: Hash256::from(3),
+            proposer: Some(1),
+            attestations: vec![(2, vec![0, 1])],
+        };
+        monitor.process_event(&block, &validators);
+        let monitored = monitor.validator(1).unwrap();
+        assert_eq!(monitored.blocks_proposed, 1);
+        assert_eq!(monitored.attestations_included, 1);
+        assert_eq!(monitored.latest_attestation_slot, Some(2));
+
+        /*
+         * Attestations seen before inclusion are only logged.
+         */
+        let attestation = BeaconNodeEvent::Attestation {
+            attestation: Attestation::zero(),
+            participants: vec![1],
+        };
+        monitor.process_event(&attestation, &validators);
+        assert_eq!(monitor.validator(1).unwrap().attestations_included, 1);
+    }
+
+    #[test]
+    fn test_cycle_attestations() {
+        let validators = validators(4);
+        let ids = vec![
+            ValidatorId::Index(0),
+            ValidatorId::Index(1),
+            ValidatorId::Index(3),
+        ];
+        let monitor = ValidatorMonitor::new(&ids, &validators, Logger::root(Discard, o!()));
+        let accounted = AccountedCycle {
+            cycle: 2,
+            participation: CycleParticipation::default(),
+            inclusion_distances: vec![(1, 2), (2, 1)].into_iter().collect(),
+        };
+        let members: BTreeSet<usize> = (0..3).collect();
+        let attestations = monitor.cycle_attestations(&accounted, &members);
+        assert_eq!(
+            attestations,
+            vec![
+                MonitoredAttestation {
+                    index: 0,
+                    inclusion_distance: None,
+                },
+                MonitoredAttestation {
+                    index: 1,
+                    inclusion_distance: Some(2),
+                },
+            ]
+        );
+        for attestation in attestations {
+            let ssz = ssz_encode(&attestation);
+            assert_eq!(
+                MonitoredAttestation::ssz_decode(&ssz, 0),
+                Ok((attestation, ssz.len()))
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use bls::PublicKey;
+use types::{Address, ValidatorRecord, ValidatorStatus};
+
+/// The epochs between a validator's exit and its balance becoming withdrawable.
+///
+/// The state transition does not yet process withdrawals, so this is the phase 0 value of
+/// `MIN_VALIDATOR_WITHDRAWABILITY_DELAY`.
+pub const WITHDRAWABILITY_DELAY_EPOCHS: u64 = 256;
+
+/// Where the balance of a validator is withdrawn to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WithdrawalCredentials {
+    /// Withdrawn to an eth1 address on the withdrawal shard.
+    Eth1Address { shard: u16, address: Address },
+    /// The withdrawal address is zero, so the balance could never be withdrawn.
+    Unset { shard: u16 },
+}
+
+/// The withdrawal credentials and exit of a validator, as found in a state.
+#[derive(Debug, PartialEq, Clone)]
+pub struct WithdrawalReport {
+    pub index: usize,
+    pub pubkey: PublicKey,
+    pub status: u8,
+    pub credentials: WithdrawalCredentials,
+    /// The epoch of the validator's exit, if it has exited or is exiting.
+    pub exit_epoch: Option<u64>,
+    /// The first epoch in which the validator's balance may be withdrawn, if it has exited or is
+    /// exiting.
+    pub withdrawable_epoch: Option<u64>,
+}
+
+impl WithdrawalReport {
+    /// Reports on the validator at `index`, whose slots are converted to epochs of
+    /// `epoch_length` slots.
+    pub fn new(index: usize, validator: &ValidatorRecord, epoch_length: u64) -> Self {
+        let credentials = if validator.withdrawal_address.is_zero() {
+            WithdrawalCredentials::Unset {
+                shard: validator.withdrawal_shard,
+            }
+        } else {
+            WithdrawalCredentials::Eth1Address {
+                shard: validator.withdrawal_shard,
+                address: validator.withdrawal_address,
+            }
+        };
+
+        /*
+         * The exit slot is only set once a validator begins to exit.
+         */
+        let exiting = validator.status != ValidatorStatus::PendingActivation as u8
+            && validator.status != ValidatorStatus::Active as u8;
+        let exit_epoch = if exiting {
+            Some(validator.exit_slot / epoch_length.max(1))
+        } else {
+            None
+        };
+
+        Self {
+            index,
+            pubkey: validator.pubkey.clone(),
+            status: validator.status,
+            credentials,
+            exit_epoch,
+            withdrawable_epoch: exit_epoch
+                .map(|epoch| epoch.saturating_add(WITHDRAWABILITY_DELAY_EPOCHS)),
+        }
+    }
+
+    /// Returns true if the balance may be withdrawn in `epoch`.
+    pub fn is_withdrawable_at(&self, epoch: u64) -> bool {
+        match self.withdrawable_epoch {
+            Some(withdrawable_epoch) => epoch >= withdrawable_epoch,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_withdrawal_report() {
+        let (mut validator, _) = ValidatorRecord::zero_with_thread_rand_keypair();
+        validator.status = ValidatorStatus::Active as u8;
+        validator.exit_slot = 640;
+        let report = WithdrawalReport::new(3, &validator, 64);
+        assert_eq!(report.index, 3);
+        assert_eq!(
+            report.credentials,
+            WithdrawalCredentials::Unset { shard: 0 }
+        );
+        assert_eq!(report.exit_epoch, None);
+        assert_eq!(report.withdrawable_epoch, None);
+        assert!(!report.is_withdrawable_at(1 << 40));
+
+        validator.status = ValidatorStatus::PendingExit as u8;
+        validator.withdrawal_shard = 2;
+        validator.withdrawal_address = Address::from(7);
+        let report = WithdrawalReport::new(3, &validator, 64);
+        assert_eq!(
+            report.credentials,
+            WithdrawalCredentials::Eth1Address {
+                shard: 2,
+                address: Address::from(7),
+            }
+        );
+        assert_eq!(report.exit_epoch, Some(10));
+        assert_eq!(
+            report.withdrawable_epoch,
+            Some(10 + WITHDRAWABILITY_DELAY_EPOCHS)
+        );
+        assert!(!report.is_withdrawable_at(10 + WITHDRAWABILITY_DELAY_EPOCHS - 1));
+        assert!(report.is_withdrawable_at(10 + WITHDRAWABILITY_DELAY_EPOCHS));
+    }
+}
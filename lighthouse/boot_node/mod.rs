@@ -0,0 +1,91 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+
+use clap::ArgMatches;
+use network::{BootNode, BootNodeConfig, DiscoveryEvent, Enr};
+use slog::Logger;
+use task_executor::TaskExecutor;
+
+/// The directory within the data dir holding the boot node key and record.
+const BOOT_NODE_DIR: &str = "boot_node";
+
+/// Runs the boot node until the process is killed.
+pub fn run(matches: &ArgMatches, data_dir: &Path, log: &Logger) {
+    let config = match parse_config(matches, data_dir) {
+        Ok(config) => config,
+        Err(e) => {
+            error!(log, "Invalid boot node configuration"; "error" => e);
+            return;
+        }
+    };
+
+    /*
+     * Should the discovery thread panic, its events end, and so does the boot node.
+     */
+    let (executor, _shutdown) = TaskExecutor::new(log.clone());
+    let (_boot_node, events) = match BootNode::start(config, &executor, log.clone()) {
+        Ok(started) => started,
+        Err(e) => {
+            error!(log, "Unable to start boot node"; "error" => format!("{:?}", e));
+            return;
+        }
+    };
+
+    /*
+     * The boot node answers queries on its own thread. The events of its periodic queries, which
+     * keep its routing table fresh, are only logged.
+     */
+    for event in events.iter() {
+        match event {
+            DiscoveryEvent::PeersDiscovered { peers, .. } => {
+                debug!(log, "Discovered peers"; "count" => peers.len())
+            }
+            DiscoveryEvent::QueryComplete { found, .. } => {
+                info!(log, "Discovery query complete"; "found" => found)
+            }
+        }
+    }
+}
+
+fn parse_config(matches: &ArgMatches, data_dir: &Path) -> Result<BootNodeConfig, String> {
+    let listen_ip: IpAddr = matches
+        .value_of("listen-address")
+        .unwrap_or("0.0.0.0")
+        .parse()
+        .map_err(|_| "Invalid listen address".to_string())?;
+    let port: u16 = matches
+        .value_of("port")
+        .unwrap_or("9000")
+        .parse()
+        .map_err(|_| "Invalid port".to_string())?;
+    let enr_address = match matches.value_of("enr-address") {
+        Some(address) => Some(
+            address
+                .parse::<Ipv4Addr>()
+                .map_err(|_| "Invalid ENR address".to_string())?,
+        ),
+        None => None,
+    };
+    let enr_port = match matches.value_of("enr-port") {
+        Some(port) => Some(
+            port.parse::<u16>()
+                .map_err(|_| "Invalid ENR port".to_string())?,
+        ),
+        None => None,
+    };
+    let bootnodes = match matches.value_of("boot-nodes") {
+        Some(enrs) => enrs
+            .split(',')
+            .map(|enr| enr.trim().parse::<Enr>())
+            .collect::<Result<Vec<Enr>, String>>()?,
+        None => vec![],
+    };
+
+    Ok(BootNodeConfig {
+        listen_addr: SocketAddr::new(listen_ip, port),
+        enr_address,
+        enr_port,
+        bootnodes,
+        dir: data_dir.join(BOOT_NODE_DIR),
+    })
+}
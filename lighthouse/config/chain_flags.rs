@@ -0,0 +1,97 @@
+use super::Flags;
+use beacon_node::{DiskGuardConfig, WeakSubjectivityCheckpoint};
+use db::stores::StatePruning;
+use hex;
+use std::time::Duration;
+use types::{ChainConfig, Hash256};
+
+/// Applies the chain flags to `config`.
+pub fn parse_chain_config(flags: &Flags, config: &mut ChainConfig) -> Result<(), String> {
+    if let Some(genesis_time) = flags.parse::<u64>("genesis-time")? {
+        config.genesis_time = genesis_time;
+    }
+    Ok(())
+}
+
+/// Applies the `--clock-disparity-millis` flag to `clock_disparity`.
+pub fn parse_clock_disparity(flags: &Flags, clock_disparity: &mut Duration) -> Result<(), String> {
+    if let Some(millis) = flags.parse::<u64>("clock-disparity-millis")? {
+        *clock_disparity = Duration::from_millis(millis);
+    }
+    Ok(())
+}
+
+/// Applies the `--state-transition-threads` flag to `threads`. At least one thread is needed.
+pub fn parse_state_transition_threads(flags: &Flags, threads: &mut usize) -> Result<(), String> {
+    if let Some(count) = flags.parse::<usize>("state-transition-threads")? {
+        if count == 0 {
+            return Err(flags.invalid("state-transition-threads", "0"));
+        }
+        *threads = count;
+    }
+    Ok(())
+}
+
+/// Applies the `--import-checkpoint-slots` flag to `slots`. Zero would persist the chain after
+/// every batch, which the flag exists to avoid.
+pub fn parse_import_checkpoint(flags: &Flags, slots: &mut u64) -> Result<(), String> {
+    if let Some(count) = flags.parse::<u64>("import-checkpoint-slots")? {
+        if count == 0 {
+            return Err(flags.invalid("import-checkpoint-slots", "0"));
+        }
+        *slots = count;
+    }
+    Ok(())
+}
+
+/// Parses the `--prune-states` and `--archive` flags, of which at most one may be given.
+pub fn parse_state_pruning(flags: &Flags) -> Result<StatePruning, String> {
+    match (
+        flags.is_present("prune-states"),
+        flags.is_present("archive"),
+    ) {
+        (true, true) => Err("--prune-states and --archive conflict".to_string()),
+        (_, true) => Ok(StatePruning::Archive),
+        _ => Ok(StatePruning::Minimal),
+    }
+}
+
+/// Applies the `--disk-prune-threshold-mb` and `--disk-halt-threshold-mb` flags to `config`. Import
+/// must halt before pruning is no longer enough.
+pub fn parse_disk_guard_config(flags: &Flags, config: &mut DiskGuardConfig) -> Result<(), String> {
+    if let Some(mb) = flags.parse::<u64>("disk-prune-threshold-mb")? {
+        config.prune_threshold_mb = mb;
+    }
+    if let Some(mb) = flags.parse::<u64>("disk-halt-threshold-mb")? {
+        config.halt_threshold_mb = mb;
+    }
+    if config.halt_threshold_mb > config.prune_threshold_mb {
+        return Err("--disk-halt-threshold-mb exceeds --disk-prune-threshold-mb".to_string());
+    }
+    Ok(())
+}
+
+/// Parses the `--weak-subjectivity-checkpoint` flag, a `0x`-prefixed block root and its slot
+/// separated by a colon.
+pub fn parse_weak_subjectivity_checkpoint(
+    flags: &Flags,
+) -> Result<Option<WeakSubjectivityCheckpoint>, String> {
+    let value = match flags.value_of("weak-subjectivity-checkpoint") {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let mut parts = value.splitn(2, ':');
+    let root = parts
+        .next()
+        .and_then(|root| root.strip_prefix("0x"))
+        .and_then(|root| hex::decode(root).ok())
+        .filter(|bytes| bytes.len() == 32);
+    let slot = parts.next().and_then(|slot| slot.parse::<u64>().ok());
+    match (root, slot) {
+        (Some(root), Some(slot)) => Ok(Some(WeakSubjectivityCheckpoint {
+            slot,
+            root: Hash256::from(&root[..]),
+        })),
+        _ => Err(flags.invalid("weak-subjectivity-checkpoint", value)),
+    }
+}
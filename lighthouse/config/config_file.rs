@@ -0,0 +1,263 @@
+use clap::ArgMatches;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use yaml_rust::{Yaml, YamlLoader};
+
+/// Whether a key of the config file is a flag without a value, or an option with one.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum KeyKind {
+    Switch,
+    Value,
+}
+
+/// The keys of the beacon node, at the top level of the config file.
+const BEACON_NODE_KEYS: &[(&str, KeyKind)] = &[
+    ("datadir", KeyKind::Value),
+    ("network", KeyKind::Value),
+    ("testnet-dir", KeyKind::Value),
+    ("listen-address", KeyKind::Value),
+    ("port", KeyKind::Value),
+    ("discovery-port", KeyKind::Value),
+    ("target-peers", KeyKind::Value),
+    ("boot-nodes", KeyKind::Value),
+    ("enr-address", KeyKind::Value),
+    ("enr-tcp-port", KeyKind::Value),
+    ("enr-udp-port", KeyKind::Value),
+    ("disable-discovery", KeyKind::Switch),
+    ("disable-upnp", KeyKind::Switch),
+    ("ban-peers", KeyKind::Value),
+    ("ban-cidrs", KeyKind::Value),
+    ("rpc", KeyKind::Switch),
+    ("rpc-address", KeyKind::Value),
+    ("rpc-port", KeyKind::Value),
+    ("http", KeyKind::Switch),
+    ("http-address", KeyKind::Value),
+    ("http-port", KeyKind::Value),
+    ("http-allow-origin", KeyKind::Value),
+    ("http-tls-cert", KeyKind::Value),
+    ("http-tls-key", KeyKind::Value),
+    ("http-read-only", KeyKind::Switch),
+    ("monitoring-endpoint", KeyKind::Value),
+    ("monitoring-interval", KeyKind::Value),
+    ("genesis-time", KeyKind::Value),
+    ("clock-disparity-millis", KeyKind::Value),
+    ("weak-subjectivity-checkpoint", KeyKind::Value),
+    ("ignore-weak-subjectivity", KeyKind::Switch),
+    ("state-transition-threads", KeyKind::Value),
+    ("import-checkpoint-slots", KeyKind::Value),
+    ("prune-states", KeyKind::Switch),
+    ("archive", KeyKind::Switch),
+    ("disk-prune-threshold-mb", KeyKind::Value),
+    ("disk-halt-threshold-mb", KeyKind::Value),
+    ("eth1", KeyKind::Switch),
+    ("eth1-endpoints", KeyKind::Value),
+    ("validators-monitor", KeyKind::Value),
+    ("participation-retention-cycles", KeyKind::Value),
+    ("log-format", KeyKind::Value),
+    ("log-level", KeyKind::Value),
+    ("log-filter", KeyKind::Value),
+    ("log-file", KeyKind::Value),
+    ("log-max-size", KeyKind::Value),
+    ("log-max-files", KeyKind::Value),
+    ("log-dedup-interval", KeyKind::Value),
+];
+
+/// The section of the config file holding the validator client's keys.
+const VALIDATOR_CLIENT_SECTION: &str = "validator_client";
+
+/// The keys of the validator client, in the `validator_client` section of the config file.
+const VALIDATOR_CLIENT_KEYS: &[(&str, KeyKind)] = &[
+    ("beacon-nodes", KeyKind::Value),
+    ("doppelganger-cycles", KeyKind::Value),
+    ("graffiti", KeyKind::Value),
+    ("graffiti-file", KeyKind::Value),
+    ("interop-validators", KeyKind::Value),
+    ("metrics", KeyKind::Switch),
+    ("metrics-address", KeyKind::Value),
+    ("metrics-port", KeyKind::Value),
+];
+
+/// The values of one command's keys in the config file, by the name of the flag each sets.
+///
+/// Switches are stored as `true` or `false`, and lists joined with commas, as on the command
+/// line.
+#[derive(Debug, Default)]
+pub struct ConfigSection {
+    values: BTreeMap<String, String>,
+}
+
+/// A YAML file setting the flags of the beacon node and, under `validator_client`, those of the
+/// validator client, with the same names as the flags, e.g.
+///
+/// ```yaml
+/// datadir: /var/lib/lighthouse
+/// http: true
+/// boot-nodes: [enr:-Iu4QL..., enr:-Iu4QM...]
+/// validator_client:
+///   beacon-nodes: [http://localhost:5052]
+///   graffiti: my node
+/// ```
+///
+/// Flags on the command line override values in the file.
+pub struct ConfigFile {
+    path: PathBuf,
+    pub beacon_node: ConfigSection,
+    pub validator_client: ConfigSection,
+}
+
+impl ConfigFile {
+    /// Reads the file at `path`, returning an error naming any key which is unknown or has a
+    /// value of the wrong type.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let docs = YamlLoader::load_from_str(&contents)
+            .map_err(|e| format!("Invalid YAML in {}: {}", path.display(), e))?;
+        let root = match docs.into_iter().next() {
+            Some(root) => root,
+            None => Yaml::Hash(Default::default()),
+        };
+        let error = |key: &str, message: &str| format!("{}: {}: {}", path.display(), key, message);
+
+        let mut beacon_node = ConfigSection::default();
+        let mut validator_client = ConfigSection::default();
+        for (key, value) in mapping(&root).ok_or_else(|| error("(root)", "expected a mapping"))? {
+            let key = key
+                .as_str()
+                .ok_or_else(|| error(&format!("{:?}", key), "expected a string key"))?;
+            if key == VALIDATOR_CLIENT_SECTION {
+                let section = mapping(value).ok_or_else(|| error(key, "expected a mapping"))?;
+                for (sub_key, value) in section {
+                    let sub_key = sub_key.as_str().unwrap_or("");
+                    let name = format!("{}.{}", key, sub_key);
+                    let value = section_value(VALIDATOR_CLIENT_KEYS, sub_key, value)
+                        .map_err(|message| error(&name, &message))?;
+                    validator_client.values.insert(sub_key.to_string(), value);
+                }
+                continue;
+            }
+            let value = section_value(BEACON_NODE_KEYS, key, value)
+                .map_err(|message| error(key, &message))?;
+            beacon_node.values.insert(key.to_string(), value);
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            beacon_node,
+            validator_client,
+        })
+    }
+}
+
+/// Returns the entries of `yaml`, if it is a mapping. An empty document is an empty mapping.
+pub fn mapping(yaml: &Yaml) -> Option<Vec<(&Yaml, &Yaml)>> {
+    match yaml {
+        Yaml::Hash(hash) => Some(hash.iter().collect()),
+        Yaml::Null => Some(vec![]),
+        _ => None,
+    }
+}
+
+/// Checks that `key` is one of `keys`, returning its value as it would be on the command line.
+fn section_value(keys: &[(&str, KeyKind)], key: &str, value: &Yaml) -> Result<String, String> {
+    let kind = keys
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, kind)| *kind)
+        .ok_or_else(|| "unknown key".to_string())?;
+    match (kind, value) {
+        (KeyKind::Switch, Yaml::Boolean(enabled)) => Ok(enabled.to_string()),
+        (KeyKind::Switch, _) => Err("expected true or false".to_string()),
+        (KeyKind::Value, Yaml::Array(items)) => items
+            .iter()
+            .map(scalar)
+            .collect::<Option<Vec<String>>>()
+            .map(|items| items.join(","))
+            .ok_or_else(|| "expected a list of values".to_string()),
+        (KeyKind::Value, value) => scalar(value).ok_or_else(|| "expected a value".to_string()),
+    }
+}
+
+fn scalar(yaml: &Yaml) -> Option<String> {
+    match yaml {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// The flags of a command, taken from its command line or else from its section of the config
+/// file, or else their defaults.
+pub struct Flags<'a> {
+    matches: &'a ArgMatches<'a>,
+    file: Option<(&'a Path, &'a ConfigSection)>,
+}
+
+impl<'a> Flags<'a> {
+    /// Takes flags not on the command line from `section` of `file`, if any.
+    pub fn with_file(
+        matches: &'a ArgMatches<'a>,
+        file: Option<&'a ConfigFile>,
+        section: fn(&ConfigFile) -> &ConfigSection,
+    ) -> Self {
+        Self {
+            matches,
+            file: file.map(|file| (file.path.as_path(), section(file))),
+        }
+    }
+
+    /// The command line, e.g. to find subcommands.
+    pub fn matches(&self) -> &'a ArgMatches<'a> {
+        self.matches
+    }
+
+    fn file_value(&self, name: &str) -> Option<&'a str> {
+        if self.matches.occurrences_of(name) > 0 {
+            return None;
+        }
+        self.file
+            .and_then(|(_, section)| section.values.get(name))
+            .map(String::as_str)
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<&'a str> {
+        self.file_value(name)
+            .or_else(|| self.matches.value_of(name))
+    }
+
+    pub fn is_present(&self, name: &str) -> bool {
+        match self.file_value(name) {
+            Some(value) => value == "true",
+            None => self.matches.is_present(name),
+        }
+    }
+
+    /// Parses the value of the flag `name`, if set.
+    pub fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value_of(name) {
+            Some(value) => value
+                .parse::<T>()
+                .map(Some)
+                .map_err(|_| self.invalid(name, value)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns an error for the invalid `value` of the flag `name`, pointing at the config file
+    /// if it was set there.
+    pub fn invalid(&self, name: &str, value: &str) -> String {
+        match (self.file, self.file_value(name)) {
+            (Some((path, _)), Some(_)) => {
+                format!(
+                    "Invalid value for {} in {}: {}",
+                    name,
+                    path.display(),
+                    value
+                )
+            }
+            _ => format!("Invalid value for --{}: {}", name, value),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use super::Flags;
+use eth1::Eth1Config;
+
+/// Applies the eth1 flags to `config`.
+pub fn parse_eth1_config(flags: &Flags, config: &mut Eth1Config) -> Result<(), String> {
+    if flags.is_present("eth1") {
+        config.enabled = true;
+    }
+    if let Some(endpoints) = flags.value_of("eth1-endpoints") {
+        config.enabled = true;
+        config.endpoints = endpoints
+            .split(',')
+            .map(|endpoint| endpoint.trim().to_string())
+            .filter(|endpoint| !endpoint.is_empty())
+            .collect();
+        if config.endpoints.is_empty() {
+            return Err(flags.invalid("eth1-endpoints", endpoints));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,480 @@
+use super::config_file::mapping;
+use super::Flags;
+use bls::{create_proof_of_possession, PublicKey, Signature};
+use eth1::Eth1Config;
+use hex;
+use network::Enr;
+use std::fs;
+use std::path::Path;
+use types::{Address, ChainConfig, Hash256, ValidatorRegistration};
+use validator_client::interop_keypairs;
+use yaml_rust::{Yaml, YamlLoader};
+
+/// The network used when neither `--network` nor `--testnet-dir` is given.
+pub const DEFAULT_NETWORK: &str = "mainnet";
+
+/// The configuration of the chain, by the names of the `/eth/v1/config/spec` endpoint.
+const CONFIG_FILE: &str = "config.yaml";
+/// Overrides the constants of the preset named by the `PRESET_BASE` of the config.
+const PRESET_FILE: &str = "preset.yaml";
+/// The validators of genesis.
+const GENESIS_FILE: &str = "genesis.yaml";
+/// The records of the nodes used to join the network.
+const BOOT_ENR_FILE: &str = "boot_enr.yaml";
+
+/// The preset of a network whose config gives no `PRESET_BASE`.
+const DEFAULT_PRESET: &str = "mainnet";
+
+/// The preset which replaces that of every network when `--preset` is not given, if any.
+#[cfg(feature = "minimal")]
+const BUILD_PRESET: Option<&str> = Some("minimal");
+#[cfg(not(feature = "minimal"))]
+const BUILD_PRESET: Option<&str> = None;
+
+/// The presets bundled with Lighthouse: the constants which are shared by many networks, as
+/// distinct from the configuration of each network.
+const BUILT_IN_PRESETS: &[(&str, &str)] = &[
+    ("mainnet", include_str!("presets/mainnet.yaml")),
+    ("minimal", include_str!("presets/minimal.yaml")),
+];
+
+/// The files of a network bundled with Lighthouse.
+struct BuiltInNetwork {
+    name: &'static str,
+    config: &'static str,
+    genesis: Option<&'static str>,
+    boot_enr: Option<&'static str>,
+}
+
+const BUILT_IN_NETWORKS: &[BuiltInNetwork] = &[
+    BuiltInNetwork {
+        name: "mainnet",
+        config: include_str!("networks/mainnet/config.yaml"),
+        genesis: None,
+        boot_enr: None,
+    },
+    BuiltInNetwork {
+        name: "interop",
+        config: include_str!("networks/interop/config.yaml"),
+        genesis: Some(include_str!("networks/interop/genesis.yaml")),
+        boot_enr: None,
+    },
+];
+
+/// A network the node may join, which has its own directory within the data dir so that the
+/// databases of different networks are never mixed.
+#[derive(Debug, Clone)]
+pub struct Eth2Network {
+    pub name: String,
+    /// The preset of the constants of the chain.
+    pub preset: String,
+    pub chain: ChainConfig,
+    /// The eth1 chain of the deposit contract, followed once endpoints are given.
+    pub eth1: Eth1Config,
+    pub boot_nodes: Vec<Enr>,
+}
+
+impl Eth2Network {
+    /// Returns the network bundled with Lighthouse called `name`, with the constants of `preset`
+    /// in place of those of its own preset if given.
+    pub fn built_in(name: &str, preset: Option<&str>) -> Result<Self, String> {
+        let network = BUILT_IN_NETWORKS
+            .iter()
+            .find(|network| network.name == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = BUILT_IN_NETWORKS.iter().map(|n| n.name).collect();
+                format!(
+                    "Unknown network {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+        let file = |file_name: &str| format!("{}/{}", name, file_name);
+        Self::parse(
+            name,
+            (&file(CONFIG_FILE), network.config),
+            preset,
+            None,
+            network
+                .genesis
+                .map(|contents| (file(GENESIS_FILE), contents)),
+            network
+                .boot_enr
+                .map(|contents| (file(BOOT_ENR_FILE), contents)),
+        )
+    }
+
+    /// Reads the network in `dir`, named after the directory, from its `config.yaml` and its
+    /// optional `preset.yaml`, `genesis.yaml` and `boot_enr.yaml`, with the constants of `preset`
+    /// in place of those of its own preset if given.
+    pub fn load(dir: &Path, preset: Option<&str>) -> Result<Self, String> {
+        let name = dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| {
+                dir.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .ok_or_else(|| format!("Unable to read testnet dir {}", dir.display()))?;
+        let read = |file_name: &str| -> Result<Option<(String, String)>, String> {
+            let path = dir.join(file_name);
+            if !path.exists() {
+                return Ok(None);
+            }
+            fs::read_to_string(&path)
+                .map(|contents| Some((format!("{}", path.display()), contents)))
+                .map_err(|e| format!("Unable to read {}: {}", path.display(), e))
+        };
+        let (config_location, config) =
+            read(CONFIG_FILE)?.ok_or_else(|| format!("No {} in {}", CONFIG_FILE, dir.display()))?;
+        let preset_name = preset;
+        let preset = read(PRESET_FILE)?;
+        let genesis = read(GENESIS_FILE)?;
+        let boot_enr = read(BOOT_ENR_FILE)?;
+        Self::parse(
+            &name,
+            (&config_location, &config),
+            preset_name,
+            preset.as_ref().map(|(l, c)| (l.as_str(), c.as_str())),
+            genesis.as_ref().map(|(l, c)| (l.as_str(), c.as_str())),
+            boot_enr.as_ref().map(|(l, c)| (l.as_str(), c.as_str())),
+        )
+    }
+
+    /// Parses the files of a network, each given with its location for errors.
+    ///
+    /// The constants of the chain are those of `preset_name`, or else of the preset named by the
+    /// config, overridden by those of the network's own preset, if any, and then by those of the
+    /// config.
+    fn parse<L: AsRef<str>, C: AsRef<str>>(
+        name: &str,
+        config: (&str, &str),
+        preset_name: Option<&str>,
+        preset: Option<(L, C)>,
+        genesis: Option<(L, C)>,
+        boot_enr: Option<(L, C)>,
+    ) -> Result<Self, String> {
+        let config_yaml = load_yaml(config.0, config.1)?;
+        let (base_name, base) = match preset_name {
+            Some(preset_name) => built_in_preset(preset_name).ok_or_else(|| {
+                format!(
+                    "Unknown preset {}, expected one of {}",
+                    preset_name,
+                    preset_names()
+                )
+            })?,
+            None => preset_base(config.0, &config_yaml)?,
+        };
+        let base_location = format!("{} preset", base_name);
+        let mut chain = ChainConfig::standard();
+        parse_preset(
+            &base_location,
+            &load_yaml(&base_location, base)?,
+            &mut chain,
+        )?;
+        if let Some((location, contents)) = preset {
+            let location = location.as_ref();
+            parse_preset(
+                location,
+                &load_yaml(location, contents.as_ref())?,
+                &mut chain,
+            )?;
+        }
+        let eth1 = parse_config(config.0, &config_yaml, &mut chain)?;
+        if let Some((location, contents)) = genesis {
+            let location = location.as_ref();
+            chain.initial_validators =
+                parse_genesis(location, &load_yaml(location, contents.as_ref())?)?;
+        }
+        let boot_nodes = match boot_enr {
+            Some((location, contents)) => {
+                let location = location.as_ref();
+                parse_boot_enr(location, &load_yaml(location, contents.as_ref())?)?
+            }
+            None => vec![],
+        };
+        Ok(Self {
+            name: name.to_string(),
+            preset: base_name.to_string(),
+            chain,
+            eth1,
+            boot_nodes,
+        })
+    }
+}
+
+/// Returns the network of `--testnet-dir` or else the built-in network of `--network`, with the
+/// constants of `--preset` if given.
+pub fn parse_eth2_network(flags: &Flags) -> Result<Eth2Network, String> {
+    let preset = flags.value_of("preset").or(BUILD_PRESET);
+    match (flags.value_of("network"), flags.value_of("testnet-dir")) {
+        (Some(_), Some(_)) => {
+            Err("Only one of --network and --testnet-dir may be given".to_string())
+        }
+        (None, Some(dir)) => Eth2Network::load(Path::new(dir), preset),
+        (name, None) => Eth2Network::built_in(name.unwrap_or(DEFAULT_NETWORK), preset),
+    }
+}
+
+/// Returns the first document of `contents`, or `Null` if there is none.
+fn load_yaml(location: &str, contents: &str) -> Result<Yaml, String> {
+    YamlLoader::load_from_str(contents)
+        .map(|docs| docs.into_iter().next().unwrap_or(Yaml::Null))
+        .map_err(|e| format!("Invalid YAML in {}: {}", location, e))
+}
+
+fn error(location: &str, key: &str, message: &str) -> String {
+    format!("{}: {}: {}", location, key, message)
+}
+
+/// Returns the entries of a mapping with string keys.
+fn entries<'a>(location: &str, yaml: &'a Yaml) -> Result<Vec<(&'a str, &'a Yaml)>, String> {
+    mapping(yaml)
+        .ok_or_else(|| error(location, "(root)", "expected a mapping"))?
+        .into_iter()
+        .map(|(key, value)| match key.as_str() {
+            Some(key) => Ok((key, value)),
+            None => Err(error(
+                location,
+                &format!("{:?}", key),
+                "expected a string key",
+            )),
+        })
+        .collect()
+}
+
+fn integer(location: &str, key: &str, value: &Yaml, max: u64) -> Result<u64, String> {
+    match value.as_i64() {
+        Some(value) if value >= 0 && value as u64 <= max => Ok(value as u64),
+        _ => Err(error(
+            location,
+            key,
+            &format!("expected an integer from 0 to {}", max),
+        )),
+    }
+}
+
+/// Returns the name and contents of the built-in preset named by the `PRESET_BASE` of
+/// `config.yaml`.
+fn preset_base(location: &str, yaml: &Yaml) -> Result<(&'static str, &'static str), String> {
+    let key = "PRESET_BASE";
+    let name = match entries(location, yaml)?
+        .into_iter()
+        .find(|(k, _)| *k == key)
+    {
+        Some((_, value)) => value
+            .as_str()
+            .ok_or_else(|| error(location, key, "expected a string"))?,
+        None => DEFAULT_PRESET,
+    };
+    built_in_preset(name).ok_or_else(|| {
+        error(
+            location,
+            key,
+            &format!("unknown preset, expected one of {}", preset_names()),
+        )
+    })
+}
+
+fn built_in_preset(name: &str) -> Option<(&'static str, &'static str)> {
+    BUILT_IN_PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .cloned()
+}
+
+fn preset_names() -> String {
+    let names: Vec<&str> = BUILT_IN_PRESETS.iter().map(|(name, _)| *name).collect();
+    names.join(", ")
+}
+
+/// Parses the constants of a preset into `config`.
+fn parse_preset(location: &str, yaml: &Yaml, config: &mut ChainConfig) -> Result<(), String> {
+    for (key, value) in entries(location, yaml)? {
+        preset_constant(location, key, value, config)?;
+    }
+    Ok(())
+}
+
+fn preset_constant(
+    location: &str,
+    key: &str,
+    value: &Yaml,
+    config: &mut ChainConfig,
+) -> Result<(), String> {
+    let max = u64::MAX;
+    match key {
+        "CYCLE_LENGTH" => {
+            config.cycle_length = integer(location, key, value, u64::from(u8::MAX))? as u8
+        }
+        "SHARD_COUNT" => {
+            config.shard_count = integer(location, key, value, u64::from(u16::MAX))? as u16
+        }
+        "DEPOSIT_SIZE_GWEI" => config.deposit_size_gwei = integer(location, key, value, max)?,
+        "MIN_COMMITTEE_SIZE" => config.min_committee_size = integer(location, key, value, max)?,
+        "MAX_VALIDATOR_CHURN_QUOTIENT" => {
+            config.max_validator_churn_quotient = integer(location, key, value, max)?
+        }
+        "EPOCH_LENGTH" => config.epoch_length = integer(location, key, value, max)?,
+        "MIN_ATTESTATION_INCLUSION_DELAY" => {
+            config.min_attestation_inclusion_delay = integer(location, key, value, max)?
+        }
+        _ => return Err(error(location, key, "unknown key")),
+    }
+    Ok(())
+}
+
+/// Parses the configuration of `config.yaml` into `config`, returning the eth1 chain, whose
+/// constants which are absent take their default values. The constants of the preset may also be
+/// given.
+fn parse_config(
+    location: &str,
+    yaml: &Yaml,
+    config: &mut ChainConfig,
+) -> Result<Eth1Config, String> {
+    let max = u64::MAX;
+    let mut eth1 = Eth1Config::default();
+    for (key, value) in entries(location, yaml)? {
+        match key {
+            "PRESET_BASE" => {}
+            "GENESIS_TIME" => config.genesis_time = integer(location, key, value, max)?,
+            "SLOT_DURATION_MILLIS" => {
+                config.slot_duration_millis = integer(location, key, value, max)?
+            }
+            "DEPOSIT_CHAIN_ID" => eth1.chain_id = integer(location, key, value, max)?,
+            "DEPOSIT_NETWORK_ID" => eth1.network_id = integer(location, key, value, max)?,
+            "ETH1_FOLLOW_DISTANCE" => eth1.follow_distance = integer(location, key, value, max)?,
+            _ => preset_constant(location, key, value, config)?,
+        }
+    }
+    if config.cycle_length == 0 || !config.validate() {
+        return Err(format!(
+            "{}: SHARD_COUNT must be at least CYCLE_LENGTH",
+            location
+        ));
+    }
+    Ok(eth1)
+}
+
+/// Parses the validators of `genesis.yaml`: the first `interop_validators` interop validators,
+/// followed by those of `validators`, e.g.
+///
+/// ```yaml
+/// interop_validators: 4
+/// validators:
+///   - pubkey: "0x..."
+///     withdrawal_shard: 0
+///     withdrawal_address: "0x..."
+///     randao_commitment: "0x..."
+///     proof_of_possession: "0x..."
+/// ```
+///
+/// Hex values are quoted, lest YAML read them as integers.
+fn parse_genesis(location: &str, yaml: &Yaml) -> Result<Vec<ValidatorRegistration>, String> {
+    let mut interop = vec![];
+    let mut explicit = vec![];
+    for (key, value) in entries(location, yaml)? {
+        match key {
+            "interop_validators" => {
+                let count = integer(location, key, value, u64::from(u32::MAX))?;
+                interop = interop_keypairs(0..count as usize)
+                    .iter()
+                    .map(|keypair| ValidatorRegistration {
+                        pubkey: keypair.pk.clone(),
+                        withdrawal_shard: 0,
+                        withdrawal_address: Address::zero(),
+                        randao_commitment: Hash256::zero(),
+                        proof_of_possession: create_proof_of_possession(keypair),
+                    })
+                    .collect();
+            }
+            "validators" => {
+                let validators = value
+                    .as_vec()
+                    .ok_or_else(|| error(location, key, "expected a list of validators"))?;
+                for (i, validator) in validators.iter().enumerate() {
+                    let location = format!("{}: validators[{}]", location, i);
+                    explicit.push(parse_validator(&location, validator)?);
+                }
+            }
+            _ => return Err(error(location, key, "unknown key")),
+        }
+    }
+    interop.append(&mut explicit);
+    Ok(interop)
+}
+
+fn parse_validator(location: &str, yaml: &Yaml) -> Result<ValidatorRegistration, String> {
+    let mut pubkey = None;
+    let mut withdrawal_shard = None;
+    let mut withdrawal_address = None;
+    let mut randao_commitment = None;
+    let mut proof_of_possession = None;
+    for (key, value) in entries(location, yaml)? {
+        let invalid = |_| error(location, key, "invalid value");
+        match key {
+            "pubkey" => {
+                pubkey = Some(
+                    PublicKey::from_bytes(&hex_value(location, key, value)?).map_err(invalid)?,
+                )
+            }
+            "withdrawal_shard" => {
+                withdrawal_shard = Some(integer(location, key, value, u64::from(u16::MAX))? as u16)
+            }
+            "withdrawal_address" => {
+                let bytes = hex_value(location, key, value)?;
+                if bytes.len() != 20 {
+                    return Err(error(location, key, "expected 20 bytes"));
+                }
+                withdrawal_address = Some(Address::from_slice(&bytes))
+            }
+            "randao_commitment" => {
+                let bytes = hex_value(location, key, value)?;
+                if bytes.len() != 32 {
+                    return Err(error(location, key, "expected 32 bytes"));
+                }
+                randao_commitment = Some(Hash256::from_slice(&bytes))
+            }
+            "proof_of_possession" => {
+                proof_of_possession = Some(
+                    Signature::from_bytes(&hex_value(location, key, value)?).map_err(invalid)?,
+                )
+            }
+            _ => return Err(error(location, key, "unknown key")),
+        }
+    }
+    let missing = |key: &str| error(location, key, "missing");
+    Ok(ValidatorRegistration {
+        pubkey: pubkey.ok_or_else(|| missing("pubkey"))?,
+        withdrawal_shard: withdrawal_shard.ok_or_else(|| missing("withdrawal_shard"))?,
+        withdrawal_address: withdrawal_address.ok_or_else(|| missing("withdrawal_address"))?,
+        randao_commitment: randao_commitment.ok_or_else(|| missing("randao_commitment"))?,
+        proof_of_possession: proof_of_possession.ok_or_else(|| missing("proof_of_possession"))?,
+    })
+}
+
+fn hex_value(location: &str, key: &str, value: &Yaml) -> Result<Vec<u8>, String> {
+    value
+        .as_str()
+        .and_then(|value| value.strip_prefix("0x"))
+        .and_then(|value| hex::decode(value).ok())
+        .ok_or_else(|| error(location, key, "expected a quoted 0x-prefixed hex string"))
+}
+
+/// Parses the list of ENRs of `boot_enr.yaml`.
+fn parse_boot_enr(location: &str, yaml: &Yaml) -> Result<Vec<Enr>, String> {
+    let enrs = match yaml {
+        Yaml::Array(enrs) => enrs.as_slice(),
+        Yaml::Null => &[],
+        _ => return Err(format!("{}: expected a list of ENRs", location)),
+    };
+    enrs.iter()
+        .map(|enr| {
+            let enr = enr
+                .as_str()
+                .ok_or_else(|| format!("{}: expected a list of ENRs", location))?;
+            enr.parse::<Enr>()
+                .map_err(|e| format!("{}: invalid ENR {} ({})", location, enr, e))
+        })
+        .collect()
+}
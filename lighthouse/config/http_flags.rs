@@ -0,0 +1,61 @@
+use super::Flags;
+use http_api::{ApiConfig, MonitoringConfig, TlsConfig};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Applies the HTTP API flags to `config`.
+pub fn parse_http_config(flags: &Flags, config: &mut ApiConfig) -> Result<(), String> {
+    if flags.is_present("http") {
+        config.enabled = true;
+    }
+    if let Some(address) = flags.parse::<IpAddr>("http-address")? {
+        config.listen_address = address;
+    }
+    if let Some(port) = flags.parse::<u16>("http-port")? {
+        config.port = port;
+    }
+    if let Some(origins) = flags.value_of("http-allow-origin") {
+        config.allow_origins = origins
+            .split(',')
+            .map(|origin| origin.trim().to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+    }
+    match (
+        flags.value_of("http-tls-cert"),
+        flags.value_of("http-tls-key"),
+    ) {
+        (Some(cert), Some(key)) => {
+            config.tls = Some(TlsConfig {
+                cert: PathBuf::from(cert),
+                key: PathBuf::from(key),
+            })
+        }
+        (None, None) => {}
+        _ => return Err("--http-tls-cert and --http-tls-key must be given together".to_string()),
+    }
+    if flags.is_present("http-read-only") {
+        config.read_only = true;
+    }
+    Ok(())
+}
+
+/// Parses the `--monitoring-endpoint` and `--monitoring-interval` flags, returning `None` if no
+/// endpoint is given.
+pub fn parse_monitoring_config(flags: &Flags) -> Result<Option<MonitoringConfig>, String> {
+    let interval = flags.parse::<u64>("monitoring-interval")?;
+    let mut config = match flags.value_of("monitoring-endpoint") {
+        Some(endpoint) => MonitoringConfig::new(endpoint.to_string()),
+        None if interval.is_some() => {
+            return Err("--monitoring-interval requires --monitoring-endpoint".to_string())
+        }
+        None => return Ok(None),
+    };
+    match interval {
+        Some(0) => return Err(flags.invalid("monitoring-interval", "0")),
+        Some(secs) => config.interval = Duration::from_secs(secs),
+        None => {}
+    }
+    Ok(Some(config))
+}
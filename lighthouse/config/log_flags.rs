@@ -0,0 +1,34 @@
+use super::Flags;
+use logging::{parse_level, parse_module_levels, LogFormat, LoggerConfig};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Returns the logger configuration of the logging flags, with defaults for those not given.
+///
+/// The maximum size of the log file is given in MiB.
+pub fn parse_logger_config(flags: &Flags) -> Result<LoggerConfig, String> {
+    let mut config = LoggerConfig::default();
+    if let Some(format) = flags.parse::<LogFormat>("log-format")? {
+        config.format = format;
+    }
+    if let Some(level) = flags.value_of("log-level") {
+        config.level = parse_level(level).map_err(|_| flags.invalid("log-level", level))?;
+    }
+    if let Some(list) = flags.value_of("log-filter") {
+        config.module_levels =
+            parse_module_levels(list).map_err(|_| flags.invalid("log-filter", list))?;
+    }
+    if let Some(path) = flags.value_of("log-file") {
+        config.file = Some(PathBuf::from(path));
+    }
+    if let Some(size) = flags.parse::<u64>("log-max-size")? {
+        config.max_file_size = size * 1024 * 1024;
+    }
+    if let Some(files) = flags.parse::<usize>("log-max-files")? {
+        config.max_files = files;
+    }
+    if let Some(seconds) = flags.parse::<u64>("log-dedup-interval")? {
+        config.dedup_interval = Duration::from_secs(seconds);
+    }
+    Ok(config)
+}
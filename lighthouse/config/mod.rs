@@ -1,32 +1,148 @@
 extern crate dirs;
 
+mod chain_flags;
+mod config_file;
+mod eth1_flags;
+mod eth2_network;
+mod http_flags;
+mod log_flags;
+mod monitor_flags;
+mod network_flags;
+mod rpc_flags;
+
+pub use self::chain_flags::{
+    parse_chain_config, parse_clock_disparity, parse_disk_guard_config, parse_import_checkpoint,
+    parse_state_pruning, parse_state_transition_threads, parse_weak_subjectivity_checkpoint,
+};
+pub use self::config_file::{ConfigFile, Flags};
+pub use self::eth1_flags::parse_eth1_config;
+pub use self::eth2_network::{parse_eth2_network, Eth2Network};
+pub use self::http_flags::{parse_http_config, parse_monitoring_config};
+pub use self::log_flags::parse_logger_config;
+pub use self::monitor_flags::{parse_participation_retention, parse_validator_monitor};
+pub use self::network_flags::parse_network_config;
+pub use self::rpc_flags::parse_rpc_config;
+
+use beacon_node::{
+    DiskGuardConfig, ValidatorId, WeakSubjectivityCheckpoint, DEFAULT_IMPORT_CHECKPOINT_SLOTS,
+    DEFAULT_PARTICIPATION_RETENTION_CYCLES, DEFAULT_STATE_TRANSITION_THREADS,
+    MAXIMUM_CLOCK_DISPARITY,
+};
+use db::stores::StatePruning;
+use eth1::Eth1Config;
+use http_api::{ApiConfig, MonitoringConfig};
+use network::NetworkConfig;
+use rpc::RpcConfig;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use types::ChainConfig;
 
 /// Stores the core configuration for this Lighthouse instance.
 /// This struct is general, other components may implement more
 /// specialized config structs.
 #[derive(Clone)]
 pub struct LighthouseConfig {
+    /// The directory of the network within the root data dir.
     pub data_dir: PathBuf,
-    pub p2p_listen_port: u16,
+    pub chain: ChainConfig,
+    /// How far the clocks of peers may be ahead of or behind our own.
+    pub clock_disparity: Duration,
+    /// The block the canonical chain must include, replacing any persisted in the database.
+    pub weak_subjectivity_checkpoint: Option<WeakSubjectivityCheckpoint>,
+    /// Starts the node even if its chain conflicts with the weak subjectivity checkpoint.
+    pub ignore_weak_subjectivity: bool,
+    /// The number of threads importing blocks published to the HTTP API.
+    pub state_transition_threads: usize,
+    /// The number of slots the head advances between each time the chain is persisted while
+    /// sync imports batches of blocks.
+    pub import_checkpoint_slots: u64,
+    pub eth1: Eth1Config,
+    pub network: NetworkConfig,
+    pub rpc: RpcConfig,
+    pub http: ApiConfig,
+    /// Where a summary of the node is pushed, if anywhere.
+    pub monitoring: Option<MonitoringConfig>,
+    /// The validators whose duties are logged as blocks are imported.
+    pub monitored_validators: Vec<ValidatorId>,
+    /// The number of the latest cycles whose participation, and that of the monitored
+    /// validators, is kept in the database.
+    pub participation_retention_cycles: u64,
+    /// Which snapshots of historical states are kept as the chain is finalized.
+    pub state_pruning: StatePruning,
+    /// When the disk holding the database is too full for snapshots, or for blocks.
+    pub disk_guard: DiskGuardConfig,
 }
 
 const DEFAULT_LIGHTHOUSE_DIR: &str = ".lighthouse";
+/// The directory within the data dir of a network holding the beacon node's data.
+pub const BEACON_DIR: &str = "beacon";
+/// The directory within the data dir of a network holding the validator keys.
+pub const VALIDATORS_DIR: &str = "validators";
+/// The directory within the beacon dir holding the network key.
+pub const NETWORK_DIR: &str = "network";
+/// The directory within the beacon dir holding the database.
+pub const DB_DIR: &str = "database";
+/// The directory within the beacon dir to which heap profiles are written by the admin API.
+pub const HEAP_PROFILE_DIR: &str = "heap_profiles";
+
+/// The directories which were kept at the top of the data dir before it was divided by network.
+const LEGACY_DIRS: &[&str] = &[DB_DIR, NETWORK_DIR, VALIDATORS_DIR, "slashing_protection"];
 
 impl LighthouseConfig {
-    /// Build a new lighthouse configuration from defaults.
-    pub fn default() -> Self {
-        let data_dir = {
+    /// Build a new lighthouse configuration for `network`, with its data in a directory named
+    /// after it within `root`, or else within `~/.lighthouse`.
+    pub fn new(root: Option<PathBuf>, network: &Eth2Network) -> Self {
+        let root = root.unwrap_or_else(|| {
             let home = dirs::home_dir().expect("Unable to determine home dir.");
             home.join(DEFAULT_LIGHTHOUSE_DIR)
-        };
+        });
+        let data_dir = root.join(&network.name);
         fs::create_dir_all(&data_dir)
             .unwrap_or_else(|_| panic!("Unable to create {:?}", &data_dir));
-        let p2p_listen_port = 0;
+        let network_config = NetworkConfig {
+            network_dir: data_dir.join(BEACON_DIR).join(NETWORK_DIR),
+            boot_nodes: network.boot_nodes.clone(),
+            ..NetworkConfig::default()
+        };
         Self {
             data_dir,
-            p2p_listen_port,
+            chain: network.chain.clone(),
+            clock_disparity: MAXIMUM_CLOCK_DISPARITY,
+            weak_subjectivity_checkpoint: None,
+            ignore_weak_subjectivity: false,
+            state_transition_threads: DEFAULT_STATE_TRANSITION_THREADS,
+            import_checkpoint_slots: DEFAULT_IMPORT_CHECKPOINT_SLOTS,
+            eth1: network.eth1.clone(),
+            network: network_config,
+            rpc: RpcConfig::default(),
+            http: ApiConfig::default(),
+            monitoring: None,
+            monitored_validators: vec![],
+            participation_retention_cycles: DEFAULT_PARTICIPATION_RETENTION_CYCLES,
+            state_pruning: StatePruning::Minimal,
+            disk_guard: DiskGuardConfig::default(),
         }
     }
+
+    /// The directory holding the beacon node's database, network key and API token.
+    pub fn beacon_dir(&self) -> PathBuf {
+        self.data_dir.join(BEACON_DIR)
+    }
+
+    /// The directory holding the validator keys and slashing protection.
+    pub fn validators_dir(&self) -> PathBuf {
+        self.data_dir.join(VALIDATORS_DIR)
+    }
+
+    /// Returns the directories at the top of the root data dir from before it was divided by
+    /// network, which are no longer used.
+    pub fn legacy_dirs(&self) -> Vec<PathBuf> {
+        let root = self.data_dir.parent().unwrap_or_else(|| Path::new("/"));
+        LEGACY_DIRS
+            .iter()
+            .map(|dir| root.join(dir))
+            .filter(|dir| dir.is_dir())
+            .collect()
+    }
 }
@@ -0,0 +1,43 @@
+use super::Flags;
+use beacon_node::ValidatorId;
+use bls::PublicKey;
+use hex;
+
+/// Parses the validators to monitor, a comma-separated list of validator indices and `0x`-prefixed
+/// public keys.
+pub fn parse_validator_monitor(flags: &Flags) -> Result<Vec<ValidatorId>, String> {
+    match flags.value_of("validators-monitor") {
+        Some(list) => list
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| parse_validator_id(id).ok_or_else(|| flags.invalid("validators-monitor", id)))
+            .collect(),
+        None => Ok(vec![]),
+    }
+}
+
+/// Applies the `--participation-retention-cycles` flag to `retention_cycles`. At least one cycle
+/// must be kept.
+pub fn parse_participation_retention(
+    flags: &Flags,
+    retention_cycles: &mut u64,
+) -> Result<(), String> {
+    if let Some(cycles) = flags.parse::<u64>("participation-retention-cycles")? {
+        if cycles == 0 {
+            return Err(flags.invalid("participation-retention-cycles", "0"));
+        }
+        *retention_cycles = cycles;
+    }
+    Ok(())
+}
+
+fn parse_validator_id(id: &str) -> Option<ValidatorId> {
+    match id.strip_prefix("0x") {
+        Some(hex_pubkey) => hex::decode(hex_pubkey)
+            .ok()
+            .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+            .map(ValidatorId::Pubkey),
+        None => id.parse::<usize>().ok().map(ValidatorId::Index),
+    }
+}
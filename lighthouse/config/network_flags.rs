@@ -0,0 +1,77 @@
+use super::Flags;
+use hex;
+use network::{Cidr, Enr, NetworkConfig, NodeId};
+use std::net::{IpAddr, Ipv4Addr};
+use types::Hash256;
+
+/// Applies the network flags to `config`, leaving fields without a flag unchanged.
+///
+/// The discovery port defaults to the listen port.
+pub fn parse_network_config(flags: &Flags, config: &mut NetworkConfig) -> Result<(), String> {
+    if let Some(address) = flags.parse::<IpAddr>("listen-address")? {
+        config.listen_address = address;
+    }
+    if let Some(port) = flags.parse::<u16>("port")? {
+        config.libp2p_port = port;
+        config.discovery_port = port;
+    }
+    if let Some(port) = flags.parse::<u16>("discovery-port")? {
+        config.discovery_port = port;
+    }
+    if let Some(target_peers) = flags.parse::<usize>("target-peers")? {
+        config.target_peers = target_peers;
+    }
+    if let Some(enrs) = flags.value_of("boot-nodes") {
+        config.boot_nodes = enrs
+            .split(',')
+            .map(|enr| {
+                enr.trim()
+                    .parse::<Enr>()
+                    .map_err(|e| format!("{} ({})", flags.invalid("boot-nodes", enr), e))
+            })
+            .collect::<Result<Vec<Enr>, String>>()?;
+    }
+    if let Some(address) = flags.parse::<Ipv4Addr>("enr-address")? {
+        config.enr_address = Some(address);
+    }
+    if let Some(port) = flags.parse::<u16>("enr-tcp-port")? {
+        config.enr_tcp_port = Some(port);
+    }
+    if let Some(port) = flags.parse::<u16>("enr-udp-port")? {
+        config.enr_udp_port = Some(port);
+    }
+    if flags.is_present("disable-discovery") {
+        config.disable_discovery = true;
+    }
+    if flags.is_present("disable-upnp") {
+        config.disable_upnp = true;
+    }
+    if let Some(peer_ids) = flags.value_of("ban-peers") {
+        config.banned_peers = peer_ids
+            .split(',')
+            .map(|peer_id| {
+                parse_node_id(peer_id.trim()).ok_or_else(|| flags.invalid("ban-peers", peer_id))
+            })
+            .collect::<Result<Vec<NodeId>, String>>()?;
+    }
+    if let Some(ranges) = flags.value_of("ban-cidrs") {
+        config.banned_ranges = ranges
+            .split(',')
+            .map(|range| {
+                range
+                    .trim()
+                    .parse::<Cidr>()
+                    .map_err(|e| format!("{} ({})", flags.invalid("ban-cidrs", range), e))
+            })
+            .collect::<Result<Vec<Cidr>, String>>()?;
+    }
+    Ok(())
+}
+
+/// Parses a node id given as 32 hex-encoded bytes, prefixed by `0x`.
+fn parse_node_id(id: &str) -> Option<NodeId> {
+    id.strip_prefix("0x")
+        .and_then(|id| hex::decode(id).ok())
+        .filter(|bytes| bytes.len() == 32)
+        .map(|bytes| NodeId(Hash256::from(&bytes[..])))
+}
@@ -0,0 +1,17 @@
+use super::Flags;
+use rpc::RpcConfig;
+use std::net::IpAddr;
+
+/// Applies the gRPC server flags to `config`.
+pub fn parse_rpc_config(flags: &Flags, config: &mut RpcConfig) -> Result<(), String> {
+    if flags.is_present("rpc") {
+        config.enabled = true;
+    }
+    if let Some(address) = flags.parse::<IpAddr>("rpc-address")? {
+        config.listen_address = address;
+    }
+    if let Some(port) = flags.parse::<u16>("rpc-port")? {
+        config.port = port;
+    }
+    Ok(())
+}
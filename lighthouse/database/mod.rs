@@ -0,0 +1,280 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use beacon_node::{replay_blocks, PersistedHead, ReplayReport};
+use clap::ArgMatches;
+use config::DB_DIR;
+use db::stores::{BeaconBlockStore, ChainStore, StatePruning, StateStore, COLUMNS};
+use db::{migrate, schema_version, ColumnCodecs, ColumnStats, DiskDB, SchemaError, SCHEMA_VERSION};
+use serde_json::{self, Map, Value};
+use slog::Logger;
+use ssz::Decodable;
+use std::time::Duration;
+use types::{BeaconBlock, ChainConfig};
+
+/// Runs the database subcommands, on the database of a beacon node which is not running.
+pub fn run(
+    matches: &ArgMatches,
+    beacon_dir: &Path,
+    pruning: StatePruning,
+    chain: &ChainConfig,
+    log: &Logger,
+) {
+    let command = match matches.subcommand_name() {
+        Some(command) => command,
+        None => {
+            error!(log, "No database command given, see --help");
+            return;
+        }
+    };
+    let db_path = beacon_dir.join(DB_DIR);
+    if !db_path.exists() {
+        error!(log, "No database in the beacon dir"; "path" => format!("{}", db_path.display()));
+        return;
+    }
+    /*
+     * RocksDB locks the database while it is open, so this fails while the node is running.
+     */
+    let db = match DiskDB::try_open(&db_path, Some(&COLUMNS)) {
+        Ok(db) => Arc::new(db.with_codecs(ColumnCodecs::standard())),
+        Err(e) => {
+            error!(log, "Unable to open database, is the beacon node running?"; "error" => e.message);
+            return;
+        }
+    };
+
+    match command {
+        "inspect" => inspect(&db, &db_path, log),
+        "prune-states" => prune_states(&db, pruning, log),
+        "migrate" => migrate_schema(&db, log),
+        "compact" => compact(&db, &db_path, log),
+        "debug-replay" => {
+            let matches = matches
+                .subcommand_matches(command)
+                .expect("Command was matched");
+            debug_replay(&db, chain, matches, log)
+        }
+        _ => unreachable!("Unknown database command"),
+    }
+    if let Err(e) = db.flush() {
+        error!(log, "Unable to flush database"; "error" => e.message);
+    }
+}
+
+fn inspect(db: &DiskDB, db_path: &Path, log: &Logger) {
+    match schema_version(db) {
+        Ok(version) => info!(log, "Schema"; "version" => version, "current" => SCHEMA_VERSION),
+        Err(e) => warn!(log, "Unable to read schema version"; "error" => format!("{:?}", e)),
+    }
+    for col in COLUMNS.iter() {
+        match ColumnStats::of(db, col) {
+            Ok(stats) => info!(log, "Column";
+                               "value_bytes" => stats.value_bytes,
+                               "key_bytes" => stats.key_bytes,
+                               "keys" => stats.keys,
+                               "name" => col),
+            Err(e) => {
+                error!(log, "Unable to read column"; "name" => col, "error" => e.message);
+                return;
+            }
+        }
+    }
+    info!(log, "Size on disk"; "bytes" => dir_size(db_path));
+}
+
+/// Deletes the blocks which are not ancestors of the heads persisted at the last shutdown, and the
+/// state snapshots which `pruning` does not keep.
+fn prune_states(db: &Arc<DiskDB>, pruning: StatePruning, log: &Logger) {
+    let head = match persisted_head(db, log) {
+        Some(head) => head,
+        None => return,
+    };
+    let heads: Vec<Vec<u8>> = head.heads.iter().map(|head| head.to_vec()).collect();
+    match BeaconBlockStore::new(db.clone()).prune(&heads) {
+        Ok(pruned) => info!(log, "Pruned blocks"; "heads" => heads.len(), "pruned" => pruned),
+        Err(e) => error!(log, "Unable to prune blocks"; "error" => format!("{:?}", e)),
+    }
+    /*
+     * The finalized block is not persisted, so the genesis state, which needs no snapshot, stands
+     * in for the finalized state.
+     */
+    let finalized_root = BeaconBlock::zero().canonical_root();
+    match StateStore::new(db.clone()).prune(pruning, &finalized_root) {
+        Ok(pruned) => {
+            info!(log, "Pruned state snapshots"; "mode" => format!("{:?}", pruning), "pruned" => pruned)
+        }
+        Err(e) => error!(log, "Unable to prune state snapshots"; "error" => e.message),
+    }
+}
+
+/// Re-imports the persisted chain up to `--to-slot`, logging the import of each block from
+/// `--from-slot` and writing the steps traced to `--report`, if given.
+fn debug_replay(db: &Arc<DiskDB>, chain: &ChainConfig, matches: &ArgMatches, log: &Logger) {
+    let (from_slot, to_slot) = match (
+        matches.value_of("from-slot").map(str::parse::<u64>),
+        matches.value_of("to-slot").map(str::parse::<u64>),
+    ) {
+        (Some(Ok(from_slot)), Some(Ok(to_slot))) if from_slot <= to_slot => (from_slot, to_slot),
+        _ => {
+            error!(log, "Invalid --from-slot or --to-slot");
+            return;
+        }
+    };
+    let head = match persisted_head(db, log) {
+        Some(head) => head,
+        None => return,
+    };
+    let blocks = BeaconBlockStore::new(db.clone());
+    let report = match replay_blocks(&blocks, chain.clone(), head.head_root, from_slot, to_slot) {
+        Ok(report) => report,
+        Err(e) => {
+            error!(log, "Unable to replay blocks"; "error" => format!("{:?}", e));
+            return;
+        }
+    };
+
+    for block in &report.blocks {
+        info!(log, "Replayed block";
+              "slot" => block.slot,
+              "root" => format!("{:?}", block.root),
+              "outcome" => format!("{:?}", block.outcome),
+              "duration_us" => micros(block.duration),
+              "head_slot" => block.head_slot);
+        for step in &block.steps {
+            debug!(log, "Import step";
+                   "slot" => block.slot,
+                   "operation" => step.operation,
+                   "duration_us" => micros(step.duration),
+                   "detail" => &step.detail);
+        }
+    }
+    info!(log, "Replayed blocks";
+          "traced" => report.blocks.len(),
+          "before_range" => report.blocks_before,
+          "from_slot" => from_slot,
+          "to_slot" => to_slot);
+
+    if let Some(path) = matches.value_of("report") {
+        let json = serde_json::to_string_pretty(&report_json(&report)).expect("JSON is valid");
+        match fs::write(path, json) {
+            Ok(()) => info!(log, "Wrote replay report"; "path" => path),
+            Err(e) => {
+                error!(log, "Unable to write replay report"; "path" => path, "error" => format!("{}", e))
+            }
+        }
+    }
+}
+
+fn report_json(report: &ReplayReport) -> Value {
+    let blocks = report
+        .blocks
+        .iter()
+        .map(|block| {
+            let steps = block
+                .steps
+                .iter()
+                .map(|step| {
+                    object(vec![
+                        ("operation", Value::from(step.operation)),
+                        ("duration_us", Value::from(micros(step.duration))),
+                        ("detail", Value::from(step.detail.clone())),
+                    ])
+                })
+                .collect();
+            object(vec![
+                ("slot", Value::from(block.slot)),
+                ("root", Value::from(format!("{:?}", block.root))),
+                ("proposer", Value::from(block.proposer.map(|p| p as u64))),
+                ("outcome", Value::from(format!("{:?}", block.outcome))),
+                ("duration_us", Value::from(micros(block.duration))),
+                ("head_slot", Value::from(block.head_slot)),
+                ("head_root", Value::from(format!("{:?}", block.head_root))),
+                ("steps", Value::Array(steps)),
+            ])
+        })
+        .collect();
+    object(vec![
+        ("from_slot", Value::from(report.from_slot)),
+        ("to_slot", Value::from(report.to_slot)),
+        ("blocks_before", Value::from(report.blocks_before as u64)),
+        ("blocks", Value::Array(blocks)),
+    ])
+}
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    let mut map = Map::new();
+    for (key, value) in fields {
+        map.insert(key.to_string(), value);
+    }
+    Value::Object(map)
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+/// Returns the heads of the chain persisted at the last shutdown, logging why if there are none.
+fn persisted_head(db: &Arc<DiskDB>, log: &Logger) -> Option<PersistedHead> {
+    match ChainStore::new(db.clone()).get_serialized_head() {
+        Ok(Some(ssz)) => match PersistedHead::ssz_decode(&ssz, 0) {
+            Ok((head, _)) => Some(head),
+            Err(_) => {
+                error!(log, "Invalid persisted chain");
+                None
+            }
+        },
+        Ok(None) => {
+            error!(
+                log,
+                "No persisted chain, shut the beacon node down gracefully first"
+            );
+            None
+        }
+        Err(e) => {
+            error!(log, "Unable to read persisted chain"; "error" => e.message);
+            None
+        }
+    }
+}
+
+fn migrate_schema(db: &DiskDB, log: &Logger) {
+    match migrate(db) {
+        Ok(SCHEMA_VERSION) => info!(log, "Database is up to date"; "version" => SCHEMA_VERSION),
+        Ok(from) => info!(log, "Migrated database"; "to" => SCHEMA_VERSION, "from" => from),
+        Err(SchemaError::Newer(version)) => {
+            error!(log, "Database is from a newer version of Lighthouse";
+                   "supported" => SCHEMA_VERSION,
+                   "version" => version)
+        }
+        Err(e) => error!(log, "Unable to migrate database"; "error" => format!("{:?}", e)),
+    }
+}
+
+fn compact(db: &DiskDB, db_path: &Path, log: &Logger) {
+    let before = dir_size(db_path);
+    for col in COLUMNS.iter() {
+        if let Err(e) = db.compact(col) {
+            error!(log, "Unable to compact column"; "name" => col, "error" => e.message);
+            return;
+        }
+    }
+    info!(log, "Compacted database"; "after_bytes" => dir_size(db_path), "before_bytes" => before);
+}
+
+/// Returns the total size of the files in `path` and its subdirectories, skipping those which
+/// cannot be read.
+fn dir_size(path: &Path) -> u64 {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(ref metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
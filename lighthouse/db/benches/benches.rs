@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate criterion;
+extern crate db;
+
+use criterion::{black_box, Benchmark, Criterion, Throughput};
+use db::stores::{BLOCKS_DB_COLUMN, COLUMNS};
+use db::{ClientDB, DiskDB, MemoryDB};
+use std::env;
+use std::fs;
+use std::sync::Arc;
+
+/// The number of values written or read in each iteration.
+const BATCH_SIZE: usize = 1_000;
+/// About the size of the SSZ of a block with a few attestations.
+const VALUE_SIZE: usize = 1_024;
+
+fn key(i: usize) -> Vec<u8> {
+    (i as u64).to_be_bytes().to_vec()
+}
+
+fn bench_db<T: ClientDB + 'static>(c: &mut Criterion, name: &str, db: Arc<T>) {
+    let value = vec![42; VALUE_SIZE];
+    let put_db = db.clone();
+    c.bench(
+        &format!("{}_put", name),
+        Benchmark::new("blocks", move |b| {
+            b.iter(|| {
+                for i in 0..BATCH_SIZE {
+                    put_db.put(BLOCKS_DB_COLUMN, &key(i), &value).unwrap();
+                }
+            })
+        })
+        .throughput(Throughput::Elements(BATCH_SIZE as u32)),
+    );
+    c.bench(
+        &format!("{}_get", name),
+        Benchmark::new("blocks", move |b| {
+            b.iter(|| {
+                for i in 0..BATCH_SIZE {
+                    black_box(db.get(BLOCKS_DB_COLUMN, &key(i)).unwrap());
+                }
+            })
+        })
+        .throughput(Throughput::Elements(BATCH_SIZE as u32)),
+    );
+}
+
+fn memory_db(c: &mut Criterion) {
+    bench_db(c, "memory_db", Arc::new(MemoryDB::open()));
+}
+
+fn disk_db(c: &mut Criterion) {
+    let path = env::temp_dir().join("lighthouse_db_bench");
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    bench_db(c, "disk_db", Arc::new(DiskDB::open(&path, Some(&COLUMNS))));
+    let _ = fs::remove_dir_all(&path);
+}
+
+criterion_group!(benches, memory_db, disk_db);
+criterion_main!(benches);
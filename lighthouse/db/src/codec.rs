@@ -0,0 +1,159 @@
+extern crate snap;
+
+use self::snap::read::FrameDecoder;
+use self::snap::write::FrameEncoder;
+use super::stores::{BLOCKS_DB_COLUMN, STATES_DB_COLUMN};
+use super::{DBError, DBValue};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// How the values of a column are stored.
+///
+/// Values are decoded with the codec of their column, whatever their contents, so changing the
+/// codec of a column requires a migration of the values stored in it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Codec {
+    Uncompressed,
+    /// Snappy framed, which roughly halves the size of blocks and states.
+    Snappy,
+}
+
+impl Codec {
+    pub fn encode(self, val: &[u8]) -> DBValue {
+        match self {
+            Codec::Uncompressed => val.to_vec(),
+            Codec::Snappy => {
+                let mut encoder = FrameEncoder::new(vec![]);
+                encoder
+                    .write_all(val)
+                    .expect("writing to a vec cannot fail");
+                encoder.into_inner().expect("flushing to a vec cannot fail")
+            }
+        }
+    }
+
+    /// Returns a value stored with this codec as it was put.
+    pub fn decode(self, stored: DBValue) -> Result<DBValue, DBError> {
+        match self {
+            Codec::Uncompressed => Ok(stored),
+            Codec::Snappy => {
+                let mut val = vec![];
+                FrameDecoder::new(&stored[..])
+                    .read_to_end(&mut val)
+                    .map_err(|e| DBError::new(format!("Invalid snappy value: {}", e)))?;
+                Ok(val)
+            }
+        }
+    }
+
+    /// Returns a stored value as `decode` does, or as it is stored if it cannot be decoded, for
+    /// iterators, which cannot fail.
+    pub fn decode_or_stored(self, stored: DBValue) -> DBValue {
+        match self {
+            Codec::Uncompressed => stored,
+            Codec::Snappy => self.decode(stored.clone()).unwrap_or(stored),
+        }
+    }
+}
+
+/// The codec of each column, of which those not given are uncompressed.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnCodecs {
+    codecs: HashMap<String, Codec>,
+}
+
+impl ColumnCodecs {
+    /// Compresses the blocks and state snapshots with snappy.
+    pub fn standard() -> Self {
+        Self::default()
+            .set(BLOCKS_DB_COLUMN, Codec::Snappy)
+            .set(STATES_DB_COLUMN, Codec::Snappy)
+    }
+
+    pub fn set(mut self, col: &str, codec: Codec) -> Self {
+        self.codecs.insert(col.to_string(), codec);
+        self
+    }
+
+    pub fn codec(&self, col: &str) -> Codec {
+        self.codecs.get(col).cloned().unwrap_or(Codec::Uncompressed)
+    }
+
+    /// Returns `val` as it is stored in `col`.
+    pub fn encode(&self, col: &str, val: &[u8]) -> DBValue {
+        self.codec(col).encode(val)
+    }
+
+    /// Returns a value stored in `col` as it was put.
+    pub fn decode(&self, col: &str, stored: DBValue) -> Result<DBValue, DBError> {
+        self.codec(col).decode(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stores::PEERS_DB_COLUMN;
+    use super::*;
+
+    /// The stream identifier which begins every snappy framed value.
+    const SNAPPY_STREAM_IDENTIFIER: [u8; 10] =
+        [0xff, 0x06, 0x00, 0x00, b's', b'N', b'a', b'P', b'p', b'Y'];
+
+    #[test]
+    fn test_snappy_round_trip() {
+        let val = vec![42; 1_000];
+        let stored = Codec::Snappy.encode(&val);
+        assert!(stored.starts_with(&SNAPPY_STREAM_IDENTIFIER));
+        assert!(stored.len() < val.len() / 2);
+        assert_eq!(Codec::Snappy.decode(stored).unwrap(), val);
+
+        let empty = Codec::Snappy.encode(&[]);
+        assert_eq!(Codec::Snappy.decode(empty).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_uncompressed_values_pass_through() {
+        assert_eq!(Codec::Uncompressed.encode(&[1, 2, 3]), vec![1, 2, 3]);
+        assert_eq!(
+            Codec::Uncompressed.decode(vec![1, 2, 3]).unwrap(),
+            vec![1, 2, 3]
+        );
+
+        let mut corrupt = Codec::Snappy.encode(&[7; 100]);
+        corrupt.truncate(corrupt.len() - 1);
+        assert!(Codec::Snappy.decode(corrupt.clone()).is_err());
+        assert_eq!(
+            Codec::Uncompressed.decode(corrupt.clone()).unwrap(),
+            corrupt
+        );
+    }
+
+    #[test]
+    fn test_uncompressed_value_like_snappy_round_trips() {
+        let codecs = ColumnCodecs::standard();
+        let mut val = SNAPPY_STREAM_IDENTIFIER.to_vec();
+        val.extend_from_slice(&[1, 2, 3]);
+        let stored = codecs.encode(PEERS_DB_COLUMN, &val);
+        assert_eq!(stored, val);
+        assert_eq!(codecs.decode(PEERS_DB_COLUMN, stored.clone()).unwrap(), val);
+        assert_eq!(Codec::Uncompressed.decode_or_stored(stored), val);
+
+        /*
+         * In a compressed column, the value is compressed like any other.
+         */
+        let stored = codecs.encode(BLOCKS_DB_COLUMN, &val);
+        assert_eq!(codecs.decode(BLOCKS_DB_COLUMN, stored).unwrap(), val);
+    }
+
+    #[test]
+    fn test_column_codecs() {
+        let codecs = ColumnCodecs::standard();
+        assert_eq!(codecs.codec(BLOCKS_DB_COLUMN), Codec::Snappy);
+        assert_eq!(codecs.codec(STATES_DB_COLUMN), Codec::Snappy);
+        assert_eq!(codecs.codec(PEERS_DB_COLUMN), Codec::Uncompressed);
+        assert_eq!(codecs.encode(PEERS_DB_COLUMN, &[1]), vec![1]);
+
+        let codecs = codecs.set(BLOCKS_DB_COLUMN, Codec::Uncompressed);
+        assert_eq!(codecs.codec(BLOCKS_DB_COLUMN), Codec::Uncompressed);
+    }
+}
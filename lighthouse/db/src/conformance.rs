@@ -0,0 +1,186 @@
+//! The semantics every `ClientDB` backend must share, as a suite of tests generated for a
+//! backend by `client_db_conformance_tests!`, so that the backends cannot silently diverge.
+//!
+//! The suite is given an expression opening an empty database with `COLUMNS`. Tests written
+//! against one backend then hold against every other.
+
+/// Generates the conformance tests for the backend opened by `$open`, which is evaluated once
+/// per test for an empty database with the columns of `COLUMNS`.
+macro_rules! client_db_conformance_tests {
+    ($open: expr) => {
+        use std::sync::Arc;
+        use std::thread;
+        use $crate::stores::{BLOCKS_DB_COLUMN, CHAIN_DB_COLUMN};
+        use $crate::{BatchOp, ClientDB};
+
+        const UNKNOWN_COLUMN: &str = "unknown";
+
+        #[test]
+        fn conformance_put_get_delete() {
+            let db = $open;
+            assert_eq!(db.get(BLOCKS_DB_COLUMN, b"key").unwrap(), None);
+            assert!(!db.exists(BLOCKS_DB_COLUMN, b"key").unwrap());
+
+            db.put(BLOCKS_DB_COLUMN, b"key", b"first").unwrap();
+            db.put(BLOCKS_DB_COLUMN, b"key", b"second").unwrap();
+            db.put(BLOCKS_DB_COLUMN, b"empty", b"").unwrap();
+            assert_eq!(
+                db.get(BLOCKS_DB_COLUMN, b"key").unwrap(),
+                Some(b"second".to_vec())
+            );
+            assert_eq!(db.get(BLOCKS_DB_COLUMN, b"empty").unwrap(), Some(vec![]));
+            assert!(db.exists(BLOCKS_DB_COLUMN, b"empty").unwrap());
+
+            /*
+             * Deleting a key which is not stored is not an error.
+             */
+            db.delete(BLOCKS_DB_COLUMN, b"key").unwrap();
+            db.delete(BLOCKS_DB_COLUMN, b"key").unwrap();
+            assert_eq!(db.get(BLOCKS_DB_COLUMN, b"key").unwrap(), None);
+            assert!(!db.exists(BLOCKS_DB_COLUMN, b"key").unwrap());
+        }
+
+        #[test]
+        fn conformance_column_isolation() {
+            let db = $open;
+            db.put(BLOCKS_DB_COLUMN, b"same", b"block").unwrap();
+            db.put(CHAIN_DB_COLUMN, b"same", b"chain").unwrap();
+            assert_eq!(
+                db.get(CHAIN_DB_COLUMN, b"same").unwrap(),
+                Some(b"chain".to_vec())
+            );
+
+            db.delete(BLOCKS_DB_COLUMN, b"same").unwrap();
+            assert!(db.exists(CHAIN_DB_COLUMN, b"same").unwrap());
+            assert_eq!(db.iter(BLOCKS_DB_COLUMN).unwrap().count(), 0);
+            assert_eq!(
+                db.iter(CHAIN_DB_COLUMN).unwrap().collect::<Vec<_>>(),
+                vec![(b"same".to_vec(), b"chain".to_vec())]
+            );
+        }
+
+        #[test]
+        fn conformance_unknown_column() {
+            let db = $open;
+            assert!(db.get(UNKNOWN_COLUMN, b"key").is_err());
+            assert!(db.put(UNKNOWN_COLUMN, b"key", b"val").is_err());
+            assert!(db.exists(UNKNOWN_COLUMN, b"key").is_err());
+            assert!(db.delete(UNKNOWN_COLUMN, b"key").is_err());
+            assert!(db.iter(UNKNOWN_COLUMN).is_err());
+        }
+
+        #[test]
+        fn conformance_iteration_order() {
+            let db = $open;
+            let keys: Vec<&[u8]> = vec![b"b", b"a\x00", b"\xff", b"", b"a", b"ab", b"\x00"];
+            for (i, key) in keys.iter().enumerate() {
+                db.put(CHAIN_DB_COLUMN, key, &[i as u8]).unwrap();
+            }
+
+            /*
+             * Keys are ordered bytewise, a key before any longer key it prefixes.
+             */
+            let iterated: Vec<Vec<u8>> = db
+                .iter(CHAIN_DB_COLUMN)
+                .unwrap()
+                .map(|(key, _)| key)
+                .collect();
+            let mut sorted: Vec<Vec<u8>> = keys.iter().map(|key| key.to_vec()).collect();
+            sorted.sort();
+            assert_eq!(iterated, sorted);
+            assert_eq!(
+                db.iter(CHAIN_DB_COLUMN).unwrap().nth(3),
+                Some((b"a\x00".to_vec(), vec![1]))
+            );
+        }
+
+        #[test]
+        fn conformance_batch_atomicity() {
+            let db = $open;
+            db.put(BLOCKS_DB_COLUMN, b"deleted", b"val").unwrap();
+            db.write_batch(&[
+                BatchOp::Put(BLOCKS_DB_COLUMN, b"a".to_vec(), b"first".to_vec()),
+                BatchOp::Put(CHAIN_DB_COLUMN, b"b".to_vec(), b"val".to_vec()),
+                BatchOp::Delete(BLOCKS_DB_COLUMN, b"deleted".to_vec()),
+                BatchOp::Put(BLOCKS_DB_COLUMN, b"a".to_vec(), b"second".to_vec()),
+                BatchOp::Put(BLOCKS_DB_COLUMN, b"c".to_vec(), b"val".to_vec()),
+                BatchOp::Delete(BLOCKS_DB_COLUMN, b"c".to_vec()),
+            ])
+            .unwrap();
+
+            /*
+             * The writes are applied in order.
+             */
+            assert_eq!(
+                db.get(BLOCKS_DB_COLUMN, b"a").unwrap(),
+                Some(b"second".to_vec())
+            );
+            assert!(db.exists(CHAIN_DB_COLUMN, b"b").unwrap());
+            assert!(!db.exists(BLOCKS_DB_COLUMN, b"deleted").unwrap());
+            assert!(!db.exists(BLOCKS_DB_COLUMN, b"c").unwrap());
+            db.write_batch(&[]).unwrap();
+
+            /*
+             * A batch with a write to an unknown column fails without writing anything.
+             */
+            assert!(db
+                .write_batch(&[
+                    BatchOp::Put(BLOCKS_DB_COLUMN, b"a".to_vec(), b"third".to_vec()),
+                    BatchOp::Delete(CHAIN_DB_COLUMN, b"b".to_vec()),
+                    BatchOp::Put(UNKNOWN_COLUMN, b"a".to_vec(), b"val".to_vec()),
+                ])
+                .is_err());
+            assert_eq!(
+                db.get(BLOCKS_DB_COLUMN, b"a").unwrap(),
+                Some(b"second".to_vec())
+            );
+            assert!(db.exists(CHAIN_DB_COLUMN, b"b").unwrap());
+        }
+
+        #[test]
+        fn conformance_concurrent_access() {
+            let db = Arc::new($open);
+            let thread_count: u8 = 8;
+            let write_count: u8 = 32;
+
+            /*
+             * Each thread writes its own keys, reading back every write and iterating meanwhile,
+             * while a batch from each thread is never seen half-applied.
+             */
+            let handles: Vec<_> = (0..thread_count)
+                .map(|t| {
+                    let db = db.clone();
+                    thread::spawn(move || {
+                        for w in 0..write_count {
+                            db.put(BLOCKS_DB_COLUMN, &[t, w], &[w]).unwrap();
+                            assert_eq!(db.get(BLOCKS_DB_COLUMN, &[t, w]).unwrap(), Some(vec![w]));
+                            db.write_batch(&[
+                                BatchOp::Put(CHAIN_DB_COLUMN, vec![t, 0], vec![w]),
+                                BatchOp::Put(CHAIN_DB_COLUMN, vec![t, 1], vec![w]),
+                            ])
+                            .unwrap();
+                            let pairs: Vec<_> = db.iter(CHAIN_DB_COLUMN).unwrap().collect();
+                            for pair in pairs.chunks(2) {
+                                assert_eq!(pair[0].1, pair[1].1);
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            assert_eq!(
+                db.iter(BLOCKS_DB_COLUMN).unwrap().count(),
+                usize::from(thread_count) * usize::from(write_count)
+            );
+            for t in 0..thread_count {
+                assert_eq!(
+                    db.get(CHAIN_DB_COLUMN, &[t, 1]).unwrap(),
+                    Some(vec![write_count - 1])
+                );
+            }
+        }
+    };
+}
@@ -1,16 +1,21 @@
 extern crate rocksdb;
 
+use super::codec::ColumnCodecs;
+use super::metrics;
 use super::rocksdb::Error as RocksError;
-use super::rocksdb::{Options, DB};
-use super::{ClientDB, DBError, DBValue};
+use super::rocksdb::{IteratorMode, Options, WriteBatch, DB};
+use super::{BatchOp, ClientDB, DBError, DBValue};
+use lighthouse_metrics::{inc_counter, inc_counter_by};
 use std::fs;
 use std::path::Path;
 
 /// A on-disk database which implements the ClientDB trait.
 ///
-/// This implementation uses RocksDB with default options.
+/// This implementation uses RocksDB with default options, storing the values of each column
+/// with its codec.
 pub struct DiskDB {
     db: DB,
+    codecs: ColumnCodecs,
 }
 
 impl DiskDB {
@@ -23,19 +28,23 @@ impl DiskDB {
     ///
     /// Panics if the database is unable to be created.
     pub fn open(path: &Path, columns: Option<&[&str]>) -> Self {
+        Self::try_open(path, columns).expect("Unable to open local database")
+    }
+
+    /// Open the RocksDB database as `open` does, returning an error if it cannot be, e.g. because
+    /// another process has it open.
+    pub fn try_open(path: &Path, columns: Option<&[&str]>) -> Result<Self, DBError> {
         /*
          * Initialise the options
          */
         let mut options = Options::default();
         options.create_if_missing(true);
-
-        // TODO: ensure that columns are created (and remove
-        // the dead_code allow)
+        options.create_missing_column_families(true);
 
         /*
          * Initialise the path
          */
-        fs::create_dir_all(&path).unwrap_or_else(|_| panic!("Unable to create {:?}", &path));
+        fs::create_dir_all(&path).map_err(|e| DBError::new(format!("{:?}: {}", path, e)))?;
         let db_path = path.join("database");
 
         /*
@@ -44,9 +53,37 @@ impl DiskDB {
         let db = match columns {
             None => DB::open(&options, db_path),
             Some(columns) => DB::open_cf(&options, db_path, columns),
-        }.expect("Unable to open local database");;
+        }?;
+
+        Ok(Self {
+            db,
+            codecs: ColumnCodecs::default(),
+        })
+    }
+
+    /// Stores and reads the values of each column with its codec in `codecs`.
+    pub fn with_codecs(mut self, codecs: ColumnCodecs) -> Self {
+        self.codecs = codecs;
+        self
+    }
 
-        Self { db }
+    /// Writes the memtables to disk, so that no writes are left to be recovered from the
+    /// write-ahead log on the next open.
+    pub fn flush(&self) -> Result<(), DBError> {
+        self.db.flush().map_err(|e| e.into())
+    }
+
+    /// Compacts the whole of a column, discarding deleted and overwritten values.
+    pub fn compact(&self, col: &str) -> Result<(), DBError> {
+        match self.db.cf_handle(col) {
+            None => Err(DBError {
+                message: "Unknown column".to_string(),
+            }),
+            Some(handle) => {
+                self.db.compact_range_cf(handle, None, None);
+                Ok(())
+            }
+        }
     }
 
     /// Create a RocksDB column family. Corresponds to the
@@ -79,10 +116,16 @@ impl ClientDB for DiskDB {
             None => Err(DBError {
                 message: "Unknown column".to_string(),
             }),
-            Some(handle) => match self.db.get_cf(handle, key)? {
-                None => Ok(None),
-                Some(db_vec) => Ok(Some(DBValue::from(&*db_vec))),
-            },
+            Some(handle) => {
+                inc_counter(&metrics::DISK_DB_READ_COUNT);
+                match self.db.get_cf(handle, key)? {
+                    None => Ok(None),
+                    Some(db_vec) => {
+                        inc_counter_by(&metrics::DISK_DB_READ_BYTES, db_vec.len() as i64);
+                        self.codecs.decode(col, DBValue::from(&*db_vec)).map(Some)
+                    }
+                }
+            }
         }
     }
 
@@ -96,7 +139,12 @@ impl ClientDB for DiskDB {
             None => Err(DBError {
                 message: "Unknown column".to_string(),
             }),
-            Some(handle) => self.db.put_cf(handle, key, val).map_err(|e| e.into()),
+            Some(handle) => {
+                let stored = self.codecs.encode(col, val);
+                inc_counter(&metrics::DISK_DB_WRITE_COUNT);
+                inc_counter_by(&metrics::DISK_DB_WRITE_BYTES, stored.len() as i64);
+                self.db.put_cf(handle, key, &stored).map_err(|e| e.into())
+            }
         }
     }
 
@@ -126,19 +174,119 @@ impl ClientDB for DiskDB {
                 message: "Unknown column".to_string(),
             }),
             Some(handle) => {
+                inc_counter(&metrics::DISK_DB_DELETE_COUNT);
                 self.db.delete_cf(handle, key)?;
                 Ok(())
             }
         }
     }
+
+    /// Apply the writes of `ops` atomically, as one RocksDB `WriteBatch`.
+    ///
+    /// Nothing is written if any column is unknown.
+    fn write_batch(&self, ops: &[BatchOp]) -> Result<(), DBError> {
+        let unknown = || DBError {
+            message: "Unknown column".to_string(),
+        };
+        let mut batch = WriteBatch::default();
+        for op in ops {
+            match op {
+                BatchOp::Put(col, key, val) => {
+                    let handle = self.db.cf_handle(col).ok_or_else(unknown)?;
+                    let stored = self.codecs.encode(col, val);
+                    inc_counter(&metrics::DISK_DB_WRITE_COUNT);
+                    inc_counter_by(&metrics::DISK_DB_WRITE_BYTES, stored.len() as i64);
+                    batch.put_cf(handle, key, &stored)?;
+                }
+                BatchOp::Delete(col, key) => {
+                    let handle = self.db.cf_handle(col).ok_or_else(unknown)?;
+                    inc_counter(&metrics::DISK_DB_DELETE_COUNT);
+                    batch.delete_cf(handle, key)?;
+                }
+            }
+        }
+        self.db.write(batch).map_err(|e| e.into())
+    }
+
+    /// Iterate over the key-value pairs of some column, in order of key.
+    ///
+    /// Corresponds to the `iterator_cf()` method on the RocksDB API. A value which cannot be
+    /// decoded with the codec of the column is returned as it is stored.
+    fn iter<'a>(
+        &'a self,
+        col: &str,
+    ) -> Result<Box<dyn Iterator<Item = (DBValue, DBValue)> + 'a>, DBError> {
+        match self.db.cf_handle(col) {
+            None => Err(DBError {
+                message: "Unknown column".to_string(),
+            }),
+            Some(handle) => {
+                let iter = self.db.iterator_cf(handle, IteratorMode::Start)?;
+                let codec = self.codecs.codec(col);
+                Ok(Box::new(iter.map(move |(key, val)| {
+                    (key.to_vec(), codec.decode_or_stored(val.to_vec()))
+                })))
+            }
+        }
+    }
+
+    fn compact(&self, col: &str) -> Result<(), DBError> {
+        DiskDB::compact(self, col)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::stores::COLUMNS;
     use super::super::ClientDB;
     use super::*;
+    use std::ops::Deref;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
-    use std::{env, fs, thread};
+    use std::{env, fs, process, thread};
+
+    /// A database with `COLUMNS` in a fresh temporary directory, which is removed once the
+    /// database is dropped.
+    struct TempDiskDB {
+        db: Option<DiskDB>,
+        path: PathBuf,
+    }
+
+    impl TempDiskDB {
+        fn open() -> Self {
+            static OPENED: AtomicUsize = AtomicUsize::new(0);
+            let path = env::temp_dir().join(format!(
+                "lighthouse_disk_db_{}_{}",
+                process::id(),
+                OPENED.fetch_add(1, Ordering::SeqCst)
+            ));
+            let _ = fs::remove_dir_all(&path);
+            let db = DiskDB::open(&path, Some(&COLUMNS)).with_codecs(ColumnCodecs::standard());
+            Self { db: Some(db), path }
+        }
+    }
+
+    impl Deref for TempDiskDB {
+        type Target = DiskDB;
+
+        fn deref(&self) -> &DiskDB {
+            self.db.as_ref().expect("Database is open until dropped")
+        }
+    }
+
+    impl Drop for TempDiskDB {
+        fn drop(&mut self) {
+            self.db.take();
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    mod conformance {
+        use super::TempDiskDB;
+
+        client_db_conformance_tests!(TempDiskDB::open());
+    }
 
     #[test]
     #[ignore]
@@ -0,0 +1,98 @@
+use super::{ClientDB, DBError, DBValue};
+
+/// The width of an integer within a key.
+pub const INT_KEY_BYTES: usize = 8;
+
+/// Returns the key of an integer, such as a slot, a cycle or an index, after `prefix`.
+///
+/// The integer is big-endian and fixed-width, so that the keys of a prefix are ordered as their
+/// integers are, and iterating over a column ordered by key visits slots in chronological order.
+pub fn int_key(prefix: &[u8], n: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(prefix.len() + INT_KEY_BYTES);
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(&n.to_be_bytes());
+    key
+}
+
+/// Returns the integer of a key given by `int_key` with `prefix`, or `None` if `key` is not one.
+pub fn int_from_key(prefix: &[u8], key: &[u8]) -> Option<u64> {
+    if key.len() != prefix.len() + INT_KEY_BYTES || !key.starts_with(prefix) {
+        return None;
+    }
+    let mut bytes = [0; INT_KEY_BYTES];
+    bytes.copy_from_slice(&key[prefix.len()..]);
+    Some(u64::from_be_bytes(bytes))
+}
+
+/// Returns the key of a root or public key after `prefix`, which keeps the keys of different
+/// kinds of object within a column apart.
+pub fn root_key(prefix: &[u8], root: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(prefix.len() + root.len());
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(root);
+    key
+}
+
+/// Returns the values of `col` under the integer keys with `prefix` with their integers, in
+/// ascending order, whatever order the database iterates in.
+pub fn iter_int_keys<T: ClientDB + ?Sized>(
+    db: &T,
+    col: &str,
+    prefix: &[u8],
+) -> Result<Vec<(u64, DBValue)>, DBError> {
+    let mut pairs: Vec<(u64, DBValue)> = db
+        .iter(col)?
+        .filter_map(|(key, val)| int_from_key(prefix, &key).map(|n| (n, val)))
+        .collect();
+    pairs.sort_by_key(|(n, _)| *n);
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stores::CHAIN_DB_COLUMN;
+    use super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_int_keys_are_ordered() {
+        let keys: Vec<Vec<u8>> = [0, 1, 255, 256, 1 << 32, u64::MAX]
+            .iter()
+            .map(|n| int_key(b"slot", *n))
+            .collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+
+        assert_eq!(
+            int_key(b"slot", 258),
+            b"slot\x00\x00\x00\x00\x00\x00\x01\x02"
+        );
+        assert_eq!(int_from_key(b"slot", &int_key(b"slot", 258)), Some(258));
+        assert_eq!(int_from_key(b"cycle", &int_key(b"slot", 258)), None);
+        assert_eq!(int_from_key(b"slot", b"slot\x01"), None);
+        assert_eq!(root_key(b"block", &[1, 2]), b"block\x01\x02");
+    }
+
+    #[test]
+    fn test_iter_int_keys() {
+        let db = MemoryDB::open();
+        for slot in &[300, 2, 1 << 40, 0] {
+            db.put(CHAIN_DB_COLUMN, &int_key(b"slot", *slot), &[*slot as u8])
+                .unwrap();
+        }
+        db.put(CHAIN_DB_COLUMN, &int_key(b"cycle", 1), &[1])
+            .unwrap();
+        db.put(CHAIN_DB_COLUMN, b"slots", &[1]).unwrap();
+
+        assert_eq!(
+            iter_int_keys(&db, CHAIN_DB_COLUMN, b"slot").unwrap(),
+            vec![
+                (0, vec![0]),
+                (2, vec![2]),
+                (300, vec![44]),
+                (1 << 40, vec![0])
+            ]
+        );
+    }
+}
@@ -1,14 +1,31 @@
-extern crate blake2_rfc as blake2;
 extern crate bls;
+#[macro_use]
+extern crate lazy_static;
+extern crate libc;
+extern crate lighthouse_metrics;
 extern crate rocksdb;
+extern crate ssz;
 
+#[cfg(test)]
+#[macro_use]
+mod conformance;
+mod codec;
 mod disk_db;
+mod disk_space;
+pub mod keys;
 mod memory_db;
+mod metrics;
+mod schema;
+mod stats;
 pub mod stores;
 mod traits;
 
 use self::stores::COLUMNS;
 
+pub use self::codec::{Codec, ColumnCodecs};
 pub use self::disk_db::DiskDB;
+pub use self::disk_space::available_space;
 pub use self::memory_db::MemoryDB;
-pub use self::traits::{ClientDB, DBError, DBValue};
+pub use self::schema::{check_schema, migrate, schema_version, SchemaError, SCHEMA_VERSION};
+pub use self::stats::ColumnStats;
+pub use self::traits::{BatchOp, ClientDB, DBError, DBValue};
@@ -1,19 +1,19 @@
-use super::blake2::blake2b::blake2b;
+use super::codec::ColumnCodecs;
 use super::COLUMNS;
-use super::{ClientDB, DBError, DBValue};
-use std::collections::{HashMap, HashSet};
+use super::{BatchOp, ClientDB, DBError, DBValue};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::RwLock;
 
-type DBHashMap = HashMap<Vec<u8>, Vec<u8>>;
-type ColumnHashSet = HashSet<String>;
+type DBHashMap = BTreeMap<Vec<u8>, Vec<u8>>;
+type ColumnHashMap = HashMap<String, DBHashMap>;
 
 /// An in-memory database implementing the ClientDB trait.
 ///
 /// It is not particularily optimized, it exists for ease and speed of testing. It's not expected
 /// this DB would be used outside of tests.
 pub struct MemoryDB {
-    db: RwLock<DBHashMap>,
-    known_columns: RwLock<ColumnHashSet>,
+    columns: RwLock<ColumnHashMap>,
+    codecs: ColumnCodecs,
 }
 
 impl MemoryDB {
@@ -22,88 +22,111 @@ impl MemoryDB {
     /// All columns must be supplied initially, you will get an error if you try to access a column
     /// that was not declared here. This condition is enforced artificially to simulate RocksDB.
     pub fn open() -> Self {
-        let db: DBHashMap = HashMap::new();
-        let mut known_columns: ColumnHashSet = HashSet::new();
+        let mut columns: ColumnHashMap = HashMap::new();
         for col in &COLUMNS {
-            known_columns.insert(col.to_string());
+            columns.insert(col.to_string(), BTreeMap::new());
         }
         Self {
-            db: RwLock::new(db),
-            known_columns: RwLock::new(known_columns),
+            columns: RwLock::new(columns),
+            codecs: ColumnCodecs::default(),
         }
     }
 
-    /// Hashes a key and a column name in order to get a unique key for the supplied column.
-    fn get_key_for_col(col: &str, key: &[u8]) -> Vec<u8> {
-        blake2b(32, col.as_bytes(), key).as_bytes().to_vec()
+    /// Stores the values of each column with its codec in `codecs`, as `DiskDB` does.
+    pub fn with_codecs(mut self, codecs: ColumnCodecs) -> Self {
+        self.codecs = codecs;
+        self
+    }
+}
+
+fn unknown_column() -> DBError {
+    DBError {
+        message: "Unknown column".to_string(),
     }
 }
 
 impl ClientDB for MemoryDB {
     /// Get the value of some key from the database. Returns `None` if the key does not exist.
     fn get(&self, col: &str, key: &[u8]) -> Result<Option<DBValue>, DBError> {
-        // Panic if the DB locks are poisoned.
-        let db = self.db.read().unwrap();
-        let known_columns = self.known_columns.read().unwrap();
-
-        if known_columns.contains(&col.to_string()) {
-            let column_key = MemoryDB::get_key_for_col(col, key);
-            Ok(db.get(&column_key).and_then(|val| Some(val.clone())))
-        } else {
-            Err(DBError {
-                message: "Unknown column".to_string(),
-            })
-        }
+        // Panic if the DB lock is poisoned.
+        let columns = self.columns.read().unwrap();
+        let db = columns.get(col).ok_or_else(unknown_column)?;
+        db.get(key)
+            .cloned()
+            .map(|stored| self.codecs.decode(col, stored))
+            .transpose()
     }
 
     /// Puts a key in the database.
     fn put(&self, col: &str, key: &[u8], val: &[u8]) -> Result<(), DBError> {
-        // Panic if the DB locks are poisoned.
-        let mut db = self.db.write().unwrap();
-        let known_columns = self.known_columns.read().unwrap();
-
-        if known_columns.contains(&col.to_string()) {
-            let column_key = MemoryDB::get_key_for_col(col, key);
-            db.insert(column_key, val.to_vec());
-            Ok(())
-        } else {
-            Err(DBError {
-                message: "Unknown column".to_string(),
-            })
-        }
+        // Panic if the DB lock is poisoned.
+        let mut columns = self.columns.write().unwrap();
+        let db = columns.get_mut(col).ok_or_else(unknown_column)?;
+        db.insert(key.to_vec(), self.codecs.encode(col, val));
+        Ok(())
     }
 
     /// Return true if some key exists in some column.
     fn exists(&self, col: &str, key: &[u8]) -> Result<bool, DBError> {
-        // Panic if the DB locks are poisoned.
-        let db = self.db.read().unwrap();
-        let known_columns = self.known_columns.read().unwrap();
-
-        if known_columns.contains(&col.to_string()) {
-            let column_key = MemoryDB::get_key_for_col(col, key);
-            Ok(db.contains_key(&column_key))
-        } else {
-            Err(DBError {
-                message: "Unknown column".to_string(),
-            })
-        }
+        // Panic if the DB lock is poisoned.
+        let columns = self.columns.read().unwrap();
+        let db = columns.get(col).ok_or_else(unknown_column)?;
+        Ok(db.contains_key(key))
     }
 
     /// Delete some key from the database.
     fn delete(&self, col: &str, key: &[u8]) -> Result<(), DBError> {
-        // Panic if the DB locks are poisoned.
-        let mut db = self.db.write().unwrap();
-        let known_columns = self.known_columns.read().unwrap();
-
-        if known_columns.contains(&col.to_string()) {
-            let column_key = MemoryDB::get_key_for_col(col, key);
-            db.remove(&column_key);
-            Ok(())
-        } else {
-            Err(DBError {
-                message: "Unknown column".to_string(),
-            })
+        // Panic if the DB lock is poisoned.
+        let mut columns = self.columns.write().unwrap();
+        let db = columns.get_mut(col).ok_or_else(unknown_column)?;
+        db.remove(key);
+        Ok(())
+    }
+
+    /// Applies the writes under one lock, once their columns are known to exist.
+    fn write_batch(&self, ops: &[BatchOp]) -> Result<(), DBError> {
+        // Panic if the DB lock is poisoned.
+        let mut columns = self.columns.write().unwrap();
+        for op in ops {
+            match op {
+                BatchOp::Put(col, _, _) | BatchOp::Delete(col, _) => {
+                    if !columns.contains_key(*col) {
+                        return Err(unknown_column());
+                    }
+                }
+            }
         }
+        for op in ops {
+            match op {
+                BatchOp::Put(col, key, val) => {
+                    let encoded = self.codecs.encode(col, val);
+                    columns
+                        .get_mut(*col)
+                        .expect("Column checked")
+                        .insert(key.clone(), encoded);
+                }
+                BatchOp::Delete(col, key) => {
+                    columns.get_mut(*col).expect("Column checked").remove(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the pairs in the column, so that the column is not locked meanwhile.
+    fn iter<'a>(
+        &'a self,
+        col: &str,
+    ) -> Result<Box<dyn Iterator<Item = (DBValue, DBValue)> + 'a>, DBError> {
+        // Panic if the DB lock is poisoned.
+        let columns = self.columns.read().unwrap();
+        let db = columns.get(col).ok_or_else(unknown_column)?;
+        let codec = self.codecs.codec(col);
+        let pairs: Vec<(DBValue, DBValue)> = db
+            .iter()
+            .map(|(key, val)| (key.clone(), codec.decode_or_stored(val.clone())))
+            .collect();
+        Ok(Box::new(pairs.into_iter()))
     }
 }
 
@@ -115,6 +138,32 @@ mod tests {
     use std::sync::Arc;
     use std::thread;
 
+    #[test]
+    fn test_memorydb_codecs() {
+        let db = MemoryDB::open().with_codecs(ColumnCodecs::standard());
+        let block = vec![7; 1_000];
+        db.put(BLOCKS_DB_COLUMN, b"root", &block).unwrap();
+        db.put(VALIDATOR_DB_COLUMN, b"key", &block).unwrap();
+        assert_eq!(db.get(BLOCKS_DB_COLUMN, b"root").unwrap().unwrap(), block);
+        assert_eq!(db.iter(BLOCKS_DB_COLUMN).unwrap().next().unwrap().1, block);
+
+        /*
+         * Only the values of compressed columns are stored compressed, and values are read with
+         * the codec of their column.
+         */
+        let stored = {
+            let columns = db.columns.read().unwrap();
+            assert_eq!(columns[VALIDATOR_DB_COLUMN][&b"key"[..]], block);
+            columns[BLOCKS_DB_COLUMN][&b"root"[..]].clone()
+        };
+        assert!(stored.len() < block.len());
+        let db = MemoryDB {
+            codecs: ColumnCodecs::default(),
+            ..db
+        };
+        assert_eq!(db.get(BLOCKS_DB_COLUMN, b"root").unwrap().unwrap(), stored);
+    }
+
     #[test]
     fn test_memorydb_can_delete() {
         let col_a: &str = BLOCKS_DB_COLUMN;
@@ -194,6 +243,28 @@ mod tests {
         assert_eq!(false, db.exists(col_b, "dogs".as_bytes()).unwrap());
     }
 
+    #[test]
+    fn test_memorydb_iter() {
+        let col_a: &str = BLOCKS_DB_COLUMN;
+        let col_b: &str = VALIDATOR_DB_COLUMN;
+
+        let db = MemoryDB::open();
+        db.put(col_a, b"cats", b"lol").unwrap();
+        db.put(col_a, b"dogs", b"woof").unwrap();
+        db.put(col_b, b"cats", b"meow").unwrap();
+
+        let mut pairs: Vec<(DBValue, DBValue)> = db.iter(col_a).unwrap().collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                (b"cats".to_vec(), b"lol".to_vec()),
+                (b"dogs".to_vec(), b"woof".to_vec()),
+            ]
+        );
+        assert!(db.iter("ColumnX").is_err());
+    }
+
     #[test]
     fn test_memorydb_threading() {
         let col_name: &str = BLOCKS_DB_COLUMN;
@@ -233,4 +304,16 @@ mod tests {
             }
         }
     }
+
+    mod conformance {
+        use super::super::MemoryDB;
+
+        client_db_conformance_tests!(MemoryDB::open());
+    }
+
+    mod conformance_with_codecs {
+        use super::super::{ColumnCodecs, MemoryDB};
+
+        client_db_conformance_tests!(MemoryDB::open().with_codecs(ColumnCodecs::standard()));
+    }
 }
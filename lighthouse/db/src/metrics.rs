@@ -0,0 +1,22 @@
+use lighthouse_metrics::{try_create_int_counter, IntCounter, Result};
+
+lazy_static! {
+    pub static ref DISK_DB_READ_COUNT: Result<IntCounter> = try_create_int_counter(
+        "store_disk_db_read_count_total",
+        "Count of reads from the disk"
+    );
+    pub static ref DISK_DB_READ_BYTES: Result<IntCounter> =
+        try_create_int_counter("store_disk_db_read_bytes_total", "Bytes read from the disk");
+    pub static ref DISK_DB_WRITE_COUNT: Result<IntCounter> = try_create_int_counter(
+        "store_disk_db_write_count_total",
+        "Count of writes to the disk"
+    );
+    pub static ref DISK_DB_WRITE_BYTES: Result<IntCounter> = try_create_int_counter(
+        "store_disk_db_write_bytes_total",
+        "Bytes written to the disk"
+    );
+    pub static ref DISK_DB_DELETE_COUNT: Result<IntCounter> = try_create_int_counter(
+        "store_disk_db_delete_count_total",
+        "Count of deletions from the disk"
+    );
+}
@@ -0,0 +1,150 @@
+use super::ssz::{ssz_encode, Decodable};
+use super::stores::{BLOCKS_DB_COLUMN, CHAIN_DB_COLUMN, STATES_DB_COLUMN};
+use super::{ClientDB, DBError};
+
+/// The version of the layout of the database written by this version of Lighthouse.
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// The key under which the schema version is stored, in the chain column.
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+/// Upgrades a database from the schema version of its index in `MIGRATIONS` to the next.
+type Migration = fn(&dyn ClientDB) -> Result<(), DBError>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [migrate_v0, migrate_v1];
+
+#[derive(Debug, PartialEq)]
+pub enum SchemaError {
+    DBError(String),
+    /// The stored schema version cannot be decoded.
+    InvalidVersion,
+    /// The database was written by a newer version of Lighthouse, with this schema version.
+    Newer(u64),
+    /// The database must be migrated from this schema version.
+    Older(u64),
+}
+
+impl From<DBError> for SchemaError {
+    fn from(e: DBError) -> Self {
+        SchemaError::DBError(e.message)
+    }
+}
+
+/// Returns the schema version of the database, which is 0 for those from before it was
+/// versioned.
+pub fn schema_version<T: ClientDB>(db: &T) -> Result<u64, SchemaError> {
+    match db.get(CHAIN_DB_COLUMN, SCHEMA_VERSION_KEY)? {
+        None => Ok(0),
+        Some(ssz) => u64::ssz_decode(&ssz, 0)
+            .map(|(version, _)| version)
+            .map_err(|_| SchemaError::InvalidVersion),
+    }
+}
+
+/// Checks that the database has the layout of this version of Lighthouse, stamping a new
+/// database with `SCHEMA_VERSION`.
+pub fn check_schema<T: ClientDB>(db: &T) -> Result<(), SchemaError> {
+    match schema_version(db)? {
+        SCHEMA_VERSION => Ok(()),
+        0 if db.iter(BLOCKS_DB_COLUMN)?.next().is_none() => {
+            Ok(put_schema_version(db, SCHEMA_VERSION)?)
+        }
+        version if version > SCHEMA_VERSION => Err(SchemaError::Newer(version)),
+        version => Err(SchemaError::Older(version)),
+    }
+}
+
+/// Migrates the database to `SCHEMA_VERSION` one version at a time, returning the version it was
+/// migrated from.
+pub fn migrate<T: ClientDB>(db: &T) -> Result<u64, SchemaError> {
+    let from = schema_version(db)?;
+    if from > SCHEMA_VERSION {
+        return Err(SchemaError::Newer(from));
+    }
+    for version in from..SCHEMA_VERSION {
+        MIGRATIONS[version as usize](db)?;
+        put_schema_version(db, version + 1)?;
+    }
+    Ok(from)
+}
+
+fn put_schema_version<T: ClientDB>(db: &T, version: u64) -> Result<(), DBError> {
+    db.put(CHAIN_DB_COLUMN, SCHEMA_VERSION_KEY, &ssz_encode(&version))
+}
+
+/// Databases from before the schema was versioned lack only the chain column, which is created
+/// when the database is opened.
+fn migrate_v0(_db: &dyn ClientDB) -> Result<(), DBError> {
+    Ok(())
+}
+
+/// Values are decoded with the codec of their column, so the blocks and state snapshots stored
+/// before their columns were compressed are rewritten with the codec of the database. Iterating
+/// returns those which cannot be decompressed as they are stored.
+fn migrate_v1(db: &dyn ClientDB) -> Result<(), DBError> {
+    for col in &[BLOCKS_DB_COLUMN, STATES_DB_COLUMN] {
+        for (key, val) in db.iter(col)? {
+            db.put(col, &key, &val)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ColumnCodecs, MemoryDB};
+    use super::*;
+
+    #[test]
+    fn test_new_database_is_stamped() {
+        let db = MemoryDB::open();
+        assert_eq!(schema_version(&db), Ok(0));
+        assert_eq!(check_schema(&db), Ok(()));
+        assert_eq!(schema_version(&db), Ok(SCHEMA_VERSION));
+        assert_eq!(check_schema(&db), Ok(()));
+    }
+
+    #[test]
+    fn test_migrate_unversioned_database() {
+        let db = MemoryDB::open();
+        db.put(BLOCKS_DB_COLUMN, b"root", b"block").unwrap();
+        assert_eq!(check_schema(&db), Err(SchemaError::Older(0)));
+
+        assert_eq!(migrate(&db), Ok(0));
+        assert_eq!(check_schema(&db), Ok(()));
+        assert_eq!(migrate(&db), Ok(SCHEMA_VERSION));
+        assert_eq!(
+            db.get(BLOCKS_DB_COLUMN, b"root").unwrap().unwrap(),
+            b"block"
+        );
+    }
+
+    #[test]
+    fn test_migrate_uncompressed_values() {
+        let db = MemoryDB::open();
+        put_schema_version(&db, 1).unwrap();
+        db.put(BLOCKS_DB_COLUMN, b"root", &[7; 100]).unwrap();
+        let db = db.with_codecs(ColumnCodecs::standard());
+        assert!(db.get(BLOCKS_DB_COLUMN, b"root").is_err());
+
+        assert_eq!(migrate(&db), Ok(1));
+        assert_eq!(
+            db.get(BLOCKS_DB_COLUMN, b"root").unwrap().unwrap(),
+            vec![7; 100]
+        );
+    }
+
+    #[test]
+    fn test_newer_database() {
+        let db = MemoryDB::open();
+        put_schema_version(&db, SCHEMA_VERSION + 1).unwrap();
+        assert_eq!(
+            check_schema(&db),
+            Err(SchemaError::Newer(SCHEMA_VERSION + 1))
+        );
+        assert_eq!(migrate(&db), Err(SchemaError::Newer(SCHEMA_VERSION + 1)));
+
+        db.put(CHAIN_DB_COLUMN, SCHEMA_VERSION_KEY, &[1]).unwrap();
+        assert_eq!(check_schema(&db), Err(SchemaError::InvalidVersion));
+    }
+}
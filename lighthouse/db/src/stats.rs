@@ -0,0 +1,49 @@
+use super::{ClientDB, DBError};
+
+/// The number and size of the entries in a column.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ColumnStats {
+    pub keys: u64,
+    pub key_bytes: u64,
+    pub value_bytes: u64,
+}
+
+impl ColumnStats {
+    /// Counts the entries of `col` by iterating over them.
+    pub fn of<T: ClientDB>(db: &T, col: &str) -> Result<Self, DBError> {
+        let mut stats = Self::default();
+        for (key, val) in db.iter(col)? {
+            stats.keys += 1;
+            stats.key_bytes += key.len() as u64;
+            stats.value_bytes += val.len() as u64;
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::stores::{BLOCKS_DB_COLUMN, PEERS_DB_COLUMN};
+    use super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_column_stats() {
+        let db = MemoryDB::open();
+        db.put(BLOCKS_DB_COLUMN, b"a", b"123").unwrap();
+        db.put(BLOCKS_DB_COLUMN, b"bc", b"4").unwrap();
+        assert_eq!(
+            ColumnStats::of(&db, BLOCKS_DB_COLUMN).unwrap(),
+            ColumnStats {
+                keys: 2,
+                key_bytes: 3,
+                value_bytes: 4,
+            }
+        );
+        assert_eq!(
+            ColumnStats::of(&db, PEERS_DB_COLUMN).unwrap(),
+            ColumnStats::default()
+        );
+        assert!(ColumnStats::of(&db, "ColumnX").is_err());
+    }
+}
@@ -3,6 +3,7 @@ extern crate ssz_helpers;
 use self::ssz_helpers::ssz_beacon_block::SszBeaconBlock;
 use super::BLOCKS_DB_COLUMN as DB_COLUMN;
 use super::{ClientDB, DBError};
+use std::collections::HashSet;
 use std::sync::Arc;
 
 type BeaconBlockHash = Vec<u8>;
@@ -73,6 +74,108 @@ impl<T: ClientDB> BeaconBlockStore<T> {
             }
         }
     }
+
+    /// Returns the blocks of the chain ending at "head_hash" with slots in
+    /// `start_slot..end_slot`, in order of ascending slot.
+    ///
+    /// Each block is a tuple of (block_hash, serialized_block). Skipped slots have no block.
+    pub fn blocks_in_slot_range(
+        &self,
+        head_hash: &[u8],
+        start_slot: u64,
+        end_slot: u64,
+    ) -> Result<Vec<(BeaconBlockHash, BeaconBlockSsz)>, BeaconBlockAtSlotError> {
+        let mut blocks = vec![];
+        for result in self.block_iter(head_hash) {
+            let (hash, ssz) = result?;
+            let slot = SszBeaconBlock::from_slice(&ssz)
+                .map_err(|_| BeaconBlockAtSlotError::InvalidBeaconBlock)?
+                .slot();
+            if slot < start_slot {
+                break;
+            }
+            if slot < end_slot {
+                blocks.push((hash, ssz));
+            }
+        }
+        blocks.reverse();
+        Ok(blocks)
+    }
+
+    /// Returns an iterator over the chain ending at "head_hash", from the head backwards.
+    ///
+    /// When given the canonical head, the iterator yields the canonical chain in order of
+    /// descending slot. See `BeaconBlockIter` for termination conditions.
+    pub fn block_iter<'a>(&'a self, head_hash: &[u8]) -> BeaconBlockIter<'a, T> {
+        BeaconBlockIter {
+            store: self,
+            next_hash: Some(head_hash.to_vec()),
+        }
+    }
+
+    /// Deletes the blocks which are not in any of the chains ending at "head_hashes", e.g. those
+    /// of abandoned forks, returning the number deleted.
+    ///
+    /// Nothing is deleted if any of the heads is unknown.
+    pub fn prune(&self, head_hashes: &[Vec<u8>]) -> Result<usize, BeaconBlockAtSlotError> {
+        let mut keep = HashSet::new();
+        for head_hash in head_hashes {
+            if !self.block_exists(head_hash)? {
+                return Err(BeaconBlockAtSlotError::UnknownBeaconBlock);
+            }
+            for result in self.block_iter(head_hash) {
+                let (hash, _) = result?;
+                /*
+                 * The ancestors of a block already kept are kept too.
+                 */
+                if !keep.insert(hash) {
+                    break;
+                }
+            }
+        }
+        let stale: Vec<BeaconBlockHash> = self
+            .db
+            .iter(DB_COLUMN)?
+            .map(|(hash, _)| hash)
+            .filter(|hash| !keep.contains(hash))
+            .collect();
+        for hash in &stale {
+            self.delete_block(hash)?;
+        }
+        Ok(stale.len())
+    }
+}
+
+/// Iterates backwards through a chain of blocks by following each block's parent hash.
+///
+/// Each item is a tuple of (block_hash, serialized_block). Iteration ends when a block is reached
+/// whose parent is not in the store (e.g., the first block after genesis). An unknown head block
+/// produces an empty iterator. If a block cannot be read or decoded, the error is returned and
+/// iteration ends.
+pub struct BeaconBlockIter<'a, T>
+where
+    T: 'a + ClientDB,
+{
+    store: &'a BeaconBlockStore<T>,
+    next_hash: Option<BeaconBlockHash>,
+}
+
+impl<'a, T: ClientDB> Iterator for BeaconBlockIter<'a, T> {
+    type Item = Result<(BeaconBlockHash, BeaconBlockSsz), BeaconBlockAtSlotError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let hash = self.next_hash.take()?;
+        let ssz = match self.store.get_serialized_block(&hash) {
+            Ok(Some(ssz)) => ssz,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e.into())),
+        };
+        match SszBeaconBlock::from_slice(&ssz) {
+            Ok(block) => self.next_hash = block.parent_hash().map(|hash| hash.to_vec()),
+            Err(_) => return Some(Err(BeaconBlockAtSlotError::InvalidBeaconBlock)),
+        }
+        Some(Ok((hash, ssz)))
+    }
 }
 
 impl From<DBError> for BeaconBlockAtSlotError {
@@ -311,4 +414,104 @@ mod tests {
         let ssz = bs.block_at_slot(&Hash256::from("unknown".as_bytes()), 2);
         assert_eq!(ssz, Err(BeaconBlockAtSlotError::UnknownBeaconBlock));
     }
+
+    #[test]
+    fn test_block_iter() {
+        let db = Arc::new(MemoryDB::open());
+        let bs = BeaconBlockStore::new(db.clone());
+
+        let hashes = [
+            Hash256::from("zero".as_bytes()),
+            Hash256::from("one".as_bytes()),
+            Hash256::from("two".as_bytes()),
+        ];
+        let slots = [0, 2, 3];
+
+        for i in 0..hashes.len() {
+            let mut block = BeaconBlock::zero();
+            block.slot = slots[i];
+            if i == 0 {
+                block.ancestor_hashes.push(Hash256::from("genesis".as_bytes()));
+            } else {
+                block.ancestor_hashes.push(hashes[i - 1]);
+            }
+            let mut s = SszStream::new();
+            s.append(&block);
+            db.put(DB_COLUMN, &hashes[i], &s.drain()).unwrap();
+        }
+
+        let iter_slots: Vec<u64> = bs
+            .block_iter(&hashes[2])
+            .map(|result| {
+                let (_, ssz) = result.unwrap();
+                SszBeaconBlock::from_slice(&ssz).unwrap().slot()
+            }).collect();
+        assert_eq!(iter_slots, vec![3, 2, 0]);
+
+        let iter_hashes: Vec<Vec<u8>> = bs
+            .block_iter(&hashes[1])
+            .map(|result| result.unwrap().0)
+            .collect();
+        assert_eq!(iter_hashes, vec![hashes[1].to_vec(), hashes[0].to_vec()]);
+
+        assert_eq!(bs.block_iter(&Hash256::from("unknown".as_bytes())).count(), 0);
+
+        let range_hashes = |start_slot, end_slot| -> Vec<Vec<u8>> {
+            bs.blocks_in_slot_range(&hashes[2], start_slot, end_slot)
+                .unwrap()
+                .into_iter()
+                .map(|(hash, _)| hash)
+                .collect()
+        };
+        assert_eq!(range_hashes(0, 3), vec![hashes[0].to_vec(), hashes[1].to_vec()]);
+        assert_eq!(range_hashes(1, 4), vec![hashes[1].to_vec(), hashes[2].to_vec()]);
+        assert_eq!(range_hashes(4, 8), Vec::<Vec<u8>>::new());
+
+        db.put(DB_COLUMN, &hashes[0], "invalid".as_bytes())
+            .unwrap();
+        let results: Vec<_> = bs.block_iter(&hashes[1]).collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1], Err(BeaconBlockAtSlotError::InvalidBeaconBlock));
+    }
+
+    #[test]
+    fn test_prune() {
+        let db = Arc::new(MemoryDB::open());
+        let bs = BeaconBlockStore::new(db.clone());
+
+        /*
+         * A chain of zero, one and two, with a fork from one and a block whose parent is unknown.
+         */
+        let hash = |name: &str| Hash256::from(name.as_bytes());
+        let blocks = [
+            ("zero", "genesis"),
+            ("one", "zero"),
+            ("two", "one"),
+            ("fork", "one"),
+            ("orphan", "unknown"),
+        ];
+        for &(name, parent) in blocks.iter() {
+            let mut block = BeaconBlock::zero();
+            block.ancestor_hashes.push(hash(parent));
+            let mut s = SszStream::new();
+            s.append(&block);
+            db.put(DB_COLUMN, &hash(name), &s.drain()).unwrap();
+        }
+
+        assert_eq!(
+            bs.prune(&[hash("two").to_vec(), hash("unknown").to_vec()]),
+            Err(BeaconBlockAtSlotError::UnknownBeaconBlock)
+        );
+        assert_eq!(db.iter(DB_COLUMN).unwrap().count(), 5);
+
+        assert_eq!(
+            bs.prune(&[hash("two").to_vec(), hash("one").to_vec()]),
+            Ok(2)
+        );
+        for &(name, _) in blocks.iter() {
+            let kept = name != "fork" && name != "orphan";
+            assert_eq!(bs.block_exists(&hash(name)).unwrap(), kept);
+        }
+        assert_eq!(bs.prune(&[hash("two").to_vec()]), Ok(0));
+    }
 }
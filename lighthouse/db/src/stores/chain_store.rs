@@ -0,0 +1,105 @@
+use super::CHAIN_DB_COLUMN as DB_COLUMN;
+use super::{ClientDB, DBError};
+use std::sync::Arc;
+
+/// The key under which the heads of the chain, as chosen by fork choice, are stored.
+const HEAD_KEY: &[u8] = b"head";
+/// The key under which the operations waiting to be included in a block are stored.
+const OP_POOL_KEY: &[u8] = b"op_pool";
+/// The key under which the blocks and latest messages known to fork choice are stored.
+const FORK_CHOICE_KEY: &[u8] = b"fork_choice";
+/// The key under which the weak subjectivity checkpoint the chain must descend from is stored.
+const WEAK_SUBJECTIVITY_KEY: &[u8] = b"weak_subjectivity";
+/// The key under which the root of the latest block imported by sync is stored.
+const IMPORT_TIP_KEY: &[u8] = b"import_tip";
+
+/// Stores the beacon node's in-memory view of the chain on shutdown, so that it may be restored
+/// on the next start.
+///
+/// The records are opaque to the store; their encoding is defined by the beacon node.
+pub struct ChainStore<T>
+where
+    T: ClientDB,
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> ChainStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self { db }
+    }
+
+    pub fn put_serialized_head(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, HEAD_KEY, ssz)
+    }
+
+    pub fn get_serialized_head(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, HEAD_KEY)
+    }
+
+    pub fn put_serialized_op_pool(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, OP_POOL_KEY, ssz)
+    }
+
+    pub fn get_serialized_op_pool(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, OP_POOL_KEY)
+    }
+
+    pub fn put_serialized_fork_choice(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, FORK_CHOICE_KEY, ssz)
+    }
+
+    pub fn get_serialized_fork_choice(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, FORK_CHOICE_KEY)
+    }
+
+    pub fn put_serialized_weak_subjectivity(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, WEAK_SUBJECTIVITY_KEY, ssz)
+    }
+
+    pub fn get_serialized_weak_subjectivity(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, WEAK_SUBJECTIVITY_KEY)
+    }
+
+    pub fn put_serialized_import_tip(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, IMPORT_TIP_KEY, ssz)
+    }
+
+    pub fn get_serialized_import_tip(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, IMPORT_TIP_KEY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_put_get() {
+        let db = Arc::new(MemoryDB::open());
+        let store = ChainStore::new(db.clone());
+
+        assert_eq!(store.get_serialized_head().unwrap(), None);
+        assert_eq!(store.get_serialized_op_pool().unwrap(), None);
+        assert_eq!(store.get_serialized_weak_subjectivity().unwrap(), None);
+        assert_eq!(store.get_serialized_fork_choice().unwrap(), None);
+
+        store.put_serialized_head(&[1, 2, 3]).unwrap();
+        store.put_serialized_op_pool(&[4, 5]).unwrap();
+        store.put_serialized_op_pool(&[6]).unwrap();
+        assert_eq!(store.get_serialized_head().unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(store.get_serialized_op_pool().unwrap(), Some(vec![6]));
+        store.put_serialized_weak_subjectivity(&[7, 8]).unwrap();
+        assert_eq!(
+            store.get_serialized_weak_subjectivity().unwrap(),
+            Some(vec![7, 8])
+        );
+        store.put_serialized_fork_choice(&[9]).unwrap();
+        assert_eq!(store.get_serialized_fork_choice().unwrap(), Some(vec![9]));
+        assert_eq!(store.get_serialized_import_tip().unwrap(), None);
+        store.put_serialized_import_tip(&[10]).unwrap();
+        assert_eq!(store.get_serialized_import_tip().unwrap(), Some(vec![10]));
+        assert!(db.exists(DB_COLUMN, HEAD_KEY).unwrap());
+    }
+}
@@ -0,0 +1,84 @@
+use super::GOSSIP_DB_COLUMN as DB_COLUMN;
+use super::{ClientDB, DBError};
+use std::sync::Arc;
+
+/// The key under which the gossip messages and duties already seen are stored.
+const SEEN_KEY: &[u8] = b"seen";
+/// The key under which the attestation subnet subscriptions are stored.
+const SUBNET_SUBSCRIPTIONS_KEY: &[u8] = b"subnet_subscriptions";
+
+/// Stores the short-lived gossip state of the network service, so that a node restarted within
+/// minutes carries on where it left off.
+///
+/// Each record carries its own expiry, and is useless soon after it is written, so the column
+/// may be cleared at any time. The records are opaque to the store; their encoding is defined by
+/// the network service.
+pub struct GossipStore<T>
+where
+    T: ClientDB,
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> GossipStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self { db }
+    }
+
+    /// Replaces the stored seen messages and duties with `ssz`.
+    pub fn put_serialized_seen(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, SEEN_KEY, ssz)
+    }
+
+    pub fn get_serialized_seen(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, SEEN_KEY)
+    }
+
+    /// Replaces the stored subnet subscriptions with `ssz`.
+    pub fn put_serialized_subnet_subscriptions(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, SUBNET_SUBSCRIPTIONS_KEY, ssz)
+    }
+
+    pub fn get_serialized_subnet_subscriptions(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, SUBNET_SUBSCRIPTIONS_KEY)
+    }
+
+    /// Deletes everything stored, e.g. once it has been restored.
+    pub fn clear(&self) -> Result<(), DBError> {
+        self.db.delete(DB_COLUMN, SEEN_KEY)?;
+        self.db.delete(DB_COLUMN, SUBNET_SUBSCRIPTIONS_KEY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_put_get_clear() {
+        let db = Arc::new(MemoryDB::open());
+        let store = GossipStore::new(db.clone());
+
+        assert_eq!(store.get_serialized_seen().unwrap(), None);
+        assert_eq!(store.get_serialized_subnet_subscriptions().unwrap(), None);
+
+        store.put_serialized_seen(&[1, 2]).unwrap();
+        store.put_serialized_subnet_subscriptions(&[3]).unwrap();
+        assert_eq!(store.get_serialized_seen().unwrap(), Some(vec![1, 2]));
+        assert_eq!(
+            store.get_serialized_subnet_subscriptions().unwrap(),
+            Some(vec![3])
+        );
+        assert!(db.exists(DB_COLUMN, SEEN_KEY).unwrap());
+
+        store.clear().unwrap();
+        assert_eq!(store.get_serialized_seen().unwrap(), None);
+        assert_eq!(store.get_serialized_subnet_subscriptions().unwrap(), None);
+
+        /*
+         * Clearing an empty store is not an error.
+         */
+        store.clear().unwrap();
+    }
+}
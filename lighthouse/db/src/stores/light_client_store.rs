@@ -0,0 +1,71 @@
+use super::super::keys::{int_key, iter_int_keys};
+use super::LIGHT_CLIENT_DB_COLUMN as DB_COLUMN;
+use super::{ClientDB, DBError};
+use std::sync::Arc;
+
+/// The prefix of the key under which the update of each period is stored.
+const UPDATE_PREFIX: &[u8] = b"update";
+
+/// Stores the data from which light clients follow the chain, one update per period of cycles.
+///
+/// The updates are opaque to the store; their encoding is defined by the beacon node.
+pub struct LightClientStore<T>
+where
+    T: ClientDB,
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> LightClientStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self { db }
+    }
+
+    /// Replaces the update of `period` with `ssz`.
+    pub fn put_serialized_update(&self, period: u64, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, &int_key(UPDATE_PREFIX, period), ssz)
+    }
+
+    pub fn get_serialized_update(&self, period: u64) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, &int_key(UPDATE_PREFIX, period))
+    }
+
+    /// Returns the updates of at most `count` periods from `start_period`, by ascending period,
+    /// skipping periods without an update.
+    pub fn serialized_updates(
+        &self,
+        start_period: u64,
+        count: u64,
+    ) -> Result<Vec<(u64, Vec<u8>)>, DBError> {
+        let end_period = start_period.saturating_add(count);
+        Ok(iter_int_keys(&*self.db, DB_COLUMN, UPDATE_PREFIX)?
+            .into_iter()
+            .filter(|(period, _)| *period >= start_period && *period < end_period)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_put_get_updates() {
+        let store = LightClientStore::new(Arc::new(MemoryDB::open()));
+
+        assert_eq!(store.get_serialized_update(0).unwrap(), None);
+        store.put_serialized_update(3, &[3]).unwrap();
+        store.put_serialized_update(0, &[0]).unwrap();
+        store.put_serialized_update(0, &[0, 1]).unwrap();
+        store.put_serialized_update(1, &[1]).unwrap();
+        assert_eq!(store.get_serialized_update(0).unwrap(), Some(vec![0, 1]));
+
+        assert_eq!(
+            store.serialized_updates(0, 4).unwrap(),
+            vec![(0, vec![0, 1]), (1, vec![1]), (3, vec![3])]
+        );
+        assert_eq!(store.serialized_updates(1, 2).unwrap(), vec![(1, vec![1])]);
+        assert!(store.serialized_updates(4, u64::MAX).unwrap().is_empty());
+    }
+}
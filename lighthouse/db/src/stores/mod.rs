@@ -1,11 +1,25 @@
 use super::{ClientDB, DBError};
 
 mod beacon_block_store;
+mod chain_store;
+mod gossip_store;
+mod light_client_store;
+mod participation_store;
+mod peer_store;
 mod pow_chain_store;
+mod slashing_protection_store;
+mod state_store;
 mod validator_store;
 
-pub use self::beacon_block_store::{BeaconBlockAtSlotError, BeaconBlockStore};
+pub use self::beacon_block_store::{BeaconBlockAtSlotError, BeaconBlockIter, BeaconBlockStore};
+pub use self::chain_store::ChainStore;
+pub use self::gossip_store::GossipStore;
+pub use self::light_client_store::LightClientStore;
+pub use self::participation_store::ParticipationStore;
+pub use self::peer_store::PeerStore;
 pub use self::pow_chain_store::PoWChainStore;
+pub use self::slashing_protection_store::SlashingProtectionStore;
+pub use self::state_store::{StatePruning, StateStore};
 pub use self::validator_store::{ValidatorStore, ValidatorStoreError};
 
 use super::bls;
@@ -13,5 +27,23 @@ use super::bls;
 pub const BLOCKS_DB_COLUMN: &str = "blocks";
 pub const POW_CHAIN_DB_COLUMN: &str = "powchain";
 pub const VALIDATOR_DB_COLUMN: &str = "validator";
+pub const PEERS_DB_COLUMN: &str = "peers";
+pub const SLASHING_PROTECTION_DB_COLUMN: &str = "slashing_protection";
+pub const CHAIN_DB_COLUMN: &str = "chain";
+pub const GOSSIP_DB_COLUMN: &str = "gossip";
+pub const STATES_DB_COLUMN: &str = "states";
+pub const PARTICIPATION_DB_COLUMN: &str = "participation";
+pub const LIGHT_CLIENT_DB_COLUMN: &str = "light_client";
 
-pub const COLUMNS: [&str; 3] = [BLOCKS_DB_COLUMN, POW_CHAIN_DB_COLUMN, VALIDATOR_DB_COLUMN];
+pub const COLUMNS: [&str; 10] = [
+    BLOCKS_DB_COLUMN,
+    POW_CHAIN_DB_COLUMN,
+    VALIDATOR_DB_COLUMN,
+    PEERS_DB_COLUMN,
+    SLASHING_PROTECTION_DB_COLUMN,
+    CHAIN_DB_COLUMN,
+    GOSSIP_DB_COLUMN,
+    STATES_DB_COLUMN,
+    PARTICIPATION_DB_COLUMN,
+    LIGHT_CLIENT_DB_COLUMN,
+];
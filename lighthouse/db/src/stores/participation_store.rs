@@ -0,0 +1,150 @@
+use super::super::keys::{int_from_key, int_key, iter_int_keys};
+use super::PARTICIPATION_DB_COLUMN as DB_COLUMN;
+use super::{ClientDB, DBError};
+use std::sync::Arc;
+
+/// The prefix of the key under which each validator's participation is stored.
+const VALIDATOR_PREFIX: &[u8] = b"validator";
+/// The prefix of the key under which the participation of the committees of each cycle is
+/// stored.
+const CYCLE_PREFIX: &[u8] = b"cycle";
+/// The prefix of the key under which the attestations of the monitored validators in each cycle
+/// are stored.
+const MONITOR_PREFIX: &[u8] = b"monitor";
+
+/// Stores what is known of the attestations of each validator from the blocks imported, by
+/// validator index, and the participation of each cycle accounted, by cycle.
+///
+/// There is one record per validator, but the records of cycles accumulate, so they are pruned
+/// once they leave the window kept.
+///
+/// The records are opaque to the store; their encoding is defined by the beacon node.
+pub struct ParticipationStore<T>
+where
+    T: ClientDB,
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> ParticipationStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self { db }
+    }
+
+    /// Replaces the participation of the validator with `index` with `ssz`.
+    pub fn put_serialized_participation(&self, index: u64, ssz: &[u8]) -> Result<(), DBError> {
+        self.db
+            .put(DB_COLUMN, &int_key(VALIDATOR_PREFIX, index), ssz)
+    }
+
+    pub fn get_serialized_participation(&self, index: u64) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, &int_key(VALIDATOR_PREFIX, index))
+    }
+
+    /// Returns the participation of every validator stored, by ascending index.
+    pub fn all_serialized_participation(&self) -> Result<Vec<(u64, Vec<u8>)>, DBError> {
+        iter_int_keys(&*self.db, DB_COLUMN, VALIDATOR_PREFIX)
+    }
+
+    pub fn put_serialized_cycle(&self, cycle: u64, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, &int_key(CYCLE_PREFIX, cycle), ssz)
+    }
+
+    pub fn get_serialized_cycle(&self, cycle: u64) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, &int_key(CYCLE_PREFIX, cycle))
+    }
+
+    pub fn put_serialized_monitor_cycle(&self, cycle: u64, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, &int_key(MONITOR_PREFIX, cycle), ssz)
+    }
+
+    pub fn get_serialized_monitor_cycle(&self, cycle: u64) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, &int_key(MONITOR_PREFIX, cycle))
+    }
+
+    /// Deletes the records of `cycle`, if any.
+    pub fn delete_cycle(&self, cycle: u64) -> Result<(), DBError> {
+        self.db.delete(DB_COLUMN, &int_key(CYCLE_PREFIX, cycle))?;
+        self.db.delete(DB_COLUMN, &int_key(MONITOR_PREFIX, cycle))
+    }
+
+    /// Deletes the records of every cycle before `cycle`, returning the number of keys deleted.
+    ///
+    /// The whole column is read, so this is for pruning records left by an earlier run; records
+    /// written since are pruned one cycle at a time by `delete_cycle`.
+    pub fn prune_cycles_before(&self, cycle: u64) -> Result<usize, DBError> {
+        let stale: Vec<Vec<u8>> = self
+            .db
+            .iter(DB_COLUMN)?
+            .map(|(key, _)| key)
+            .filter(|key| {
+                [CYCLE_PREFIX, MONITOR_PREFIX]
+                    .iter()
+                    .filter_map(|prefix| int_from_key(prefix, key))
+                    .any(|n| n < cycle)
+            })
+            .collect();
+        for key in &stale {
+            self.db.delete(DB_COLUMN, key)?;
+        }
+        Ok(stale.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_put_get_participation() {
+        let store = ParticipationStore::new(Arc::new(MemoryDB::open()));
+
+        assert_eq!(store.get_serialized_participation(1).unwrap(), None);
+        assert!(store.all_serialized_participation().unwrap().is_empty());
+        store.put_serialized_participation(300, &[3]).unwrap();
+        store.put_serialized_participation(1, &[1]).unwrap();
+        store.put_serialized_participation(1, &[1, 2]).unwrap();
+        assert_eq!(
+            store.get_serialized_participation(1).unwrap(),
+            Some(vec![1, 2])
+        );
+        assert_eq!(
+            store.all_serialized_participation().unwrap(),
+            vec![(1, vec![1, 2]), (300, vec![3])]
+        );
+    }
+
+    #[test]
+    fn test_prune_cycles() {
+        let store = ParticipationStore::new(Arc::new(MemoryDB::open()));
+        store.put_serialized_participation(1, &[1]).unwrap();
+        for cycle in 0..4 {
+            store.put_serialized_cycle(cycle, &[cycle as u8]).unwrap();
+            store
+                .put_serialized_monitor_cycle(cycle, &[cycle as u8])
+                .unwrap();
+        }
+        assert_eq!(store.get_serialized_cycle(2).unwrap(), Some(vec![2]));
+
+        store.delete_cycle(3).unwrap();
+        assert_eq!(store.get_serialized_cycle(3).unwrap(), None);
+        assert_eq!(store.get_serialized_monitor_cycle(3).unwrap(), None);
+
+        /*
+         * The records of validators are kept, whatever their index.
+         */
+        assert_eq!(store.prune_cycles_before(2).unwrap(), 4);
+        assert_eq!(store.get_serialized_cycle(1).unwrap(), None);
+        assert_eq!(store.get_serialized_monitor_cycle(0).unwrap(), None);
+        assert_eq!(
+            store.get_serialized_monitor_cycle(2).unwrap(),
+            Some(vec![2])
+        );
+        assert_eq!(
+            store.get_serialized_participation(1).unwrap(),
+            Some(vec![1])
+        );
+        assert_eq!(store.prune_cycles_before(2).unwrap(), 0);
+    }
+}
@@ -0,0 +1,103 @@
+use super::PEERS_DB_COLUMN as DB_COLUMN;
+use super::{ClientDB, DBError};
+use std::sync::Arc;
+
+/// The key under which the known peers are stored.
+const PEERS_KEY: &[u8] = b"known_peers";
+/// The key under which peers banned for misbehaviour are stored.
+const BANS_KEY: &[u8] = b"banned_peers";
+/// The key under which the record of the local node is stored.
+const LOCAL_ENR_KEY: &[u8] = b"local_enr";
+
+/// Stores the peers known to the network service so they may be dialed after a restart, along
+/// with the local node's own record.
+///
+/// The records are opaque to the store; their encoding is defined by the network service.
+pub struct PeerStore<T>
+where
+    T: ClientDB,
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> PeerStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self { db }
+    }
+
+    /// Replaces the stored peer records with `ssz`.
+    pub fn put_serialized_peers(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, PEERS_KEY, ssz)
+    }
+
+    pub fn get_serialized_peers(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, PEERS_KEY)
+    }
+
+    pub fn delete_peers(&self) -> Result<(), DBError> {
+        self.db.delete(DB_COLUMN, PEERS_KEY)
+    }
+
+    /// Replaces the stored bans with `ssz`.
+    pub fn put_serialized_bans(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, BANS_KEY, ssz)
+    }
+
+    pub fn get_serialized_bans(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, BANS_KEY)
+    }
+
+    pub fn put_serialized_local_enr(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, LOCAL_ENR_KEY, ssz)
+    }
+
+    pub fn get_serialized_local_enr(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, LOCAL_ENR_KEY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_put_get_delete_peers() {
+        let db = Arc::new(MemoryDB::open());
+        let store = PeerStore::new(db.clone());
+
+        assert_eq!(store.get_serialized_peers().unwrap(), None);
+
+        store.put_serialized_peers(&[1, 2, 3]).unwrap();
+        store.put_serialized_peers(&[4, 5]).unwrap();
+        assert_eq!(store.get_serialized_peers().unwrap(), Some(vec![4, 5]));
+        assert!(db.exists(DB_COLUMN, PEERS_KEY).unwrap());
+
+        store.delete_peers().unwrap();
+        assert_eq!(store.get_serialized_peers().unwrap(), None);
+    }
+
+    #[test]
+    fn test_put_get_local_enr() {
+        let db = Arc::new(MemoryDB::open());
+        let store = PeerStore::new(db);
+
+        store.put_serialized_peers(&[1]).unwrap();
+        assert_eq!(store.get_serialized_local_enr().unwrap(), None);
+
+        store.put_serialized_local_enr(&[2]).unwrap();
+        assert_eq!(store.get_serialized_local_enr().unwrap(), Some(vec![2]));
+        assert_eq!(store.get_serialized_peers().unwrap(), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_put_get_bans() {
+        let db = Arc::new(MemoryDB::open());
+        let store = PeerStore::new(db);
+
+        assert_eq!(store.get_serialized_bans().unwrap(), None);
+        store.put_serialized_bans(&[3]).unwrap();
+        assert_eq!(store.get_serialized_bans().unwrap(), Some(vec![3]));
+        assert_eq!(store.get_serialized_peers().unwrap(), None);
+    }
+}
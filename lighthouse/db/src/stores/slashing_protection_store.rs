@@ -0,0 +1,101 @@
+use super::super::keys::root_key;
+use super::SLASHING_PROTECTION_DB_COLUMN as DB_COLUMN;
+use super::{ClientDB, DBError};
+use std::sync::Arc;
+
+/// The key under which the genesis root of the protected chain is stored.
+const GENESIS_ROOT_KEY: &[u8] = b"genesis_root";
+/// The key under which the public keys of the protected validators are stored.
+const PUBKEYS_KEY: &[u8] = b"pubkeys";
+/// The prefix of the key under which each validator's signing history is stored.
+const HISTORY_PREFIX: &[u8] = b"history";
+
+/// Stores the messages signed by each validator, so that a validator never signs a message which
+/// conflicts with one it has already signed.
+///
+/// The histories are opaque to the store; their encoding is defined by the validator client.
+pub struct SlashingProtectionStore<T>
+where
+    T: ClientDB,
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> SlashingProtectionStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self { db }
+    }
+
+    fn history_key(pubkey: &[u8]) -> Vec<u8> {
+        root_key(HISTORY_PREFIX, pubkey)
+    }
+
+    /// Replaces the signing history of the validator with `pubkey` with `ssz`.
+    pub fn put_serialized_history(&self, pubkey: &[u8], ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, &Self::history_key(pubkey), ssz)
+    }
+
+    pub fn get_serialized_history(&self, pubkey: &[u8]) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, &Self::history_key(pubkey))
+    }
+
+    /// Replaces the stored public keys with `ssz`.
+    pub fn put_serialized_pubkeys(&self, ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, PUBKEYS_KEY, ssz)
+    }
+
+    pub fn get_serialized_pubkeys(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, PUBKEYS_KEY)
+    }
+
+    pub fn put_genesis_root(&self, root: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, GENESIS_ROOT_KEY, root)
+    }
+
+    pub fn get_genesis_root(&self) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, GENESIS_ROOT_KEY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_put_get_history() {
+        let db = Arc::new(MemoryDB::open());
+        let store = SlashingProtectionStore::new(db.clone());
+
+        assert_eq!(store.get_serialized_history(&[1; 48]).unwrap(), None);
+        store.put_serialized_history(&[1; 48], &[1, 2]).unwrap();
+        store.put_serialized_history(&[2; 48], &[3]).unwrap();
+        assert_eq!(
+            store.get_serialized_history(&[1; 48]).unwrap(),
+            Some(vec![1, 2])
+        );
+        assert_eq!(
+            store.get_serialized_history(&[2; 48]).unwrap(),
+            Some(vec![3])
+        );
+        assert!(db
+            .exists(
+                DB_COLUMN,
+                &SlashingProtectionStore::<MemoryDB>::history_key(&[1; 48])
+            )
+            .unwrap());
+    }
+
+    #[test]
+    fn test_put_get_pubkeys_and_genesis_root() {
+        let db = Arc::new(MemoryDB::open());
+        let store = SlashingProtectionStore::new(db);
+
+        assert_eq!(store.get_serialized_pubkeys().unwrap(), None);
+        assert_eq!(store.get_genesis_root().unwrap(), None);
+        store.put_serialized_pubkeys(&[4]).unwrap();
+        store.put_genesis_root(&[5; 32]).unwrap();
+        assert_eq!(store.get_serialized_pubkeys().unwrap(), Some(vec![4]));
+        assert_eq!(store.get_genesis_root().unwrap(), Some(vec![5; 32]));
+    }
+}
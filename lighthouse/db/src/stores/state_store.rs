@@ -0,0 +1,106 @@
+use super::STATES_DB_COLUMN as DB_COLUMN;
+use super::{ClientDB, DBError};
+use std::sync::Arc;
+
+/// Which snapshots are kept when the chain is finalized.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StatePruning {
+    /// Every snapshot is kept, so that historical states are quick to regenerate.
+    Archive,
+    /// Only the snapshot of the finalized state is kept, and later states are regenerated from
+    /// it and the blocks after it.
+    Minimal,
+}
+
+/// Stores snapshots of the state after some blocks, keyed by block root, from which the states
+/// after later blocks are regenerated.
+///
+/// The snapshots are opaque to the store; their encoding is defined by the beacon node. As any
+/// state may be regenerated from genesis, snapshots may be deleted at any time.
+pub struct StateStore<T>
+where
+    T: ClientDB,
+{
+    db: Arc<T>,
+}
+
+impl<T: ClientDB> StateStore<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self { db }
+    }
+
+    pub fn put_serialized_snapshot(&self, block_root: &[u8], ssz: &[u8]) -> Result<(), DBError> {
+        self.db.put(DB_COLUMN, block_root, ssz)
+    }
+
+    pub fn get_serialized_snapshot(&self, block_root: &[u8]) -> Result<Option<Vec<u8>>, DBError> {
+        self.db.get(DB_COLUMN, block_root)
+    }
+
+    pub fn delete_snapshot(&self, block_root: &[u8]) -> Result<(), DBError> {
+        self.db.delete(DB_COLUMN, block_root)
+    }
+
+    /// Deletes the snapshots which `pruning` does not keep once the block with `finalized_root`
+    /// is finalized, returning the number deleted.
+    pub fn prune(&self, pruning: StatePruning, finalized_root: &[u8]) -> Result<usize, DBError> {
+        if pruning == StatePruning::Archive {
+            return Ok(0);
+        }
+        let stale: Vec<Vec<u8>> = self
+            .db
+            .iter(DB_COLUMN)?
+            .map(|(root, _)| root)
+            .filter(|root| root.as_slice() != finalized_root)
+            .collect();
+        for root in &stale {
+            self.delete_snapshot(root)?;
+        }
+        Ok(stale.len())
+    }
+
+    /// Reclaims the space of the snapshots deleted.
+    pub fn compact(&self) -> Result<(), DBError> {
+        self.db.compact(DB_COLUMN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MemoryDB;
+    use super::*;
+
+    #[test]
+    fn test_put_get_delete_snapshot() {
+        let db = Arc::new(MemoryDB::open());
+        let store = StateStore::new(db.clone());
+        let (a, b) = ([1; 32], [2; 32]);
+
+        assert_eq!(store.get_serialized_snapshot(&a).unwrap(), None);
+        store.put_serialized_snapshot(&a, &[1, 2]).unwrap();
+        store.put_serialized_snapshot(&b, &[3]).unwrap();
+        assert_eq!(store.get_serialized_snapshot(&a).unwrap(), Some(vec![1, 2]));
+        assert!(db.exists(DB_COLUMN, &b).unwrap());
+
+        store.delete_snapshot(&a).unwrap();
+        assert_eq!(store.get_serialized_snapshot(&a).unwrap(), None);
+        assert_eq!(store.get_serialized_snapshot(&b).unwrap(), Some(vec![3]));
+    }
+
+    #[test]
+    fn test_prune() {
+        let store = StateStore::new(Arc::new(MemoryDB::open()));
+        let (a, b, c) = ([1; 32], [2; 32], [3; 32]);
+        for root in &[a, b, c] {
+            store.put_serialized_snapshot(root, &[0]).unwrap();
+        }
+
+        assert_eq!(store.prune(StatePruning::Archive, &b).unwrap(), 0);
+        assert!(store.get_serialized_snapshot(&a).unwrap().is_some());
+
+        assert_eq!(store.prune(StatePruning::Minimal, &b).unwrap(), 2);
+        assert_eq!(store.get_serialized_snapshot(&a).unwrap(), None);
+        assert_eq!(store.get_serialized_snapshot(&b).unwrap(), Some(vec![0]));
+        assert_eq!(store.get_serialized_snapshot(&c).unwrap(), None);
+    }
+}
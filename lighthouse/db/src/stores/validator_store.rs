@@ -1,6 +1,6 @@
 extern crate bytes;
 
-use self::bytes::{BufMut, BytesMut};
+use super::super::keys::{int_key, root_key, INT_KEY_BYTES};
 use super::bls::PublicKey;
 use super::VALIDATOR_DB_COLUMN as DB_COLUMN;
 use super::{ClientDB, DBError};
@@ -21,6 +21,7 @@ impl From<DBError> for ValidatorStoreError {
 #[derive(Debug, PartialEq)]
 enum KeyPrefixes {
     PublicKey,
+    Index,
 }
 
 pub struct ValidatorStore<T>
@@ -38,14 +39,12 @@ impl<T: ClientDB> ValidatorStore<T> {
     fn prefix_bytes(&self, key_prefix: &KeyPrefixes) -> Vec<u8> {
         match key_prefix {
             KeyPrefixes::PublicKey => b"pubkey".to_vec(),
+            KeyPrefixes::Index => b"index".to_vec(),
         }
     }
 
     fn get_db_key_for_index(&self, key_prefix: &KeyPrefixes, index: usize) -> Vec<u8> {
-        let mut buf = BytesMut::with_capacity(6 + 8);
-        buf.put(self.prefix_bytes(key_prefix));
-        buf.put_u64_be(index as u64);
-        buf.take().to_vec()
+        int_key(&self.prefix_bytes(key_prefix), index as u64)
     }
 
     pub fn put_public_key_by_index(
@@ -74,12 +73,48 @@ impl<T: ClientDB> ValidatorStore<T> {
             },
         }
     }
+
+    fn get_db_key_for_public_key(&self, public_key: &PublicKey) -> Vec<u8> {
+        root_key(
+            &self.prefix_bytes(&KeyPrefixes::Index),
+            &public_key.as_bytes(),
+        )
+    }
+
+    /// Records `index` as the index in the registry of the validator with `public_key`.
+    pub fn put_index_by_public_key(
+        &self,
+        public_key: &PublicKey,
+        index: usize,
+    ) -> Result<(), ValidatorStoreError> {
+        let key = self.get_db_key_for_public_key(public_key);
+        self.db
+            .put(DB_COLUMN, &key[..], &(index as u64).to_be_bytes())
+            .map_err(ValidatorStoreError::from)
+    }
+
+    pub fn get_index_by_public_key(
+        &self,
+        public_key: &PublicKey,
+    ) -> Result<Option<usize>, ValidatorStoreError> {
+        let key = self.get_db_key_for_public_key(public_key);
+        match self.db.get(DB_COLUMN, &key[..])? {
+            None => Ok(None),
+            Some(ref val) if val.len() == INT_KEY_BYTES => {
+                let mut bytes = [0; INT_KEY_BYTES];
+                bytes.copy_from_slice(val);
+                Ok(Some(u64::from_be_bytes(bytes) as usize))
+            }
+            Some(_) => Err(ValidatorStoreError::DecodeError),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::super::MemoryDB;
     use super::super::bls::Keypair;
+    use super::bytes::{BufMut, BytesMut};
     use super::*;
 
     #[test]
@@ -167,6 +202,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_index_by_public_key() {
+        let db = Arc::new(MemoryDB::open());
+        let store = ValidatorStore::new(db.clone());
+
+        let public_key = Keypair::random().pk;
+        assert_eq!(store.get_index_by_public_key(&public_key), Ok(None));
+        store.put_index_by_public_key(&public_key, 300).unwrap();
+        assert_eq!(store.get_index_by_public_key(&public_key), Ok(Some(300)));
+        assert_eq!(
+            store.get_index_by_public_key(&Keypair::random().pk),
+            Ok(None)
+        );
+
+        let key = store.get_db_key_for_public_key(&public_key);
+        db.put(DB_COLUMN, &key[..], "cats".as_bytes()).unwrap();
+        assert_eq!(
+            store.get_index_by_public_key(&public_key),
+            Err(ValidatorStoreError::DecodeError)
+        );
+    }
+
     #[test]
     fn test_validator_store_put_get() {
         let db = Arc::new(MemoryDB::open());
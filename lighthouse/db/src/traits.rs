@@ -11,6 +11,13 @@ impl DBError {
     }
 }
 
+/// A write within a batch given to `ClientDB::write_batch`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BatchOp<'a> {
+    Put(&'a str, DBValue, DBValue),
+    Delete(&'a str, DBValue),
+}
+
 /// A generic database to be used by the "client' (i.e.,
 /// the lighthouse blockchain client).
 ///
@@ -25,4 +32,19 @@ pub trait ClientDB: Sync + Send {
     fn exists(&self, col: &str, key: &[u8]) -> Result<bool, DBError>;
 
     fn delete(&self, col: &str, key: &[u8]) -> Result<(), DBError>;
+
+    /// Applies the writes of `ops` in order, either all of them or, on error, none.
+    fn write_batch(&self, ops: &[BatchOp]) -> Result<(), DBError>;
+
+    /// Returns the key-value pairs of a column, in ascending order of key.
+    fn iter<'a>(
+        &'a self,
+        col: &str,
+    ) -> Result<Box<dyn Iterator<Item = (DBValue, DBValue)> + 'a>, DBError>;
+
+    /// Reclaims the space of the values deleted from a column. A no-op for databases which free
+    /// it as values are deleted.
+    fn compact(&self, _col: &str) -> Result<(), DBError> {
+        Ok(())
+    }
 }
@@ -0,0 +1,354 @@
+mod ntp;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use self::ntp::{clock_offset_millis, DEFAULT_NTP_SERVER};
+use beacon_node::{DiskSpace, PersistedHead};
+use clap::ArgMatches;
+use config::{LighthouseConfig, DB_DIR};
+use db::stores::{BeaconBlockStore, ChainStore, COLUMNS};
+use db::{available_space, check_schema, ColumnCodecs, DiskDB, SchemaError, SCHEMA_VERSION};
+use eth1::Eth1Client;
+use slog::Logger;
+use ssz::Decodable;
+use types::BeaconBlock;
+
+/// The time allowed for the NTP server to respond.
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Logs the findings of the checks, counting the problems.
+struct Findings<'a> {
+    log: &'a Logger,
+    warnings: usize,
+    failures: usize,
+}
+
+impl<'a> Findings<'a> {
+    fn ok(&self, check: &str, message: &str) {
+        info!(self.log, "{}", message; "check" => check);
+    }
+
+    fn warn(&mut self, check: &str, message: &str, help: &str) {
+        self.warnings += 1;
+        warn!(self.log, "{}", message; "help" => help, "check" => check);
+    }
+
+    fn fail(&mut self, check: &str, message: &str, help: &str) {
+        self.failures += 1;
+        error!(self.log, "{}", message; "help" => help, "check" => check);
+    }
+}
+
+/// Checks the data dir and the environment of a beacon node which is not running, logging what
+/// is wrong and how to put it right.
+pub fn run(matches: &ArgMatches, config: &LighthouseConfig, log: &Logger) {
+    let mut findings = Findings {
+        log,
+        warnings: 0,
+        failures: 0,
+    };
+    let db_path = config.beacon_dir().join(DB_DIR);
+    if let Some(db) = open_db(&db_path, &mut findings) {
+        check_schema_version(&db, &mut findings);
+        check_chain(&db, &config.beacon_dir(), &mut findings);
+    }
+    check_disk_space(&db_path, config, &mut findings);
+    check_clock(
+        matches.value_of("ntp-server").unwrap_or(DEFAULT_NTP_SERVER),
+        config.clock_disparity,
+        &mut findings,
+    );
+    check_eth1(config, &mut findings);
+
+    if findings.failures + findings.warnings == 0 {
+        info!(log, "No problems found");
+    } else {
+        warn!(log, "Problems found"; "warnings" => findings.warnings, "failures" => findings.failures);
+    }
+}
+
+fn open_db(db_path: &Path, findings: &mut Findings) -> Option<Arc<DiskDB>> {
+    if !db_path.exists() {
+        findings.warn(
+            "database",
+            "No database in the beacon dir",
+            "start the beacon node to create one, or check --datadir and --network",
+        );
+        return None;
+    }
+    /*
+     * RocksDB locks the database while it is open, so this fails while the node is running.
+     */
+    match DiskDB::try_open(db_path, Some(&COLUMNS)) {
+        Ok(db) => {
+            findings.ok("database", "Database opened");
+            Some(Arc::new(db.with_codecs(ColumnCodecs::standard())))
+        }
+        Err(e) => {
+            findings.fail(
+                "database",
+                &format!("Unable to open database: {}", e.message),
+                "stop the beacon node first; if it is not running, the database may be corrupt",
+            );
+            None
+        }
+    }
+}
+
+fn check_schema_version(db: &DiskDB, findings: &mut Findings) {
+    match check_schema(db) {
+        Ok(()) => findings.ok(
+            "schema",
+            &format!("Schema version is current ({})", SCHEMA_VERSION),
+        ),
+        Err(SchemaError::Older(version)) => findings.fail(
+            "schema",
+            &format!(
+                "Schema version {} is older than {}",
+                version, SCHEMA_VERSION
+            ),
+            "run lighthouse db migrate",
+        ),
+        Err(SchemaError::Newer(version)) => findings.fail(
+            "schema",
+            &format!(
+                "Schema version {} is newer than {}",
+                version, SCHEMA_VERSION
+            ),
+            "upgrade Lighthouse to the version which wrote the database",
+        ),
+        Err(e) => findings.fail(
+            "schema",
+            &format!("Unable to read schema version: {:?}", e),
+            "the database may be corrupt, remove it and resync",
+        ),
+    }
+}
+
+/// Checks that the persisted head descends from the finalized block, and that the tips of the
+/// other chains are stored.
+fn check_chain(db: &Arc<DiskDB>, beacon_dir: &Path, findings: &mut Findings) {
+    let resync = format!(
+        "the database is corrupt, remove {} and resync",
+        beacon_dir.display()
+    );
+    let head = match ChainStore::new(db.clone()).get_serialized_head() {
+        Ok(Some(ssz)) => match PersistedHead::ssz_decode(&ssz, 0) {
+            Ok((head, _)) => head,
+            Err(_) => return findings.fail("chain", "Invalid persisted chain", &resync),
+        },
+        Ok(None) => {
+            return findings.warn(
+                "chain",
+                "No persisted chain",
+                "shut the beacon node down gracefully, so that its chain is persisted",
+            )
+        }
+        Err(e) => {
+            return findings.fail(
+                "chain",
+                &format!("Unable to read persisted chain: {}", e.message),
+                &resync,
+            )
+        }
+    };
+
+    /*
+     * The finalized block is not persisted, so the genesis block, from which the finalized block
+     * descends, stands in for it.
+     */
+    let blocks = BeaconBlockStore::new(db.clone());
+    let finalized_root = BeaconBlock::zero().canonical_root();
+    let mut root = head.head_root;
+    let mut child_slot = None;
+    let mut ancestors = 0;
+    while root != finalized_root {
+        let block = match blocks.get_serialized_block(&root) {
+            Ok(Some(ssz)) => match BeaconBlock::ssz_decode(&ssz, 0) {
+                Ok((block, _)) => block,
+                Err(_) => {
+                    return findings.fail("chain", &format!("Invalid block {:?}", root), &resync)
+                }
+            },
+            Ok(None) => {
+                return findings.fail(
+                    "chain",
+                    &format!(
+                        "Missing block {:?}, {} blocks below the head",
+                        root, ancestors
+                    ),
+                    &resync,
+                )
+            }
+            Err(e) => {
+                return findings.fail(
+                    "chain",
+                    &format!("Unable to read block {:?}: {}", root, e.message),
+                    &resync,
+                )
+            }
+        };
+        match child_slot {
+            None if block.slot != head.head_slot => {
+                return findings.fail(
+                    "chain",
+                    &format!(
+                        "Head is at slot {}, not the persisted slot {}",
+                        block.slot, head.head_slot
+                    ),
+                    &resync,
+                )
+            }
+            Some(child_slot) if block.slot >= child_slot => {
+                return findings.fail(
+                    "chain",
+                    &format!("Block {:?} is not below its child", root),
+                    &resync,
+                )
+            }
+            _ => {}
+        }
+        root = match block.parent_hash() {
+            Some(parent) => *parent,
+            None => return findings.fail("chain", &format!("Invalid block {:?}", root), &resync),
+        };
+        child_slot = Some(block.slot);
+        ancestors += 1;
+    }
+    findings.ok(
+        "chain",
+        &format!(
+            "Head at slot {} descends from the finalized block through {} blocks",
+            head.head_slot, ancestors
+        ),
+    );
+
+    let missing = head
+        .heads
+        .iter()
+        .filter(|tip| {
+            blocks
+                .block_exists(tip)
+                .map(|exists| !exists)
+                .unwrap_or(true)
+        })
+        .count();
+    if missing > 0 {
+        findings.warn(
+            "chain",
+            &format!("{} of {} chain tips are missing", missing, head.heads.len()),
+            "run lighthouse db prune-states to drop the chains of the missing tips",
+        );
+    }
+}
+
+fn check_disk_space(db_path: &Path, config: &LighthouseConfig, findings: &mut Findings) {
+    let path = if db_path.exists() {
+        db_path
+    } else {
+        config.data_dir.as_path()
+    };
+    let available = match available_space(path) {
+        Ok(available) => available,
+        Err(e) => {
+            return findings.warn(
+                "disk",
+                &format!("Unable to read free space: {}", e),
+                "check that the data dir exists and is readable",
+            )
+        }
+    };
+    let message = format!("{} MB free", available >> 20);
+    match config.disk_guard.classify(available) {
+        DiskSpace::Sufficient => findings.ok("disk", &message),
+        DiskSpace::Low => findings.warn(
+            "disk",
+            &message,
+            "free space, or run lighthouse db prune-states and lighthouse db compact; state snapshots will be pruned",
+        ),
+        DiskSpace::Critical => findings.fail(
+            "disk",
+            &message,
+            "free space; the beacon node will not import blocks below --disk-halt-threshold-mb",
+        ),
+    }
+}
+
+fn check_clock(server: &str, disparity: Duration, findings: &mut Findings) {
+    let offset = match clock_offset_millis(server, NTP_TIMEOUT) {
+        Ok(offset) => offset,
+        Err(e) => {
+            return findings.warn(
+                "clock",
+                &format!("Unable to query NTP server {}: {}", server, e),
+                "allow outgoing UDP on port 123, or give a reachable --ntp-server",
+            )
+        }
+    };
+    let message = format!("Clock is {} ms off NTP server {}", offset, server);
+    if offset.unsigned_abs() > disparity.as_millis() as u64 {
+        findings.fail(
+            "clock",
+            &message,
+            "synchronize the system clock, e.g. by enabling systemd-timesyncd or chrony; blocks and attestations are otherwise rejected",
+        );
+    } else {
+        findings.ok("clock", &message);
+    }
+}
+
+fn check_eth1(config: &LighthouseConfig, findings: &mut Findings) {
+    if !config.eth1.enabled {
+        return findings.ok("eth1", "Eth1 is disabled, endpoints not checked");
+    }
+    for url in &config.eth1.endpoints {
+        let client = match Eth1Client::new(url, config.eth1.timeout) {
+            Ok(client) => client,
+            Err(e) => {
+                findings.fail(
+                    "eth1",
+                    &format!("Invalid endpoint {}: {:?}", url, e),
+                    "fix --eth1-endpoints",
+                );
+                continue;
+            }
+        };
+        match client.chain_id() {
+            Ok(chain_id) if chain_id == config.eth1.chain_id => {}
+            Ok(chain_id) => {
+                findings.fail(
+                    "eth1",
+                    &format!(
+                        "Endpoint {} is on chain {}, not {}",
+                        url, chain_id, config.eth1.chain_id
+                    ),
+                    "point the endpoint at the eth1 chain of the network",
+                );
+                continue;
+            }
+            Err(e) => {
+                findings.fail(
+                    "eth1",
+                    &format!("Endpoint {} is unreachable: {:?}", url, e),
+                    "check that the eth1 node is running and serves HTTP JSON-RPC",
+                );
+                continue;
+            }
+        }
+        match client.syncing() {
+            Ok(None) => findings.ok("eth1", &format!("Endpoint {} is synced", url)),
+            Ok(Some(distance)) => findings.warn(
+                "eth1",
+                &format!("Endpoint {} is {} blocks behind", url, distance),
+                "wait for the eth1 node to sync",
+            ),
+            Err(e) => findings.warn(
+                "eth1",
+                &format!("Unable to query sync status of {}: {:?}", url, e),
+                "check the eth1 node serves the eth API",
+            ),
+        }
+    }
+}
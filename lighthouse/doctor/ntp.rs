@@ -0,0 +1,62 @@
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The server queried when none is given.
+pub const DEFAULT_NTP_SERVER: &str = "pool.ntp.org:123";
+/// The seconds from the NTP epoch, 1900, to the Unix epoch.
+const NTP_UNIX_OFFSET_SECS: u64 = 2_208_988_800;
+const PACKET_LEN: usize = 48;
+
+/// Queries `server` once over SNTP, returning the offset of the server's clock from the local
+/// clock in milliseconds, positive if the local clock is behind.
+pub fn clock_offset_millis(server: &str, timeout: Duration) -> io::Result<i64> {
+    let addr = server
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Server has no address"))?;
+    let socket = UdpSocket::bind(if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })?;
+    socket.set_read_timeout(Some(timeout))?;
+
+    /*
+     * LI 0, version 3, mode 3 (client); the rest of the request may be zero.
+     */
+    let mut packet = [0; PACKET_LEN];
+    packet[0] = 0x1b;
+    let sent = unix_millis(SystemTime::now());
+    socket.send_to(&packet, addr)?;
+    let (len, _) = socket.recv_from(&mut packet)?;
+    let received = unix_millis(SystemTime::now());
+    if len < PACKET_LEN || packet[0] & 0x07 != 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid server response",
+        ));
+    }
+
+    let server_received = ntp_millis(&packet[32..40]);
+    let server_sent = ntp_millis(&packet[40..48]);
+    Ok(((server_received - sent) + (server_sent - received)) / 2)
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() * 1000 + u64::from(d.subsec_millis())) as i64,
+        Err(e) => -((e.duration().as_secs() * 1000) as i64),
+    }
+}
+
+/// Converts a 64-bit NTP timestamp, of seconds and a fraction of a second, to Unix milliseconds.
+fn ntp_millis(bytes: &[u8]) -> i64 {
+    let be_u32 = |b: &[u8]| {
+        b.iter()
+            .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte))
+    };
+    let secs = be_u32(&bytes[..4]);
+    let millis = (be_u32(&bytes[4..]) * 1000) >> 32;
+    (secs as i64 - NTP_UNIX_OFFSET_SECS as i64) * 1000 + millis as i64
+}
@@ -0,0 +1,23 @@
+use super::http::Eth1Block;
+use super::service::Eth1Service;
+
+/// The source of the eth1 block voted for by produced blocks.
+pub trait Eth1Backend: Send + Sync {
+    /// Returns the block to vote for, if any.
+    fn voting_block(&self) -> Option<Eth1Block>;
+}
+
+impl Eth1Backend for Eth1Service {
+    fn voting_block(&self) -> Option<Eth1Block> {
+        Eth1Service::voting_block(self)
+    }
+}
+
+/// Votes for no eth1 block, for nodes which do not follow the eth1 chain.
+pub struct NoEth1Backend;
+
+impl Eth1Backend for NoEth1Backend {
+    fn voting_block(&self) -> Option<Eth1Block> {
+        None
+    }
+}
@@ -0,0 +1,127 @@
+use super::http::Eth1Block;
+use std::collections::VecDeque;
+
+#[derive(Debug, PartialEq)]
+pub enum BlockCacheError {
+    /// The block does not follow the highest block in the cache.
+    NonConsecutive { expected: u64, got: u64 },
+    /// The block is not a child of the highest block in the cache, so the eth1 chain has
+    /// reorganised.
+    ParentMismatch,
+}
+
+/// A chain of recent eth1 blocks, ending at the block voted for.
+#[derive(Debug)]
+pub struct BlockCache {
+    blocks: VecDeque<Eth1Block>,
+    max_len: usize,
+}
+
+impl BlockCache {
+    /// Creates a cache keeping at most `max_len` blocks, which must not be zero.
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            blocks: VecDeque::new(),
+            max_len: max_len.max(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn highest(&self) -> Option<&Eth1Block> {
+        self.blocks.back()
+    }
+
+    pub fn lowest(&self) -> Option<&Eth1Block> {
+        self.blocks.front()
+    }
+
+    pub fn block_by_number(&self, number: u64) -> Option<&Eth1Block> {
+        let lowest = self.lowest()?.number;
+        self.blocks.get(number.checked_sub(lowest)? as usize)
+    }
+
+    /// Appends `block`, which must be the child of the highest block, dropping the lowest blocks
+    /// beyond the capacity.
+    pub fn insert(&mut self, block: Eth1Block) -> Result<(), BlockCacheError> {
+        if let Some(highest) = self.highest() {
+            if block.number != highest.number + 1 {
+                return Err(BlockCacheError::NonConsecutive {
+                    expected: highest.number + 1,
+                    got: block.number,
+                });
+            }
+            if block.parent_hash != highest.hash {
+                return Err(BlockCacheError::ParentMismatch);
+            }
+        }
+        self.blocks.push_back(block);
+        while self.blocks.len() > self.max_len {
+            self.blocks.pop_front();
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Hash256;
+
+    fn block(number: u64, parent_hash: Hash256) -> Eth1Block {
+        Eth1Block {
+            number,
+            hash: Hash256::from(number + 1),
+            parent_hash,
+            timestamp: number * 14,
+        }
+    }
+
+    #[test]
+    fn test_block_cache() {
+        let mut cache = BlockCache::new(3);
+        assert!(cache.is_empty());
+        assert_eq!(cache.highest(), None);
+
+        cache.insert(block(10, Hash256::zero())).unwrap();
+        for number in 11..15 {
+            let parent_hash = cache.highest().unwrap().hash;
+            cache.insert(block(number, parent_hash)).unwrap();
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.lowest().unwrap().number, 12);
+        assert_eq!(cache.highest().unwrap().number, 14);
+        assert_eq!(cache.block_by_number(13).unwrap().number, 13);
+        assert_eq!(cache.block_by_number(11), None);
+        assert_eq!(cache.block_by_number(15), None);
+
+        let parent_hash = cache.highest().unwrap().hash;
+        assert_eq!(
+            cache.insert(block(16, parent_hash)),
+            Err(BlockCacheError::NonConsecutive {
+                expected: 15,
+                got: 16
+            })
+        );
+        assert_eq!(
+            cache.insert(block(15, Hash256::from(99))),
+            Err(BlockCacheError::ParentMismatch)
+        );
+        assert_eq!(cache.highest().unwrap().number, 14);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        cache.insert(block(20, Hash256::zero())).unwrap();
+        assert_eq!(cache.highest().unwrap().number, 20);
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+/// The endpoint used when none is given: a local eth1 node.
+pub const DEFAULT_ETH1_ENDPOINT: &str = "http://localhost:8545";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eth1Config {
+    pub enabled: bool,
+    /// The JSON-RPC endpoints of eth1 nodes, in order of preference.
+    pub endpoints: Vec<String>,
+    /// The `eth_chainId` of the chain of the deposit contract.
+    pub chain_id: u64,
+    /// The `net_version` of the chain of the deposit contract.
+    pub network_id: u64,
+    /// The number of blocks behind the eth1 head at which blocks are voted for, so that votes are
+    /// not undone by eth1 reorgs.
+    pub follow_distance: u64,
+    /// The number of blocks kept in the cache, ending at the block voted for.
+    pub cache_size: u64,
+    pub update_interval: Duration,
+    /// The time allowed for each request.
+    pub timeout: Duration,
+}
+
+impl Default for Eth1Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoints: vec![DEFAULT_ETH1_ENDPOINT.to_string()],
+            chain_id: 1,
+            network_id: 1,
+            follow_distance: 1_024,
+            cache_size: 64,
+            update_interval: Duration::from_secs(7),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
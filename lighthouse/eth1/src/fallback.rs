@@ -0,0 +1,214 @@
+use super::http::{Eth1Client, Eth1Error};
+use super::metrics;
+use lighthouse_metrics::set_gauge;
+use slog::Logger;
+use std::sync::RwLock;
+
+/// The health of an eth1 endpoint when it was last checked, ordered from best to worst.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Health {
+    Synced,
+    /// Syncing, with the number of blocks by which the endpoint's head is behind.
+    Syncing(u64),
+    /// Not yet checked, or the endpoint did not respond.
+    Offline,
+    /// The endpoint follows a chain other than that of the deposit contract, so is never used.
+    WrongChain,
+}
+
+struct Candidate {
+    client: Eth1Client,
+    health: RwLock<Health>,
+}
+
+/// A set of eth1 endpoints, of which the healthiest available is used for each request.
+///
+/// Requests are sent to the endpoints in order of their health, falling back to the next when a
+/// request fails. Endpoints of equal health are used in the order they were given.
+pub struct Eth1Fallback {
+    candidates: Vec<Candidate>,
+    chain_id: u64,
+    network_id: u64,
+    log: Logger,
+}
+
+impl Eth1Fallback {
+    /// Creates a fallback of `clients`, which must follow the chain with `chain_id` and
+    /// `network_id`.
+    ///
+    /// The endpoints are considered offline until their health is checked.
+    pub fn new(clients: Vec<Eth1Client>, chain_id: u64, network_id: u64, log: Logger) -> Self {
+        Self {
+            candidates: clients
+                .into_iter()
+                .map(|client| Candidate {
+                    client,
+                    health: RwLock::new(Health::Offline),
+                })
+                .collect(),
+            chain_id,
+            network_id,
+            log,
+        }
+    }
+
+    /// Returns the URL and health of each endpoint, in the order they were given.
+    pub fn health(&self) -> Vec<(String, Health)> {
+        self.candidates
+            .iter()
+            .map(|c| (c.client.url().to_string(), read(&c.health)))
+            .collect()
+    }
+
+    /// Returns the number of endpoints which are synced or syncing.
+    pub fn num_available(&self) -> usize {
+        self.candidates
+            .iter()
+            .filter(|c| read(&c.health) < Health::Offline)
+            .count()
+    }
+
+    /// Checks the chain and sync status of every endpoint.
+    pub fn update_health(&self) {
+        for candidate in &self.candidates {
+            let health = self.check_health(&candidate.client);
+            let previous = {
+                let mut current = candidate.health.write().expect("Health lock poisoned");
+                ::std::mem::replace(&mut *current, health)
+            };
+            if health == Health::WrongChain && previous != Health::WrongChain {
+                error!(self.log, "Eth1 endpoint is on another chain";
+                       "url" => candidate.client.url(),
+                       "expected_chain_id" => self.chain_id,
+                       "expected_network_id" => self.network_id);
+            } else if (health < Health::Offline) != (previous < Health::Offline) {
+                info!(self.log, "Eth1 endpoint health changed";
+                      "url" => candidate.client.url(),
+                      "health" => format!("{:?}", health));
+            }
+        }
+        set_gauge(
+            &metrics::ETH1_ENDPOINTS_AVAILABLE,
+            self.num_available() as i64,
+        );
+    }
+
+    /// Returns the result of `request` from the first endpoint to answer it, trying the endpoints
+    /// in order of health. An endpoint which cannot be reached is marked offline until its next
+    /// check.
+    ///
+    /// Returns the error of the last endpoint tried if none succeeds.
+    pub fn first_success<T, F>(&self, request: F) -> Result<T, Eth1Error>
+    where
+        F: Fn(&Eth1Client) -> Result<T, Eth1Error>,
+    {
+        let mut candidates: Vec<(Health, &Candidate)> = self
+            .candidates
+            .iter()
+            .map(|c| (read(&c.health), c))
+            .filter(|(health, _)| *health != Health::WrongChain)
+            .collect();
+        candidates.sort_by_key(|(health, _)| *health);
+
+        let mut last_error = Eth1Error::Request("No eth1 endpoints available".to_string());
+        for (_, candidate) in candidates {
+            match request(&candidate.client) {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    debug!(self.log, "Eth1 request failed";
+                           "url" => candidate.client.url(),
+                           "error" => format!("{:?}", e));
+                    match e {
+                        Eth1Error::Request(_) | Eth1Error::Timeout | Eth1Error::Status(_) => {
+                            *candidate.health.write().expect("Health lock poisoned") =
+                                Health::Offline;
+                        }
+                        _ => {}
+                    }
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    fn check_health(&self, client: &Eth1Client) -> Health {
+        match (client.chain_id(), client.network_id()) {
+            (Ok(chain_id), Ok(network_id))
+                if chain_id == self.chain_id && network_id == self.network_id => {}
+            (Ok(_), Ok(_)) => return Health::WrongChain,
+            _ => return Health::Offline,
+        }
+        match client.syncing() {
+            Ok(Some(distance)) => Health::Syncing(distance),
+            Ok(None) => Health::Synced,
+            Err(_) => Health::Offline,
+        }
+    }
+}
+
+fn read(health: &RwLock<Health>) -> Health {
+    *health.read().expect("Health lock poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::http::tests::MockEth1;
+    use super::*;
+    use slog::Discard;
+    use std::time::Duration;
+
+    #[test]
+    fn test_fallback() {
+        let eth1 = MockEth1::start(5, 100);
+        let offline = Eth1Client::new("http://127.0.0.1:1", Duration::from_secs(1)).unwrap();
+        let log = Logger::root(Discard, o!());
+        let fallback = Eth1Fallback::new(vec![offline, eth1.client()], 5, 5, log);
+        assert_eq!(fallback.num_available(), 0);
+
+        /*
+         * Unchecked endpoints are tried in order, until one answers.
+         */
+        assert_eq!(fallback.first_success(|c| c.block_number()), Ok(100));
+
+        eth1.chain.write().unwrap().syncing = Some(3);
+        fallback.update_health();
+        let health: Vec<Health> = fallback.health().into_iter().map(|(_, h)| h).collect();
+        assert_eq!(health, vec![Health::Offline, Health::Syncing(3)]);
+        assert_eq!(fallback.num_available(), 1);
+
+        /*
+         * An endpoint which fails is marked offline, and the error of the last endpoint is
+         * returned.
+         */
+        drop(eth1);
+        match fallback.first_success(|c| c.block_number()) {
+            Err(Eth1Error::Request(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert_eq!(fallback.num_available(), 0);
+    }
+
+    #[test]
+    fn test_wrong_chain() {
+        let eth1 = MockEth1::start(5, 100);
+        let log = Logger::root(Discard, o!());
+        let fallback = Eth1Fallback::new(vec![eth1.client()], 5, 1, log.clone());
+        fallback.update_health();
+        assert_eq!(fallback.health()[0].1, Health::WrongChain);
+        assert_eq!(fallback.num_available(), 0);
+        match fallback.first_success(|c| c.block_number()) {
+            Err(Eth1Error::Request(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let fallback = Eth1Fallback::new(vec![eth1.client()], 1, 5, log);
+        fallback.update_health();
+        assert_eq!(fallback.health()[0].1, Health::WrongChain);
+
+        eth1.chain.write().unwrap().network_id = 1;
+        let fallback = Eth1Fallback::new(vec![eth1.client()], 5, 1, Logger::root(Discard, o!()));
+        fallback.update_health();
+        assert_eq!(fallback.health()[0].1, Health::Synced);
+    }
+}
@@ -0,0 +1,398 @@
+use futures::sync::oneshot;
+use futures::{Future, Stream};
+use hex;
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request, Uri};
+use serde_json::{self, Value};
+use std::time::Duration;
+use tokio::runtime::{Builder, Runtime};
+use tokio::timer::Timeout;
+use types::Hash256;
+
+#[derive(Debug, PartialEq)]
+pub enum Eth1Error {
+    InvalidUrl(String),
+    /// The request could not be sent, or the connection failed.
+    Request(String),
+    /// No response arrived within the request timeout.
+    Timeout,
+    /// The endpoint responded with an error status.
+    Status(u16),
+    /// The endpoint returned a JSON-RPC error, with its code and message.
+    Rpc(i64, String),
+    /// The response could not be understood.
+    InvalidResponse(String),
+}
+
+/// The fields of an eth1 block needed to vote for it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Eth1Block {
+    pub number: u64,
+    pub hash: Hash256,
+    pub parent_hash: Hash256,
+    pub timestamp: u64,
+}
+
+/// A blocking client of the JSON-RPC API of an eth1 node.
+///
+/// Requests run on the client's own runtime, so the client may be shared between threads.
+pub struct Eth1Client {
+    url: String,
+    uri: Uri,
+    client: Client<HttpConnector>,
+    timeout: Duration,
+    runtime: Runtime,
+}
+
+impl Eth1Client {
+    /// Creates a client of the endpoint at `url`, e.g. `http://localhost:8545`.
+    pub fn new(url: &str, timeout: Duration) -> Result<Self, Eth1Error> {
+        let uri = url
+            .parse::<Uri>()
+            .map_err(|_| Eth1Error::InvalidUrl(url.to_string()))?;
+        let runtime = Builder::new()
+            .name_prefix("eth1-client-")
+            .core_threads(1)
+            .build()
+            .map_err(|e| Eth1Error::Request(format!("{}", e)))?;
+        Ok(Self {
+            url: url.to_string(),
+            uri,
+            client: Client::new(),
+            timeout,
+            runtime,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// `eth_chainId`
+    pub fn chain_id(&self) -> Result<u64, Eth1Error> {
+        parse_quantity(&self.call("eth_chainId", json!([]))?)
+    }
+
+    /// `net_version`, which unlike other quantities is decimal.
+    pub fn network_id(&self) -> Result<u64, Eth1Error> {
+        let result = self.call("net_version", json!([]))?;
+        result
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid(&result))
+    }
+
+    /// `eth_syncing`, returning the number of blocks by which the node is behind, or `None` if it
+    /// is synced.
+    pub fn syncing(&self) -> Result<Option<u64>, Eth1Error> {
+        let result = self.call("eth_syncing", json!([]))?;
+        if result == Value::Bool(false) {
+            return Ok(None);
+        }
+        let current = parse_quantity(&result["currentBlock"])?;
+        let highest = parse_quantity(&result["highestBlock"])?;
+        Ok(Some(highest.saturating_sub(current)))
+    }
+
+    /// `eth_blockNumber`, the number of the head block.
+    pub fn block_number(&self) -> Result<u64, Eth1Error> {
+        parse_quantity(&self.call("eth_blockNumber", json!([]))?)
+    }
+
+    /// `eth_getBlockByNumber`, returning `None` if the node has no block with `number`.
+    pub fn block_by_number(&self, number: u64) -> Result<Option<Eth1Block>, Eth1Error> {
+        let result = self.call(
+            "eth_getBlockByNumber",
+            json!([format!("0x{:x}", number), false]),
+        )?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        let block = Eth1Block {
+            number: parse_quantity(&result["number"])?,
+            hash: parse_hash(&result["hash"])?,
+            parent_hash: parse_hash(&result["parentHash"])?,
+            timestamp: parse_quantity(&result["timestamp"])?,
+        };
+        if block.number != number {
+            return Err(Eth1Error::InvalidResponse(format!(
+                "Requested block {}, got {}",
+                number, block.number
+            )));
+        }
+        Ok(Some(block))
+    }
+
+    /// Returns the `result` of calling `method` with `params`.
+    fn call(&self, method: &str, params: Value) -> Result<Value, Eth1Error> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1,
+        });
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(self.uri.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .map_err(|e| Eth1Error::Request(format!("{}", e)))?;
+
+        let future = self.client.request(req).and_then(|response| {
+            let status = response.status();
+            response
+                .into_body()
+                .concat2()
+                .map(move |body| (status, body.to_vec()))
+        });
+        let future = Timeout::new(future, self.timeout).map_err(|e| {
+            if e.is_elapsed() {
+                Eth1Error::Timeout
+            } else {
+                match e.into_inner() {
+                    Some(e) => Eth1Error::Request(format!("{}", e)),
+                    None => Eth1Error::Request("Timer failed".to_string()),
+                }
+            }
+        });
+        let (status, body) = oneshot::spawn(future, &self.runtime.executor()).wait()?;
+        if !status.is_success() {
+            return Err(Eth1Error::Status(status.as_u16()));
+        }
+
+        let mut response: Value = serde_json::from_slice(&body)
+            .map_err(|_| Eth1Error::InvalidResponse("Invalid JSON".to_string()))?;
+        if let Some(error) = response.get("error") {
+            return Err(Eth1Error::Rpc(
+                error["code"].as_i64().unwrap_or(0),
+                error["message"].as_str().unwrap_or("").to_string(),
+            ));
+        }
+        match response.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => Err(Eth1Error::InvalidResponse(
+                "Missing field: result".to_string(),
+            )),
+        }
+    }
+}
+
+fn invalid(value: &Value) -> Eth1Error {
+    Eth1Error::InvalidResponse(format!("Invalid value: {}", value))
+}
+
+/// Parses a `0x`-prefixed hex quantity.
+fn parse_quantity(value: &Value) -> Result<u64, Eth1Error> {
+    value
+        .as_str()
+        .filter(|s| s.starts_with("0x") && s.len() > 2)
+        .and_then(|s| u64::from_str_radix(&s[2..], 16).ok())
+        .ok_or_else(|| invalid(value))
+}
+
+fn parse_hash(value: &Value) -> Result<Hash256, Eth1Error> {
+    value
+        .as_str()
+        .filter(|s| s.starts_with("0x"))
+        .and_then(|s| hex::decode(&s[2..]).ok())
+        .filter(|bytes| bytes.len() == 32)
+        .map(|bytes| Hash256::from(&bytes[..]))
+        .ok_or_else(|| invalid(value))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use futures::sync::oneshot::Sender;
+    use hyper::service::service_fn;
+    use hyper::{Response, Server};
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, RwLock};
+    use std::thread::{self, JoinHandle};
+
+    /// The chain served by a `MockEth1`.
+    #[derive(Debug, Clone)]
+    pub struct MockChain {
+        pub chain_id: u64,
+        pub network_id: u64,
+        pub head: u64,
+        pub syncing: Option<u64>,
+        /// The blocks from this number on are replaced, as if by a reorg.
+        pub fork_from: Option<u64>,
+    }
+
+    impl MockChain {
+        pub fn block(&self, number: u64) -> Option<Eth1Block> {
+            if number > self.head {
+                return None;
+            }
+            Some(Eth1Block {
+                number,
+                hash: self.hash(number),
+                parent_hash: self.hash(number.saturating_sub(1)),
+                timestamp: number * 14,
+            })
+        }
+
+        fn hash(&self, number: u64) -> Hash256 {
+            let forked = number >= self.fork_from.unwrap_or(u64::MAX);
+            let mut bytes = [0; 32];
+            bytes[..8].copy_from_slice(&number.to_be_bytes());
+            bytes[8] = forked as u8;
+            bytes[31] = 1;
+            Hash256::from(&bytes[..])
+        }
+    }
+
+    /// An eth1 JSON-RPC endpoint serving `chain` from a background thread, until dropped.
+    pub struct MockEth1 {
+        pub chain: Arc<RwLock<MockChain>>,
+        addr: SocketAddr,
+        shutdown: Option<Sender<()>>,
+        handle: Option<JoinHandle<()>>,
+    }
+
+    impl MockEth1 {
+        pub fn start(chain_id: u64, head: u64) -> Self {
+            let chain = Arc::new(RwLock::new(MockChain {
+                chain_id,
+                network_id: chain_id,
+                head,
+                syncing: None,
+                fork_from: None,
+            }));
+            let served = chain.clone();
+            let new_service = move || {
+                let chain = served.clone();
+                service_fn(move |req: Request<Body>| {
+                    let chain = chain.clone();
+                    req.into_body().concat2().map(move |body| {
+                        let request: Value = serde_json::from_slice(&body).unwrap();
+                        let chain = chain.read().unwrap();
+                        Response::new(Body::from(respond(&chain, &request).to_string()))
+                    })
+                })
+            };
+            let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+            let (shutdown, shutdown_rx) = oneshot::channel();
+            let server = Server::bind(&addr).serve(new_service);
+            let addr = server.local_addr();
+            let server = server
+                .with_graceful_shutdown(shutdown_rx)
+                .map_err(|e| panic!("Mock eth1 failed: {}", e));
+            let handle = thread::spawn(move || hyper::rt::run(server));
+            Self {
+                chain,
+                addr,
+                shutdown: Some(shutdown),
+                handle: Some(handle),
+            }
+        }
+
+        pub fn url(&self) -> String {
+            format!("http://{}", self.addr)
+        }
+
+        pub fn client(&self) -> Eth1Client {
+            Eth1Client::new(&self.url(), Duration::from_secs(5)).unwrap()
+        }
+    }
+
+    impl Drop for MockEth1 {
+        fn drop(&mut self) {
+            if let Some(shutdown) = self.shutdown.take() {
+                let _ = shutdown.send(());
+            }
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    fn respond(chain: &MockChain, request: &Value) -> Value {
+        let quantity = |n: u64| Value::String(format!("0x{:x}", n));
+        let hash = |h: Hash256| Value::String(format!("0x{}", hex::encode(h)));
+        let result = match request["method"].as_str().unwrap() {
+            "eth_chainId" => quantity(chain.chain_id),
+            "net_version" => Value::String(chain.network_id.to_string()),
+            "eth_syncing" => match chain.syncing {
+                Some(distance) => json!({
+                    "currentBlock": quantity(chain.head),
+                    "highestBlock": quantity(chain.head + distance),
+                }),
+                None => Value::Bool(false),
+            },
+            "eth_blockNumber" => quantity(chain.head),
+            "eth_getBlockByNumber" => {
+                let number = parse_quantity(&request["params"][0]).unwrap();
+                match chain.block(number) {
+                    Some(block) => json!({
+                        "number": quantity(block.number),
+                        "hash": hash(block.hash),
+                        "parentHash": hash(block.parent_hash),
+                        "timestamp": quantity(block.timestamp),
+                    }),
+                    None => Value::Null,
+                }
+            }
+            method => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {"code": -32601, "message": format!("Unknown method {}", method)},
+                })
+            }
+        };
+        json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+    }
+
+    #[test]
+    fn test_client() {
+        let eth1 = MockEth1::start(5, 100);
+        let client = eth1.client();
+        assert_eq!(client.url(), eth1.url());
+        assert_eq!(client.chain_id(), Ok(5));
+        assert_eq!(client.network_id(), Ok(5));
+        assert_eq!(client.syncing(), Ok(None));
+        assert_eq!(client.block_number(), Ok(100));
+
+        let block = client.block_by_number(42).unwrap().unwrap();
+        assert_eq!(Some(block.clone()), eth1.chain.read().unwrap().block(42));
+        assert_eq!(block.timestamp, 42 * 14);
+        assert_eq!(
+            block.parent_hash,
+            client.block_by_number(41).unwrap().unwrap().hash
+        );
+        assert_eq!(client.block_by_number(101), Ok(None));
+
+        eth1.chain.write().unwrap().syncing = Some(30);
+        assert_eq!(client.syncing(), Ok(Some(30)));
+        match client.call("eth_unknown", json!([])) {
+            Err(Eth1Error::Rpc(-32601, _)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_client_errors() {
+        assert_eq!(
+            Eth1Client::new("not a url", Duration::from_secs(1)).err(),
+            Some(Eth1Error::InvalidUrl("not a url".to_string()))
+        );
+        let offline = Eth1Client::new("http://127.0.0.1:1", Duration::from_secs(1)).unwrap();
+        match offline.chain_id() {
+            Err(Eth1Error::Request(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity(&json!("0x0")), Ok(0));
+        assert_eq!(parse_quantity(&json!("0x1b4")), Ok(436));
+        assert!(parse_quantity(&json!("0x")).is_err());
+        assert!(parse_quantity(&json!("1b4")).is_err());
+        assert!(parse_quantity(&json!(436)).is_err());
+    }
+}
@@ -0,0 +1,28 @@
+extern crate futures;
+extern crate hex;
+extern crate hyper;
+#[macro_use]
+extern crate lazy_static;
+extern crate lighthouse_metrics;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+extern crate task_executor;
+extern crate tokio;
+extern crate types;
+
+mod backend;
+mod block_cache;
+mod config;
+mod fallback;
+mod http;
+mod metrics;
+mod service;
+
+pub use backend::{Eth1Backend, NoEth1Backend};
+pub use block_cache::{BlockCache, BlockCacheError};
+pub use config::{Eth1Config, DEFAULT_ETH1_ENDPOINT};
+pub use fallback::{Eth1Fallback, Health};
+pub use http::{Eth1Block, Eth1Client, Eth1Error};
+pub use service::Eth1Service;
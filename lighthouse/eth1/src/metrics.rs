@@ -0,0 +1,22 @@
+use lighthouse_metrics::{
+    try_create_int_counter, try_create_int_gauge, IntCounter, IntGauge, Result,
+};
+
+lazy_static! {
+    pub static ref ETH1_ENDPOINTS_AVAILABLE: Result<IntGauge> = try_create_int_gauge(
+        "eth1_endpoints_available",
+        "Number of eth1 endpoints which are online and on the deposit chain"
+    );
+    pub static ref ETH1_HEAD_NUMBER: Result<IntGauge> = try_create_int_gauge(
+        "eth1_head_number",
+        "Number of the head block of the eth1 chain"
+    );
+    pub static ref ETH1_VOTING_BLOCK_NUMBER: Result<IntGauge> = try_create_int_gauge(
+        "eth1_voting_block_number",
+        "Number of the eth1 block voted for in produced blocks"
+    );
+    pub static ref ETH1_CACHE_RESETS: Result<IntCounter> = try_create_int_counter(
+        "eth1_cache_resets_total",
+        "Count of times the eth1 block cache was cleared by a reorg"
+    );
+}
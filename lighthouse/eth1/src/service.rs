@@ -0,0 +1,258 @@
+use super::block_cache::BlockCache;
+use super::config::Eth1Config;
+use super::fallback::{Eth1Fallback, Health};
+use super::http::{Eth1Block, Eth1Client, Eth1Error};
+use super::metrics;
+use lighthouse_metrics::{inc_counter, set_gauge};
+use slog::Logger;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use task_executor::TaskExecutor;
+
+/// Follows the eth1 chain from a background thread, caching the blocks at the follow distance
+/// from its head, until the service is dropped.
+///
+/// The highest cached block is the one voted for by produced blocks.
+pub struct Eth1Service {
+    fallback: Arc<Eth1Fallback>,
+    cache: Arc<RwLock<BlockCache>>,
+    shutdown: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Eth1Service {
+    pub fn start(
+        config: Eth1Config,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> Result<Self, Eth1Error> {
+        let clients = config
+            .endpoints
+            .iter()
+            .map(|url| Eth1Client::new(url, config.timeout))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fallback = Arc::new(Eth1Fallback::new(
+            clients,
+            config.chain_id,
+            config.network_id,
+            log.clone(),
+        ));
+        let cache = Arc::new(RwLock::new(BlockCache::new(config.cache_size as usize)));
+
+        let (shutdown_tx, shutdown_rx) = channel();
+        let handle = {
+            let (fallback, cache) = (fallback.clone(), cache.clone());
+            executor.spawn("eth1", move |_| {
+                run(&fallback, &cache, &config, &shutdown_rx, &log)
+            })
+        };
+        Ok(Self {
+            fallback,
+            cache,
+            shutdown: shutdown_tx,
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the block to vote for, or `None` until the first update.
+    pub fn voting_block(&self) -> Option<Eth1Block> {
+        self.cache
+            .read()
+            .expect("Eth1 cache lock poisoned")
+            .highest()
+            .cloned()
+    }
+
+    /// Returns the URL and health of each endpoint.
+    pub fn health(&self) -> Vec<(String, Health)> {
+        self.fallback.health()
+    }
+}
+
+impl Drop for Eth1Service {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    fallback: &Eth1Fallback,
+    cache: &RwLock<BlockCache>,
+    config: &Eth1Config,
+    shutdown: &Receiver<()>,
+    log: &Logger,
+) {
+    info!(log, "Eth1 service started";
+          "endpoints" => config.endpoints.len(),
+          "chain_id" => config.chain_id,
+          "follow_distance" => config.follow_distance);
+    loop {
+        fallback.update_health();
+        if fallback.num_available() == 0 {
+            warn!(log, "No eth1 endpoint available"; "endpoints" => config.endpoints.len());
+        } else if let Err(e) = update_cache(fallback, cache, config, log) {
+            warn!(log, "Unable to update eth1 cache"; "error" => format!("{:?}", e));
+        }
+
+        match shutdown.recv_timeout(config.update_interval) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/// Extends the cache up to the block at the follow distance from the eth1 head, returning the
+/// number of blocks added.
+///
+/// If the eth1 chain has reorganised beyond the follow distance, the cache is cleared and
+/// refilled from the new chain.
+pub fn update_cache(
+    fallback: &Eth1Fallback,
+    cache: &RwLock<BlockCache>,
+    config: &Eth1Config,
+    log: &Logger,
+) -> Result<usize, Eth1Error> {
+    let head = fallback.first_success(|c| c.block_number())?;
+    set_gauge(&metrics::ETH1_HEAD_NUMBER, head as i64);
+    let target = match head.checked_sub(config.follow_distance) {
+        Some(target) => target,
+        None => return Ok(0),
+    };
+
+    /*
+     * Blocks are fetched without holding the lock, so that voting is never blocked by eth1.
+     */
+    let mut added = 0;
+    loop {
+        let next = {
+            let cache = cache.read().expect("Eth1 cache lock poisoned");
+            match cache.highest() {
+                Some(highest) => highest.number + 1,
+                None => target.saturating_sub(config.cache_size.saturating_sub(1)),
+            }
+        };
+        if next > target {
+            break;
+        }
+        let block = fallback
+            .first_success(|c| c.block_by_number(next))?
+            .ok_or_else(|| Eth1Error::InvalidResponse(format!("Missing eth1 block {}", next)))?;
+
+        let mut cache = cache.write().expect("Eth1 cache lock poisoned");
+        match cache.insert(block) {
+            Ok(()) => added += 1,
+            Err(e) => {
+                warn!(log, "Eth1 chain reorganised beyond the follow distance";
+                      "block_number" => next,
+                      "error" => format!("{:?}", e));
+                inc_counter(&metrics::ETH1_CACHE_RESETS);
+                cache.clear();
+            }
+        }
+    }
+
+    if let Some(block) = cache.read().expect("Eth1 cache lock poisoned").highest() {
+        set_gauge(&metrics::ETH1_VOTING_BLOCK_NUMBER, block.number as i64);
+        if added > 0 {
+            debug!(log, "Eth1 cache updated";
+                   "voting_block_number" => block.number,
+                   "voting_block_hash" => format!("{:?}", block.hash),
+                   "added" => added);
+        }
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::http::tests::MockEth1;
+    use super::*;
+    use slog::Discard;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn config(eth1: &MockEth1) -> Eth1Config {
+        Eth1Config {
+            enabled: true,
+            endpoints: vec![eth1.url()],
+            chain_id: 5,
+            network_id: 5,
+            follow_distance: 10,
+            cache_size: 4,
+            update_interval: Duration::from_millis(50),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_update_cache() {
+        let eth1 = MockEth1::start(5, 5);
+        let config = config(&eth1);
+        let log = Logger::root(Discard, o!());
+        let fallback = Eth1Fallback::new(vec![eth1.client()], 5, 5, log.clone());
+        fallback.update_health();
+        let cache = RwLock::new(BlockCache::new(4));
+
+        /*
+         * Nothing is cached until the chain is longer than the follow distance.
+         */
+        assert_eq!(update_cache(&fallback, &cache, &config, &log), Ok(0));
+        assert!(cache.read().unwrap().is_empty());
+
+        eth1.chain.write().unwrap().head = 100;
+        assert_eq!(update_cache(&fallback, &cache, &config, &log), Ok(4));
+        assert_eq!(cache.read().unwrap().lowest().unwrap().number, 87);
+        assert_eq!(cache.read().unwrap().highest().unwrap().number, 90);
+
+        eth1.chain.write().unwrap().head = 102;
+        assert_eq!(update_cache(&fallback, &cache, &config, &log), Ok(2));
+        let highest = cache.read().unwrap().highest().cloned();
+        assert_eq!(highest, eth1.chain.read().unwrap().block(92));
+
+        /*
+         * A reorg beyond the follow distance replaces the cached blocks.
+         */
+        {
+            let mut chain = eth1.chain.write().unwrap();
+            chain.fork_from = Some(91);
+            chain.head = 103;
+        }
+        update_cache(&fallback, &cache, &config, &log).unwrap();
+        update_cache(&fallback, &cache, &config, &log).unwrap();
+        let cache = cache.read().unwrap();
+        assert_eq!(
+            cache.highest(),
+            eth1.chain.read().unwrap().block(93).as_ref()
+        );
+        assert_eq!(cache.lowest().unwrap().number, 90);
+    }
+
+    #[test]
+    fn test_service() {
+        let eth1 = MockEth1::start(5, 100);
+        let offline = "http://127.0.0.1:1".to_string();
+        let mut config = config(&eth1);
+        config.endpoints.insert(0, offline);
+        let (executor, _) = TaskExecutor::new(Logger::root(Discard, o!()));
+        let service = Eth1Service::start(config, &executor, Logger::root(Discard, o!())).unwrap();
+
+        let start = Instant::now();
+        while service.voting_block().is_none() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(service.voting_block(), eth1.chain.read().unwrap().block(90));
+        let health: Vec<Health> = service.health().into_iter().map(|(_, h)| h).collect();
+        assert_eq!(health, vec![Health::Offline, Health::Synced]);
+
+        let config = Eth1Config {
+            endpoints: vec!["not a url".to_string()],
+            ..Eth1Config::default()
+        };
+        assert!(Eth1Service::start(config, &executor, Logger::root(Discard, o!())).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use super::error::{ApiError, ApiResult};
+use super::json::{data_response, hex_bytes, parse_hash};
+use super::Context;
+use db::ClientDB;
+use hyper::header::AUTHORIZATION;
+use hyper::{Request, Response};
+use network::peer_manager::ConnectionState;
+use network::{NodeId, PeerManager};
+use process_metrics::{dump_heap_profile, ProfileError, HEAP_PROFILE_EXTENSION};
+use rand::{thread_rng, Rng};
+use serde_json::{self, Value};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::RwLockWriteGuard;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// The file in the data directory holding the token of the admin endpoints.
+pub const API_TOKEN_FILE: &str = "api-token.txt";
+
+/*
+ * The admin endpoints, under `/lighthouse/admin`, change the node's behaviour and so require the
+ * token in the `Authorization: Bearer <token>` header.
+ */
+
+/// Returns the token in `path`, first writing a new random token if there is none.
+pub fn load_or_create_token(path: &Path) -> io::Result<String> {
+    if path.exists() {
+        return Ok(fs::read_to_string(path)?.trim().to_string());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let bytes: [u8; 32] = thread_rng().gen();
+    let token = hex_bytes(&bytes);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+/// Returns an error unless `req` has the admin token.
+pub fn authorize<T: ClientDB>(ctx: &Context<T>, req: &Request<Vec<u8>>) -> Result<(), ApiError> {
+    let token = match ctx.admin_token {
+        Some(ref token) => token,
+        None => return Err(ApiError::Forbidden("Admin API is disabled".to_string())),
+    };
+    let given = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| {
+            let mut parts = header.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some("Bearer"), Some(given)) => Some(given),
+                _ => None,
+            }
+        });
+    match given {
+        Some(given) if constant_time_eq(given.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(ApiError::Unauthorized("Invalid API token".to_string())),
+    }
+}
+
+/// `POST /lighthouse/admin/heap_profile`
+///
+/// Writes a profile of the allocator's heap to a new file in the heap profile dir, returning its
+/// path, to diagnose the growth of the node's memory. With glibc's malloc this is the XML of
+/// `malloc_info`, and with jemalloc a profile for `jeprof`.
+pub fn post_heap_profile<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let dir = match ctx.heap_profile_dir {
+        Some(ref dir) => dir,
+        None => {
+            return Err(ApiError::Forbidden(
+                "Heap profiles are disabled".to_string(),
+            ))
+        }
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let path = dir.join(format!(
+        "heap-{}.{:03}.{}",
+        now.as_secs(),
+        now.subsec_millis(),
+        HEAP_PROFILE_EXTENSION
+    ));
+    fs::create_dir_all(dir)
+        .map_err(|e| ApiError::ServerError(format!("Unable to create heap profile dir: {}", e)))?;
+    match dump_heap_profile(&path) {
+        Ok(()) => {
+            info!(ctx.log, "Wrote heap profile via API"; "path" => format!("{}", path.display()));
+            Ok(data_response(
+                json!({ "path": format!("{}", path.display()) }),
+            ))
+        }
+        Err(ProfileError::Unsupported) => Err(ApiError::Forbidden(
+            "The allocator of this build has no heap profiles".to_string(),
+        )),
+        Err(e) => Err(ApiError::ServerError(format!(
+            "Unable to write heap profile: {:?}",
+            e
+        ))),
+    }
+}
+
+/// `GET /lighthouse/admin/peers`
+///
+/// Lists every known peer with its score, including banned and discovered peers.
+pub fn get_peers<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let peer_manager = match ctx.peer_manager {
+        Some(ref peer_manager) => peer_manager.read().expect("Peer manager lock poisoned"),
+        None => return Ok(data_response(json!([]))),
+    };
+    let mut peers: Vec<_> = peer_manager.peers().collect();
+    peers.sort_by_key(|(peer_id, _)| **peer_id);
+    let peers: Vec<Value> = peers
+        .into_iter()
+        .map(|(peer_id, info)| {
+            let state = match info.state {
+                ConnectionState::Connected { .. } => "connected",
+                ConnectionState::Disconnected { .. } => "disconnected",
+                ConnectionState::Banned { .. } => "banned",
+            };
+            json!({
+                "peer_id": hex_bytes(&peer_id.0),
+                "score": info.score.value(),
+                "state": state,
+                "banned": peer_manager.is_banned(peer_id),
+                "direction": info.direction.map(|d| format!("{:?}", d).to_lowercase()),
+                "enr": info.enr.as_ref().map(|enr| format!("{}", enr)),
+            })
+        })
+        .collect();
+    Ok(data_response(Value::Array(peers)))
+}
+
+/// `POST /lighthouse/admin/peers/{peer_id}/ban`
+///
+/// Bans the peer until it is unbanned or the node restarts.
+pub fn post_ban<T: ClientDB>(ctx: &Context<T>, peer_id: &str) -> ApiResult {
+    let peer_id = NodeId(parse_hash(peer_id)?);
+    info!(ctx.log, "Banning peer via API"; "peer_id" => hex_bytes(&peer_id.0));
+    peer_manager(ctx)?.ban_peer(peer_id);
+    Ok(Response::new(vec![]))
+}
+
+/// `DELETE /lighthouse/admin/peers/{peer_id}/ban`
+pub fn delete_ban<T: ClientDB>(ctx: &Context<T>, peer_id: &str) -> ApiResult {
+    let peer_id = NodeId(parse_hash(peer_id)?);
+    if !peer_manager(ctx)?.unban_peer(&peer_id, Instant::now()) {
+        return Err(ApiError::NotFound("Peer is not banned".to_string()));
+    }
+    Ok(Response::new(vec![]))
+}
+
+/// `POST /lighthouse/admin/peers/dial`
+///
+/// The body is `{"multiaddr": "/ip4/1.2.3.4/tcp/9000"}`.
+pub fn post_dial<T: ClientDB>(ctx: &Context<T>, body: &[u8]) -> ApiResult {
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|_| ApiError::BadRequest("Invalid JSON".to_string()))?;
+    let multiaddr = value["multiaddr"]
+        .as_str()
+        .ok_or_else(|| ApiError::BadRequest("Missing field: multiaddr".to_string()))?;
+    let address = parse_multiaddr(multiaddr)
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid multiaddr: {}", multiaddr)))?;
+
+    if !peer_manager(ctx)?.dial(address) {
+        return Err(ApiError::BadRequest(format!(
+            "Address is banned: {}",
+            address.ip()
+        )));
+    }
+    info!(ctx.log, "Dialing peer via API"; "address" => format!("{}", address));
+    Ok(Response::new(vec![]))
+}
+
+fn peer_manager<T: ClientDB>(
+    ctx: &Context<T>,
+) -> Result<RwLockWriteGuard<'_, PeerManager>, ApiError> {
+    match ctx.peer_manager {
+        Some(ref peer_manager) => Ok(peer_manager.write().expect("Peer manager lock poisoned")),
+        None => Err(ApiError::ServerError(
+            "Networking is not running".to_string(),
+        )),
+    }
+}
+
+/// Parses a TCP multiaddr of the form `/ip4/{address}/tcp/{port}` or `/ip6/{address}/tcp/{port}`.
+fn parse_multiaddr(multiaddr: &str) -> Option<SocketAddr> {
+    let parts: Vec<&str> = multiaddr.split('/').collect();
+    match &parts[..] {
+        ["", protocol, ip, "tcp", port] => {
+            let ip: IpAddr = ip.parse().ok()?;
+            match (*protocol, ip) {
+                ("ip4", IpAddr::V4(_)) | ("ip6", IpAddr::V6(_)) => {
+                    Some(SocketAddr::new(ip, port.parse().ok()?))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Compares secrets without revealing the length of their common prefix.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::handle;
+    use super::super::router::tests::context;
+    use super::*;
+    use hyper::StatusCode;
+    use network::{PeerManagerConfig, PeerManagerEvent};
+    use slog::{Discard, Logger};
+    use std::sync::{Arc, RwLock};
+
+    fn request(method: &str, uri: &str, token: Option<&str>, body: &str) -> Request<Vec<u8>> {
+        let mut builder = Request::builder();
+        builder.method(method).uri(uri);
+        if let Some(token) = token {
+            builder.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        builder.body(body.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_requires_token() {
+        let mut ctx = context();
+        let req = request("GET", "/lighthouse/admin/peers", None, "");
+        assert_eq!(handle(&ctx, &req).status(), StatusCode::FORBIDDEN);
+
+        ctx.admin_token = Some("secret".to_string());
+        assert_eq!(handle(&ctx, &req).status(), StatusCode::UNAUTHORIZED);
+        let req = request("GET", "/lighthouse/admin/peers", Some("secrets"), "");
+        assert_eq!(handle(&ctx, &req).status(), StatusCode::UNAUTHORIZED);
+        let req = request("GET", "/lighthouse/admin/peers", Some("secret"), "");
+        assert_eq!(handle(&ctx, &req).status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_ban_unban_and_dial() {
+        let mut ctx = context();
+        ctx.admin_token = Some("secret".to_string());
+        let now = Instant::now();
+        let mut pm = PeerManager::new(PeerManagerConfig::default(), Logger::root(Discard, o!()));
+        let peer_id = NodeId::random();
+        assert!(pm.on_connect(peer_id, now));
+        let pm = Arc::new(RwLock::new(pm));
+        ctx.peer_manager = Some(pm.clone());
+
+        let uri = format!("/lighthouse/admin/peers/{}/ban", hex_bytes(&peer_id.0));
+        let response = handle(&ctx, &request("POST", &uri, Some("secret"), ""));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(pm.read().unwrap().is_banned(&peer_id));
+
+        let req = request("GET", "/lighthouse/admin/peers", Some("secret"), "");
+        let body: Value = serde_json::from_slice(handle(&ctx, &req).body()).unwrap();
+        assert_eq!(body["data"][0]["banned"], true);
+        assert_eq!(body["data"][0]["direction"], "outbound");
+
+        let response = handle(&ctx, &request("DELETE", &uri, Some("secret"), ""));
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = handle(&ctx, &request("DELETE", &uri, Some("secret"), ""));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let dial = |body: &str| {
+            let req = request("POST", "/lighthouse/admin/peers/dial", Some("secret"), body);
+            handle(&ctx, &req).status()
+        };
+        assert_eq!(
+            dial(r#"{"multiaddr": "/ip4/1.2.3.4/tcp/9000"}"#),
+            StatusCode::OK
+        );
+        assert_eq!(
+            dial(r#"{"multiaddr": "/ip4/::1/tcp/9000"}"#),
+            StatusCode::BAD_REQUEST
+        );
+        let mut pm = pm.write().unwrap();
+        let mut events = vec![];
+        while let Some(event) = pm.poll() {
+            events.push(event);
+        }
+        assert_eq!(
+            events.last(),
+            Some(&PeerManagerEvent::Dial {
+                address: "1.2.3.4:9000".parse().unwrap()
+            })
+        );
+    }
+
+    #[test]
+    fn test_heap_profile() {
+        let mut ctx = context();
+        ctx.admin_token = Some("secret".to_string());
+        let req = request("POST", "/lighthouse/admin/heap_profile", Some("secret"), "");
+        assert_eq!(handle(&ctx, &req).status(), StatusCode::FORBIDDEN);
+
+        let dir = std::env::temp_dir().join(format!("heap_profile_test_{}", NodeId::random().0));
+        ctx.heap_profile_dir = Some(dir.clone());
+        let response = handle(&ctx, &req);
+        if cfg!(all(target_os = "linux", target_env = "gnu")) {
+            assert_eq!(response.status(), StatusCode::OK);
+            let body: Value = serde_json::from_slice(response.body()).unwrap();
+            let path = body["data"]["path"].as_str().unwrap();
+            assert!(fs::metadata(path).unwrap().len() > 0);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_token_file() {
+        let dir = std::env::temp_dir().join(format!("api_token_test_{}", NodeId::random().0));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(API_TOKEN_FILE);
+        let token = load_or_create_token(&path).unwrap();
+        assert_eq!(token.len(), 66);
+        assert_eq!(load_or_create_token(&path).unwrap(), token);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+use super::block_id::{canonical_root_at_slot, BlockId};
+use super::encoding::Encoding;
+use super::error::{ApiError, ApiResult};
+use super::json::{
+    block_json, data_response, header_json, hex_bytes, json_response, parse_hash, ssz_response,
+};
+use super::query::Query;
+use super::Context;
+use beacon_node::BeaconNode;
+use db::ClientDB;
+use hashing::canonical_hash;
+use hyper::header::HeaderValue;
+use serde_json::Value;
+use ssz::{ssz_encode, Decodable, Encodable, SszStream};
+use types::{BeaconBlock, Hash256};
+
+/// The most slots whose blocks are returned by a request for a range of blocks.
+pub const MAX_SLOTS_PER_RANGE: u64 = 256;
+/// The size of SSZ blocks after which no more are added to a range.
+pub const MAX_RANGE_BYTES: usize = 4 * 1024 * 1024;
+/// Gives the slot from which to request the next page of a range of blocks.
+pub const NEXT_START_SLOT_HEADER: &str = "eth-next-start-slot";
+
+/// `GET /eth/v1/beacon/blocks/{block_id}`
+pub fn get_block<T: ClientDB>(ctx: &Context<T>, block_id: &str, encoding: Encoding) -> ApiResult {
+    let block_id: BlockId = block_id.parse()?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    if encoding == Encoding::Ssz {
+        let (_, ssz) = block_id.block_ssz(&node)?;
+        return Ok(ssz_response(ssz));
+    }
+    let (_, block) = block_id.block(&node)?;
+    Ok(data_response(json!({ "message": block_json(&block) })))
+}
+
+/// `GET /lighthouse/beacon/blocks?start_slot,count`
+///
+/// Returns the canonical blocks of the `count` slots from `start_slot`, as JSON or as an SSZ list.
+/// `count` defaults to, and is capped at, `MAX_SLOTS_PER_RANGE`, and no more blocks are added once
+/// `MAX_RANGE_BYTES` of SSZ is reached. If the range does not reach the head, the
+/// `eth-next-start-slot` header, and `meta.next_start_slot` of JSON responses, give the start of
+/// the next page.
+pub fn get_blocks_range<T: ClientDB>(
+    ctx: &Context<T>,
+    query: &Query,
+    encoding: Encoding,
+) -> ApiResult {
+    let start_slot = query
+        .parse_value::<u64>("start_slot")?
+        .ok_or_else(|| ApiError::BadRequest("Missing parameter: start_slot".to_string()))?;
+    let count = query
+        .parse_value::<u64>("count")?
+        .unwrap_or(MAX_SLOTS_PER_RANGE)
+        .min(MAX_SLOTS_PER_RANGE);
+    if count == 0 {
+        return Err(ApiError::BadRequest("count must be positive".to_string()));
+    }
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (head_slot, head_root) = node.head();
+    let end_slot = start_slot.saturating_add(count);
+
+    let mut next_start_slot = Some(end_slot).filter(|slot| *slot <= head_slot);
+    let mut size = 0;
+    let mut blocks = vec![];
+    for (root, ssz) in node
+        .store()
+        .blocks_in_slot_range(&head_root, start_slot, end_slot)?
+    {
+        let (block, _) = BeaconBlock::ssz_decode(&ssz, 0)
+            .map_err(|_| ApiError::ServerError("Invalid block in database".to_string()))?;
+        /*
+         * At least one block is returned, so that every page makes progress.
+         */
+        if !blocks.is_empty() && size + ssz.len() > MAX_RANGE_BYTES {
+            next_start_slot = Some(block.slot);
+            break;
+        }
+        size += ssz.len();
+        blocks.push((Hash256::from(&root[..]), block));
+    }
+
+    let mut response = if encoding == Encoding::Ssz {
+        let mut stream = SszStream::new();
+        stream.append_vec(
+            &blocks
+                .into_iter()
+                .map(|(_, block)| block)
+                .collect::<Vec<_>>(),
+        );
+        ssz_response(stream.drain())
+    } else {
+        let blocks = blocks
+            .iter()
+            .map(|(root, block)| json!({ "root": hex_bytes(root), "message": block_json(block) }))
+            .collect::<Vec<Value>>();
+        json_response(&json!({
+            "data": blocks,
+            "meta": { "next_start_slot": next_start_slot.map(|slot| slot.to_string()) },
+        }))
+    };
+    if let Some(slot) = next_start_slot {
+        response
+            .headers_mut()
+            .insert(NEXT_START_SLOT_HEADER, HeaderValue::from(slot));
+    }
+    Ok(response)
+}
+
+/// `GET /eth/v1/beacon/headers/{block_id}`
+///
+/// As SSZ, only the header itself is returned, without its root or whether it is canonical.
+pub fn get_header<T: ClientDB>(ctx: &Context<T>, block_id: &str, encoding: Encoding) -> ApiResult {
+    let block_id: BlockId = block_id.parse()?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (root, block) = block_id.block(&node)?;
+    if encoding == Encoding::Ssz {
+        return Ok(ssz_response(ssz_encode(&BlockHeader::from(&block))));
+    }
+    Ok(data_response(header_data(&node, &root, &block)?))
+}
+
+/// `GET /eth/v1/beacon/headers?slot,parent_root`
+///
+/// Returns the headers of all known blocks at `slot` and with the parent `parent_root`, canonical
+/// or not. Without either filter, returns the header of the head block. As SSZ, the headers are
+/// returned as a list, by ascending slot.
+pub fn get_headers<T: ClientDB>(ctx: &Context<T>, query: &Query, encoding: Encoding) -> ApiResult {
+    let slot = query.parse_value::<u64>("slot")?;
+    let parent_root = match query.get("parent_root") {
+        Some(root) => Some(parse_hash(root)?),
+        None => None,
+    };
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+
+    let blocks = match (slot, parent_root) {
+        (None, None) => {
+            let (root, block) = BlockId::Head.block(&node)?;
+            vec![(root, block)]
+        }
+        (slot, parent_root) => {
+            /*
+             * Only blocks after the parent can be its children, so the search can stop there.
+             */
+            let parent_slot = match parent_root {
+                Some(root) => match BlockId::Root(root).root(&node)? {
+                    Some(_) => Some(BlockId::Root(root).block(&node)?.1.slot),
+                    None => return headers_response(&node, &[], encoding),
+                },
+                None => None,
+            };
+            let min_slot = slot.unwrap_or(0).max(parent_slot.map_or(0, |s| s + 1));
+            known_blocks(&node, min_slot)?
+                .into_iter()
+                .filter(|(_, block)| slot.is_none() || Some(block.slot) == slot)
+                .filter(|(_, block)| {
+                    parent_root.is_none() || block.parent_hash() == parent_root.as_ref()
+                })
+                .collect()
+        }
+    };
+
+    headers_response(&node, &blocks, encoding)
+}
+
+fn headers_response<T: ClientDB>(
+    node: &BeaconNode<T>,
+    blocks: &[(Hash256, BeaconBlock)],
+    encoding: Encoding,
+) -> ApiResult {
+    if encoding == Encoding::Ssz {
+        let mut stream = SszStream::new();
+        stream.append_vec(
+            &blocks
+                .iter()
+                .map(|(_, block)| BlockHeader::from(block))
+                .collect::<Vec<_>>(),
+        );
+        return Ok(ssz_response(stream.drain()));
+    }
+    let headers = blocks
+        .iter()
+        .map(|(root, block)| header_data(node, root, block))
+        .collect::<Result<Vec<Value>, ApiError>>()?;
+    Ok(data_response(Value::Array(headers)))
+}
+
+fn header_data<T: ClientDB>(
+    node: &BeaconNode<T>,
+    root: &Hash256,
+    block: &BeaconBlock,
+) -> Result<Value, ApiError> {
+    let canonical = canonical_root_at_slot(node, block.slot)? == Some(*root);
+    Ok(json!({
+        "root": hex_bytes(root),
+        "canonical": canonical,
+        "header": {
+            "message": header_json(block, &body_root(block)),
+        },
+    }))
+}
+
+/// The header of a block, as served in SSZ: the block without its attestations and specials,
+/// which are committed to by `body_root`.
+struct BlockHeader {
+    slot: u64,
+    parent_root: Hash256,
+    active_state_root: Hash256,
+    crystallized_state_root: Hash256,
+    body_root: Hash256,
+}
+
+impl<'a> From<&'a BeaconBlock> for BlockHeader {
+    fn from(block: &BeaconBlock) -> Self {
+        Self {
+            slot: block.slot,
+            parent_root: block.parent_hash().cloned().unwrap_or_else(Hash256::zero),
+            active_state_root: block.active_state_root,
+            crystallized_state_root: block.crystallized_state_root,
+            body_root: body_root(block),
+        }
+    }
+}
+
+impl Encodable for BlockHeader {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.slot);
+        s.append(&self.parent_root);
+        s.append(&self.active_state_root);
+        s.append(&self.crystallized_state_root);
+        s.append(&self.body_root);
+    }
+}
+
+/// Returns the root committing to the attestations and specials of `block`.
+fn body_root(block: &BeaconBlock) -> Hash256 {
+    let mut stream = SszStream::new();
+    stream.append_vec(&block.attestations);
+    stream.append_vec(&block.specials);
+    Hash256::from(&canonical_hash(&stream.drain())[..])
+}
+
+/// Returns every known block with a slot of at least `min_slot`, found by walking back from each
+/// of the chain tips.
+pub fn known_blocks<T: ClientDB>(
+    node: &BeaconNode<T>,
+    min_slot: u64,
+) -> Result<Vec<(Hash256, BeaconBlock)>, ApiError> {
+    let mut blocks: Vec<(Hash256, BeaconBlock)> = vec![];
+    for head in node.heads() {
+        for item in node.store().block_iter(head) {
+            let (root, ssz) = item?;
+            let root = Hash256::from(&root[..]);
+            let (block, _) = BeaconBlock::ssz_decode(&ssz, 0)
+                .map_err(|_| ApiError::ServerError("Invalid block in database".to_string()))?;
+            /*
+             * Chains share their ancestors, so a known block means the rest of the chain has
+             * already been visited.
+             */
+            if block.slot < min_slot || blocks.iter().any(|(known, _)| *known == root) {
+                break;
+            }
+            blocks.push((root, block));
+        }
+    }
+    blocks.sort_by_key(|(_, block)| block.slot);
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::handle;
+    use super::super::router::tests::{context, get, import_block};
+    use super::*;
+    use hyper::header::ACCEPT;
+    use hyper::{Request, StatusCode};
+    use ssz::decode_ssz_list;
+
+    #[test]
+    fn test_get_blocks_range() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let mut parent = genesis;
+        for slot in &[1, 2, 4, 5] {
+            parent = import_block(&ctx, parent, *slot);
+        }
+
+        let (status, body) = get(&ctx, "/lighthouse/beacon/blocks?start_slot=1&count=3");
+        assert_eq!(status, StatusCode::OK);
+        let slots: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| block["message"]["slot"].as_str().unwrap())
+            .collect();
+        assert_eq!(slots, vec!["1", "2"]);
+        assert_eq!(body["meta"]["next_start_slot"], "4");
+
+        let (_, body) = get(&ctx, "/lighthouse/beacon/blocks?start_slot=4");
+        assert_eq!(body["data"].as_array().unwrap().len(), 2);
+        assert_eq!(body["data"][1]["root"], hex_bytes(&parent));
+        assert_eq!(body["meta"]["next_start_slot"], Value::Null);
+
+        let req = Request::get("/lighthouse/beacon/blocks?start_slot=0&count=2")
+            .header(ACCEPT, "application/octet-stream")
+            .body(vec![])
+            .unwrap();
+        let response = handle(&ctx, &req);
+        assert_eq!(response.headers()[NEXT_START_SLOT_HEADER], "2");
+        let (blocks, _): (Vec<BeaconBlock>, _) = decode_ssz_list(response.body(), 0).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].slot, 1);
+
+        let (status, _) = get(&ctx, "/lighthouse/beacon/blocks");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&ctx, "/lighthouse/beacon/blocks?start_slot=0&count=0");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,112 @@
+use super::error::ApiError;
+use super::json::parse_hash;
+use beacon_node::BeaconNode;
+use db::ClientDB;
+use ssz::Decodable;
+use std::str::FromStr;
+use types::{BeaconBlock, Hash256};
+
+/// Identifies a block in a request path.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockId {
+    Head,
+    Genesis,
+    Finalized,
+    /// The canonical block at the slot, if the slot was not skipped.
+    Slot(u64),
+    Root(Hash256),
+}
+
+impl FromStr for BlockId {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, ApiError> {
+        match s {
+            "head" => Ok(BlockId::Head),
+            "genesis" => Ok(BlockId::Genesis),
+            "finalized" => Ok(BlockId::Finalized),
+            _ if s.starts_with("0x") => parse_hash(s).map(BlockId::Root),
+            _ => s
+                .parse::<u64>()
+                .map(BlockId::Slot)
+                .map_err(|_| ApiError::BadRequest(format!("Invalid block id: {}", s))),
+        }
+    }
+}
+
+impl BlockId {
+    /// Returns the root of the identified block, if it is known.
+    pub fn root<T: ClientDB>(&self, node: &BeaconNode<T>) -> Result<Option<Hash256>, ApiError> {
+        match self {
+            BlockId::Head => Ok(Some(node.head().1)),
+            BlockId::Genesis => Ok(Some(node.genesis_root())),
+            BlockId::Finalized => Ok(Some(node.finalized_root())),
+            BlockId::Slot(slot) => canonical_root_at_slot(node, *slot),
+            BlockId::Root(root) => {
+                if node.store().block_exists(root)? {
+                    Ok(Some(*root))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Returns the root and SSZ of the identified block.
+    pub fn block_ssz<T: ClientDB>(
+        &self,
+        node: &BeaconNode<T>,
+    ) -> Result<(Hash256, Vec<u8>), ApiError> {
+        let not_found = || ApiError::NotFound(format!("Unknown block: {:?}", self));
+        let root = self.root(node)?.ok_or_else(not_found)?;
+        let ssz = node
+            .store()
+            .get_serialized_block(&root)?
+            .ok_or_else(not_found)?;
+        Ok((root, ssz))
+    }
+
+    /// Returns the root of the identified block, together with the block.
+    pub fn block<T: ClientDB>(
+        &self,
+        node: &BeaconNode<T>,
+    ) -> Result<(Hash256, BeaconBlock), ApiError> {
+        let (root, ssz) = self.block_ssz(node)?;
+        let (block, _) = BeaconBlock::ssz_decode(&ssz, 0)
+            .map_err(|_| ApiError::ServerError("Invalid block in database".to_string()))?;
+        Ok((root, block))
+    }
+}
+
+/// Returns the root of the block at `slot` on the canonical chain, if the slot was not skipped.
+pub fn canonical_root_at_slot<T: ClientDB>(
+    node: &BeaconNode<T>,
+    slot: u64,
+) -> Result<Option<Hash256>, ApiError> {
+    let (head_slot, head_root) = node.head();
+    if slot > head_slot {
+        return Ok(None);
+    }
+    let block = node.store().block_at_slot(&head_root, slot)?;
+    Ok(block.map(|(root, _)| Hash256::from(&root[..])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_block_id() {
+        assert_eq!("head".parse(), Ok(BlockId::Head));
+        assert_eq!("genesis".parse(), Ok(BlockId::Genesis));
+        assert_eq!("finalized".parse(), Ok(BlockId::Finalized));
+        assert_eq!("12".parse(), Ok(BlockId::Slot(12)));
+        let root = Hash256::from(7);
+        assert_eq!(
+            format!("0x{}", ::hex::encode(&root[..])).parse(),
+            Ok(BlockId::Root(root))
+        );
+        assert!("justified".parse::<BlockId>().is_err());
+        assert!("0x12".parse::<BlockId>().is_err());
+    }
+}
@@ -0,0 +1,37 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+
+/// The configuration of the HTTP API server.
+#[derive(Clone, Debug)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    pub listen_address: IpAddr,
+    pub port: u16,
+    /// The origins from which browsers may make requests, or `*` for any. No CORS headers are sent
+    /// if empty.
+    pub allow_origins: Vec<String>,
+    /// Serves HTTPS instead of HTTP, if set.
+    pub tls: Option<TlsConfig>,
+    /// Rejects the endpoints which publish objects or change the node's behaviour.
+    pub read_only: bool,
+}
+
+/// The PEM files of the server's certificate chain and PKCS #8 private key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: Ipv4Addr::LOCALHOST.into(),
+            port: 5052,
+            allow_origins: vec![],
+            tls: None,
+            read_only: false,
+        }
+    }
+}
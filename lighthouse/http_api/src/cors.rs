@@ -0,0 +1,150 @@
+use hyper::header::{
+    HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+    VARY,
+};
+use hyper::{Method, Request, Response, StatusCode};
+
+const ALLOW_METHODS: &str = "GET, POST, DELETE, OPTIONS";
+const ALLOW_HEADERS: &str = "Accept, Authorization, Content-Type";
+/// How long browsers may cache a preflight response, in seconds.
+const MAX_AGE: &str = "3600";
+
+/// Allows browsers to make requests from the configured origins.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    allow_origins: Vec<String>,
+}
+
+impl Cors {
+    pub fn new(allow_origins: Vec<String>) -> Self {
+        Self { allow_origins }
+    }
+
+    /// Returns the response to `req` if it is a preflight request.
+    pub fn preflight<B>(&self, req: &Request<B>) -> Option<Response<Vec<u8>>> {
+        if req.method() != Method::OPTIONS
+            || !req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            return None;
+        }
+        let mut response = Response::new(vec![]);
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        if self.allow_origin(req).is_some() {
+            let headers = response.headers_mut();
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static(ALLOW_METHODS),
+            );
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                HeaderValue::from_static(ALLOW_HEADERS),
+            );
+            headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(MAX_AGE));
+        }
+        self.apply(req, &mut response);
+        Some(response)
+    }
+
+    /// Adds the CORS headers for `req` to `response`.
+    pub fn apply<B, C>(&self, req: &Request<B>, response: &mut Response<C>) {
+        if self.allow_origins.is_empty() {
+            return;
+        }
+        let headers = response.headers_mut();
+        headers.insert(VARY, HeaderValue::from_static("Origin"));
+        if let Some(origin) = self.allow_origin(req) {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        }
+    }
+
+    /// Returns the allowed origin of `req`, if its origin is allowed.
+    fn allow_origin<B>(&self, req: &Request<B>) -> Option<HeaderValue> {
+        let origin = req.headers().get(ORIGIN)?;
+        if self.allow_origins.iter().any(|o| o == "*") {
+            Some(HeaderValue::from_static("*"))
+        } else if self
+            .allow_origins
+            .iter()
+            .any(|o| o.as_bytes() == origin.as_bytes())
+        {
+            Some(origin.clone())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, origin: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder();
+        builder.method(method).uri("/eth/v1/node/version");
+        if let Some(origin) = origin {
+            builder.header(ORIGIN, origin);
+        }
+        if method == "OPTIONS" {
+            builder.header(ACCESS_CONTROL_REQUEST_METHOD, "GET");
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn allowed_origin(cors: &Cors, req: &Request<()>) -> Option<String> {
+        let mut response = Response::new(());
+        cors.apply(req, &mut response);
+        response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|origin| origin.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_allow_origins() {
+        let cors = Cors::new(vec!["https://a.example".to_string()]);
+        let origin = Some("https://a.example");
+        assert_eq!(
+            allowed_origin(&cors, &request("GET", origin)),
+            Some("https://a.example".to_string())
+        );
+        assert_eq!(
+            allowed_origin(&cors, &request("GET", Some("https://b.example"))),
+            None
+        );
+        assert_eq!(allowed_origin(&cors, &request("GET", None)), None);
+
+        let any = Cors::new(vec!["*".to_string()]);
+        assert_eq!(
+            allowed_origin(&any, &request("GET", Some("https://b.example"))),
+            Some("*".to_string())
+        );
+        let none = Cors::new(vec![]);
+        assert_eq!(allowed_origin(&none, &request("GET", origin)), None);
+    }
+
+    #[test]
+    fn test_preflight() {
+        let cors = Cors::new(vec!["https://a.example".to_string()]);
+        assert!(cors
+            .preflight(&request("GET", Some("https://a.example")))
+            .is_none());
+
+        let response = cors
+            .preflight(&request("OPTIONS", Some("https://a.example")))
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_METHODS],
+            ALLOW_METHODS
+        );
+
+        let response = cors
+            .preflight(&request("OPTIONS", Some("https://b.example")))
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(ACCESS_CONTROL_ALLOW_METHODS));
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
@@ -0,0 +1,160 @@
+use super::beacon::known_blocks;
+use super::encoding::Encoding;
+use super::error::ApiResult;
+use super::json::{active_state_json, data_response, hex_bytes};
+use super::state::regenerated_state;
+use super::Context;
+use db::ClientDB;
+use serde_json::Value;
+
+/// `GET /eth/v1/debug/beacon/heads`
+///
+/// Lists the tips of every known chain, from which fork choice picks the head.
+pub fn get_heads<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let mut heads = vec![];
+    for root in node.heads() {
+        let block = node.block(root)?;
+        heads.push(json!({
+            "root": hex_bytes(root),
+            "slot": block.slot.to_string(),
+        }));
+    }
+    Ok(data_response(Value::Array(heads)))
+}
+
+/// `GET /eth/v1/debug/beacon/states/{state_id}`
+///
+/// Returns the whole active state, which is regenerated from the blocks if not cached. Clients
+/// downloading states should ask for SSZ, as the JSON of a large state is slow to serialize.
+pub fn get_state<T: ClientDB>(ctx: &Context<T>, state_id: &str, encoding: Encoding) -> ApiResult {
+    let state = regenerated_state(ctx, state_id)?;
+    Ok(encoding.respond(&state.active_state, active_state_json))
+}
+
+/// `GET /eth/v1/debug/fork_choice`
+///
+/// Dumps every block after finality, with its ancestors, as seen by fork choice. The naive fork
+/// choice gives blocks no weight: the head is the tip with the highest slot, with ties broken by
+/// the lowest root.
+pub fn get_fork_choice<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (_, head_root) = node.head();
+    let finalized_root = node.finalized_root();
+    let finalized_slot = node.block(&finalized_root)?.slot;
+
+    let nodes: Vec<Value> = known_blocks(&node, finalized_slot)?
+        .iter()
+        .map(|(root, block)| {
+            json!({
+                "slot": block.slot.to_string(),
+                "block_root": hex_bytes(root),
+                "parent_root": block.parent_hash().map(|hash| hex_bytes(hash)),
+                "ancestor_hashes": block
+                    .ancestor_hashes
+                    .iter()
+                    .map(|hash| hex_bytes(hash))
+                    .collect::<Vec<String>>(),
+                "is_tip": node.heads().contains(root),
+                "is_head": *root == head_root,
+            })
+        })
+        .collect();
+    Ok(data_response(json!({
+        "fork_choice": "naive",
+        "head_root": hex_bytes(&head_root),
+        "justified_root": hex_bytes(&finalized_root),
+        "finalized_root": hex_bytes(&finalized_root),
+        "fork_choice_nodes": nodes,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::handle;
+    use super::super::router::tests::{context, get, import_block};
+    use super::super::state::tests::enable_regen;
+    use super::*;
+    use hyper::header::{ACCEPT, CONTENT_TYPE};
+    use hyper::{Request, StatusCode};
+    use ssz::Decodable;
+    use types::{ActiveState, Hash256};
+
+    #[test]
+    fn test_heads_and_fork_choice() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let first = import_block(&ctx, genesis, 1);
+        let fork = import_block(&ctx, genesis, 2);
+        let head = import_block(&ctx, first, 3);
+
+        let (status, body) = get(&ctx, "/eth/v1/debug/beacon/heads");
+        assert_eq!(status, StatusCode::OK);
+        let mut heads: Vec<(String, String)> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|h| {
+                (
+                    h["slot"].as_str().unwrap().to_string(),
+                    h["root"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        heads.sort();
+        assert_eq!(
+            heads,
+            vec![
+                ("2".to_string(), hex_bytes(&fork)),
+                ("3".to_string(), hex_bytes(&head))
+            ]
+        );
+
+        let (status, body) = get(&ctx, "/eth/v1/debug/fork_choice");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["head_root"], hex_bytes(&head));
+        let nodes = body["data"]["fork_choice_nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[0]["block_root"], hex_bytes(&genesis));
+        assert_eq!(nodes[0]["parent_root"], Value::Null);
+        let head_node = nodes.iter().find(|n| n["is_head"] == true).unwrap();
+        assert_eq!(head_node["parent_root"], hex_bytes(&first));
+        assert_eq!(head_node["is_tip"], true);
+        assert_eq!(nodes.iter().filter(|n| n["is_tip"] == true).count(), 2);
+    }
+
+    #[test]
+    fn test_get_state() {
+        let mut ctx = context();
+        let (status, _) = get(&ctx, "/eth/v1/debug/beacon/states/head");
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        enable_regen(&mut ctx);
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        import_block(&ctx, genesis, 1);
+
+        let (status, body) = get(&ctx, "/eth/v1/debug/beacon/states/head");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["randao_mix"], hex_bytes(&Hash256::zero()));
+        assert!(body["data"]["recent_block_hashes"].is_array());
+
+        let req = Request::get("/eth/v1/debug/beacon/states/head")
+            .header(ACCEPT, "application/json;q=0.5, application/octet-stream")
+            .body(vec![])
+            .unwrap();
+        let response = handle(&ctx, &req);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/octet-stream");
+        let (state, _) = ActiveState::ssz_decode(response.body(), 0).unwrap();
+        assert_eq!(state.randao_mix, Hash256::zero());
+        assert_eq!(
+            body["data"]["recent_block_hashes"]
+                .as_array()
+                .unwrap()
+                .len(),
+            state.recent_block_hashes.len()
+        );
+
+        let (status, _) = get(&ctx, "/eth/v1/debug/beacon/states/7");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
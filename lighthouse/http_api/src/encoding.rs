@@ -0,0 +1,183 @@
+use super::error::ApiError;
+use super::json::{data_response, ssz_response};
+use hyper::header::{ACCEPT, CONTENT_TYPE};
+use hyper::{Request, Response};
+use serde_json::Value;
+use ssz::{ssz_encode, Encodable};
+
+pub const JSON_MEDIA_TYPE: &str = "application/json";
+pub const SSZ_MEDIA_TYPE: &str = "application/octet-stream";
+
+/// The encoding of a response body.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Encoding {
+    Json,
+    Ssz,
+}
+
+impl Encoding {
+    /// Returns `item` as SSZ, or as `{"data": json(item)}`.
+    pub fn respond<E, F>(self, item: &E, json: F) -> Response<Vec<u8>>
+    where
+        E: Encodable,
+        F: FnOnce(&E) -> Value,
+    {
+        match self {
+            Encoding::Ssz => ssz_response(ssz_encode(item)),
+            Encoding::Json => data_response(json(item)),
+        }
+    }
+}
+
+/// The encodings a client accepts, with their quality, as given by the `Accept` header of its
+/// request.
+///
+/// Without an `Accept` header, any encoding is accepted. A quality of zero means the encoding is
+/// not accepted.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Accept {
+    json: f32,
+    ssz: f32,
+}
+
+impl Accept {
+    pub fn parse<B>(req: &Request<B>) -> Self {
+        match req.headers().get(ACCEPT).map(|accept| accept.to_str()) {
+            Some(Ok(accept)) => Self::parse_header(accept),
+            _ => Self {
+                json: 1.0,
+                ssz: 1.0,
+            },
+        }
+    }
+
+    /// Parses a list of media ranges, e.g. `application/octet-stream;q=1, application/json;q=0.9`.
+    /// The most specific range matching an encoding gives its quality.
+    fn parse_header(accept: &str) -> Self {
+        let (mut json, mut ssz): ((u8, f32), (u8, f32)) = ((0, 0.0), (0, 0.0));
+        for range in accept.split(',') {
+            let mut params = range.split(';').map(str::trim);
+            let media_type = params.next().unwrap_or("").to_lowercase();
+            let quality = params
+                .filter_map(|param| {
+                    let mut kv = param.splitn(2, '=').map(str::trim);
+                    match (kv.next(), kv.next()) {
+                        (Some("q"), Some(q)) => q.parse::<f32>().ok(),
+                        _ => None,
+                    }
+                })
+                .next()
+                .unwrap_or(1.0)
+                .max(0.0)
+                .min(1.0);
+            let specificity = |full: &str| match media_type.as_str() {
+                t if t == full => 3,
+                "application/*" => 2,
+                "*/*" => 1,
+                _ => 0,
+            };
+            for (best, full) in vec![(&mut json, JSON_MEDIA_TYPE), (&mut ssz, SSZ_MEDIA_TYPE)] {
+                if specificity(full) > best.0 {
+                    *best = (specificity(full), quality);
+                }
+            }
+        }
+        Self {
+            json: json.1,
+            ssz: ssz.1,
+        }
+    }
+
+    pub fn accepts(&self, encoding: Encoding) -> bool {
+        match encoding {
+            Encoding::Json => self.json > 0.0,
+            Encoding::Ssz => self.ssz > 0.0,
+        }
+    }
+
+    /// Returns the encoding of the response of an endpoint serving both JSON and SSZ.
+    ///
+    /// SSZ is only chosen if the client prefers it, so that clients accepting anything get JSON.
+    pub fn preferred(&self) -> Result<Encoding, ApiError> {
+        if self.ssz > self.json {
+            Ok(Encoding::Ssz)
+        } else if self.accepts(Encoding::Json) {
+            Ok(Encoding::Json)
+        } else {
+            Err(not_acceptable())
+        }
+    }
+
+    /// Checks that the client accepts `response`, for endpoints serving a single encoding.
+    pub fn check(&self, response: &Response<Vec<u8>>) -> Result<(), ApiError> {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|t| t.to_str().ok());
+        let encoding = match content_type {
+            Some(t) if t.starts_with(JSON_MEDIA_TYPE) => Encoding::Json,
+            Some(t) if t.starts_with(SSZ_MEDIA_TYPE) => Encoding::Ssz,
+            _ => return Ok(()),
+        };
+        if self.accepts(encoding) {
+            Ok(())
+        } else {
+            Err(not_acceptable())
+        }
+    }
+}
+
+fn not_acceptable() -> ApiError {
+    ApiError::NotAcceptable(format!(
+        "Only {} and {} are supported",
+        JSON_MEDIA_TYPE, SSZ_MEDIA_TYPE
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(header: &str) -> Accept {
+        let req = Request::get("/").header(ACCEPT, header).body(()).unwrap();
+        Accept::parse(&req)
+    }
+
+    #[test]
+    fn test_negotiation() {
+        let any = Accept::parse(&Request::get("/").body(()).unwrap());
+        assert_eq!(any.preferred(), Ok(Encoding::Json));
+        assert!(any.accepts(Encoding::Ssz));
+
+        assert_eq!(
+            accept("application/octet-stream").preferred(),
+            Ok(Encoding::Ssz)
+        );
+        assert!(!accept("application/octet-stream").accepts(Encoding::Json));
+        assert_eq!(accept("application/json").preferred(), Ok(Encoding::Json));
+        assert_eq!(accept("*/*").preferred(), Ok(Encoding::Json));
+
+        /*
+         * Qualities decide between the encodings, with specific ranges overriding wildcards.
+         */
+        let header = "application/json;q=0.9, application/octet-stream";
+        assert_eq!(accept(header).preferred(), Ok(Encoding::Ssz));
+        let header = "application/octet-stream; q=0.5, application/json";
+        assert_eq!(accept(header).preferred(), Ok(Encoding::Json));
+        let header = "application/*;q=0.2, application/octet-stream";
+        assert_eq!(accept(header).preferred(), Ok(Encoding::Ssz));
+        assert!(accept(header).accepts(Encoding::Json));
+        let header = "*/*, application/json;q=0";
+        assert!(!accept(header).accepts(Encoding::Json));
+        assert_eq!(accept(header).preferred(), Ok(Encoding::Ssz));
+
+        let header = "text/html, application/json;q=0";
+        assert!(accept(header).preferred().is_err());
+        let json = data_response(json!({}));
+        assert!(accept("application/json").check(&json).is_ok());
+        assert!(accept("application/octet-stream").check(&json).is_err());
+        assert!(accept("application/octet-stream")
+            .check(&ssz_response(vec![]))
+            .is_ok());
+    }
+}
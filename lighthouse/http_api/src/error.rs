@@ -0,0 +1,101 @@
+use beacon_node::BeaconNodeError;
+use db::stores::BeaconBlockAtSlotError;
+use db::DBError;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Response, StatusCode};
+
+pub type ApiResult = Result<Response<Vec<u8>>, ApiError>;
+
+#[derive(Debug, PartialEq)]
+pub enum ApiError {
+    /// The request is malformed, e.g., an identifier or query parameter cannot be parsed.
+    BadRequest(String),
+    /// Some of the objects in a request are invalid, listed with their indices.
+    IndexedBadRequest(String, Vec<(usize, String)>),
+    /// The request lacks a valid API token.
+    Unauthorized(String),
+    /// The endpoint is disabled.
+    Forbidden(String),
+    NotFound(String),
+    /// The client accepts none of the encodings the endpoint responds with.
+    NotAcceptable(String),
+    /// The request body exceeds `MAX_REQUEST_BODY_SIZE`.
+    PayloadTooLarge(String),
+    ServerError(String),
+    /// The node cannot serve the request for now, e.g. block import is halted.
+    ServiceUnavailable(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::IndexedBadRequest(..) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::NotAcceptable(_) => StatusCode::NOT_ACCEPTABLE,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::ServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// Returns the error as a response with a JSON body of the form
+    /// `{"code": 404, "message": "..."}`, with `"failures": [{"index": 0, "message": "..."}]` for
+    /// indexed errors.
+    pub fn into_response(self) -> Response<Vec<u8>> {
+        let status = self.status();
+        let body = match self {
+            ApiError::IndexedBadRequest(message, failures) => json!({
+                "code": status.as_u16(),
+                "message": message,
+                "failures": failures
+                    .into_iter()
+                    .map(|(index, message)| json!({ "index": index, "message": message }))
+                    .collect::<Vec<_>>(),
+            }),
+            ApiError::BadRequest(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::NotAcceptable(message)
+            | ApiError::PayloadTooLarge(message)
+            | ApiError::ServerError(message)
+            | ApiError::ServiceUnavailable(message) => json!({
+                "code": status.as_u16(),
+                "message": message,
+            }),
+        };
+        Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string().into_bytes())
+            .expect("Error response is valid")
+    }
+}
+
+impl From<BeaconNodeError> for ApiError {
+    fn from(e: BeaconNodeError) -> ApiError {
+        match e {
+            BeaconNodeError::BlockImportHalted(reason) => {
+                ApiError::ServiceUnavailable(format!("Block import halted: {}", reason))
+            }
+            BeaconNodeError::CycleOutOfRange => {
+                ApiError::BadRequest("Cycle out of range".to_string())
+            }
+            e => ApiError::ServerError(format!("{:?}", e)),
+        }
+    }
+}
+
+impl From<BeaconBlockAtSlotError> for ApiError {
+    fn from(e: BeaconBlockAtSlotError) -> ApiError {
+        ApiError::ServerError(format!("{:?}", e))
+    }
+}
+
+impl From<DBError> for ApiError {
+    fn from(e: DBError) -> ApiError {
+        ApiError::ServerError(e.message)
+    }
+}
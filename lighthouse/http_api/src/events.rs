@@ -0,0 +1,286 @@
+use super::error::ApiError;
+use super::json::{attestation_json, hex_bytes, proposer_slashing_json, registry_delta_json};
+use super::query::Query;
+use super::Context;
+use beacon_node::BeaconNodeEvent;
+use db::ClientDB;
+use futures::sync::mpsc;
+use futures::{Future, Sink, Stream};
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::{Body, Chunk, Response};
+use serde_json::Value;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const TOPICS: [&str; 7] = [
+    "head",
+    "block",
+    "attestation",
+    "finalized_checkpoint",
+    "chain_reorg",
+    "proposer_slashing",
+    "registry_delta",
+];
+
+/// How long a stream may be idle before a comment is sent, so that proxies keep it open.
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often each stream checks whether the server is stopping.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The number of events buffered for a client which is slow to read them.
+const STREAM_BUFFER: usize = 16;
+
+/// The maximum number of streams open at once, as each has its own thread.
+pub const MAX_EVENT_STREAMS: usize = 32;
+
+/// `GET /eth/v1/events?topics`
+///
+/// Streams the events of the given `topics` as server-sent events until the client disconnects.
+/// Each stream is fed from the node's event bus by its own task of the node's executor, so
+/// requests beyond `MAX_EVENT_STREAMS` open streams are refused.
+pub fn get_events<T: ClientDB>(
+    ctx: &Context<T>,
+    query: &Query,
+) -> Result<Response<Body>, ApiError> {
+    let topics: Vec<String> = query
+        .get_all("topics")
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+    if topics.is_empty() {
+        return Err(ApiError::BadRequest("Missing query: topics".to_string()));
+    }
+    for topic in &topics {
+        if !TOPICS.contains(&topic.as_str()) {
+            return Err(ApiError::BadRequest(format!("Invalid topic: {}", topic)));
+        }
+    }
+
+    let slot = StreamSlot::acquire(&ctx.event_streams).ok_or_else(|| {
+        ApiError::ServiceUnavailable(format!(
+            "Too many event streams, at most {} are served",
+            MAX_EVENT_STREAMS
+        ))
+    })?;
+    let events = ctx
+        .node
+        .read()
+        .expect("Beacon node lock poisoned")
+        .events()
+        .subscribe();
+    let closing = ctx.closing.clone();
+    let (mut tx, rx) = mpsc::channel(STREAM_BUFFER);
+    ctx.executor.spawn("event_stream", move |exit| {
+        let _slot = slot;
+        let mut last_sent = Instant::now();
+        while !closing.load(Ordering::Relaxed) && !exit.is_exiting() {
+            let frame = match events.recv_timeout(SHUTDOWN_POLL_INTERVAL) {
+                Ok(event) => {
+                    let (topic, data) = event_json(&event);
+                    if !topics.iter().any(|t| t == topic) {
+                        continue;
+                    }
+                    format!("event: {}\ndata: {}\n\n", topic, data)
+                }
+                Err(RecvTimeoutError::Timeout) if last_sent.elapsed() >= KEEP_ALIVE_INTERVAL => {
+                    ": keep-alive\n\n".to_string()
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            /*
+             * Sending fails once the client disconnects, which ends the stream and its
+             * subscription.
+             */
+            tx = match tx.send(Chunk::from(frame)).wait() {
+                Ok(tx) => tx,
+                Err(_) => break,
+            };
+            last_sent = Instant::now();
+        }
+    });
+
+    // The receiver never fails, but the body requires an error type.
+    let body = rx.map_err(|()| io::Error::from(io::ErrorKind::BrokenPipe));
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::wrap_stream(body))
+        .expect("Event stream response is valid"))
+}
+
+/// An open stream, counted against `MAX_EVENT_STREAMS` until dropped.
+struct StreamSlot(Arc<AtomicUsize>);
+
+impl StreamSlot {
+    /// Returns `None` if `MAX_EVENT_STREAMS` streams are already open.
+    fn acquire(streams: &Arc<AtomicUsize>) -> Option<Self> {
+        if streams.fetch_add(1, Ordering::SeqCst) >= MAX_EVENT_STREAMS {
+            streams.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(StreamSlot(streams.clone()))
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns the topic of `event` and its data.
+fn event_json(event: &BeaconNodeEvent) -> (&'static str, Value) {
+    match event {
+        BeaconNodeEvent::Head {
+            slot,
+            root,
+            state_root,
+            cycle_transition,
+        } => (
+            "head",
+            json!({
+                "slot": slot.to_string(),
+                "block": hex_bytes(root),
+                "state": hex_bytes(state_root),
+                "epoch_transition": cycle_transition,
+                "execution_optimistic": false,
+            }),
+        ),
+        BeaconNodeEvent::Block { slot, root, .. } => (
+            "block",
+            json!({
+                "slot": slot.to_string(),
+                "block": hex_bytes(root),
+                "execution_optimistic": false,
+            }),
+        ),
+        BeaconNodeEvent::Attestation { attestation, .. } => {
+            ("attestation", attestation_json(attestation))
+        }
+        BeaconNodeEvent::ChainReorg {
+            slot,
+            depth,
+            old_head_root,
+            new_head_root,
+            old_head_state_root,
+            new_head_state_root,
+        } => (
+            "chain_reorg",
+            json!({
+                "slot": slot.to_string(),
+                "depth": depth.to_string(),
+                "old_head_block": hex_bytes(old_head_root),
+                "new_head_block": hex_bytes(new_head_root),
+                "old_head_state": hex_bytes(old_head_state_root),
+                "new_head_state": hex_bytes(new_head_state_root),
+                "execution_optimistic": false,
+            }),
+        ),
+        BeaconNodeEvent::ProposerSlashing(slashing) => {
+            ("proposer_slashing", proposer_slashing_json(slashing))
+        }
+        BeaconNodeEvent::RegistryDelta(delta) => ("registry_delta", registry_delta_json(delta)),
+        BeaconNodeEvent::FinalizedCheckpoint {
+            root,
+            state_root,
+            cycle,
+        } => (
+            "finalized_checkpoint",
+            json!({
+                "block": hex_bytes(root),
+                "state": hex_bytes(state_root),
+                "epoch": cycle.to_string(),
+                "execution_optimistic": false,
+            }),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::serve;
+    use super::super::router::tests::{context, import_block};
+    use super::*;
+    use hyper::{Request, StatusCode};
+    use std::thread;
+
+    fn serve_status(ctx: &Context<db::MemoryDB>, uri: &str) -> StatusCode {
+        serve(ctx, &Request::get(uri).body(vec![]).unwrap()).status()
+    }
+
+    #[test]
+    fn test_invalid_topics() {
+        let ctx = context();
+        assert_eq!(
+            serve_status(&ctx, "/eth/v1/events"),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            serve_status(&ctx, "/eth/v1/events?topics=head,voluntary_exit"),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            serve_status(&ctx, "/eth/v1/events?topics=head,block"),
+            StatusCode::OK
+        );
+        assert_eq!(
+            serve_status(&ctx, "/eth/v1/events?topics=attestation"),
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn test_streams_events_of_topics() {
+        let ctx = context();
+        let query = Query::parse(Some("topics=head"));
+        let response = get_events(&ctx, &query).unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/event-stream");
+
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let root = import_block(&ctx, genesis, 1);
+        let (chunk, body) = response.into_body().into_future().wait().ok().unwrap();
+        let frame = String::from_utf8(chunk.unwrap().to_vec()).unwrap();
+        assert!(frame.starts_with("event: head\ndata: "));
+        assert!(frame.contains(&hex_bytes(&root)));
+        assert!(frame.ends_with("\n\n"));
+
+        /*
+         * The stream ends when the server stops.
+         */
+        ctx.closing.store(true, Ordering::Relaxed);
+        let (chunk, _) = body.into_future().wait().ok().unwrap();
+        assert!(chunk.is_none());
+    }
+
+    #[test]
+    fn test_stream_limit() {
+        let ctx = context();
+        let query = Query::parse(Some("topics=head"));
+        let streams: Vec<_> = (0..MAX_EVENT_STREAMS)
+            .map(|_| get_events(&ctx, &query).unwrap())
+            .collect();
+        assert_eq!(
+            serve_status(&ctx, "/eth/v1/events?topics=head"),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        /*
+         * The streams are released once they end.
+         */
+        ctx.closing.store(true, Ordering::Relaxed);
+        for stream in streams {
+            let (chunk, _) = stream.into_body().into_future().wait().ok().unwrap();
+            assert!(chunk.is_none());
+        }
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while ctx.event_streams.load(Ordering::SeqCst) > 0 {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
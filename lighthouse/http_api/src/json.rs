@@ -0,0 +1,403 @@
+use super::encoding::{JSON_MEDIA_TYPE, SSZ_MEDIA_TYPE};
+use super::error::ApiError;
+use beacon_node::{BlockReward, ProposerSlashing, RegistryDelta};
+use bls::{AggregateSignature, Signature};
+use hex;
+use hyper::header::CONTENT_TYPE;
+use hyper::Response;
+use serde_json::Value;
+use types::{
+    ActiveState, AggregateAndProof, Attestation, AttestationData, BeaconBlock, Bitfield, Hash256,
+    ShardAndCommittee, SpecialRecord, ValidatorRecord, ValidatorStatus,
+};
+
+/// Returns `bytes` as a `0x`-prefixed hex string.
+pub fn hex_bytes(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Parses a `0x`-prefixed hex string.
+pub fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.starts_with("0x") {
+        return None;
+    }
+    hex::decode(&s[2..]).ok()
+}
+
+/// Parses a `0x`-prefixed, 32-byte hex string.
+pub fn parse_hash(s: &str) -> Result<Hash256, ApiError> {
+    match parse_hex(s) {
+        Some(ref bytes) if bytes.len() == 32 => Ok(Hash256::from(&bytes[..])),
+        _ => Err(ApiError::BadRequest(format!("Invalid root: {}", s))),
+    }
+}
+
+/// Returns a response with `data` wrapped as `{"data": data}`.
+pub fn data_response(data: Value) -> Response<Vec<u8>> {
+    json_response(&json!({ "data": data }))
+}
+
+/// Returns a page of results as `{"data": items, "meta": {"total": total}}`, where `total` is the
+/// number of results across all pages.
+pub fn page_response(items: Vec<Value>, total: usize) -> Response<Vec<u8>> {
+    json_response(&json!({
+        "data": items,
+        "meta": { "total": total },
+    }))
+}
+
+pub fn json_response(body: &Value) -> Response<Vec<u8>> {
+    Response::builder()
+        .header(CONTENT_TYPE, JSON_MEDIA_TYPE)
+        .body(body.to_string().into_bytes())
+        .expect("JSON response is valid")
+}
+
+pub fn ssz_response(ssz: Vec<u8>) -> Response<Vec<u8>> {
+    Response::builder()
+        .header(CONTENT_TYPE, SSZ_MEDIA_TYPE)
+        .body(ssz)
+        .expect("SSZ response is valid")
+}
+
+/*
+ * Integers are quoted, as JSON numbers cannot represent every u64.
+ */
+
+pub fn block_json(block: &BeaconBlock) -> Value {
+    json!({
+        "slot": block.slot.to_string(),
+        "parent_root": block.parent_hash().map(|hash| hex_bytes(hash)),
+        "randao_reveal": hex_bytes(&block.randao_reveal),
+        "pow_chain_reference": hex_bytes(&block.pow_chain_reference),
+        "ancestor_hashes": block
+            .ancestor_hashes
+            .iter()
+            .map(|hash| hex_bytes(hash))
+            .collect::<Vec<String>>(),
+        "active_state_root": hex_bytes(&block.active_state_root),
+        "crystallized_state_root": hex_bytes(&block.crystallized_state_root),
+        "attestations": block.attestations.iter().map(attestation_json).collect::<Vec<Value>>(),
+        "specials": block.specials.iter().map(special_json).collect::<Vec<Value>>(),
+        "graffiti": hex_bytes(&block.graffiti),
+    })
+}
+
+/// Returns the fields of `block` without its attestations and specials, which are committed to
+/// by `body_root`.
+pub fn header_json(block: &BeaconBlock, body_root: &Hash256) -> Value {
+    json!({
+        "slot": block.slot.to_string(),
+        "parent_root": block.parent_hash().map(|hash| hex_bytes(hash)),
+        "active_state_root": hex_bytes(&block.active_state_root),
+        "crystallized_state_root": hex_bytes(&block.crystallized_state_root),
+        "body_root": hex_bytes(body_root),
+    })
+}
+
+pub fn attestation_json(attestation: &Attestation) -> Value {
+    json!({
+        "data": attestation_data_json(&attestation.data),
+        "participation_bitfield": hex_bytes(&attestation.participation_bitfield.to_bytes()),
+        "custody_bitfield": hex_bytes(&attestation.custody_bitfield.to_bytes()),
+        "aggregate_sig": hex_bytes(&attestation.aggregate_sig.as_bytes()),
+    })
+}
+
+pub fn attestation_data_json(data: &AttestationData) -> Value {
+    json!({
+        "slot": data.slot.to_string(),
+        "shard": data.shard.to_string(),
+        "beacon_block_hash": hex_bytes(&data.beacon_block_hash),
+        "epoch_boundary_hash": hex_bytes(&data.epoch_boundary_hash),
+        "shard_block_hash": hex_bytes(&data.shard_block_hash),
+        "latest_crosslink_hash": hex_bytes(&data.latest_crosslink_hash),
+        "justified_slot": data.justified_slot.to_string(),
+        "justified_block_hash": hex_bytes(&data.justified_block_hash),
+    })
+}
+
+pub fn validator_json(index: usize, validator: &ValidatorRecord) -> Value {
+    json!({
+        "index": index.to_string(),
+        "balance": validator.balance.to_string(),
+        "status": validator_status_name(validator.status),
+        "validator": {
+            "pubkey": hex_bytes(&validator.pubkey.as_bytes()),
+            "withdrawal_shard": validator.withdrawal_shard.to_string(),
+            "withdrawal_address": hex_bytes(&validator.withdrawal_address),
+            "randao_commitment": hex_bytes(&validator.randao_commitment),
+            "randao_last_change": validator.randao_last_change.to_string(),
+            "exit_slot": validator.exit_slot.to_string(),
+        },
+    })
+}
+
+pub fn committee_json(slot: u64, committee: &ShardAndCommittee) -> Value {
+    json!({
+        "slot": slot.to_string(),
+        "shard": committee.shard.to_string(),
+        "validators": committee
+            .committee
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>(),
+    })
+}
+
+/// Returns the name of a `ValidatorStatus`, as used in requests and responses.
+pub fn validator_status_name(status: u8) -> &'static str {
+    match status {
+        s if s == ValidatorStatus::PendingActivation as u8 => "pending_activation",
+        s if s == ValidatorStatus::Active as u8 => "active",
+        s if s == ValidatorStatus::PendingExit as u8 => "pending_exit",
+        s if s == ValidatorStatus::PendingWithdraw as u8 => "pending_withdraw",
+        s if s == ValidatorStatus::Withdrawn as u8 => "withdrawn",
+        s if s == ValidatorStatus::Penalized as u8 => "penalized",
+        _ => "unknown",
+    }
+}
+
+pub fn active_state_json(state: &ActiveState) -> Value {
+    json!({
+        "pending_attestations": state
+            .pending_attestations
+            .iter()
+            .map(attestation_json)
+            .collect::<Vec<Value>>(),
+        "pending_specials": state.pending_specials.iter().map(special_json).collect::<Vec<Value>>(),
+        "recent_block_hashes": state
+            .recent_block_hashes
+            .iter()
+            .map(|hash| hex_bytes(hash))
+            .collect::<Vec<String>>(),
+        "randao_mix": hex_bytes(&state.randao_mix),
+    })
+}
+
+pub fn special_json(special: &SpecialRecord) -> Value {
+    json!({
+        "kind": special.kind,
+        "data": hex_bytes(&special.data),
+    })
+}
+
+pub fn proposer_slashing_json(slashing: &ProposerSlashing) -> Value {
+    json!({
+        "proposer_index": slashing.proposer_index.to_string(),
+        "slot": slashing.slot.to_string(),
+        "block_root_1": hex_bytes(&slashing.block_root_1),
+        "block_root_2": hex_bytes(&slashing.block_root_2),
+        "verified": false,
+    })
+}
+
+pub fn registry_delta_json(delta: &RegistryDelta) -> Value {
+    let indices =
+        |indices: &[usize]| -> Vec<String> { indices.iter().map(|i| i.to_string()).collect() };
+    let top_ups: Vec<Value> = delta
+        .top_ups
+        .iter()
+        .map(|(index, amount)| {
+            json!({
+                "index": index.to_string(),
+                "amount_gwei": amount.to_string(),
+            })
+        })
+        .collect();
+    json!({
+        "epoch": delta.cycle.to_string(),
+        "deposits": indices(&delta.deposits),
+        "activations": indices(&delta.activations),
+        "exits": indices(&delta.exits),
+        "slashings": indices(&delta.slashings),
+        "top_ups": top_ups,
+    })
+}
+
+pub fn block_reward_json(reward: &BlockReward) -> Value {
+    let attestations: Vec<Value> = reward
+        .attestations
+        .iter()
+        .map(|a| {
+            json!({
+                "index": a.index.to_string(),
+                "slot": a.slot.to_string(),
+                "shard": a.shard.to_string(),
+                "participants": a.participants.to_string(),
+                "new_votes": a.new_votes.to_string(),
+                "reward_gwei": a.reward.to_string(),
+            })
+        })
+        .collect();
+    json!({
+        "slot": reward.slot.to_string(),
+        "root": hex_bytes(&reward.root),
+        "proposer_index": reward.proposer.map(|index| index.to_string()),
+        "total_gwei": reward.attestations_reward().to_string(),
+        "attestations_gwei": reward.attestations_reward().to_string(),
+        "redundant_votes": reward.redundant_votes().to_string(),
+        "attestations": attestations,
+    })
+}
+
+/*
+ * Objects published by clients are decoded from the same fields they are encoded to.
+ */
+
+pub fn block_from_json(value: &Value) -> Result<BeaconBlock, String> {
+    let ancestor_hashes = array_field(value, "ancestor_hashes")?
+        .iter()
+        .map(|hash| parse_hash(hash.as_str().unwrap_or("")).map_err(|_| invalid("ancestor_hashes")))
+        .collect::<Result<Vec<Hash256>, String>>()?;
+    let attestations = array_field(value, "attestations")?
+        .iter()
+        .map(attestation_from_json)
+        .collect::<Result<Vec<Attestation>, String>>()?;
+    let specials = array_field(value, "specials")?
+        .iter()
+        .map(|special| {
+            let kind = u64_field(special, "kind")?;
+            if kind > 0xff {
+                return Err(invalid("kind"));
+            }
+            Ok(SpecialRecord {
+                kind: kind as u8,
+                data: bytes_field(special, "data")?,
+            })
+        })
+        .collect::<Result<Vec<SpecialRecord>, String>>()?;
+
+    Ok(BeaconBlock {
+        slot: u64_field(value, "slot")?,
+        randao_reveal: hash_field(value, "randao_reveal")?,
+        pow_chain_reference: hash_field(value, "pow_chain_reference")?,
+        ancestor_hashes,
+        active_state_root: hash_field(value, "active_state_root")?,
+        crystallized_state_root: hash_field(value, "crystallized_state_root")?,
+        attestations,
+        specials,
+        graffiti: hash_field(value, "graffiti")?,
+    })
+}
+
+pub fn attestation_from_json(value: &Value) -> Result<Attestation, String> {
+    let data = value.get("data").ok_or_else(|| missing("data"))?;
+    let aggregate_sig = AggregateSignature::from_bytes(&bytes_field(value, "aggregate_sig")?)
+        .map_err(|_| invalid("aggregate_sig"))?;
+    Ok(Attestation {
+        data: AttestationData {
+            slot: u64_field(data, "slot")?,
+            shard: u64_field(data, "shard")?,
+            beacon_block_hash: hash_field(data, "beacon_block_hash")?,
+            epoch_boundary_hash: hash_field(data, "epoch_boundary_hash")?,
+            shard_block_hash: hash_field(data, "shard_block_hash")?,
+            latest_crosslink_hash: hash_field(data, "latest_crosslink_hash")?,
+            justified_slot: u64_field(data, "justified_slot")?,
+            justified_block_hash: hash_field(data, "justified_block_hash")?,
+        },
+        participation_bitfield: Bitfield::from_bytes(&bytes_field(
+            value,
+            "participation_bitfield",
+        )?),
+        custody_bitfield: Bitfield::from_bytes(&bytes_field(value, "custody_bitfield")?),
+        aggregate_sig,
+    })
+}
+
+pub fn aggregate_and_proof_from_json(value: &Value) -> Result<AggregateAndProof, String> {
+    let aggregate = value.get("aggregate").ok_or_else(|| missing("aggregate"))?;
+    let selection_proof = Signature::from_bytes(&bytes_field(value, "selection_proof")?)
+        .map_err(|_| invalid("selection_proof"))?;
+    Ok(AggregateAndProof {
+        aggregator_index: u64_field(value, "aggregator_index")?,
+        aggregate: attestation_from_json(aggregate)?,
+        selection_proof,
+    })
+}
+
+fn missing(name: &str) -> String {
+    format!("Missing field: {}", name)
+}
+
+fn invalid(name: &str) -> String {
+    format!("Invalid field: {}", name)
+}
+
+/// Reads an integer field, which may be quoted.
+fn u64_field(value: &Value, name: &str) -> Result<u64, String> {
+    match value.get(name) {
+        Some(Value::String(s)) => s.parse::<u64>().map_err(|_| invalid(name)),
+        Some(Value::Number(n)) => n.as_u64().ok_or_else(|| invalid(name)),
+        Some(_) => Err(invalid(name)),
+        None => Err(missing(name)),
+    }
+}
+
+fn bytes_field(value: &Value, name: &str) -> Result<Vec<u8>, String> {
+    let s = value
+        .get(name)
+        .ok_or_else(|| missing(name))?
+        .as_str()
+        .ok_or_else(|| invalid(name))?;
+    parse_hex(s).ok_or_else(|| invalid(name))
+}
+
+fn hash_field(value: &Value, name: &str) -> Result<Hash256, String> {
+    match bytes_field(value, name)? {
+        ref bytes if bytes.len() == 32 => Ok(Hash256::from(&bytes[..])),
+        _ => Err(invalid(name)),
+    }
+}
+
+fn array_field<'a>(value: &'a Value, name: &str) -> Result<&'a Vec<Value>, String> {
+    value
+        .get(name)
+        .ok_or_else(|| missing(name))?
+        .as_array()
+        .ok_or_else(|| invalid(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hash() {
+        let hash = Hash256::from(42);
+        assert_eq!(parse_hash(&hex_bytes(&hash)), Ok(hash));
+        assert!(parse_hash(&hex::encode(&hash[..])).is_err());
+        assert!(parse_hash("0x1234").is_err());
+        assert!(parse_hash("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_block_json_round_trip() {
+        let mut attestation = Attestation::zero();
+        attestation.data.slot = 3;
+        attestation.participation_bitfield = Bitfield::from_elem(8, false);
+        attestation.participation_bitfield.set(2, true);
+        let block = BeaconBlock {
+            slot: 4,
+            randao_reveal: Hash256::from(1),
+            ancestor_hashes: vec![Hash256::from(2), Hash256::from(3)],
+            attestations: vec![attestation],
+            specials: vec![SpecialRecord {
+                kind: 1,
+                data: vec![7, 8],
+            }],
+            graffiti: Hash256::from(9),
+            ..BeaconBlock::zero()
+        };
+        assert_eq!(block_from_json(&block_json(&block)), Ok(block.clone()));
+
+        let mut value = block_json(&block);
+        value["slot"] = json!(4);
+        assert_eq!(block_from_json(&value), Ok(block));
+        value["randao_reveal"] = json!("0x12");
+        assert_eq!(
+            block_from_json(&value),
+            Err("Invalid field: randao_reveal".to_string())
+        );
+    }
+}
@@ -0,0 +1,213 @@
+extern crate beacon_node;
+extern crate bls;
+extern crate db;
+extern crate futures;
+extern crate hashing;
+extern crate hex;
+extern crate hyper;
+extern crate hyper_tls;
+#[macro_use]
+extern crate lazy_static;
+extern crate lighthouse_metrics;
+extern crate merkle_proof;
+extern crate native_tls;
+extern crate network;
+extern crate process_metrics;
+extern crate rand;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+extern crate ssz;
+extern crate task_executor;
+extern crate tokio;
+extern crate tokio_tcp;
+extern crate tokio_tls;
+extern crate types;
+
+mod admin;
+mod beacon;
+mod block_id;
+mod config;
+mod cors;
+mod debug;
+mod encoding;
+mod error;
+mod events;
+mod json;
+mod light_client;
+mod metrics;
+mod monitor;
+mod monitoring;
+mod node;
+mod pool;
+mod proof;
+mod publish;
+mod query;
+mod registry;
+mod rewards;
+mod router;
+mod server;
+mod spec;
+mod state;
+mod validator;
+
+pub use admin::{load_or_create_token, API_TOKEN_FILE};
+pub use block_id::BlockId;
+pub use config::{ApiConfig, TlsConfig};
+pub use error::ApiError;
+pub use monitoring::{
+    summary, MonitoringConfig, MonitoringError, MonitoringService, DEFAULT_MONITORING_INTERVAL,
+};
+pub use publish::{publish_attestation, PubsubMessage};
+pub use router::handle;
+pub use server::{ApiServer, ApiServerError};
+pub use spec::GENESIS_FORK_VERSION;
+
+use beacon_node::{BeaconNode, StateRegenService, StateTransitionPool};
+use db::stores::GossipStore;
+use db::ClientDB;
+use network::enr::ATTESTATION_SUBNET_COUNT;
+use network::gossip::{
+    load_subnet_subscriptions, persist_subnet_subscriptions, DuplicateFilter,
+    GossipPersistenceError, VerificationCache,
+};
+use network::PeerManager;
+use slog::Logger;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+use task_executor::TaskExecutor;
+
+/// The components of the beacon node served by the API.
+pub struct Context<T: ClientDB> {
+    pub node: Arc<RwLock<BeaconNode<T>>>,
+    /// Receives the blocks and attestations to publish to peers, if networking is running.
+    pub network: Option<Mutex<Sender<PubsubMessage>>>,
+    /// The blocks and attestations already published, so that no duty is published twice.
+    pub duplicates: Mutex<DuplicateFilter>,
+    /// The attestation data and selection proofs of recent aggregates which passed verification.
+    pub verified_aggregates: Mutex<VerificationCache>,
+    /// The peers of the node, if networking is running.
+    pub peer_manager: Option<Arc<RwLock<PeerManager>>>,
+    /// The token required by the admin endpoints, which are disabled if `None`.
+    pub admin_token: Option<String>,
+    /// The directory to which heap profiles are written, which are disabled if `None`.
+    pub heap_profile_dir: Option<PathBuf>,
+    /// The attestation subnets requested by validator clients, each with the last slot for which
+    /// it is needed.
+    pub subnet_subscriptions: Mutex<BTreeMap<u64, u64>>,
+    /// The validators of the node which aggregate upcoming attestations, by slot and shard.
+    pub aggregator_duties: Mutex<BTreeMap<(u64, u64), BTreeSet<u64>>>,
+    /// Regenerates historical states for the state endpoints which need more than the block.
+    pub regen: Option<Arc<StateRegenService<T>>>,
+    /// Imports published blocks off the server's runtime, if set.
+    pub transition_pool: Option<Arc<StateTransitionPool>>,
+    /// Runs the thread feeding each event stream.
+    executor: TaskExecutor,
+    pub log: Logger,
+    /// Set when the server is stopping, to end open event streams.
+    closing: Arc<AtomicBool>,
+    /// The number of open event streams.
+    event_streams: Arc<AtomicUsize>,
+}
+
+impl<T: ClientDB> Context<T> {
+    pub fn new(
+        node: Arc<RwLock<BeaconNode<T>>>,
+        network: Option<Sender<PubsubMessage>>,
+        executor: TaskExecutor,
+        log: Logger,
+    ) -> Self {
+        Self {
+            node,
+            network: network.map(Mutex::new),
+            duplicates: Mutex::new(DuplicateFilter::default()),
+            verified_aggregates: Mutex::new(VerificationCache::default()),
+            peer_manager: None,
+            admin_token: None,
+            heap_profile_dir: None,
+            subnet_subscriptions: Mutex::new(BTreeMap::new()),
+            aggregator_duties: Mutex::new(BTreeMap::new()),
+            regen: None,
+            transition_pool: None,
+            executor,
+            log,
+            closing: Arc::new(AtomicBool::new(false)),
+            event_streams: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Returns the subnet of each upcoming aggregator duty, with the slot of the earliest duty
+    /// on the subnet.
+    pub fn aggregation_subnets(&self) -> BTreeMap<u64, u64> {
+        let mut subnets = BTreeMap::new();
+        let duties = self
+            .aggregator_duties
+            .lock()
+            .expect("Aggregator duties lock poisoned");
+        for &(slot, shard) in duties.keys() {
+            subnets
+                .entry(shard % ATTESTATION_SUBNET_COUNT as u64)
+                .or_insert(slot);
+        }
+        subnets
+    }
+
+    /// Writes the duties already published and the subnet subscriptions to `store`, so that a
+    /// node restarted soon after neither republishes duties nor forgets subscriptions.
+    pub fn persist_gossip<U: ClientDB>(
+        &self,
+        store: &GossipStore<U>,
+        now: Instant,
+    ) -> Result<(), GossipPersistenceError> {
+        self.duplicates
+            .lock()
+            .expect("Duplicate filter lock poisoned")
+            .persist(store, now)?;
+        let subscriptions = self
+            .subnet_subscriptions
+            .lock()
+            .expect("Subnet subscriptions lock poisoned");
+        persist_subnet_subscriptions(store, &subscriptions)
+    }
+
+    /// Restores the state written by `persist_gossip` which has not expired, before the API is
+    /// served.
+    pub fn load_gossip<U: ClientDB>(
+        &self,
+        store: &GossipStore<U>,
+        now: Instant,
+    ) -> Result<(), GossipPersistenceError> {
+        let present_slot = self
+            .node
+            .read()
+            .expect("Beacon node lock poisoned")
+            .present_slot();
+        let seen = self
+            .duplicates
+            .lock()
+            .expect("Duplicate filter lock poisoned")
+            .load(store, now)?;
+        let subscriptions = load_subnet_subscriptions(store, present_slot)?;
+        info!(self.log, "Restored gossip state"; "seen" => seen, "subnet_subscriptions" => subscriptions.len());
+        *self
+            .subnet_subscriptions
+            .lock()
+            .expect("Subnet subscriptions lock poisoned") = subscriptions;
+        Ok(())
+    }
+
+    /// Sends `message` to the network service, if it is running.
+    pub fn publish(&self, message: PubsubMessage) {
+        if let Some(ref network) = self.network {
+            let network = network.lock().expect("Network sender lock poisoned");
+            if network.send(message).is_err() {
+                warn!(self.log, "Unable to publish, network service stopped");
+            }
+        }
+    }
+}
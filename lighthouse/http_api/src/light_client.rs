@@ -0,0 +1,141 @@
+use super::encoding::Encoding;
+use super::error::{ApiError, ApiResult};
+use super::json::{data_response, hex_bytes, ssz_response};
+use super::query::Query;
+use super::Context;
+use beacon_node::{BeaconNodeError, LightClientUpdate};
+use db::ClientDB;
+use serde_json::Value;
+use ssz::SszStream;
+use types::Hash256;
+
+/// The most periods whose updates are returned in one request.
+pub const MAX_LIGHT_CLIENT_UPDATES: u64 = 128;
+
+/// `GET /eth/v1/beacon/light_client/updates?start_period,count`
+///
+/// Returns the update of the latest checkpoint finalized in each period from `start_period`, by
+/// ascending period, skipping periods in which nothing was finalized. Served as SSZ if the client
+/// prefers it.
+pub fn get_updates<T: ClientDB>(ctx: &Context<T>, query: &Query, encoding: Encoding) -> ApiResult {
+    let start_period = query.require::<u64>("start_period")?;
+    let count = query
+        .parse_value::<u64>("count")?
+        .unwrap_or(1)
+        .min(MAX_LIGHT_CLIENT_UPDATES);
+    if count == 0 {
+        return Err(ApiError::BadRequest("count must be positive".to_string()));
+    }
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let updates = match node.light_client_updates(start_period, count) {
+        Ok(updates) => updates,
+        Err(BeaconNodeError::MissingStore) => {
+            return Err(ApiError::Forbidden(
+                "Light client updates are not stored".to_string(),
+            ))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if encoding == Encoding::Ssz {
+        let mut stream = SszStream::new();
+        stream.append_vec(&updates);
+        return Ok(ssz_response(stream.drain()));
+    }
+    Ok(data_response(Value::Array(
+        updates.iter().map(update_json).collect(),
+    )))
+}
+
+fn update_json(update: &LightClientUpdate) -> Value {
+    let header = &update.finalized_header;
+    json!({
+        "period": update.period.to_string(),
+        "finalized_header": {
+            "slot": header.slot.to_string(),
+            "root": hex_bytes(&header.root),
+            "parent_root": hex_bytes(&header.parent_root),
+            "state_root": hex_bytes(&header.state_root),
+        },
+        "summary_root": hex_bytes(&update.summary_root),
+        "header_branch": branch_json(&update.header_branch),
+        "validators_root": hex_bytes(&update.validators_root),
+        "validators_branch": branch_json(&update.validators_branch),
+    })
+}
+
+fn branch_json(branch: &[Hash256]) -> Value {
+    Value::Array(
+        branch
+            .iter()
+            .map(|node| Value::from(hex_bytes(node)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::handle;
+    use super::super::router::tests::{context, get, import_block};
+    use super::*;
+    use beacon_node::LIGHT_CLIENT_PERIOD_CYCLES;
+    use db::stores::LightClientStore;
+    use db::MemoryDB;
+    use hyper::header::ACCEPT;
+    use hyper::{Request, StatusCode};
+    use ssz::{decode_ssz_list, ssz_encode};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_get_updates() {
+        let ctx = context();
+        let (status, _) = get(&ctx, "/eth/v1/beacon/light_client/updates?start_period=0");
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let root = import_block(&ctx, genesis, 3);
+        let update = {
+            let mut node = ctx.node.write().unwrap();
+            let store = LightClientStore::new(Arc::new(MemoryDB::open()));
+            let update = node
+                .light_client_update(root, LIGHT_CLIENT_PERIOD_CYCLES)
+                .unwrap();
+            store
+                .put_serialized_update(update.period, &ssz_encode(&update))
+                .unwrap();
+            node.store_light_client_updates(store);
+            update
+        };
+
+        let (status, body) = get(
+            &ctx,
+            "/eth/v1/beacon/light_client/updates?start_period=0&count=2",
+        );
+        assert_eq!(status, StatusCode::OK);
+        let updates = body["data"].as_array().unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0]["period"], "1");
+        assert_eq!(updates[0]["finalized_header"]["slot"], "3");
+        assert_eq!(updates[0]["finalized_header"]["root"], hex_bytes(&root));
+        assert_eq!(updates[0]["header_branch"].as_array().unwrap().len(), 3);
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/light_client/updates?start_period=0");
+        assert_eq!(body["data"].as_array().unwrap().len(), 0);
+
+        let req = Request::get("/eth/v1/beacon/light_client/updates?start_period=1")
+            .header(ACCEPT, "application/octet-stream")
+            .body(vec![])
+            .unwrap();
+        let response = handle(&ctx, &req);
+        let (decoded, _): (Vec<LightClientUpdate>, _) =
+            decode_ssz_list(response.body(), 0).unwrap();
+        assert_eq!(decoded, vec![update]);
+
+        let (status, _) = get(&ctx, "/eth/v1/beacon/light_client/updates");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(
+            &ctx,
+            "/eth/v1/beacon/light_client/updates?start_period=0&count=0",
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
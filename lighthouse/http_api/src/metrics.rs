@@ -0,0 +1,57 @@
+use hyper::header::CONTENT_TYPE;
+use hyper::Response;
+use lighthouse_metrics::{
+    encode_text, try_create_histogram, try_create_int_counter, Histogram, IntCounter, Result,
+    TEXT_CONTENT_TYPE,
+};
+use process_metrics;
+
+lazy_static! {
+    pub static ref HTTP_API_REQUESTS: Result<IntCounter> =
+        try_create_int_counter("http_api_requests_total", "Count of HTTP API requests");
+    pub static ref HTTP_API_REQUEST_TIMES: Result<Histogram> = try_create_histogram(
+        "http_api_request_seconds",
+        "Time taken to serve an HTTP API request"
+    );
+    pub static ref MONITORING_PUSHES: Result<IntCounter> = try_create_int_counter(
+        "monitoring_pushes_total",
+        "Count of summaries pushed to the monitoring endpoint"
+    );
+    pub static ref MONITORING_PUSH_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "monitoring_push_failures_total",
+        "Count of summaries which could not be pushed to the monitoring endpoint"
+    );
+}
+
+/// `GET /metrics`
+///
+/// Returns the metrics of every component of the node and of its process, for Prometheus to
+/// scrape.
+pub fn get_metrics() -> Response<Vec<u8>> {
+    process_metrics::scrape();
+    Response::builder()
+        .header(CONTENT_TYPE, TEXT_CONTENT_TYPE)
+        .body(encode_text())
+        .expect("Metrics response is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::handle;
+    use super::super::router::tests::{context, import_block};
+    use hyper::{Request, StatusCode};
+
+    #[test]
+    fn test_metrics() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        import_block(&ctx, genesis, 1);
+
+        let response = handle(&ctx, &Request::get("/metrics").body(vec![]).unwrap());
+        assert_eq!(response.status(), StatusCode::OK);
+        let text = String::from_utf8(response.body().clone()).unwrap();
+        assert!(text.contains("beacon_block_processing_successes_total"));
+        assert!(text.contains("http_api_requests_total"));
+        assert!(text.contains("process_resident_memory_bytes"));
+    }
+}
@@ -0,0 +1,99 @@
+use super::error::{ApiError, ApiResult};
+use super::json::{data_response, hex_bytes, validator_status_name};
+use super::Context;
+use beacon_node::{WithdrawalCredentials, WithdrawalReport};
+use db::ClientDB;
+use serde_json::Value;
+
+/// `GET /lighthouse/validator_monitor/withdrawals`
+///
+/// Reports the withdrawal credentials, exit epoch and withdrawable epoch of each monitored
+/// validator in the head state, and whether its balance is withdrawable at the epoch of the head.
+pub fn get_withdrawals<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    if node.validator_monitor().is_none() {
+        return Err(ApiError::Forbidden(
+            "The validator monitor is not enabled".to_string(),
+        ));
+    }
+    let (head_slot, head_root) = node.head();
+    let epoch = head_slot / node.config().epoch_length.max(1);
+    let validators = node
+        .monitored_withdrawals()
+        .iter()
+        .map(|report| withdrawal_json(report, epoch))
+        .collect();
+    Ok(data_response(json!({
+        "head_root": hex_bytes(&head_root),
+        "epoch": epoch.to_string(),
+        "validators": Value::Array(validators),
+    })))
+}
+
+fn withdrawal_json(report: &WithdrawalReport, epoch: u64) -> Value {
+    let credentials = match report.credentials {
+        WithdrawalCredentials::Eth1Address { shard, address } => json!({
+            "type": "eth1_address",
+            "shard": shard.to_string(),
+            "address": hex_bytes(&address),
+        }),
+        WithdrawalCredentials::Unset { shard } => json!({
+            "type": "unset",
+            "shard": shard.to_string(),
+            "address": Value::Null,
+        }),
+    };
+    json!({
+        "index": report.index.to_string(),
+        "pubkey": hex_bytes(&report.pubkey.as_bytes()),
+        "status": validator_status_name(report.status),
+        "withdrawal_credentials": credentials,
+        "exit_epoch": report.exit_epoch.map(|e| e.to_string()),
+        "withdrawable_epoch": report.withdrawable_epoch.map(|e| e.to_string()),
+        "is_withdrawable": report.is_withdrawable_at(epoch),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::tests::{context, get, import_block};
+    use super::*;
+    use beacon_node::ValidatorId;
+    use hyper::StatusCode;
+    use slog::{Discard, Logger};
+
+    #[test]
+    fn test_get_withdrawals() {
+        let ctx = context();
+        let (status, _) = get(&ctx, "/lighthouse/validator_monitor/withdrawals");
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        ctx.node.write().unwrap().monitor_validators(
+            &[ValidatorId::Index(3), ValidatorId::Index(1)],
+            Logger::root(Discard, o!()),
+        );
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let root = import_block(&ctx, genesis, 70);
+
+        let (status, body) = get(&ctx, "/lighthouse/validator_monitor/withdrawals");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["head_root"], hex_bytes(&root));
+        assert_eq!(body["data"]["epoch"], "1");
+        let validators = body["data"]["validators"].as_array().unwrap();
+        assert_eq!(validators.len(), 2);
+        assert_eq!(validators[0]["index"], "1");
+
+        let validator = ctx.node.read().unwrap().validators()[3].clone();
+        let report = &validators[1];
+        assert_eq!(report["index"], "3");
+        assert_eq!(report["status"], "active");
+        assert_eq!(report["withdrawal_credentials"]["type"], "eth1_address");
+        assert_eq!(
+            report["withdrawal_credentials"]["address"],
+            hex_bytes(&validator.withdrawal_address)
+        );
+        assert_eq!(report["exit_epoch"], Value::Null);
+        assert_eq!(report["withdrawable_epoch"], Value::Null);
+        assert_eq!(report["is_withdrawable"], false);
+    }
+}
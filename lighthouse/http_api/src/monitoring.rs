@@ -0,0 +1,298 @@
+use super::metrics;
+use super::node::{sync_status, version};
+use super::Context;
+use db::ClientDB;
+use futures::sync::oneshot;
+use futures::{Future, Stream};
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request, Uri};
+use hyper_tls::HttpsConnector;
+use lighthouse_metrics::inc_counter;
+use serde_json::Value;
+use slog::Logger;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use task_executor::TaskExecutor;
+use tokio::runtime::{Builder, Runtime};
+use tokio::timer::Timeout;
+use types::ValidatorStatus;
+
+/// How often the summary is pushed by default.
+pub const DEFAULT_MONITORING_INTERVAL: Duration = Duration::from_secs(60);
+/// The time allowed for each push.
+const MONITORING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where and how often the summary of the node is pushed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitoringConfig {
+    /// The URL to which the summary is `POST`ed, e.g. `https://example.com/api/v1/client/metrics`.
+    pub endpoint: String,
+    pub interval: Duration,
+}
+
+impl MonitoringConfig {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            interval: DEFAULT_MONITORING_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MonitoringError {
+    InvalidUrl(String),
+    /// The request could not be sent, or the connection failed.
+    Request(String),
+    /// No response arrived within the timeout.
+    Timeout,
+    /// The endpoint responded with an error status.
+    Status(u16),
+}
+
+/// Returns a compact summary of the node, for those who cannot scrape its metrics:
+///
+/// ```json
+/// {
+///   "version": "Lighthouse/v0.1.0/linux-x86_64",
+///   "timestamp": 1600000000000,
+///   "sync": {"head_slot": 100, "sync_distance": 0, "is_syncing": false, "sync_state": "synced"},
+///   "network": {"peers_connected": 12},
+///   "validators": {"total": 64, "active": 64, "participation_cycle": 11, "participation_rate": 0.98}
+/// }
+/// ```
+///
+/// `peers_connected` is `null` without networking, and the participation fields are `null` until
+/// a cycle is accounted.
+pub fn summary<T: ClientDB>(ctx: &Context<T>) -> Value {
+    let (head_slot, sync_distance, sync_state) = sync_status(ctx);
+    let peers_connected = ctx.peer_manager.as_ref().map(|peer_manager| {
+        peer_manager
+            .read()
+            .expect("Peer manager lock poisoned")
+            .connected_peers()
+            .len()
+    });
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let validators = node.validators();
+    let active = validators
+        .iter()
+        .filter(|v| v.status == ValidatorStatus::Active as u8)
+        .count();
+    let participation = node.participation().participation_rate();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() * 1000 + u64::from(now.subsec_millis()))
+        .unwrap_or(0);
+    json!({
+        "version": version(),
+        "timestamp": timestamp,
+        "sync": {
+            "head_slot": head_slot,
+            "sync_distance": sync_distance,
+            "is_syncing": !sync_state.is_synced(),
+            "sync_state": sync_state.name(),
+        },
+        "network": {
+            "peers_connected": peers_connected,
+        },
+        "validators": {
+            "total": validators.len(),
+            "active": active,
+            "participation_cycle": participation.map(|(cycle, _)| cycle),
+            "participation_rate": participation.map(|(_, rate)| rate),
+        },
+    })
+}
+
+/// A blocking client of the monitoring endpoint, over HTTP or HTTPS.
+struct MonitoringClient {
+    uri: Uri,
+    client: Client<HttpsConnector<HttpConnector>>,
+    runtime: Runtime,
+}
+
+impl MonitoringClient {
+    fn new(endpoint: &str) -> Result<Self, MonitoringError> {
+        let uri = endpoint
+            .parse::<Uri>()
+            .map_err(|_| MonitoringError::InvalidUrl(endpoint.to_string()))?;
+        match uri.scheme_part().map(|scheme| scheme.as_str()) {
+            Some("http") | Some("https") => {}
+            _ => return Err(MonitoringError::InvalidUrl(endpoint.to_string())),
+        }
+        let connector =
+            HttpsConnector::new(1).map_err(|e| MonitoringError::Request(format!("{}", e)))?;
+        let runtime = Builder::new()
+            .name_prefix("monitoring-client-")
+            .core_threads(1)
+            .build()
+            .map_err(|e| MonitoringError::Request(format!("{}", e)))?;
+        Ok(Self {
+            uri,
+            client: Client::builder().build(connector),
+            runtime,
+        })
+    }
+
+    fn post(&self, body: &Value) -> Result<(), MonitoringError> {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(self.uri.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .map_err(|e| MonitoringError::Request(format!("{}", e)))?;
+        let future = self.client.request(req).and_then(|response| {
+            let status = response.status();
+            response.into_body().concat2().map(move |_| status)
+        });
+        let future = Timeout::new(future, MONITORING_TIMEOUT).map_err(|e| {
+            if e.is_elapsed() {
+                MonitoringError::Timeout
+            } else {
+                match e.into_inner() {
+                    Some(e) => MonitoringError::Request(format!("{}", e)),
+                    None => MonitoringError::Request("Timer failed".to_string()),
+                }
+            }
+        });
+        let status = oneshot::spawn(future, &self.runtime.executor()).wait()?;
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(MonitoringError::Status(status.as_u16()))
+        }
+    }
+}
+
+/// Pushes the `summary` of the node to the monitoring endpoint from a background thread, once at
+/// the start and then each interval, until the service is dropped.
+pub struct MonitoringService {
+    shutdown: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MonitoringService {
+    pub fn start<T: ClientDB + 'static>(
+        config: MonitoringConfig,
+        ctx: Arc<Context<T>>,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> Result<Self, MonitoringError> {
+        let client = MonitoringClient::new(&config.endpoint)?;
+        let (shutdown_tx, shutdown_rx) = channel();
+        let handle = executor.spawn("monitoring", move |_| {
+            run(&client, &ctx, &config, &shutdown_rx, &log)
+        });
+        Ok(Self {
+            shutdown: shutdown_tx,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for MonitoringService {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run<T: ClientDB>(
+    client: &MonitoringClient,
+    ctx: &Context<T>,
+    config: &MonitoringConfig,
+    shutdown: &Receiver<()>,
+    log: &Logger,
+) {
+    info!(log, "Monitoring service started"; "endpoint" => &config.endpoint, "interval_secs" => config.interval.as_secs());
+    loop {
+        match client.post(&summary(ctx)) {
+            Ok(()) => {
+                inc_counter(&metrics::MONITORING_PUSHES);
+                debug!(log, "Pushed summary to monitoring endpoint");
+            }
+            Err(e) => {
+                inc_counter(&metrics::MONITORING_PUSH_FAILURES);
+                warn!(log, "Unable to push summary to monitoring endpoint"; "error" => format!("{:?}", e));
+            }
+        }
+
+        match shutdown.recv_timeout(config.interval) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::tests::{context, import_block};
+    use super::*;
+    use serde_json;
+    use slog::Discard;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_summary() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        import_block(&ctx, genesis, 1);
+
+        let summary = summary(&ctx);
+        assert_eq!(summary["sync"]["head_slot"], 1);
+        assert_eq!(summary["sync"]["is_syncing"], true);
+        assert_eq!(summary["network"]["peers_connected"], Value::Null);
+        assert_eq!(summary["validators"]["total"], 4);
+        assert_eq!(summary["validators"]["active"], 4);
+        assert!(summary["timestamp"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_push() {
+        assert_eq!(
+            MonitoringClient::new("ftp://example.com").err(),
+            Some(MonitoringError::InvalidUrl("ftp://example.com".to_string()))
+        );
+
+        /*
+         * The endpoint receives the summary as soon as the service starts.
+         */
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/metrics", listener.local_addr().unwrap());
+        let service = MonitoringService::start(
+            MonitoringConfig::new(endpoint),
+            Arc::new(context()),
+            &TaskExecutor::new(Logger::root(Discard, o!())).0,
+            Logger::root(Discard, o!()),
+        )
+        .unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = vec![];
+        let mut buf = [0; 4096];
+        let body = loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(i) = text.find("\r\n\r\n") {
+                if let Ok(body) = serde_json::from_str::<Value>(&text[i + 4..]) {
+                    break (text, body);
+                }
+            }
+            assert!(n > 0, "Request ended early");
+        };
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .unwrap();
+        assert!(body.0.starts_with("POST /metrics HTTP/1.1"));
+        assert_eq!(body.1["validators"]["total"], 4);
+        drop(service);
+    }
+}
@@ -0,0 +1,232 @@
+use super::error::{ApiError, ApiResult};
+use super::json::{data_response, hex_bytes, json_response};
+use super::query::Query;
+use super::Context;
+use db::ClientDB;
+use hyper::{Response, StatusCode};
+use network::peer_manager::{ConnectionState, PeerInfo};
+use network::{ConnectionDirection, NodeId, SyncState};
+use std::env::consts::{ARCH, OS};
+
+/// How far the head may fall behind the present slot, in cycles, before the node is syncing.
+pub const SYNC_TOLERANCE_CYCLES: u64 = 1;
+
+const PEER_STATES: [&str; 2] = ["connected", "disconnected"];
+const PEER_DIRECTIONS: [&str; 2] = ["inbound", "outbound"];
+
+/// `GET /eth/v1/node/version`
+pub fn get_version() -> ApiResult {
+    Ok(data_response(json!({ "version": version() })))
+}
+
+/// Identifies the client, e.g. `Lighthouse/v0.1.0/linux-x86_64`.
+pub fn version() -> String {
+    format!("Lighthouse/v{}/{}-{}", env!("CARGO_PKG_VERSION"), OS, ARCH)
+}
+
+/// `GET /eth/v1/node/syncing`
+pub fn get_syncing<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let (head_slot, sync_distance, state) = sync_status(ctx);
+    Ok(data_response(json!({
+        "head_slot": head_slot.to_string(),
+        "sync_distance": sync_distance.to_string(),
+        "is_syncing": !state.is_synced(),
+        "is_optimistic": false,
+        "el_offline": false,
+        "sync_state": state.name(),
+    })))
+}
+
+/// `GET /eth/v1/node/health?syncing_status`
+///
+/// Responds with `200` if the node is synced, otherwise `206`, or the `syncing_status` code if
+/// given. The body is empty so that load balancers need only check the status.
+pub fn get_health<T: ClientDB>(ctx: &Context<T>, query: &Query) -> ApiResult {
+    let syncing_status = match query.parse_value::<u16>("syncing_status")? {
+        Some(code) => StatusCode::from_u16(code)
+            .map_err(|_| ApiError::BadRequest(format!("Invalid syncing_status: {}", code)))?,
+        None => StatusCode::PARTIAL_CONTENT,
+    };
+    let (_, _, state) = sync_status(ctx);
+    let mut response = Response::new(vec![]);
+    if !state.is_synced() {
+        *response.status_mut() = syncing_status;
+    }
+    Ok(response)
+}
+
+/// `GET /eth/v1/node/peers?state,direction`
+///
+/// Lists the peers which have been connected, optionally filtered by `state` and `direction`,
+/// which may be repeated or comma-separated. Peers only found by discovery are not listed.
+pub fn get_peers<T: ClientDB>(ctx: &Context<T>, query: &Query) -> ApiResult {
+    let states = query.get_all("state");
+    let directions = query.get_all("direction");
+    for state in &states {
+        if !PEER_STATES.contains(state) {
+            return Err(ApiError::BadRequest(format!("Invalid state: {}", state)));
+        }
+    }
+    for direction in &directions {
+        if !PEER_DIRECTIONS.contains(direction) {
+            return Err(ApiError::BadRequest(format!(
+                "Invalid direction: {}",
+                direction
+            )));
+        }
+    }
+
+    let peer_manager = match ctx.peer_manager {
+        Some(ref peer_manager) => peer_manager.read().expect("Peer manager lock poisoned"),
+        None => return Ok(peers_response(vec![])),
+    };
+    let mut peers: Vec<(&NodeId, &PeerInfo, ConnectionDirection)> = peer_manager
+        .peers()
+        .filter_map(|(peer_id, info)| info.direction.map(|d| (peer_id, info, d)))
+        .filter(|(_, info, direction)| {
+            (states.is_empty() || states.contains(&state_name(&info.state)))
+                && (directions.is_empty() || directions.contains(&direction_name(*direction)))
+        })
+        .collect();
+    peers.sort_by_key(|(peer_id, _, _)| **peer_id);
+
+    let peers = peers
+        .into_iter()
+        .map(|(peer_id, info, direction)| {
+            let enr = info.enr.as_ref();
+            json!({
+                "peer_id": hex_bytes(&peer_id.0),
+                "enr": enr.map(|enr| format!("{}", enr)),
+                "last_seen_p2p_address": enr
+                    .and_then(|enr| enr.tcp_socket())
+                    .map(|addr| format!("/ip4/{}/tcp/{}", addr.ip(), addr.port())),
+                "state": state_name(&info.state),
+                "direction": direction_name(direction),
+            })
+        })
+        .collect();
+    Ok(peers_response(peers))
+}
+
+/// Returns the slot of the head, the slots by which it is behind the present slot, and whether
+/// that is too many for the node to be synced. A node which is behind is stalled if none of the
+/// connected peers is ahead of it; without networking, the peers are unknown.
+pub fn sync_status<T: ClientDB>(ctx: &Context<T>) -> (u64, u64, SyncState) {
+    let best_peer_head = ctx.peer_manager.as_ref().map(|peer_manager| {
+        peer_manager
+            .read()
+            .expect("Peer manager lock poisoned")
+            .best_peer_head()
+            .unwrap_or(0)
+    });
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (head_slot, _) = node.head();
+    let present_slot = node.present_slot();
+    let tolerance = SYNC_TOLERANCE_CYCLES * u64::from(node.config().cycle_length);
+    let state = SyncState::new(head_slot, present_slot, tolerance, best_peer_head);
+    (head_slot, present_slot.saturating_sub(head_slot), state)
+}
+
+fn peers_response(peers: Vec<serde_json::Value>) -> Response<Vec<u8>> {
+    json_response(&json!({
+        "meta": { "count": peers.len() },
+        "data": peers,
+    }))
+}
+
+fn state_name(state: &ConnectionState) -> &'static str {
+    match state {
+        ConnectionState::Connected { .. } => "connected",
+        ConnectionState::Disconnected { .. } | ConnectionState::Banned { .. } => "disconnected",
+    }
+}
+
+fn direction_name(direction: ConnectionDirection) -> &'static str {
+    match direction {
+        ConnectionDirection::Inbound => "inbound",
+        ConnectionDirection::Outbound => "outbound",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::handle;
+    use super::super::router::tests::{context, get, import_block};
+    use super::*;
+    use hyper::Request;
+    use network::{PeerManager, PeerManagerConfig};
+    use slog::{Discard, Logger};
+    use std::sync::{Arc, RwLock};
+    use std::time::Instant;
+
+    fn health(ctx: &Context<db::MemoryDB>, uri: &str) -> StatusCode {
+        handle(ctx, &Request::get(uri).body(vec![]).unwrap()).status()
+    }
+
+    #[test]
+    fn test_syncing_and_health() {
+        let ctx = context();
+        let (status, body) = get(&ctx, "/eth/v1/node/syncing");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["head_slot"], "0");
+        assert_eq!(body["data"]["is_syncing"], true);
+        assert_eq!(
+            health(&ctx, "/eth/v1/node/health"),
+            StatusCode::PARTIAL_CONTENT
+        );
+        assert_eq!(
+            health(&ctx, "/eth/v1/node/health?syncing_status=503"),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let present_slot = ctx.node.read().unwrap().present_slot();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        import_block(&ctx, genesis, present_slot);
+        let (_, body) = get(&ctx, "/eth/v1/node/syncing");
+        assert_eq!(body["data"]["sync_distance"], "0");
+        assert_eq!(body["data"]["is_syncing"], false);
+        assert_eq!(health(&ctx, "/eth/v1/node/health"), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_version() {
+        let (status, body) = get(&context(), "/eth/v1/node/version");
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["data"]["version"]
+            .as_str()
+            .unwrap()
+            .starts_with("Lighthouse/v"));
+    }
+
+    #[test]
+    fn test_peers() {
+        let mut ctx = context();
+        let (_, body) = get(&ctx, "/eth/v1/node/peers");
+        assert_eq!(body["meta"]["count"], 0);
+
+        let now = Instant::now();
+        let mut peer_manager =
+            PeerManager::new(PeerManagerConfig::default(), Logger::root(Discard, o!()));
+        let (inbound, outbound) = (NodeId::random(), NodeId::random());
+        assert!(peer_manager.on_connect_from(inbound, &"1.1.1.1".parse().unwrap(), now));
+        assert!(peer_manager.on_connect(outbound, now));
+        peer_manager.on_disconnect(&outbound, now);
+        ctx.peer_manager = Some(Arc::new(RwLock::new(peer_manager)));
+
+        let (_, body) = get(&ctx, "/eth/v1/node/peers");
+        assert_eq!(body["meta"]["count"], 2);
+        let (_, body) = get(&ctx, "/eth/v1/node/peers?state=connected");
+        assert_eq!(body["meta"]["count"], 1);
+        assert_eq!(body["data"][0]["peer_id"], hex_bytes(&inbound.0));
+        assert_eq!(body["data"][0]["direction"], "inbound");
+        let (_, body) = get(
+            &ctx,
+            "/eth/v1/node/peers?state=disconnected&direction=outbound",
+        );
+        assert_eq!(body["data"][0]["peer_id"], hex_bytes(&outbound.0));
+        assert_eq!(body["data"][0]["enr"], serde_json::Value::Null);
+
+        let (status, _) = get(&ctx, "/eth/v1/node/peers?state=dialing");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,130 @@
+use super::error::ApiResult;
+use super::json::{attestation_json, data_response, proposer_slashing_json, special_json};
+use super::query::Query;
+use super::Context;
+use db::ClientDB;
+use serde_json::Value;
+use types::SpecialRecordKind;
+
+/// `GET /eth/v1/beacon/pool/attestations?slot,committee_index`
+///
+/// `committee_index` is the position of the attestation's committee among the committees of its
+/// slot.
+pub fn get_attestations<T: ClientDB>(ctx: &Context<T>, query: &Query) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let slot = query.parse_value::<u64>("slot")?;
+    let committee_index = query.parse_value::<usize>("committee_index")?;
+
+    let attestations = node
+        .pooled_attestations()
+        .iter()
+        .filter(|a| slot.is_none() || slot == Some(a.data.slot))
+        .filter(|a| {
+            committee_index.is_none()
+                || node
+                    .committees(a.data.slot)
+                    .iter()
+                    .position(|c| u64::from(c.shard) == a.data.shard)
+                    == committee_index
+        })
+        .map(attestation_json)
+        .collect();
+    Ok(data_response(Value::Array(attestations)))
+}
+
+/// `GET /eth/v1/beacon/pool/voluntary_exits`
+///
+/// Exits are the logout specials of the pool.
+pub fn get_voluntary_exits<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    pooled_specials(ctx, SpecialRecordKind::Logout)
+}
+
+/// `GET /eth/v1/beacon/pool/attester_slashings`
+///
+/// Attester slashings are the Casper slashing specials of the pool.
+pub fn get_attester_slashings<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    pooled_specials(ctx, SpecialRecordKind::CasperSlashing)
+}
+
+/// `GET /eth/v1/beacon/pool/proposer_slashings`
+///
+/// Proposer slashings do not yet exist in the spec this node follows, so the pool holds the
+/// equivocations detected from gossip since the finalized slot, identified by the roots of the
+/// two blocks. Blocks are unsigned, so each is marked unverified.
+pub fn get_proposer_slashings<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let slashings = node
+        .pooled_proposer_slashings()
+        .iter()
+        .map(proposer_slashing_json)
+        .collect();
+    Ok(data_response(Value::Array(slashings)))
+}
+
+fn pooled_specials<T: ClientDB>(ctx: &Context<T>, kind: SpecialRecordKind) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let specials = node
+        .pooled_specials()
+        .iter()
+        .filter(|special| special.resolve_kind() == Some(kind))
+        .map(special_json)
+        .collect();
+    Ok(data_response(Value::Array(specials)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::tests::{context, get};
+    use beacon_node::ProposerSlashing;
+    use types::{Attestation, Bitfield, Hash256, SpecialRecord};
+
+    #[test]
+    fn test_pools() {
+        let ctx = context();
+        {
+            let mut node = ctx.node.write().unwrap();
+            let present_slot = node.present_slot();
+            for slot in present_slot - 1..=present_slot {
+                for (i, committee) in node.committees(slot).to_vec().iter().enumerate() {
+                    let mut attestation = Attestation::zero();
+                    attestation.data = node
+                        .produce_attestation_data(slot, u64::from(committee.shard))
+                        .unwrap();
+                    attestation.participation_bitfield = Bitfield::from_elem(8, false);
+                    attestation.participation_bitfield.set(i, true);
+                    node.process_attestation(attestation, present_slot).unwrap();
+                }
+            }
+            node.pool_special(SpecialRecord::logout(&[1]));
+            node.pool_special(SpecialRecord::casper_slashing(&[2]));
+            node.pool_proposer_slashing(ProposerSlashing {
+                proposer_index: 3,
+                slot: 1,
+                block_root_1: Hash256::from(1),
+                block_root_2: Hash256::from(2),
+            });
+        }
+        let present_slot = ctx.node.read().unwrap().present_slot().to_string();
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/pool/attestations");
+        let total = body["data"].as_array().unwrap().len();
+        assert!(total >= 2);
+        let uri = format!("/eth/v1/beacon/pool/attestations?slot={}", present_slot);
+        let (_, body) = get(&ctx, &uri);
+        let at_slot = body["data"].as_array().unwrap();
+        assert!(at_slot.len() < total);
+        assert!(at_slot.iter().all(|a| a["data"]["slot"] == present_slot));
+        let uri = format!("{}&committee_index=0", uri);
+        let (_, body) = get(&ctx, &uri);
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/pool/voluntary_exits");
+        assert_eq!(body["data"][0]["data"], "0x01");
+        let (_, body) = get(&ctx, "/eth/v1/beacon/pool/attester_slashings");
+        assert_eq!(body["data"][0]["data"], "0x02");
+        let (_, body) = get(&ctx, "/eth/v1/beacon/pool/proposer_slashings");
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(body["data"][0]["proposer_index"], "3");
+        assert_eq!(body["data"][0]["verified"], false);
+    }
+}
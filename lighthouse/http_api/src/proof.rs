@@ -0,0 +1,157 @@
+use super::error::{ApiError, ApiResult};
+use super::json::{data_response, hex_bytes};
+use super::query::Query;
+use super::state::state_block;
+use super::Context;
+use beacon_node::{state_summary, validators_tree, VALIDATORS_GINDEX};
+use db::ClientDB;
+use merkle_proof::{concat_gindices, gindex_depth, MerkleTree};
+use serde_json::Value;
+use types::Hash256;
+
+/// The most generalized indices proven in one request.
+pub const MAX_PROOFS_PER_REQUEST: usize = 64;
+
+/// `GET /lighthouse/proofs/states/{state_id}?gindex,validator`
+///
+/// Returns the branches proving the nodes at each `gindex`, then the leaf of each `validator`
+/// index, against the summary root of the state, as laid out by `state_summary`. For example, the
+/// finalized root is at `13`. Both parameters may be repeated or comma-separated.
+pub fn get_state_proofs<T: ClientDB>(ctx: &Context<T>, state_id: &str, query: &Query) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (block_root, block) = state_block(&node, state_id)?;
+    let validators = validators_tree(node.validators());
+    let summary = state_summary(block.slot, block_root, node.finalized_root(), &validators);
+
+    let mut gindices = parse_all::<usize>(query, "gindex")?;
+    for index in parse_all::<usize>(query, "validator")? {
+        if index >= node.validators().len() {
+            return Err(ApiError::NotFound(format!("Unknown validator: {}", index)));
+        }
+        gindices.push(concat_gindices(
+            VALIDATORS_GINDEX,
+            validators.leaf_gindex(index),
+        ));
+    }
+    if gindices.is_empty() || gindices.len() > MAX_PROOFS_PER_REQUEST {
+        return Err(ApiError::BadRequest(format!(
+            "Between 1 and {} generalized indices are required",
+            MAX_PROOFS_PER_REQUEST
+        )));
+    }
+
+    let mut proofs = vec![];
+    for gindex in gindices {
+        let (leaf, branch) = prove(&summary, &validators, gindex)
+            .ok_or_else(|| ApiError::BadRequest(format!("Invalid gindex: {}", gindex)))?;
+        proofs.push(json!({
+            "gindex": gindex.to_string(),
+            "leaf": hex_bytes(&leaf),
+            "branch": branch.iter().map(|node| Value::from(hex_bytes(node))).collect::<Vec<_>>(),
+        }));
+    }
+    Ok(data_response(json!({
+        "state_root": hex_bytes(&summary.root()),
+        "block_root": hex_bytes(&block_root),
+        "proofs": proofs,
+    })))
+}
+
+/// Returns the node at `gindex` of the summary, with its branch, descending into the validators
+/// tree below `VALIDATORS_GINDEX`.
+fn prove(
+    summary: &MerkleTree,
+    validators: &MerkleTree,
+    gindex: usize,
+) -> Option<(Hash256, Vec<Hash256>)> {
+    let summary_depth = summary.depth() as usize;
+    if gindex == 0 || gindex_depth(gindex) <= summary_depth {
+        return Some((summary.node(gindex)?, summary.branch(gindex)?));
+    }
+    /*
+     * Split the index into the summary node it descends from and its index within that node's
+     * subtree, which only exists for the validators.
+     */
+    let below = gindex_depth(gindex) - summary_depth;
+    if gindex >> below != VALIDATORS_GINDEX {
+        return None;
+    }
+    let subtree_gindex = (1 << below) | (gindex & ((1 << below) - 1));
+    let mut branch = validators.branch(subtree_gindex)?;
+    branch.extend(summary.branch(VALIDATORS_GINDEX)?);
+    Some((validators.node(subtree_gindex)?, branch))
+}
+
+fn parse_all<T: ::std::str::FromStr>(query: &Query, key: &str) -> Result<Vec<T>, ApiError> {
+    query
+        .get_all(key)
+        .into_iter()
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| ApiError::BadRequest(format!("Invalid value for {}: {}", key, value)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::json::parse_hash;
+    use super::super::router::tests::{context, get, import_block};
+    use super::*;
+    use beacon_node::validator_leaf;
+    use hyper::StatusCode;
+    use merkle_proof::verify_merkle_proof;
+
+    fn hash(value: &Value) -> Hash256 {
+        parse_hash(value.as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_state_proofs() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let root = import_block(&ctx, genesis, 3);
+
+        let (status, body) = get(
+            &ctx,
+            "/lighthouse/proofs/states/head?gindex=9,13&validator=3&gindex=14",
+        );
+        assert_eq!(status, StatusCode::OK);
+        let state_root = hash(&body["data"]["state_root"]);
+        let proofs = body["data"]["proofs"].as_array().unwrap();
+        assert_eq!(proofs.len(), 4);
+        assert_eq!(hash(&proofs[0]["leaf"]), root);
+        assert_eq!(hash(&proofs[1]["leaf"]), genesis);
+        /*
+         * Four validators give a validators tree of depth 2.
+         */
+        assert_eq!(proofs[3]["gindex"], "59");
+        let validator = ctx.node.read().unwrap().validators()[3].clone();
+        assert_eq!(hash(&proofs[3]["leaf"]), validator_leaf(&validator));
+        for proof in proofs {
+            let branch: Vec<Hash256> = proof["branch"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(hash)
+                .collect();
+            let gindex = proof["gindex"].as_str().unwrap().parse().unwrap();
+            assert!(verify_merkle_proof(
+                hash(&proof["leaf"]),
+                &branch,
+                gindex,
+                state_root
+            ));
+        }
+
+        let (status, _) = get(&ctx, "/lighthouse/proofs/states/head");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&ctx, "/lighthouse/proofs/states/head?gindex=16");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&ctx, "/lighthouse/proofs/states/head?validator=4");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&ctx, "/lighthouse/proofs/states/0x00?gindex=1");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
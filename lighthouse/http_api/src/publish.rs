@@ -0,0 +1,686 @@
+use super::error::{ApiError, ApiResult};
+use super::json::{aggregate_and_proof_from_json, attestation_from_json, block_from_json};
+use super::spec::GENESIS_FORK_VERSION;
+use super::Context;
+use beacon_node::{AttestationOutcome, BeaconNode, BlockProcessingOutcome, ProposerSlashing};
+use bls::PublicKey;
+use db::ClientDB;
+use hyper::Response;
+use network::gossip::{verify_attestation_batch, BlockObservation};
+use network::rpc::ForkDigest;
+use serde_json::{self, Value};
+use ssz::Decodable;
+use std::slice;
+use std::time::{Duration, Instant};
+use types::{is_aggregator, AggregateAndProof, Attestation, BeaconBlock};
+
+/// A block or attestation to be published to peers by the network service.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PubsubMessage {
+    BeaconBlock(BeaconBlock),
+    Attestation(Attestation),
+}
+
+/*
+ * Published objects are validated as if they were received by gossip, so that the node never
+ * publishes anything its peers would reject. Only the signatures of attestations are verified;
+ * those of blocks and aggregates are not verified until the state transition is restored.
+ */
+
+/// `POST /eth/v1/beacon/blocks`
+///
+/// The body is `{"message": block, "signature": signature}`, or the SSZ of the block. The block is
+/// published once it is imported.
+pub fn post_block<T: ClientDB>(ctx: &Context<T>, body: &[u8], is_ssz: bool) -> ApiResult {
+    let block = parse_block(body, is_ssz)?;
+
+    let mut node = ctx.node.write().expect("Beacon node lock poisoned");
+    let root = block.canonical_root();
+    if node.store().block_exists(&root)? {
+        return Ok(Response::new(vec![]));
+    }
+    let proposer = verify_block_for_gossip(ctx, &mut node, &block)?;
+
+    let present_slot = node.present_slot_with_future_tolerance();
+    match node.process_block(&block, present_slot)? {
+        BlockProcessingOutcome::Imported => {
+            info!(ctx.log, "Published block imported"; "slot" => block.slot);
+            /*
+             * The proposal is only recorded once imported, so that a block which fails to import
+             * does not cause the proposer's next attempt to be rejected as a duplicate.
+             */
+            ctx.duplicates
+                .lock()
+                .expect("Duplicate filter lock poisoned")
+                .observe_block(proposer as u64, block.slot, root, Instant::now());
+            ctx.publish(PubsubMessage::BeaconBlock(block));
+            Ok(Response::new(vec![]))
+        }
+        BlockProcessingOutcome::AlreadyKnown => Ok(Response::new(vec![])),
+        outcome => Err(ApiError::BadRequest(format!(
+            "Invalid block: {:?}",
+            outcome
+        ))),
+    }
+}
+
+/// Decodes a block from SSZ, or from JSON with the block as its `message`.
+pub fn parse_block(body: &[u8], is_ssz: bool) -> Result<BeaconBlock, ApiError> {
+    if is_ssz {
+        return BeaconBlock::ssz_decode(body, 0)
+            .map(|(block, _)| block)
+            .map_err(|_| ApiError::BadRequest("Invalid block SSZ".to_string()));
+    }
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|_| ApiError::BadRequest("Invalid JSON".to_string()))?;
+    let message = value
+        .get("message")
+        .ok_or_else(|| ApiError::BadRequest("Missing field: message".to_string()))?;
+    block_from_json(message).map_err(ApiError::BadRequest)
+}
+
+/// `POST /eth/v1/beacon/pool/attestations`
+///
+/// The body is a JSON array of unaggregated attestations. Each valid attestation is pooled and
+/// published, even if others are invalid. The invalid attestations are listed by their index in
+/// the `failures` of the error.
+///
+/// The signatures of the attestations which pass the other checks are verified as a batch.
+pub fn post_attestations<T: ClientDB>(ctx: &Context<T>, body: &[u8]) -> ApiResult {
+    let values: Vec<Value> = serde_json::from_slice(body)
+        .map_err(|_| ApiError::BadRequest("Body must be an array of attestations".to_string()))?;
+
+    let mut node = ctx.node.write().expect("Beacon node lock poisoned");
+    let mut failures = vec![];
+    let mut indices = vec![];
+    let mut attestations = vec![];
+    for (i, value) in values.iter().enumerate() {
+        let result = attestation_from_json(value).and_then(|attestation| {
+            check_attestation(&node, &attestation).map(|validator| (attestation, validator))
+        });
+        match result {
+            Ok((attestation, Some(validator_index))) => {
+                indices.push((i, validator_index));
+                attestations.push(attestation);
+            }
+            Ok((_, None)) => {}
+            Err(message) => failures.push((i, message)),
+        }
+    }
+
+    let fork_digest = ForkDigest::new(GENESIS_FORK_VERSION, &node.genesis_root());
+    let signed = verify_attestation_batch(&attestations, &fork_digest, |attestation| {
+        attestation_pubkeys(&node, attestation)
+    });
+    for (((i, validator_index), attestation), signed) in
+        indices.into_iter().zip(attestations).zip(signed)
+    {
+        let result = if signed {
+            pool_attestation(ctx, &mut node, attestation, validator_index)
+        } else {
+            Err("Invalid signature".to_string())
+        };
+        if let Err(message) = result {
+            failures.push((i, message));
+        }
+    }
+    failures.sort_by_key(|&(i, _)| i);
+    for &(i, ref message) in &failures {
+        debug!(ctx.log, "Published attestation rejected"; "index" => i, "error" => message);
+    }
+
+    if failures.is_empty() {
+        Ok(Response::new(vec![]))
+    } else {
+        Err(ApiError::IndexedBadRequest(
+            "Some attestations failed validation".to_string(),
+            failures,
+        ))
+    }
+}
+
+/// Verifies `attestation` as `post_attestations` does, signature included, then pools and
+/// publishes it if it is new. Serves attestations published other than through the HTTP API.
+pub fn publish_attestation<T: ClientDB>(
+    ctx: &Context<T>,
+    attestation: Attestation,
+) -> Result<(), String> {
+    let mut node = ctx.node.write().expect("Beacon node lock poisoned");
+    let validator_index = match check_attestation(&node, &attestation)? {
+        Some(validator_index) => validator_index,
+        None => return Ok(()),
+    };
+    let fork_digest = ForkDigest::new(GENESIS_FORK_VERSION, &node.genesis_root());
+    let signed = verify_attestation_batch(slice::from_ref(&attestation), &fork_digest, |a| {
+        attestation_pubkeys(&node, a)
+    });
+    if signed != [true] {
+        return Err("Invalid signature".to_string());
+    }
+    pool_attestation(ctx, &mut node, attestation, validator_index)
+}
+
+/// `POST /eth/v1/validator/aggregate_and_proofs`
+///
+/// The body is a JSON array of `{"message": aggregate_and_proof, "signature": signature}`. Each
+/// aggregate from a selected aggregator is pooled and published, and the invalid ones are listed
+/// in the `failures` of the error.
+pub fn post_aggregate_and_proofs<T: ClientDB>(ctx: &Context<T>, body: &[u8]) -> ApiResult {
+    let values: Vec<Value> = serde_json::from_slice(body).map_err(|_| {
+        ApiError::BadRequest("Body must be an array of aggregate and proofs".to_string())
+    })?;
+
+    let mut node = ctx.node.write().expect("Beacon node lock poisoned");
+    let mut failures = vec![];
+    for (i, value) in values.iter().enumerate() {
+        let result = value
+            .get("message")
+            .ok_or_else(|| "Missing field: message".to_string())
+            .and_then(aggregate_and_proof_from_json)
+            .and_then(|aggregate| pool_aggregate(ctx, &mut node, aggregate));
+        if let Err(message) = result {
+            debug!(ctx.log, "Published aggregate rejected"; "index" => i, "error" => &message);
+            failures.push((i, message));
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(Response::new(vec![]))
+    } else {
+        Err(ApiError::IndexedBadRequest(
+            "Some aggregates failed validation".to_string(),
+            failures,
+        ))
+    }
+}
+
+/// Checks `block` as if it were received by gossip, returning the index of its proposer.
+fn verify_block_for_gossip<T: ClientDB>(
+    ctx: &Context<T>,
+    node: &mut BeaconNode<T>,
+    block: &BeaconBlock,
+) -> Result<usize, ApiError> {
+    let reject = |message: String| Err(ApiError::BadRequest(message));
+    if block.slot > node.present_slot_with_future_tolerance() {
+        return reject(format!("Block is from a future slot: {}", block.slot));
+    }
+    if block.slot <= node.block(&node.finalized_root())?.slot {
+        return reject("Block is not after the finalized block".to_string());
+    }
+    match block.parent_hash() {
+        Some(parent) if node.store().block_exists(parent)? => {}
+        _ => return reject("Block parent is unknown".to_string()),
+    }
+    let proposer = match node.block_proposer(block.slot) {
+        Some(proposer) => proposer,
+        None => return reject(format!("No proposer for slot {}", block.slot)),
+    };
+    /*
+     * Only the first block of a proposer for a slot is published; the rest are equivocations,
+     * which are pooled as proposer slashings.
+     */
+    let root = block.canonical_root();
+    let observation = ctx
+        .duplicates
+        .lock()
+        .expect("Duplicate filter lock poisoned")
+        .check_block(proposer as u64, block.slot, root, Instant::now());
+    match observation {
+        BlockObservation::New => {
+            let arrival = node.observe_block_arrival(block.slot, proposer);
+            let delay_ms = as_millis(arrival.delay);
+            let worst_delay_ms = as_millis(arrival.worst_delay);
+            if arrival.late {
+                warn!(ctx.log, "Late block arrival"; "slot" => block.slot, "proposer" => proposer, "delay_ms" => delay_ms, "worst_delay_ms" => worst_delay_ms, "worst_slot" => arrival.worst_slot);
+            } else {
+                debug!(ctx.log, "Block arrived"; "slot" => block.slot, "proposer" => proposer, "delay_ms" => delay_ms, "worst_delay_ms" => worst_delay_ms, "worst_slot" => arrival.worst_slot);
+            }
+            Ok(proposer)
+        }
+        BlockObservation::Duplicate => reject(format!(
+            "A block from the proposer of slot {} has already been seen",
+            block.slot
+        )),
+        BlockObservation::Equivocation(first_root) => {
+            let slashing = ProposerSlashing {
+                proposer_index: proposer as u64,
+                slot: block.slot,
+                block_root_1: first_root,
+                block_root_2: root,
+            };
+            if node.pool_proposer_slashing(slashing) {
+                warn!(ctx.log, "Unverified proposer equivocation detected"; "proposer" => proposer, "slot" => block.slot);
+            }
+            reject(format!(
+                "Block is an equivocation by the proposer of slot {}",
+                block.slot
+            ))
+        }
+    }
+}
+
+/// Checks `attestation` for anything but its signature, returning the index of its participant,
+/// or `None` if it is already pooled.
+fn check_attestation<T: ClientDB>(
+    node: &BeaconNode<T>,
+    attestation: &Attestation,
+) -> Result<Option<usize>, String> {
+    let present_slot = node.present_slot_with_future_tolerance();
+    let earliest_slot = node.present_slot_with_past_tolerance();
+    let cycle_length = u64::from(node.config().cycle_length.max(1));
+    let data = &attestation.data;
+    if data.slot > present_slot {
+        return Err(format!("Attestation is from a future slot: {}", data.slot));
+    }
+    if data.slot + cycle_length < earliest_slot {
+        return Err(format!("Attestation is too old: {}", data.slot));
+    }
+    if node.pooled_attestations().contains(attestation) {
+        return Ok(None);
+    }
+
+    let participant = match attestation.participation_bitfield.highest_set_bit() {
+        Some(i) if attestation.participation_bitfield.num_set_bits() == 1 => i,
+        _ => return Err("Attestation must have exactly one participant".to_string()),
+    };
+    let validator_index = match node.committee(data.slot, data.shard) {
+        Some(committee) if participant < committee.len() => committee[participant],
+        Some(_) => return Err("Participant is not in the committee".to_string()),
+        None => return Err(format!("No committee for shard {}", data.shard)),
+    };
+    match node.store().block_exists(&data.beacon_block_hash) {
+        Ok(true) => {}
+        Ok(false) => return Err("Attested block is unknown".to_string()),
+        Err(e) => return Err(e.message),
+    }
+    Ok(Some(validator_index))
+}
+
+/// Returns the public keys of the participants of `attestation`, or `None` if its committee is
+/// unknown.
+fn attestation_pubkeys<T: ClientDB>(
+    node: &BeaconNode<T>,
+    attestation: &Attestation,
+) -> Option<Vec<PublicKey>> {
+    let data = &attestation.data;
+    let committee = node.committee(data.slot, data.shard)?;
+    let validators = node.validators();
+    Some(
+        committee
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| attestation.participation_bitfield.get(i) == Ok(true))
+            .filter_map(|(_, &index)| validators.get(index))
+            .map(|validator| validator.pubkey.clone())
+            .collect(),
+    )
+}
+
+/// Adds `attestation` from `validator_index`, which passed verification, to the pool, publishing
+/// it if it is new.
+fn pool_attestation<T: ClientDB>(
+    ctx: &Context<T>,
+    node: &mut BeaconNode<T>,
+    attestation: Attestation,
+    validator_index: usize,
+) -> Result<(), String> {
+    let present_slot = node.present_slot_with_future_tolerance();
+    let cycle_length = u64::from(node.config().cycle_length.max(1));
+    let data = &attestation.data;
+    {
+        let mut duplicates = ctx
+            .duplicates
+            .lock()
+            .expect("Duplicate filter lock poisoned");
+        let cycle = data.slot / cycle_length;
+        if !duplicates.observe_attestation(validator_index as u64, cycle, Instant::now()) {
+            return Err(format!(
+                "An attestation from validator {} has already been seen",
+                validator_index
+            ));
+        }
+    }
+
+    match node.process_attestation(attestation.clone(), present_slot) {
+        Ok(AttestationOutcome::Pooled) => {
+            ctx.publish(PubsubMessage::Attestation(attestation));
+            Ok(())
+        }
+        Ok(AttestationOutcome::AlreadyKnown) => Ok(()),
+        Ok(outcome) => Err(format!("Invalid attestation: {:?}", outcome)),
+        Err(e) => Err(format!("{:?}", e)),
+    }
+}
+
+/// Verifies that the aggregator of `aggregate_and_proof` was selected, then adds the aggregate to
+/// the pool, publishing it if it is new.
+///
+/// The selection proof and the attestation data are only verified the first time they are seen.
+fn pool_aggregate<T: ClientDB>(
+    ctx: &Context<T>,
+    node: &mut BeaconNode<T>,
+    aggregate_and_proof: AggregateAndProof,
+) -> Result<(), String> {
+    let present_slot = node.present_slot_with_future_tolerance();
+    let earliest_slot = node.present_slot_with_past_tolerance();
+    let cycle_length = u64::from(node.config().cycle_length.max(1));
+    let aggregate = aggregate_and_proof.aggregate;
+    let data = &aggregate.data;
+    if data.slot > present_slot {
+        return Err(format!("Aggregate is from a future slot: {}", data.slot));
+    }
+    if data.slot + cycle_length < earliest_slot {
+        return Err(format!("Aggregate is too old: {}", data.slot));
+    }
+
+    let mut verified = ctx
+        .verified_aggregates
+        .lock()
+        .expect("Aggregate verification cache lock poisoned");
+    verified.prune(earliest_slot.saturating_sub(cycle_length));
+    let aggregator_index = aggregate_and_proof.aggregator_index;
+    let proof = &aggregate_and_proof.selection_proof;
+    if !verified.is_selection_proof_verified(data, aggregator_index, proof) {
+        let committee = match node.committee(data.slot, data.shard) {
+            Some(committee) => committee,
+            None => return Err(format!("No committee for shard {}", data.shard)),
+        };
+        let aggregator = aggregator_index as usize;
+        if !committee.contains(&aggregator) {
+            return Err("Aggregator is not in the committee".to_string());
+        }
+        if !is_aggregator(committee.len(), proof) {
+            return Err(format!("Validator {} is not an aggregator", aggregator));
+        }
+        verified.insert_selection_proof(data, aggregator_index, proof);
+    }
+    if !verified.is_data_verified(data) {
+        match node.store().block_exists(&data.beacon_block_hash) {
+            Ok(true) => {}
+            Ok(false) => return Err("Attested block is unknown".to_string()),
+            Err(e) => return Err(e.message),
+        }
+        verified.insert_data(data);
+    }
+    drop(verified);
+
+    match node.process_attestation(aggregate.clone(), present_slot) {
+        Ok(AttestationOutcome::Pooled) => {
+            let delay = node.observe_aggregate_arrival(data.slot);
+            debug!(ctx.log, "Aggregate arrived"; "slot" => data.slot, "aggregator" => aggregator_index, "delay_ms" => as_millis(delay));
+            ctx.publish(PubsubMessage::Attestation(aggregate));
+            Ok(())
+        }
+        Ok(AttestationOutcome::AlreadyKnown) => Ok(()),
+        Ok(outcome) => Err(format!("Invalid aggregate: {:?}", outcome)),
+        Err(e) => Err(format!("{:?}", e)),
+    }
+}
+
+fn as_millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::json::{attestation_json, block_json, hex_bytes};
+    use super::super::router::handle;
+    use super::super::router::tests::{context_with_network, context_with_validators};
+    use super::*;
+    use beacon_node::{SlotClock, TestingSlotClock, MAXIMUM_CLOCK_DISPARITY};
+    use bls::{Keypair, Signature};
+    use hyper::header::CONTENT_TYPE;
+    use hyper::{Request, StatusCode};
+    use ssz::ssz_encode;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use types::signing::attestation_signing_root;
+    use types::{Bitfield, Hash256};
+
+    fn post(ctx: &Context<db::MemoryDB>, uri: &str, body: Vec<u8>) -> (StatusCode, Vec<u8>) {
+        let req = Request::post(uri).body(body).unwrap();
+        let response = handle(ctx, &req);
+        (response.status(), response.body().clone())
+    }
+
+    #[test]
+    fn test_post_block() {
+        let (ctx, network) = context_with_network();
+        let block = ctx
+            .node
+            .read()
+            .unwrap()
+            .produce_block(1, Hash256::from(1), Hash256::zero())
+            .unwrap();
+        let body = json!({ "message": block_json(&block) }).to_string();
+
+        let (status, _) = post(&ctx, "/eth/v1/beacon/blocks", body.clone().into_bytes());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(ctx.node.read().unwrap().head().1, block.canonical_root());
+        assert_eq!(
+            network.try_recv(),
+            Ok(PubsubMessage::BeaconBlock(block.clone()))
+        );
+
+        /*
+         * A known block is accepted again but not republished, while a second block from the
+         * same proposer is rejected and pooled as a proposer slashing.
+         */
+        let (status, _) = post(&ctx, "/eth/v1/beacon/blocks", body.into_bytes());
+        assert_eq!(status, StatusCode::OK);
+        assert!(network.try_recv().is_err());
+        let mut equivocation = block.clone();
+        equivocation.randao_reveal = Hash256::from(2);
+        let req = Request::post("/eth/v1/beacon/blocks")
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(ssz_encode(&equivocation))
+            .unwrap();
+        let response = handle(&ctx, &req);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["code"], 400);
+        let slashings = ctx
+            .node
+            .read()
+            .unwrap()
+            .pooled_proposer_slashings()
+            .to_vec();
+        assert_eq!(slashings.len(), 1);
+        assert_eq!(slashings[0].slot, 1);
+        assert_eq!(slashings[0].block_root_1, block.canonical_root());
+        assert_eq!(slashings[0].block_root_2, equivocation.canonical_root());
+
+        let mut future = block.clone();
+        future.slot = 1_000;
+        let body = json!({ "message": block_json(&future) }).to_string();
+        let (status, _) = post(&ctx, "/eth/v1/beacon/blocks", body.into_bytes());
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(network.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_post_block_after_failed_import() {
+        let (ctx, network) = context_with_network();
+        let block = ctx
+            .node
+            .read()
+            .unwrap()
+            .produce_block(1, Hash256::from(1), Hash256::zero())
+            .unwrap();
+        let body = json!({ "message": block_json(&block) }).to_string();
+
+        ctx.node
+            .write()
+            .unwrap()
+            .halt_block_import("Low disk space".to_string());
+        let (status, _) = post(&ctx, "/eth/v1/beacon/blocks", body.clone().into_bytes());
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(network.try_recv().is_err());
+
+        /*
+         * The block was not imported, so it is not a duplicate when published again.
+         */
+        ctx.node.write().unwrap().resume_block_import();
+        let (status, _) = post(&ctx, "/eth/v1/beacon/blocks", body.into_bytes());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(network.try_recv(), Ok(PubsubMessage::BeaconBlock(block)));
+    }
+
+    #[test]
+    fn test_post_block_within_clock_disparity() {
+        let (ctx, network) = context_with_network();
+        let block = {
+            let mut node = ctx.node.write().unwrap();
+            let config = node.config().clone();
+            let clock =
+                TestingSlotClock::new(config.genesis_time, config.slot_duration_millis).unwrap();
+            clock.set_now(clock.start_of(1) - Duration::from_millis(300));
+            node.set_slot_clock(Arc::new(clock));
+            node.set_clock_disparity(Duration::from_secs(0));
+            node.produce_block(1, Hash256::from(1), Hash256::zero())
+                .unwrap()
+        };
+        let body = json!({ "message": block_json(&block) }).to_string();
+
+        let (status, _) = post(&ctx, "/eth/v1/beacon/blocks", body.clone().into_bytes());
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(network.try_recv().is_err());
+
+        /*
+         * The block is early by less than the clock disparity, so it may be from a peer whose
+         * clock is ahead.
+         */
+        ctx.node
+            .write()
+            .unwrap()
+            .set_clock_disparity(MAXIMUM_CLOCK_DISPARITY);
+        let (status, _) = post(&ctx, "/eth/v1/beacon/blocks", body.into_bytes());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(network.try_recv(), Ok(PubsubMessage::BeaconBlock(block)));
+    }
+
+    #[test]
+    fn test_post_attestations() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (ctx, network) = context_with_validators(&keypairs);
+        let (attestation, overfull, forged) = {
+            let node = ctx.node.read().unwrap();
+            let slot = node.present_slot() - 1;
+            let shard = u64::from(node.committees(slot)[0].shard);
+            let committee = node.committee(slot, shard).unwrap().to_vec();
+            let fork_digest = ForkDigest::new(GENESIS_FORK_VERSION, &node.genesis_root());
+            let data = node.produce_attestation_data(slot, shard).unwrap();
+            let root = attestation_signing_root(&data, fork_digest.0);
+            let attestation = |participant: usize, signer: usize| {
+                let mut attestation = Attestation::zero();
+                attestation.data = data.clone();
+                attestation.participation_bitfield = Bitfield::from_elem(8, false);
+                attestation.participation_bitfield.set(participant, true);
+                let keypair = &keypairs[committee[signer]];
+                attestation
+                    .aggregate_sig
+                    .add(&Signature::new(&root[..], &keypair.sk));
+                attestation
+            };
+            let mut overfull = attestation(0, 0);
+            overfull.participation_bitfield.set(1, true);
+            (attestation(0, 0), overfull, attestation(1, 0))
+        };
+
+        let body = json!([attestation_json(&attestation)]).to_string();
+        let (status, _) = post(&ctx, "/eth/v1/beacon/pool/attestations", body.into_bytes());
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            network.try_recv(),
+            Ok(PubsubMessage::Attestation(attestation.clone()))
+        );
+        assert_eq!(ctx.node.read().unwrap().pooled_attestations().len(), 1);
+
+        /*
+         * An attestation signed by another member of the committee is rejected with the rest.
+         */
+        let body = json!([
+            attestation_json(&attestation),
+            attestation_json(&forged),
+            attestation_json(&overfull)
+        ])
+        .to_string();
+        let (status, body) = post(&ctx, "/eth/v1/beacon/pool/attestations", body.into_bytes());
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["failures"].as_array().unwrap().len(), 2);
+        assert_eq!(body["failures"][0]["index"], 1);
+        assert_eq!(body["failures"][1]["index"], 2);
+        assert!(network.try_recv().is_err());
+        assert_eq!(ctx.node.read().unwrap().pooled_attestations().len(), 1);
+
+        /*
+         * Attestations published other than through the API are verified alike.
+         */
+        assert_eq!(
+            publish_attestation(&ctx, forged),
+            Err("Invalid signature".to_string())
+        );
+        assert_eq!(publish_attestation(&ctx, attestation), Ok(()));
+        assert!(network.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_post_aggregate_and_proofs() {
+        let (ctx, network) = context_with_network();
+        let (aggregate, committee) = {
+            let node = ctx.node.read().unwrap();
+            let slot = node.present_slot() - 1;
+            let shard = u64::from(node.committees(slot)[0].shard);
+            let mut aggregate = Attestation::zero();
+            aggregate.data = node.produce_attestation_data(slot, shard).unwrap();
+            aggregate.participation_bitfield = Bitfield::from_elem(2, true);
+            (aggregate, node.committee(slot, shard).unwrap().to_vec())
+        };
+        let message = |aggregator_index: usize| {
+            let proof = Signature::new(&[1], &Keypair::random().sk);
+            let signature = Signature::new(&[2], &Keypair::random().sk);
+            json!({
+                "message": {
+                    "aggregator_index": aggregator_index.to_string(),
+                    "aggregate": attestation_json(&aggregate),
+                    "selection_proof": hex_bytes(&proof.as_bytes()),
+                },
+                "signature": hex_bytes(&signature.as_bytes()),
+            })
+        };
+
+        let body = json!([message(committee[0])]).to_string();
+        let (status, _) = post(
+            &ctx,
+            "/eth/v1/validator/aggregate_and_proofs",
+            body.into_bytes(),
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            network.try_recv(),
+            Ok(PubsubMessage::Attestation(aggregate.clone()))
+        );
+        assert_eq!(ctx.node.read().unwrap().pooled_attestations().len(), 1);
+        assert_eq!(ctx.verified_aggregates.lock().unwrap().len(), 2);
+
+        let outsider = (0..4).find(|i| !committee.contains(i)).unwrap();
+        let body = json!([message(committee[1]), message(outsider)]).to_string();
+        let (status, body) = post(
+            &ctx,
+            "/eth/v1/validator/aggregate_and_proofs",
+            body.into_bytes(),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["failures"].as_array().unwrap().len(), 1);
+        assert_eq!(body["failures"][0]["index"], 1);
+        assert!(network.try_recv().is_err());
+
+        /*
+         * Only the selection proof of the new aggregator is verified and remembered; the data
+         * was already verified, and the outsider was rejected.
+         */
+        assert_eq!(ctx.verified_aggregates.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,133 @@
+use super::error::ApiError;
+use std::str::FromStr;
+
+/// The parameters of a request's query string.
+///
+/// Values are not percent-decoded, as no parameter takes a value which needs encoding.
+#[derive(Debug, Default)]
+pub struct Query {
+    params: Vec<(String, String)>,
+}
+
+impl Query {
+    pub fn parse(query: Option<&str>) -> Self {
+        let params = query
+            .unwrap_or("")
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut split = pair.splitn(2, '=');
+                let key = split.next().unwrap_or("").to_string();
+                let value = split.next().unwrap_or("").to_string();
+                (key, value)
+            })
+            .collect();
+        Self { params }
+    }
+
+    /// Returns the first value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns every value of `key`, splitting comma-separated lists.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.params
+            .iter()
+            .filter(|(k, _)| k == key)
+            .flat_map(|(_, v)| v.split(','))
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
+    /// Returns the page requested by the `offset` and `limit` parameters. The limit defaults to,
+    /// and is capped at, `max_limit`.
+    pub fn pagination(&self, max_limit: usize) -> Result<Pagination, ApiError> {
+        let offset = self.parse_value::<usize>("offset")?.unwrap_or(0);
+        let limit = self
+            .parse_value::<usize>("limit")?
+            .unwrap_or(max_limit)
+            .min(max_limit);
+        Ok(Pagination { offset, limit })
+    }
+
+    /// Parses the first value of `key`, if present.
+    pub fn parse_value<T: FromStr>(&self, key: &str) -> Result<Option<T>, ApiError> {
+        match self.get(key) {
+            Some(value) => value
+                .parse::<T>()
+                .map(Some)
+                .map_err(|_| ApiError::BadRequest(format!("Invalid value for {}: {}", key, value))),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the first value of `key`, which must be present.
+    pub fn require<T: FromStr>(&self, key: &str) -> Result<T, ApiError> {
+        self.parse_value(key)?
+            .ok_or_else(|| ApiError::BadRequest(format!("Missing query parameter: {}", key)))
+    }
+}
+
+/// A range of the results of a request.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Pagination {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Pagination {
+    pub fn page<T>(&self, items: Vec<T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let query = Query::parse(Some("slot=3&parent_root=0xab&slot=4&flag"));
+        assert_eq!(query.get("slot"), Some("3"));
+        assert_eq!(query.get("parent_root"), Some("0xab"));
+        assert_eq!(query.get("flag"), Some(""));
+        assert_eq!(query.get("missing"), None);
+        assert_eq!(query.parse_value::<u64>("slot"), Ok(Some(3)));
+        assert!(query.parse_value::<u64>("parent_root").is_err());
+        assert_eq!(Query::parse(None).get("slot"), None);
+        assert_eq!(
+            Query::parse(Some("id=1,2&id=3&id=")).get_all("id"),
+            vec!["1", "2", "3"]
+        );
+    }
+
+    #[test]
+    fn test_pagination() {
+        let pagination = Query::parse(Some("offset=2&limit=500"))
+            .pagination(100)
+            .unwrap();
+        assert_eq!(
+            pagination,
+            Pagination {
+                offset: 2,
+                limit: 100
+            }
+        );
+        assert_eq!(
+            Query::parse(Some("limit=2"))
+                .pagination(100)
+                .unwrap()
+                .page(vec![1, 2, 3]),
+            vec![1, 2]
+        );
+        assert!(Query::parse(Some("offset=-1")).pagination(100).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use super::error::{ApiError, ApiResult};
+use super::json::{data_response, registry_delta_json};
+use super::query::Query;
+use super::Context;
+use db::ClientDB;
+use serde_json::Value;
+
+/// `GET /lighthouse/validator_registry/deltas?cycle`
+///
+/// Returns the changes to the validator registry over each recent cycle, oldest first, or over
+/// the given `cycle` only.
+pub fn get_deltas<T: ClientDB>(ctx: &Context<T>, query: &Query) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let deltas = node.registry_deltas();
+    match query.parse_value::<u64>("cycle")? {
+        Some(cycle) => match deltas.iter().find(|delta| delta.cycle == cycle) {
+            Some(delta) => Ok(data_response(registry_delta_json(delta))),
+            None => Err(ApiError::NotFound(format!(
+                "No registry delta for cycle {}",
+                cycle
+            ))),
+        },
+        None => Ok(data_response(Value::Array(
+            deltas.iter().map(registry_delta_json).collect(),
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::tests::{context, get, import_block};
+    use hyper::StatusCode;
+
+    #[test]
+    fn test_get_deltas() {
+        let ctx = context();
+        let (status, body) = get(&ctx, "/lighthouse/validator_registry/deltas");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 0);
+
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let root = import_block(&ctx, genesis, 3);
+        import_block(&ctx, root, 5);
+
+        let (status, body) = get(&ctx, "/lighthouse/validator_registry/deltas");
+        assert_eq!(status, StatusCode::OK);
+        let deltas = body["data"].as_array().unwrap();
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0]["epoch"], "0");
+        assert_eq!(deltas[1]["epoch"], "1");
+        assert_eq!(deltas[1]["activations"].as_array().unwrap().len(), 0);
+        assert_eq!(deltas[1]["top_ups"].as_array().unwrap().len(), 0);
+
+        let (status, body) = get(&ctx, "/lighthouse/validator_registry/deltas?cycle=1");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["epoch"], "1");
+
+        let (status, _) = get(&ctx, "/lighthouse/validator_registry/deltas?cycle=2");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&ctx, "/lighthouse/validator_registry/deltas?cycle=x");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
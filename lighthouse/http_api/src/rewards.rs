@@ -0,0 +1,116 @@
+use super::block_id::BlockId;
+use super::error::{ApiError, ApiResult};
+use super::json::{block_reward_json, data_response, hex_bytes};
+use super::publish::parse_block;
+use super::Context;
+use db::ClientDB;
+
+/// `GET /eth/v1/beacon/rewards/blocks/{block_id}`
+///
+/// Returns the estimated reward of the proposer of the block, with the votes first included by
+/// each of its attestations and their value, to evaluate how well the block was packed.
+pub fn get_block_rewards<T: ClientDB>(ctx: &Context<T>, block_id: &str) -> ApiResult {
+    let block_id: BlockId = block_id.parse()?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (_, block) = block_id.block(&node)?;
+    let reward = node.block_reward(&block)?;
+    Ok(data_response(block_reward_json(&reward)))
+}
+
+/// `POST /lighthouse/analysis/block_rewards`
+///
+/// The body is a block as published to `POST /eth/v1/beacon/blocks`, whose parent is known. The
+/// block is not imported, so that blocks proposed by the node and by relays can be compared
+/// before one is published.
+pub fn post_block_rewards<T: ClientDB>(ctx: &Context<T>, body: &[u8], is_ssz: bool) -> ApiResult {
+    let block = parse_block(body, is_ssz)?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    if let Some(parent) = block.parent_hash() {
+        if !node.store().block_exists(parent)? {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown parent: {}",
+                hex_bytes(parent)
+            )));
+        }
+    }
+    let reward = node.block_reward(&block)?;
+    Ok(data_response(block_reward_json(&reward)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::json::block_json;
+    use super::super::router::handle;
+    use super::super::router::tests::{context, get};
+    use super::*;
+    use beacon_node::includer_reward;
+    use hyper::{Request, StatusCode};
+    use serde_json::{self, Value};
+    use types::{Attestation, Bitfield, Hash256};
+
+    #[test]
+    fn test_block_rewards() {
+        let ctx = context();
+        let (block, expected) = {
+            let mut node = ctx.node.write().unwrap();
+            let present_slot = node.present_slot();
+            let slot = present_slot - node.config().min_attestation_inclusion_delay;
+            let committee = node.committees(slot)[0].clone();
+            let data = node
+                .produce_attestation_data(slot, u64::from(committee.shard))
+                .unwrap();
+            for participant in 0..2 {
+                let mut attestation = Attestation::zero();
+                attestation.data = data.clone();
+                attestation.participation_bitfield = Bitfield::from_elem(2, false);
+                attestation.participation_bitfield.set(participant, true);
+                node.process_attestation(attestation, present_slot).unwrap();
+            }
+            let block = node
+                .produce_block(present_slot, Hash256::zero(), Hash256::zero())
+                .unwrap();
+            node.process_block(&block, present_slot).unwrap();
+            let expected: u64 = committee.committee[..2]
+                .iter()
+                .map(|i| includer_reward(node.validators()[*i].balance))
+                .sum();
+            (block, expected)
+        };
+
+        let (status, body) = get(&ctx, "/eth/v1/beacon/rewards/blocks/head");
+        assert_eq!(status, StatusCode::OK);
+        let reward = &body["data"];
+        assert_eq!(reward["root"], hex_bytes(&block.canonical_root()));
+        assert_eq!(reward["total_gwei"], expected.to_string());
+        assert_eq!(reward["redundant_votes"], "0");
+        assert_eq!(reward["attestations"].as_array().unwrap().len(), 2);
+        assert_eq!(reward["attestations"][1]["new_votes"], "1");
+
+        /*
+         * A child repeating the attestations would earn nothing for them.
+         */
+        let post = |body: Value| {
+            let req = Request::post("/lighthouse/analysis/block_rewards")
+                .body(body.to_string().into_bytes())
+                .unwrap();
+            let response = handle(&ctx, &req);
+            let body: Value = serde_json::from_slice(response.body()).unwrap();
+            (response.status(), body)
+        };
+        let mut child = block.clone();
+        child.slot += 1;
+        child.ancestor_hashes = vec![block.canonical_root()];
+        let (status, body) = post(json!({ "message": block_json(&child) }));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total_gwei"], "0");
+        assert_eq!(body["data"]["redundant_votes"], "2");
+        let store = ctx.node.read().unwrap().store().clone();
+        assert!(!store.block_exists(&child.canonical_root()).unwrap());
+
+        child.ancestor_hashes = vec![Hash256::from(9)];
+        let (status, _) = post(json!({ "message": block_json(&child) }));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&ctx, "/eth/v1/beacon/rewards/blocks/50");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
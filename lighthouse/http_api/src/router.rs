@@ -0,0 +1,433 @@
+use super::admin;
+use super::beacon;
+use super::debug;
+use super::encoding::{Accept, SSZ_MEDIA_TYPE};
+use super::error::{ApiError, ApiResult};
+use super::events;
+use super::light_client;
+use super::metrics;
+use super::monitor;
+use super::node;
+use super::pool;
+use super::proof;
+use super::publish;
+use super::query::Query;
+use super::registry;
+use super::rewards;
+use super::spec;
+use super::state;
+use super::validator;
+use super::Context;
+use db::ClientDB;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Method, Request, Response};
+use lighthouse_metrics::{inc_counter, start_timer};
+
+/// Serves `req`, returning errors as JSON responses.
+pub fn handle<T: ClientDB>(ctx: &Context<T>, req: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    inc_counter(&metrics::HTTP_API_REQUESTS);
+    let _timer = start_timer(&metrics::HTTP_API_REQUEST_TIMES);
+    match route(ctx, req) {
+        Ok(response) => response,
+        Err(e) => {
+            debug!(ctx.log, "API request failed"; "path" => req.uri().path(), "error" => format!("{:?}", e));
+            e.into_response()
+        }
+    }
+}
+
+/// Serves `req` as per `handle`, including the endpoints which stream their response.
+pub fn serve<T: ClientDB>(ctx: &Context<T>, req: &Request<Vec<u8>>) -> Response<Body> {
+    if req.method() == Method::GET && req.uri().path().trim_matches('/') == "eth/v1/events" {
+        let query = Query::parse(req.uri().query());
+        return events::get_events(ctx, &query)
+            .unwrap_or_else(|e| e.into_response().map(Body::from));
+    }
+    handle(ctx, req).map(Body::from)
+}
+
+fn route<T: ClientDB>(ctx: &Context<T>, req: &Request<Vec<u8>>) -> ApiResult {
+    let path: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    let query = Query::parse(req.uri().query());
+
+    let accept = Accept::parse(req);
+
+    if path.starts_with(&["lighthouse", "admin"]) {
+        admin::authorize(ctx, req)?;
+    }
+
+    let response = match (req.method(), &path[..]) {
+        (&Method::POST, ["lighthouse", "admin", "heap_profile"]) => admin::post_heap_profile(ctx),
+        (&Method::GET, ["lighthouse", "admin", "peers"]) => admin::get_peers(ctx),
+        (&Method::POST, ["lighthouse", "admin", "peers", "dial"]) => {
+            admin::post_dial(ctx, req.body())
+        }
+        (&Method::POST, ["lighthouse", "admin", "peers", peer_id, "ban"]) => {
+            admin::post_ban(ctx, peer_id)
+        }
+        (&Method::DELETE, ["lighthouse", "admin", "peers", peer_id, "ban"]) => {
+            admin::delete_ban(ctx, peer_id)
+        }
+        (&Method::GET, ["lighthouse", "beacon", "blocks"]) => {
+            beacon::get_blocks_range(ctx, &query, accept.preferred()?)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "blocks", block_id]) => {
+            beacon::get_block(ctx, block_id, accept.preferred()?)
+        }
+        (&Method::POST, ["eth", "v1", "beacon", "blocks"]) => {
+            publish::post_block(ctx, req.body(), is_ssz(req))
+        }
+        (&Method::POST, ["eth", "v1", "beacon", "pool", "attestations"]) => {
+            publish::post_attestations(ctx, req.body())
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "pool", "attestations"]) => {
+            pool::get_attestations(ctx, &query)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "pool", "voluntary_exits"]) => {
+            pool::get_voluntary_exits(ctx)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "pool", "attester_slashings"]) => {
+            pool::get_attester_slashings(ctx)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "pool", "proposer_slashings"]) => {
+            pool::get_proposer_slashings(ctx)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "genesis"]) => spec::get_genesis(ctx),
+        (&Method::GET, ["eth", "v1", "config", "fork_schedule"]) => spec::get_fork_schedule(ctx),
+        (&Method::GET, ["eth", "v1", "config", "spec"]) => spec::get_spec(ctx),
+        (&Method::GET, ["eth", "v1", "beacon", "headers"]) => {
+            beacon::get_headers(ctx, &query, accept.preferred()?)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "headers", block_id]) => {
+            beacon::get_header(ctx, block_id, accept.preferred()?)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "light_client", "updates"]) => {
+            light_client::get_updates(ctx, &query, accept.preferred()?)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "rewards", "blocks", block_id]) => {
+            rewards::get_block_rewards(ctx, block_id)
+        }
+        (&Method::POST, ["lighthouse", "analysis", "block_rewards"]) => {
+            rewards::post_block_rewards(ctx, req.body(), is_ssz(req))
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "states", state_id, "root"]) => {
+            state::get_state_root(ctx, state_id)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "states", state_id, "validators"]) => {
+            state::get_validators(ctx, state_id, &query)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "states", state_id, "validators", validator_id]) => {
+            state::get_validator(ctx, state_id, validator_id)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "states", state_id, "validator_balances"]) => {
+            state::get_validator_balances(ctx, state_id, &query)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "states", state_id, "committees"]) => {
+            state::get_committees(ctx, state_id, &query)
+        }
+        (&Method::GET, ["eth", "v1", "beacon", "states", state_id, "randao"]) => {
+            state::get_randao(ctx, state_id)
+        }
+        (&Method::GET, ["lighthouse", "proofs", "states", state_id]) => {
+            proof::get_state_proofs(ctx, state_id, &query)
+        }
+        (&Method::GET, ["lighthouse", "validator_monitor", "withdrawals"]) => {
+            monitor::get_withdrawals(ctx)
+        }
+        (&Method::GET, ["lighthouse", "validator_registry", "deltas"]) => {
+            registry::get_deltas(ctx, &query)
+        }
+        (&Method::GET, ["eth", "v1", "debug", "beacon", "heads"]) => debug::get_heads(ctx),
+        (&Method::GET, ["eth", "v1", "debug", "beacon", "states", state_id]) => {
+            debug::get_state(ctx, state_id, accept.preferred()?)
+        }
+        (&Method::GET, ["eth", "v1", "debug", "fork_choice"]) => debug::get_fork_choice(ctx),
+        (&Method::GET, ["metrics"]) => Ok(metrics::get_metrics()),
+        (&Method::GET, ["eth", "v1", "node", "version"]) => node::get_version(),
+        (&Method::GET, ["eth", "v1", "node", "syncing"]) => node::get_syncing(ctx),
+        (&Method::GET, ["eth", "v1", "node", "health"]) => node::get_health(ctx, &query),
+        (&Method::GET, ["eth", "v1", "node", "peers"]) => node::get_peers(ctx, &query),
+        (&Method::POST, ["eth", "v1", "validator", "duties", "attester", epoch]) => {
+            validator::post_attester_duties(ctx, epoch, req.body())
+        }
+        (&Method::GET, ["eth", "v1", "validator", "duties", "proposer", epoch]) => {
+            validator::get_proposer_duties(ctx, epoch)
+        }
+        (&Method::POST, ["eth", "v1", "validator", "liveness", epoch]) => {
+            validator::post_liveness(ctx, epoch, req.body())
+        }
+        (&Method::POST, ["eth", "v1", "validator", "beacon_committee_subscriptions"]) => {
+            validator::post_subscriptions(ctx, req.body())
+        }
+        (&Method::GET, ["eth", "v1", "validator", "blocks", slot]) => {
+            validator::get_block(ctx, slot, &query, accept.preferred()?)
+        }
+        (&Method::GET, ["eth", "v1", "validator", "attestation_data"]) => {
+            validator::get_attestation_data(ctx, &query, accept.preferred()?)
+        }
+        (&Method::GET, ["eth", "v1", "validator", "aggregate_attestation"]) => {
+            validator::get_aggregate_attestation(ctx, &query, accept.preferred()?)
+        }
+        (&Method::POST, ["eth", "v1", "validator", "aggregate_and_proofs"]) => {
+            publish::post_aggregate_and_proofs(ctx, req.body())
+        }
+        _ => Err(ApiError::NotFound(format!(
+            "No endpoint for {} {}",
+            req.method(),
+            req.uri().path()
+        ))),
+    }?;
+    /*
+     * Endpoints serving a single encoding ignore the `Accept` header, so whether the client
+     * accepts it is checked here. Requests which change the node are not refused after the fact.
+     */
+    if req.method() == Method::GET {
+        accept.check(&response)?;
+    }
+    Ok(response)
+}
+
+/// Returns true if `req` publishes objects or changes the node's behaviour, rather than only
+/// reading from it.
+pub fn is_mutating<B>(req: &Request<B>) -> bool {
+    let path: Vec<&str> = req.uri().path().trim_matches('/').split('/').collect();
+    match *req.method() {
+        Method::POST => {
+            !path.starts_with(&["eth", "v1", "validator", "duties"])
+                && !path.starts_with(&["eth", "v1", "validator", "liveness"])
+                && !path.starts_with(&["lighthouse", "analysis"])
+        }
+        Method::DELETE | Method::PUT | Method::PATCH => true,
+        _ => false,
+    }
+}
+
+/// Returns true if `req` imports a block, and so runs a state transition.
+pub fn imports_block<B>(req: &Request<B>) -> bool {
+    req.method() == Method::POST && req.uri().path().trim_matches('/') == "eth/v1/beacon/blocks"
+}
+
+/// Returns true if the body of the request is SSZ.
+fn is_ssz(req: &Request<Vec<u8>>) -> bool {
+    match req.headers().get(CONTENT_TYPE).map(|t| t.to_str()) {
+        Some(Ok(content_type)) => content_type.starts_with(SSZ_MEDIA_TYPE),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::json::hex_bytes;
+    use super::*;
+    use beacon_node::BeaconNode;
+    use bls::{create_proof_of_possession, Keypair};
+    use db::stores::BeaconBlockStore;
+    use db::MemoryDB;
+    use hyper::header::ACCEPT;
+    use hyper::StatusCode;
+    use serde_json::{self, Value};
+    use slog::{Discard, Logger};
+    use ssz::ssz_encode;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::{Arc, RwLock};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use task_executor::TaskExecutor;
+    use types::{Address, BeaconBlock, ChainConfig, Hash256, ValidatorRegistration};
+    use PubsubMessage;
+
+    /// Returns a context with four validators, where the present slot is 100.
+    pub fn context() -> Context<MemoryDB> {
+        context_with_network().0
+    }
+
+    pub fn context_with_network() -> (Context<MemoryDB>, Receiver<PubsubMessage>) {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        context_with_validators(&keypairs)
+    }
+
+    /// Returns a context as `context_with_network`, with the validators of `keypairs`.
+    pub fn context_with_validators(
+        keypairs: &[Keypair],
+    ) -> (Context<MemoryDB>, Receiver<PubsubMessage>) {
+        let mut config = ChainConfig::standard();
+        config.cycle_length = 2;
+        config.shard_count = 2;
+        config.min_committee_size = 2;
+        config.initial_validators = keypairs
+            .iter()
+            .map(|keypair| ValidatorRegistration {
+                pubkey: keypair.pk.clone(),
+                withdrawal_shard: 0,
+                withdrawal_address: Address::random(),
+                randao_commitment: Hash256::random(),
+                proof_of_possession: create_proof_of_possession(keypair),
+            })
+            .collect();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        config.genesis_time = now.as_secs() - 100 * config.slot_duration_millis / 1000;
+
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        let node = Arc::new(RwLock::new(BeaconNode::new(config, store).unwrap()));
+        let (tx, rx) = channel();
+        let (executor, _) = TaskExecutor::new(Logger::root(Discard, o!()));
+        let ctx = Context::new(node, Some(tx), executor, Logger::root(Discard, o!()));
+        (ctx, rx)
+    }
+
+    /// Imports a block at `slot` on `parent`, returning its root.
+    pub fn import_block(ctx: &Context<MemoryDB>, parent: Hash256, slot: u64) -> Hash256 {
+        let mut block = BeaconBlock::zero();
+        block.slot = slot;
+        block.ancestor_hashes.push(parent);
+        ctx.node
+            .write()
+            .unwrap()
+            .process_block(&block, slot)
+            .unwrap();
+        block.canonical_root()
+    }
+
+    pub fn get(ctx: &Context<MemoryDB>, uri: &str) -> (StatusCode, Value) {
+        let req = Request::get(uri).body(vec![]).unwrap();
+        let response = handle(ctx, &req);
+        let body = serde_json::from_slice(response.body()).unwrap();
+        (response.status(), body)
+    }
+
+    #[test]
+    fn test_get_blocks() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let first = import_block(&ctx, genesis, 1);
+        import_block(&ctx, first, 3);
+
+        let (status, body) = get(&ctx, "/eth/v1/beacon/blocks/head");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["message"]["slot"], "3");
+        assert_eq!(body["data"]["message"]["parent_root"], hex_bytes(&first));
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/blocks/1");
+        assert_eq!(body["data"]["message"]["parent_root"], hex_bytes(&genesis));
+        let (_, body) = get(
+            &ctx,
+            &format!("/eth/v1/beacon/blocks/{}", hex_bytes(&first)),
+        );
+        assert_eq!(body["data"]["message"]["slot"], "1");
+        let (_, body) = get(&ctx, "/eth/v1/beacon/blocks/finalized");
+        assert_eq!(body["data"]["message"]["slot"], "0");
+
+        /*
+         * Skipped slots and unknown roots are not found.
+         */
+        let (status, body) = get(&ctx, "/eth/v1/beacon/blocks/2");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], 404);
+        let (status, _) = get(
+            &ctx,
+            &format!("/eth/v1/beacon/blocks/{}", hex_bytes(&Hash256::from(9))),
+        );
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&ctx, "/eth/v1/beacon/blocks/latest");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let req = Request::get("/eth/v1/beacon/blocks/genesis")
+            .header(ACCEPT, "application/octet-stream")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(handle(&ctx, &req).body(), &ssz_encode(&BeaconBlock::zero()));
+    }
+
+    #[test]
+    fn test_get_headers() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let first = import_block(&ctx, genesis, 1);
+        let second = import_block(&ctx, first, 2);
+        let fork = import_block(&ctx, first, 3);
+        let fork_child = import_block(&ctx, fork, 4);
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/headers");
+        assert_eq!(body["data"][0]["root"], hex_bytes(&fork_child));
+        assert_eq!(body["data"][0]["canonical"], true);
+
+        /*
+         * Both children of the first block are found, though only one is canonical.
+         */
+        let (_, body) = get(
+            &ctx,
+            &format!("/eth/v1/beacon/headers?parent_root={}", hex_bytes(&first)),
+        );
+        let headers = body["data"].as_array().unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[0]["root"], hex_bytes(&second));
+        assert_eq!(headers[0]["canonical"], false);
+        assert_eq!(headers[1]["root"], hex_bytes(&fork));
+        assert_eq!(headers[1]["canonical"], true);
+        assert_eq!(headers[1]["header"]["message"]["slot"], "3");
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/headers?slot=2");
+        assert_eq!(body["data"][0]["root"], hex_bytes(&second));
+        let (_, body) = get(&ctx, "/eth/v1/beacon/headers/genesis");
+        assert_eq!(body["data"]["root"], hex_bytes(&genesis));
+        assert_eq!(
+            body["data"]["header"]["message"]["parent_root"],
+            Value::Null
+        );
+        let (status, _) = get(&ctx, "/eth/v1/beacon/headers?slot=two");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        /*
+         * As SSZ, a header is its slot followed by four roots.
+         */
+        let get_ssz = |uri: &str| {
+            let req = Request::get(uri)
+                .header(ACCEPT, "application/octet-stream")
+                .body(vec![])
+                .unwrap();
+            handle(&ctx, &req).into_body()
+        };
+        let header = get_ssz(&format!("/eth/v1/beacon/headers/{}", hex_bytes(&fork)));
+        assert_eq!(header.len(), 8 + 4 * 32);
+        assert_eq!(&header[..8], &ssz_encode(&3u64)[..]);
+        assert_eq!(&header[8..40], &first[..]);
+        let headers = get_ssz(&format!(
+            "/eth/v1/beacon/headers?parent_root={}",
+            hex_bytes(&first)
+        ));
+        assert_eq!(
+            &headers[4..],
+            &[&get_ssz("/eth/v1/beacon/headers/2")[..], &header[..]].concat()[..]
+        );
+    }
+
+    #[test]
+    fn test_not_acceptable() {
+        let ctx = context();
+        let request = |uri: &str, accept: &str| {
+            let req = Request::get(uri)
+                .header(ACCEPT, accept)
+                .body(vec![])
+                .unwrap();
+            handle(&ctx, &req).status()
+        };
+        assert_eq!(
+            request("/eth/v1/node/version", "application/octet-stream"),
+            StatusCode::NOT_ACCEPTABLE
+        );
+        assert_eq!(
+            request(
+                "/eth/v1/node/version",
+                "application/octet-stream, */*;q=0.1"
+            ),
+            StatusCode::OK
+        );
+        assert_eq!(
+            request("/eth/v1/beacon/blocks/head", "text/html"),
+            StatusCode::NOT_ACCEPTABLE
+        );
+        assert_eq!(
+            request("/eth/v1/beacon/blocks/head", "application/*"),
+            StatusCode::OK
+        );
+    }
+}
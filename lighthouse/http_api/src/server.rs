@@ -0,0 +1,368 @@
+use super::config::{ApiConfig, TlsConfig};
+use super::cors::Cors;
+use super::error::ApiError;
+use super::router::{imports_block, is_mutating, serve};
+use super::Context;
+use db::ClientDB;
+use futures::future::{self, Either};
+use futures::sync::oneshot;
+use futures::{Future, Stream};
+use hyper::header::CONTENT_LENGTH;
+use hyper::http::request::Parts;
+use hyper::service::service_fn;
+use hyper::{Body, Request, Response, Server};
+use native_tls::{self, Identity};
+use network::gossip::GOSSIP_MAX_SIZE;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use task_executor::TaskExecutor;
+use tokio_tcp::TcpListener;
+use tokio_tls::TlsAcceptor;
+
+/// The most TLS handshakes in progress at once.
+const MAX_CONCURRENT_HANDSHAKES: usize = 64;
+
+/// The largest request body accepted: the JSON of a block of `GOSSIP_MAX_SIZE` bytes of SSZ, in
+/// which hex doubles the size of every byte, with room to spare.
+pub const MAX_REQUEST_BODY_SIZE: usize = 4 * GOSSIP_MAX_SIZE;
+
+type ServerFuture = Box<dyn Future<Item = (), Error = ()> + Send>;
+
+type BodyFuture = Box<dyn Future<Item = Result<Vec<u8>, ApiError>, Error = hyper::Error> + Send>;
+
+#[derive(Debug)]
+pub enum ApiServerError {
+    Hyper(hyper::Error),
+    Io(io::Error),
+    /// The certificate or key is invalid.
+    Tls(native_tls::Error),
+}
+
+impl From<hyper::Error> for ApiServerError {
+    fn from(e: hyper::Error) -> ApiServerError {
+        ApiServerError::Hyper(e)
+    }
+}
+
+impl From<io::Error> for ApiServerError {
+    fn from(e: io::Error) -> ApiServerError {
+        ApiServerError::Io(e)
+    }
+}
+
+impl From<native_tls::Error> for ApiServerError {
+    fn from(e: native_tls::Error) -> ApiServerError {
+        ApiServerError::Tls(e)
+    }
+}
+
+/// Serves the HTTP API on a background thread.
+///
+/// The server stops accepting connections when dropped, and `drop` returns once open
+/// connections are closed.
+pub struct ApiServer {
+    local_addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    closing: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ApiServer {
+    pub fn start<T: ClientDB + 'static>(
+        config: &ApiConfig,
+        ctx: Arc<Context<T>>,
+        executor: &TaskExecutor,
+    ) -> Result<Self, ApiServerError> {
+        let addr = SocketAddr::new(config.listen_address, config.port);
+        let log = ctx.log.clone();
+        let closing = ctx.closing.clone();
+        let cors = Cors::new(config.allow_origins.clone());
+        let read_only = config.read_only;
+
+        let new_service = move || {
+            let ctx = ctx.clone();
+            let cors = cors.clone();
+            service_fn(move |req: Request<Body>| {
+                let ctx = ctx.clone();
+                let cors = cors.clone();
+                let (parts, body) = req.into_parts();
+                /*
+                 * The body is collected before routing, as requests are small and every endpoint
+                 * needs all of it.
+                 */
+                collect_body(&parts, body).and_then(move |body| {
+                    let body = match body {
+                        Ok(body) => body,
+                        Err(e) => return Either::A(future::ok(e.into_response().map(Body::from))),
+                    };
+                    let req = Request::from_parts(parts, body);
+                    let pool = match ctx.transition_pool {
+                        Some(ref pool) if imports_block(&req) => pool.clone(),
+                        _ => return Either::A(future::ok(respond(&ctx, &cors, read_only, &req))),
+                    };
+                    /*
+                     * Block imports run on the state transition threads, so that a slow cycle
+                     * transition does not hold up the runtime serving other requests.
+                     */
+                    let (tx, rx) = oneshot::channel();
+                    pool.spawn(move || {
+                        let _ = tx.send(respond(&ctx, &cors, read_only, &req));
+                    });
+                    Either::B(rx.or_else(|_| {
+                        Ok::<_, hyper::Error>(
+                            ApiError::ServiceUnavailable("The node is stopping".to_string())
+                                .into_response()
+                                .map(Body::from),
+                        )
+                    }))
+                })
+            })
+        };
+
+        if !addr.ip().is_loopback() && config.tls.is_none() {
+            warn!(log, "HTTP API exposed without TLS"; "address" => format!("{}", addr));
+        }
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let (local_addr, server): (SocketAddr, ServerFuture) = match config.tls {
+            Some(ref tls) => {
+                let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity(tls)?)?);
+                let listener = TcpListener::bind(&addr)?;
+                let local_addr = listener.local_addr()?;
+                /*
+                 * Connections which fail to be accepted or to complete the handshake are dropped
+                 * without stopping the server.
+                 */
+                let incoming = listener
+                    .incoming()
+                    .then(|stream| Ok::<_, io::Error>(stream.ok()))
+                    .filter_map(|stream| stream)
+                    .map(move |stream| {
+                        acceptor
+                            .accept(stream)
+                            .then(|stream| Ok::<_, io::Error>(stream.ok()))
+                    })
+                    .buffer_unordered(MAX_CONCURRENT_HANDSHAKES)
+                    .filter_map(|stream| stream);
+                let log = log.clone();
+                let server = Server::builder(incoming)
+                    .serve(new_service)
+                    .with_graceful_shutdown(shutdown_rx)
+                    .map_err(move |e| error!(log, "HTTP API failed"; "error" => format!("{}", e)));
+                (local_addr, Box::new(server))
+            }
+            None => {
+                let server = Server::try_bind(&addr)?.serve(new_service);
+                let local_addr = server.local_addr();
+                let log = log.clone();
+                let server = server
+                    .with_graceful_shutdown(shutdown_rx)
+                    .map_err(move |e| error!(log, "HTTP API failed"; "error" => format!("{}", e)));
+                (local_addr, Box::new(server))
+            }
+        };
+        info!(log, "HTTP API started"; "address" => format!("{}", local_addr), "tls" => config.tls.is_some());
+        let handle = executor.spawn("http_api", move |_| hyper::rt::run(server));
+
+        Ok(Self {
+            local_addr,
+            shutdown: Some(shutdown),
+            closing,
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the address on which the server is listening.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+/// Collects `body`, failing with `ApiError::PayloadTooLarge` if it exceeds
+/// `MAX_REQUEST_BODY_SIZE`: before reading any of it if its declared length does, and otherwise
+/// as soon as it does, without reading the rest.
+fn collect_body(parts: &Parts, body: Body) -> BodyFuture {
+    enum BodyError {
+        Hyper(hyper::Error),
+        TooLarge,
+    }
+    let too_large =
+        || ApiError::PayloadTooLarge(format!("The body exceeds {} bytes", MAX_REQUEST_BODY_SIZE));
+
+    let declared = parts
+        .headers
+        .get(CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| len.parse::<u64>().ok());
+    if declared.map_or(false, |len| len > MAX_REQUEST_BODY_SIZE as u64) {
+        return Box::new(future::ok(Err(too_large())));
+    }
+    let collected = body
+        .map_err(BodyError::Hyper)
+        .fold(vec![], |mut collected, chunk| {
+            if collected.len() + chunk.len() > MAX_REQUEST_BODY_SIZE {
+                return Err(BodyError::TooLarge);
+            }
+            collected.extend_from_slice(&chunk);
+            Ok(collected)
+        })
+        .then(move |result| match result {
+            Ok(body) => Ok(Ok(body)),
+            Err(BodyError::TooLarge) => Ok(Err(too_large())),
+            Err(BodyError::Hyper(e)) => Err(e),
+        });
+    Box::new(collected)
+}
+
+/// Serves `req`, unless it is a preflight or is refused as the API is read-only.
+fn respond<T: ClientDB>(
+    ctx: &Context<T>,
+    cors: &Cors,
+    read_only: bool,
+    req: &Request<Vec<u8>>,
+) -> Response<Body> {
+    if let Some(response) = cors.preflight(req) {
+        return response.map(Body::from);
+    }
+    let mut response = if read_only && is_mutating(req) {
+        ApiError::Forbidden("The API is read-only".to_string())
+            .into_response()
+            .map(Body::from)
+    } else {
+        serve(ctx, req)
+    };
+    cors.apply(req, &mut response);
+    response
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        /*
+         * Event streams never end by themselves, so they must be closed for the server to stop.
+         */
+        self.closing.store(true, Ordering::Relaxed);
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn identity(tls: &TlsConfig) -> Result<Identity, ApiServerError> {
+    let cert = fs::read(&tls.cert)?;
+    let key = fs::read(&tls.key)?;
+    Ok(Identity::from_pkcs8(&cert, &key)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::tests::context;
+    use super::*;
+    use beacon_node::StateTransitionPool;
+    use slog::{Discard, Logger};
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, TcpStream};
+
+    fn config() -> ApiConfig {
+        ApiConfig {
+            enabled: true,
+            listen_address: Ipv4Addr::LOCALHOST.into(),
+            port: 0,
+            ..ApiConfig::default()
+        }
+    }
+
+    fn executor() -> TaskExecutor {
+        TaskExecutor::new(Logger::root(Discard, o!())).0
+    }
+
+    fn request(server: &ApiServer, request: &str) -> String {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serves_requests() {
+        let server = ApiServer::start(&config(), Arc::new(context()), &executor()).unwrap();
+        let response = request(
+            &server,
+            "GET /eth/v1/beacon/headers/head HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"slot\":\"0\""));
+        drop(server);
+    }
+
+    #[test]
+    fn test_block_imports_on_transition_pool() {
+        let mut ctx = context();
+        ctx.transition_pool = Some(Arc::new(StateTransitionPool::start(1, &executor())));
+        let server = ApiServer::start(&config(), Arc::new(ctx), &executor()).unwrap();
+        let response = request(
+            &server,
+            "POST /eth/v1/beacon/blocks HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 2\r\n\r\n{}",
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(response.contains("Missing field: message"));
+    }
+
+    #[test]
+    fn test_body_size_limit() {
+        let server = ApiServer::start(&config(), Arc::new(context()), &executor()).unwrap();
+        let response = request(
+            &server,
+            &format!(
+                "POST /eth/v1/beacon/pool/attestations HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n[]",
+                MAX_REQUEST_BODY_SIZE + 1
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    }
+
+    #[test]
+    fn test_read_only_and_cors() {
+        let config = ApiConfig {
+            allow_origins: vec!["https://a.example".to_string()],
+            read_only: true,
+            ..config()
+        };
+        let server = ApiServer::start(&config, Arc::new(context()), &executor()).unwrap();
+        let response = request(
+            &server,
+            "POST /eth/v1/beacon/pool/attestations HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 2\r\n\r\n[]",
+        );
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden"));
+
+        let response = request(
+            &server,
+            "GET /eth/v1/node/version HTTP/1.1\r\nHost: localhost\r\nOrigin: https://a.example\r\nConnection: close\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response
+            .to_lowercase()
+            .contains("access-control-allow-origin: https://a.example"));
+    }
+
+    #[test]
+    fn test_invalid_tls_config() {
+        let config = ApiConfig {
+            tls: Some(TlsConfig {
+                cert: "/nonexistent/cert.pem".into(),
+                key: "/nonexistent/key.pem".into(),
+            }),
+            ..config()
+        };
+        match ApiServer::start(&config, Arc::new(context()), &executor()) {
+            Err(ApiServerError::Io(_)) => {}
+            _ => panic!("expected an io error"),
+        }
+    }
+}
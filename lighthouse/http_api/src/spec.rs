@@ -0,0 +1,106 @@
+use super::error::ApiResult;
+use super::json::{data_response, hex_bytes};
+use super::Context;
+use beacon_node::validators_tree;
+use db::ClientDB;
+use network::rpc::ForkDigest;
+
+/// The fork version of the genesis block. There has been no fork since.
+pub const GENESIS_FORK_VERSION: u64 = 0;
+
+/*
+ * These endpoints let a validator client check that it shares the node's network before it
+ * signs anything.
+ */
+
+/// `GET /eth/v1/beacon/genesis`
+///
+/// `genesis_validators_root` is the root of the tree of validator hashes used by state proofs.
+pub fn get_genesis<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let genesis_root = node.genesis_root();
+    Ok(data_response(json!({
+        "genesis_time": node.config().genesis_time.to_string(),
+        "genesis_block_root": hex_bytes(&genesis_root),
+        "genesis_validators_root": hex_bytes(&validators_tree(node.validators()).root()),
+        "genesis_fork_version": fork_version_hex(GENESIS_FORK_VERSION),
+        "fork_digest": hex_bytes(&ForkDigest::new(GENESIS_FORK_VERSION, &genesis_root).0),
+    })))
+}
+
+/// `GET /eth/v1/config/fork_schedule`
+pub fn get_fork_schedule<T: ClientDB>(_ctx: &Context<T>) -> ApiResult {
+    Ok(data_response(json!([{
+        "previous_version": fork_version_hex(GENESIS_FORK_VERSION),
+        "current_version": fork_version_hex(GENESIS_FORK_VERSION),
+        "epoch": "0",
+    }])))
+}
+
+/// `GET /eth/v1/config/spec`
+///
+/// Returns the chain config, with every value quoted. The initial validators are omitted, being
+/// committed to by the genesis validators root.
+pub fn get_spec<T: ClientDB>(ctx: &Context<T>) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let config = node.config();
+    Ok(data_response(json!({
+        "CYCLE_LENGTH": config.cycle_length.to_string(),
+        "DEPOSIT_SIZE_GWEI": config.deposit_size_gwei.to_string(),
+        "SHARD_COUNT": config.shard_count.to_string(),
+        "MIN_COMMITTEE_SIZE": config.min_committee_size.to_string(),
+        "MAX_VALIDATOR_CHURN_QUOTIENT": config.max_validator_churn_quotient.to_string(),
+        "GENESIS_TIME": config.genesis_time.to_string(),
+        "SLOT_DURATION_MILLIS": config.slot_duration_millis.to_string(),
+        "EPOCH_LENGTH": config.epoch_length.to_string(),
+        "MIN_ATTESTATION_INCLUSION_DELAY": config.min_attestation_inclusion_delay.to_string(),
+        "INITIAL_VALIDATOR_COUNT": config.initial_validators.len().to_string(),
+        "GENESIS_FORK_VERSION": fork_version_hex(GENESIS_FORK_VERSION),
+    })))
+}
+
+/// Fork versions are shown as four bytes, as in the fork digest.
+fn fork_version_hex(version: u64) -> String {
+    hex_bytes(&[
+        (version >> 24) as u8,
+        (version >> 16) as u8,
+        (version >> 8) as u8,
+        version as u8,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::tests::{context, get};
+    use super::*;
+    use hyper::StatusCode;
+
+    #[test]
+    fn test_genesis_and_config() {
+        let ctx = context();
+        let (genesis_root, genesis_time) = {
+            let node = ctx.node.read().unwrap();
+            (node.genesis_root(), node.config().genesis_time)
+        };
+
+        let (status, body) = get(&ctx, "/eth/v1/beacon/genesis");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["genesis_time"], genesis_time.to_string());
+        assert_eq!(body["data"]["genesis_block_root"], hex_bytes(&genesis_root));
+        assert_eq!(body["data"]["genesis_fork_version"], "0x00000000");
+        assert_eq!(
+            body["data"]["fork_digest"],
+            hex_bytes(&ForkDigest::new(0, &genesis_root).0)
+        );
+
+        let (_, body) = get(&ctx, "/eth/v1/config/fork_schedule");
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(body["data"][0]["current_version"], "0x00000000");
+
+        let (_, body) = get(&ctx, "/eth/v1/config/spec");
+        assert_eq!(body["data"]["CYCLE_LENGTH"], "2");
+        assert_eq!(body["data"]["SHARD_COUNT"], "2");
+        assert_eq!(body["data"]["INITIAL_VALIDATOR_COUNT"], "4");
+        assert_eq!(body["data"]["GENESIS_TIME"], genesis_time.to_string());
+    }
+}
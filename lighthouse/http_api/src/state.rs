@@ -0,0 +1,382 @@
+use super::block_id::BlockId;
+use super::error::{ApiError, ApiResult};
+use super::json::{
+    committee_json, data_response, hex_bytes, page_response, parse_hex, validator_json,
+    validator_status_name,
+};
+use super::query::Query;
+use super::Context;
+use beacon_node::{BeaconNode, RegeneratedState};
+use bls::PublicKey;
+use db::ClientDB;
+use serde_json::Value;
+use std::sync::Arc;
+use types::{BeaconBlock, Hash256, ValidatorRecord, ValidatorStatus};
+
+/// The most validators, or balances, returned in a page.
+pub const MAX_VALIDATORS_PER_PAGE: usize = 1_000;
+
+const VALIDATOR_STATUSES: [ValidatorStatus; 6] = [
+    ValidatorStatus::PendingActivation,
+    ValidatorStatus::Active,
+    ValidatorStatus::PendingExit,
+    ValidatorStatus::PendingWithdraw,
+    ValidatorStatus::Withdrawn,
+    ValidatorStatus::Penalized,
+];
+
+/*
+ * States are not yet stored, so a state is identified by the block it follows, using the same
+ * identifiers as blocks. Until the state transition is restored, the validator set is fixed at
+ * genesis, so every state has the same validators and committees.
+ */
+
+/// `GET /eth/v1/beacon/states/{state_id}/root`
+pub fn get_state_root<T: ClientDB>(ctx: &Context<T>, state_id: &str) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (_, block) = state_block(&node, state_id)?;
+    Ok(data_response(
+        json!({ "root": hex_bytes(&block.crystallized_state_root) }),
+    ))
+}
+
+/// `GET /eth/v1/beacon/states/{state_id}/validators?id,status,offset,limit`
+///
+/// `id` is a validator index or public key. Both `id` and `status` may be repeated or
+/// comma-separated, and unknown ids are ignored.
+pub fn get_validators<T: ClientDB>(ctx: &Context<T>, state_id: &str, query: &Query) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    state_block(&node, state_id)?;
+    let validators = filter_validators(&node, query)?;
+    let total = validators.len();
+    let page = query
+        .pagination(MAX_VALIDATORS_PER_PAGE)?
+        .page(validators)
+        .into_iter()
+        .map(|(index, validator)| validator_json(index, validator))
+        .collect();
+    Ok(page_response(page, total))
+}
+
+/// `GET /eth/v1/beacon/states/{state_id}/validators/{validator_id}`
+///
+/// `validator_id` is a validator index or public key. Any state of the canonical chain may be
+/// given, including historical slots; as the validator set is fixed at genesis, no state needs to
+/// be reconstructed to answer for them.
+pub fn get_validator<T: ClientDB>(
+    ctx: &Context<T>,
+    state_id: &str,
+    validator_id: &str,
+) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    state_block(&node, state_id)?;
+    let index = validator_index(&node, validator_id)?
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown validator: {}", validator_id)))?;
+    Ok(data_response(validator_json(
+        index,
+        &node.validators()[index],
+    )))
+}
+
+/// `GET /eth/v1/beacon/states/{state_id}/validator_balances?id,status,offset,limit`
+pub fn get_validator_balances<T: ClientDB>(
+    ctx: &Context<T>,
+    state_id: &str,
+    query: &Query,
+) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    state_block(&node, state_id)?;
+    let validators = filter_validators(&node, query)?;
+    let total = validators.len();
+    let page = query
+        .pagination(MAX_VALIDATORS_PER_PAGE)?
+        .page(validators)
+        .into_iter()
+        .map(|(index, validator)| {
+            json!({
+                "index": index.to_string(),
+                "balance": validator.balance.to_string(),
+            })
+        })
+        .collect();
+    Ok(page_response(page, total))
+}
+
+/// `GET /eth/v1/beacon/states/{state_id}/committees?cycle,slot,shard`
+///
+/// Returns the committees of the cycle of the state, unless another `cycle` is given.
+pub fn get_committees<T: ClientDB>(ctx: &Context<T>, state_id: &str, query: &Query) -> ApiResult {
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let (_, block) = state_block(&node, state_id)?;
+    let cycle_length = u64::from(node.config().cycle_length.max(1));
+    let cycle = query
+        .parse_value::<u64>("cycle")?
+        .unwrap_or(block.slot / cycle_length);
+    let slot = query.parse_value::<u64>("slot")?;
+    let shard = query.parse_value::<u16>("shard")?;
+    let start_slot = cycle
+        .checked_mul(cycle_length)
+        .filter(|start_slot| start_slot.checked_add(cycle_length).is_some())
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid cycle: {}", cycle)))?;
+
+    let mut committees = vec![];
+    for s in start_slot..start_slot + cycle_length {
+        if slot.is_some() && slot != Some(s) {
+            continue;
+        }
+        for committee in node.committees(s) {
+            if shard.is_none() || shard == Some(committee.shard) {
+                committees.push(committee_json(s, committee));
+            }
+        }
+    }
+    Ok(data_response(Value::Array(committees)))
+}
+
+/// `GET /eth/v1/beacon/states/{state_id}/randao`
+///
+/// The randao mix of the active state, which is regenerated from the blocks if not cached.
+pub fn get_randao<T: ClientDB>(ctx: &Context<T>, state_id: &str) -> ApiResult {
+    let state = regenerated_state(ctx, state_id)?;
+    Ok(data_response(
+        json!({ "randao": hex_bytes(&state.active_state.randao_mix) }),
+    ))
+}
+
+/// Returns the state identified by `state_id`, regenerated from the blocks if not cached.
+pub fn regenerated_state<T: ClientDB>(
+    ctx: &Context<T>,
+    state_id: &str,
+) -> Result<Arc<RegeneratedState>, ApiError> {
+    let regen = ctx
+        .regen
+        .as_ref()
+        .ok_or_else(|| ApiError::Forbidden("State regeneration is not enabled".to_string()))?;
+    let (root, _) = {
+        let node = ctx.node.read().expect("Beacon node lock poisoned");
+        state_block(&node, state_id)?
+    };
+    regen
+        .state(root)
+        .map_err(|e| ApiError::ServerError(format!("Unable to regenerate state: {:?}", e)))
+}
+
+pub fn state_block<T: ClientDB>(
+    node: &BeaconNode<T>,
+    state_id: &str,
+) -> Result<(Hash256, BeaconBlock), ApiError> {
+    let block_id: BlockId = state_id.parse()?;
+    block_id
+        .block(node)
+        .map_err(|_| ApiError::NotFound(format!("Unknown state: {}", state_id)))
+}
+
+/// Returns the validators selected by the `id` and `status` parameters of `query`, with their
+/// indices.
+fn filter_validators<'a, T: ClientDB>(
+    node: &'a BeaconNode<T>,
+    query: &Query,
+) -> Result<Vec<(usize, &'a ValidatorRecord)>, ApiError> {
+    let ids = query.get_all("id");
+    let indices = if ids.is_empty() {
+        None
+    } else {
+        let mut indices = vec![];
+        for id in ids {
+            if let Some(index) = validator_index(node, id)? {
+                indices.push(index);
+            }
+        }
+        Some(indices)
+    };
+
+    let statuses = query.get_all("status");
+    for status in &statuses {
+        if !VALIDATOR_STATUSES
+            .iter()
+            .any(|s| validator_status_name(*s as u8) == *status)
+        {
+            return Err(ApiError::BadRequest(format!("Invalid status: {}", status)));
+        }
+    }
+
+    Ok(node
+        .validators()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| match indices {
+            Some(ref indices) => indices.contains(i),
+            None => true,
+        })
+        .filter(|(_, v)| statuses.is_empty() || statuses.contains(&validator_status_name(v.status)))
+        .collect())
+}
+
+/// Returns the index of the validator identified by an index or `0x`-prefixed public key, if it
+/// is known.
+fn validator_index<T: ClientDB>(node: &BeaconNode<T>, id: &str) -> Result<Option<usize>, ApiError> {
+    let invalid = || ApiError::BadRequest(format!("Invalid validator id: {}", id));
+    if id.starts_with("0x") {
+        let bytes = parse_hex(id).ok_or_else(invalid)?;
+        let pubkey = PublicKey::from_bytes(&bytes).map_err(|_| invalid())?;
+        Ok(node.validator_index(&pubkey))
+    } else {
+        let index = id.parse::<usize>().map_err(|_| invalid())?;
+        Ok(Some(index).filter(|i| *i < node.validators().len()))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::router::tests::{context, get, import_block};
+    use super::*;
+    use beacon_node::{StateRegenService, StateRegenerator, DEFAULT_REGEN_CACHE_SIZE};
+    use db::stores::{StatePruning, StateStore};
+    use db::MemoryDB;
+    use hyper::StatusCode;
+    use slog::{Discard, Logger};
+    use std::sync::Arc;
+    use task_executor::TaskExecutor;
+
+    #[test]
+    fn test_get_validators() {
+        let ctx = context();
+        let pubkey = ctx.node.read().unwrap().validators()[2].pubkey.clone();
+
+        let (status, body) = get(&ctx, "/eth/v1/beacon/states/head/validators");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["meta"]["total"], 4);
+        assert_eq!(body["data"][0]["status"], "active");
+
+        let (_, body) = get(
+            &ctx,
+            &format!(
+                "/eth/v1/beacon/states/genesis/validators?id=0,{}&id=99",
+                hex_bytes(&pubkey.as_bytes())
+            ),
+        );
+        let validators = body["data"].as_array().unwrap();
+        assert_eq!(validators.len(), 2);
+        assert_eq!(validators[1]["index"], "2");
+
+        let (_, body) = get(
+            &ctx,
+            "/eth/v1/beacon/states/head/validator_balances?offset=1&limit=2",
+        );
+        assert_eq!(body["meta"]["total"], 4);
+        assert_eq!(body["data"].as_array().unwrap().len(), 2);
+        assert_eq!(body["data"][0]["index"], "1");
+
+        let (_, body) = get(
+            &ctx,
+            "/eth/v1/beacon/states/head/validators?status=withdrawn",
+        );
+        assert_eq!(body["meta"]["total"], 0);
+        let (status, _) = get(&ctx, "/eth/v1/beacon/states/head/validators?status=asleep");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&ctx, "/eth/v1/beacon/states/7/validators");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_get_validator() {
+        let ctx = context();
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        import_block(&ctx, genesis, 2);
+        let pubkey = ctx.node.read().unwrap().validators()[1].pubkey.clone();
+
+        let (status, body) = get(&ctx, "/eth/v1/beacon/states/head/validators/1");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["index"], "1");
+        assert_eq!(body["data"]["status"], "active");
+
+        /*
+         * Historical states give the same answer, by slot or by public key.
+         */
+        for state_id in &["genesis", "finalized", "0", "2"] {
+            let uri = format!(
+                "/eth/v1/beacon/states/{}/validators/{}",
+                state_id,
+                hex_bytes(&pubkey.as_bytes())
+            );
+            let (status, historical) = get(&ctx, &uri);
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(historical, body);
+        }
+
+        let (status, _) = get(&ctx, "/eth/v1/beacon/states/head/validators/4");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&ctx, "/eth/v1/beacon/states/head/validators/0x01");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&ctx, "/eth/v1/beacon/states/3/validators/1");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_get_committees() {
+        let ctx = context();
+        let (_, body) = get(&ctx, "/eth/v1/beacon/states/head/committees");
+        let committees = body["data"].as_array().unwrap();
+        assert_eq!(committees.len(), 2);
+        let members: usize = committees
+            .iter()
+            .map(|c| c["validators"].as_array().unwrap().len())
+            .sum();
+        assert_eq!(members, 4);
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/states/head/committees?cycle=3&slot=7");
+        assert_eq!(body["data"][0]["slot"], "7");
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        let path = format!("/eth/v1/beacon/states/head/committees?cycle={}", u64::MAX);
+        let (status, _) = get(&ctx, &path);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, body) = get(&ctx, "/eth/v1/beacon/states/head/root");
+        assert_eq!(body["data"]["root"], hex_bytes(&Hash256::zero()));
+    }
+
+    /// Enables state regeneration, with a single worker.
+    pub fn enable_regen(ctx: &mut Context<MemoryDB>) {
+        let regenerator = {
+            let node = ctx.node.read().unwrap();
+            StateRegenerator::new(
+                node.store().clone(),
+                StateStore::new(Arc::new(MemoryDB::open())),
+                StatePruning::Archive,
+                node.genesis_root(),
+                node.config().cycle_length,
+                DEFAULT_REGEN_CACHE_SIZE,
+            )
+        };
+        ctx.regen = Some(Arc::new(StateRegenService::start(
+            regenerator,
+            1,
+            &TaskExecutor::new(Logger::root(Discard, o!())).0,
+            Logger::root(Discard, o!()),
+        )));
+    }
+
+    #[test]
+    fn test_get_randao() {
+        let mut ctx = context();
+        let (status, _) = get(&ctx, "/eth/v1/beacon/states/head/randao");
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        enable_regen(&mut ctx);
+        let genesis = ctx.node.read().unwrap().genesis_root();
+        let mut block = BeaconBlock::zero();
+        block.slot = 1;
+        block.randao_reveal = Hash256::from(7);
+        block.ancestor_hashes.push(genesis);
+        ctx.node.write().unwrap().process_block(&block, 1).unwrap();
+        import_block(&ctx, block.canonical_root(), 2);
+
+        let (status, body) = get(&ctx, "/eth/v1/beacon/states/head/randao");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["randao"], hex_bytes(&Hash256::from(7)));
+        let (_, body) = get(&ctx, "/eth/v1/beacon/states/genesis/randao");
+        assert_eq!(body["data"]["randao"], hex_bytes(&Hash256::zero()));
+        let (status, _) = get(&ctx, "/eth/v1/beacon/states/99/randao");
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,757 @@
+use super::encoding::Encoding;
+use super::error::{ApiError, ApiResult};
+use super::json::{
+    attestation_data_json, attestation_json, block_json, data_response, hex_bytes, json_response,
+    parse_hash,
+};
+use super::node::sync_status;
+use super::query::Query;
+use super::Context;
+use beacon_node::BeaconNode;
+use db::ClientDB;
+use hashing::canonical_hash;
+use hyper::Response;
+use network::enr::ATTESTATION_SUBNET_COUNT;
+use serde_json::{self, Value};
+use ssz::{ssz_encode, Decodable};
+use std::ops::Range;
+use types::{BeaconBlock, Hash256};
+
+/*
+ * Duties are assigned per cycle, so the `epoch` of a request is a cycle.
+ *
+ * Duty responses include the `dependent_root` of the cycle: the root of the last canonical block
+ * before the cycle starts. Should the chain reorganise past that block, the duties may change and
+ * a validator client must fetch them again.
+ *
+ * Duties are refused while the node is not synced, as they are computed from a head which may be
+ * far behind the chain of the network.
+ */
+
+/// `POST /eth/v1/validator/duties/attester/{epoch}`
+///
+/// The body is a JSON array of validator indices, as strings. Validators without an attestation
+/// duty in the cycle are omitted.
+pub fn post_attester_duties<T: ClientDB>(ctx: &Context<T>, epoch: &str, body: &[u8]) -> ApiResult {
+    let cycle = parse_epoch(epoch)?;
+    let indices = parse_indices(body)?;
+    require_synced(ctx)?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let dependent_root = dependent_root(&node, cycle)?;
+
+    let mut duties = vec![];
+    for index in indices {
+        let validator = node
+            .validators()
+            .get(index)
+            .ok_or_else(|| ApiError::BadRequest(format!("Unknown validator: {}", index)))?;
+        let duty = node.validator_duties(index, cycle)?;
+        if let (Some(slot), Some(shard), Some(committee_index)) = (
+            duty.attestation_slot,
+            duty.attestation_shard,
+            duty.committee_index,
+        ) {
+            let committee_length = node.committee(slot, shard).map_or(0, |c| c.len());
+            duties.push(json!({
+                "pubkey": hex_bytes(&validator.pubkey.as_bytes()),
+                "validator_index": index.to_string(),
+                "slot": slot.to_string(),
+                "shard": shard.to_string(),
+                "committee_index": committee_index.to_string(),
+                "committee_length": committee_length.to_string(),
+            }));
+        }
+    }
+    Ok(duties_response(dependent_root, duties))
+}
+
+/// `GET /eth/v1/validator/duties/proposer/{epoch}`
+pub fn get_proposer_duties<T: ClientDB>(ctx: &Context<T>, epoch: &str) -> ApiResult {
+    let cycle = parse_epoch(epoch)?;
+    require_synced(ctx)?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let cycle_length = u64::from(node.config().cycle_length);
+    let dependent_root = dependent_root(&node, cycle)?;
+
+    let mut duties = vec![];
+    for slot in cycle_slots(cycle, cycle_length)? {
+        if let Some(index) = node.block_proposer(slot) {
+            duties.push(json!({
+                "pubkey": hex_bytes(&node.validators()[index].pubkey.as_bytes()),
+                "validator_index": index.to_string(),
+                "slot": slot.to_string(),
+            }));
+        }
+    }
+    Ok(duties_response(dependent_root, duties))
+}
+
+/// `POST /eth/v1/validator/liveness/{epoch}`
+///
+/// The body is a JSON array of validator indices, as strings. A validator is live if it was seen
+/// attesting in the cycle, which is known only for the last `LIVENESS_CYCLES` cycles.
+pub fn post_liveness<T: ClientDB>(ctx: &Context<T>, epoch: &str, body: &[u8]) -> ApiResult {
+    let cycle = parse_epoch(epoch)?;
+    let indices = parse_indices(body)?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+
+    let mut liveness = vec![];
+    for index in indices {
+        if index >= node.validators().len() {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown validator: {}",
+                index
+            )));
+        }
+        liveness.push(json!({
+            "index": index.to_string(),
+            "is_live": node.is_live(index, cycle),
+        }));
+    }
+    Ok(data_response(Value::Array(liveness)))
+}
+
+/// `POST /eth/v1/validator/beacon_committee_subscriptions`
+///
+/// The body is a JSON array of `{"validator_index", "slot", "shard", "is_aggregator"}`, one for
+/// each attestation duty, where `is_aggregator` may be omitted if `false`. The subnet of each
+/// shard is kept in `subnet_subscriptions` until the duty's slot has passed, so that the node
+/// joins it ahead of the duty, and aggregators are kept in `aggregator_duties` until then.
+pub fn post_subscriptions<T: ClientDB>(ctx: &Context<T>, body: &[u8]) -> ApiResult {
+    let invalid = || ApiError::BadRequest("Body must be an array of subscriptions".to_string());
+    let values: Vec<Value> = serde_json::from_slice(body).map_err(|_| invalid())?;
+    let field = |value: &Value, name: &str| {
+        value[name]
+            .as_str()
+            .and_then(|v| v.parse::<u64>().ok())
+            .ok_or_else(|| ApiError::BadRequest(format!("Invalid field: {}", name)))
+    };
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let present_slot = node.present_slot();
+
+    let mut subscriptions = ctx
+        .subnet_subscriptions
+        .lock()
+        .expect("Subnet subscriptions lock poisoned");
+    let mut aggregator_duties = ctx
+        .aggregator_duties
+        .lock()
+        .expect("Aggregator duties lock poisoned");
+    subscriptions.retain(|_, until_slot| *until_slot >= present_slot);
+    aggregator_duties.retain(|&(slot, _), _| slot >= present_slot);
+    for value in &values {
+        let index = field(value, "validator_index")?;
+        let slot = field(value, "slot")?;
+        let shard = field(value, "shard")?;
+        let is_aggregator = match value["is_aggregator"] {
+            Value::Null => false,
+            Value::Bool(is_aggregator) => is_aggregator,
+            _ => {
+                return Err(ApiError::BadRequest(
+                    "Invalid field: is_aggregator".to_string(),
+                ))
+            }
+        };
+        if index as usize >= node.validators().len() {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown validator: {}",
+                index
+            )));
+        }
+        if node.committee(slot, shard).is_none() {
+            return Err(ApiError::BadRequest(format!(
+                "No committee for shard {} at slot {}",
+                shard, slot
+            )));
+        }
+        if slot < present_slot {
+            continue;
+        }
+        let subnet = shard % ATTESTATION_SUBNET_COUNT as u64;
+        let until_slot = subscriptions.entry(subnet).or_insert(slot);
+        if *until_slot < slot {
+            *until_slot = slot;
+        }
+        if is_aggregator {
+            aggregator_duties
+                .entry((slot, shard))
+                .or_default()
+                .insert(index);
+        }
+        debug!(ctx.log, "Subnet subscription"; "subnet" => subnet, "until_slot" => *until_slot, "aggregator" => is_aggregator);
+    }
+    Ok(Response::new(vec![]))
+}
+
+/// `GET /eth/v1/validator/blocks/{slot}?randao_reveal,graffiti`
+///
+/// Returns an unsigned block for `slot`, built on the head and voting for the eth1 block of the
+/// node's eth1 backend, if any. The graffiti is zero if not given.
+pub fn get_block<T: ClientDB>(
+    ctx: &Context<T>,
+    slot: &str,
+    query: &Query,
+    encoding: Encoding,
+) -> ApiResult {
+    let slot = slot
+        .parse::<u64>()
+        .map_err(|_| ApiError::BadRequest(format!("Invalid slot: {}", slot)))?;
+    let randao_reveal = match query.get("randao_reveal") {
+        Some(reveal) => parse_hash(reveal)?,
+        None => {
+            return Err(ApiError::BadRequest(
+                "Missing query parameter: randao_reveal".to_string(),
+            ))
+        }
+    };
+
+    let graffiti = match query.get("graffiti") {
+        Some(graffiti) => parse_hash(graffiti)?,
+        None => Hash256::zero(),
+    };
+
+    let block = ctx
+        .node
+        .read()
+        .expect("Beacon node lock poisoned")
+        .produce_block(slot, randao_reveal, graffiti)
+        .map_err(|e| ApiError::BadRequest(format!("Unable to produce block: {:?}", e)))?;
+    Ok(encoding.respond(&block, block_json))
+}
+
+/// `GET /eth/v1/validator/attestation_data?slot,shard`
+///
+/// Returns the data for the committee of `shard` to attest to at `slot`, voting for the head.
+pub fn get_attestation_data<T: ClientDB>(
+    ctx: &Context<T>,
+    query: &Query,
+    encoding: Encoding,
+) -> ApiResult {
+    let slot = query.require::<u64>("slot")?;
+    let shard = query.require::<u64>("shard")?;
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    if slot > node.present_slot() {
+        return Err(ApiError::BadRequest(format!(
+            "Slot has not yet started: {}",
+            slot
+        )));
+    }
+    let data = node
+        .produce_attestation_data(slot, shard)
+        .map_err(|e| ApiError::BadRequest(format!("Unable to produce attestation: {:?}", e)))?;
+    Ok(encoding.respond(&data, attestation_data_json))
+}
+
+/// `GET /eth/v1/validator/aggregate_attestation?slot,attestation_data_root`
+///
+/// Returns the best aggregate of the pooled attestations to the data with the given root, as
+/// chosen by `BeaconNode::aggregate_attestation`.
+pub fn get_aggregate_attestation<T: ClientDB>(
+    ctx: &Context<T>,
+    query: &Query,
+    encoding: Encoding,
+) -> ApiResult {
+    let slot = query.require::<u64>("slot")?;
+    let root = match query.get("attestation_data_root") {
+        Some(root) => parse_hash(root)?,
+        None => {
+            return Err(ApiError::BadRequest(
+                "Missing query parameter: attestation_data_root".to_string(),
+            ))
+        }
+    };
+    let node = ctx.node.read().expect("Beacon node lock poisoned");
+    let aggregate = node
+        .pooled_attestations()
+        .iter()
+        .find(|a| a.data.slot == slot && canonical_hash(&ssz_encode(&a.data))[..] == root[..])
+        .and_then(|a| node.aggregate_attestation(&a.data))
+        .ok_or_else(|| ApiError::NotFound("No attestations to the data".to_string()))?;
+    Ok(encoding.respond(&aggregate, attestation_json))
+}
+
+/// Returns the root of the last canonical block before `cycle`.
+fn dependent_root<T: ClientDB>(node: &BeaconNode<T>, cycle: u64) -> Result<Hash256, ApiError> {
+    let start_slot = cycle_slots(cycle, u64::from(node.config().cycle_length))?.start;
+    if start_slot == 0 {
+        return Ok(node.genesis_root());
+    }
+    let (_, head_root) = node.head();
+    for item in node.store().block_iter(&head_root) {
+        let (root, ssz) = item?;
+        let (block, _) = BeaconBlock::ssz_decode(&ssz, 0)
+            .map_err(|_| ApiError::ServerError("Invalid block in database".to_string()))?;
+        if block.slot < start_slot {
+            return Ok(Hash256::from(&root[..]));
+        }
+    }
+    Ok(node.genesis_root())
+}
+
+/// Returns the slots of `cycle`, refusing cycles whose slots are beyond a `u64`.
+fn cycle_slots(cycle: u64, cycle_length: u64) -> Result<Range<u64>, ApiError> {
+    cycle
+        .checked_mul(cycle_length)
+        .and_then(|start_slot| Some(start_slot..start_slot.checked_add(cycle_length)?))
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid epoch: {}", cycle)))
+}
+
+fn duties_response(dependent_root: Hash256, duties: Vec<Value>) -> Response<Vec<u8>> {
+    json_response(&json!({
+        "dependent_root": hex_bytes(&dependent_root),
+        "data": duties,
+    }))
+}
+
+fn require_synced<T: ClientDB>(ctx: &Context<T>) -> Result<(), ApiError> {
+    let (head_slot, _, state) = sync_status(ctx);
+    if state.is_synced() {
+        Ok(())
+    } else {
+        Err(ApiError::ServiceUnavailable(format!(
+            "Node is {}, with its head at slot {}, {} slots behind",
+            state.name(),
+            head_slot,
+            state.distance()
+        )))
+    }
+}
+
+fn parse_epoch(epoch: &str) -> Result<u64, ApiError> {
+    epoch
+        .parse::<u64>()
+        .map_err(|_| ApiError::BadRequest(format!("Invalid epoch: {}", epoch)))
+}
+
+fn parse_indices(body: &[u8]) -> Result<Vec<usize>, ApiError> {
+    let invalid = || ApiError::BadRequest("Body must be an array of validator indices".to_string());
+    let indices: Vec<String> = serde_json::from_slice(body).map_err(|_| invalid())?;
+    indices
+        .iter()
+        .map(|index| index.parse::<usize>().map_err(|_| invalid()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::router::tests::{context, get, import_block};
+    use super::super::router::{handle, is_mutating};
+    use super::*;
+    use beacon_node::TestingSlotClock;
+    use db::stores::GossipStore;
+    use db::MemoryDB;
+    use hyper::{Request, StatusCode};
+    use network::rpc::{ForkDigest, StatusMessage};
+    use network::{NodeId, PeerManager, PeerManagerConfig};
+    use slog::{Discard, Logger};
+    use std::sync::{Arc, RwLock};
+    use std::time::Instant;
+    use types::{Attestation, Bitfield};
+
+    /// Moves the clock of the node to the start of `slot`.
+    fn set_slot(ctx: &Context<MemoryDB>, slot: u64) {
+        let mut node = ctx.node.write().unwrap();
+        let config = node.config().clone();
+        let clock =
+            TestingSlotClock::new(config.genesis_time, config.slot_duration_millis).unwrap();
+        clock.set_slot(slot);
+        node.set_slot_clock(Arc::new(clock));
+    }
+
+    #[test]
+    fn test_duties() {
+        let ctx = context();
+        set_slot(&ctx, 0);
+        let (status, body) = get(&ctx, "/eth/v1/validator/duties/proposer/1");
+        assert_eq!(status, StatusCode::OK);
+        let duties = body["data"].as_array().unwrap();
+        assert_eq!(duties.len(), 2);
+        assert_eq!(duties[0]["slot"], "2");
+
+        let req = Request::post("/eth/v1/validator/duties/attester/1")
+            .body(br#"["0", "3"]"#.to_vec())
+            .unwrap();
+        let response = handle(&ctx, &req);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        let duties = body["data"].as_array().unwrap();
+        assert_eq!(duties.len(), 2);
+        assert_eq!(duties[1]["validator_index"], "3");
+        assert_eq!(duties[1]["committee_length"], "2");
+
+        let req = Request::post("/eth/v1/validator/duties/attester/1")
+            .body(br#"["4"]"#.to_vec())
+            .unwrap();
+        assert_eq!(handle(&ctx, &req).status(), StatusCode::BAD_REQUEST);
+
+        /*
+         * Cycles whose slots are beyond a `u64` are refused.
+         */
+        let path = format!("/eth/v1/validator/duties/proposer/{}", u64::MAX);
+        assert_eq!(get(&ctx, &path).0, StatusCode::BAD_REQUEST);
+        let req = Request::post(format!("/eth/v1/validator/duties/attester/{}", u64::MAX))
+            .body(br#"["0"]"#.to_vec())
+            .unwrap();
+        assert_eq!(handle(&ctx, &req).status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_dependent_root() {
+        let ctx = context();
+        let genesis_root = ctx.node.read().unwrap().genesis_root();
+        set_slot(&ctx, 4);
+        let first = import_block(&ctx, genesis_root, 1);
+        let second = import_block(&ctx, first, 3);
+        let dependent_root = |uri: &str| get(&ctx, uri).1["dependent_root"].clone();
+
+        assert_eq!(
+            dependent_root("/eth/v1/validator/duties/proposer/0"),
+            hex_bytes(&genesis_root)
+        );
+        assert_eq!(
+            dependent_root("/eth/v1/validator/duties/proposer/1"),
+            hex_bytes(&first)
+        );
+        assert_eq!(
+            dependent_root("/eth/v1/validator/duties/proposer/2"),
+            hex_bytes(&second)
+        );
+        /*
+         * A reorg onto a chain without the block at slot 3 changes the dependent root.
+         */
+        let second = import_block(&ctx, first, 2);
+        import_block(&ctx, second, 4);
+        assert_eq!(
+            dependent_root("/eth/v1/validator/duties/proposer/2"),
+            hex_bytes(&second)
+        );
+    }
+
+    #[test]
+    fn test_duties_refused_until_synced() {
+        let mut ctx = context();
+        let (status, body) = get(&ctx, "/eth/v1/validator/duties/proposer/1");
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Node is syncing"));
+
+        /*
+         * Without a peer ahead of its head, the node is stalled rather than syncing.
+         */
+        let now = Instant::now();
+        let peer_id = NodeId::random();
+        let mut peer_manager =
+            PeerManager::new(PeerManagerConfig::default(), Logger::root(Discard, o!()));
+        peer_manager.on_connect(peer_id, now);
+        ctx.peer_manager = Some(Arc::new(RwLock::new(peer_manager)));
+        let (_, body) = get(&ctx, "/eth/v1/validator/duties/proposer/1");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Node is stalled"));
+        assert_eq!(
+            get(&ctx, "/eth/v1/node/syncing").1["data"]["sync_state"],
+            "stalled"
+        );
+
+        let status = StatusMessage {
+            fork_digest: ForkDigest::new(0, &Hash256::zero()),
+            finalized_root: Hash256::zero(),
+            finalized_slot: 0,
+            head_root: Hash256::zero(),
+            head_slot: 1_000,
+        };
+        ctx.peer_manager
+            .as_ref()
+            .unwrap()
+            .write()
+            .unwrap()
+            .update_sync_status(&peer_id, &status, now);
+        assert_eq!(
+            get(&ctx, "/eth/v1/node/syncing").1["data"]["sync_state"],
+            "syncing"
+        );
+
+        let present_slot = ctx.node.read().unwrap().present_slot();
+        let genesis_root = ctx.node.read().unwrap().genesis_root();
+        import_block(&ctx, genesis_root, present_slot);
+        let (status, _) = get(&ctx, "/eth/v1/validator/duties/proposer/1");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            get(&ctx, "/eth/v1/node/syncing").1["data"]["sync_state"],
+            "synced"
+        );
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let ctx = context();
+        let (present_slot, shard) = {
+            let node = ctx.node.read().unwrap();
+            let present_slot = node.present_slot();
+            (
+                present_slot,
+                u64::from(node.committees(present_slot)[0].shard),
+            )
+        };
+        let subscribe = |body: String| {
+            let req = Request::post("/eth/v1/validator/beacon_committee_subscriptions")
+                .body(body.into_bytes())
+                .unwrap();
+            handle(&ctx, &req).status()
+        };
+        let subscription = |slot: u64, shard: u64| {
+            format!(
+                r#"{{"validator_index": "0", "slot": "{}", "shard": "{}"}}"#,
+                slot, shard
+            )
+        };
+        let aggregation = |index: u64, slot: u64, shard: u64| {
+            format!(
+                r#"{{"validator_index": "{}", "slot": "{}", "shard": "{}", "is_aggregator": true}}"#,
+                index, slot, shard
+            )
+        };
+
+        let body = format!(
+            "[{}, {}, {}]",
+            subscription(present_slot, shard),
+            subscription(present_slot + 2, shard),
+            subscription(present_slot - 2, shard)
+        );
+        assert_eq!(subscribe(body), StatusCode::OK);
+        assert_eq!(
+            ctx.subnet_subscriptions
+                .lock()
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![(&shard, &(present_slot + 2))]
+        );
+
+        assert!(ctx.aggregator_duties.lock().unwrap().is_empty());
+
+        /*
+         * Aggregators are recorded until their slot has passed.
+         */
+        let body = format!(
+            "[{}, {}, {}]",
+            aggregation(1, present_slot + 2, shard),
+            aggregation(3, present_slot + 2, shard),
+            aggregation(2, present_slot - 2, shard)
+        );
+        assert_eq!(subscribe(body), StatusCode::OK);
+        assert_eq!(
+            *ctx.aggregator_duties.lock().unwrap(),
+            vec![((present_slot + 2, shard), vec![1, 3].into_iter().collect())]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            ctx.aggregation_subnets(),
+            vec![(shard % ATTESTATION_SUBNET_COUNT as u64, present_slot + 2)]
+                .into_iter()
+                .collect()
+        );
+
+        let body = format!("[{}]", subscription(present_slot, 2));
+        assert_eq!(subscribe(body), StatusCode::BAD_REQUEST);
+        assert_eq!(subscribe("[{}]".to_string()), StatusCode::BAD_REQUEST);
+        let body = format!(
+            r#"[{{"validator_index": "0", "slot": "{}", "shard": "{}", "is_aggregator": "yes"}}]"#,
+            present_slot, shard
+        );
+        assert_eq!(subscribe(body), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_persist_and_load_gossip() {
+        let store = GossipStore::new(Arc::new(MemoryDB::open()));
+        let now = Instant::now();
+        let ctx = context();
+        let present_slot = ctx.node.read().unwrap().present_slot();
+        {
+            let mut subscriptions = ctx.subnet_subscriptions.lock().unwrap();
+            subscriptions.insert(1, present_slot + 2);
+            subscriptions.insert(2, present_slot - 1);
+        }
+        ctx.duplicates
+            .lock()
+            .unwrap()
+            .observe_block_proposal(3, present_slot, now);
+        ctx.persist_gossip(&store, now).unwrap();
+
+        /*
+         * A restarted node keeps the subscriptions still needed, and does not republish.
+         */
+        let restarted = context();
+        restarted.load_gossip(&store, now).unwrap();
+        assert_eq!(
+            *restarted.subnet_subscriptions.lock().unwrap(),
+            vec![(1, present_slot + 2)].into_iter().collect()
+        );
+        assert!(!restarted
+            .duplicates
+            .lock()
+            .unwrap()
+            .observe_block_proposal(3, present_slot, now));
+    }
+
+    #[test]
+    fn test_produce_block() {
+        let ctx = context();
+        let reveal = hex_bytes(&Hash256::from(5));
+        let (status, body) = get(
+            &ctx,
+            &format!("/eth/v1/validator/blocks/1?randao_reveal={}", reveal),
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["slot"], "1");
+        assert_eq!(body["data"]["randao_reveal"], reveal);
+        assert_eq!(body["data"]["graffiti"], hex_bytes(&Hash256::zero()));
+        assert_eq!(
+            body["data"]["pow_chain_reference"],
+            hex_bytes(&Hash256::zero())
+        );
+        let graffiti = hex_bytes(&Hash256::from(6));
+        let (_, body) = get(
+            &ctx,
+            &format!(
+                "/eth/v1/validator/blocks/1?randao_reveal={}&graffiti={}",
+                reveal, graffiti
+            ),
+        );
+        assert_eq!(body["data"]["graffiti"], graffiti);
+
+        let (status, _) = get(&ctx, "/eth/v1/validator/blocks/1");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(
+            &ctx,
+            &format!("/eth/v1/validator/blocks/0?randao_reveal={}", reveal),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_attestation_data() {
+        let ctx = context();
+        let (slot, shard) = {
+            let node = ctx.node.read().unwrap();
+            let slot = node.present_slot() - 1;
+            (slot, node.committees(slot)[0].shard)
+        };
+        let (status, body) = get(
+            &ctx,
+            &format!(
+                "/eth/v1/validator/attestation_data?slot={}&shard={}",
+                slot, shard
+            ),
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["slot"], slot.to_string());
+        assert_eq!(
+            body["data"]["beacon_block_hash"],
+            hex_bytes(&ctx.node.read().unwrap().genesis_root())
+        );
+
+        let (status, _) = get(
+            &ctx,
+            &format!("/eth/v1/validator/attestation_data?slot={}", slot),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = get(&ctx, "/eth/v1/validator/attestation_data?slot=1000&shard=0");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_aggregate_attestation() {
+        let ctx = context();
+        let data = {
+            let mut node = ctx.node.write().unwrap();
+            let present_slot = node.present_slot();
+            let slot = present_slot - 1;
+            let shard = u64::from(node.committees(slot)[0].shard);
+            let data = node.produce_attestation_data(slot, shard).unwrap();
+            for participant in 0..2 {
+                let mut attestation = Attestation::zero();
+                attestation.data = data.clone();
+                attestation.participation_bitfield = Bitfield::from_elem(2, false);
+                attestation.participation_bitfield.set(participant, true);
+                node.process_attestation(attestation, present_slot).unwrap();
+            }
+            data
+        };
+        let root = hex_bytes(&canonical_hash(&ssz_encode(&data)));
+        let (status, body) = get(
+            &ctx,
+            &format!(
+                "/eth/v1/validator/aggregate_attestation?slot={}&attestation_data_root={}",
+                data.slot, root
+            ),
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["participation_bitfield"], "0xc0");
+
+        let (status, _) = get(
+            &ctx,
+            &format!(
+                "/eth/v1/validator/aggregate_attestation?slot={}&attestation_data_root={}",
+                data.slot + 1,
+                root
+            ),
+        );
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(
+            &ctx,
+            &format!("/eth/v1/validator/aggregate_attestation?slot={}", data.slot),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_liveness() {
+        let ctx = context();
+        let (cycle, live_index) = {
+            let mut node = ctx.node.write().unwrap();
+            let slot = node.present_slot();
+            let committee = &node.committees(slot)[0];
+            let (shard, live_index) = (u64::from(committee.shard), committee.committee[0]);
+            let mut attestation = Attestation::zero();
+            attestation.data = node.produce_attestation_data(slot, shard).unwrap();
+            attestation.participation_bitfield = Bitfield::from_elem(2, false);
+            attestation.participation_bitfield.set(0, true);
+            node.process_attestation(attestation, slot).unwrap();
+            (slot / 2, live_index)
+        };
+        let liveness = |cycle: u64, body: &str| {
+            let req = Request::post(format!("/eth/v1/validator/liveness/{}", cycle))
+                .body(body.as_bytes().to_vec())
+                .unwrap();
+            let response = handle(&ctx, &req);
+            let body: Value = serde_json::from_slice(response.body()).unwrap();
+            (response.status(), body)
+        };
+
+        let other_index = (live_index + 1) % 4;
+        let body = format!(r#"["{}", "{}"]"#, live_index, other_index);
+        let (status, body) = liveness(cycle, &body);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["data"],
+            json!([
+                { "index": live_index.to_string(), "is_live": true },
+                { "index": other_index.to_string(), "is_live": false },
+            ])
+        );
+        let (_, body) = liveness(cycle - 1, &format!(r#"["{}"]"#, live_index));
+        assert_eq!(body["data"][0]["is_live"], false);
+        assert_eq!(liveness(cycle, r#"["4"]"#).0, StatusCode::BAD_REQUEST);
+        assert!(!is_mutating(
+            &Request::post("/eth/v1/validator/liveness/1")
+                .body(())
+                .unwrap()
+        ));
+    }
+}
@@ -1,36 +1,110 @@
 #[macro_use]
 extern crate slog;
-extern crate slog_async;
-extern crate slog_term;
-// extern crate ssz;
 extern crate clap;
+extern crate ctrlc;
 extern crate futures;
 
+extern crate beacon_node;
+extern crate bls;
 extern crate db;
+extern crate eth1;
+extern crate grpcio;
+extern crate hex;
+extern crate http_api;
+extern crate logging;
+extern crate network;
+extern crate protos;
+extern crate rpassword;
+extern crate serde_json;
+extern crate ssz;
+extern crate task_executor;
+extern crate types;
+extern crate validator_client;
+extern crate yaml_rust;
 
+mod account;
+mod boot_node;
 mod config;
+mod database;
+mod doctor;
+mod rpc;
+mod shutdown;
+mod validator;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
-use clap::{App, Arg};
-use config::LighthouseConfig;
-use slog::Drain;
+use beacon_node::{
+    duration_to_genesis, recover_imports, wait_for_genesis, BeaconNodeBuilder,
+    CommitteePrecomputeService, DiskGuard, DiskGuardService, StateRegenService, StateRegenerator,
+    StateTransitionPool, WeakSubjectivityOutcome, DEFAULT_REGEN_CACHE_SIZE, DEFAULT_REGEN_WORKERS,
+    NETWORK_START_OFFSET,
+};
+use clap::{App, Arg, SubCommand};
+use config::{
+    parse_chain_config, parse_clock_disparity, parse_disk_guard_config, parse_eth1_config,
+    parse_eth2_network, parse_http_config, parse_import_checkpoint, parse_logger_config,
+    parse_monitoring_config, parse_network_config, parse_participation_retention, parse_rpc_config,
+    parse_state_pruning, parse_state_transition_threads, parse_validator_monitor,
+    parse_weak_subjectivity_checkpoint, ConfigFile, Flags, LighthouseConfig, DB_DIR,
+    HEAP_PROFILE_DIR,
+};
+use db::stores::{
+    BeaconBlockStore, ChainStore, GossipStore, LightClientStore, ParticipationStore, PeerStore,
+    StateStore, ValidatorStore, COLUMNS,
+};
+use db::{check_schema, ColumnCodecs, DiskDB, SchemaError};
+use eth1::Eth1Service;
+use logging::{build_logger, LoggerConfig};
+use network::gossip::PeerScoreParams;
+use network::rpc::ForkDigest;
+use network::{NetworkService, PeerManager};
+use shutdown::{handle_signals, stop_within, SHUTDOWN_TIMEOUT};
+use task_executor::{ShutdownReason, TaskExecutor};
 
 fn main() {
-    let decorator = slog_term::TermDecorator::new().build();
-    let drain = slog_term::CompactFormat::new(decorator).build().fuse();
-    let drain = slog_async::Async::new(drain).build().fuse();
-    let log = slog::Logger::root(drain, o!());
-
     let matches = App::new("Lighthouse")
         .version("0.0.1")
         .author("Sigma Prime <paul@sigmaprime.io>")
         .about("Eth 2.0 Client")
         .arg(
+            Arg::with_name("config-file")
+                .long("config-file")
+                .value_name("FILE")
+                .help("YAML file of flags for the beacon node, and for the validator client under validator_client. Flags given on the command line take precedence.")
+                .takes_value(true),
+        ).arg(
             Arg::with_name("datadir")
                 .long("datadir")
                 .value_name("DIR")
-                .help("Data directory for keys and databases.")
+                .help("Root data directory, holding the keys and databases of each network in a directory named after it. Defaults to ~/.lighthouse.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("network")
+                .long("network")
+                .value_name("NAME")
+                .help("Built-in network to join: mainnet (default) or interop.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("testnet-dir")
+                .long("testnet-dir")
+                .value_name("DIR")
+                .help("Directory of a network to join instead of a built-in one, with config.yaml and optionally preset.yaml, genesis.yaml and boot_enr.yaml. The network is named after the directory.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("preset")
+                .long("preset")
+                .value_name("NAME")
+                .help("Preset of the chain constants in place of that of the network: mainnet or minimal, with small committees and short epochs for spec tests and local devnets.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("listen-address")
+                .long("listen-address")
+                .value_name("ADDRESS")
+                .help("Address on which to listen for p2p connections and discovery.")
                 .takes_value(true),
         ).arg(
             Arg::with_name("port")
@@ -38,29 +112,1073 @@ fn main() {
                 .value_name("PORT")
                 .help("Network listen port for p2p connections.")
                 .takes_value(true),
+        ).arg(
+            Arg::with_name("discovery-port")
+                .long("discovery-port")
+                .value_name("PORT")
+                .help("UDP port for discovery, if not the listen port.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("target-peers")
+                .long("target-peers")
+                .value_name("COUNT")
+                .help("The number of peers to maintain.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("boot-nodes")
+                .long("boot-nodes")
+                .value_name("ENRS")
+                .help("Comma-separated records of nodes used to join the network.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("enr-address")
+                .long("enr-address")
+                .value_name("ADDRESS")
+                .help("Public IPv4 address to advertise, if not the listen address.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("enr-tcp-port")
+                .long("enr-tcp-port")
+                .value_name("PORT")
+                .help("Public TCP port to advertise, if not the listen port.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("enr-udp-port")
+                .long("enr-udp-port")
+                .value_name("PORT")
+                .help("Public UDP port to advertise, if not the discovery port.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("disable-discovery")
+                .long("disable-discovery")
+                .help("Disables peer discovery; only peers which dial this node are connected."),
+        ).arg(
+            Arg::with_name("disable-upnp")
+                .long("disable-upnp")
+                .help("Disables forwarding the listen ports on the internet gateway with UPnP."),
+        ).arg(
+            Arg::with_name("ban-peers")
+                .long("ban-peers")
+                .value_name("NODE_IDS")
+                .help("Comma-separated 0x-prefixed ids of nodes which are never dialed or accepted.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("ban-cidrs")
+                .long("ban-cidrs")
+                .value_name("CIDRS")
+                .help("Comma-separated address ranges, e.g. 10.0.0.0/8, from which peers are never dialed or accepted.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("rpc")
+                .long("rpc")
+                .help("Enables the gRPC server used by validator clients."),
+        ).arg(
+            Arg::with_name("rpc-address")
+                .long("rpc-address")
+                .value_name("ADDRESS")
+                .help("Address on which to listen for gRPC connections.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("rpc-port")
+                .long("rpc-port")
+                .value_name("PORT")
+                .help("Port on which to listen for gRPC connections.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("http")
+                .long("http")
+                .help("Enables the HTTP API."),
+        ).arg(
+            Arg::with_name("http-address")
+                .long("http-address")
+                .value_name("ADDRESS")
+                .help("Address on which to listen for HTTP API connections.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("http-port")
+                .long("http-port")
+                .value_name("PORT")
+                .help("Port on which to listen for HTTP API connections.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("http-allow-origin")
+                .long("http-allow-origin")
+                .value_name("ORIGINS")
+                .help("Comma-separated origins from which browsers may use the HTTP API, or * for any.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("http-tls-cert")
+                .long("http-tls-cert")
+                .value_name("PATH")
+                .help("PEM certificate chain with which to serve the HTTP API over TLS.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("http-tls-key")
+                .long("http-tls-key")
+                .value_name("PATH")
+                .help("PEM PKCS #8 private key of --http-tls-cert.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("http-read-only")
+                .long("http-read-only")
+                .help("Disables the HTTP API endpoints which publish objects or change the node."),
+        ).arg(
+            Arg::with_name("monitoring-endpoint")
+                .long("monitoring-endpoint")
+                .value_name("URL")
+                .help("Periodically POSTs a JSON summary of the node's sync status, peers and validators to this HTTP or HTTPS URL, for monitoring without Prometheus.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("monitoring-interval")
+                .long("monitoring-interval")
+                .value_name("SECONDS")
+                .help("How often the summary is pushed to --monitoring-endpoint. Defaults to 60.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("genesis-time")
+                .long("genesis-time")
+                .value_name("SECONDS")
+                .help("Unix time of genesis. Before it, the node waits, starting networking shortly before genesis.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("clock-disparity-millis")
+                .long("clock-disparity-millis")
+                .value_name("MILLIS")
+                .help("How far the clocks of peers may be ahead of or behind our own, when checking the slots of published blocks and attestations. Defaults to 500.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("state-transition-threads")
+                .long("state-transition-threads")
+                .value_name("THREADS")
+                .help("The number of threads importing the blocks published to the HTTP API, and running the cycle transitions within them, apart from the threads serving the API. Blocks received otherwise are imported where they are received. Defaults to 1.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("import-checkpoint-slots")
+                .long("import-checkpoint-slots")
+                .value_name("SLOTS")
+                .help("The number of slots the head advances between each time the chain is persisted while sync imports batches of blocks. After a crash, the blocks imported since are imported again. Defaults to 256.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("weak-subjectivity-checkpoint")
+                .long("weak-subjectivity-checkpoint")
+                .value_name("ROOT:SLOT")
+                .help("The 0x-prefixed root and slot of a block from a trusted source, which the chain must include. The node refuses to start if its chain conflicts. Remembered in the database.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("ignore-weak-subjectivity")
+                .long("ignore-weak-subjectivity")
+                .help("Starts the node even if its chain conflicts with the weak subjectivity checkpoint."),
+        ).arg(
+            Arg::with_name("prune-states")
+                .long("prune-states")
+                .conflicts_with("archive")
+                .help("Keeps only the snapshot of the finalized state, regenerating later states from its blocks. The default."),
+        ).arg(
+            Arg::with_name("archive")
+                .long("archive")
+                .help("Keeps snapshots of historical states as they are regenerated, so that historical HTTP API queries are quicker, at the cost of disk space."),
+        ).arg(
+            Arg::with_name("disk-prune-threshold-mb")
+                .long("disk-prune-threshold-mb")
+                .value_name("MB")
+                .help("Below this much free space on the disk of the database, state snapshots are pruned as if by --prune-states, whatever the mode. Defaults to 5120.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("disk-halt-threshold-mb")
+                .long("disk-halt-threshold-mb")
+                .value_name("MB")
+                .help("Below this much free space on the disk of the database, blocks are no longer imported until space is freed, so that the database is never left corrupt by a full disk. Defaults to 1024.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("eth1")
+                .long("eth1")
+                .help("Follows the eth1 chain of the deposit contract, voting for its blocks in produced blocks."),
+        ).arg(
+            Arg::with_name("eth1-endpoints")
+                .long("eth1-endpoints")
+                .value_name("URLS")
+                .help("Comma-separated JSON-RPC endpoints of eth1 nodes, in order of preference, falling back to the next when one fails. Implies --eth1. Defaults to http://localhost:8545.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("validators-monitor")
+                .long("validators-monitor")
+                .value_name("VALIDATORS")
+                .help("Comma-separated indices or 0x-prefixed public keys of validators whose attestations, proposals and balances are logged and recorded in metrics.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("participation-retention-cycles")
+                .long("participation-retention-cycles")
+                .value_name("CYCLES")
+                .help("The number of the latest cycles whose attestation participation, and that of the monitored validators, is kept in the database. Older records are pruned. Defaults to 1024.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .help("Format of log records: terminal, or json for one JSON object per line.")
+                .possible_values(&["terminal", "json"])
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Least severe level logged, of trace, debug, info, warn, error and crit. Defaults to info.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("log-filter")
+                .long("log-filter")
+                .value_name("MODULE=LEVEL,...")
+                .help("Comma-separated levels of modules and their submodules, overriding --log-level, e.g. network=debug.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .value_name("FILE")
+                .help("File to log to instead of the terminal, rotated as it grows.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("log-max-size")
+                .long("log-max-size")
+                .value_name("MIB")
+                .help("Size in MiB beyond which the log file is rotated. Defaults to 100.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("log-max-files")
+                .long("log-max-files")
+                .value_name("COUNT")
+                .help("Number of rotated log files kept. Defaults to 5.")
+                .takes_value(true),
+        ).arg(
+            Arg::with_name("log-dedup-interval")
+                .long("log-dedup-interval")
+                .value_name("SECONDS")
+                .help("Seconds for which repeats of a warning or error are counted rather than logged, 0 to log every one. Defaults to 30.")
+                .takes_value(true),
+        ).subcommand(
+            SubCommand::with_name("boot_node")
+                .about("Runs only peer discovery, to serve as an entry point to the network.")
+                .arg(
+                    Arg::with_name("listen-address")
+                        .long("listen-address")
+                        .value_name("ADDRESS")
+                        .help("Address on which to listen for discovery packets.")
+                        .default_value("0.0.0.0")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .help("UDP port on which to listen for discovery packets.")
+                        .default_value("9000")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("enr-address")
+                        .long("enr-address")
+                        .value_name("ADDRESS")
+                        .help("Public IPv4 address to advertise, if not the listen address.")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("enr-port")
+                        .long("enr-port")
+                        .value_name("PORT")
+                        .help("Public UDP port to advertise, if not the listen port.")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("boot-nodes")
+                        .long("boot-nodes")
+                        .value_name("ENRS")
+                        .help("Comma-separated records of other boot nodes.")
+                        .takes_value(true),
+                ),
+        ).subcommand(
+            SubCommand::with_name("validator_client")
+                .about("Performs the duties of the validators in the data dir using beacon nodes.")
+                .arg(
+                    Arg::with_name("beacon-nodes")
+                        .long("beacon-nodes")
+                        .alias("beacon-node")
+                        .value_name("URLS")
+                        .help("Comma-separated URLs of the beacon nodes' HTTP APIs, in order of preference. Others are used while the first is unavailable.")
+                        .default_value("http://localhost:5052")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("doppelganger-cycles")
+                        .long("doppelganger-cycles")
+                        .value_name("CYCLES")
+                        .help("The number of cycles each validator is watched for attestations by another client before signing, or 0 to sign at once.")
+                        .default_value("2")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("graffiti")
+                        .long("graffiti")
+                        .value_name("TEXT")
+                        .help("The graffiti of blocks proposed by validators without their own, of at most 32 bytes.")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("graffiti-file")
+                        .long("graffiti-file")
+                        .value_name("FILE")
+                        .help("A file of graffiti for each validator, with lines of `0x<pubkey>: <graffiti>` or `default: <graffiti>`, read before each proposal.")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("interop-validators")
+                        .long("interop-validators")
+                        .value_name("RANGE")
+                        .help("Performs the duties of the validators with indices in RANGE, e.g. 0..8, using their publicly known interop keys instead of the validators dir. For test networks only.")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("metrics")
+                        .long("metrics")
+                        .help("Serves the validator client's metrics at /metrics and its validators' performance at /summary."),
+                ).arg(
+                    Arg::with_name("metrics-address")
+                        .long("metrics-address")
+                        .value_name("ADDRESS")
+                        .help("Address on which to serve the validator client's metrics.")
+                        .default_value("127.0.0.1")
+                        .takes_value(true),
+                ).arg(
+                    Arg::with_name("metrics-port")
+                        .long("metrics-port")
+                        .value_name("PORT")
+                        .help("Port on which to serve the validator client's metrics.")
+                        .default_value("5064")
+                        .takes_value(true),
+                ).subcommand(
+                    SubCommand::with_name("import_slashing_protection")
+                        .about("Imports the slashing protection of an EIP-3076 interchange file.")
+                        .arg(Arg::with_name("FILE").required(true)),
+                ).subcommand(
+                    SubCommand::with_name("export_slashing_protection")
+                        .about("Exports the slashing protection to an EIP-3076 interchange file.")
+                        .arg(Arg::with_name("FILE").required(true)),
+                ),
+        ).subcommand(
+            SubCommand::with_name("account")
+                .about("Manages the validator accounts of the data dir.")
+                .subcommand(
+                    SubCommand::with_name("validator")
+                        .about("Manages validator keystores.")
+                        .subcommand(
+                            SubCommand::with_name("import")
+                                .about("Imports the EIP-2335 keystores of a directory for the validator client.")
+                                .arg(
+                                    Arg::with_name("directory")
+                                        .long("directory")
+                                        .value_name("DIR")
+                                        .help("Directory searched for keystore*.json files.")
+                                        .required(true)
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("password-file")
+                                        .long("password-file")
+                                        .value_name("FILE")
+                                        .help("File holding the password of every keystore, instead of prompting for each.")
+                                        .takes_value(true),
+                                ),
+                        ).subcommand(
+                            SubCommand::with_name("deposit")
+                                .about("Derives validator keys from a mnemonic, writing their keystores and the deposit data for the launchpad.")
+                                .arg(
+                                    Arg::with_name("output-dir")
+                                        .long("output-dir")
+                                        .value_name("DIR")
+                                        .help("Directory in which the keystores and deposit data are written.")
+                                        .required(true)
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("mnemonic-file")
+                                        .long("mnemonic-file")
+                                        .value_name("FILE")
+                                        .help("File holding the BIP-39 mnemonic of the keys. A new mnemonic is generated and printed if absent.")
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("password-file")
+                                        .long("password-file")
+                                        .value_name("FILE")
+                                        .help("File holding the password of the keystores, instead of prompting for it.")
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("count")
+                                        .long("count")
+                                        .value_name("COUNT")
+                                        .help("Number of validators.")
+                                        .default_value("1")
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("first-index")
+                                        .long("first-index")
+                                        .value_name("INDEX")
+                                        .help("Index in the mnemonic of the first validator, to add validators to an earlier deposit.")
+                                        .default_value("0")
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("amount")
+                                        .long("amount")
+                                        .value_name("GWEI")
+                                        .help("Deposit of each validator, in Gwei.")
+                                        .default_value("32000000000")
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("fork-version")
+                                        .long("fork-version")
+                                        .value_name("HEX")
+                                        .help("Genesis fork version of the chain to which the deposits are made.")
+                                        .default_value("0x00000000")
+                                        .takes_value(true),
+                                ).arg(
+                                    Arg::with_name("network-name")
+                                        .long("network-name")
+                                        .value_name("NAME")
+                                        .help("Name of the chain, which the launchpad checks.")
+                                        .default_value("mainnet")
+                                        .takes_value(true),
+                                ),
+                        ),
+                ),
+        ).subcommand(
+            SubCommand::with_name("db")
+                .about("Manages the database of the data dir, which the beacon node must not be using.")
+                .subcommand(
+                    SubCommand::with_name("inspect")
+                        .about("Logs the schema version, the number and size of the entries of each column, and the size on disk."),
+                ).subcommand(
+                    SubCommand::with_name("prune-states")
+                        .about("Deletes the blocks off the chains persisted at the last shutdown, which are what remains of abandoned forks, and the state snapshots which --archive would keep."),
+                ).subcommand(
+                    SubCommand::with_name("migrate")
+                        .about("Upgrades the database to the schema of this version of Lighthouse."),
+                ).subcommand(
+                    SubCommand::with_name("compact")
+                        .about("Compacts every column, reclaiming the space of deleted and overwritten entries."),
+                ).subcommand(
+                    SubCommand::with_name("debug-replay")
+                        .about("Re-imports the blocks of the chain persisted at the last shutdown onto an in-memory node, tracing each step of the import of the blocks in a range of slots, to debug discrepancies with other clients.")
+                        .arg(
+                            Arg::with_name("from-slot")
+                                .long("from-slot")
+                                .value_name("SLOT")
+                                .help("First slot of the range traced.")
+                                .required(true)
+                                .takes_value(true),
+                        ).arg(
+                            Arg::with_name("to-slot")
+                                .long("to-slot")
+                                .value_name("SLOT")
+                                .help("Last slot of the range traced.")
+                                .required(true)
+                                .takes_value(true),
+                        ).arg(
+                            Arg::with_name("report")
+                                .long("report")
+                                .value_name("FILE")
+                                .help("File to which a JSON report of each block and step traced, with timings, is written.")
+                                .takes_value(true),
+                        ),
+                ),
+        ).subcommand(
+            SubCommand::with_name("doctor")
+                .about("Diagnoses the data dir of a beacon node which is not running: whether the database opens, its schema version, whether the persisted head descends from the finalized block, the free disk space, the skew of the clock from NTP, and whether the eth1 endpoints are reachable.")
+                .arg(
+                    Arg::with_name("ntp-server")
+                        .long("ntp-server")
+                        .value_name("HOST:PORT")
+                        .help("NTP server against which the clock is checked. Defaults to pool.ntp.org:123.")
+                        .takes_value(true),
+                ),
         ).get_matches();
 
-    let mut config = LighthouseConfig::default();
+    let config_file = matches
+        .value_of("config-file")
+        .map(|path| ConfigFile::load(Path::new(path)));
+    let file = config_file.as_ref().and_then(|file| file.as_ref().ok());
+    let flags = Flags::with_file(&matches, file, |file| &file.beacon_node);
 
-    // Custom datadir
-    if let Some(dir) = matches.value_of("datadir") {
-        config.data_dir = PathBuf::from(dir.to_string());
+    /*
+     * The logger is configured first, so that errors in the rest of the configuration are logged
+     * as configured, falling back on the default logger should its own configuration be invalid.
+     */
+    let (log, _log_guard) = match parse_logger_config(&flags)
+        .and_then(|config| build_logger(&config).map_err(|e| format!("{:?}", e)))
+    {
+        Ok(logger) => logger,
+        Err(e) => {
+            let (log, _log_guard) =
+                build_logger(&LoggerConfig::default()).expect("Default logger is valid");
+            error!(log, "Invalid logging configuration"; "error" => e);
+            return;
+        }
+    };
+    if let Some(Err(e)) = &config_file {
+        error!(log, "Invalid config file"; "error" => e);
+        return;
     }
 
-    // Custom p2p listen port
-    if let Some(port_str) = matches.value_of("port") {
-        if let Ok(port) = port_str.parse::<u16>() {
-            config.p2p_listen_port = port;
-        } else {
-            error!(log, "Invalid port"; "port" => port_str);
+    /*
+     * The network is parsed first, as its directory holds the data of the node, and its config
+     * is the default of the chain and network flags.
+     */
+    let eth2_network = match parse_eth2_network(&flags) {
+        Ok(network) => network,
+        Err(e) => {
+            error!(log, "Invalid network"; "error" => e);
             return;
         }
+    };
+    let mut config =
+        LighthouseConfig::new(flags.value_of("datadir").map(PathBuf::from), &eth2_network);
+    for dir in config.legacy_dirs() {
+        warn!(log, "Ignoring data from before the data dir was divided by network";
+              "help" => format!("move it into {}", config.data_dir.display()),
+              "path" => format!("{}", dir.display()));
+    }
+
+    if let Err(e) = parse_network_config(&flags, &mut config.network) {
+        error!(log, "Invalid network configuration"; "error" => e);
+        return;
+    }
+    if let Err(e) = parse_rpc_config(&flags, &mut config.rpc) {
+        error!(log, "Invalid RPC configuration"; "error" => e);
+        return;
+    }
+    if let Err(e) = parse_http_config(&flags, &mut config.http) {
+        error!(log, "Invalid HTTP API configuration"; "error" => e);
+        return;
+    }
+    match parse_monitoring_config(&flags) {
+        Ok(monitoring) => config.monitoring = monitoring,
+        Err(e) => {
+            error!(log, "Invalid monitoring configuration"; "error" => e);
+            return;
+        }
+    }
+    if let Err(e) = parse_chain_config(&flags, &mut config.chain) {
+        error!(log, "Invalid chain configuration"; "error" => e);
+        return;
+    }
+    if let Err(e) = parse_clock_disparity(&flags, &mut config.clock_disparity) {
+        error!(log, "Invalid chain configuration"; "error" => e);
+        return;
+    }
+    if let Err(e) = parse_state_transition_threads(&flags, &mut config.state_transition_threads) {
+        error!(log, "Invalid chain configuration"; "error" => e);
+        return;
+    }
+    if let Err(e) = parse_import_checkpoint(&flags, &mut config.import_checkpoint_slots) {
+        error!(log, "Invalid chain configuration"; "error" => e);
+        return;
+    }
+    match parse_weak_subjectivity_checkpoint(&flags) {
+        Ok(checkpoint) => config.weak_subjectivity_checkpoint = checkpoint,
+        Err(e) => {
+            error!(log, "Invalid chain configuration"; "error" => e);
+            return;
+        }
+    }
+    config.ignore_weak_subjectivity = flags.is_present("ignore-weak-subjectivity");
+    match parse_state_pruning(&flags) {
+        Ok(pruning) => config.state_pruning = pruning,
+        Err(e) => {
+            error!(log, "Invalid chain configuration"; "error" => e);
+            return;
+        }
+    }
+    if let Err(e) = parse_disk_guard_config(&flags, &mut config.disk_guard) {
+        error!(log, "Invalid chain configuration"; "error" => e);
+        return;
+    }
+    if let Err(e) = parse_eth1_config(&flags, &mut config.eth1) {
+        error!(log, "Invalid eth1 configuration"; "error" => e);
+        return;
+    }
+    match parse_validator_monitor(&flags) {
+        Ok(validators) => config.monitored_validators = validators,
+        Err(e) => {
+            error!(log, "Invalid validator monitor configuration"; "error" => e);
+            return;
+        }
+    }
+    if let Err(e) =
+        parse_participation_retention(&flags, &mut config.participation_retention_cycles)
+    {
+        error!(log, "Invalid validator monitor configuration"; "error" => e);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("boot_node") {
+        boot_node::run(matches, &config.data_dir, &log);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("validator_client") {
+        let flags = Flags::with_file(matches, file, |file| &file.validator_client);
+        validator::run(&flags, &config.validators_dir(), &log);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("account") {
+        account::run(matches, &config.validators_dir(), &log);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("db") {
+        database::run(
+            matches,
+            &config.beacon_dir(),
+            config.state_pruning,
+            &config.chain,
+            &log,
+        );
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("doctor") {
+        doctor::run(matches, &config, &log);
+        return;
     }
 
     // Log configuration
     info!(log, "";
+          "network" => &eth2_network.name,
+          "preset" => &eth2_network.preset,
           "data_dir" => &config.data_dir.to_str(),
-          "port" => &config.p2p_listen_port);
+          "listen_address" => format!("{}", config.network.listen_address),
+          "port" => config.network.libp2p_port,
+          "discovery_port" => config.network.discovery_port,
+          "target_peers" => config.network.target_peers,
+          "boot_nodes" => config.network.boot_nodes.len(),
+          "discovery" => !config.network.disable_discovery,
+          "upnp" => !config.network.disable_upnp,
+          "rpc" => config.rpc.enabled,
+          "http" => config.http.enabled,
+          "eth1" => config.eth1.enabled,
+          "monitored_validators" => config.monitored_validators.len());
+
+    if config.rpc.enabled || config.http.enabled {
+        /*
+         * The node shuts down on SIGINT or SIGTERM, or should a background task panic.
+         */
+        let (executor, shutdown) = TaskExecutor::new(log.clone());
+        if let Err(e) = handle_signals(executor.clone(), &log) {
+            error!(log, "Unable to handle signals"; "error" => format!("{}", e));
+            return;
+        }
+        let db_path = config.beacon_dir().join(DB_DIR);
+        let db =
+            Arc::new(DiskDB::open(&db_path, Some(&COLUMNS)).with_codecs(ColumnCodecs::standard()));
+        match check_schema(&*db) {
+            Ok(()) => {}
+            Err(SchemaError::Older(version)) => {
+                error!(log, "Database must be migrated"; "version" => version, "help" => "run lighthouse db migrate");
+                return;
+            }
+            Err(e) => {
+                error!(log, "Unable to use database"; "error" => format!("{:?}", e));
+                return;
+            }
+        }
+        let store = Arc::new(BeaconBlockStore::new(db.clone()));
+        let chain_store = ChainStore::new(db.clone());
+        let eth1 = if config.eth1.enabled {
+            match Eth1Service::start(config.eth1.clone(), &executor, log.clone()) {
+                Ok(service) => Some(Arc::new(service)),
+                Err(e) => {
+                    error!(log, "Unable to start eth1 service"; "error" => format!("{:?}", e));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        let mut builder = BeaconNodeBuilder::new(config.chain.clone()).store(store);
+        if let Some(ref eth1) = eth1 {
+            builder = builder.eth1_backend(eth1.clone());
+        }
+        let node = match builder.build() {
+            Ok(mut node) => {
+                match node.restore(&chain_store) {
+                    Ok(true) => {
+                        info!(log, "Restored chain from database"; "head_slot" => node.head().0);
+                        /*
+                         * Blocks imported by sync since the chain was last persisted survive a
+                         * crash in the block store, and are imported again.
+                         */
+                        match recover_imports(&mut node, &chain_store) {
+                            Ok(0) => {}
+                            Ok(n) => {
+                                info!(log, "Recovered blocks imported since last checkpoint"; "blocks" => n, "head_slot" => node.head().0)
+                            }
+                            Err(e) => {
+                                warn!(log, "Unable to recover blocks imported since last checkpoint"; "error" => format!("{:?}", e))
+                            }
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        warn!(log, "Unable to restore chain, starting from genesis"; "error" => format!("{:?}", e))
+                    }
+                }
+                if let Some(checkpoint) = config.weak_subjectivity_checkpoint {
+                    node.set_weak_subjectivity_checkpoint(checkpoint);
+                }
+                /*
+                 * A chain which conflicts with the checkpoint was restored from an untrusted
+                 * database, or was built by following an attacker's fork.
+                 */
+                match node.verify_weak_subjectivity() {
+                    Ok(None) => {}
+                    Ok(Some(WeakSubjectivityOutcome::Conflict(found))) => {
+                        let checkpoint = node
+                            .weak_subjectivity_checkpoint()
+                            .expect("Checkpoint was verified");
+                        let found = found
+                            .map(|root| format!("{:?}", root))
+                            .unwrap_or_else(|| "none".to_string());
+                        if config.ignore_weak_subjectivity {
+                            warn!(log, "Chain conflicts with weak subjectivity checkpoint"; "slot" => checkpoint.slot, "expected_root" => format!("{:?}", checkpoint.root), "found_root" => found);
+                        } else {
+                            crit!(log, "Chain conflicts with weak subjectivity checkpoint"; "slot" => checkpoint.slot, "expected_root" => format!("{:?}", checkpoint.root), "found_root" => found, "help" => "remove the database, or pass --ignore-weak-subjectivity");
+                            return;
+                        }
+                    }
+                    Ok(Some(outcome)) => {
+                        info!(log, "Checked weak subjectivity checkpoint"; "outcome" => format!("{:?}", outcome))
+                    }
+                    Err(e) => {
+                        error!(log, "Unable to check weak subjectivity checkpoint"; "error" => format!("{:?}", e));
+                        return;
+                    }
+                }
+                node.set_clock_disparity(config.clock_disparity);
+                node.persist_on_finalization(ChainStore::new(db.clone()));
+                node.checkpoint_imports(
+                    ChainStore::new(db.clone()),
+                    config.import_checkpoint_slots,
+                );
+                node.store_light_client_updates(LightClientStore::new(db.clone()));
+                if let Err(e) = node.persist_participation(
+                    ParticipationStore::new(db.clone()),
+                    config.participation_retention_cycles,
+                ) {
+                    warn!(log, "Unable to restore validator participation"; "error" => format!("{:?}", e));
+                }
+                match node.persist_validator_indices(ValidatorStore::new(db.clone())) {
+                    Ok(restored) => {
+                        debug!(log, "Loaded validator indices"; "restored" => restored, "validators" => node.validators().len())
+                    }
+                    Err(e) => {
+                        warn!(log, "Unable to persist validator indices"; "error" => format!("{:?}", e))
+                    }
+                }
+                if !config.monitored_validators.is_empty() {
+                    node.monitor_validators(&config.monitored_validators, log.clone());
+                }
+                Arc::new(RwLock::new(node))
+            }
+            Err(e) => {
+                error!(log, "Unable to start beacon node"; "error" => format!("{:?}", e));
+                return;
+            }
+        };
+        let genesis_root = node
+            .read()
+            .expect("Beacon node lock poisoned")
+            .genesis_root();
+        /*
+         * The disk guard prunes snapshots as the disk fills, and halts block import before the
+         * database can be corrupted by running out of space.
+         */
+        let disk_guard = {
+            let regenerator = {
+                let node = node.read().expect("Beacon node lock poisoned");
+                StateRegenerator::new(
+                    node.store().clone(),
+                    StateStore::new(db.clone()),
+                    config.state_pruning,
+                    genesis_root,
+                    node.config().cycle_length,
+                    0,
+                )
+            };
+            DiskGuardService::start(
+                DiskGuard::new(
+                    config.disk_guard,
+                    db_path.clone(),
+                    node.clone(),
+                    regenerator,
+                    log.clone(),
+                ),
+                &executor,
+            )
+        };
+        /*
+         * The committees of each cycle are computed as the cycle before begins, ahead of the
+         * requests for duties from validator clients.
+         */
+        let committee_precompute = {
+            let events = node
+                .read()
+                .expect("Beacon node lock poisoned")
+                .events()
+                .subscribe();
+            CommitteePrecomputeService::start(node.clone(), events, &executor, log.clone())
+        };
+        /*
+         * The peers are shared between the network service and the HTTP API, which serves them
+         * before the network starts.
+         */
+        let peer_manager = Arc::new(RwLock::new(PeerManager::new(
+            config.network.peer_manager_config(),
+            log.clone(),
+        )));
+        let mut api_ctx = None;
+        let http_server = if config.http.enabled {
+            let mut ctx = http_api::Context::new(node.clone(), None, executor.clone(), log.clone());
+            ctx.peer_manager = Some(peer_manager.clone());
+            let regenerator = {
+                let node = node.read().expect("Beacon node lock poisoned");
+                StateRegenerator::new(
+                    node.store().clone(),
+                    StateStore::new(db.clone()),
+                    config.state_pruning,
+                    genesis_root,
+                    node.config().cycle_length,
+                    DEFAULT_REGEN_CACHE_SIZE,
+                )
+            };
+            let finalized_root = node
+                .read()
+                .expect("Beacon node lock poisoned")
+                .finalized_root();
+            match regenerator.prune(finalized_root) {
+                Ok(pruned) => {
+                    info!(log, "Pruned state snapshots"; "pruned" => pruned, "mode" => format!("{:?}", config.state_pruning))
+                }
+                Err(e) => {
+                    warn!(log, "Unable to prune state snapshots"; "error" => format!("{:?}", e))
+                }
+            }
+            let mut regen = StateRegenService::start(
+                regenerator,
+                DEFAULT_REGEN_WORKERS,
+                &executor,
+                log.clone(),
+            );
+            let events = node
+                .read()
+                .expect("Beacon node lock poisoned")
+                .events()
+                .subscribe();
+            regen.prune_on_finalization(events, &executor, log.clone());
+            ctx.regen = Some(Arc::new(regen));
+            ctx.transition_pool = Some(Arc::new(StateTransitionPool::start(
+                config.state_transition_threads,
+                &executor,
+            )));
+            let token_path = config.beacon_dir().join(http_api::API_TOKEN_FILE);
+            match http_api::load_or_create_token(&token_path) {
+                Ok(token) => {
+                    info!(log, "HTTP admin API enabled"; "token_file" => format!("{}", token_path.display()));
+                    ctx.admin_token = Some(token);
+                    ctx.heap_profile_dir = Some(config.beacon_dir().join(HEAP_PROFILE_DIR));
+                }
+                Err(e) => warn!(log, "HTTP admin API disabled, unable to load token"; "error" => format!("{}", e)),
+            }
+            if let Err(e) = ctx.load_gossip(&GossipStore::new(db.clone()), Instant::now()) {
+                warn!(log, "Unable to restore gossip state"; "error" => format!("{:?}", e));
+            }
+            let ctx = Arc::new(ctx);
+            api_ctx = Some(ctx.clone());
+            match http_api::ApiServer::start(&config.http, ctx, &executor) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    error!(log, "Unable to start HTTP API"; "error" => format!("{:?}", e));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        /*
+         * The gRPC server and the monitoring service share the context of the HTTP API if it is
+         * enabled, so that an attestation published through either is only published once.
+         */
+        let shared_ctx = match api_ctx {
+            Some(ref ctx) => ctx.clone(),
+            None => {
+                let mut ctx =
+                    http_api::Context::new(node.clone(), None, executor.clone(), log.clone());
+                ctx.peer_manager = Some(peer_manager.clone());
+                Arc::new(ctx)
+            }
+        };
+        let rpc_server = if config.rpc.enabled {
+            match rpc::start_server(&config.rpc, shared_ctx.clone(), &log) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    error!(log, "Unable to start gRPC server"; "error" => format!("{:?}", e));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        let monitoring = match config.monitoring.clone() {
+            Some(monitoring) => {
+                match http_api::MonitoringService::start(
+                    monitoring,
+                    shared_ctx.clone(),
+                    &executor,
+                    log.clone(),
+                ) {
+                    Ok(service) => Some(service),
+                    Err(e) => {
+                        error!(log, "Unable to start monitoring service"; "error" => format!("{:?}", e));
+                        return;
+                    }
+                }
+            }
+            None => None,
+        };
+
+        /*
+         * Before genesis the servers already answer validator clients, while networking starts
+         * only shortly before genesis, so that peers are found by then.
+         */
+        let genesis_time = config.chain.genesis_time;
+        let before_genesis = duration_to_genesis(genesis_time, Duration::from_secs(0)).is_some();
+        let mut network = None;
+        if wait_for_genesis(genesis_time, NETWORK_START_OFFSET, &shutdown, &log) {
+            let fork_digest = ForkDigest::new(http_api::GENESIS_FORK_VERSION, &genesis_root);
+            let gossip_score_params = PeerScoreParams::standard(
+                Duration::from_millis(config.chain.slot_duration_millis),
+                u64::from(config.chain.cycle_length),
+                config.chain.initial_validators.len(),
+            );
+            network = match NetworkService::start(
+                &config.network,
+                peer_manager.clone(),
+                fork_digest,
+                gossip_score_params,
+                PeerStore::new(db.clone()),
+                &executor,
+                log.clone(),
+            ) {
+                Ok((network, _events)) => Some(network),
+                Err(e) => {
+                    error!(log, "Unable to start network service"; "error" => format!("{:?}", e));
+                    return;
+                }
+            };
+            if !before_genesis
+                || wait_for_genesis(genesis_time, Duration::from_secs(0), &shutdown, &log)
+            {
+                if before_genesis {
+                    info!(log, "Genesis reached"; "genesis_time" => genesis_time);
+                }
+                /*
+                 * The servers and network run on their own threads until shutdown, while each
+                 * slot the peers are scored and their events carried out, the local record is
+                 * updated to advertise the attestation subnets requested by validator clients,
+                 * and the meshes of subnets with imminent aggregation duties are checked.
+                 */
+                let slot_duration = Duration::from_millis(config.chain.slot_duration_millis);
+                loop {
+                    if let Some(network) = network.as_mut() {
+                        network.heartbeat(Instant::now());
+                        while let Some(address) = network.next_dial() {
+                            warn!(log, "Unable to dial peer, no transport is running"; "address" => format!("{}", address));
+                        }
+                    }
+                    if let (Some(network), Some(ctx)) = (network.as_mut(), api_ctx.as_ref()) {
+                        let present_slot = node
+                            .read()
+                            .expect("Beacon node lock poisoned")
+                            .present_slot();
+                        let subscriptions = ctx
+                            .subnet_subscriptions
+                            .lock()
+                            .expect("Subnet subscriptions lock poisoned")
+                            .clone();
+                        match network.update_subnets(&subscriptions, present_slot) {
+                            Ok(true) => {
+                                debug!(log, "Updated subnets of local record"; "seq" => network.local_enr().enr().seq())
+                            }
+                            Ok(false) => {}
+                            Err(e) => {
+                                warn!(log, "Unable to update subnets of local record"; "error" => format!("{:?}", e))
+                            }
+                        }
+                        network.update_mesh_metrics();
+                        let aggregations = ctx.aggregation_subnets();
+                        for (subnet, slot) in
+                            network.unmeshed_aggregation_subnets(&aggregations, present_slot)
+                        {
+                            warn!(log, "No mesh peers on subnet ahead of aggregation"; "subnet" => subnet, "duty_slot" => slot);
+                        }
+                    }
+                    match shutdown.recv_timeout(slot_duration) {
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Ok(_) | Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            }
+        }
+
+        /*
+         * The network and servers are stopped before the chain is persisted, so that nothing
+         * changes it meanwhile, and the database is closed last.
+         */
+        let reason = executor.exit().reason().unwrap_or(ShutdownReason::Signal);
+        info!(log, "Shutting down"; "reason" => format!("{:?}", reason));
+        if let Some(ref network) = network {
+            if let Err(e) = network.persist(Instant::now()) {
+                warn!(log, "Unable to persist peers"; "error" => format!("{:?}", e));
+            }
+        }
+        drop(rpc_server);
+        if !stop_within(
+            move || {
+                drop((
+                    network,
+                    http_server,
+                    eth1,
+                    disk_guard,
+                    committee_precompute,
+                    monitoring,
+                ))
+            },
+            SHUTDOWN_TIMEOUT,
+        ) {
+            warn!(log, "Services did not stop in time"; "timeout_secs" => SHUTDOWN_TIMEOUT.as_secs());
+        }
+        if let Some(ctx) = api_ctx {
+            if let Err(e) = ctx.persist_gossip(&GossipStore::new(db.clone()), Instant::now()) {
+                warn!(log, "Unable to persist gossip state"; "error" => format!("{:?}", e));
+            }
+        }
+        let persisted = node
+            .read()
+            .expect("Beacon node lock poisoned")
+            .persist(&chain_store);
+        match persisted {
+            Ok(()) => info!(log, "Persisted chain to database"),
+            Err(e) => error!(log, "Unable to persist chain"; "error" => format!("{:?}", e)),
+        }
+        drop(chain_store);
+        drop(node);
+        if let Err(e) = db.flush() {
+            error!(log, "Unable to flush database"; "error" => e.message);
+        }
+        if Arc::try_unwrap(db).is_err() {
+            warn!(log, "Database still in use, exiting without closing it");
+        }
+        info!(log, "Shutdown complete");
+        /*
+         * A non-zero status lets a supervisor restart the node after a failure.
+         */
+        if reason.is_failure() {
+            process::exit(1);
+        }
+        return;
+    }
 
     error!(
         log,
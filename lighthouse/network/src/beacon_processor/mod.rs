@@ -0,0 +1,512 @@
+mod queue;
+mod service;
+
+pub use self::queue::{FifoQueue, LifoQueue};
+pub use self::service::BeaconProcessorService;
+
+use super::rpc::{BlocksByRangeRequest, BlocksByRootRequest, PeerId, RequestId};
+use super::sync::BatchId;
+use super::types::{Attestation, BeaconBlock};
+use slog::Logger;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeaconProcessorConfig {
+    /// The maximum number of work items processed at once.
+    pub max_workers: usize,
+    pub max_gossip_block_queue_len: usize,
+    /// Applies to both parent chains and range sync segments.
+    pub max_chain_segment_queue_len: usize,
+    pub max_aggregate_queue_len: usize,
+    pub max_attestation_queue_len: usize,
+    /// Applies to each of the blocks by range and blocks by root queues.
+    pub max_request_queue_len: usize,
+    /// The most attestations released together as a `GossipAttestationBatch`. One disables
+    /// batching.
+    pub max_attestation_batch_size: usize,
+    /// How long attestations are held for more to batch them with, unless a full batch is
+    /// queued sooner.
+    pub attestation_batch_delay: Duration,
+}
+
+impl Default for BeaconProcessorConfig {
+    fn default() -> Self {
+        Self {
+            max_workers: 4,
+            max_gossip_block_queue_len: 1_024,
+            max_chain_segment_queue_len: 64,
+            max_aggregate_queue_len: 4_096,
+            max_attestation_queue_len: 16_384,
+            max_request_queue_len: 1_024,
+            max_attestation_batch_size: 64,
+            attestation_batch_delay: Duration::from_millis(5),
+        }
+    }
+}
+
+/// An item of work received from the network, to be handed to the chain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Work {
+    GossipBlock {
+        peer_id: PeerId,
+        block: BeaconBlock,
+    },
+    /// The ancestors of a block with an unknown parent, oldest first, found by `ParentLookup`.
+    ParentChain {
+        blocks: Vec<BeaconBlock>,
+    },
+    /// A batch downloaded by `RangeSync`, in ascending slot order.
+    ChainSegment {
+        batch_id: BatchId,
+        blocks: Vec<BeaconBlock>,
+    },
+    GossipAggregate {
+        peer_id: PeerId,
+        attestation: Attestation,
+    },
+    GossipAttestation {
+        peer_id: PeerId,
+        attestation: Attestation,
+    },
+    /// Attestations released together so that their signatures are verified as one batch, e.g.
+    /// by `gossip::verify_attestation_batch`, newest first.
+    GossipAttestationBatch {
+        attestations: Vec<(PeerId, Attestation)>,
+    },
+    BlocksByRangeRequest {
+        peer_id: PeerId,
+        id: RequestId,
+        request: BlocksByRangeRequest,
+    },
+    BlocksByRootRequest {
+        peer_id: PeerId,
+        id: RequestId,
+        request: BlocksByRootRequest,
+    },
+}
+
+impl Work {
+    pub fn work_type(&self) -> WorkType {
+        match self {
+            Work::GossipBlock { .. } => WorkType::GossipBlock,
+            Work::ParentChain { .. } => WorkType::ParentChain,
+            Work::ChainSegment { .. } => WorkType::ChainSegment,
+            Work::GossipAggregate { .. } => WorkType::GossipAggregate,
+            Work::GossipAttestation { .. } | Work::GossipAttestationBatch { .. } => {
+                WorkType::GossipAttestation
+            }
+            Work::BlocksByRangeRequest { .. } => WorkType::BlocksByRangeRequest,
+            Work::BlocksByRootRequest { .. } => WorkType::BlocksByRootRequest,
+        }
+    }
+}
+
+/// The kinds of `Work`, in order of priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WorkType {
+    GossipBlock,
+    ParentChain,
+    ChainSegment,
+    GossipAggregate,
+    GossipAttestation,
+    BlocksByRangeRequest,
+    BlocksByRootRequest,
+}
+
+/// Queues work between the network and the chain, releasing it to a bounded number of workers
+/// in order of priority.
+///
+/// Gossip blocks come first, as they move the head, followed by the blocks of sync. Attestations
+/// are next, aggregates before unaggregated, and requests from peers come last. Each kind of work
+/// has its own bounded queue, so a flood of one kind cannot exhaust memory or starve another:
+///
+/// - Blocks and requests are queued first-in, first-out. When full, new work is dropped.
+/// - Attestations are queued last-in, first-out, as they lose value as they age. When full, the
+///   oldest is dropped.
+///
+/// Unaggregated attestations are held for up to `attestation_batch_delay` once a worker is free
+/// for them, or until `max_attestation_batch_size` are queued, and released together as a
+/// `GossipAttestationBatch`, so that the work of verifying their signatures is shared.
+///
+/// No threads are used; `BeaconProcessorService` runs the processor with a pool of workers.
+pub struct BeaconProcessor {
+    gossip_blocks: FifoQueue<Work>,
+    parent_chains: FifoQueue<Work>,
+    chain_segments: FifoQueue<Work>,
+    aggregates: LifoQueue<Work>,
+    attestations: LifoQueue<Work>,
+    blocks_by_range_requests: FifoQueue<Work>,
+    blocks_by_root_requests: FifoQueue<Work>,
+    max_attestation_batch_size: usize,
+    attestation_batch_delay: Duration,
+    /// When a worker was first free for the attestations now queued.
+    attestations_held_since: Option<Instant>,
+    max_workers: usize,
+    active_workers: usize,
+    dropped: HashMap<WorkType, u64>,
+    log: Logger,
+}
+
+impl BeaconProcessor {
+    pub fn new(config: &BeaconProcessorConfig, log: Logger) -> Self {
+        Self {
+            gossip_blocks: FifoQueue::new(config.max_gossip_block_queue_len),
+            parent_chains: FifoQueue::new(config.max_chain_segment_queue_len),
+            chain_segments: FifoQueue::new(config.max_chain_segment_queue_len),
+            aggregates: LifoQueue::new(config.max_aggregate_queue_len),
+            attestations: LifoQueue::new(config.max_attestation_queue_len),
+            blocks_by_range_requests: FifoQueue::new(config.max_request_queue_len),
+            blocks_by_root_requests: FifoQueue::new(config.max_request_queue_len),
+            max_attestation_batch_size: config.max_attestation_batch_size,
+            attestation_batch_delay: config.attestation_batch_delay,
+            attestations_held_since: None,
+            max_workers: config.max_workers,
+            active_workers: 0,
+            dropped: HashMap::new(),
+            log,
+        }
+    }
+
+    /// Queues `work`, returning any work dropped to bound the queue.
+    ///
+    /// The dropped work may be `work` itself or, for attestations, older work of the same kind.
+    /// Dropped requests should still be answered, so that the stream is closed.
+    pub fn push(&mut self, work: Work) -> Option<Work> {
+        let dropped = match work.work_type() {
+            WorkType::GossipBlock => self.gossip_blocks.push(work).err(),
+            WorkType::ParentChain => self.parent_chains.push(work).err(),
+            WorkType::ChainSegment => self.chain_segments.push(work).err(),
+            WorkType::GossipAggregate => self.aggregates.push(work),
+            WorkType::GossipAttestation => self.attestations.push(work),
+            WorkType::BlocksByRangeRequest => self.blocks_by_range_requests.push(work).err(),
+            WorkType::BlocksByRootRequest => self.blocks_by_root_requests.push(work).err(),
+        };
+        if let Some(ref work) = dropped {
+            let work_type = work.work_type();
+            let count = self.dropped.entry(work_type).or_insert(0);
+            *count += 1;
+            /*
+             * Log the first drop, then every thousandth, so that an overloaded node does not
+             * also flood its logs.
+             */
+            if *count % 1_000 == 1 {
+                warn!(self.log, "Work queue full, dropping work"; "work_type" => format!("{:?}", work_type), "dropped" => *count);
+            }
+        }
+        dropped
+    }
+
+    /// Returns the highest priority work, if a worker is free to process it.
+    ///
+    /// `on_work_complete` must be called once the work has been processed.
+    pub fn next_work(&mut self, now: Instant) -> Option<Work> {
+        if self.active_workers >= self.max_workers {
+            return None;
+        }
+        let work = self
+            .gossip_blocks
+            .pop()
+            .or_else(|| self.parent_chains.pop())
+            .or_else(|| self.chain_segments.pop())
+            .or_else(|| self.aggregates.pop())
+            .or_else(|| self.next_attestations(now))
+            .or_else(|| self.blocks_by_range_requests.pop())
+            .or_else(|| self.blocks_by_root_requests.pop());
+        if work.is_some() {
+            self.active_workers += 1;
+        }
+        work
+    }
+
+    /// Returns the attestations to release, unless they are held for more to batch them with.
+    fn next_attestations(&mut self, now: Instant) -> Option<Work> {
+        if self.attestations.is_empty() {
+            self.attestations_held_since = None;
+            return None;
+        }
+        if self.max_attestation_batch_size <= 1 {
+            return self.attestations.pop();
+        }
+        let held_since = *self.attestations_held_since.get_or_insert(now);
+        if self.attestations.len() < self.max_attestation_batch_size
+            && now < held_since + self.attestation_batch_delay
+        {
+            return None;
+        }
+
+        let mut batch = vec![];
+        while batch.len() < self.max_attestation_batch_size {
+            match self.attestations.pop() {
+                Some(Work::GossipAttestation {
+                    peer_id,
+                    attestation,
+                }) => batch.push((peer_id, attestation)),
+                Some(Work::GossipAttestationBatch { attestations }) => batch.extend(attestations),
+                Some(_) => {}
+                None => break,
+            }
+        }
+        /*
+         * Attestations left over have been held long enough, so are released as soon as a worker
+         * is free.
+         */
+        if self.attestations.is_empty() {
+            self.attestations_held_since = None;
+        }
+        if batch.len() == 1 {
+            let (peer_id, attestation) = batch.remove(0);
+            Some(Work::GossipAttestation {
+                peer_id,
+                attestation,
+            })
+        } else {
+            Some(Work::GossipAttestationBatch {
+                attestations: batch,
+            })
+        }
+    }
+
+    /// Returns when the attestations held for batching are to be released, if a worker is free
+    /// for them.
+    pub fn attestation_deadline(&self) -> Option<Instant> {
+        if self.active_workers >= self.max_workers || self.attestations.is_empty() {
+            return None;
+        }
+        self.attestations_held_since
+            .map(|since| since + self.attestation_batch_delay)
+    }
+
+    /// Frees the worker of work returned by `next_work`.
+    pub fn on_work_complete(&mut self) {
+        self.active_workers = self.active_workers.saturating_sub(1);
+    }
+
+    pub fn active_workers(&self) -> usize {
+        self.active_workers
+    }
+
+    /// Returns the number of items of `work_type` waiting for a worker.
+    pub fn queue_len(&self, work_type: WorkType) -> usize {
+        match work_type {
+            WorkType::GossipBlock => self.gossip_blocks.len(),
+            WorkType::ParentChain => self.parent_chains.len(),
+            WorkType::ChainSegment => self.chain_segments.len(),
+            WorkType::GossipAggregate => self.aggregates.len(),
+            WorkType::GossipAttestation => self.attestations.len(),
+            WorkType::BlocksByRangeRequest => self.blocks_by_range_requests.len(),
+            WorkType::BlocksByRootRequest => self.blocks_by_root_requests.len(),
+        }
+    }
+
+    /// Returns the number of items of `work_type` dropped because their queue was full.
+    pub fn dropped_count(&self, work_type: WorkType) -> u64 {
+        self.dropped.get(&work_type).cloned().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gossip_blocks.is_empty()
+            && self.parent_chains.is_empty()
+            && self.chain_segments.is_empty()
+            && self.aggregates.is_empty()
+            && self.attestations.is_empty()
+            && self.blocks_by_range_requests.is_empty()
+            && self.blocks_by_root_requests.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::enr::NodeId;
+    use super::*;
+    use slog::Discard;
+
+    fn processor(config: &BeaconProcessorConfig) -> BeaconProcessor {
+        BeaconProcessor::new(config, Logger::root(Discard, o!()))
+    }
+
+    fn block(slot: u64) -> Work {
+        let mut block = BeaconBlock::zero();
+        block.slot = slot;
+        Work::GossipBlock {
+            peer_id: NodeId::random(),
+            block,
+        }
+    }
+
+    fn attestation(slot: u64) -> Work {
+        let mut attestation = Attestation::zero();
+        attestation.data.slot = slot;
+        Work::GossipAttestation {
+            peer_id: NodeId::random(),
+            attestation,
+        }
+    }
+
+    fn request() -> Work {
+        Work::BlocksByRangeRequest {
+            peer_id: NodeId::random(),
+            id: 0,
+            request: BlocksByRangeRequest {
+                start_slot: 0,
+                count: 1,
+                step: 1,
+            },
+        }
+    }
+
+    fn next_type(processor: &mut BeaconProcessor) -> Option<WorkType> {
+        let work = processor.next_work(Instant::now());
+        processor.on_work_complete();
+        work.map(|work| work.work_type())
+    }
+
+    #[test]
+    fn test_work_released_by_priority() {
+        let config = BeaconProcessorConfig {
+            attestation_batch_delay: Duration::from_millis(0),
+            ..BeaconProcessorConfig::default()
+        };
+        let mut processor = processor(&config);
+        assert_eq!(processor.push(request()), None);
+        assert_eq!(processor.push(attestation(1)), None);
+        assert_eq!(
+            processor.push(Work::ChainSegment {
+                batch_id: 0,
+                blocks: vec![],
+            }),
+            None
+        );
+        assert_eq!(processor.push(block(2)), None);
+
+        assert_eq!(next_type(&mut processor), Some(WorkType::GossipBlock));
+        assert_eq!(next_type(&mut processor), Some(WorkType::ChainSegment));
+        assert_eq!(next_type(&mut processor), Some(WorkType::GossipAttestation));
+        assert_eq!(
+            next_type(&mut processor),
+            Some(WorkType::BlocksByRangeRequest)
+        );
+        assert_eq!(next_type(&mut processor), None);
+        assert!(processor.is_empty());
+    }
+
+    #[test]
+    fn test_full_queues_shed_work() {
+        let config = BeaconProcessorConfig {
+            max_gossip_block_queue_len: 2,
+            max_attestation_queue_len: 2,
+            max_attestation_batch_size: 1,
+            ..BeaconProcessorConfig::default()
+        };
+        let mut processor = processor(&config);
+
+        /*
+         * Blocks already queued are kept, and the newest attestations are kept.
+         */
+        for slot in 0..2 {
+            assert_eq!(processor.push(block(slot)), None);
+        }
+        match processor.push(block(2)) {
+            Some(Work::GossipBlock { block, .. }) => assert_eq!(block.slot, 2),
+            other => panic!("expected the new block to be dropped, got {:?}", other),
+        }
+        for slot in 0..2 {
+            assert_eq!(processor.push(attestation(slot)), None);
+        }
+        match processor.push(attestation(2)) {
+            Some(Work::GossipAttestation { attestation, .. }) => {
+                assert_eq!(attestation.data.slot, 0)
+            }
+            other => panic!(
+                "expected the oldest attestation to be dropped, got {:?}",
+                other
+            ),
+        }
+        assert_eq!(processor.dropped_count(WorkType::GossipBlock), 1);
+        assert_eq!(processor.dropped_count(WorkType::GossipAttestation), 1);
+        assert_eq!(processor.queue_len(WorkType::GossipAttestation), 2);
+
+        let slots: Vec<u64> = (0..4)
+            .filter_map(|_| match processor.next_work(Instant::now()) {
+                Some(Work::GossipBlock { block, .. }) => Some(block.slot),
+                Some(Work::GossipAttestation { attestation, .. }) => Some(attestation.data.slot),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(slots, vec![0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_workers_bounded() {
+        let config = BeaconProcessorConfig {
+            max_workers: 2,
+            ..BeaconProcessorConfig::default()
+        };
+        let mut processor = processor(&config);
+        let now = Instant::now();
+        for slot in 0..3 {
+            processor.push(block(slot));
+        }
+        assert!(processor.next_work(now).is_some());
+        assert!(processor.next_work(now).is_some());
+        assert_eq!(processor.next_work(now), None);
+        assert_eq!(processor.active_workers(), 2);
+
+        processor.on_work_complete();
+        assert!(processor.next_work(now).is_some());
+        assert!(processor.is_empty());
+    }
+
+    #[test]
+    fn test_attestations_batched() {
+        let config = BeaconProcessorConfig {
+            max_attestation_batch_size: 3,
+            attestation_batch_delay: Duration::from_millis(5),
+            ..BeaconProcessorConfig::default()
+        };
+        let mut processor = processor(&config);
+        let now = Instant::now();
+        let delay = config.attestation_batch_delay;
+        let slots = |work: Option<Work>| -> Vec<u64> {
+            match work {
+                Some(Work::GossipAttestationBatch { attestations }) => attestations
+                    .iter()
+                    .map(|(_, attestation)| attestation.data.slot)
+                    .collect(),
+                Some(Work::GossipAttestation { attestation, .. }) => vec![attestation.data.slot],
+                other => panic!("expected attestations, got {:?}", other),
+            }
+        };
+
+        /*
+         * An attestation is held until the delay has passed, while other work is released.
+         */
+        processor.push(attestation(0));
+        processor.push(request());
+        assert_eq!(
+            processor.next_work(now).map(|work| work.work_type()),
+            Some(WorkType::BlocksByRangeRequest)
+        );
+        processor.on_work_complete();
+        assert_eq!(processor.attestation_deadline(), Some(now + delay));
+        processor.push(attestation(1));
+        assert_eq!(processor.next_work(now + delay / 2), None);
+        assert_eq!(slots(processor.next_work(now + delay)), vec![1, 0]);
+        processor.on_work_complete();
+        assert_eq!(processor.attestation_deadline(), None);
+
+        /*
+         * A full batch is released at once, newest first, and the rest once the delay passes.
+         */
+        for slot in 2..6 {
+            processor.push(attestation(slot));
+        }
+        let later = now + delay * 2;
+        assert_eq!(slots(processor.next_work(later)), vec![5, 4, 3]);
+        processor.on_work_complete();
+        assert_eq!(processor.next_work(later), None);
+        assert_eq!(processor.attestation_deadline(), Some(later + delay));
+        assert_eq!(slots(processor.next_work(later + delay)), vec![2]);
+        assert!(processor.is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+/// A bounded first-in, first-out queue.
+///
+/// When full, new items are rejected, so that work already queued is never lost. Used for work
+/// where order matters, such as blocks.
+pub struct FifoQueue<T> {
+    queue: VecDeque<T>,
+    max_length: usize,
+}
+
+impl<T> FifoQueue<T> {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            max_length,
+        }
+    }
+
+    /// Adds `item` to the back of the queue, returning it if the queue is full.
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        if self.queue.len() >= self.max_length {
+            Err(item)
+        } else {
+            self.queue.push_back(item);
+            Ok(())
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// A bounded last-in, first-out queue.
+///
+/// When full, the oldest item is evicted to make room. Used for work which loses value as it
+/// ages, such as attestations, so that the freshest is processed first and a backlog sheds its
+/// stalest items.
+pub struct LifoQueue<T> {
+    queue: VecDeque<T>,
+    max_length: usize,
+}
+
+impl<T> LifoQueue<T> {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            max_length,
+        }
+    }
+
+    /// Adds `item` to the front of the queue, returning the evicted item if the queue was full.
+    pub fn push(&mut self, item: T) -> Option<T> {
+        if self.max_length == 0 {
+            return Some(item);
+        }
+        let evicted = if self.queue.len() >= self.max_length {
+            self.queue.pop_back()
+        } else {
+            None
+        };
+        self.queue.push_front(item);
+        evicted
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.queue.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fifo_rejects_when_full() {
+        let mut queue = FifoQueue::new(2);
+        assert_eq!(queue.push(1), Ok(()));
+        assert_eq!(queue.push(2), Ok(()));
+        assert_eq!(queue.push(3), Err(3));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.push(4), Ok(()));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(4));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_lifo_evicts_oldest_when_full() {
+        let mut queue = LifoQueue::new(2);
+        assert_eq!(queue.push(1), None);
+        assert_eq!(queue.push(2), None);
+        assert_eq!(queue.push(3), Some(1));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), None);
+
+        let mut queue = LifoQueue::new(0);
+        assert_eq!(queue.push(1), Some(1));
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+use super::{BeaconProcessor, BeaconProcessorConfig, Work};
+use slog::Logger;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use task_executor::TaskExecutor;
+
+enum Event {
+    Work(Box<Work>),
+    WorkComplete,
+    Shutdown,
+}
+
+/// Runs a `BeaconProcessor` on a background thread, handing work to a pool of
+/// `max_workers` worker threads, each of which calls `handler`.
+///
+/// Work still queued when the service is dropped is discarded. Work already being processed is
+/// completed before `drop` returns.
+pub struct BeaconProcessorService {
+    events: Sender<Event>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BeaconProcessorService {
+    pub fn start<F>(
+        config: BeaconProcessorConfig,
+        handler: F,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> Self
+    where
+        F: Fn(Work) + Send + Sync + 'static,
+    {
+        let (event_tx, event_rx) = channel();
+        let (work_tx, work_rx) = channel();
+        let work_rx = Arc::new(Mutex::new(work_rx));
+        let handler = Arc::new(handler);
+
+        let workers = (0..config.max_workers)
+            .map(|_| {
+                let work_rx = work_rx.clone();
+                let event_tx = event_tx.clone();
+                let handler = handler.clone();
+                executor.spawn("beacon_processor_worker", move |_| {
+                    work(&work_rx, &event_tx, &*handler)
+                })
+            })
+            .collect();
+
+        let processor = BeaconProcessor::new(&config, log.clone());
+        let handle = executor.spawn("beacon_processor", move |_| {
+            manage(processor, &event_rx, work_tx, workers, &log)
+        });
+
+        Self {
+            events: event_tx,
+            handle: Some(handle),
+        }
+    }
+
+    /// Queues `work`. Work dropped because its queue is full is counted and logged.
+    pub fn send(&self, work: Work) {
+        let _ = self.events.send(Event::Work(Box::new(work)));
+    }
+}
+
+impl Drop for BeaconProcessorService {
+    fn drop(&mut self) {
+        let _ = self.events.send(Event::Shutdown);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn manage(
+    mut processor: BeaconProcessor,
+    events: &Receiver<Event>,
+    work_tx: Sender<Work>,
+    workers: Vec<JoinHandle<()>>,
+    log: &Logger,
+) {
+    loop {
+        /*
+         * Attestations held for batching are released when their deadline passes, even if
+         * nothing else arrives.
+         */
+        let event = match processor.attestation_deadline() {
+            Some(deadline) => {
+                let now = Instant::now();
+                let timeout = if deadline > now {
+                    deadline - now
+                } else {
+                    Duration::from_millis(0)
+                };
+                match events.recv_timeout(timeout) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match events.recv() {
+                Ok(event) => Some(event),
+                Err(_) => break,
+            },
+        };
+        match event {
+            Some(Event::Work(work)) => {
+                processor.push(*work);
+            }
+            Some(Event::WorkComplete) => processor.on_work_complete(),
+            Some(Event::Shutdown) => break,
+            None => {}
+        }
+        /*
+         * Work is only released to the pool while a worker is idle, so that anything which
+         * arrives in the meantime is still ordered by priority.
+         */
+        while let Some(work) = processor.next_work(Instant::now()) {
+            if work_tx.send(work).is_err() {
+                break;
+            }
+        }
+    }
+
+    debug!(log, "Beacon processor shutting down"; "active_workers" => processor.active_workers());
+    drop(work_tx);
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+fn work<F>(work_rx: &Mutex<Receiver<Work>>, events: &Sender<Event>, handler: &F)
+where
+    F: Fn(Work),
+{
+    loop {
+        /*
+         * The lock is released before the work is processed, so other workers may take work.
+         */
+        let work = match work_rx.lock() {
+            Ok(work_rx) => work_rx.recv(),
+            Err(_) => return,
+        };
+        match work {
+            Ok(work) => {
+                handler(work);
+                let _ = events.send(Event::WorkComplete);
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::enr::NodeId;
+    use super::super::super::types::{Attestation, BeaconBlock};
+    use super::super::WorkType;
+    use super::*;
+    use slog::Discard;
+    use std::time::Duration;
+
+    #[test]
+    fn test_service_processes_by_priority() {
+        let config = BeaconProcessorConfig {
+            max_workers: 1,
+            ..BeaconProcessorConfig::default()
+        };
+        let (processed_tx, processed_rx) = channel();
+        let (gate_tx, gate_rx) = channel::<()>();
+        let (processed_tx, gate_rx) = (Mutex::new(processed_tx), Mutex::new(gate_rx));
+        let handler = move |work: Work| {
+            processed_tx.lock().unwrap().send(work.work_type()).unwrap();
+            gate_rx.lock().unwrap().recv().unwrap();
+        };
+        let log = Logger::root(Discard, o!());
+        let (executor, _) = TaskExecutor::new(log.clone());
+        let service = BeaconProcessorService::start(config, handler, &executor, log);
+
+        let peer_id = NodeId::random();
+        let attestation = || Work::GossipAttestation {
+            peer_id,
+            attestation: Attestation::zero(),
+        };
+        let timeout = Duration::from_secs(5);
+
+        /*
+         * The only worker is held on the first attestation while the rest of the work is queued.
+         */
+        service.send(attestation());
+        assert_eq!(
+            processed_rx.recv_timeout(timeout).unwrap(),
+            WorkType::GossipAttestation
+        );
+        service.send(attestation());
+        service.send(Work::GossipAggregate {
+            peer_id,
+            attestation: Attestation::zero(),
+        });
+        service.send(Work::GossipBlock {
+            peer_id,
+            block: BeaconBlock::zero(),
+        });
+        for _ in 0..4 {
+            gate_tx.send(()).unwrap();
+        }
+
+        let order: Vec<WorkType> = (0..3)
+            .map(|_| processed_rx.recv_timeout(timeout).unwrap())
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                WorkType::GossipBlock,
+                WorkType::GossipAggregate,
+                WorkType::GossipAttestation
+            ]
+        );
+        drop(service);
+    }
+}
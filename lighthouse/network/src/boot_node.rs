@@ -0,0 +1,271 @@
+use super::bls::{Keypair, PublicKey, SecretKey};
+use super::discovery::{DiscoveryConfig, DiscoveryEvent, DiscoveryService};
+use super::enr::Enr;
+use super::ssz::{ssz_encode, Decodable};
+use slog::Logger;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use task_executor::TaskExecutor;
+
+/// The file in the boot node directory holding the secret key.
+const KEY_FILE: &str = "key";
+/// The file in the boot node directory holding the SSZ of the last advertised record.
+const ENR_FILE: &str = "enr";
+
+#[derive(Debug)]
+pub enum BootNodeError {
+    Io(io::Error),
+    /// The key file does not contain a valid secret key.
+    InvalidKey,
+}
+
+impl From<io::Error> for BootNodeError {
+    fn from(e: io::Error) -> BootNodeError {
+        BootNodeError::Io(e)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BootNodeConfig {
+    /// The address on which discovery listens.
+    pub listen_addr: SocketAddr,
+    /// The address advertised in the record. Defaults to the listen address, if it is a specific
+    /// IPv4 address.
+    pub enr_address: Option<Ipv4Addr>,
+    /// The UDP port advertised in the record. Defaults to the listen port.
+    pub enr_port: Option<u16>,
+    /// Other boot nodes, used to populate the routing table.
+    pub bootnodes: Vec<Enr>,
+    /// The directory holding the key and record.
+    pub dir: PathBuf,
+}
+
+/// Runs discovery alone, without a beacon chain or database, so that it can serve as an entry
+/// point to the network.
+///
+/// The only state kept is the node's key and its last record, so that its identity survives
+/// restarts and its `seq` keeps increasing as its address changes.
+pub struct BootNode {
+    service: DiscoveryService,
+    enr: Enr,
+}
+
+impl BootNode {
+    pub fn start(
+        config: BootNodeConfig,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> Result<(Self, Receiver<DiscoveryEvent>), BootNodeError> {
+        fs::create_dir_all(&config.dir)?;
+        let keypair = load_or_generate_keypair(&config.dir.join(KEY_FILE))?;
+
+        let ip = config.enr_address.or_else(|| match config.listen_addr {
+            SocketAddr::V4(addr) if !addr.ip().is_unspecified() => Some(*addr.ip()),
+            _ => None,
+        });
+        let udp = config.enr_port.unwrap_or_else(|| config.listen_addr.port());
+        let enr = load_or_build_enr(&config.dir.join(ENR_FILE), &keypair, ip, udp)?;
+        if ip.is_none() {
+            warn!(log, "Boot node record has no address"; "help" => "set the advertised address");
+        }
+
+        let discovery_config = DiscoveryConfig {
+            bootnodes: config.bootnodes,
+            ..DiscoveryConfig::default()
+        };
+        let (service, events) = DiscoveryService::start(
+            config.listen_addr,
+            enr.clone(),
+            discovery_config,
+            executor,
+            log.clone(),
+        )?;
+        info!(log, "Boot node started"; "enr" => format!("{}", enr), "node_id" => format!("{:?}", enr.node_id()));
+
+        Ok((Self { service, enr }, events))
+    }
+
+    /// The record advertised by the boot node, to be distributed to other nodes.
+    pub fn enr(&self) -> &Enr {
+        &self.enr
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.service.local_addr()
+    }
+}
+
+/// Reads the secret key at `path`, generating and saving a new one if the file does not exist.
+pub fn load_or_generate_keypair(path: &Path) -> Result<Keypair, BootNodeError> {
+    match File::open(path) {
+        Ok(mut file) => {
+            let mut bytes = vec![];
+            file.read_to_end(&mut bytes)?;
+            let sk = SecretKey::from_bytes(&bytes).map_err(|_| BootNodeError::InvalidKey)?;
+            let pk = PublicKey::from_secret_key(&sk);
+            Ok(Keypair { sk, pk })
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            let keypair = Keypair::random();
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            set_private(&mut options);
+            options.open(path)?.write_all(&keypair.sk.as_bytes())?;
+            Ok(keypair)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(unix)]
+fn set_private(options: &mut OpenOptions) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+}
+
+#[cfg(not(unix))]
+fn set_private(_: &mut OpenOptions) {}
+
+/// Builds the record for `ip` and `udp`, continuing from the record saved at `path` if it was
+/// made with the same key, and saves the result.
+///
+/// Boot nodes do not accept connections, so advertise no TCP port.
+fn load_or_build_enr(
+    path: &Path,
+    keypair: &Keypair,
+    ip: Option<Ipv4Addr>,
+    udp: u16,
+) -> Result<Enr, BootNodeError> {
+    let persisted = match fs::read(path) {
+        Ok(bytes) => Enr::ssz_decode(&bytes, 0).ok().map(|(enr, _)| enr),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut enr = match persisted {
+        Some(ref enr) if *enr.public_key() == keypair.pk && enr.verify() => enr.clone(),
+        _ => Enr::new(keypair, ip, None, Some(udp)),
+    };
+    enr.set_ip(ip, keypair);
+    enr.set_tcp(None, keypair);
+    enr.set_udp(Some(udp), keypair);
+    fs::write(path, ssz_encode(&enr))?;
+    Ok(enr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::rand;
+    use super::*;
+    use slog::Discard;
+    use std::time::Duration;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("boot_node_test_{}", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn test_identity_persists() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let key_path = dir.join(KEY_FILE);
+        let enr_path = dir.join(ENR_FILE);
+
+        let keypair = load_or_generate_keypair(&key_path).unwrap();
+        assert_eq!(load_or_generate_keypair(&key_path).unwrap().pk, keypair.pk);
+
+        let ip = Some(Ipv4Addr::new(10, 0, 0, 1));
+        let first = load_or_build_enr(&enr_path, &keypair, ip, 9000).unwrap();
+        assert_eq!(first.tcp(), None);
+        let same = load_or_build_enr(&enr_path, &keypair, ip, 9000).unwrap();
+        assert_eq!(same, first);
+
+        /*
+         * A change of address continues the sequence of the saved record.
+         */
+        let moved = load_or_build_enr(&enr_path, &keypair, ip, 9001).unwrap();
+        assert_eq!(moved.udp(), Some(9001));
+        assert!(moved.seq() > first.seq());
+
+        fs::write(&key_path, b"not a key").unwrap();
+        match load_or_generate_keypair(&key_path) {
+            Err(BootNodeError::InvalidKey) => {}
+            other => panic!("expected invalid key, got {:?}", other.map(|k| k.pk)),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Starts a regular node, which advertises a TCP port and so is dialable.
+    fn start_node(bootnodes: Vec<Enr>) -> (DiscoveryService, Receiver<DiscoveryEvent>, Enr) {
+        let port = free_port();
+        let keypair = Keypair::random();
+        let enr = Enr::new(&keypair, Some(Ipv4Addr::LOCALHOST), Some(port), Some(port));
+        let config = DiscoveryConfig {
+            bootnodes,
+            ..DiscoveryConfig::default()
+        };
+        let listen_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+        let (service, events) = DiscoveryService::start(
+            listen_addr,
+            enr.clone(),
+            config,
+            &TaskExecutor::new(Logger::root(Discard, o!())).0,
+            Logger::root(Discard, o!()),
+        )
+        .unwrap();
+        (service, events, enr)
+    }
+
+    fn free_port() -> u16 {
+        std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn test_peers_discovered_via_boot_node() {
+        let config = BootNodeConfig {
+            listen_addr: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), free_port()),
+            enr_address: None,
+            enr_port: None,
+            bootnodes: vec![],
+            dir: temp_dir(),
+        };
+        let (executor, _) = TaskExecutor::new(Logger::root(Discard, o!()));
+        let (boot_node, _boot_node_events) =
+            BootNode::start(config.clone(), &executor, Logger::root(Discard, o!())).unwrap();
+        assert_eq!(boot_node.enr().udp_socket(), Some(config.listen_addr));
+
+        /*
+         * The boot node is not dialable, so is never reported as a peer itself. It only
+         * introduces nodes to each other.
+         */
+        let (_first, first_events, first_enr) = start_node(vec![boot_node.enr().clone()]);
+        match first_events.recv_timeout(Duration::from_secs(5)).unwrap() {
+            DiscoveryEvent::QueryComplete { found: 0, .. } => {}
+            other => panic!("expected no peers, got {:?}", other),
+        }
+
+        /*
+         * Each query targets a random id, and the boot node only returns the buckets near the
+         * target, so a query may miss the first node.
+         */
+        let (second, second_events, _) = start_node(vec![boot_node.enr().clone()]);
+        let mut found = vec![];
+        for _ in 0..20 {
+            match second_events.recv_timeout(Duration::from_secs(5)).unwrap() {
+                DiscoveryEvent::PeersDiscovered { mut peers, .. } => {
+                    found.append(&mut peers);
+                    break;
+                }
+                DiscoveryEvent::QueryComplete { .. } => second.discover_peers(),
+            }
+        }
+        assert_eq!(found, vec![first_enr]);
+        fs::remove_dir_all(&config.dir).unwrap();
+    }
+}
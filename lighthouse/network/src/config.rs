@@ -0,0 +1,182 @@
+use super::discovery::DiscoveryConfig;
+use super::enr::Enr;
+use super::local_enr::EnrConfig;
+use super::peer_manager::{Cidr, PeerManagerConfig};
+use super::rpc::{ForkDigest, PeerId};
+use super::upnp::UPnPConfig;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+/// The configuration of the network service, as set by the operator.
+#[derive(Clone, Debug)]
+pub struct NetworkConfig {
+    /// The directory holding the node's key.
+    pub network_dir: PathBuf,
+    /// The address on which both connections and discovery packets are accepted.
+    pub listen_address: IpAddr,
+    /// The TCP port on which peers connect.
+    pub libp2p_port: u16,
+    /// The UDP port used for discovery.
+    pub discovery_port: u16,
+    /// The number of peers to maintain.
+    pub target_peers: usize,
+    /// Records used to join the network when the routing table is empty.
+    pub boot_nodes: Vec<Enr>,
+    /// The address advertised in the local record. Defaults to the listen address, if it is a
+    /// specific IPv4 address.
+    pub enr_address: Option<Ipv4Addr>,
+    /// The TCP port advertised in the local record. Defaults to `libp2p_port`.
+    pub enr_tcp_port: Option<u16>,
+    /// The UDP port advertised in the local record. Defaults to `discovery_port`.
+    pub enr_udp_port: Option<u16>,
+    /// Disables discovery, so that only peers which dial the node are connected.
+    pub disable_discovery: bool,
+    /// Disables forwarding the ports on the internet gateway with UPnP, which otherwise updates
+    /// the local record with the external address.
+    pub disable_upnp: bool,
+    /// Peers which are never dialed or accepted.
+    pub banned_peers: Vec<PeerId>,
+    /// Address ranges from which peers are never dialed or accepted.
+    pub banned_ranges: Vec<Cidr>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            network_dir: PathBuf::from("network"),
+            listen_address: Ipv4Addr::UNSPECIFIED.into(),
+            libp2p_port: 9000,
+            discovery_port: 9000,
+            target_peers: PeerManagerConfig::default().target_peers,
+            boot_nodes: vec![],
+            enr_address: None,
+            enr_tcp_port: None,
+            enr_udp_port: None,
+            disable_discovery: false,
+            disable_upnp: false,
+            banned_peers: vec![],
+            banned_ranges: vec![],
+        }
+    }
+}
+
+impl NetworkConfig {
+    pub fn discovery_listen_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.listen_address, self.discovery_port)
+    }
+
+    pub fn discovery_config(&self) -> DiscoveryConfig {
+        DiscoveryConfig {
+            bootnodes: self.boot_nodes.clone(),
+            ..DiscoveryConfig::default()
+        }
+    }
+
+    pub fn peer_manager_config(&self) -> PeerManagerConfig {
+        PeerManagerConfig {
+            target_peers: self.target_peers,
+            banned_peers: self.banned_peers.clone(),
+            banned_ranges: self.banned_ranges.clone(),
+            ..PeerManagerConfig::default()
+        }
+    }
+
+    /// The ports to forward with UPnP. The discovery port is only forwarded while discovery is
+    /// enabled.
+    pub fn upnp_config(&self) -> UPnPConfig {
+        UPnPConfig {
+            tcp_port: self.libp2p_port,
+            udp_port: if self.disable_discovery {
+                None
+            } else {
+                Some(self.discovery_port)
+            },
+            ..UPnPConfig::default()
+        }
+    }
+
+    /// The fields of the local record, with the overrides applied.
+    ///
+    /// No UDP port is advertised while discovery is disabled, as nothing is listening on it.
+    pub fn enr_config(&self, fork_digest: ForkDigest) -> EnrConfig {
+        let ip = self.enr_address.or_else(|| match self.listen_address {
+            IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+            _ => None,
+        });
+        let udp = if self.disable_discovery {
+            None
+        } else {
+            Some(self.enr_udp_port.unwrap_or(self.discovery_port))
+        };
+        EnrConfig {
+            ip,
+            tcp: Some(self.enr_tcp_port.unwrap_or(self.libp2p_port)),
+            udp,
+            fork_digest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enr_config_overrides() {
+        let fork_digest = ForkDigest([1, 2, 3, 4]);
+        let mut config = NetworkConfig {
+            libp2p_port: 9000,
+            discovery_port: 9001,
+            ..NetworkConfig::default()
+        };
+        assert_eq!(
+            config.enr_config(fork_digest),
+            EnrConfig {
+                ip: None,
+                tcp: Some(9000),
+                udp: Some(9001),
+                fork_digest,
+            }
+        );
+
+        config.listen_address = Ipv4Addr::new(10, 0, 0, 1).into();
+        assert_eq!(
+            config.enr_config(fork_digest).ip,
+            Some(Ipv4Addr::new(10, 0, 0, 1))
+        );
+
+        config.enr_address = Some(Ipv4Addr::new(1, 2, 3, 4));
+        config.enr_tcp_port = Some(30303);
+        config.enr_udp_port = Some(30304);
+        assert_eq!(
+            config.enr_config(fork_digest),
+            EnrConfig {
+                ip: Some(Ipv4Addr::new(1, 2, 3, 4)),
+                tcp: Some(30303),
+                udp: Some(30304),
+                fork_digest,
+            }
+        );
+
+        assert_eq!(config.upnp_config().udp_port, Some(9001));
+        config.disable_discovery = true;
+        assert_eq!(config.enr_config(fork_digest).udp, None);
+        assert_eq!(config.upnp_config().udp_port, None);
+    }
+
+    #[test]
+    fn test_peer_manager_config_bans() {
+        let peer_id = PeerId::random();
+        let range = "10.0.0.0/8".parse::<Cidr>().unwrap();
+        let config = NetworkConfig {
+            target_peers: 10,
+            banned_peers: vec![peer_id],
+            banned_ranges: vec![range],
+            ..NetworkConfig::default()
+        };
+        let peer_manager_config = config.peer_manager_config();
+        assert_eq!(peer_manager_config.target_peers, 10);
+        assert_eq!(peer_manager_config.banned_peers, vec![peer_id]);
+        assert_eq!(peer_manager_config.banned_ranges, vec![range]);
+    }
+}
@@ -0,0 +1,217 @@
+use super::super::enr::{Enr, NodeId};
+use std::time::Instant;
+
+/// The maximum number of nodes stored in each bucket.
+pub const K_BUCKET_SIZE: usize = 16;
+/// One bucket per possible `log2_distance` (1..=256).
+pub const NUM_BUCKETS: usize = 256;
+
+#[derive(Debug, PartialEq)]
+pub enum InsertResult {
+    /// The node was not previously known and has been added.
+    Inserted,
+    /// The node was known; its record was replaced if the new record had a higher `seq`.
+    Updated,
+    /// The bucket for this node is full, the node was not added.
+    BucketFull,
+    /// The record describes the local node, which is never stored.
+    LocalNode,
+}
+
+struct Entry {
+    enr: Enr,
+    last_seen: Instant,
+}
+
+/// A Kademlia routing table, storing the records of nodes bucketed by their `log2_distance` to
+/// the local node.
+///
+/// Buckets are kept in order of when each node was last seen, least-recently seen first.
+pub struct KBucketsTable {
+    local_id: NodeId,
+    buckets: Vec<Vec<Entry>>,
+}
+
+impl KBucketsTable {
+    pub fn new(local_id: NodeId) -> Self {
+        Self {
+            local_id,
+            buckets: (0..NUM_BUCKETS).map(|_| vec![]).collect(),
+        }
+    }
+
+    fn bucket_index(&self, id: &NodeId) -> Option<usize> {
+        self.local_id
+            .log2_distance(id)
+            .map(|distance| distance as usize - 1)
+    }
+
+    /// Inserts or updates a node, marking it as seen at `now`.
+    pub fn insert(&mut self, enr: Enr, now: Instant) -> InsertResult {
+        let id = enr.node_id();
+        let index = match self.bucket_index(&id) {
+            Some(index) => index,
+            None => return InsertResult::LocalNode,
+        };
+        let bucket = &mut self.buckets[index];
+
+        match bucket.iter().position(|e| e.enr.node_id() == id) {
+            Some(position) => {
+                let mut entry = bucket.remove(position);
+                if enr.seq() > entry.enr.seq() {
+                    entry.enr = enr;
+                }
+                entry.last_seen = now;
+                bucket.push(entry);
+                InsertResult::Updated
+            }
+            None if bucket.len() >= K_BUCKET_SIZE => InsertResult::BucketFull,
+            None => {
+                bucket.push(Entry {
+                    enr,
+                    last_seen: now,
+                });
+                InsertResult::Inserted
+            }
+        }
+    }
+
+    /// Removes a node from the table, returning its record if it was present.
+    pub fn remove(&mut self, id: &NodeId) -> Option<Enr> {
+        let index = self.bucket_index(id)?;
+        let bucket = &mut self.buckets[index];
+        let position = bucket.iter().position(|e| e.enr.node_id() == *id)?;
+        Some(bucket.remove(position).enr)
+    }
+
+    pub fn get(&self, id: &NodeId) -> Option<&Enr> {
+        let index = self.bucket_index(id)?;
+        self.buckets[index]
+            .iter()
+            .find(|e| e.enr.node_id() == *id)
+            .map(|e| &e.enr)
+    }
+
+    /// Returns the least-recently seen node in the bucket `id` would be placed in, if any.
+    ///
+    /// When a bucket is full, this is the node which should be checked for liveness before a new
+    /// node is admitted.
+    pub fn least_recently_seen(&self, id: &NodeId) -> Option<(&Enr, Instant)> {
+        let index = self.bucket_index(id)?;
+        self.buckets[index].first().map(|e| (&e.enr, e.last_seen))
+    }
+
+    /// Returns the records of all nodes at exactly `distance` from the local node.
+    pub fn nodes_at_distance(&self, distance: u64) -> Vec<Enr> {
+        if distance == 0 || distance as usize > NUM_BUCKETS {
+            return vec![];
+        }
+        self.buckets[distance as usize - 1]
+            .iter()
+            .map(|e| e.enr.clone())
+            .collect()
+    }
+
+    /// Returns up to `count` records of the nodes closest to `target`, closest first.
+    pub fn closest(&self, target: &NodeId, count: usize) -> Vec<Enr> {
+        let mut nodes: Vec<&Enr> = self.buckets.iter().flatten().map(|e| &e.enr).collect();
+        nodes.sort_by_key(|enr| enr.node_id().xor_distance(target));
+        nodes.into_iter().take(count).cloned().collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Enr> {
+        self.buckets.iter().flatten().map(|e| &e.enr)
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(|b| b.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::bls::Keypair;
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn random_enr() -> Enr {
+        Enr::new(&Keypair::random(), Some(Ipv4Addr::LOCALHOST), None, Some(9000))
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let local = random_enr();
+        let mut table = KBucketsTable::new(local.node_id());
+        let now = Instant::now();
+
+        assert_eq!(table.insert(local.clone(), now), InsertResult::LocalNode);
+        assert!(table.is_empty());
+
+        let enr = random_enr();
+        assert_eq!(table.insert(enr.clone(), now), InsertResult::Inserted);
+        assert_eq!(table.insert(enr.clone(), now), InsertResult::Updated);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(&enr.node_id()), Some(&enr));
+
+        let distance = local.node_id().log2_distance(&enr.node_id()).unwrap();
+        assert_eq!(table.nodes_at_distance(distance), vec![enr.clone()]);
+
+        assert_eq!(table.remove(&enr.node_id()), Some(enr.clone()));
+        assert_eq!(table.get(&enr.node_id()), None);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_bucket_full() {
+        let local = random_enr();
+        let mut table = KBucketsTable::new(local.node_id());
+        let now = Instant::now();
+
+        /*
+         * Half of all random ids fall into the furthest bucket, so it fills quickly.
+         */
+        let mut inserted = 0;
+        while inserted < K_BUCKET_SIZE {
+            let enr = random_enr();
+            if local.node_id().log2_distance(&enr.node_id()) == Some(256) {
+                assert_eq!(table.insert(enr, now), InsertResult::Inserted);
+                inserted += 1;
+            }
+        }
+
+        loop {
+            let enr = random_enr();
+            if local.node_id().log2_distance(&enr.node_id()) == Some(256) {
+                assert_eq!(table.insert(enr, now), InsertResult::BucketFull);
+                break;
+            }
+        }
+        assert_eq!(table.nodes_at_distance(256).len(), K_BUCKET_SIZE);
+    }
+
+    #[test]
+    fn test_closest() {
+        let local = random_enr();
+        let mut table = KBucketsTable::new(local.node_id());
+        let now = Instant::now();
+
+        for _ in 0..20 {
+            table.insert(random_enr(), now);
+        }
+
+        let target = NodeId::random();
+        let closest = table.closest(&target, 5);
+        assert_eq!(closest.len(), 5);
+
+        let furthest_returned = closest[4].node_id().xor_distance(&target);
+        for enr in table.iter() {
+            if !closest.contains(enr) {
+                assert!(enr.node_id().xor_distance(&target) > furthest_returned);
+            }
+        }
+    }
+}
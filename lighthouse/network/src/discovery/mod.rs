@@ -0,0 +1,629 @@
+mod kbucket;
+mod packet;
+mod query;
+mod service;
+
+pub use self::kbucket::{InsertResult, KBucketsTable};
+pub use self::packet::{Message, Packet, PacketError, RequestId};
+pub use self::query::{QueryId, QueryType};
+pub use self::service::DiscoveryService;
+
+use self::packet::MAX_NODES_PER_PACKET;
+use self::query::Query;
+use super::enr::{Enr, NodeId};
+use slog::Logger;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// The maximum number of distances a remote node may request in one `FindNode`.
+const MAX_FIND_NODE_DISTANCES: usize = 4;
+
+#[derive(Clone, Debug)]
+pub struct DiscoveryConfig {
+    /// Records used to join the network when the routing table is empty.
+    pub bootnodes: Vec<Enr>,
+    /// How often a random-target query is started to find new peers.
+    pub query_interval: Duration,
+    /// How long to wait for a response before considering a request failed.
+    pub request_timeout: Duration,
+    /// The maximum duration of a single query.
+    pub query_timeout: Duration,
+    /// The maximum number of queries which may run concurrently.
+    pub max_concurrent_queries: usize,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            bootnodes: vec![],
+            query_interval: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(1),
+            query_timeout: Duration::from_secs(30),
+            max_concurrent_queries: 2,
+        }
+    }
+}
+
+/// Events emitted by `Discovery` for consumption by the peer dialer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiscoveryEvent {
+    /// Dialable peers found by a query, filtered by the query's `QueryType`.
+    PeersDiscovered {
+        query_type: QueryType,
+        peers: Vec<Enr>,
+    },
+    /// A query has finished, having reported `found` matching peers in total.
+    QueryComplete { query_type: QueryType, found: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RequestKind {
+    Ping,
+    /// A `FindNode` request issued on behalf of a query.
+    FindNode(Option<QueryId>),
+}
+
+struct ActiveRequest {
+    node_id: NodeId,
+    kind: RequestKind,
+    sent: Instant,
+    /// The number of `Nodes` packets received so far.
+    responses: u64,
+}
+
+/// A discv5-style node discovery protocol.
+///
+/// `Discovery` is a pure state machine: packets are supplied via `on_packet`, outbound packets are
+/// collected via `next_outbound` and time only advances when `poll` is called. This allows the
+/// protocol to be tested deterministically, with `DiscoveryService` providing the UDP runtime.
+pub struct Discovery {
+    local_enr: Enr,
+    table: KBucketsTable,
+    config: DiscoveryConfig,
+    active_requests: HashMap<RequestId, ActiveRequest>,
+    queries: HashMap<QueryId, Query>,
+    /// Subnets for which a query has been requested but could not yet be started.
+    pending_queries: VecDeque<QueryType>,
+    next_request_id: RequestId,
+    next_query_id: QueryId,
+    last_random_query: Option<Instant>,
+    outbound: VecDeque<(SocketAddr, Packet)>,
+    events: VecDeque<DiscoveryEvent>,
+    log: Logger,
+}
+
+impl Discovery {
+    pub fn new(local_enr: Enr, config: DiscoveryConfig, log: Logger) -> Self {
+        let table = KBucketsTable::new(local_enr.node_id());
+        let mut discovery = Self {
+            local_enr,
+            table,
+            config,
+            active_requests: HashMap::new(),
+            queries: HashMap::new(),
+            pending_queries: VecDeque::new(),
+            next_request_id: 0,
+            next_query_id: 0,
+            last_random_query: None,
+            outbound: VecDeque::new(),
+            events: VecDeque::new(),
+            log,
+        };
+
+        let bootnodes = discovery.config.bootnodes.clone();
+        for enr in bootnodes {
+            discovery.add_enr(enr, Instant::now());
+        }
+        discovery
+    }
+
+    pub fn local_enr(&self) -> &Enr {
+        &self.local_enr
+    }
+
+    /// Replaces the local record, e.g. after its address or subnet subscriptions have changed.
+    ///
+    /// Remote nodes learn of the new record from the `enr_seq` of our subsequent pings and pongs.
+    pub fn set_local_enr(&mut self, enr: Enr) {
+        self.local_enr = enr;
+    }
+
+    pub fn table(&self) -> &KBucketsTable {
+        &self.table
+    }
+
+    /// Adds a record to the routing table (e.g., a bootnode) and pings it.
+    ///
+    /// Records with an invalid signature are ignored.
+    pub fn add_enr(&mut self, enr: Enr, now: Instant) {
+        if !enr.verify() {
+            warn!(self.log, "Ignoring ENR with invalid signature"; "node_id" => format!("{:?}", enr.node_id()));
+            return;
+        }
+        if self.table.insert(enr.clone(), now) == InsertResult::Inserted {
+            debug!(self.log, "Added node to routing table"; "node_id" => format!("{:?}", enr.node_id()));
+            self.send_request(&enr, RequestKind::Ping, now);
+        }
+    }
+
+    /// Starts a query for any dialable peers, targeting a random id.
+    pub fn discover_peers(&mut self) {
+        self.pending_queries.push_back(QueryType::FindPeers);
+    }
+
+    /// Starts a query for dialable peers subscribed to the given attestation subnet.
+    pub fn discover_subnet_peers(&mut self, subnet_id: u64) {
+        let query_type = QueryType::Subnet(subnet_id);
+        let already_queued = self.pending_queries.contains(&query_type)
+            || self.queries.values().any(|q| q.query_type == query_type);
+        if !already_queued {
+            self.pending_queries.push_back(query_type);
+        }
+    }
+
+    /// Returns the next packet to be sent, if any.
+    pub fn next_outbound(&mut self) -> Option<(SocketAddr, Packet)> {
+        self.outbound.pop_front()
+    }
+
+    /// Advances timers (request timeouts, query timeouts and periodic queries) and returns the
+    /// next event, if any.
+    pub fn poll(&mut self, now: Instant) -> Option<DiscoveryEvent> {
+        self.expire_requests(now);
+
+        let due = match self.last_random_query {
+            Some(last) => now >= last + self.config.query_interval,
+            None => true,
+        };
+        if due && !self.table.is_empty() {
+            self.last_random_query = Some(now);
+            self.discover_peers();
+        }
+
+        while self.queries.len() < self.config.max_concurrent_queries {
+            match self.pending_queries.pop_front() {
+                Some(query_type) => self.start_query(query_type, now),
+                None => break,
+            }
+        }
+
+        self.progress_queries(now);
+        self.events.pop_front()
+    }
+
+    /// Processes a packet received from `src`.
+    pub fn on_packet(&mut self, src: SocketAddr, packet: Packet, now: Instant) {
+        let node_id = packet.src_id;
+        match packet.message {
+            Message::Ping { id, enr_seq } => {
+                let (observed_ip, observed_port) = match src {
+                    SocketAddr::V4(addr) => (*addr.ip(), addr.port()),
+                    SocketAddr::V6(_) => return,
+                };
+                self.send_packet(
+                    src,
+                    Message::Pong {
+                        id,
+                        enr_seq: self.local_enr.seq(),
+                        observed_ip,
+                        observed_port,
+                    },
+                );
+                self.check_enr_seq(&node_id, src, enr_seq, now);
+            }
+            Message::FindNode { id, distances } => {
+                self.on_find_node(src, id, &distances);
+            }
+            Message::Pong { id, enr_seq, .. } => {
+                if self.take_request(id, &node_id, now).is_some() {
+                    self.check_enr_seq(&node_id, src, enr_seq, now);
+                }
+            }
+            Message::Nodes { id, total, enrs } => {
+                self.on_nodes(id, node_id, total, enrs, now);
+            }
+        }
+    }
+
+    fn on_find_node(&mut self, src: SocketAddr, id: RequestId, distances: &[u64]) {
+        let mut enrs = vec![];
+        for distance in distances.iter().take(MAX_FIND_NODE_DISTANCES) {
+            if *distance == 0 {
+                enrs.push(self.local_enr.clone());
+            } else {
+                enrs.append(&mut self.table.nodes_at_distance(*distance));
+            }
+        }
+
+        /*
+         * Always send at least one packet so the requester knows the response is complete.
+         */
+        let chunks: Vec<Vec<Enr>> = if enrs.is_empty() {
+            vec![vec![]]
+        } else {
+            enrs.chunks(MAX_NODES_PER_PACKET)
+                .map(|chunk| chunk.to_vec())
+                .collect()
+        };
+        let total = chunks.len() as u64;
+        for enrs in chunks {
+            self.send_packet(src, Message::Nodes { id, total, enrs });
+        }
+    }
+
+    fn on_nodes(&mut self, id: RequestId, node_id: NodeId, total: u64, enrs: Vec<Enr>, now: Instant) {
+        let kind = match self.active_requests.get_mut(&id) {
+            Some(ref mut request) if request.node_id == node_id => {
+                request.responses += 1;
+                request.kind
+            }
+            _ => {
+                debug!(self.log, "Unsolicited Nodes response"; "node_id" => format!("{:?}", node_id));
+                return;
+            }
+        };
+        let finished = self.active_requests[&id].responses >= total;
+        if finished {
+            self.take_request(id, &node_id, now);
+        }
+
+        let valid: Vec<Enr> = enrs.into_iter().filter(|enr| enr.verify()).collect();
+        for enr in &valid {
+            if enr.node_id() != self.local_enr.node_id() {
+                self.table.insert(enr.clone(), now);
+            }
+        }
+
+        if let RequestKind::FindNode(Some(query_id)) = kind {
+            if let Some(query) = self.queries.get_mut(&query_id) {
+                let local_id = self.local_enr.node_id();
+                let peers: Vec<Enr> = query
+                    .add_nodes(valid)
+                    .into_iter()
+                    .filter(|enr| enr.node_id() != local_id)
+                    .collect();
+                if !peers.is_empty() {
+                    self.events.push_back(DiscoveryEvent::PeersDiscovered {
+                        query_type: query.query_type,
+                        peers,
+                    });
+                }
+                if finished {
+                    query.on_request_complete(&node_id);
+                }
+            }
+        }
+    }
+
+    /// Requests the record of a node if it is unknown or advertises a newer `enr_seq` than the
+    /// record we hold.
+    fn check_enr_seq(&mut self, node_id: &NodeId, src: SocketAddr, enr_seq: u64, now: Instant) {
+        let outdated = match self.table.get(node_id) {
+            Some(enr) => enr.seq() < enr_seq,
+            None => true,
+        };
+        if outdated {
+            let id = self.new_request(*node_id, RequestKind::FindNode(None), now);
+            self.send_packet(
+                src,
+                Message::FindNode {
+                    id,
+                    distances: vec![0],
+                },
+            );
+        }
+    }
+
+    fn start_query(&mut self, query_type: QueryType, now: Instant) {
+        let target = match query_type {
+            QueryType::FindPeers => NodeId::random(),
+            // Subnet subscriptions are unrelated to node ids, so any region of the id space is as
+            // good as any other.
+            QueryType::Subnet(_) => NodeId::random(),
+        };
+        let mut query = Query::new(query_type, target, now);
+
+        /*
+         * Nodes already in the routing table which match the query are reported immediately.
+         */
+        let seeds = self.table.closest(&target, query::QUERY_RESULT_SIZE);
+        let matching = query.add_nodes(seeds);
+        if !matching.is_empty() {
+            self.events.push_back(DiscoveryEvent::PeersDiscovered {
+                query_type,
+                peers: matching,
+            });
+        }
+
+        let query_id = self.next_query_id;
+        self.next_query_id += 1;
+        debug!(self.log, "Starting discovery query"; "query_type" => format!("{:?}", query_type));
+        self.queries.insert(query_id, query);
+    }
+
+    fn progress_queries(&mut self, now: Instant) {
+        let mut finished = vec![];
+        let mut requests = vec![];
+        for (query_id, query) in self.queries.iter_mut() {
+            if query.is_finished() || now >= query.started + self.config.query_timeout {
+                finished.push(*query_id);
+            } else {
+                for enr in query.next_peers() {
+                    requests.push((*query_id, enr, query.target));
+                }
+            }
+        }
+
+        for (query_id, enr, target) in requests {
+            /*
+             * Request the bucket the target would occupy in the remote routing table, plus its
+             * neighbours, which are the nodes closest to the target known to that node.
+             */
+            let distance = enr.node_id().log2_distance(&target).unwrap_or(1);
+            let mut distances = vec![distance];
+            if distance < 256 {
+                distances.push(distance + 1);
+            }
+            if distance > 1 {
+                distances.push(distance - 1);
+            }
+            let kind = RequestKind::FindNode(Some(query_id));
+            if !self.send_find_node(&enr, kind, distances, now) {
+                if let Some(query) = self.queries.get_mut(&query_id) {
+                    query.on_request_complete(&enr.node_id());
+                }
+            }
+        }
+
+        for query_id in finished {
+            if let Some(query) = self.queries.remove(&query_id) {
+                debug!(self.log, "Discovery query complete";
+                       "query_type" => format!("{:?}", query.query_type),
+                       "found" => query.reported_count());
+                self.events.push_back(DiscoveryEvent::QueryComplete {
+                    query_type: query.query_type,
+                    found: query.reported_count(),
+                });
+            }
+        }
+    }
+
+    /// Fails any requests which have not been responded to within the timeout. Nodes which fail
+    /// to respond are removed from the routing table.
+    fn expire_requests(&mut self, now: Instant) {
+        let timeout = self.config.request_timeout;
+        let expired: Vec<RequestId> = self
+            .active_requests
+            .iter()
+            .filter(|(_, request)| now >= request.sent + timeout)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            if let Some(request) = self.active_requests.remove(&id) {
+                debug!(self.log, "Discovery request timed out"; "node_id" => format!("{:?}", request.node_id));
+                self.table.remove(&request.node_id);
+                if let RequestKind::FindNode(Some(query_id)) = request.kind {
+                    if let Some(query) = self.queries.get_mut(&query_id) {
+                        query.on_request_complete(&request.node_id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes and returns a completed request, provided it was sent to `node_id`. The node is
+    /// marked as seen in the routing table.
+    fn take_request(&mut self, id: RequestId, node_id: &NodeId, now: Instant) -> Option<ActiveRequest> {
+        let recipient = self.active_requests.get(&id).map(|request| request.node_id);
+        if recipient != Some(*node_id) {
+            return None;
+        }
+        if let Some(enr) = self.table.get(node_id).cloned() {
+            self.table.insert(enr, now);
+        }
+        self.active_requests.remove(&id)
+    }
+
+    fn send_find_node(&mut self, enr: &Enr, kind: RequestKind, distances: Vec<u64>, now: Instant) -> bool {
+        match enr.udp_socket() {
+            Some(addr) => {
+                let id = self.new_request(enr.node_id(), kind, now);
+                self.send_packet(addr, Message::FindNode { id, distances });
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn send_request(&mut self, enr: &Enr, kind: RequestKind, now: Instant) {
+        let addr = match enr.udp_socket() {
+            Some(addr) => addr,
+            None => return,
+        };
+        let id = self.new_request(enr.node_id(), kind, now);
+        let message = match kind {
+            RequestKind::Ping => Message::Ping {
+                id,
+                enr_seq: self.local_enr.seq(),
+            },
+            RequestKind::FindNode(_) => Message::FindNode {
+                id,
+                distances: vec![0],
+            },
+        };
+        self.send_packet(addr, message);
+    }
+
+    fn new_request(&mut self, node_id: NodeId, kind: RequestKind, now: Instant) -> RequestId {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        self.active_requests.insert(
+            id,
+            ActiveRequest {
+                node_id,
+                kind,
+                sent: now,
+                responses: 0,
+            },
+        );
+        id
+    }
+
+    fn send_packet(&mut self, dst: SocketAddr, message: Message) {
+        let packet = Packet {
+            src_id: self.local_enr.node_id(),
+            message,
+        };
+        self.outbound.push_back((dst, packet));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bls::Keypair;
+    use super::*;
+    use slog::Discard;
+    use std::net::Ipv4Addr;
+
+    fn node(port: u16, bootnodes: Vec<Enr>) -> Discovery {
+        let keypair = Keypair::random();
+        let enr = Enr::new(&keypair, Some(Ipv4Addr::LOCALHOST), Some(port), Some(port));
+        let config = DiscoveryConfig {
+            bootnodes,
+            ..DiscoveryConfig::default()
+        };
+        Discovery::new(enr, config, Logger::root(Discard, o!()))
+    }
+
+    /// Delivers all outbound packets between the given nodes until there are none left.
+    fn run(nodes: &mut [Discovery], now: Instant) -> Vec<DiscoveryEvent> {
+        let mut events = vec![];
+        loop {
+            let mut packets = vec![];
+            for (i, node) in nodes.iter_mut().enumerate() {
+                while let Some(event) = node.poll(now) {
+                    events.push(event);
+                }
+                while let Some((dst, packet)) = node.next_outbound() {
+                    let src = node.local_enr().udp_socket().unwrap();
+                    packets.push((i, src, dst, packet));
+                }
+            }
+            if packets.is_empty() {
+                break;
+            }
+            for (_, src, dst, packet) in packets {
+                if let Some(dst_node) = nodes
+                    .iter_mut()
+                    .find(|n| n.local_enr().udp_socket() == Some(dst))
+                {
+                    dst_node.on_packet(src, packet, now);
+                }
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_bootnode_is_pinged() {
+        let bootnode = node(9000, vec![]);
+        let mut joiner = node(9001, vec![bootnode.local_enr().clone()]);
+
+        assert_eq!(joiner.table().len(), 1);
+        let (dst, packet) = joiner.next_outbound().unwrap();
+        assert_eq!(Some(dst), bootnode.local_enr().udp_socket());
+        match packet.message {
+            Message::Ping { enr_seq, .. } => assert_eq!(enr_seq, 1),
+            other => panic!("expected ping, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_discovers_peers_via_bootnode() {
+        let bootnode = node(9000, vec![]);
+        let bootnode_enr = bootnode.local_enr().clone();
+        let mut nodes = vec![bootnode];
+        for port in 9001..9006 {
+            nodes.push(node(port, vec![bootnode_enr.clone()]));
+        }
+
+        let now = Instant::now();
+        run(&mut nodes, now);
+
+        /*
+         * Every node pinged the bootnode, which learns of them by requesting their records in
+         * response. The next round of queries finds the other joiners via the bootnode.
+         */
+        assert_eq!(nodes[0].table().len(), 5);
+        let events = run(&mut nodes, now + Duration::from_secs(60));
+
+        for node in &nodes[1..] {
+            assert!(node.table().get(&bootnode_enr.node_id()).is_some());
+        }
+        assert!(nodes[1..].iter().any(|node| node.table().len() > 1));
+
+        let discovered_joiner = events.iter().any(|event| match event {
+            DiscoveryEvent::PeersDiscovered { peers, .. } => {
+                peers.iter().any(|enr| enr.node_id() != bootnode_enr.node_id())
+            }
+            _ => false,
+        });
+        assert!(discovered_joiner);
+    }
+
+    #[test]
+    fn test_find_node_distance_zero_returns_local_enr() {
+        let mut a = node(9000, vec![]);
+        let b = node(9001, vec![]);
+        let src = b.local_enr().udp_socket().unwrap();
+
+        a.on_packet(
+            src,
+            Packet {
+                src_id: b.local_enr().node_id(),
+                message: Message::FindNode {
+                    id: 7,
+                    distances: vec![0],
+                },
+            },
+            Instant::now(),
+        );
+
+        let (dst, packet) = a.next_outbound().unwrap();
+        assert_eq!(dst, src);
+        assert_eq!(
+            packet.message,
+            Message::Nodes {
+                id: 7,
+                total: 1,
+                enrs: vec![a.local_enr().clone()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_unresponsive_node_is_removed() {
+        let bootnode = node(9000, vec![]);
+        let mut joiner = node(9001, vec![bootnode.local_enr().clone()]);
+        let now = Instant::now();
+
+        // Drop the ping instead of delivering it.
+        while joiner.next_outbound().is_some() {}
+        assert_eq!(joiner.table().len(), 1);
+
+        joiner.poll(now + Duration::from_secs(5));
+        assert!(joiner.table().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_enr_is_ignored() {
+        let mut a = node(9000, vec![]);
+        let mut enr = node(9001, vec![]).local_enr().clone();
+        enr.sign(&Keypair::random());
+
+        a.add_enr(enr, Instant::now());
+        assert!(a.table().is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use super::super::enr::{Enr, NodeId};
+use super::super::ssz::{Decodable, DecodeError, Encodable, SszStream};
+use std::net::Ipv4Addr;
+
+/// The maximum size of a discovery packet, chosen to fit within the minimum IPv6 MTU.
+pub const MAX_PACKET_SIZE: usize = 1_280;
+/// The maximum number of records sent in a single `Nodes` packet, keeping it under
+/// `MAX_PACKET_SIZE`.
+pub const MAX_NODES_PER_PACKET: usize = 4;
+
+pub type RequestId = u64;
+
+const PING: u8 = 0;
+const PONG: u8 = 1;
+const FIND_NODE: u8 = 2;
+const NODES: u8 = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum PacketError {
+    TooLarge,
+    UnknownMessageType(u8),
+    TrailingBytes,
+    InvalidSsz(DecodeError),
+}
+
+impl From<DecodeError> for PacketError {
+    fn from(e: DecodeError) -> Self {
+        PacketError::InvalidSsz(e)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// Checks liveness of a node and advertises the sender's current `Enr` sequence number.
+    Ping { id: RequestId, enr_seq: u64 },
+    /// Response to `Ping`, informing the sender how its address was observed.
+    Pong {
+        id: RequestId,
+        enr_seq: u64,
+        observed_ip: Ipv4Addr,
+        observed_port: u16,
+    },
+    /// Requests the records of all nodes at the given distances from the recipient. A distance
+    /// of `0` requests the recipient's own record.
+    FindNode { id: RequestId, distances: Vec<u64> },
+    /// Response to `FindNode`. Large responses are split across `total` packets.
+    Nodes {
+        id: RequestId,
+        total: u64,
+        enrs: Vec<Enr>,
+    },
+}
+
+impl Message {
+    pub fn id(&self) -> RequestId {
+        match self {
+            Message::Ping { id, .. }
+            | Message::Pong { id, .. }
+            | Message::FindNode { id, .. }
+            | Message::Nodes { id, .. } => *id,
+        }
+    }
+
+    pub fn is_request(&self) -> bool {
+        match self {
+            Message::Ping { .. } | Message::FindNode { .. } => true,
+            Message::Pong { .. } | Message::Nodes { .. } => false,
+        }
+    }
+}
+
+/// A discovery message and the id of the node which sent it.
+///
+/// Packets are currently unauthenticated; a node may only harm itself by lying about `src_id` as
+/// any records it relays are verified against their own signatures.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet {
+    pub src_id: NodeId,
+    pub message: Message,
+}
+
+impl Packet {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut s = SszStream::new();
+        s.append(self);
+        s.drain()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PacketError> {
+        if bytes.len() > MAX_PACKET_SIZE {
+            return Err(PacketError::TooLarge);
+        }
+        let (packet, i) = Packet::decode(bytes)?;
+        if i != bytes.len() {
+            return Err(PacketError::TrailingBytes);
+        }
+        Ok(packet)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<(Self, usize), PacketError> {
+        let (src_id, i) = NodeId::ssz_decode(bytes, 0)?;
+        let (message_type, i) = u8::ssz_decode(bytes, i)?;
+        let (id, i) = u64::ssz_decode(bytes, i)?;
+
+        let (message, i) = match message_type {
+            PING => {
+                let (enr_seq, i) = u64::ssz_decode(bytes, i)?;
+                (Message::Ping { id, enr_seq }, i)
+            }
+            PONG => {
+                let (enr_seq, i) = u64::ssz_decode(bytes, i)?;
+                let (observed_ip, i) = u32::ssz_decode(bytes, i)?;
+                let (observed_port, i) = u16::ssz_decode(bytes, i)?;
+                let message = Message::Pong {
+                    id,
+                    enr_seq,
+                    observed_ip: Ipv4Addr::from(observed_ip),
+                    observed_port,
+                };
+                (message, i)
+            }
+            FIND_NODE => {
+                let (distances, i) = Decodable::ssz_decode(bytes, i)?;
+                (Message::FindNode { id, distances }, i)
+            }
+            NODES => {
+                let (total, i) = u64::ssz_decode(bytes, i)?;
+                let (enrs, i) = Decodable::ssz_decode(bytes, i)?;
+                (Message::Nodes { id, total, enrs }, i)
+            }
+            other => return Err(PacketError::UnknownMessageType(other)),
+        };
+
+        Ok((Packet { src_id, message }, i))
+    }
+}
+
+impl Encodable for Packet {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.src_id);
+        match &self.message {
+            Message::Ping { id, enr_seq } => {
+                s.append(&PING).append(id).append(enr_seq);
+            }
+            Message::Pong {
+                id,
+                enr_seq,
+                observed_ip,
+                observed_port,
+            } => {
+                s.append(&PONG)
+                    .append(id)
+                    .append(enr_seq)
+                    .append(&u32::from(*observed_ip))
+                    .append(observed_port);
+            }
+            Message::FindNode { id, distances } => {
+                s.append(&FIND_NODE).append(id);
+                s.append_vec(distances);
+            }
+            Message::Nodes { id, total, enrs } => {
+                s.append(&NODES).append(id).append(total);
+                s.append_vec(enrs);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::bls::Keypair;
+    use super::*;
+
+    fn round_trip(message: Message) {
+        let packet = Packet {
+            src_id: NodeId::random(),
+            message,
+        };
+        let bytes = packet.to_bytes();
+        assert!(bytes.len() <= MAX_PACKET_SIZE);
+        assert_eq!(Packet::from_bytes(&bytes), Ok(packet));
+    }
+
+    #[test]
+    fn test_packet_round_trip() {
+        round_trip(Message::Ping { id: 1, enr_seq: 42 });
+        round_trip(Message::Pong {
+            id: 2,
+            enr_seq: 42,
+            observed_ip: Ipv4Addr::new(1, 2, 3, 4),
+            observed_port: 9000,
+        });
+        round_trip(Message::FindNode {
+            id: 3,
+            distances: vec![0, 255, 256],
+        });
+
+        let enrs = (0..MAX_NODES_PER_PACKET)
+            .map(|_| {
+                Enr::new(
+                    &Keypair::random(),
+                    Some(Ipv4Addr::LOCALHOST),
+                    Some(9000),
+                    Some(9000),
+                )
+            }).collect();
+        round_trip(Message::Nodes {
+            id: 4,
+            total: 2,
+            enrs,
+        });
+    }
+
+    #[test]
+    fn test_packet_errors() {
+        let packet = Packet {
+            src_id: NodeId::random(),
+            message: Message::Ping { id: 1, enr_seq: 1 },
+        };
+        let mut bytes = packet.to_bytes();
+
+        bytes.push(0);
+        assert_eq!(Packet::from_bytes(&bytes), Err(PacketError::TrailingBytes));
+
+        bytes.truncate(bytes.len() - 2);
+        assert!(Packet::from_bytes(&bytes).is_err());
+
+        bytes[32] = 42;
+        assert_eq!(
+            Packet::from_bytes(&bytes),
+            Err(PacketError::UnknownMessageType(42))
+        );
+
+        assert_eq!(
+            Packet::from_bytes(&vec![0; MAX_PACKET_SIZE + 1]),
+            Err(PacketError::TooLarge)
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use super::super::enr::{Enr, NodeId};
+use std::collections::HashSet;
+use std::time::Instant;
+
+pub type QueryId = u64;
+
+/// The number of nodes queried in parallel by a single query.
+pub const ALPHA: usize = 3;
+/// The number of closest nodes a query must have queried before it terminates.
+pub const QUERY_RESULT_SIZE: usize = 16;
+
+/// Determines which discovered nodes are of interest to the caller of a query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QueryType {
+    /// Any node which can be dialed.
+    FindPeers,
+    /// A node which can be dialed and advertises a subscription to the given attestation subnet.
+    Subnet(u64),
+}
+
+impl QueryType {
+    pub fn matches(&self, enr: &Enr) -> bool {
+        if enr.tcp_socket().is_none() {
+            return false;
+        }
+        match self {
+            QueryType::FindPeers => true,
+            QueryType::Subnet(subnet_id) => enr.is_subscribed_to_subnet(*subnet_id),
+        }
+    }
+}
+
+/// An iterative Kademlia lookup, progressively querying the nodes closest to `target`.
+pub struct Query {
+    pub query_type: QueryType,
+    pub target: NodeId,
+    pub started: Instant,
+    /// All nodes learned of during the query, closest to `target` first.
+    closest: Vec<Enr>,
+    /// Nodes which have been sent a `FindNode` request.
+    queried: HashSet<NodeId>,
+    /// Nodes which have been sent a request which is yet to complete.
+    in_flight: HashSet<NodeId>,
+    /// Matching nodes which have already been returned to the caller.
+    reported: HashSet<NodeId>,
+}
+
+impl Query {
+    pub fn new(query_type: QueryType, target: NodeId, started: Instant) -> Self {
+        Self {
+            query_type,
+            target,
+            started,
+            closest: vec![],
+            queried: HashSet::new(),
+            in_flight: HashSet::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Adds nodes to the query, returning those which match the `QueryType` and have not been
+    /// returned previously.
+    pub fn add_nodes(&mut self, enrs: Vec<Enr>) -> Vec<Enr> {
+        let mut matching = vec![];
+        for enr in enrs {
+            let id = enr.node_id();
+            if self.query_type.matches(&enr) && self.reported.insert(id) {
+                matching.push(enr.clone());
+            }
+            if !self.closest.iter().any(|e| e.node_id() == id) {
+                self.closest.push(enr);
+            }
+        }
+        let target = self.target;
+        self.closest
+            .sort_by_key(|enr| enr.node_id().xor_distance(&target));
+        matching
+    }
+
+    /// Returns the next nodes to send `FindNode` requests to, marking them as in-flight.
+    pub fn next_peers(&mut self) -> Vec<Enr> {
+        let mut peers = vec![];
+        for enr in self.closest.iter().take(QUERY_RESULT_SIZE) {
+            if self.in_flight.len() + peers.len() >= ALPHA {
+                break;
+            }
+            if !self.queried.contains(&enr.node_id()) {
+                peers.push(enr.clone());
+            }
+        }
+        for enr in &peers {
+            self.queried.insert(enr.node_id());
+            self.in_flight.insert(enr.node_id());
+        }
+        peers
+    }
+
+    /// Marks a request to `id` as complete, whether it succeeded or not.
+    pub fn on_request_complete(&mut self, id: &NodeId) {
+        self.in_flight.remove(id);
+    }
+
+    /// Returns `true` once each of the closest known nodes has been queried and there are no
+    /// requests remaining in-flight.
+    pub fn is_finished(&self) -> bool {
+        self.in_flight.is_empty()
+            && self
+                .closest
+                .iter()
+                .take(QUERY_RESULT_SIZE)
+                .all(|enr| self.queried.contains(&enr.node_id()))
+    }
+
+    pub fn reported_count(&self) -> usize {
+        self.reported.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::bls::Keypair;
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn dialable_enr() -> Enr {
+        Enr::new(
+            &Keypair::random(),
+            Some(Ipv4Addr::LOCALHOST),
+            Some(9000),
+            Some(9000),
+        )
+    }
+
+    #[test]
+    fn test_query_alpha_and_termination() {
+        let seeds: Vec<Enr> = (0..5).map(|_| dialable_enr()).collect();
+        let mut query = Query::new(QueryType::FindPeers, NodeId::random(), Instant::now());
+
+        assert_eq!(query.add_nodes(seeds).len(), 5);
+        assert_eq!(query.reported_count(), 5);
+        assert!(!query.is_finished());
+
+        let first = query.next_peers();
+        assert_eq!(first.len(), ALPHA);
+        assert_eq!(query.next_peers().len(), 0);
+
+        for enr in &first {
+            query.on_request_complete(&enr.node_id());
+        }
+        let second = query.next_peers();
+        assert_eq!(second.len(), 2);
+        for enr in &second {
+            query.on_request_complete(&enr.node_id());
+        }
+        assert!(query.is_finished());
+    }
+
+    #[test]
+    fn test_query_reports_each_node_once() {
+        let enr = dialable_enr();
+        let mut query = Query::new(QueryType::FindPeers, NodeId::random(), Instant::now());
+
+        assert_eq!(query.add_nodes(vec![enr.clone()]), vec![enr.clone()]);
+        assert_eq!(query.add_nodes(vec![enr.clone()]), vec![]);
+    }
+
+    #[test]
+    fn test_query_type_filtering() {
+        let keypair = Keypair::random();
+        let undialable = Enr::new(&keypair, Some(Ipv4Addr::LOCALHOST), None, Some(9000));
+        let dialable = dialable_enr();
+
+        assert!(!QueryType::FindPeers.matches(&undialable));
+        assert!(QueryType::FindPeers.matches(&dialable));
+        assert!(!QueryType::Subnet(3).matches(&dialable));
+    }
+}
@@ -0,0 +1,199 @@
+use super::super::enr::Enr;
+use super::packet::MAX_PACKET_SIZE;
+use super::{Discovery, DiscoveryConfig, DiscoveryEvent, Packet};
+use slog::Logger;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use task_executor::TaskExecutor;
+
+/// How long the service blocks waiting for a packet before servicing timers and commands.
+const SOCKET_READ_TIMEOUT: Duration = Duration::from_millis(50);
+
+enum Command {
+    AddEnr(Enr),
+    DiscoverPeers,
+    DiscoverSubnetPeers(u64),
+    SetLocalEnr(Enr),
+    Shutdown,
+}
+
+/// Runs `Discovery` on a background thread, exchanging packets over a UDP socket.
+///
+/// Discovered peers are delivered on the receiver returned by `DiscoveryService::start`, to be
+/// consumed by whichever component dials peers.
+pub struct DiscoveryService {
+    commands: Sender<Command>,
+    local_addr: SocketAddr,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DiscoveryService {
+    /// Binds a UDP socket to `listen_addr` and starts the discovery thread.
+    pub fn start(
+        listen_addr: SocketAddr,
+        local_enr: Enr,
+        config: DiscoveryConfig,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> io::Result<(Self, Receiver<DiscoveryEvent>)> {
+        let socket = UdpSocket::bind(listen_addr)?;
+        socket.set_read_timeout(Some(SOCKET_READ_TIMEOUT))?;
+        let local_addr = socket.local_addr()?;
+
+        let (command_tx, command_rx) = channel();
+        let (event_tx, event_rx) = channel();
+
+        info!(log, "Starting discovery"; "listen_addr" => format!("{}", local_addr));
+        let discovery = Discovery::new(local_enr, config, log.clone());
+        let handle = executor.spawn("discovery", move |_| {
+            run(socket, discovery, command_rx, event_tx, log)
+        });
+
+        let service = Self {
+            commands: command_tx,
+            local_addr,
+            handle: Some(handle),
+        };
+        Ok((service, event_rx))
+    }
+
+    /// The address of the UDP socket, useful when binding to port `0`.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn add_enr(&self, enr: Enr) {
+        let _ = self.commands.send(Command::AddEnr(enr));
+    }
+
+    pub fn discover_peers(&self) {
+        let _ = self.commands.send(Command::DiscoverPeers);
+    }
+
+    pub fn discover_subnet_peers(&self, subnet_id: u64) {
+        let _ = self.commands.send(Command::DiscoverSubnetPeers(subnet_id));
+    }
+
+    /// Replaces the advertised record, e.g. after the external address has been discovered.
+    pub fn set_local_enr(&self, enr: Enr) {
+        let _ = self.commands.send(Command::SetLocalEnr(enr));
+    }
+}
+
+impl Drop for DiscoveryService {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Shutdown);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    socket: UdpSocket,
+    mut discovery: Discovery,
+    commands: Receiver<Command>,
+    events: Sender<DiscoveryEvent>,
+    log: Logger,
+) {
+    let mut buf = [0; MAX_PACKET_SIZE];
+    loop {
+        /*
+         * Apply any commands from the service handle.
+         */
+        loop {
+            match commands.try_recv() {
+                Ok(Command::AddEnr(enr)) => discovery.add_enr(enr, Instant::now()),
+                Ok(Command::DiscoverPeers) => discovery.discover_peers(),
+                Ok(Command::DiscoverSubnetPeers(subnet_id)) => {
+                    discovery.discover_subnet_peers(subnet_id)
+                }
+                Ok(Command::SetLocalEnr(enr)) => discovery.set_local_enr(enr),
+                Ok(Command::Shutdown) | Err(TryRecvError::Disconnected) => {
+                    debug!(log, "Discovery shutting down");
+                    return;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        /*
+         * Read a single packet, waiting no longer than the socket read timeout.
+         */
+        match socket.recv_from(&mut buf) {
+            Ok((len, src)) => match Packet::from_bytes(&buf[..len]) {
+                Ok(packet) => discovery.on_packet(src, packet, Instant::now()),
+                Err(e) => {
+                    debug!(log, "Invalid discovery packet"; "src" => format!("{}", src), "error" => format!("{:?}", e))
+                }
+            },
+            Err(ref e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+            }
+            Err(e) => warn!(log, "Discovery socket error"; "error" => format!("{}", e)),
+        }
+
+        while let Some(event) = discovery.poll(Instant::now()) {
+            if events.send(event).is_err() {
+                debug!(log, "Discovery event receiver dropped");
+                return;
+            }
+        }
+
+        while let Some((dst, packet)) = discovery.next_outbound() {
+            if let Err(e) = socket.send_to(&packet.to_bytes(), dst) {
+                debug!(log, "Failed to send discovery packet"; "dst" => format!("{}", dst), "error" => format!("{}", e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::bls::Keypair;
+    use super::*;
+    use slog::Discard;
+    use std::net::Ipv4Addr;
+
+    fn start_service(bootnodes: Vec<Enr>) -> (DiscoveryService, Receiver<DiscoveryEvent>, Enr) {
+        /*
+         * Bind to an ephemeral port first so the record can advertise the real port.
+         */
+        let port = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let keypair = Keypair::random();
+        let enr = Enr::new(&keypair, Some(Ipv4Addr::LOCALHOST), Some(port), Some(port));
+        let config = DiscoveryConfig {
+            bootnodes,
+            ..DiscoveryConfig::default()
+        };
+        let listen_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+        let (service, events) = DiscoveryService::start(
+            listen_addr,
+            enr.clone(),
+            config,
+            &TaskExecutor::new(Logger::root(Discard, o!())).0,
+            Logger::root(Discard, o!()),
+        )
+        .unwrap();
+        (service, events, enr)
+    }
+
+    #[test]
+    fn test_service_discovers_bootnode() {
+        let (_bootnode, _, bootnode_enr) = start_service(vec![]);
+        let (_joiner, events, _) = start_service(vec![bootnode_enr.clone()]);
+
+        let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        match event {
+            DiscoveryEvent::PeersDiscovered { peers, .. } => assert!(peers.contains(&bootnode_enr)),
+            other => panic!("expected peers, got {:?}", other),
+        }
+    }
+}
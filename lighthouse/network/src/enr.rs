@@ -0,0 +1,461 @@
+use super::bls::{Keypair, PublicKey, Signature};
+use super::hashing::canonical_hash;
+use super::rpc::ForkDigest;
+use super::ssz::{decode_ssz_list, ssz_encode, Decodable, DecodeError, Encodable, SszStream};
+use super::types::{Bitfield, Hash256};
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::str::FromStr;
+
+/// The prefix of the textual form of an `Enr`.
+const ENR_PREFIX: &str = "enr:";
+
+/// The number of attestation subnets advertised in the `attnets` field of an `Enr`.
+pub const ATTESTATION_SUBNET_COUNT: usize = 64;
+
+/// Uniquely identifies a node on the network.
+///
+/// The id is the hash of the node's public key, so it cannot be chosen freely by a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub Hash256);
+
+impl NodeId {
+    pub fn from_public_key(public_key: &PublicKey) -> Self {
+        NodeId(Hash256::from(&canonical_hash(&public_key.as_bytes())[..]))
+    }
+
+    /// Generates a random id, useful as the target of a discovery query.
+    pub fn random() -> Self {
+        NodeId(Hash256::random())
+    }
+
+    /// Returns the logarithmic (base 2) distance between `self` and `other`, defined as the index
+    /// of the highest differing bit (counting from 1) in `self XOR other`.
+    ///
+    /// Returns `None` if the two ids are identical.
+    pub fn log2_distance(&self, other: &NodeId) -> Option<u64> {
+        for (i, (a, b)) in self.0.iter().zip(other.0.iter()).enumerate() {
+            let xor = a ^ b;
+            if xor != 0 {
+                let remaining_bytes = (self.0.len() - i - 1) as u64;
+                return Some(remaining_bytes * 8 + 8 - u64::from(xor.leading_zeros()));
+            }
+        }
+        None
+    }
+
+    /// Returns `self XOR other`, used to order nodes by their closeness to some target.
+    pub fn xor_distance(&self, other: &NodeId) -> Hash256 {
+        self.0 ^ other.0
+    }
+}
+
+impl Encodable for NodeId {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.0);
+    }
+}
+
+impl Decodable for NodeId {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (hash, i) = Hash256::ssz_decode(bytes, i)?;
+        Ok((NodeId(hash), i))
+    }
+}
+
+/// An Ethereum Node Record, describing how to contact some node.
+///
+/// The record is signed by the node it describes. Any modification to its contents must be
+/// accompanied by an increment of `seq` and a new signature, so remote nodes can always
+/// determine which of two records is the latest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Enr {
+    seq: u64,
+    public_key: PublicKey,
+    ip: Option<Ipv4Addr>,
+    tcp: Option<u16>,
+    udp: Option<u16>,
+    /// Identifies the network and fork the node is following.
+    fork_digest: ForkDigest,
+    attnets: Bitfield,
+    signature: Signature,
+}
+
+impl Enr {
+    /// Builds and signs a new record with a sequence number of `1`, a zero fork digest and no
+    /// subnet subscriptions.
+    pub fn new(
+        keypair: &Keypair,
+        ip: Option<Ipv4Addr>,
+        tcp: Option<u16>,
+        udp: Option<u16>,
+    ) -> Self {
+        let mut enr = Self {
+            seq: 1,
+            public_key: keypair.pk.clone(),
+            ip,
+            tcp,
+            udp,
+            fork_digest: ForkDigest([0; 4]),
+            attnets: Bitfield::from_elem(ATTESTATION_SUBNET_COUNT, false),
+            signature: Signature::new(&[], &keypair.sk),
+        };
+        enr.sign(keypair);
+        enr
+    }
+
+    pub fn node_id(&self) -> NodeId {
+        NodeId::from_public_key(&self.public_key)
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn ip(&self) -> Option<Ipv4Addr> {
+        self.ip
+    }
+
+    pub fn tcp(&self) -> Option<u16> {
+        self.tcp
+    }
+
+    pub fn udp(&self) -> Option<u16> {
+        self.udp
+    }
+
+    pub fn fork_digest(&self) -> ForkDigest {
+        self.fork_digest
+    }
+
+    pub fn attnets(&self) -> &Bitfield {
+        &self.attnets
+    }
+
+    /// Returns the address on which the node accepts discovery packets, if it advertises one.
+    pub fn udp_socket(&self) -> Option<SocketAddr> {
+        match (self.ip, self.udp) {
+            (Some(ip), Some(port)) => Some(SocketAddr::V4(SocketAddrV4::new(ip, port))),
+            _ => None,
+        }
+    }
+
+    /// Returns the address on which the node accepts peer connections, if it advertises one.
+    pub fn tcp_socket(&self) -> Option<SocketAddr> {
+        match (self.ip, self.tcp) {
+            (Some(ip), Some(port)) => Some(SocketAddr::V4(SocketAddrV4::new(ip, port))),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the node advertises a subscription to the given attestation subnet.
+    pub fn is_subscribed_to_subnet(&self, subnet_id: u64) -> bool {
+        self.attnets.get(subnet_id as usize).unwrap_or(false)
+    }
+
+    /// Returns `true` if the signature on this record was produced by its `public_key`.
+    pub fn verify(&self) -> bool {
+        self.signature
+            .verify(&self.signing_root(), &self.public_key)
+    }
+
+    /*
+     * Each of the setters below increments `seq` and re-signs the record if, and only if, the
+     * record was changed. They return `true` if the record was changed.
+     */
+
+    pub fn set_ip(&mut self, ip: Option<Ipv4Addr>, keypair: &Keypair) -> bool {
+        self.update(keypair, |enr| enr.ip = ip)
+    }
+
+    pub fn set_tcp(&mut self, tcp: Option<u16>, keypair: &Keypair) -> bool {
+        self.update(keypair, |enr| enr.tcp = tcp)
+    }
+
+    pub fn set_udp(&mut self, udp: Option<u16>, keypair: &Keypair) -> bool {
+        self.update(keypair, |enr| enr.udp = udp)
+    }
+
+    pub fn set_fork_digest(&mut self, fork_digest: ForkDigest, keypair: &Keypair) -> bool {
+        self.update(keypair, |enr| enr.fork_digest = fork_digest)
+    }
+
+    pub fn set_attnets(&mut self, attnets: Bitfield, keypair: &Keypair) -> bool {
+        self.update(keypair, |enr| enr.attnets = attnets)
+    }
+
+    /// Sets whether the node advertises a subscription to the given attestation subnet.
+    pub fn set_subnet(&mut self, subnet_id: u64, subscribed: bool, keypair: &Keypair) -> bool {
+        if subnet_id >= ATTESTATION_SUBNET_COUNT as u64 {
+            return false;
+        }
+        self.update(keypair, |enr| {
+            enr.attnets.set(subnet_id as usize, subscribed);
+        })
+    }
+
+    /// Applies `f` to the record, incrementing `seq` and re-signing if the record changed.
+    fn update<F: FnOnce(&mut Enr)>(&mut self, keypair: &Keypair, f: F) -> bool {
+        let mut updated = self.clone();
+        f(&mut updated);
+        if updated == *self {
+            return false;
+        }
+        updated.seq += 1;
+        updated.sign(keypair);
+        *self = updated;
+        true
+    }
+
+    /// Signs the record with the given `keypair`.
+    ///
+    /// The `keypair` must match the `public_key` of the record, otherwise the resulting record
+    /// will fail verification.
+    pub(crate) fn sign(&mut self, keypair: &Keypair) {
+        self.signature = Signature::new(&self.signing_root(), &keypair.sk);
+    }
+
+    /// Returns the hash of the record contents, excluding the signature.
+    fn signing_root(&self) -> Vec<u8> {
+        let mut s = SszStream::new();
+        self.ssz_append_content(&mut s);
+        canonical_hash(&s.drain())
+    }
+
+    fn ssz_append_content(&self, s: &mut SszStream) {
+        s.append(&self.seq);
+        s.append_vec(&self.public_key.as_bytes());
+        /*
+         * An unset ip or port is encoded as zero, which is never a valid value for either.
+         */
+        s.append(&self.ip.map_or(0, u32::from));
+        s.append(&self.tcp.unwrap_or(0));
+        s.append(&self.udp.unwrap_or(0));
+        s.append(&self.fork_digest);
+        s.append(&self.attnets);
+    }
+}
+
+impl Encodable for Enr {
+    fn ssz_append(&self, s: &mut SszStream) {
+        self.ssz_append_content(s);
+        s.append_vec(&self.signature.as_bytes());
+    }
+}
+
+impl Decodable for Enr {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (seq, i) = u64::ssz_decode(bytes, i)?;
+        let (public_key_bytes, i): (Vec<u8>, usize) = decode_ssz_list(bytes, i)?;
+        let (ip, i) = u32::ssz_decode(bytes, i)?;
+        let (tcp, i) = u16::ssz_decode(bytes, i)?;
+        let (udp, i) = u16::ssz_decode(bytes, i)?;
+        let (fork_digest, i) = ForkDigest::ssz_decode(bytes, i)?;
+        let (attnets, i) = Bitfield::ssz_decode(bytes, i)?;
+        let (signature_bytes, i): (Vec<u8>, usize) = decode_ssz_list(bytes, i)?;
+
+        let public_key =
+            PublicKey::from_bytes(&public_key_bytes).map_err(|_| DecodeError::TooShort)?;
+        let signature =
+            Signature::from_bytes(&signature_bytes).map_err(|_| DecodeError::TooShort)?;
+
+        let enr = Self {
+            seq,
+            public_key,
+            ip: if ip == 0 {
+                None
+            } else {
+                Some(Ipv4Addr::from(ip))
+            },
+            tcp: if tcp == 0 { None } else { Some(tcp) },
+            udp: if udp == 0 { None } else { Some(udp) },
+            fork_digest,
+            attnets,
+            signature,
+        };
+        Ok((enr, i))
+    }
+}
+
+/// Records are shared between operators (e.g. to configure bootnodes) as `enr:` followed by the
+/// hex-encoded SSZ of the record.
+impl fmt::Display for Enr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", ENR_PREFIX)?;
+        for byte in ssz_encode(self) {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Enr {
+    type Err = String;
+
+    /// Parses the textual form of a record, rejecting it if the signature is invalid.
+    fn from_str(s: &str) -> Result<Self, String> {
+        if !s.starts_with(ENR_PREFIX) {
+            return Err(format!("Record must start with {}", ENR_PREFIX));
+        }
+        let hex = &s[ENR_PREFIX.len()..];
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err("Invalid hex in record".to_string());
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| "Invalid hex in record".to_string())?;
+        let (enr, _) = Enr::ssz_decode(&bytes, 0).map_err(|_| "Invalid record".to_string())?;
+        if !enr.verify() {
+            return Err("Invalid record signature".to_string());
+        }
+        Ok(enr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_enr(keypair: &Keypair) -> Enr {
+        Enr::new(
+            keypair,
+            Some(Ipv4Addr::new(10, 0, 0, 1)),
+            Some(9000),
+            Some(9001),
+        )
+    }
+
+    #[test]
+    fn test_enr_sockets() {
+        let enr = test_enr(&Keypair::random());
+
+        assert_eq!(enr.seq(), 1);
+        assert_eq!(enr.udp_socket().unwrap(), "10.0.0.1:9001".parse().unwrap());
+        assert_eq!(enr.tcp_socket().unwrap(), "10.0.0.1:9000".parse().unwrap());
+
+        let enr = Enr::new(&Keypair::random(), None, Some(9000), Some(9001));
+        assert_eq!(enr.udp_socket(), None);
+        assert_eq!(enr.tcp_socket(), None);
+    }
+
+    #[test]
+    fn test_enr_signature() {
+        let keypair = Keypair::random();
+        let enr = test_enr(&keypair);
+        assert!(enr.verify());
+
+        let mut modified = enr.clone();
+        modified.seq += 1;
+        assert!(!modified.verify());
+
+        modified.sign(&keypair);
+        assert!(modified.verify());
+
+        let mut forged = enr.clone();
+        forged.sign(&Keypair::random());
+        assert!(!forged.verify());
+    }
+
+    #[test]
+    fn test_enr_ssz_round_trip() {
+        let enr = test_enr(&Keypair::random());
+        let ssz = ssz_encode(&enr);
+        let (decoded, i) = Enr::ssz_decode(&ssz, 0).unwrap();
+
+        assert_eq!(enr, decoded);
+        assert_eq!(i, ssz.len());
+        assert!(decoded.verify());
+
+        let enr = Enr::new(&Keypair::random(), None, None, None);
+        let (decoded, _) = Enr::ssz_decode(&ssz_encode(&enr), 0).unwrap();
+        assert_eq!(enr, decoded);
+    }
+
+    #[test]
+    fn test_enr_unsubscribed_by_default() {
+        let enr = test_enr(&Keypair::random());
+
+        for subnet in 0..ATTESTATION_SUBNET_COUNT as u64 {
+            assert!(!enr.is_subscribed_to_subnet(subnet));
+        }
+        assert!(!enr.is_subscribed_to_subnet(ATTESTATION_SUBNET_COUNT as u64 + 1));
+    }
+
+    #[test]
+    fn test_enr_updates() {
+        let keypair = Keypair::random();
+        let mut enr = test_enr(&keypair);
+
+        assert!(!enr.set_tcp(Some(9000), &keypair));
+        assert_eq!(enr.seq(), 1);
+
+        assert!(enr.set_ip(Some(Ipv4Addr::new(1, 2, 3, 4)), &keypair));
+        assert!(enr.set_fork_digest(ForkDigest([1, 2, 3, 4]), &keypair));
+        assert!(enr.set_subnet(5, true, &keypair));
+        assert!(!enr.set_subnet(5, true, &keypair));
+        assert!(!enr.set_subnet(ATTESTATION_SUBNET_COUNT as u64, true, &keypair));
+
+        assert_eq!(enr.seq(), 4);
+        assert!(enr.verify());
+        assert!(enr.is_subscribed_to_subnet(5));
+        assert_eq!(enr.fork_digest(), ForkDigest([1, 2, 3, 4]));
+        assert_eq!(enr.udp_socket().unwrap(), "1.2.3.4:9001".parse().unwrap());
+
+        let (decoded, _) = Enr::ssz_decode(&ssz_encode(&enr), 0).unwrap();
+        assert_eq!(enr, decoded);
+    }
+
+    #[test]
+    fn test_log2_distance() {
+        let a = NodeId(Hash256::zero());
+        let mut b_bytes = [0; 32];
+
+        assert_eq!(a.log2_distance(&a), None);
+
+        b_bytes[31] = 1;
+        assert_eq!(
+            a.log2_distance(&NodeId(Hash256::from(&b_bytes[..]))),
+            Some(1)
+        );
+
+        b_bytes[31] = 0b1000_0000;
+        assert_eq!(
+            a.log2_distance(&NodeId(Hash256::from(&b_bytes[..]))),
+            Some(8)
+        );
+
+        b_bytes[30] = 1;
+        assert_eq!(
+            a.log2_distance(&NodeId(Hash256::from(&b_bytes[..]))),
+            Some(9)
+        );
+
+        b_bytes[0] = 0b1000_0000;
+        assert_eq!(
+            a.log2_distance(&NodeId(Hash256::from(&b_bytes[..]))),
+            Some(256)
+        );
+    }
+
+    #[test]
+    fn test_enr_text_round_trip() {
+        let enr = test_enr(&Keypair::random());
+        let text = enr.to_string();
+        assert!(text.starts_with("enr:"));
+        assert_eq!(text.parse::<Enr>(), Ok(enr.clone()));
+
+        assert!(text[4..].parse::<Enr>().is_err());
+        assert!(format!("{}0", text).parse::<Enr>().is_err());
+        assert!("enr:zz".parse::<Enr>().is_err());
+
+        let mut forged = enr;
+        forged.seq += 1;
+        assert!(forged.to_string().parse::<Enr>().is_err());
+    }
+}
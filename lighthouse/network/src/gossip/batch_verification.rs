@@ -0,0 +1,170 @@
+use super::super::bls::{AggregatePublicKey, PublicKey};
+use super::super::metrics;
+use super::super::rpc::ForkDigest;
+use super::super::types::signing::attestation_signing_root;
+use super::super::types::{Attestation, AttestationData, Hash256};
+use lighthouse_metrics::{inc_counter_by, observe};
+
+/// Verifies the signatures of a batch of attestations, returning whether each is valid.
+///
+/// `pubkeys` returns the public keys of the participants of an attestation, or `None` if its
+/// committee is unknown, in which case the attestation is invalid.
+///
+/// Attestations to the same data sign the same root, which is computed once for the batch. Each
+/// signature is still verified on its own: the BLS library offers no random coefficients to
+/// weigh the signatures of a group by, so a single check of their aggregate would accept
+/// signatures crafted to cancel each other out.
+pub fn verify_attestation_batch<F>(
+    attestations: &[Attestation],
+    fork_digest: &ForkDigest,
+    pubkeys: F,
+) -> Vec<bool>
+where
+    F: Fn(&Attestation) -> Option<Vec<PublicKey>>,
+{
+    observe(&metrics::ATTESTATION_BATCH_SIZE, attestations.len() as f64);
+    let mut roots: Vec<(&AttestationData, Hash256)> = vec![];
+    let mut valid = Vec::with_capacity(attestations.len());
+    for attestation in attestations {
+        let keys = pubkeys(attestation).unwrap_or_else(Vec::new);
+        if keys.is_empty() {
+            valid.push(false);
+            continue;
+        }
+        let data = &attestation.data;
+        let root = match roots.iter().find(|(known, _)| *known == data) {
+            Some(&(_, root)) => root,
+            None => {
+                let root = attestation_signing_root(data, fork_digest.0);
+                roots.push((data, root));
+                root
+            }
+        };
+        let mut aggregate_pubkey = AggregatePublicKey::new();
+        for pubkey in &keys {
+            aggregate_pubkey.add(pubkey);
+        }
+        valid.push(
+            attestation
+                .aggregate_sig
+                .verify(&root[..], &aggregate_pubkey),
+        );
+    }
+    let invalid = valid.iter().filter(|valid| !**valid).count();
+    inc_counter_by(
+        &metrics::ATTESTATION_BATCH_INVALID_SIGNATURES,
+        invalid as i64,
+    );
+    valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::bls::{Keypair, Signature};
+    use super::super::super::types::Bitfield;
+    use super::*;
+
+    /// Returns an attestation to `data` from the members of its committee at `positions`, signed
+    /// by `signers`.
+    fn attestation(
+        data: &AttestationData,
+        positions: &[usize],
+        signers: &[&Keypair],
+    ) -> Attestation {
+        let root = attestation_signing_root(data, [1, 2, 3, 4]);
+        let mut attestation = Attestation::zero();
+        attestation.data = data.clone();
+        attestation.participation_bitfield = Bitfield::from_elem(3, false);
+        for &i in positions {
+            attestation.participation_bitfield.set(i, true).unwrap();
+        }
+        for keypair in signers {
+            attestation
+                .aggregate_sig
+                .add(&Signature::new(&root[..], &keypair.sk));
+        }
+        attestation
+    }
+
+    #[test]
+    fn test_batch_verification() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (k0, k1, k2, k3) = (&keypairs[0], &keypairs[1], &keypairs[2], &keypairs[3]);
+        let data = AttestationData::zero();
+        let other_data = AttestationData {
+            slot: 1,
+            ..AttestationData::zero()
+        };
+        let fork_digest = ForkDigest([1, 2, 3, 4]);
+
+        /*
+         * The committee of each slot, by the index of the keypair of each member.
+         */
+        let committees = vec![vec![0, 1, 2], vec![3]];
+        let pubkeys = |attestation: &Attestation| {
+            let committee = committees.get(attestation.data.slot as usize)?;
+            Some(
+                committee
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| attestation.participation_bitfield.get(*i) == Ok(true))
+                    .map(|(_, &j)| keypairs[j].pk.clone())
+                    .collect(),
+            )
+        };
+
+        let mut attestations = vec![
+            attestation(&data, &[0, 1], &[k0, k1]),
+            attestation(&data, &[2], &[k2]),
+            attestation(&other_data, &[0], &[k3]),
+        ];
+        assert_eq!(
+            verify_attestation_batch(&attestations, &fork_digest, &pubkeys),
+            vec![true, true, true]
+        );
+
+        /*
+         * An attestation signed by the wrong member fails on its own, without failing the valid
+         * attestations to the same data.
+         */
+        attestations.push(attestation(&data, &[0], &[k1]));
+        attestations.push(attestation(&data, &[1], &[k1]));
+        assert_eq!(
+            verify_attestation_batch(&attestations, &fork_digest, &pubkeys),
+            vec![true, true, true, false, true]
+        );
+
+        /*
+         * Two members who swap their signatures make two invalid attestations, though the
+         * aggregate of their signatures is valid for the aggregate of their keys.
+         */
+        let swapped = vec![
+            attestation(&data, &[0], &[k1]),
+            attestation(&data, &[1], &[k0]),
+        ];
+        assert_eq!(
+            verify_attestation_batch(&swapped, &fork_digest, &pubkeys),
+            vec![false, false]
+        );
+
+        /*
+         * Attestations signed on another chain, or from unknown committees, are invalid.
+         */
+        assert_eq!(
+            verify_attestation_batch(&attestations, &ForkDigest([0; 4]), &pubkeys),
+            vec![false; 5]
+        );
+        let unknown = AttestationData {
+            slot: 2,
+            ..AttestationData::zero()
+        };
+        assert_eq!(
+            verify_attestation_batch(
+                &[attestation(&unknown, &[0], &[k0])],
+                &fork_digest,
+                &pubkeys
+            ),
+            vec![false]
+        );
+    }
+}
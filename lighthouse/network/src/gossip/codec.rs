@@ -0,0 +1,92 @@
+use super::super::snap;
+
+/// The maximum length of the uncompressed SSZ payload of a gossip message.
+pub const GOSSIP_MAX_SIZE: usize = 1_048_576;
+
+#[derive(Debug, PartialEq)]
+pub enum GossipCodecError {
+    /// The message, compressed or uncompressed, exceeds the maximum size.
+    TooLarge,
+    /// The message could not be decompressed.
+    InvalidCompression,
+}
+
+/*
+ * Unlike req/resp, gossip payloads are compressed with the raw snappy block format rather than
+ * the framing format. Each message is a single block, and its uncompressed length is encoded in
+ * the block header.
+ */
+
+/// Compresses the SSZ payload of a gossip message.
+pub fn encode(ssz: &[u8]) -> Result<Vec<u8>, GossipCodecError> {
+    if ssz.len() > GOSSIP_MAX_SIZE {
+        return Err(GossipCodecError::TooLarge);
+    }
+    snap::raw::Encoder::new()
+        .compress_vec(ssz)
+        .map_err(|_| GossipCodecError::TooLarge)
+}
+
+/// Decompresses a gossip message, returning its SSZ payload.
+///
+/// Both the compressed length and the uncompressed length given in the block header are checked
+/// before anything is decompressed, so a peer cannot cause a large allocation.
+pub fn decode(bytes: &[u8]) -> Result<Vec<u8>, GossipCodecError> {
+    if bytes.len() > snap::raw::max_compress_len(GOSSIP_MAX_SIZE) {
+        return Err(GossipCodecError::TooLarge);
+    }
+    let len = snap::raw::decompress_len(bytes).map_err(|_| GossipCodecError::InvalidCompression)?;
+    if len > GOSSIP_MAX_SIZE {
+        return Err(GossipCodecError::TooLarge);
+    }
+    snap::raw::Decoder::new()
+        .decompress_vec(bytes)
+        .map_err(|_| GossipCodecError::InvalidCompression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let ssz = vec![3; 10_000];
+        let bytes = encode(&ssz).unwrap();
+        assert!(bytes.len() < ssz.len());
+        assert_eq!(decode(&bytes), Ok(ssz));
+
+        let ssz = vec![3; GOSSIP_MAX_SIZE];
+        assert_eq!(decode(&encode(&ssz).unwrap()), Ok(ssz));
+    }
+
+    #[test]
+    fn test_size_limits() {
+        assert_eq!(
+            encode(&vec![0; GOSSIP_MAX_SIZE + 1]),
+            Err(GossipCodecError::TooLarge)
+        );
+
+        /*
+         * A block header claiming a length above the maximum is rejected without decompressing.
+         */
+        let compressed = snap::raw::Encoder::new()
+            .compress_vec(&vec![0; GOSSIP_MAX_SIZE + 1])
+            .unwrap();
+        assert_eq!(decode(&compressed), Err(GossipCodecError::TooLarge));
+
+        let oversized = vec![0; snap::raw::max_compress_len(GOSSIP_MAX_SIZE) + 1];
+        assert_eq!(decode(&oversized), Err(GossipCodecError::TooLarge));
+    }
+
+    #[test]
+    fn test_invalid_compression() {
+        assert_eq!(
+            decode(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Err(GossipCodecError::InvalidCompression)
+        );
+
+        let mut bytes = encode(&[1, 2, 3, 4]).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert_eq!(decode(&bytes), Err(GossipCodecError::InvalidCompression));
+    }
+}
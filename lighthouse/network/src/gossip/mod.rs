@@ -0,0 +1,19 @@
+mod batch_verification;
+mod codec;
+mod persistence;
+mod scoring;
+mod seen_cache;
+mod verification_cache;
+
+pub use self::batch_verification::verify_attestation_batch;
+pub use self::codec::{decode, encode, GossipCodecError, GOSSIP_MAX_SIZE};
+pub use self::persistence::{
+    load_subnet_subscriptions, persist_subnet_subscriptions, GossipPersistenceError, PersistedSeen,
+    PersistedSubscription,
+};
+pub use self::scoring::{
+    GossipKind, PeerScoreParams, PeerScores, TopicScoreParams, GOSSIP_THRESHOLD,
+    GRAYLIST_THRESHOLD, PUBLISH_THRESHOLD,
+};
+pub use self::seen_cache::{BlockObservation, DuplicateFilter, MessageId, SeenCache, SEEN_TTL};
+pub use self::verification_cache::VerificationCache;
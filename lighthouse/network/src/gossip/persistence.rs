@@ -0,0 +1,289 @@
+use super::super::db::stores::GossipStore;
+use super::super::db::{ClientDB, DBError};
+use super::super::ssz::{decode_ssz_list, Decodable, DecodeError, Encodable, SszStream};
+use super::super::time::unix_time;
+use super::seen_cache::{DuplicateFilter, MessageId, SeenCache};
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq)]
+pub enum GossipPersistenceError {
+    DBError(String),
+    DecodeError,
+}
+
+impl From<DBError> for GossipPersistenceError {
+    fn from(error: DBError) -> Self {
+        GossipPersistenceError::DBError(error.message)
+    }
+}
+
+impl From<DecodeError> for GossipPersistenceError {
+    fn from(_: DecodeError) -> Self {
+        GossipPersistenceError::DecodeError
+    }
+}
+
+/// A message or duty seen on gossip, as written to disk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PersistedSeen<K> {
+    pub key: K,
+    /// Seconds since the unix epoch.
+    pub observed: u64,
+}
+
+impl Encodable for PersistedSeen<MessageId> {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.key);
+        s.append(&self.observed);
+    }
+}
+
+impl Decodable for PersistedSeen<MessageId> {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (key, i) = MessageId::ssz_decode(bytes, i)?;
+        let (observed, i) = u64::ssz_decode(bytes, i)?;
+        Ok((PersistedSeen { key, observed }, i))
+    }
+}
+
+impl Encodable for PersistedSeen<(u64, u64)> {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.key.0);
+        s.append(&self.key.1);
+        s.append(&self.observed);
+    }
+}
+
+impl Decodable for PersistedSeen<(u64, u64)> {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (index, i) = u64::ssz_decode(bytes, i)?;
+        let (slot, i) = u64::ssz_decode(bytes, i)?;
+        let (observed, i) = u64::ssz_decode(bytes, i)?;
+        let seen = PersistedSeen {
+            key: (index, slot),
+            observed,
+        };
+        Ok((seen, i))
+    }
+}
+
+/// The last slot for which an attestation subnet is needed, as written to disk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PersistedSubscription {
+    pub subnet: u64,
+    pub until_slot: u64,
+}
+
+impl Encodable for PersistedSubscription {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.subnet);
+        s.append(&self.until_slot);
+    }
+}
+
+impl Decodable for PersistedSubscription {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (subnet, i) = u64::ssz_decode(bytes, i)?;
+        let (until_slot, i) = u64::ssz_decode(bytes, i)?;
+        Ok((PersistedSubscription { subnet, until_slot }, i))
+    }
+}
+
+impl DuplicateFilter {
+    /// Writes the messages and duties seen to `store`, replacing those previously written.
+    pub fn persist<T: ClientDB>(
+        &self,
+        store: &GossipStore<T>,
+        now: Instant,
+    ) -> Result<(), GossipPersistenceError> {
+        let mut s = SszStream::new();
+        s.append_vec(&persisted(&self.messages, now));
+        s.append_vec(&persisted(&self.block_proposals, now));
+        s.append_vec(&persisted(&self.attestations, now));
+        store.put_serialized_seen(&s.drain())?;
+        Ok(())
+    }
+
+    /// Restores the messages and duties written by `persist` which were seen within the ttl,
+    /// returning how many were restored.
+    ///
+    /// Must be called before anything is observed.
+    pub fn load<T: ClientDB>(
+        &mut self,
+        store: &GossipStore<T>,
+        now: Instant,
+    ) -> Result<usize, GossipPersistenceError> {
+        let ssz = match store.get_serialized_seen()? {
+            Some(ssz) => ssz,
+            None => return Ok(0),
+        };
+        let (messages, i) = decode_ssz_list(&ssz, 0)?;
+        let (block_proposals, i) = decode_ssz_list(&ssz, i)?;
+        let (attestations, _) = decode_ssz_list(&ssz, i)?;
+
+        restore(&mut self.messages, messages, now);
+        restore(&mut self.block_proposals, block_proposals, now);
+        restore(&mut self.attestations, attestations, now);
+        self.prune(now);
+        Ok(self.messages.len() + self.block_proposals.len() + self.attestations.len())
+    }
+}
+
+/// Writes the attestation subnets needed, with the last slot each is needed for, to `store`.
+pub fn persist_subnet_subscriptions<T: ClientDB>(
+    store: &GossipStore<T>,
+    subscriptions: &BTreeMap<u64, u64>,
+) -> Result<(), GossipPersistenceError> {
+    let persisted: Vec<PersistedSubscription> = subscriptions
+        .iter()
+        .map(|(subnet, until_slot)| PersistedSubscription {
+            subnet: *subnet,
+            until_slot: *until_slot,
+        })
+        .collect();
+    let mut s = SszStream::new();
+    s.append_vec(&persisted);
+    store.put_serialized_subnet_subscriptions(&s.drain())?;
+    Ok(())
+}
+
+/// Restores the subnet subscriptions written by `persist_subnet_subscriptions` which are still
+/// needed at `present_slot`.
+pub fn load_subnet_subscriptions<T: ClientDB>(
+    store: &GossipStore<T>,
+    present_slot: u64,
+) -> Result<BTreeMap<u64, u64>, GossipPersistenceError> {
+    let ssz = match store.get_serialized_subnet_subscriptions()? {
+        Some(ssz) => ssz,
+        None => return Ok(BTreeMap::new()),
+    };
+    let (persisted, _): (Vec<PersistedSubscription>, usize) = decode_ssz_list(&ssz, 0)?;
+    Ok(persisted
+        .into_iter()
+        .filter(|s| s.until_slot >= present_slot)
+        .map(|s| (s.subnet, s.until_slot))
+        .collect())
+}
+
+fn persisted<K: Clone + Eq + Hash>(cache: &SeenCache<K>, now: Instant) -> Vec<PersistedSeen<K>> {
+    let unix_now = unix_time();
+    cache
+        .observed()
+        .into_iter()
+        .map(|(key, observed)| PersistedSeen {
+            key,
+            observed: unix_now.saturating_sub(now.duration_since(observed).as_secs()),
+        })
+        .collect()
+}
+
+fn restore<K: Clone + Eq + Hash>(
+    cache: &mut SeenCache<K>,
+    mut persisted: Vec<PersistedSeen<K>>,
+    now: Instant,
+) {
+    let unix_now = unix_time();
+    persisted.sort_by_key(|seen| seen.observed);
+    for seen in persisted {
+        let age = Duration::from_secs(unix_now.saturating_sub(seen.observed));
+        cache.insert_observed(seen.key, now.checked_sub(age).unwrap_or(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::db::MemoryDB;
+    use super::super::SEEN_TTL;
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_persisted_seen_ssz_round_trip() {
+        let seen = PersistedSeen {
+            key: (3, 100),
+            observed: 1_000,
+        };
+        let mut s = SszStream::new();
+        s.append(&seen);
+        let bytes = s.drain();
+        assert_eq!(
+            PersistedSeen::<(u64, u64)>::ssz_decode(&bytes, 0),
+            Ok((seen, bytes.len()))
+        );
+    }
+
+    #[test]
+    fn test_persist_and_load_duplicate_filter() {
+        let store = GossipStore::new(Arc::new(MemoryDB::open()));
+        let now = Instant::now();
+        let mut filter = DuplicateFilter::default();
+        filter.observe_message(b"block", now);
+        filter.observe_block_proposal(3, 100, now);
+        filter.observe_attestation(7, 2, now);
+        filter.persist(&store, now).unwrap();
+
+        let mut restored = DuplicateFilter::default();
+        assert_eq!(restored.load(&store, now).unwrap(), 3);
+        assert!(!restored.observe_message(b"block", now));
+        assert!(!restored.observe_block_proposal(3, 100, now));
+        assert!(!restored.observe_attestation(7, 2, now));
+        assert!(restored.observe_attestation(8, 2, now));
+
+        /*
+         * Restored entries expire as they would have without the restart.
+         */
+        let later = now + SEEN_TTL + Duration::from_secs(1);
+        assert!(restored.observe_message(b"block", later));
+    }
+
+    #[test]
+    fn test_load_discards_expired() {
+        let store = GossipStore::new(Arc::new(MemoryDB::open()));
+        let expired = PersistedSeen {
+            key: (3, 100),
+            observed: unix_time() - SEEN_TTL.as_secs() - 1,
+        };
+        let mut s = SszStream::new();
+        s.append_vec::<PersistedSeen<MessageId>>(&[]);
+        s.append_vec(&[expired]);
+        s.append_vec::<PersistedSeen<(u64, u64)>>(&[]);
+        store.put_serialized_seen(&s.drain()).unwrap();
+
+        let mut filter = DuplicateFilter::default();
+        assert_eq!(filter.load(&store, Instant::now()).unwrap(), 0);
+        assert!(filter.observe_block_proposal(3, 100, Instant::now()));
+    }
+
+    #[test]
+    fn test_persist_and_load_subnet_subscriptions() {
+        let store = GossipStore::new(Arc::new(MemoryDB::open()));
+        assert_eq!(
+            load_subnet_subscriptions(&store, 0).unwrap(),
+            BTreeMap::new()
+        );
+
+        let subscriptions: BTreeMap<u64, u64> = vec![(1, 10), (4, 20)].into_iter().collect();
+        persist_subnet_subscriptions(&store, &subscriptions).unwrap();
+        assert_eq!(
+            load_subnet_subscriptions(&store, 10).unwrap(),
+            subscriptions
+        );
+        assert_eq!(
+            load_subnet_subscriptions(&store, 11).unwrap(),
+            vec![(4, 20)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_load_empty_store() {
+        let store = GossipStore::new(Arc::new(MemoryDB::open()));
+        assert_eq!(
+            DuplicateFilter::default()
+                .load(&store, Instant::now())
+                .unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,611 @@
+use super::super::enr::ATTESTATION_SUBNET_COUNT;
+use super::super::rpc::PeerId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Peers scoring below this are not gossiped to.
+pub const GOSSIP_THRESHOLD: f64 = -4000.0;
+/// Messages published by this node are not sent to peers scoring below this.
+pub const PUBLISH_THRESHOLD: f64 = -8000.0;
+/// Everything from peers scoring below this is ignored.
+pub const GRAYLIST_THRESHOLD: f64 = -16000.0;
+
+/*
+ * The topic parameters are derived as recommended for the beacon chain, from the share of a
+ * peer's positive score each topic may contribute.
+ */
+const MAX_IN_MESH_SCORE: f64 = 10.0;
+const MAX_FIRST_MESSAGE_DELIVERIES_SCORE: f64 = 40.0;
+const BEACON_BLOCK_WEIGHT: f64 = 0.5;
+const BEACON_AGGREGATE_PROOF_WEIGHT: f64 = 0.5;
+const BEACON_ATTESTATION_SUBNET_WEIGHT: f64 = 1.0 / ATTESTATION_SUBNET_COUNT as f64;
+const VOLUNTARY_EXIT_WEIGHT: f64 = 0.05;
+const PROPOSER_SLASHING_WEIGHT: f64 = 0.05;
+const ATTESTER_SLASHING_WEIGHT: f64 = 0.05;
+/// The number of peers in the mesh of a topic.
+const MESH_N: f64 = 8.0;
+const TARGET_COMMITTEE_SIZE: usize = 128;
+const MAX_COMMITTEES_PER_SLOT: usize = 64;
+const TARGET_AGGREGATORS_PER_COMMITTEE: f64 = 16.0;
+/// Counters below this after decaying are zeroed.
+const DECAY_TO_ZERO: f64 = 0.01;
+
+/// A topic whose messages are scored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GossipKind {
+    BeaconBlock,
+    BeaconAggregateAndProof,
+    /// The attestations of a subnet.
+    Attestation(u64),
+    VoluntaryExit,
+    ProposerSlashing,
+    AttesterSlashing,
+}
+
+/// The parameters of the score a peer earns in a topic, as in gossipsub v1.1. The weights of
+/// penalties are negative.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopicScoreParams {
+    pub topic_weight: f64,
+    pub time_in_mesh_weight: f64,
+    pub time_in_mesh_quantum: Duration,
+    pub time_in_mesh_cap: f64,
+    pub first_message_deliveries_weight: f64,
+    pub first_message_deliveries_decay: f64,
+    pub first_message_deliveries_cap: f64,
+    /// Zero if mesh peers are not expected to deliver messages, as in topics too quiet for a
+    /// shortfall to be told from silence.
+    pub mesh_message_deliveries_weight: f64,
+    pub mesh_message_deliveries_decay: f64,
+    pub mesh_message_deliveries_threshold: f64,
+    pub mesh_message_deliveries_cap: f64,
+    /// How long a peer is in the mesh before its deliveries are expected.
+    pub mesh_message_deliveries_activation: Duration,
+    pub mesh_failure_penalty_weight: f64,
+    pub mesh_failure_penalty_decay: f64,
+    pub invalid_message_deliveries_weight: f64,
+    pub invalid_message_deliveries_decay: f64,
+}
+
+/// The parameters of the gossip scores of peers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerScoreParams {
+    pub topics: HashMap<GossipKind, TopicScoreParams>,
+    /// The greatest positive score of all topics together, so that a peer cannot build up
+    /// enough credit in quiet topics to misbehave in busy ones.
+    pub topic_score_cap: f64,
+    pub behaviour_penalty_weight: f64,
+    /// The behaviour penalties, e.g. for broken promises, tolerated before any is scored.
+    pub behaviour_penalty_threshold: f64,
+    pub behaviour_penalty_decay: f64,
+    /// How often counters decay.
+    pub decay_interval: Duration,
+}
+
+impl PeerScoreParams {
+    /// The recommended parameters for a chain with `slot_duration`, `slots_per_epoch` and
+    /// `active_validators`, from which the expected rate of each topic is found.
+    pub fn standard(
+        slot_duration: Duration,
+        slots_per_epoch: u64,
+        active_validators: usize,
+    ) -> Self {
+        let slot = slot_duration.max(Duration::from_secs(1));
+        let slots_per_epoch = slots_per_epoch.max(1);
+        let epoch = slot * slots_per_epoch as u32;
+        let max_positive_score = (MAX_IN_MESH_SCORE + MAX_FIRST_MESSAGE_DELIVERIES_SCORE)
+            * (BEACON_BLOCK_WEIGHT
+                + BEACON_AGGREGATE_PROOF_WEIGHT
+                + BEACON_ATTESTATION_SUBNET_WEIGHT * ATTESTATION_SUBNET_COUNT as f64
+                + VOLUNTARY_EXIT_WEIGHT
+                + PROPOSER_SLASHING_WEIGHT
+                + ATTESTER_SLASHING_WEIGHT);
+        let derive = Derivation {
+            slot,
+            epoch,
+            decay_interval: slot,
+            max_positive_score,
+        };
+
+        /*
+         * The expected number of messages of each topic per slot.
+         */
+        let committees_per_slot =
+            (active_validators / slots_per_epoch as usize / TARGET_COMMITTEE_SIZE)
+                .max(1)
+                .min(MAX_COMMITTEES_PER_SLOT);
+        let aggregates_per_slot = TARGET_AGGREGATORS_PER_COMMITTEE * committees_per_slot as f64;
+        let attestations_per_subnet =
+            active_validators as f64 / (slots_per_epoch as f64 * ATTESTATION_SUBNET_COUNT as f64);
+
+        let mut topics = HashMap::new();
+        topics.insert(
+            GossipKind::BeaconBlock,
+            derive.topic(
+                BEACON_BLOCK_WEIGHT,
+                1.0,
+                epoch * 20,
+                Some((epoch * 5, epoch * 3)),
+            ),
+        );
+        topics.insert(
+            GossipKind::BeaconAggregateAndProof,
+            derive.topic(
+                BEACON_AGGREGATE_PROOF_WEIGHT,
+                aggregates_per_slot,
+                epoch,
+                Some((epoch * 2, epoch)),
+            ),
+        );
+        for subnet in 0..ATTESTATION_SUBNET_COUNT as u64 {
+            topics.insert(
+                GossipKind::Attestation(subnet),
+                derive.topic(
+                    BEACON_ATTESTATION_SUBNET_WEIGHT,
+                    attestations_per_subnet,
+                    epoch * 10,
+                    Some((epoch * 4, epoch)),
+                ),
+            );
+        }
+        for (kind, weight, per_epoch) in &[
+            (GossipKind::VoluntaryExit, VOLUNTARY_EXIT_WEIGHT, 4.0),
+            (GossipKind::ProposerSlashing, PROPOSER_SLASHING_WEIGHT, 1.0),
+            (GossipKind::AttesterSlashing, ATTESTER_SLASHING_WEIGHT, 1.0),
+        ] {
+            topics.insert(
+                *kind,
+                derive.topic(
+                    *weight,
+                    per_epoch / slots_per_epoch as f64,
+                    epoch * 100,
+                    None,
+                ),
+            );
+        }
+
+        /*
+         * Ten behaviour penalties an epoch, beyond the threshold, take a peer to the gossip
+         * threshold.
+         */
+        let behaviour_penalty_threshold = 6.0;
+        let behaviour_penalty_decay = derive.decay(epoch * 10);
+        let target = decay_convergence(behaviour_penalty_decay, 10.0 / slots_per_epoch as f64)
+            - behaviour_penalty_threshold;
+
+        Self {
+            topics,
+            topic_score_cap: max_positive_score * 0.5,
+            behaviour_penalty_weight: GOSSIP_THRESHOLD / (target * target),
+            behaviour_penalty_threshold,
+            behaviour_penalty_decay,
+            decay_interval: slot,
+        }
+    }
+}
+
+/// Derives the parameters of topics.
+struct Derivation {
+    slot: Duration,
+    epoch: Duration,
+    decay_interval: Duration,
+    max_positive_score: f64,
+}
+
+impl Derivation {
+    /// The decay for each interval by which a counter falls to zero over `decay_time`.
+    fn decay(&self, decay_time: Duration) -> f64 {
+        let intervals = secs(decay_time) / secs(self.decay_interval);
+        DECAY_TO_ZERO.powf(1.0 / intervals)
+    }
+
+    /// The parameters of a topic with `expected_message_rate` messages per slot. `mesh` is the
+    /// decay time and activation of mesh deliveries, if they are scored.
+    fn topic(
+        &self,
+        topic_weight: f64,
+        expected_message_rate: f64,
+        first_message_decay_time: Duration,
+        mesh: Option<(Duration, Duration)>,
+    ) -> TopicScoreParams {
+        let time_in_mesh_cap = 3600.0 / secs(self.slot);
+        let first_message_deliveries_decay = self.decay(first_message_decay_time);
+        let first_message_deliveries_cap = decay_convergence(
+            first_message_deliveries_decay,
+            2.0 * expected_message_rate / MESH_N,
+        );
+        let mut params = TopicScoreParams {
+            topic_weight,
+            time_in_mesh_weight: MAX_IN_MESH_SCORE / time_in_mesh_cap,
+            time_in_mesh_quantum: self.slot,
+            time_in_mesh_cap,
+            first_message_deliveries_weight: MAX_FIRST_MESSAGE_DELIVERIES_SCORE
+                / first_message_deliveries_cap,
+            first_message_deliveries_decay,
+            first_message_deliveries_cap,
+            mesh_message_deliveries_weight: 0.0,
+            mesh_message_deliveries_decay: 0.0,
+            mesh_message_deliveries_threshold: 0.0,
+            mesh_message_deliveries_cap: 0.0,
+            mesh_message_deliveries_activation: Duration::from_secs(0),
+            mesh_failure_penalty_weight: 0.0,
+            mesh_failure_penalty_decay: 0.0,
+            invalid_message_deliveries_weight: -self.max_positive_score / topic_weight,
+            invalid_message_deliveries_decay: self.decay(self.epoch * 50),
+        };
+        if let Some((decay_time, activation)) = mesh {
+            let decay = self.decay(decay_time);
+            let threshold = decay_convergence(decay, expected_message_rate / 50.0) * decay;
+            let weight = -self.max_positive_score / (topic_weight * threshold * threshold);
+            params.mesh_message_deliveries_weight = weight;
+            params.mesh_message_deliveries_decay = decay;
+            params.mesh_message_deliveries_threshold = threshold;
+            params.mesh_message_deliveries_cap = (2.0 * threshold).max(2.0);
+            params.mesh_message_deliveries_activation = activation;
+            params.mesh_failure_penalty_weight = weight;
+            params.mesh_failure_penalty_decay = decay;
+        }
+        params
+    }
+}
+
+/// The value to which a counter incremented by `rate` each interval converges under `decay`.
+fn decay_convergence(decay: f64, rate: f64) -> f64 {
+    rate / (1.0 - decay)
+}
+
+fn secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+/// The counters of a peer in a topic.
+#[derive(Clone, Debug, Default)]
+struct TopicCounters {
+    /// When the peer joined the mesh of the topic, if it is in it.
+    in_mesh_since: Option<Instant>,
+    first_message_deliveries: f64,
+    mesh_message_deliveries: f64,
+    mesh_failure_penalty: f64,
+    invalid_message_deliveries: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+struct PeerCounters {
+    topics: HashMap<GossipKind, TopicCounters>,
+    behaviour_penalty: f64,
+}
+
+/// Scores gossip peers by what they deliver, as gossipsub v1.1 does: time in the mesh and first
+/// deliveries earn credit, while invalid messages, too few deliveries from the mesh and
+/// misbehaviour cost it.
+///
+/// The counters of a peer outlive its connection, so that a peer cannot escape its score by
+/// reconnecting, and are forgotten once they decay to zero.
+pub struct PeerScores {
+    params: PeerScoreParams,
+    peers: HashMap<PeerId, PeerCounters>,
+    last_decay: Instant,
+}
+
+impl PeerScores {
+    pub fn new(params: PeerScoreParams, now: Instant) -> Self {
+        Self {
+            params,
+            peers: HashMap::new(),
+            last_decay: now,
+        }
+    }
+
+    pub fn params(&self) -> &PeerScoreParams {
+        &self.params
+    }
+
+    /// Records that `peer_id` joined the mesh of `kind`.
+    pub fn graft(&mut self, peer_id: PeerId, kind: GossipKind, now: Instant) {
+        let counters = self.topic_counters(peer_id, kind);
+        if counters.in_mesh_since.is_none() {
+            counters.in_mesh_since = Some(now);
+        }
+    }
+
+    /// Records that `peer_id` left the mesh of `kind`, penalising any shortfall of its
+    /// deliveries.
+    pub fn prune(&mut self, peer_id: &PeerId, kind: GossipKind, now: Instant) {
+        let params = match self.params.topics.get(&kind) {
+            Some(params) => params,
+            None => return,
+        };
+        let counters = match self
+            .peers
+            .get_mut(peer_id)
+            .and_then(|peer| peer.topics.get_mut(&kind))
+        {
+            Some(counters) => counters,
+            None => return,
+        };
+        if let Some(deficit) = mesh_deficit(params, counters, now) {
+            counters.mesh_failure_penalty += deficit * deficit;
+        }
+        counters.in_mesh_since = None;
+    }
+
+    /// Removes `peer_id` from every mesh, as it has disconnected. Its counters are kept.
+    pub fn disconnect(&mut self, peer_id: &PeerId, now: Instant) {
+        let kinds: Vec<GossipKind> = match self.peers.get(peer_id) {
+            Some(peer) => peer.topics.keys().cloned().collect(),
+            None => return,
+        };
+        for kind in kinds {
+            self.prune(peer_id, kind, now);
+        }
+    }
+
+    /// Records a valid message of `kind` from `peer_id`, `first` if no other peer delivered it
+    /// before. Only first deliveries are credited to the mesh, as when a duplicate arrived is
+    /// not known.
+    pub fn deliver(&mut self, peer_id: PeerId, kind: GossipKind, first: bool) {
+        let (first_cap, mesh_cap) = match self.params.topics.get(&kind) {
+            Some(params) => (
+                params.first_message_deliveries_cap,
+                params.mesh_message_deliveries_cap,
+            ),
+            None => return,
+        };
+        if !first {
+            return;
+        }
+        let counters = self.topic_counters(peer_id, kind);
+        counters.first_message_deliveries =
+            (counters.first_message_deliveries + 1.0).min(first_cap);
+        if counters.in_mesh_since.is_some() {
+            counters.mesh_message_deliveries =
+                (counters.mesh_message_deliveries + 1.0).min(mesh_cap);
+        }
+    }
+
+    /// Records an invalid message of `kind` from `peer_id`.
+    pub fn reject(&mut self, peer_id: PeerId, kind: GossipKind) {
+        if self.params.topics.contains_key(&kind) {
+            self.topic_counters(peer_id, kind)
+                .invalid_message_deliveries += 1.0;
+        }
+    }
+
+    /// Records misbehaviour of the protocol by `peer_id`, e.g. grafting during a backoff.
+    pub fn penalize(&mut self, peer_id: PeerId, count: f64) {
+        self.peers.entry(peer_id).or_default().behaviour_penalty += count;
+    }
+
+    /// Decays the counters once for each decay interval elapsed, forgetting peers whose counters
+    /// have all decayed to zero.
+    pub fn refresh(&mut self, now: Instant) {
+        while now >= self.last_decay + self.params.decay_interval {
+            self.last_decay += self.params.decay_interval;
+            let params = &self.params;
+            for peer in self.peers.values_mut() {
+                peer.behaviour_penalty =
+                    decay(peer.behaviour_penalty, params.behaviour_penalty_decay);
+                for (kind, counters) in &mut peer.topics {
+                    let topic = &params.topics[kind];
+                    counters.first_message_deliveries = decay(
+                        counters.first_message_deliveries,
+                        topic.first_message_deliveries_decay,
+                    );
+                    counters.mesh_message_deliveries = decay(
+                        counters.mesh_message_deliveries,
+                        topic.mesh_message_deliveries_decay,
+                    );
+                    counters.mesh_failure_penalty = decay(
+                        counters.mesh_failure_penalty,
+                        topic.mesh_failure_penalty_decay,
+                    );
+                    counters.invalid_message_deliveries = decay(
+                        counters.invalid_message_deliveries,
+                        topic.invalid_message_deliveries_decay,
+                    );
+                }
+            }
+        }
+        self.peers.retain(|_, peer| {
+            peer.behaviour_penalty > 0.0
+                || peer.topics.values().any(|counters| {
+                    counters.in_mesh_since.is_some()
+                        || counters.first_message_deliveries > 0.0
+                        || counters.mesh_message_deliveries > 0.0
+                        || counters.mesh_failure_penalty > 0.0
+                        || counters.invalid_message_deliveries > 0.0
+                })
+        });
+    }
+
+    /// Returns the score of `peer_id`, which is zero for unknown peers.
+    pub fn score(&self, peer_id: &PeerId, now: Instant) -> f64 {
+        let peer = match self.peers.get(peer_id) {
+            Some(peer) => peer,
+            None => return 0.0,
+        };
+        let mut score = 0.0;
+        for (kind, counters) in &peer.topics {
+            let params = &self.params.topics[kind];
+            let mut topic_score = 0.0;
+            if let Some(since) = counters.in_mesh_since {
+                let quanta = secs(now.duration_since(since)) / secs(params.time_in_mesh_quantum);
+                topic_score += params.time_in_mesh_weight * quanta.min(params.time_in_mesh_cap);
+            }
+            topic_score +=
+                params.first_message_deliveries_weight * counters.first_message_deliveries;
+            if let Some(deficit) = mesh_deficit(params, counters, now) {
+                topic_score += params.mesh_message_deliveries_weight * deficit * deficit;
+            }
+            topic_score += params.mesh_failure_penalty_weight * counters.mesh_failure_penalty;
+            topic_score += params.invalid_message_deliveries_weight
+                * counters.invalid_message_deliveries
+                * counters.invalid_message_deliveries;
+            score += topic_score * params.topic_weight;
+        }
+        score = score.min(self.params.topic_score_cap);
+
+        let excess = peer.behaviour_penalty - self.params.behaviour_penalty_threshold;
+        if excess > 0.0 {
+            score += self.params.behaviour_penalty_weight * excess * excess;
+        }
+        score
+    }
+
+    /// Returns the score of every peer with counters.
+    pub fn scores(&self, now: Instant) -> Vec<(PeerId, f64)> {
+        self.peers
+            .keys()
+            .map(|peer_id| (*peer_id, self.score(peer_id, now)))
+            .collect()
+    }
+
+    /// Returns the number of peers in the mesh of `kind`.
+    pub fn mesh_peers(&self, kind: GossipKind) -> usize {
+        self.peers
+            .values()
+            .filter(|peer| {
+                peer.topics
+                    .get(&kind)
+                    .map_or(false, |counters| counters.in_mesh_since.is_some())
+            })
+            .count()
+    }
+
+    fn topic_counters(&mut self, peer_id: PeerId, kind: GossipKind) -> &mut TopicCounters {
+        self.peers
+            .entry(peer_id)
+            .or_default()
+            .topics
+            .entry(kind)
+            .or_default()
+    }
+}
+
+/// Returns how far the mesh deliveries of a peer fall short of the threshold, if it has been in
+/// the mesh long enough for deliveries to be expected.
+fn mesh_deficit(params: &TopicScoreParams, counters: &TopicCounters, now: Instant) -> Option<f64> {
+    let since = counters.in_mesh_since?;
+    if params.mesh_message_deliveries_weight == 0.0
+        || now.duration_since(since) < params.mesh_message_deliveries_activation
+        || counters.mesh_message_deliveries >= params.mesh_message_deliveries_threshold
+    {
+        return None;
+    }
+    Some(params.mesh_message_deliveries_threshold - counters.mesh_message_deliveries)
+}
+
+fn decay(value: f64, decay: f64) -> f64 {
+    let value = value * decay;
+    if value < DECAY_TO_ZERO {
+        0.0
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::enr::NodeId;
+    use super::*;
+
+    fn scores(now: Instant) -> PeerScores {
+        PeerScores::new(
+            PeerScoreParams::standard(Duration::from_secs(12), 32, 16_384),
+            now,
+        )
+    }
+
+    #[test]
+    fn test_standard_params() {
+        let params = PeerScoreParams::standard(Duration::from_secs(12), 32, 16_384);
+        assert_eq!(params.topics.len(), 5 + ATTESTATION_SUBNET_COUNT);
+        assert_eq!(params.decay_interval, Duration::from_secs(12));
+        assert!(params.behaviour_penalty_weight < 0.0);
+        for topic in params.topics.values() {
+            assert!(topic.first_message_deliveries_cap > 0.0);
+            assert!(topic.first_message_deliveries_decay < 1.0);
+            assert!(topic.invalid_message_deliveries_weight < 0.0);
+            assert!(topic.mesh_message_deliveries_weight <= 0.0);
+        }
+        let block = &params.topics[&GossipKind::BeaconBlock];
+        assert!(block.mesh_message_deliveries_threshold > 0.0);
+        assert_eq!(
+            params.topics[&GossipKind::VoluntaryExit].mesh_message_deliveries_weight,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_deliveries_earn_credit() {
+        let now = Instant::now();
+        let mut scores = scores(now);
+        let peer_id = NodeId::random();
+        assert_eq!(scores.score(&peer_id, now), 0.0);
+
+        scores.graft(peer_id, GossipKind::BeaconBlock, now);
+        scores.deliver(peer_id, GossipKind::BeaconBlock, true);
+        scores.deliver(peer_id, GossipKind::BeaconBlock, false);
+        let score = scores.score(&peer_id, now + Duration::from_secs(60));
+        assert!(score > 0.0);
+        assert!(score <= scores.params().topic_score_cap);
+    }
+
+    #[test]
+    fn test_invalid_messages_reach_graylist() {
+        let now = Instant::now();
+        let mut scores = scores(now);
+        let peer_id = NodeId::random();
+        for _ in 0..20 {
+            scores.reject(peer_id, GossipKind::BeaconAggregateAndProof);
+        }
+        assert!(scores.score(&peer_id, now) < GRAYLIST_THRESHOLD);
+        assert_eq!(scores.scores(now).len(), 1);
+
+        /*
+         * The penalty decays away, and the peer is then forgotten.
+         */
+        let later = now + Duration::from_secs(12 * 32 * 150);
+        scores.refresh(later);
+        assert!(scores.scores(later).is_empty());
+    }
+
+    #[test]
+    fn test_silent_mesh_peers_are_penalised() {
+        let now = Instant::now();
+        let mut scores = scores(now);
+        let (silent, busy) = (NodeId::random(), NodeId::random());
+        for peer_id in &[silent, busy] {
+            scores.graft(*peer_id, GossipKind::BeaconBlock, now);
+        }
+        let activation =
+            scores.params().topics[&GossipKind::BeaconBlock].mesh_message_deliveries_activation;
+        for _ in 0..10 {
+            scores.deliver(busy, GossipKind::BeaconBlock, true);
+        }
+
+        let later = now + activation;
+        assert!(scores.score(&silent, later) < 0.0);
+        assert!(scores.score(&busy, later) > 0.0);
+
+        /*
+         * The shortfall remains as a penalty once the peer leaves the mesh.
+         */
+        assert_eq!(scores.mesh_peers(GossipKind::BeaconBlock), 2);
+        scores.disconnect(&silent, later);
+        assert!(scores.score(&silent, later) < 0.0);
+        assert_eq!(scores.mesh_peers(GossipKind::BeaconBlock), 1);
+        assert_eq!(scores.mesh_peers(GossipKind::Attestation(0)), 0);
+    }
+
+    #[test]
+    fn test_behaviour_penalty_threshold() {
+        let now = Instant::now();
+        let mut scores = scores(now);
+        let peer_id = NodeId::random();
+        scores.penalize(peer_id, 6.0);
+        assert_eq!(scores.score(&peer_id, now), 0.0);
+        scores.penalize(peer_id, 4.0);
+        assert!(scores.score(&peer_id, now) < 0.0);
+    }
+}
@@ -0,0 +1,376 @@
+use super::super::hashing::canonical_hash;
+use super::super::metrics;
+use super::super::ssz::{Decodable, DecodeError, Encodable, SszStream};
+use super::super::types::Hash256;
+use lighthouse_metrics::inc_counter;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// How long a message is remembered. Gossip older than this is rejected on other grounds (e.g.,
+/// its slot is too far in the past), so need not be deduplicated.
+pub const SEEN_TTL: Duration = Duration::from_secs(6 * 64);
+
+/// Identifies a gossip message by its content, regardless of the peer which relayed it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MessageId([u8; 20]);
+
+impl MessageId {
+    /// Derives the id of a message from its data, as received on the wire.
+    pub fn new(data: &[u8]) -> Self {
+        let mut id = [0; 20];
+        id.copy_from_slice(&canonical_hash(data)[..20]);
+        MessageId(id)
+    }
+}
+
+impl Encodable for MessageId {
+    fn ssz_append(&self, s: &mut SszStream) {
+        for byte in &self.0 {
+            s.append(byte);
+        }
+    }
+}
+
+impl Decodable for MessageId {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let slice = bytes.get(i..i + 20).ok_or(DecodeError::TooShort)?;
+        let mut id = [0; 20];
+        id.copy_from_slice(slice);
+        Ok((MessageId(id), i + 20))
+    }
+}
+
+/// A set of keys which forgets each key `ttl` after it was first observed.
+pub struct SeenCache<K> {
+    ttl: Duration,
+    seen: HashMap<K, Instant>,
+    /// Keys in the order they were observed, so expired keys can be found without a scan.
+    order: VecDeque<(Instant, K)>,
+}
+
+impl<K: Clone + Eq + Hash> SeenCache<K> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Records `key`, returning `true` if it had not been observed within the ttl.
+    pub fn observe(&mut self, key: K, now: Instant) -> bool {
+        self.prune(now);
+        if self.seen.contains_key(&key) {
+            return false;
+        }
+        self.seen.insert(key.clone(), now);
+        self.order.push_back((now, key));
+        true
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.seen.contains_key(key)
+    }
+
+    /// Returns the keys with the instants they were observed, earliest first.
+    pub fn observed(&self) -> Vec<(K, Instant)> {
+        self.order
+            .iter()
+            .map(|(observed, key)| (key.clone(), *observed))
+            .collect()
+    }
+
+    /// Records `key` as observed at `observed`, e.g. when restoring a cache, returning `true` if
+    /// it had not been observed.
+    ///
+    /// Keys must be inserted in the order they were observed, and before any later key is
+    /// observed, or they may be remembered for longer than the ttl.
+    pub fn insert_observed(&mut self, key: K, observed: Instant) -> bool {
+        if self.seen.contains_key(&key) {
+            return false;
+        }
+        self.seen.insert(key.clone(), observed);
+        self.order.push_back((observed, key));
+        true
+    }
+
+    /// Forgets all keys observed more than the ttl before `now`.
+    pub fn prune(&mut self, now: Instant) {
+        while let Some(&(observed, _)) = self.order.front() {
+            if now.duration_since(observed) < self.ttl {
+                break;
+            }
+            if let Some((_, key)) = self.order.pop_front() {
+                self.seen.remove(&key);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+/// The result of observing a gossip block.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockObservation {
+    /// No block has been seen from the proposer for the slot.
+    New,
+    /// The same block, or a block whose root was not remembered across a restart, has been seen.
+    Duplicate,
+    /// The proposer has already published another block, with the given root, for the slot.
+    Equivocation(Hash256),
+}
+
+/// Drops duplicate gossip before it reaches signature verification.
+///
+/// Besides exact duplicates, a message is a duplicate if another message for the same duty has
+/// already been seen: a block from the same proposer for the same slot, or an attestation from
+/// the same validator for the same target. Only the first such message is propagated; any
+/// equivocation is left to the slashing machinery.
+pub struct DuplicateFilter {
+    pub(super) messages: SeenCache<MessageId>,
+    /// Keyed by `(proposer_index, slot)`.
+    pub(super) block_proposals: SeenCache<(u64, u64)>,
+    /// The root of the first block of each of the `block_proposals`.
+    block_roots: HashMap<(u64, u64), Hash256>,
+    /// Keyed by `(validator_index, target_epoch)`.
+    pub(super) attestations: SeenCache<(u64, u64)>,
+}
+
+impl DuplicateFilter {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            messages: SeenCache::new(ttl),
+            block_proposals: SeenCache::new(ttl),
+            block_roots: HashMap::new(),
+            attestations: SeenCache::new(ttl),
+        }
+    }
+
+    /// Returns `true` if the message data has not been seen before.
+    pub fn observe_message(&mut self, data: &[u8], now: Instant) -> bool {
+        count_duplicate(self.messages.observe(MessageId::new(data), now))
+    }
+
+    /// Returns `true` if no block has been seen from `proposer_index` for `slot`.
+    pub fn observe_block_proposal(&mut self, proposer_index: u64, slot: u64, now: Instant) -> bool {
+        count_duplicate(self.block_proposals.observe((proposer_index, slot), now))
+    }
+
+    /// Records the block with `root` from `proposer_index` for `slot`, detecting equivocations
+    /// by comparing it with the first block seen from the proposer for the slot.
+    pub fn observe_block(
+        &mut self,
+        proposer_index: u64,
+        slot: u64,
+        root: Hash256,
+        now: Instant,
+    ) -> BlockObservation {
+        let key = (proposer_index, slot);
+        if count_duplicate(self.block_proposals.observe(key, now)) {
+            let proposals = &self.block_proposals;
+            self.block_roots.retain(|key, _| proposals.contains(key));
+            self.block_roots.insert(key, root);
+            return BlockObservation::New;
+        }
+        self.seen_block(key, root)
+    }
+
+    /// Returns what `observe_block` would, without recording the block, e.g. so that a block is
+    /// only recorded once it is imported.
+    pub fn check_block(
+        &mut self,
+        proposer_index: u64,
+        slot: u64,
+        root: Hash256,
+        now: Instant,
+    ) -> BlockObservation {
+        self.prune(now);
+        let key = (proposer_index, slot);
+        if count_duplicate(!self.block_proposals.contains(&key)) {
+            return BlockObservation::New;
+        }
+        self.seen_block(key, root)
+    }
+
+    /// Compares the block with `root` with the first block seen for the proposal `key`.
+    fn seen_block(&self, key: (u64, u64), root: Hash256) -> BlockObservation {
+        match self.block_roots.get(&key) {
+            Some(first) if *first != root => BlockObservation::Equivocation(*first),
+            _ => BlockObservation::Duplicate,
+        }
+    }
+
+    /// Returns `true` if no attestation has been seen from `validator_index` for `target_epoch`.
+    pub fn observe_attestation(
+        &mut self,
+        validator_index: u64,
+        target_epoch: u64,
+        now: Instant,
+    ) -> bool {
+        count_duplicate(
+            self.attestations
+                .observe((validator_index, target_epoch), now),
+        )
+    }
+
+    pub fn prune(&mut self, now: Instant) {
+        self.messages.prune(now);
+        self.block_proposals.prune(now);
+        let proposals = &self.block_proposals;
+        self.block_roots.retain(|key, _| proposals.contains(key));
+        self.attestations.prune(now);
+    }
+}
+
+/// Passes through the result of an observation, counting duplicates.
+fn count_duplicate(is_new: bool) -> bool {
+    if !is_new {
+        inc_counter(&metrics::GOSSIP_DUPLICATES);
+    }
+    is_new
+}
+
+impl Default for DuplicateFilter {
+    fn default() -> Self {
+        Self::new(SEEN_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seen_cache_expiry() {
+        let now = Instant::now();
+        let ttl = Duration::from_secs(10);
+        let mut cache = SeenCache::new(ttl);
+
+        assert!(cache.observe(1, now));
+        assert!(!cache.observe(1, now + Duration::from_secs(5)));
+        assert!(cache.observe(2, now + Duration::from_secs(5)));
+        assert_eq!(cache.len(), 2);
+
+        /*
+         * Re-observing a key does not extend its lifetime.
+         */
+        cache.prune(now + ttl);
+        assert!(!cache.contains(&1));
+        assert!(cache.contains(&2));
+        assert!(cache.observe(1, now + ttl));
+
+        cache.prune(now + ttl * 3);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_filter() {
+        let now = Instant::now();
+        let mut filter = DuplicateFilter::default();
+
+        assert!(filter.observe_message(b"block", now));
+        assert!(!filter.observe_message(b"block", now));
+        assert!(filter.observe_message(b"other block", now));
+
+        assert!(filter.observe_block_proposal(3, 100, now));
+        assert!(!filter.observe_block_proposal(3, 100, now));
+        assert!(filter.observe_block_proposal(3, 101, now));
+
+        assert!(filter.observe_attestation(7, 2, now));
+        assert!(!filter.observe_attestation(7, 2, now));
+        assert!(filter.observe_attestation(8, 2, now));
+
+        filter.prune(now + SEEN_TTL);
+        assert!(filter.observe_message(b"block", now + SEEN_TTL));
+        assert!(filter.observe_block_proposal(3, 100, now + SEEN_TTL));
+    }
+
+    #[test]
+    fn test_observe_block() {
+        let now = Instant::now();
+        let mut filter = DuplicateFilter::default();
+        let (first, second) = (Hash256::from(1), Hash256::from(2));
+
+        assert_eq!(
+            filter.check_block(3, 100, first, now),
+            BlockObservation::New
+        );
+        assert_eq!(
+            filter.observe_block(3, 100, first, now),
+            BlockObservation::New
+        );
+        assert_eq!(
+            filter.observe_block(3, 100, first, now),
+            BlockObservation::Duplicate
+        );
+        assert_eq!(
+            filter.check_block(3, 100, second, now),
+            BlockObservation::Equivocation(first)
+        );
+        assert_eq!(
+            filter.observe_block(3, 100, second, now),
+            BlockObservation::Equivocation(first)
+        );
+        assert_eq!(
+            filter.observe_block(3, 101, second, now),
+            BlockObservation::New
+        );
+
+        /*
+         * A proposal observed without its root, e.g. when restored, cannot be compared.
+         */
+        assert!(filter.observe_block_proposal(4, 100, now));
+        assert_eq!(
+            filter.observe_block(4, 100, first, now),
+            BlockObservation::Duplicate
+        );
+
+        filter.prune(now + SEEN_TTL);
+        assert!(filter.block_roots.is_empty());
+        assert_eq!(
+            filter.observe_block(3, 100, second, now + SEEN_TTL),
+            BlockObservation::New
+        );
+    }
+
+    #[test]
+    fn test_message_id() {
+        assert_eq!(MessageId::new(b"a"), MessageId::new(b"a"));
+        assert_ne!(MessageId::new(b"a"), MessageId::new(b"b"));
+
+        let id = MessageId::new(b"a");
+        let mut s = SszStream::new();
+        s.append(&id);
+        let bytes = s.drain();
+        assert_eq!(MessageId::ssz_decode(&bytes, 0), Ok((id, 20)));
+        assert_eq!(
+            MessageId::ssz_decode(&bytes[..19], 0),
+            Err(DecodeError::TooShort)
+        );
+    }
+
+    #[test]
+    fn test_insert_observed() {
+        let now = Instant::now();
+        let ttl = Duration::from_secs(10);
+        let mut cache = SeenCache::new(ttl);
+
+        assert!(cache.insert_observed(1, now));
+        assert!(cache.insert_observed(2, now + Duration::from_secs(2)));
+        assert!(!cache.insert_observed(1, now + Duration::from_secs(3)));
+        assert_eq!(
+            cache.observed(),
+            vec![(1, now), (2, now + Duration::from_secs(2))]
+        );
+
+        cache.prune(now + ttl);
+        assert_eq!(cache.observed(), vec![(2, now + Duration::from_secs(2))]);
+    }
+}
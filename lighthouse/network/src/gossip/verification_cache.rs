@@ -0,0 +1,147 @@
+use super::super::bls::Signature;
+use super::super::hashing::canonical_hash;
+use super::super::metrics;
+use super::super::ssz::ssz_encode;
+use super::super::types::AttestationData;
+use super::seen_cache::MessageId;
+use lighthouse_metrics::inc_counter;
+use std::collections::{BTreeMap, HashSet};
+
+/// Remembers the attestation data and selection proofs of aggregates which have passed
+/// verification, by slot, so that the same aggregate relayed by several peers, or the many
+/// aggregates of the same data, are only verified once.
+///
+/// Only successes are remembered: data attesting to an unknown block may become valid once the
+/// block is imported.
+#[derive(Default)]
+pub struct VerificationCache {
+    /// The ids of the verified attestation data of each slot.
+    data: BTreeMap<u64, HashSet<MessageId>>,
+    /// Keyed by `(shard, aggregator_index, proof_id)` for each slot.
+    selection_proofs: BTreeMap<u64, HashSet<(u64, u64, MessageId)>>,
+}
+
+impl VerificationCache {
+    /// Returns `true` if `data` has already been verified.
+    pub fn is_data_verified(&self, data: &AttestationData) -> bool {
+        let hit = match self.data.get(&data.slot) {
+            Some(ids) => ids.contains(&data_id(data)),
+            None => false,
+        };
+        count_hit(hit)
+    }
+
+    pub fn insert_data(&mut self, data: &AttestationData) {
+        self.data
+            .entry(data.slot)
+            .or_default()
+            .insert(data_id(data));
+    }
+
+    /// Returns `true` if `selection_proof` has already been verified as selecting
+    /// `aggregator_index` to aggregate for the committee of `data`.
+    pub fn is_selection_proof_verified(
+        &self,
+        data: &AttestationData,
+        aggregator_index: u64,
+        selection_proof: &Signature,
+    ) -> bool {
+        let key = (
+            data.shard,
+            aggregator_index,
+            MessageId::new(&selection_proof.as_bytes()),
+        );
+        let hit = match self.selection_proofs.get(&data.slot) {
+            Some(keys) => keys.contains(&key),
+            None => false,
+        };
+        count_hit(hit)
+    }
+
+    pub fn insert_selection_proof(
+        &mut self,
+        data: &AttestationData,
+        aggregator_index: u64,
+        selection_proof: &Signature,
+    ) {
+        self.selection_proofs.entry(data.slot).or_default().insert((
+            data.shard,
+            aggregator_index,
+            MessageId::new(&selection_proof.as_bytes()),
+        ));
+    }
+
+    /// Forgets the slots before `earliest_slot`, whose aggregates are rejected as too old.
+    pub fn prune(&mut self, earliest_slot: u64) {
+        self.data = self.data.split_off(&earliest_slot);
+        self.selection_proofs = self.selection_proofs.split_off(&earliest_slot);
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.values().map(HashSet::len).sum::<usize>()
+            + self
+                .selection_proofs
+                .values()
+                .map(HashSet::len)
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn data_id(data: &AttestationData) -> MessageId {
+    MessageId::new(&canonical_hash(&ssz_encode(data)))
+}
+
+/// Passes through the result of a lookup, counting hits.
+fn count_hit(hit: bool) -> bool {
+    if hit {
+        inc_counter(&metrics::AGGREGATE_VERIFICATION_CACHE_HITS);
+    }
+    hit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::bls::Keypair;
+    use super::*;
+
+    #[test]
+    fn test_verification_cache() {
+        let mut cache = VerificationCache::default();
+        let data = AttestationData {
+            slot: 3,
+            shard: 1,
+            ..AttestationData::zero()
+        };
+        let proof = Signature::new(&[3], &Keypair::random().sk);
+
+        assert!(!cache.is_data_verified(&data));
+        cache.insert_data(&data);
+        assert!(cache.is_data_verified(&data));
+        let other_shard = AttestationData {
+            shard: 0,
+            ..data.clone()
+        };
+        assert!(!cache.is_data_verified(&other_shard));
+
+        assert!(!cache.is_selection_proof_verified(&data, 5, &proof));
+        cache.insert_selection_proof(&data, 5, &proof);
+        assert!(cache.is_selection_proof_verified(&data, 5, &proof));
+        assert!(!cache.is_selection_proof_verified(&data, 6, &proof));
+        assert!(!cache.is_selection_proof_verified(&other_shard, 5, &proof));
+        assert_eq!(cache.len(), 2);
+
+        /*
+         * Slots before the earliest are forgotten.
+         */
+        cache.prune(3);
+        assert!(cache.is_data_verified(&data));
+        cache.prune(4);
+        assert!(!cache.is_data_verified(&data));
+        assert!(!cache.is_selection_proof_verified(&data, 5, &proof));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+extern crate bls;
+extern crate db;
+extern crate hashing;
+#[macro_use]
+extern crate lazy_static;
+extern crate lighthouse_metrics;
+extern crate rand;
+#[macro_use]
+extern crate slog;
+extern crate snap;
+extern crate ssz;
+extern crate ssz_helpers;
+extern crate task_executor;
+extern crate types;
+
+pub mod beacon_processor;
+pub mod boot_node;
+pub mod config;
+pub mod discovery;
+pub mod enr;
+pub mod gossip;
+pub mod local_enr;
+pub mod metadata;
+mod metrics;
+pub mod peer_manager;
+pub mod rpc;
+pub mod service;
+pub mod status;
+pub mod sync;
+mod time;
+pub mod upnp;
+
+pub use beacon_processor::{
+    BeaconProcessor, BeaconProcessorConfig, BeaconProcessorService, Work, WorkType,
+};
+pub use boot_node::{BootNode, BootNodeConfig, BootNodeError};
+pub use config::NetworkConfig;
+pub use discovery::{Discovery, DiscoveryConfig, DiscoveryEvent, DiscoveryService};
+pub use enr::{Enr, NodeId};
+pub use local_enr::{EnrConfig, LocalEnr};
+pub use metadata::{MetaDataEvent, MetaDataManager};
+pub use peer_manager::{
+    Cidr, ConnectionDirection, PeerAction, PeerManager, PeerManagerConfig, PeerManagerEvent,
+    PeerPersistenceError, PeerSyncStatus,
+};
+pub use rpc::{GoodbyeReason, RPCEvent, RPCRequest, RPCResponse, RPC};
+pub use service::{NetworkError, NetworkService};
+pub use status::{Handshake, HandshakeEvent, IncompatibleReason};
+pub use sync::{
+    BackfillEvent, BackfillSync, BatchProcessResult, ImportQueue, ParentLookup, ParentLookupEvent,
+    RangeSync, SyncEvent, SyncState,
+};
+pub use upnp::{UPnPConfig, UPnPEvent, UPnPService};
@@ -0,0 +1,220 @@
+use super::bls::Keypair;
+use super::db::stores::PeerStore;
+use super::db::{ClientDB, DBError};
+use super::enr::{Enr, ATTESTATION_SUBNET_COUNT};
+use super::rpc::ForkDigest;
+use super::ssz::{ssz_encode, Decodable};
+use super::types::Bitfield;
+use std::net::Ipv4Addr;
+
+#[derive(Debug, PartialEq)]
+pub enum LocalEnrError {
+    DBError(String),
+    DecodeError,
+}
+
+impl From<DBError> for LocalEnrError {
+    fn from(error: DBError) -> Self {
+        LocalEnrError::DBError(error.message)
+    }
+}
+
+/// The fields of the local node's record which are chosen by the node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnrConfig {
+    pub ip: Option<Ipv4Addr>,
+    pub tcp: Option<u16>,
+    pub udp: Option<u16>,
+    pub fork_digest: ForkDigest,
+}
+
+/// The record of the local node, together with the keypair used to sign it.
+///
+/// The record is persisted whenever it changes, so its `seq` increases monotonically across
+/// restarts and remote nodes never discard a new record in favour of a stale one.
+pub struct LocalEnr {
+    keypair: Keypair,
+    enr: Enr,
+}
+
+impl LocalEnr {
+    /// Builds the local record from `config`, reusing the `seq` of the record in `store` if one
+    /// was persisted for the same `keypair`.
+    ///
+    /// Subnet subscriptions are not restored, as they are re-established at runtime.
+    pub fn load_or_build<T: ClientDB>(
+        keypair: Keypair,
+        config: &EnrConfig,
+        store: &PeerStore<T>,
+    ) -> Result<Self, LocalEnrError> {
+        let persisted = match store.get_serialized_local_enr()? {
+            Some(ssz) => {
+                let (enr, _) = Enr::ssz_decode(&ssz, 0).map_err(|_| LocalEnrError::DecodeError)?;
+                Some(enr)
+            }
+            None => None,
+        };
+
+        let mut enr = match persisted {
+            Some(ref enr) if *enr.public_key() == keypair.pk && enr.verify() => enr.clone(),
+            _ => Enr::new(&keypair, config.ip, config.tcp, config.udp),
+        };
+        enr.set_ip(config.ip, &keypair);
+        enr.set_tcp(config.tcp, &keypair);
+        enr.set_udp(config.udp, &keypair);
+        enr.set_fork_digest(config.fork_digest, &keypair);
+        enr.set_attnets(
+            Bitfield::from_elem(ATTESTATION_SUBNET_COUNT, false),
+            &keypair,
+        );
+
+        let local = LocalEnr { keypair, enr };
+        local.persist(store)?;
+        Ok(local)
+    }
+
+    pub fn enr(&self) -> &Enr {
+        &self.enr
+    }
+
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+
+    /// Updates the advertised address to one observed externally, e.g. reported by peers or a
+    /// UPnP gateway, together with the ports reachable there. Ports which are `None` are left
+    /// unchanged.
+    ///
+    /// Returns `true` if the record changed, in which case it has been re-signed and persisted
+    /// and should be pushed to discovery.
+    pub fn set_external_address<T: ClientDB>(
+        &mut self,
+        ip: Ipv4Addr,
+        tcp_port: Option<u16>,
+        udp_port: Option<u16>,
+        store: &PeerStore<T>,
+    ) -> Result<bool, LocalEnrError> {
+        let mut changed = self.enr.set_ip(Some(ip), &self.keypair);
+        if let Some(port) = tcp_port {
+            changed |= self.enr.set_tcp(Some(port), &self.keypair);
+        }
+        if let Some(port) = udp_port {
+            changed |= self.enr.set_udp(Some(port), &self.keypair);
+        }
+        self.persist_if(changed, store)
+    }
+
+    pub fn set_fork_digest<T: ClientDB>(
+        &mut self,
+        fork_digest: ForkDigest,
+        store: &PeerStore<T>,
+    ) -> Result<bool, LocalEnrError> {
+        let changed = self.enr.set_fork_digest(fork_digest, &self.keypair);
+        self.persist_if(changed, store)
+    }
+
+    /// Sets whether the node advertises a subscription to the given attestation subnet.
+    pub fn set_subnet<T: ClientDB>(
+        &mut self,
+        subnet_id: u64,
+        subscribed: bool,
+        store: &PeerStore<T>,
+    ) -> Result<bool, LocalEnrError> {
+        let changed = self.enr.set_subnet(subnet_id, subscribed, &self.keypair);
+        self.persist_if(changed, store)
+    }
+
+    fn persist_if<T: ClientDB>(
+        &self,
+        changed: bool,
+        store: &PeerStore<T>,
+    ) -> Result<bool, LocalEnrError> {
+        if changed {
+            self.persist(store)?;
+        }
+        Ok(changed)
+    }
+
+    fn persist<T: ClientDB>(&self, store: &PeerStore<T>) -> Result<(), LocalEnrError> {
+        store.put_serialized_local_enr(&ssz_encode(&self.enr))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::db::MemoryDB;
+    use super::*;
+    use std::sync::Arc;
+
+    fn config() -> EnrConfig {
+        EnrConfig {
+            ip: Some(Ipv4Addr::new(10, 0, 0, 1)),
+            tcp: Some(9000),
+            udp: Some(9000),
+            fork_digest: ForkDigest([1, 2, 3, 4]),
+        }
+    }
+
+    #[test]
+    fn test_build_and_persist() {
+        let store = PeerStore::new(Arc::new(MemoryDB::open()));
+        let keypair = Keypair::random();
+
+        let local = LocalEnr::load_or_build(keypair.clone(), &config(), &store).unwrap();
+        let seq = local.enr().seq();
+        assert!(local.enr().verify());
+        assert_eq!(local.enr().fork_digest(), ForkDigest([1, 2, 3, 4]));
+
+        /*
+         * Restarting with the same config keeps the record, while a changed config produces a
+         * record with a greater `seq`.
+         */
+        let local = LocalEnr::load_or_build(keypair.clone(), &config(), &store).unwrap();
+        assert_eq!(local.enr().seq(), seq);
+
+        let mut changed = config();
+        changed.tcp = Some(9100);
+        let local = LocalEnr::load_or_build(keypair.clone(), &changed, &store).unwrap();
+        assert!(local.enr().seq() > seq);
+        assert_eq!(local.enr().tcp(), Some(9100));
+
+        /*
+         * A record persisted for a different key is replaced by a new record.
+         */
+        let other = Keypair::random();
+        let local = LocalEnr::load_or_build(other.clone(), &config(), &store).unwrap();
+        assert_eq!(*local.enr().public_key(), other.pk);
+        assert_eq!(local.enr().seq(), seq);
+    }
+
+    #[test]
+    fn test_runtime_updates() {
+        let store = PeerStore::new(Arc::new(MemoryDB::open()));
+        let keypair = Keypair::random();
+        let mut local = LocalEnr::load_or_build(keypair.clone(), &config(), &store).unwrap();
+        let seq = local.enr().seq();
+
+        let ip = Ipv4Addr::new(1, 2, 3, 4);
+        let set = |local: &mut LocalEnr, tcp, udp| local.set_external_address(ip, tcp, udp, &store);
+        assert_eq!(set(&mut local, Some(9000), Some(9001)), Ok(true));
+        assert_eq!(set(&mut local, Some(9000), Some(9001)), Ok(false));
+        assert_eq!(local.set_subnet(7, true, &store), Ok(true));
+        assert_eq!(local.enr().seq(), seq + 2);
+        assert_eq!(
+            local.enr().tcp_socket(),
+            Some("1.2.3.4:9000".parse().unwrap())
+        );
+        assert_eq!(local.enr().udp(), Some(9001));
+
+        /*
+         * A port which was not mapped keeps its configured value.
+         */
+        assert_eq!(set(&mut local, Some(9002), None), Ok(true));
+        assert_eq!(local.enr().tcp(), Some(9002));
+        assert_eq!(local.enr().udp(), Some(9001));
+
+        let restored = LocalEnr::load_or_build(keypair, &config(), &store).unwrap();
+        assert!(restored.enr().seq() > local.enr().seq());
+    }
+}
@@ -0,0 +1,341 @@
+use super::rpc::{MetaData, PeerId, Ping, RPCEvent, RPCRequest, RPCResponse, RequestId, RPC};
+use super::types::Bitfield;
+use slog::Logger;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// The interval at which each connected peer is pinged.
+pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq)]
+pub enum MetaDataEvent {
+    /// A newer `MetaData` has been received from a peer.
+    Updated { peer_id: PeerId, metadata: MetaData },
+    /// A peer failed to respond to a ping or metadata request.
+    Unresponsive { peer_id: PeerId },
+}
+
+struct PeerMetaData {
+    metadata: Option<MetaData>,
+    last_ping: Instant,
+    /// The outstanding metadata request, if any, so at most one is made at a time.
+    metadata_request: Option<RequestId>,
+}
+
+/// Keeps the `MetaData` of connected peers up to date, and serves our own `MetaData`.
+///
+/// Each peer is pinged every `PING_INTERVAL`. Pings and their responses carry the sender's
+/// `MetaData` sequence number, and the sender's `MetaData` is requested whenever the sequence
+/// number is greater than that of the last `MetaData` received.
+pub struct MetaDataManager {
+    local: MetaData,
+    peers: HashMap<PeerId, PeerMetaData>,
+    /// Ping requests we have made which have not yet completed.
+    pings: HashSet<(PeerId, RequestId)>,
+    events: VecDeque<MetaDataEvent>,
+    log: Logger,
+}
+
+impl MetaDataManager {
+    pub fn new(local: MetaData, log: Logger) -> Self {
+        Self {
+            local,
+            peers: HashMap::new(),
+            pings: HashSet::new(),
+            events: VecDeque::new(),
+            log,
+        }
+    }
+
+    pub fn local(&self) -> &MetaData {
+        &self.local
+    }
+
+    /// Updates our attestation subnets, incrementing our sequence number if they have changed.
+    pub fn set_attnets(&mut self, attnets: Bitfield) {
+        if self.local.attnets != attnets {
+            self.local.attnets = attnets;
+            self.local.seq_number += 1;
+        }
+    }
+
+    /// Returns the latest `MetaData` received from `peer_id`, if any.
+    pub fn peer_metadata(&self, peer_id: &PeerId) -> Option<&MetaData> {
+        self.peers
+            .get(peer_id)
+            .and_then(|peer| peer.metadata.as_ref())
+    }
+
+    /// Begins tracking a newly connected peer, requesting its `MetaData`.
+    pub fn on_connect(&mut self, rpc: &mut RPC, peer_id: PeerId, now: Instant) {
+        let id = rpc.send_request(peer_id, RPCRequest::MetaData, now);
+        self.peers.insert(
+            peer_id,
+            PeerMetaData {
+                metadata: None,
+                last_ping: now,
+                metadata_request: Some(id),
+            },
+        );
+    }
+
+    pub fn on_disconnect(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+        self.pings.retain(|(peer, _)| peer != peer_id);
+    }
+
+    /// Pings each peer which has not been pinged within `PING_INTERVAL`.
+    pub fn heartbeat(&mut self, rpc: &mut RPC, now: Instant) {
+        let ping = Ping {
+            seq_number: self.local.seq_number,
+        };
+        for (peer_id, peer) in &mut self.peers {
+            if now.duration_since(peer.last_ping) >= PING_INTERVAL {
+                peer.last_ping = now;
+                let id = rpc.send_request(*peer_id, RPCRequest::Ping(ping), now);
+                self.pings.insert((*peer_id, id));
+            }
+        }
+    }
+
+    /// Processes the ping and metadata `RPCEvent`s, responding to requests as required.
+    ///
+    /// Events unrelated to ping or metadata are returned to the caller.
+    pub fn on_rpc_event(
+        &mut self,
+        rpc: &mut RPC,
+        event: RPCEvent,
+        now: Instant,
+    ) -> Option<RPCEvent> {
+        match event {
+            RPCEvent::Request {
+                peer_id,
+                id,
+                request: RPCRequest::Ping(ping),
+            } => {
+                let pong = Ping {
+                    seq_number: self.local.seq_number,
+                };
+                rpc.send_response(peer_id, id, &RPCResponse::Pong(pong));
+                rpc.end_response(peer_id, id);
+                self.on_seq_number(rpc, peer_id, ping.seq_number, now);
+                None
+            }
+            RPCEvent::Request {
+                peer_id,
+                id,
+                request: RPCRequest::MetaData,
+            } => {
+                rpc.send_response(peer_id, id, &RPCResponse::MetaData(self.local.clone()));
+                rpc.end_response(peer_id, id);
+                None
+            }
+            RPCEvent::Response {
+                peer_id,
+                response: RPCResponse::Pong(pong),
+                ..
+            } => {
+                self.on_seq_number(rpc, peer_id, pong.seq_number, now);
+                None
+            }
+            RPCEvent::Response {
+                peer_id,
+                response: RPCResponse::MetaData(metadata),
+                ..
+            } => {
+                self.on_metadata(peer_id, metadata);
+                None
+            }
+            RPCEvent::ResponseComplete { peer_id, id } if self.is_own_request(peer_id, id) => {
+                self.pings.remove(&(peer_id, id));
+                self.clear_metadata_request(peer_id, id);
+                None
+            }
+            RPCEvent::RequestFailed { peer_id, id, error } if self.is_own_request(peer_id, id) => {
+                debug!(self.log, "Ping or metadata request failed";
+                       "peer_id" => format!("{:?}", peer_id),
+                       "error" => format!("{:?}", error));
+                self.pings.remove(&(peer_id, id));
+                self.clear_metadata_request(peer_id, id);
+                self.events
+                    .push_back(MetaDataEvent::Unresponsive { peer_id });
+                None
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Returns the next event, if any.
+    pub fn poll(&mut self) -> Option<MetaDataEvent> {
+        self.events.pop_front()
+    }
+
+    /// Requests the `MetaData` of `peer_id` if `seq_number` is newer than the last received.
+    fn on_seq_number(&mut self, rpc: &mut RPC, peer_id: PeerId, seq_number: u64, now: Instant) {
+        let peer = match self.peers.get_mut(&peer_id) {
+            Some(peer) => peer,
+            None => return,
+        };
+        let outdated = match peer.metadata {
+            Some(ref metadata) => seq_number > metadata.seq_number,
+            None => true,
+        };
+        if outdated && peer.metadata_request.is_none() {
+            peer.metadata_request = Some(rpc.send_request(peer_id, RPCRequest::MetaData, now));
+        }
+    }
+
+    fn on_metadata(&mut self, peer_id: PeerId, metadata: MetaData) {
+        let peer = match self.peers.get_mut(&peer_id) {
+            Some(peer) => peer,
+            None => return,
+        };
+        let newer = match peer.metadata {
+            Some(ref known) => metadata.seq_number > known.seq_number,
+            None => true,
+        };
+        if newer {
+            peer.metadata = Some(metadata.clone());
+            self.events
+                .push_back(MetaDataEvent::Updated { peer_id, metadata });
+        }
+    }
+
+    fn is_own_request(&self, peer_id: PeerId, id: RequestId) -> bool {
+        self.pings.contains(&(peer_id, id))
+            || self
+                .peers
+                .get(&peer_id)
+                .map(|peer| peer.metadata_request == Some(id))
+                .unwrap_or(false)
+    }
+
+    fn clear_metadata_request(&mut self, peer_id: PeerId, id: RequestId) {
+        if let Some(peer) = self.peers.get_mut(&peer_id) {
+            if peer.metadata_request == Some(id) {
+                peer.metadata_request = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::enr::{NodeId, ATTESTATION_SUBNET_COUNT};
+    use super::*;
+    use slog::Discard;
+
+    struct Node {
+        id: PeerId,
+        rpc: RPC,
+        manager: MetaDataManager,
+    }
+
+    fn node() -> Node {
+        let log = Logger::root(Discard, o!());
+        let metadata = MetaData {
+            seq_number: 1,
+            attnets: Bitfield::from_elem(ATTESTATION_SUBNET_COUNT, false),
+        };
+        Node {
+            id: NodeId::random(),
+            rpc: RPC::new(log.clone()),
+            manager: MetaDataManager::new(metadata, log),
+        }
+    }
+
+    /// Delivers all messages between `a` and `b`, passing events through their managers.
+    fn exchange(a: &mut Node, b: &mut Node, now: Instant) {
+        loop {
+            let mut delivered = false;
+            while let Some((_, message)) = a.rpc.next_outbound() {
+                b.rpc.on_message(a.id, message, now);
+                delivered = true;
+            }
+            while let Some((_, message)) = b.rpc.next_outbound() {
+                a.rpc.on_message(b.id, message, now);
+                delivered = true;
+            }
+            for node in &mut [&mut *a, &mut *b] {
+                while let Some(event) = node.rpc.poll(now) {
+                    assert_eq!(node.manager.on_rpc_event(&mut node.rpc, event, now), None);
+                }
+            }
+            if !delivered {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn test_metadata_on_connect() {
+        let (mut a, mut b) = (node(), node());
+        let now = Instant::now();
+
+        a.manager.on_connect(&mut a.rpc, b.id, now);
+        exchange(&mut a, &mut b, now);
+
+        assert_eq!(a.manager.peer_metadata(&b.id), Some(b.manager.local()));
+        assert_eq!(
+            a.manager.poll(),
+            Some(MetaDataEvent::Updated {
+                peer_id: b.id,
+                metadata: b.manager.local().clone(),
+            })
+        );
+        assert_eq!(a.manager.poll(), None);
+    }
+
+    #[test]
+    fn test_seq_number_refresh() {
+        let (mut a, mut b) = (node(), node());
+        let now = Instant::now();
+
+        a.manager.on_connect(&mut a.rpc, b.id, now);
+        b.manager.on_connect(&mut b.rpc, a.id, now);
+        exchange(&mut a, &mut b, now);
+        while a.manager.poll().is_some() {}
+
+        /*
+         * A ping with an unchanged sequence number does not trigger a metadata request.
+         */
+        let later = now + PING_INTERVAL;
+        a.manager.heartbeat(&mut a.rpc, later);
+        exchange(&mut a, &mut b, later);
+        assert_eq!(a.manager.poll(), None);
+
+        /*
+         * Once `b` changes its subnets, `a` learns of it via the next ping.
+         */
+        let mut attnets = Bitfield::from_elem(ATTESTATION_SUBNET_COUNT, false);
+        attnets.set(3, true);
+        b.manager.set_attnets(attnets.clone());
+        assert_eq!(b.manager.local().seq_number, 2);
+
+        let later = later + PING_INTERVAL;
+        a.manager.heartbeat(&mut a.rpc, later);
+        exchange(&mut a, &mut b, later);
+        assert_eq!(a.manager.peer_metadata(&b.id).unwrap().attnets, attnets);
+        match a.manager.poll() {
+            Some(MetaDataEvent::Updated { metadata, .. }) => assert_eq!(metadata.seq_number, 2),
+            other => panic!("expected updated metadata, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unresponsive_peer() {
+        let mut a = node();
+        let b_id = NodeId::random();
+        let now = Instant::now();
+
+        a.manager.on_connect(&mut a.rpc, b_id, now);
+        a.rpc.on_disconnect(b_id);
+        while let Some(event) = a.rpc.poll(now) {
+            assert_eq!(a.manager.on_rpc_event(&mut a.rpc, event, now), None);
+        }
+        assert_eq!(
+            a.manager.poll(),
+            Some(MetaDataEvent::Unresponsive { peer_id: b_id })
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use lighthouse_metrics::{
+    try_create_histogram_vec, try_create_histogram_with_buckets, try_create_int_counter,
+    try_create_int_counter_vec, try_create_int_gauge, try_create_int_gauge_vec, Histogram,
+    HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Result,
+};
+
+lazy_static! {
+    pub static ref PEERS_CONNECTED: Result<IntGauge> =
+        try_create_int_gauge("libp2p_peers", "Count of connected peers");
+    pub static ref PEER_CONNECT_EVENT_COUNT: Result<IntCounter> = try_create_int_counter(
+        "libp2p_peer_connect_event_total",
+        "Count of peers accepted on connection"
+    );
+    pub static ref PEER_DISCONNECT_EVENT_COUNT: Result<IntCounter> = try_create_int_counter(
+        "libp2p_peer_disconnect_event_total",
+        "Count of peers disconnected, by either side"
+    );
+    pub static ref PEERS_BANNED: Result<IntCounter> = try_create_int_counter(
+        "libp2p_peers_banned_total",
+        "Count of peers banned for their score"
+    );
+    pub static ref GOSSIP_DUPLICATES: Result<IntCounter> = try_create_int_counter(
+        "gossipsub_duplicate_messages_total",
+        "Count of gossip messages dropped as duplicates or equivocations"
+    );
+    pub static ref GOSSIP_GRAYLISTED_PEERS: Result<IntGauge> = try_create_int_gauge(
+        "gossipsub_graylisted_peers",
+        "Count of peers whose gossip score is below the graylist threshold"
+    );
+    pub static ref AGGREGATE_VERIFICATION_CACHE_HITS: Result<IntCounter> = try_create_int_counter(
+        "gossipsub_aggregate_verification_cache_hits_total",
+        "Count of aggregate checks skipped as already verified"
+    );
+    /*
+     * Attestation subnets
+     */
+    pub static ref SUBNET_MESH_PEERS: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "gossipsub_subnet_mesh_peers",
+        "Count of peers in the mesh of each attestation subnet",
+        &["subnet"]
+    );
+    pub static ref SUBNET_MESSAGES: Result<IntCounterVec> = try_create_int_counter_vec(
+        "gossipsub_subnet_messages_total",
+        "Count of valid attestations received on each subnet, including duplicates",
+        &["subnet"]
+    );
+    pub static ref SUBNET_FIRST_SEEN_DELAY: Result<HistogramVec> = try_create_histogram_vec(
+        "gossipsub_subnet_first_seen_delay_seconds",
+        "Time from the start of its slot until an attestation is first seen on each subnet",
+        &["subnet"]
+    );
+    /*
+     * Attestation batches
+     */
+    pub static ref ATTESTATION_BATCH_SIZE: Result<Histogram> = try_create_histogram_with_buckets(
+        "attestation_batch_size",
+        "Count of the attestations whose signatures are verified as a batch",
+        vec![1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0]
+    );
+    pub static ref ATTESTATION_BATCH_INVALID_SIGNATURES: Result<IntCounter> =
+        try_create_int_counter(
+            "attestation_batch_invalid_signatures_total",
+            "Count of the batched attestations whose signatures are invalid"
+        );
+}
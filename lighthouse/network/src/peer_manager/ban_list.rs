@@ -0,0 +1,171 @@
+use super::super::rpc::PeerId;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// A range of IP addresses, e.g. `10.0.0.0/8`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Returns `None` if `prefix_len` is longer than the address.
+    pub fn new(network: IpAddr, prefix_len: u8) -> Option<Self> {
+        let max_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_len {
+            return None;
+        }
+        Some(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    /// Returns `true` if `ip` is within the range. Addresses of the other family never are.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = prefix_mask(self.prefix_len, 32) as u32;
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = prefix_mask(self.prefix_len, 128);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns a mask with the highest `prefix_len` of `bits` bits set.
+fn prefix_mask(prefix_len: u8, bits: u32) -> u128 {
+    if prefix_len == 0 {
+        return 0;
+    }
+    let all = if bits == 128 {
+        u128::max_value()
+    } else {
+        (1u128 << bits) - 1
+    };
+    all & !((1u128 << (bits - u32::from(prefix_len))) - 1)
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    /// Parses `address/prefix_len`, or a lone address as a range of one.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut parts = s.splitn(2, '/');
+        let network: IpAddr = parts
+            .next()
+            .unwrap_or("")
+            .parse()
+            .map_err(|_| format!("Invalid IP address in {}", s))?;
+        let prefix_len = match parts.next() {
+            Some(len) => len
+                .parse()
+                .map_err(|_| format!("Invalid prefix length in {}", s))?,
+            None => match network {
+                IpAddr::V4(_) => 32,
+                IpAddr::V6(_) => 128,
+            },
+        };
+        Cidr::new(network, prefix_len).ok_or_else(|| format!("Prefix length too long in {}", s))
+    }
+}
+
+/// Peers and address ranges banned by the operator, which are never dialed or accepted.
+///
+/// Unlike the bans applied by the peer manager for misbehaviour, these never expire.
+#[derive(Clone, Debug, Default)]
+pub struct BanList {
+    peer_ids: HashSet<PeerId>,
+    ranges: Vec<Cidr>,
+}
+
+impl BanList {
+    pub fn new(peer_ids: &[PeerId], ranges: &[Cidr]) -> Self {
+        Self {
+            peer_ids: peer_ids.iter().cloned().collect(),
+            ranges: ranges.to_vec(),
+        }
+    }
+
+    /// Bans `peer_id`, returning `false` if it was already banned.
+    pub fn ban_peer(&mut self, peer_id: PeerId) -> bool {
+        self.peer_ids.insert(peer_id)
+    }
+
+    /// Lifts the ban of `peer_id`, returning `false` if it was not banned.
+    pub fn unban_peer(&mut self, peer_id: &PeerId) -> bool {
+        self.peer_ids.remove(peer_id)
+    }
+
+    pub fn is_peer_banned(&self, peer_id: &PeerId) -> bool {
+        self.peer_ids.contains(peer_id)
+    }
+
+    pub fn is_ip_banned(&self, ip: &IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::enr::NodeId;
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_parse() {
+        assert_eq!(
+            "10.0.0.0/8".parse(),
+            Ok(Cidr::new(ip("10.0.0.0"), 8).unwrap())
+        );
+        assert_eq!(
+            "10.1.2.3".parse(),
+            Ok(Cidr::new(ip("10.1.2.3"), 32).unwrap())
+        );
+        assert_eq!("fd00::/8".parse(), Ok(Cidr::new(ip("fd00::"), 8).unwrap()));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let range: Cidr = "192.168.0.0/16".parse().unwrap();
+        assert!(range.contains(&ip("192.168.4.20")));
+        assert!(!range.contains(&ip("192.169.0.1")));
+        assert!(!range.contains(&ip("::1")));
+
+        let single: Cidr = "1.2.3.4".parse().unwrap();
+        assert!(single.contains(&ip("1.2.3.4")));
+        assert!(!single.contains(&ip("1.2.3.5")));
+
+        let all: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(&ip("8.8.8.8")));
+
+        let v6: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(&ip("2001:db8:1::1")));
+        assert!(!v6.contains(&ip("2001:db9::1")));
+    }
+
+    #[test]
+    fn test_ban_list() {
+        let banned = NodeId::random();
+        let list = BanList::new(&[banned], &["10.0.0.0/8".parse().unwrap()]);
+        assert!(list.is_peer_banned(&banned));
+        assert!(!list.is_peer_banned(&NodeId::random()));
+        assert!(list.is_ip_banned(&ip("10.20.30.40")));
+        assert!(!list.is_ip_banned(&ip("11.0.0.1")));
+    }
+}
@@ -0,0 +1,814 @@
+mod ban_list;
+mod persistence;
+mod score;
+
+pub use self::ban_list::{BanList, Cidr};
+pub use self::persistence::{PeerPersistenceError, PersistedBan, PersistedPeer, MAX_PEER_AGE};
+pub use self::score::{
+    PeerAction, ReportSource, Score, GOSSIP_SCORE_WEIGHT, MIN_SCORE_BEFORE_BAN,
+    MIN_SCORE_BEFORE_DISCONNECT, SCORE_HALFLIFE,
+};
+pub(crate) use self::score::duration_as_secs_f64;
+
+use super::enr::Enr;
+use super::gossip::GRAYLIST_THRESHOLD;
+use super::metrics;
+use super::rpc::{GoodbyeReason, PeerId, StatusMessage};
+use super::types::Hash256;
+use lighthouse_metrics::{inc_counter, set_gauge};
+use slog::Logger;
+use std::collections::hash_map::Iter;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct PeerManagerConfig {
+    /// The number of peers to maintain. Excess peers are pruned during the heartbeat.
+    pub target_peers: usize,
+    /// The number of connections permitted above `target_peers`, so new peers may be tried
+    /// before the worst are pruned.
+    pub excess_peers: usize,
+    /// How long a misbehaving peer is banned for.
+    pub ban_duration: Duration,
+    /// The maximum number of disconnected peers to remember, in order to retain their scores.
+    pub max_disconnected_peers: usize,
+    /// Peers which are never dialed or accepted.
+    pub banned_peers: Vec<PeerId>,
+    /// Address ranges from which peers are never dialed or accepted.
+    pub banned_ranges: Vec<Cidr>,
+}
+
+impl Default for PeerManagerConfig {
+    fn default() -> Self {
+        Self {
+            target_peers: 50,
+            excess_peers: 5,
+            ban_duration: Duration::from_secs(30 * 60),
+            max_disconnected_peers: 500,
+            banned_peers: vec![],
+            banned_ranges: vec![],
+        }
+    }
+}
+
+/// Events emitted by `PeerManager`, to be carried out by the network service.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PeerManagerEvent {
+    /// The peer should be sent a goodbye with `reason` and disconnected.
+    DisconnectPeer {
+        peer_id: PeerId,
+        reason: GoodbyeReason,
+    },
+    /// The peer has been banned and must not be dialed or accepted until it is unbanned.
+    Banned { peer_id: PeerId },
+    /// A ban has expired or been lifted.
+    Unbanned { peer_id: PeerId },
+    /// The operator asked for a connection to `address`.
+    Dial { address: SocketAddr },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionState {
+    Connected { since: Instant },
+    Disconnected { since: Instant },
+    Banned { until: Instant },
+}
+
+/// Which side opened a connection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionDirection {
+    Inbound,
+    Outbound,
+}
+
+#[derive(Clone, Debug)]
+pub struct PeerInfo {
+    pub score: Score,
+    pub state: ConnectionState,
+    /// The direction of the latest connection, `None` if the peer has never been connected.
+    pub direction: Option<ConnectionDirection>,
+    /// The latest record of the peer, if known, required to dial the peer.
+    pub enr: Option<Enr>,
+    /// The last time the peer was connected, or when it was first learned of.
+    pub last_seen: Instant,
+    /// The chain of the peer as of its latest status, while it is connected.
+    pub sync_status: Option<PeerSyncStatus>,
+}
+
+/// The head and finalized checkpoint a peer advertised in its latest status message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerSyncStatus {
+    pub head_slot: u64,
+    pub head_root: Hash256,
+    pub finalized_slot: u64,
+    pub finalized_root: Hash256,
+    /// When the status was received.
+    pub updated: Instant,
+}
+
+impl PeerInfo {
+    fn new(state: ConnectionState, now: Instant) -> Self {
+        Self {
+            score: Score::new(now),
+            state,
+            direction: None,
+            enr: None,
+            last_seen: now,
+            sync_status: None,
+        }
+    }
+}
+
+/// Tracks the reputation of peers and decides which peers should remain connected.
+///
+/// Misbehaviour is reported via `report_peer`. Peers whose score falls too low are disconnected
+/// and, for serious misbehaviour, temporarily banned. The number of connected peers is held at
+/// `target_peers` by pruning the lowest scoring peers during `heartbeat`.
+pub struct PeerManager {
+    config: PeerManagerConfig,
+    ban_list: BanList,
+    peers: HashMap<PeerId, PeerInfo>,
+    events: VecDeque<PeerManagerEvent>,
+    log: Logger,
+}
+
+impl PeerManager {
+    pub fn new(config: PeerManagerConfig, log: Logger) -> Self {
+        Self {
+            ban_list: BanList::new(&config.banned_peers, &config.banned_ranges),
+            config,
+            peers: HashMap::new(),
+            events: VecDeque::new(),
+            log,
+        }
+    }
+
+    pub fn peer_info(&self, peer_id: &PeerId) -> Option<&PeerInfo> {
+        self.peers.get(peer_id)
+    }
+
+    /// Iterates over every known peer, connected or not.
+    pub fn peers(&self) -> Iter<'_, PeerId, PeerInfo> {
+        self.peers.iter()
+    }
+
+    /// Returns the current score of `peer_id`, or `None` if the peer is unknown.
+    pub fn score(&self, peer_id: &PeerId) -> Option<f64> {
+        self.peers.get(peer_id).map(|info| info.score.value())
+    }
+
+    pub fn connected_peers(&self) -> Vec<PeerId> {
+        self.peers
+            .iter()
+            .filter(|(_, info)| match info.state {
+                ConnectionState::Connected { .. } => true,
+                _ => false,
+            })
+            .map(|(peer_id, _)| *peer_id)
+            .collect()
+    }
+
+    /// Records the chain `status` advertised by a connected peer.
+    pub fn update_sync_status(&mut self, peer_id: &PeerId, status: &StatusMessage, now: Instant) {
+        if let Some(info) = self.peers.get_mut(peer_id) {
+            if let ConnectionState::Connected { .. } = info.state {
+                info.sync_status = Some(PeerSyncStatus {
+                    head_slot: status.head_slot,
+                    head_root: status.head_root,
+                    finalized_slot: status.finalized_slot,
+                    finalized_root: status.finalized_root,
+                    updated: now,
+                });
+            }
+        }
+    }
+
+    /// Returns the greatest head slot advertised by a connected peer, or `None` if no connected
+    /// peer has sent its status.
+    pub fn best_peer_head(&self) -> Option<u64> {
+        self.peers
+            .values()
+            .filter_map(|info| match info.state {
+                ConnectionState::Connected { .. } => info.sync_status.as_ref(),
+                _ => None,
+            })
+            .map(|status| status.head_slot)
+            .max()
+    }
+
+    /// Returns `true` if the peer is banned, either temporarily or by the operator.
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        if self.ban_list.is_peer_banned(peer_id) {
+            return true;
+        }
+        match self.peers.get(peer_id).map(|info| info.state) {
+            Some(ConnectionState::Banned { .. }) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_ip_banned(&self, ip: &IpAddr) -> bool {
+        self.ban_list.is_ip_banned(ip)
+    }
+
+    /// Returns `true` if the peer with record `enr` may be dialed.
+    pub fn is_dialable(&self, enr: &Enr) -> bool {
+        let ip_banned = match enr.ip() {
+            Some(ip) => self.is_ip_banned(&IpAddr::V4(ip)),
+            None => false,
+        };
+        !ip_banned && !self.is_banned(&enr.node_id())
+    }
+
+    /// Returns `true` if another peer may be dialed without exceeding the target.
+    pub fn wants_peers(&self) -> bool {
+        self.connected_peers().len() < self.config.target_peers
+    }
+
+    /// Records a new connection dialed by this node, returning `false` if the peer should not
+    /// remain connected.
+    ///
+    /// A `DisconnectPeer` event is emitted for rejected peers.
+    pub fn on_connect(&mut self, peer_id: PeerId, now: Instant) -> bool {
+        self.connect(peer_id, ConnectionDirection::Outbound, now)
+    }
+
+    /// Records a new connection from `ip`, rejecting it if the address is banned, otherwise
+    /// as per `on_connect`.
+    pub fn on_connect_from(&mut self, peer_id: PeerId, ip: &IpAddr, now: Instant) -> bool {
+        if self.is_ip_banned(ip) {
+            self.disconnect(peer_id, GoodbyeReason::Banned);
+            return false;
+        }
+        self.connect(peer_id, ConnectionDirection::Inbound, now)
+    }
+
+    fn connect(&mut self, peer_id: PeerId, direction: ConnectionDirection, now: Instant) -> bool {
+        if self.is_banned(&peer_id) {
+            self.disconnect(peer_id, GoodbyeReason::Banned);
+            return false;
+        }
+        if self.connected_peers().len() >= self.config.target_peers + self.config.excess_peers {
+            self.disconnect(peer_id, GoodbyeReason::TooManyPeers);
+            return false;
+        }
+
+        let info = self
+            .peers
+            .entry(peer_id)
+            .or_insert_with(|| PeerInfo::new(ConnectionState::Connected { since: now }, now));
+        info.score.update(now);
+        info.state = ConnectionState::Connected { since: now };
+        info.direction = Some(direction);
+        info.last_seen = now;
+        if info.score.is_disconnect_worthy() {
+            self.disconnect(peer_id, GoodbyeReason::BadScore);
+            return false;
+        }
+        inc_counter(&metrics::PEER_CONNECT_EVENT_COUNT);
+        self.update_peer_gauge();
+        true
+    }
+
+    /// Records a disconnection. The peer's score is retained.
+    pub fn on_disconnect(&mut self, peer_id: &PeerId, now: Instant) {
+        if let Some(info) = self.peers.get_mut(peer_id) {
+            if let ConnectionState::Connected { .. } = info.state {
+                info.state = ConnectionState::Disconnected { since: now };
+                inc_counter(&metrics::PEER_DISCONNECT_EVENT_COUNT);
+            }
+            info.last_seen = now;
+            info.sync_status = None;
+        }
+        self.update_peer_gauge();
+    }
+
+    /// Bans `peer_id` until it is unbanned, disconnecting it if connected. Like the operator bans
+    /// of the config, the ban is not persisted.
+    pub fn ban_peer(&mut self, peer_id: PeerId) {
+        if !self.ban_list.ban_peer(peer_id) {
+            return;
+        }
+        warn!(self.log, "Peer banned by operator"; "peer_id" => format!("{:?}", peer_id));
+        let connected = match self.peers.get(&peer_id).map(|info| info.state) {
+            Some(ConnectionState::Connected { .. }) => true,
+            _ => false,
+        };
+        if connected {
+            self.disconnect(peer_id, GoodbyeReason::Banned);
+        }
+        self.events.push_back(PeerManagerEvent::Banned { peer_id });
+    }
+
+    /// Lifts any ban of `peer_id`, whether by the operator or for misbehaviour, returning `false`
+    /// if the peer was not banned.
+    pub fn unban_peer(&mut self, peer_id: &PeerId, now: Instant) -> bool {
+        let mut unbanned = self.ban_list.unban_peer(peer_id);
+        if let Some(info) = self.peers.get_mut(peer_id) {
+            if let ConnectionState::Banned { .. } = info.state {
+                info.score.reset(now);
+                info.state = ConnectionState::Disconnected { since: now };
+                unbanned = true;
+            }
+        }
+        if unbanned {
+            info!(self.log, "Peer unbanned by operator"; "peer_id" => format!("{:?}", peer_id));
+            self.events
+                .push_back(PeerManagerEvent::Unbanned { peer_id: *peer_id });
+        }
+        unbanned
+    }
+
+    /// Asks the network service to dial `address`, returning `false` if the address is banned.
+    pub fn dial(&mut self, address: SocketAddr) -> bool {
+        if self.is_ip_banned(&address.ip()) {
+            return false;
+        }
+        self.events.push_back(PeerManagerEvent::Dial { address });
+        true
+    }
+
+    /// Records the ENR of a peer, e.g. one found by discovery, so it may later be persisted and
+    /// dialed. Records older than the one already known are ignored.
+    pub fn add_enr(&mut self, enr: Enr, now: Instant) {
+        let info = self
+            .peers
+            .entry(enr.node_id())
+            .or_insert_with(|| PeerInfo::new(ConnectionState::Disconnected { since: now }, now));
+        let newer = match info.enr {
+            Some(ref known) => enr.seq() > known.seq(),
+            None => true,
+        };
+        if newer {
+            info.enr = Some(enr);
+        }
+    }
+
+    /// Penalises `peer_id` for misbehaviour observed by `source`, disconnecting or banning it if
+    /// its score falls too low.
+    pub fn report_peer(
+        &mut self,
+        peer_id: &PeerId,
+        action: PeerAction,
+        source: ReportSource,
+        now: Instant,
+    ) {
+        let (score, state) = match self.peers.get_mut(peer_id) {
+            Some(info) => {
+                info.score.update(now);
+                info.score.apply_action(action);
+                (info.score.clone(), info.state)
+            }
+            None => return,
+        };
+        debug!(self.log, "Peer reported";
+               "peer_id" => format!("{:?}", peer_id),
+               "action" => format!("{:?}", action),
+               "source" => format!("{:?}", source),
+               "score" => score.value());
+        self.enforce_score(*peer_id, &score, state, now);
+    }
+
+    /// Replaces the gossip scores of known peers with `scores` from the gossip router,
+    /// disconnecting or banning those whose combined score falls too low.
+    pub fn update_gossip_scores(&mut self, scores: &[(PeerId, f64)], now: Instant) {
+        let mut graylisted = 0;
+        for (peer_id, gossip_score) in scores {
+            if *gossip_score < GRAYLIST_THRESHOLD {
+                graylisted += 1;
+            }
+            let (score, state) = match self.peers.get_mut(peer_id) {
+                Some(info) => {
+                    info.score.update(now);
+                    info.score.update_gossip_score(*gossip_score);
+                    (info.score.clone(), info.state)
+                }
+                None => continue,
+            };
+            self.enforce_score(*peer_id, &score, state, now);
+        }
+        set_gauge(&metrics::GOSSIP_GRAYLISTED_PEERS, graylisted);
+    }
+
+    /// Decays scores, expires bans and prunes the lowest scoring peers above the target.
+    pub fn heartbeat(&mut self, now: Instant) {
+        let mut unbanned = vec![];
+        for (peer_id, info) in &mut self.peers {
+            match info.state {
+                ConnectionState::Banned { until } if now >= until => {
+                    info.score.reset(now);
+                    info.state = ConnectionState::Disconnected { since: now };
+                    unbanned.push(*peer_id);
+                }
+                // Scores do not decay during a ban.
+                ConnectionState::Banned { .. } => {}
+                ConnectionState::Connected { .. } => {
+                    info.score.update(now);
+                    info.last_seen = now;
+                }
+                ConnectionState::Disconnected { .. } => info.score.update(now),
+            }
+        }
+        for peer_id in unbanned {
+            info!(self.log, "Peer unbanned"; "peer_id" => format!("{:?}", peer_id));
+            self.events
+                .push_back(PeerManagerEvent::Unbanned { peer_id });
+        }
+
+        let mut connected: Vec<(PeerId, f64)> = self
+            .connected_peers()
+            .into_iter()
+            .map(|peer_id| (peer_id, self.peers[&peer_id].score.value()))
+            .collect();
+        if connected.len() > self.config.target_peers {
+            connected.sort_by(|a, b| a.1.partial_cmp(&b.1).expect("scores are never NaN"));
+            let excess = connected.len() - self.config.target_peers;
+            for (peer_id, _) in connected.into_iter().take(excess) {
+                self.disconnect(peer_id, GoodbyeReason::TooManyPeers);
+            }
+        }
+
+        self.prune_disconnected_peers();
+    }
+
+    /// Returns the next event, if any.
+    pub fn poll(&mut self) -> Option<PeerManagerEvent> {
+        self.events.pop_front()
+    }
+
+    /// Bans or disconnects `peer_id` if `score` calls for it. Banned peers are left alone.
+    fn enforce_score(
+        &mut self,
+        peer_id: PeerId,
+        score: &Score,
+        state: ConnectionState,
+        now: Instant,
+    ) {
+        match state {
+            ConnectionState::Banned { .. } => {}
+            _ if score.is_ban_worthy() => self.ban(peer_id, now),
+            ConnectionState::Connected { .. } if score.is_disconnect_worthy() => {
+                self.disconnect(peer_id, GoodbyeReason::BadScore)
+            }
+            _ => {}
+        }
+    }
+
+    fn ban(&mut self, peer_id: PeerId, now: Instant) {
+        let connected = match self.peers.get_mut(&peer_id) {
+            Some(info) => {
+                let connected = match info.state {
+                    ConnectionState::Connected { .. } => true,
+                    _ => false,
+                };
+                info.state = ConnectionState::Banned {
+                    until: now + self.config.ban_duration,
+                };
+                connected
+            }
+            None => return,
+        };
+        warn!(self.log, "Peer banned"; "peer_id" => format!("{:?}", peer_id));
+        inc_counter(&metrics::PEERS_BANNED);
+        if connected {
+            self.disconnect(peer_id, GoodbyeReason::Banned);
+        }
+        self.events.push_back(PeerManagerEvent::Banned { peer_id });
+    }
+
+    /// Emits a `DisconnectPeer` event. The peer is considered disconnected immediately, so it is
+    /// not counted towards the target while the goodbye is sent.
+    fn disconnect(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
+        if let Some(info) = self.peers.get_mut(&peer_id) {
+            if let ConnectionState::Connected { since } = info.state {
+                info.state = ConnectionState::Disconnected { since };
+                inc_counter(&metrics::PEER_DISCONNECT_EVENT_COUNT);
+            }
+        }
+        self.update_peer_gauge();
+        self.events
+            .push_back(PeerManagerEvent::DisconnectPeer { peer_id, reason });
+    }
+
+    fn update_peer_gauge(&self) {
+        set_gauge(
+            &metrics::PEERS_CONNECTED,
+            self.connected_peers().len() as i64,
+        );
+    }
+
+    /// Forgets the disconnected peers with the highest scores, keeping those with poor scores so
+    /// they cannot escape their reputation by reconnecting.
+    fn prune_disconnected_peers(&mut self) {
+        let mut disconnected: Vec<(PeerId, f64)> = self
+            .peers
+            .iter()
+            .filter_map(|(peer_id, info)| match info.state {
+                ConnectionState::Disconnected { .. } => Some((*peer_id, info.score.value())),
+                _ => None,
+            })
+            .collect();
+        if disconnected.len() <= self.config.max_disconnected_peers {
+            return;
+        }
+        disconnected.sort_by(|a, b| b.1.partial_cmp(&a.1).expect("scores are never NaN"));
+        let excess = disconnected.len() - self.config.max_disconnected_peers;
+        for (peer_id, _) in disconnected.into_iter().take(excess) {
+            self.peers.remove(&peer_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::enr::NodeId;
+    use super::super::gossip::GOSSIP_THRESHOLD;
+    use super::super::rpc::ForkDigest;
+    use super::*;
+    use slog::Discard;
+
+    fn peer_manager(target_peers: usize) -> PeerManager {
+        let config = PeerManagerConfig {
+            target_peers,
+            excess_peers: 1,
+            ..PeerManagerConfig::default()
+        };
+        PeerManager::new(config, Logger::root(Discard, o!()))
+    }
+
+    fn events(pm: &mut PeerManager) -> Vec<PeerManagerEvent> {
+        let mut events = vec![];
+        while let Some(event) = pm.poll() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn test_disconnect_on_low_score() {
+        let mut pm = peer_manager(10);
+        let now = Instant::now();
+        let peer_id = NodeId::random();
+        assert!(pm.on_connect(peer_id, now));
+
+        for _ in 0..2 {
+            pm.report_peer(
+                &peer_id,
+                PeerAction::LowToleranceError,
+                ReportSource::Gossip,
+                now,
+            );
+        }
+        assert!(events(&mut pm).is_empty());
+
+        pm.report_peer(
+            &peer_id,
+            PeerAction::LowToleranceError,
+            ReportSource::Gossip,
+            now,
+        );
+        assert_eq!(
+            events(&mut pm),
+            vec![PeerManagerEvent::DisconnectPeer {
+                peer_id,
+                reason: GoodbyeReason::BadScore,
+            }]
+        );
+        assert!(pm.connected_peers().is_empty());
+
+        /*
+         * The score is retained, so the peer is rejected if it reconnects immediately but accepted
+         * once its score has decayed.
+         */
+        assert!(!pm.on_connect(peer_id, now));
+        assert!(pm.on_connect(peer_id, now + SCORE_HALFLIFE));
+    }
+
+    #[test]
+    fn test_temporary_ban() {
+        let mut pm = peer_manager(10);
+        let now = Instant::now();
+        let peer_id = NodeId::random();
+        pm.on_connect(peer_id, now);
+
+        pm.report_peer(
+            &peer_id,
+            PeerAction::Fatal,
+            ReportSource::ChainRelevance,
+            now,
+        );
+        assert_eq!(
+            events(&mut pm),
+            vec![
+                PeerManagerEvent::DisconnectPeer {
+                    peer_id,
+                    reason: GoodbyeReason::Banned,
+                },
+                PeerManagerEvent::Banned { peer_id },
+            ]
+        );
+        assert!(pm.is_banned(&peer_id));
+        assert!(!pm.on_connect(peer_id, now));
+        events(&mut pm);
+
+        let ban_duration = PeerManagerConfig::default().ban_duration;
+        pm.heartbeat(now + ban_duration - Duration::from_secs(1));
+        assert!(pm.is_banned(&peer_id));
+
+        pm.heartbeat(now + ban_duration);
+        assert_eq!(
+            events(&mut pm),
+            vec![PeerManagerEvent::Unbanned { peer_id }]
+        );
+        assert!(pm.on_connect(peer_id, now + ban_duration));
+    }
+
+    #[test]
+    fn test_operator_bans() {
+        let banned = NodeId::random();
+        let config = PeerManagerConfig {
+            banned_peers: vec![banned],
+            banned_ranges: vec!["10.0.0.0/8".parse().unwrap()],
+            ..PeerManagerConfig::default()
+        };
+        let mut pm = PeerManager::new(config, Logger::root(Discard, o!()));
+        let now = Instant::now();
+
+        assert!(pm.is_banned(&banned));
+        assert!(!pm.on_connect(banned, now));
+
+        let peer_id = NodeId::random();
+        assert!(!pm.on_connect_from(peer_id, &"10.1.1.1".parse().unwrap(), now));
+        assert!(pm.on_connect_from(peer_id, &"11.1.1.1".parse().unwrap(), now));
+        assert_eq!(
+            pm.peer_info(&peer_id).unwrap().direction,
+            Some(ConnectionDirection::Inbound)
+        );
+        assert_eq!(
+            events(&mut pm),
+            vec![
+                PeerManagerEvent::DisconnectPeer {
+                    peer_id: banned,
+                    reason: GoodbyeReason::Banned,
+                },
+                PeerManagerEvent::DisconnectPeer {
+                    peer_id,
+                    reason: GoodbyeReason::Banned,
+                },
+            ]
+        );
+
+        /*
+         * Operator bans never expire.
+         */
+        pm.heartbeat(now + PeerManagerConfig::default().ban_duration * 2);
+        assert!(pm.is_banned(&banned));
+    }
+
+    #[test]
+    fn test_ban_and_unban_by_operator() {
+        let mut pm = peer_manager(10);
+        let now = Instant::now();
+        let peer_id = NodeId::random();
+        assert!(pm.on_connect(peer_id, now));
+
+        pm.ban_peer(peer_id);
+        assert!(pm.is_banned(&peer_id));
+        assert!(!pm.on_connect(peer_id, now));
+        events(&mut pm);
+
+        assert!(pm.unban_peer(&peer_id, now));
+        assert!(!pm.unban_peer(&peer_id, now));
+        assert_eq!(
+            events(&mut pm),
+            vec![PeerManagerEvent::Unbanned { peer_id }]
+        );
+        assert!(pm.on_connect(peer_id, now));
+
+        let address = "1.2.3.4:9000".parse().unwrap();
+        assert!(pm.dial(address));
+        assert_eq!(events(&mut pm), vec![PeerManagerEvent::Dial { address }]);
+    }
+
+    #[test]
+    fn test_prune_worst_peers() {
+        let mut pm = peer_manager(2);
+        let now = Instant::now();
+        let peers: Vec<PeerId> = (0..3).map(|_| NodeId::random()).collect();
+        for peer_id in &peers {
+            assert!(pm.on_connect(*peer_id, now));
+        }
+        let rejected = NodeId::random();
+        assert!(!pm.on_connect(rejected, now));
+        assert_eq!(
+            events(&mut pm),
+            vec![PeerManagerEvent::DisconnectPeer {
+                peer_id: rejected,
+                reason: GoodbyeReason::TooManyPeers,
+            }]
+        );
+
+        pm.report_peer(
+            &peers[1],
+            PeerAction::HighToleranceError,
+            ReportSource::RPC,
+            now,
+        );
+        pm.heartbeat(now);
+        assert_eq!(
+            events(&mut pm),
+            vec![PeerManagerEvent::DisconnectPeer {
+                peer_id: peers[1],
+                reason: GoodbyeReason::TooManyPeers,
+            }]
+        );
+        assert_eq!(pm.connected_peers().len(), 2);
+    }
+
+    #[test]
+    fn test_gossip_scores() {
+        let mut pm = peer_manager(2);
+        let now = Instant::now();
+        let peers: Vec<PeerId> = (0..3).map(|_| NodeId::random()).collect();
+        for peer_id in &peers[..2] {
+            assert!(pm.on_connect(*peer_id, now));
+        }
+
+        /*
+         * A graylisted peer is only demoted, until reported misbehaviour adds to it.
+         */
+        pm.update_gossip_scores(&[(peers[0], GRAYLIST_THRESHOLD), (peers[2], 10.0)], now);
+        assert!(events(&mut pm).is_empty());
+        assert!(pm.score(&peers[0]).unwrap() < pm.score(&peers[1]).unwrap());
+        assert_eq!(pm.score(&peers[2]), None);
+
+        pm.report_peer(
+            &peers[0],
+            PeerAction::MidToleranceError,
+            ReportSource::Gossip,
+            now,
+        );
+        assert_eq!(
+            events(&mut pm),
+            vec![PeerManagerEvent::DisconnectPeer {
+                peer_id: peers[0],
+                reason: GoodbyeReason::BadScore,
+            }]
+        );
+
+        /*
+         * A peer already near disconnection is disconnected by a poor gossip score.
+         */
+        for _ in 0..2 {
+            pm.report_peer(
+                &peers[1],
+                PeerAction::LowToleranceError,
+                ReportSource::RPC,
+                now,
+            );
+        }
+        assert!(events(&mut pm).is_empty());
+        pm.update_gossip_scores(&[(peers[1], GOSSIP_THRESHOLD)], now);
+        assert_eq!(
+            events(&mut pm),
+            vec![PeerManagerEvent::DisconnectPeer {
+                peer_id: peers[1],
+                reason: GoodbyeReason::BadScore,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sync_status() {
+        let mut pm = peer_manager(10);
+        let now = Instant::now();
+        let peers: Vec<PeerId> = (0..3).map(|_| NodeId::random()).collect();
+        let status = |head_slot| StatusMessage {
+            fork_digest: ForkDigest::new(0, &Hash256::zero()),
+            finalized_root: Hash256::zero(),
+            finalized_slot: 0,
+            head_root: Hash256::from(&[head_slot as u8; 32][..]),
+            head_slot,
+        };
+        assert_eq!(pm.best_peer_head(), None);
+
+        /*
+         * Only the statuses of connected peers are recorded.
+         */
+        pm.update_sync_status(&peers[2], &status(30), now);
+        assert_eq!(pm.best_peer_head(), None);
+        for peer_id in &peers[..2] {
+            assert!(pm.on_connect(*peer_id, now));
+        }
+        pm.update_sync_status(&peers[0], &status(10), now);
+        pm.update_sync_status(&peers[1], &status(20), now);
+        assert_eq!(pm.best_peer_head(), Some(20));
+        let sync_status = pm.peer_info(&peers[1]).unwrap().sync_status.clone();
+        assert_eq!(sync_status.unwrap().head_root, status(20).head_root);
+
+        pm.on_disconnect(&peers[1], now);
+        assert_eq!(pm.peer_info(&peers[1]).unwrap().sync_status, None);
+        assert_eq!(pm.best_peer_head(), Some(10));
+    }
+}
@@ -0,0 +1,342 @@
+use super::super::db::stores::PeerStore;
+use super::super::db::{ClientDB, DBError};
+use super::super::enr::{Enr, NodeId};
+use super::super::ssz::{decode_ssz_list, Decodable, DecodeError, Encodable, SszStream};
+use super::super::time::unix_time;
+use super::score::MAX_SCORE;
+use super::{ConnectionState, PeerInfo, PeerManager, Score};
+use std::time::{Duration, Instant};
+
+/// The maximum number of peers written to disk.
+pub const MAX_PERSISTED_PEERS: usize = 500;
+/// Peers not seen for longer than this are not restored.
+pub const MAX_PEER_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, PartialEq)]
+pub enum PeerPersistenceError {
+    DBError(String),
+    DecodeError,
+}
+
+impl From<DBError> for PeerPersistenceError {
+    fn from(error: DBError) -> Self {
+        PeerPersistenceError::DBError(error.message)
+    }
+}
+
+impl From<DecodeError> for PeerPersistenceError {
+    fn from(_: DecodeError) -> Self {
+        PeerPersistenceError::DecodeError
+    }
+}
+
+/// A known peer, as written to disk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PersistedPeer {
+    pub enr: Enr,
+    pub score: f64,
+    /// Seconds since the unix epoch.
+    pub last_seen: u64,
+}
+
+impl Encodable for PersistedPeer {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.enr);
+        s.append(&self.score.to_bits());
+        s.append(&self.last_seen);
+    }
+}
+
+impl Decodable for PersistedPeer {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (enr, i) = Enr::ssz_decode(bytes, i)?;
+        let (score, i) = u64::ssz_decode(bytes, i)?;
+        let (last_seen, i) = u64::ssz_decode(bytes, i)?;
+        let peer = PersistedPeer {
+            enr,
+            score: f64::from_bits(score),
+            last_seen,
+        };
+        Ok((peer, i))
+    }
+}
+
+/// A peer banned for misbehaviour, as written to disk.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PersistedBan {
+    pub peer_id: NodeId,
+    /// Seconds since the unix epoch at which the ban expires.
+    pub until: u64,
+}
+
+impl Encodable for PersistedBan {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.peer_id);
+        s.append(&self.until);
+    }
+}
+
+impl Decodable for PersistedBan {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (peer_id, i) = NodeId::ssz_decode(bytes, i)?;
+        let (until, i) = u64::ssz_decode(bytes, i)?;
+        Ok((PersistedBan { peer_id, until }, i))
+    }
+}
+
+impl PeerManager {
+    /// Returns the peers with known ENRs, highest score first, limited to `MAX_PERSISTED_PEERS`.
+    pub fn persisted_peers(&self, now: Instant) -> Vec<PersistedPeer> {
+        let unix_now = unix_time();
+        let mut peers: Vec<PersistedPeer> = self
+            .peers
+            .values()
+            .filter_map(|info| {
+                let enr = info.enr.clone()?;
+                let mut score = info.score.clone();
+                score.update(now);
+                let age = now.duration_since(info.last_seen).as_secs();
+                Some(PersistedPeer {
+                    enr,
+                    score: score.reported_value(),
+                    last_seen: unix_now.saturating_sub(age),
+                })
+            })
+            .collect();
+        peers.sort_by(|a, b| b.score.partial_cmp(&a.score).expect("scores are never NaN"));
+        peers.truncate(MAX_PERSISTED_PEERS);
+        peers
+    }
+
+    /// Returns the peers currently banned for misbehaviour. Bans configured by the operator
+    /// are not included, as they are restored from the config.
+    pub fn persisted_bans(&self, now: Instant) -> Vec<PersistedBan> {
+        let unix_now = unix_time();
+        self.peers
+            .iter()
+            .filter_map(|(peer_id, info)| match info.state {
+                ConnectionState::Banned { until } if until > now => Some(PersistedBan {
+                    peer_id: *peer_id,
+                    until: unix_now + until.duration_since(now).as_secs(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Writes the known peers and current bans to `store`, replacing those previously written.
+    pub fn persist<T: ClientDB>(
+        &self,
+        store: &PeerStore<T>,
+        now: Instant,
+    ) -> Result<(), PeerPersistenceError> {
+        let mut s = SszStream::new();
+        s.append_vec(&self.persisted_peers(now));
+        store.put_serialized_peers(&s.drain())?;
+
+        let mut s = SszStream::new();
+        s.append_vec(&self.persisted_bans(now));
+        store.put_serialized_bans(&s.drain())?;
+        Ok(())
+    }
+
+    /// Restores the peers written by `persist`, returning the ENRs of those worth dialing,
+    /// highest score first.
+    ///
+    /// Peers not seen within `MAX_PEER_AGE` are discarded. Poorly scoring and banned peers are
+    /// restored but not returned, so they cannot escape their reputation across a restart.
+    pub fn load<T: ClientDB>(
+        &mut self,
+        store: &PeerStore<T>,
+        now: Instant,
+    ) -> Result<Vec<Enr>, PeerPersistenceError> {
+        self.load_bans(store, now)?;
+        let ssz = match store.get_serialized_peers()? {
+            Some(ssz) => ssz,
+            None => return Ok(vec![]),
+        };
+        let (peers, _): (Vec<PersistedPeer>, usize) = decode_ssz_list(&ssz, 0)?;
+
+        let unix_now = unix_time();
+        let mut to_dial = vec![];
+        for peer in peers {
+            let age = Duration::from_secs(unix_now.saturating_sub(peer.last_seen));
+            if age > MAX_PEER_AGE || !peer.enr.verify() {
+                continue;
+            }
+            let score = Score::from_value(peer.score, now);
+            if !score.is_disconnect_worthy() && self.is_dialable(&peer.enr) {
+                to_dial.push(peer.enr.clone());
+            }
+            let peer_id = peer.enr.node_id();
+            let state = ConnectionState::Disconnected { since: now };
+            let info = self.peers.entry(peer_id).or_insert_with(|| PeerInfo {
+                score,
+                state,
+                direction: None,
+                enr: None,
+                last_seen: now.checked_sub(age).unwrap_or(now),
+                sync_status: None,
+            });
+            // A peer with a restored ban keeps its ban, but its record is still useful.
+            if info.enr.is_none() {
+                info.enr = Some(peer.enr);
+            }
+        }
+        info!(self.log, "Loaded persisted peers"; "dialable" => to_dial.len(), "total" => self.peers.len());
+        Ok(to_dial)
+    }
+}
+
+impl PeerManager {
+    /// Restores the bans written by `persist` which have not yet expired.
+    fn load_bans<T: ClientDB>(
+        &mut self,
+        store: &PeerStore<T>,
+        now: Instant,
+    ) -> Result<(), PeerPersistenceError> {
+        let ssz = match store.get_serialized_bans()? {
+            Some(ssz) => ssz,
+            None => return Ok(()),
+        };
+        let (bans, _): (Vec<PersistedBan>, usize) = decode_ssz_list(&ssz, 0)?;
+
+        let unix_now = unix_time();
+        for ban in bans.into_iter().filter(|ban| ban.until > unix_now) {
+            let until = now + Duration::from_secs(ban.until - unix_now);
+            let state = ConnectionState::Banned { until };
+            let mut info = PeerInfo::new(state, now);
+            info.score.add(-MAX_SCORE);
+            self.peers.insert(ban.peer_id, info);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::bls::Keypair;
+    use super::super::super::db::MemoryDB;
+    use super::super::{PeerAction, PeerManagerConfig, ReportSource, MIN_SCORE_BEFORE_BAN};
+    use super::*;
+    use slog::{Discard, Logger};
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    fn peer_manager() -> PeerManager {
+        PeerManager::new(PeerManagerConfig::default(), Logger::root(Discard, o!()))
+    }
+
+    fn enr() -> Enr {
+        Enr::new(
+            &Keypair::random(),
+            Some(Ipv4Addr::new(127, 0, 0, 1)),
+            Some(9000),
+            Some(9000),
+        )
+    }
+
+    #[test]
+    fn test_persisted_peer_ssz_round_trip() {
+        let peer = PersistedPeer {
+            enr: enr(),
+            score: -12.5,
+            last_seen: 1_000,
+        };
+        let mut s = SszStream::new();
+        s.append(&peer);
+        let bytes = s.drain();
+        assert_eq!(
+            PersistedPeer::ssz_decode(&bytes, 0),
+            Ok((peer, bytes.len()))
+        );
+    }
+
+    #[test]
+    fn test_persist_and_load() {
+        let store = PeerStore::new(Arc::new(MemoryDB::open()));
+        let now = Instant::now();
+        let (good, bad, unknown) = (enr(), enr(), enr());
+
+        let mut pm = peer_manager();
+        for enr in &[good.clone(), bad.clone()] {
+            pm.add_enr(enr.clone(), now);
+            pm.on_connect(enr.node_id(), now);
+        }
+        pm.report_peer(&bad.node_id(), PeerAction::Fatal, ReportSource::Gossip, now);
+        // A peer without an ENR cannot be dialed, so it is not persisted.
+        pm.on_connect(unknown.node_id(), now);
+        pm.persist(&store, now).unwrap();
+
+        let mut restored = peer_manager();
+        assert_eq!(restored.load(&store, now).unwrap(), vec![good.clone()]);
+        assert_eq!(restored.score(&good.node_id()), Some(0.0));
+        assert!(restored.score(&bad.node_id()).unwrap() < MIN_SCORE_BEFORE_BAN);
+        assert_eq!(restored.score(&unknown.node_id()), None);
+        assert!(!restored.on_connect(bad.node_id(), now));
+    }
+
+    #[test]
+    fn test_load_discards_old_peers() {
+        let store = PeerStore::new(Arc::new(MemoryDB::open()));
+        let peer = PersistedPeer {
+            enr: enr(),
+            score: 0.0,
+            last_seen: unix_time() - MAX_PEER_AGE.as_secs() - 1,
+        };
+        let mut s = SszStream::new();
+        s.append_vec(&[peer]);
+        store.put_serialized_peers(&s.drain()).unwrap();
+
+        let mut pm = peer_manager();
+        assert_eq!(pm.load(&store, Instant::now()).unwrap(), vec![]);
+        assert!(pm.peers.is_empty());
+    }
+
+    #[test]
+    fn test_persist_and_load_bans() {
+        let store = PeerStore::new(Arc::new(MemoryDB::open()));
+        let now = Instant::now();
+        let (banned, expired) = (enr(), NodeId::random());
+
+        let mut pm = peer_manager();
+        pm.add_enr(banned.clone(), now);
+        pm.on_connect(banned.node_id(), now);
+        pm.report_peer(&banned.node_id(), PeerAction::Fatal, ReportSource::RPC, now);
+        pm.persist(&store, now).unwrap();
+        assert_eq!(pm.persisted_bans(now).len(), 1);
+
+        /*
+         * Add a ban which has already expired, which is not restored.
+         */
+        let (mut bans, _): (Vec<PersistedBan>, usize) =
+            decode_ssz_list(&store.get_serialized_bans().unwrap().unwrap(), 0).unwrap();
+        bans.push(PersistedBan {
+            peer_id: expired,
+            until: unix_time() - 1,
+        });
+        let mut s = SszStream::new();
+        s.append_vec(&bans);
+        store.put_serialized_bans(&s.drain()).unwrap();
+
+        let mut restored = peer_manager();
+        assert_eq!(restored.load(&store, now).unwrap(), vec![]);
+        assert!(restored.is_banned(&banned.node_id()));
+        assert!(!restored.is_dialable(&banned));
+        assert!(!restored.on_connect(banned.node_id(), now));
+        assert!(!restored.is_banned(&expired));
+
+        /*
+         * The ban expires at the same time it would have without the restart.
+         */
+        let ban_duration = PeerManagerConfig::default().ban_duration;
+        restored.heartbeat(now + ban_duration + Duration::from_secs(1));
+        assert!(!restored.is_banned(&banned.node_id()));
+    }
+
+    #[test]
+    fn test_load_empty_store() {
+        let store = PeerStore::new(Arc::new(MemoryDB::open()));
+        assert_eq!(peer_manager().load(&store, Instant::now()).unwrap(), vec![]);
+    }
+}
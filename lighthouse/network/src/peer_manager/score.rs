@@ -0,0 +1,197 @@
+use super::super::gossip::GRAYLIST_THRESHOLD;
+use std::time::{Duration, Instant};
+
+/// The score of a newly seen peer.
+pub const DEFAULT_SCORE: f64 = 0.0;
+/// The maximum magnitude of a score.
+pub const MAX_SCORE: f64 = 100.0;
+/// Peers with a score below this are disconnected.
+pub const MIN_SCORE_BEFORE_DISCONNECT: f64 = -20.0;
+/// Peers with a score below this are disconnected and temporarily banned.
+pub const MIN_SCORE_BEFORE_BAN: f64 = -50.0;
+/// The time taken for a score to decay halfway towards zero.
+pub const SCORE_HALFLIFE: Duration = Duration::from_secs(600);
+/// The weight of the gossip score in a peer's score. The weighted gossip score saturates once a
+/// peer is graylisted, just short of disconnecting it, so that gossip alone only demotes a peer
+/// to be among the first pruned.
+pub const GOSSIP_SCORE_WEIGHT: f64 = (MIN_SCORE_BEFORE_DISCONNECT + 1.0) / GRAYLIST_THRESHOLD;
+
+/// Misbehaviour by a peer, classified by how many times it may be tolerated before the peer is
+/// disconnected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PeerAction {
+    /// The peer must be banned immediately, e.g. for being on an irrelevant chain.
+    Fatal,
+    /// Tolerated a few times, e.g. sending an invalid block.
+    LowToleranceError,
+    /// Tolerated around ten times, e.g. an invalid RPC response.
+    MidToleranceError,
+    /// Tolerated many times, e.g. an RPC timeout.
+    HighToleranceError,
+}
+
+impl PeerAction {
+    fn score_delta(&self) -> f64 {
+        match self {
+            PeerAction::Fatal => -2.0 * MAX_SCORE,
+            PeerAction::LowToleranceError => -10.0,
+            PeerAction::MidToleranceError => -5.0,
+            PeerAction::HighToleranceError => -1.0,
+        }
+    }
+}
+
+/// The subsystem which observed some misbehaviour, recorded for logging.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportSource {
+    Gossip,
+    RPC,
+    /// The peer's chain is not relevant to our own, e.g. a failed status handshake.
+    ChainRelevance,
+}
+
+/// The reputation of a peer, which decays exponentially towards zero over time, combined with
+/// its latest gossip score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    value: f64,
+    /// The score of the peer's gossip, which decays by its own parameters.
+    gossip_score: f64,
+    last_updated: Instant,
+}
+
+impl Score {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            value: DEFAULT_SCORE,
+            gossip_score: 0.0,
+            last_updated: now,
+        }
+    }
+
+    /// Restores a score with the given reported value, e.g. one loaded from disk.
+    pub fn from_value(value: f64, now: Instant) -> Self {
+        let mut score = Score::new(now);
+        score.add(value);
+        score
+    }
+
+    /// The reported score combined with the weighted gossip score.
+    pub fn value(&self) -> f64 {
+        let gossip =
+            (self.gossip_score * GOSSIP_SCORE_WEIGHT).max(MIN_SCORE_BEFORE_DISCONNECT + 1.0);
+        (self.value + gossip).max(-MAX_SCORE).min(MAX_SCORE)
+    }
+
+    /// The score of misbehaviour reported, without the gossip score.
+    pub fn reported_value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn gossip_score(&self) -> f64 {
+        self.gossip_score
+    }
+
+    /// Replaces the gossip score with the latest from the gossip router.
+    pub fn update_gossip_score(&mut self, gossip_score: f64) {
+        self.gossip_score = gossip_score;
+    }
+
+    pub fn apply_action(&mut self, action: PeerAction) {
+        self.add(action.score_delta());
+    }
+
+    /// Adds `delta` to the score, clamping it to within `MAX_SCORE` of zero.
+    pub fn add(&mut self, delta: f64) {
+        self.value = (self.value + delta).max(-MAX_SCORE).min(MAX_SCORE);
+    }
+
+    /// Decays the score for the time elapsed since it was last updated.
+    pub fn update(&mut self, now: Instant) {
+        if now <= self.last_updated {
+            return;
+        }
+        let elapsed = now.duration_since(self.last_updated);
+        let halflives = duration_as_secs_f64(elapsed) / duration_as_secs_f64(SCORE_HALFLIFE);
+        self.value *= 0.5_f64.powf(halflives);
+        self.last_updated = now;
+    }
+
+    /// Restores the default score, e.g. once a ban has been served.
+    pub fn reset(&mut self, now: Instant) {
+        *self = Score::new(now);
+    }
+
+    pub fn is_disconnect_worthy(&self) -> bool {
+        self.value() < MIN_SCORE_BEFORE_DISCONNECT
+    }
+
+    pub fn is_ban_worthy(&self) -> bool {
+        self.value() < MIN_SCORE_BEFORE_BAN
+    }
+}
+
+/// Returns `duration` in fractional seconds.
+pub(crate) fn duration_as_secs_f64(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_thresholds() {
+        let mut score = Score::new(Instant::now());
+        for _ in 0..2 {
+            score.apply_action(PeerAction::LowToleranceError);
+        }
+        assert!(!score.is_disconnect_worthy());
+
+        score.apply_action(PeerAction::LowToleranceError);
+        assert!(score.is_disconnect_worthy());
+        assert!(!score.is_ban_worthy());
+
+        score.apply_action(PeerAction::Fatal);
+        assert!(score.is_ban_worthy());
+        assert_eq!(score.value(), -MAX_SCORE);
+    }
+
+    #[test]
+    fn test_score_decay() {
+        let now = Instant::now();
+        let mut score = Score::new(now);
+        score.add(-40.0);
+
+        score.update(now + SCORE_HALFLIFE);
+        assert!((score.value() + 20.0).abs() < 1e-9);
+
+        score.update(now + SCORE_HALFLIFE * 3);
+        assert!((score.value() + 5.0).abs() < 1e-9);
+
+        score.reset(now);
+        assert_eq!(score.value(), DEFAULT_SCORE);
+    }
+
+    #[test]
+    fn test_gossip_score() {
+        let now = Instant::now();
+        let mut score = Score::new(now);
+        score.update_gossip_score(GRAYLIST_THRESHOLD * 2.0);
+        assert!(score.value() < -1.0);
+        assert!(!score.is_disconnect_worthy());
+        assert_eq!(score.reported_value(), DEFAULT_SCORE);
+
+        /*
+         * Gossip misbehaviour adds to reported misbehaviour, and does not decay with it.
+         */
+        score.apply_action(PeerAction::MidToleranceError);
+        assert!(score.is_disconnect_worthy());
+        score.update(now + SCORE_HALFLIFE * 10);
+        assert!(!score.is_disconnect_worthy());
+        assert!(score.value() < MIN_SCORE_BEFORE_DISCONNECT + 1.0);
+
+        score.reset(now);
+        assert_eq!(score.gossip_score(), 0.0);
+    }
+}
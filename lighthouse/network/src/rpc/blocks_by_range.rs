@@ -0,0 +1,167 @@
+use super::super::db::stores::{BeaconBlockAtSlotError, BeaconBlockStore};
+use super::super::db::ClientDB;
+use super::super::ssz::Decodable;
+use super::super::ssz_helpers::ssz_beacon_block::SszBeaconBlock;
+use super::super::types::{BeaconBlock, Hash256};
+use super::{BlocksByRangeRequest, PeerId, RPCResponse, RequestId, ResponseCode, RPC};
+
+impl RPC {
+    /// Responds to a `BlocksByRange` request from `peer_id` with blocks from the canonical chain
+    /// ending at `head_hash`, then closes the stream.
+    pub fn respond_blocks_by_range<T: ClientDB>(
+        &mut self,
+        peer_id: PeerId,
+        id: RequestId,
+        request: &BlocksByRangeRequest,
+        store: &BeaconBlockStore<T>,
+        head_hash: &Hash256,
+    ) {
+        if !request.is_valid() {
+            self.send_error(peer_id, id, ResponseCode::InvalidRequest, "Invalid range");
+            self.end_response(peer_id, id);
+            return;
+        }
+
+        match blocks_by_range(store, head_hash, request) {
+            Ok(blocks) => {
+                for block in blocks {
+                    self.send_response(peer_id, id, &RPCResponse::BlocksByRange(block));
+                }
+            }
+            Err(e) => {
+                warn!(self.log, "Unable to read blocks for range request"; "error" => format!("{:?}", e));
+                self.send_error(peer_id, id, ResponseCode::ServerError, "Unable to read blocks");
+            }
+        }
+        self.end_response(peer_id, id);
+    }
+}
+
+/// Returns the blocks of the chain ending at `head_hash` which fall within the range of
+/// `request`, in ascending slot order.
+pub fn blocks_by_range<T: ClientDB>(
+    store: &BeaconBlockStore<T>,
+    head_hash: &Hash256,
+    request: &BlocksByRangeRequest,
+) -> Result<Vec<BeaconBlock>, BeaconBlockAtSlotError> {
+    let mut blocks = vec![];
+    for result in store.block_iter(head_hash) {
+        let (_, ssz) = result?;
+        let slot = SszBeaconBlock::from_slice(&ssz)
+            .map_err(|_| BeaconBlockAtSlotError::InvalidBeaconBlock)?
+            .slot();
+        if slot < request.start_slot {
+            break;
+        }
+        if request.contains_slot(slot) {
+            let (block, _) = BeaconBlock::ssz_decode(&ssz, 0)
+                .map_err(|_| BeaconBlockAtSlotError::InvalidBeaconBlock)?;
+            blocks.push(block);
+        }
+    }
+    blocks.reverse();
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::db::MemoryDB;
+    use super::super::super::enr::NodeId;
+    use super::super::super::ssz::ssz_encode;
+    use super::super::codec::encode_request;
+    use super::super::{Protocol, RPCEvent, StreamMessage, MAX_REQUEST_BLOCKS};
+    use super::*;
+    use slog::{Discard, Logger};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    /// Stores a chain with a block at each of `slots`, returning the hash of the head.
+    fn build_chain(store: &BeaconBlockStore<MemoryDB>, slots: &[u64]) -> Hash256 {
+        let mut parent_hash = Hash256::from("genesis".as_bytes());
+        for slot in slots {
+            let mut block = BeaconBlock::zero();
+            block.slot = *slot;
+            block.ancestor_hashes.push(parent_hash);
+
+            let hash = Hash256::random();
+            store
+                .put_serialized_block(&hash, &ssz_encode(&block))
+                .unwrap();
+            parent_hash = hash;
+        }
+        parent_hash
+    }
+
+    fn slots(blocks: &[BeaconBlock]) -> Vec<u64> {
+        blocks.iter().map(|block| block.slot).collect()
+    }
+
+    #[test]
+    fn test_blocks_by_range() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let head = build_chain(&store, &[0, 1, 2, 4, 5, 6, 8]);
+
+        let request = |start_slot, count, step| BlocksByRangeRequest {
+            start_slot,
+            count,
+            step,
+        };
+
+        let blocks = blocks_by_range(&store, &head, &request(1, 4, 1)).unwrap();
+        assert_eq!(slots(&blocks), vec![1, 2, 4]);
+
+        let blocks = blocks_by_range(&store, &head, &request(0, 5, 2)).unwrap();
+        assert_eq!(slots(&blocks), vec![0, 2, 4, 6, 8]);
+
+        let blocks = blocks_by_range(&store, &head, &request(9, 10, 1)).unwrap();
+        assert!(blocks.is_empty());
+    }
+
+    #[test]
+    fn test_respond_blocks_by_range() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let head = build_chain(&store, &[0, 1, 2]);
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+        let peer_id = NodeId::random();
+
+        let request = BlocksByRangeRequest {
+            start_slot: 1,
+            count: 2,
+            step: 1,
+        };
+        rpc.on_message(
+            peer_id,
+            StreamMessage::Request {
+                id: 0,
+                protocol: Protocol::BeaconBlocksByRange.id(),
+                bytes: encode_request(&ssz_encode(&request)),
+            },
+            Instant::now(),
+        );
+        match rpc.poll(Instant::now()) {
+            Some(RPCEvent::Request { .. }) => {}
+            other => panic!("expected request, got {:?}", other),
+        }
+
+        rpc.respond_blocks_by_range(peer_id, 0, &request, &store, &head);
+        let messages: Vec<StreamMessage> = (0..3).map(|_| rpc.next_outbound().unwrap().1).collect();
+        assert_eq!(messages[2], StreamMessage::Close { id: 0 });
+        assert_eq!(rpc.next_outbound(), None);
+
+        let invalid = BlocksByRangeRequest {
+            start_slot: 0,
+            count: MAX_REQUEST_BLOCKS + 1,
+            step: 1,
+        };
+        rpc.inbound_requests
+            .insert((peer_id, 1), Protocol::BeaconBlocksByRange);
+        rpc.respond_blocks_by_range(peer_id, 1, &invalid, &store, &head);
+        let (_, message) = rpc.next_outbound().unwrap();
+        match message {
+            StreamMessage::Response { bytes, .. } => {
+                assert_eq!(bytes[0], ResponseCode::InvalidRequest.as_u8())
+            }
+            other => panic!("expected response, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use super::super::db::stores::{BeaconBlockAtSlotError, BeaconBlockStore};
+use super::super::db::ClientDB;
+use super::super::ssz::Decodable;
+use super::super::types::BeaconBlock;
+use super::{BlocksByRootRequest, PeerId, RPCResponse, RequestId, ResponseCode, RPC};
+
+impl RPC {
+    /// Responds to a `BlocksByRoot` request from `peer_id` with each requested block found in
+    /// `store`, then closes the stream.
+    pub fn respond_blocks_by_root<T: ClientDB>(
+        &mut self,
+        peer_id: PeerId,
+        id: RequestId,
+        request: &BlocksByRootRequest,
+        store: &BeaconBlockStore<T>,
+    ) {
+        if !request.is_valid() {
+            self.send_error(peer_id, id, ResponseCode::InvalidRequest, "Invalid block roots");
+            self.end_response(peer_id, id);
+            return;
+        }
+
+        match blocks_by_root(store, request) {
+            Ok(blocks) => {
+                for block in blocks {
+                    self.send_response(peer_id, id, &RPCResponse::BlocksByRoot(block));
+                }
+            }
+            Err(e) => {
+                warn!(self.log, "Unable to read blocks for root request"; "error" => format!("{:?}", e));
+                self.send_error(peer_id, id, ResponseCode::ServerError, "Unable to read blocks");
+            }
+        }
+        self.end_response(peer_id, id);
+    }
+}
+
+/// Returns the blocks in `store` with the roots given in `request`, in the order requested.
+///
+/// Unknown roots are skipped.
+pub fn blocks_by_root<T: ClientDB>(
+    store: &BeaconBlockStore<T>,
+    request: &BlocksByRootRequest,
+) -> Result<Vec<BeaconBlock>, BeaconBlockAtSlotError> {
+    let mut blocks = vec![];
+    for root in &request.block_roots {
+        if let Some(ssz) = store.get_serialized_block(root)? {
+            let (block, _) = BeaconBlock::ssz_decode(&ssz, 0)
+                .map_err(|_| BeaconBlockAtSlotError::InvalidBeaconBlock)?;
+            blocks.push(block);
+        }
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::db::MemoryDB;
+    use super::super::super::enr::NodeId;
+    use super::super::super::hashing::canonical_hash;
+    use super::super::super::ssz::ssz_encode;
+    use super::super::super::types::Hash256;
+    use super::super::{Protocol, StreamMessage};
+    use super::*;
+    use slog::{Discard, Logger};
+    use std::sync::Arc;
+
+    fn store_block(store: &BeaconBlockStore<MemoryDB>, slot: u64) -> (Hash256, BeaconBlock) {
+        let mut block = BeaconBlock::zero();
+        block.slot = slot;
+        let ssz = ssz_encode(&block);
+        let root = Hash256::from(&canonical_hash(&ssz)[..]);
+        store.put_serialized_block(&root, &ssz).unwrap();
+        (root, block)
+    }
+
+    #[test]
+    fn test_blocks_by_root() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let (root_a, block_a) = store_block(&store, 1);
+        let (root_b, block_b) = store_block(&store, 2);
+
+        let request = BlocksByRootRequest {
+            block_roots: vec![root_b, Hash256::from("unknown".as_bytes()), root_a],
+        };
+        assert_eq!(
+            blocks_by_root(&store, &request).unwrap(),
+            vec![block_b, block_a]
+        );
+    }
+
+    #[test]
+    fn test_respond_blocks_by_root() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let (root, _) = store_block(&store, 1);
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+        let peer_id = NodeId::random();
+
+        rpc.inbound_requests
+            .insert((peer_id, 0), Protocol::BeaconBlocksByRoot);
+        let request = BlocksByRootRequest {
+            block_roots: vec![root],
+        };
+        rpc.respond_blocks_by_root(peer_id, 0, &request, &store);
+
+        match rpc.next_outbound() {
+            Some((_, StreamMessage::Response { bytes, .. })) => {
+                assert_eq!(bytes[0], ResponseCode::Success.as_u8())
+            }
+            other => panic!("expected response, got {:?}", other),
+        }
+        assert_eq!(
+            rpc.next_outbound(),
+            Some((peer_id, StreamMessage::Close { id: 0 }))
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use super::super::snap;
+use super::methods::ResponseCode;
+use std::io::{self, Cursor, Read, Write};
+
+/// The maximum length of the uncompressed SSZ payload of a request or response chunk.
+pub const MAX_CHUNK_SIZE: u64 = 1_048_576;
+
+/// The maximum number of bytes in an encoded varint (enough for any `u64`).
+const MAX_VARINT_BYTES: usize = 10;
+/// The maximum number of uncompressed bytes in a single snappy frame.
+const MAX_FRAME_LEN: u64 = 65_536;
+/// The length of the snappy stream identifier frame.
+const STREAM_IDENTIFIER_LEN: u64 = 10;
+/// The length of the header and checksum of each snappy frame.
+const FRAME_HEADER_LEN: u64 = 8;
+
+/// Errors which may arise when decoding SSZ-snappy framed messages.
+#[derive(Debug, PartialEq)]
+pub enum CodecError {
+    /// The length prefix is not a valid varint.
+    InvalidLengthPrefix,
+    /// The snappy frames could not be decompressed.
+    InvalidCompression,
+    /// Bytes were found after the end of a request.
+    TrailingBytes,
+    /// The stream ended part-way through a message.
+    Incomplete,
+    /// The length prefix exceeds `MAX_CHUNK_SIZE`, or more compressed bytes were received than
+    /// could encode a payload of that length.
+    PayloadTooLarge,
+}
+
+/*
+ * The SSZ-snappy framing:
+ *
+ * request        = <length> <snappy frames>
+ * response_chunk = <result code> <length> <snappy frames>
+ *
+ * Where <length> is the unsigned LEB128 varint encoding of the length of the uncompressed SSZ
+ * payload and <snappy frames> is the payload compressed with the snappy framing format.
+ */
+
+/// Encodes an SSZ request payload.
+pub fn encode_request(ssz: &[u8]) -> Vec<u8> {
+    let mut bytes = encode_varint(ssz.len() as u64);
+    bytes.append(&mut compress(ssz));
+    bytes
+}
+
+/// Decodes a complete request, as written by `encode_request`, returning the SSZ payload.
+///
+/// A requester writes exactly one request to a stream, so all of `bytes` must be consumed.
+pub fn decode_request(bytes: &[u8]) -> Result<Vec<u8>, CodecError> {
+    match decode_payload(bytes)? {
+        Some((ssz, consumed)) if consumed == bytes.len() => Ok(ssz),
+        Some(_) => Err(CodecError::TrailingBytes),
+        None => Err(CodecError::Incomplete),
+    }
+}
+
+/// Encodes a single response chunk.
+///
+/// For `ResponseCode::Success` the payload is the SSZ of the response, otherwise it is an error
+/// message.
+pub fn encode_response_chunk(code: ResponseCode, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![code.as_u8()];
+    bytes.append(&mut encode_varint(payload.len() as u64));
+    bytes.append(&mut compress(payload));
+    bytes
+}
+
+/// Decodes response chunks from a stream which may be delivered in arbitrary pieces.
+#[derive(Default)]
+pub struct ResponseDecoder {
+    buf: Vec<u8>,
+}
+
+impl ResponseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends bytes read from the stream.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Returns the next complete chunk, or `None` if more bytes are required.
+    pub fn next_chunk(&mut self) -> Result<Option<(ResponseCode, Vec<u8>)>, CodecError> {
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+        let code = ResponseCode::from(self.buf[0]);
+        match decode_payload(&self.buf[1..])? {
+            Some((payload, consumed)) => {
+                self.buf.drain(..=consumed);
+                Ok(Some((code, payload)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `true` if there are buffered bytes which do not yet form a complete chunk.
+    pub fn has_partial_chunk(&self) -> bool {
+        !self.buf.is_empty()
+    }
+}
+
+/// Decodes a length-prefixed, snappy compressed payload from the start of `bytes`.
+///
+/// Returns the payload and the number of bytes consumed, or `None` if `bytes` ends before the
+/// payload is complete.
+///
+/// The length prefix is checked against `MAX_CHUNK_SIZE` before anything is decompressed, so a
+/// peer cannot cause a large allocation by lying about the length.
+fn decode_payload(bytes: &[u8]) -> Result<Option<(Vec<u8>, usize)>, CodecError> {
+    let (len, prefix_len) = match decode_varint(bytes)? {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+    if len > MAX_CHUNK_SIZE {
+        return Err(CodecError::PayloadTooLarge);
+    }
+
+    /*
+     * The frame decoder reads whole snappy frames from the underlying reader, so once `len` bytes
+     * have been decompressed the cursor sits at the end of the final frame of the payload.
+     */
+    let mut cursor = Cursor::new(&bytes[prefix_len..]);
+    let mut payload = vec![0; len as usize];
+    let result = snap::read::FrameDecoder::new(&mut cursor).read_exact(&mut payload);
+    match result {
+        Ok(()) => Ok(Some((payload, prefix_len + cursor.position() as usize))),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            /*
+             * A payload which is still incomplete after more bytes than its maximum compressed
+             * length will never complete, so fail now rather than buffering indefinitely.
+             */
+            if (bytes.len() - prefix_len) as u64 > max_framed_len(len) {
+                Err(CodecError::PayloadTooLarge)
+            } else {
+                Ok(None)
+            }
+        }
+        Err(_) => Err(CodecError::InvalidCompression),
+    }
+}
+
+/// Returns an upper bound on the length of a payload of `len` bytes once snappy framed.
+fn max_framed_len(len: u64) -> u64 {
+    let frames = ((len + MAX_FRAME_LEN - 1) / MAX_FRAME_LEN).max(1);
+    let max_compressed = snap::raw::max_compress_len(MAX_FRAME_LEN as usize) as u64;
+    STREAM_IDENTIFIER_LEN + frames * (FRAME_HEADER_LEN + max_compressed)
+}
+
+fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = snap::write::FrameEncoder::new(vec![]);
+    encoder
+        .write_all(bytes)
+        .expect("writing to a vec cannot fail");
+    encoder.into_inner().expect("flushing to a vec cannot fail")
+}
+
+fn encode_varint(mut value: u64) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Decodes a varint from the start of `bytes`, returning the value and the number of bytes read.
+fn decode_varint(bytes: &[u8]) -> Result<Option<(u64, usize)>, CodecError> {
+    let mut value: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if i >= MAX_VARINT_BYTES {
+            return Err(CodecError::InvalidLengthPrefix);
+        }
+        value |= u64::from(byte & 0x7f)
+            .checked_shl(7 * i as u32)
+            .ok_or(CodecError::InvalidLengthPrefix)?;
+        if byte & 0x80 == 0 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_round_trip() {
+        for value in &[0, 1, 127, 128, 300, 1 << 32, 1 << 63] {
+            let bytes = encode_varint(*value);
+            assert_eq!(decode_varint(&bytes), Ok(Some((*value, bytes.len()))));
+        }
+        assert_eq!(encode_varint(300), vec![0xac, 0x02]);
+        assert_eq!(decode_varint(&[0x80]), Ok(None));
+        assert_eq!(
+            decode_varint(&[0xff; 11]),
+            Err(CodecError::InvalidLengthPrefix)
+        );
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let ssz = vec![42; 1_000];
+        let bytes = encode_request(&ssz);
+        assert!(bytes.len() < ssz.len());
+        assert_eq!(decode_request(&bytes), Ok(ssz));
+
+        assert_eq!(
+            decode_request(&bytes[..bytes.len() - 1]),
+            Err(CodecError::Incomplete)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode_request(&trailing), Err(CodecError::TrailingBytes));
+    }
+
+    #[test]
+    fn test_response_chunks_across_pieces() {
+        let mut stream = encode_response_chunk(ResponseCode::Success, &[1; 100_000]);
+        stream.append(&mut encode_response_chunk(ResponseCode::Success, &[]));
+        stream.append(&mut encode_response_chunk(
+            ResponseCode::ServerError,
+            "oops".as_bytes(),
+        ));
+
+        let mut decoder = ResponseDecoder::new();
+        let mut chunks = vec![];
+        for piece in stream.chunks(7) {
+            decoder.push(piece);
+            while let Some(chunk) = decoder.next_chunk().unwrap() {
+                chunks.push(chunk);
+            }
+        }
+
+        assert!(!decoder.has_partial_chunk());
+        assert_eq!(
+            chunks,
+            vec![
+                (ResponseCode::Success, vec![1; 100_000]),
+                (ResponseCode::Success, vec![]),
+                (ResponseCode::ServerError, "oops".as_bytes().to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_payload_size_limits() {
+        let ssz = vec![7; MAX_CHUNK_SIZE as usize];
+        assert_eq!(decode_request(&encode_request(&ssz)), Ok(ssz));
+
+        /*
+         * The length prefix alone is enough to reject an oversized payload.
+         */
+        let prefix = encode_varint(MAX_CHUNK_SIZE + 1);
+        assert_eq!(decode_request(&prefix), Err(CodecError::PayloadTooLarge));
+
+        let mut decoder = ResponseDecoder::new();
+        decoder.push(&[ResponseCode::Success.as_u8()]);
+        decoder.push(&prefix);
+        assert_eq!(decoder.next_chunk(), Err(CodecError::PayloadTooLarge));
+
+        /*
+         * A small payload followed by an endless stream of bytes is rejected once the bytes
+         * exceed what could possibly encode it.
+         */
+        let mut padded = encode_varint(3);
+        padded.extend_from_slice(&compress(&[1, 2, 3])[..STREAM_IDENTIFIER_LEN as usize]);
+        while padded.len() as u64 <= max_framed_len(3) {
+            // A padding frame of 65,535 bytes.
+            padded.extend_from_slice(&[0xfe, 0xff, 0xff, 0x00]);
+            padded.extend_from_slice(&[0; 0xffff]);
+        }
+        assert_eq!(decode_request(&padded), Err(CodecError::PayloadTooLarge));
+    }
+
+    #[test]
+    fn test_invalid_compression() {
+        let mut bytes = encode_response_chunk(ResponseCode::Success, &[1, 2, 3]);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+
+        let mut decoder = ResponseDecoder::new();
+        decoder.push(&bytes);
+        assert_eq!(decoder.next_chunk(), Err(CodecError::InvalidCompression));
+    }
+}
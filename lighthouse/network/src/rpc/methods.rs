@@ -0,0 +1,604 @@
+use super::super::hashing::canonical_hash;
+use super::super::ssz::{Decodable, DecodeError, Encodable, SszStream};
+use super::super::types::{BeaconBlock, Bitfield, Hash256};
+
+/// The maximum number of blocks which may be requested in a single `BlocksByRangeRequest` or
+/// `BlocksByRootRequest`.
+pub const MAX_REQUEST_BLOCKS: u64 = 1_024;
+
+/// The req/resp protocols supported by this node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    Status,
+    Goodbye,
+    Ping,
+    MetaData,
+    BeaconBlocksByRange,
+    BeaconBlocksByRoot,
+}
+
+impl Protocol {
+    /// The identifier used to negotiate the protocol when a stream is opened.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Protocol::Status => "/eth2/beacon_chain/req/status/1/ssz_snappy",
+            Protocol::Goodbye => "/eth2/beacon_chain/req/goodbye/1/ssz_snappy",
+            Protocol::Ping => "/eth2/beacon_chain/req/ping/1/ssz_snappy",
+            Protocol::MetaData => "/eth2/beacon_chain/req/metadata/1/ssz_snappy",
+            Protocol::BeaconBlocksByRange => {
+                "/eth2/beacon_chain/req/beacon_blocks_by_range/1/ssz_snappy"
+            }
+            Protocol::BeaconBlocksByRoot => {
+                "/eth2/beacon_chain/req/beacon_blocks_by_root/1/ssz_snappy"
+            }
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [
+            Protocol::Status,
+            Protocol::Goodbye,
+            Protocol::Ping,
+            Protocol::MetaData,
+            Protocol::BeaconBlocksByRange,
+            Protocol::BeaconBlocksByRoot,
+        ]
+        .iter()
+        .find(|protocol| protocol.id() == id)
+        .cloned()
+    }
+}
+
+/// The result code prefixed to each response chunk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseCode {
+    Success,
+    /// The request was malformed or could not be understood.
+    InvalidRequest,
+    /// The responder failed to process a valid request.
+    ServerError,
+    /// A code not defined by the protocol.
+    Unknown(u8),
+}
+
+impl ResponseCode {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            ResponseCode::Success => 0,
+            ResponseCode::InvalidRequest => 1,
+            ResponseCode::ServerError => 2,
+            ResponseCode::Unknown(code) => *code,
+        }
+    }
+}
+
+impl From<u8> for ResponseCode {
+    fn from(code: u8) -> Self {
+        match code {
+            0 => ResponseCode::Success,
+            1 => ResponseCode::InvalidRequest,
+            2 => ResponseCode::ServerError,
+            code => ResponseCode::Unknown(code),
+        }
+    }
+}
+
+/// Identifies the network a node is on, derived from the fork version and the genesis block root.
+///
+/// Nodes with differing fork digests cannot usefully communicate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ForkDigest(pub [u8; 4]);
+
+impl ForkDigest {
+    /// Returns the first four bytes of `hash(fork_version || genesis_root)`.
+    pub fn new(fork_version: u64, genesis_root: &Hash256) -> Self {
+        let mut s = SszStream::new();
+        s.append(&fork_version);
+        s.append(genesis_root);
+        let hash = canonical_hash(&s.drain());
+        ForkDigest([hash[0], hash[1], hash[2], hash[3]])
+    }
+}
+
+impl Encodable for ForkDigest {
+    fn ssz_append(&self, s: &mut SszStream) {
+        for byte in &self.0 {
+            s.append(byte);
+        }
+    }
+}
+
+impl Decodable for ForkDigest {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let digest = bytes.get(i..i + 4).ok_or(DecodeError::TooShort)?;
+        Ok((
+            ForkDigest([digest[0], digest[1], digest[2], digest[3]]),
+            i + 4,
+        ))
+    }
+}
+
+/// Describes the chain of a node, exchanged by both peers when a connection is established.
+///
+/// The finalized block is identified by its slot, as the chain tracks finality by slot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatusMessage {
+    pub fork_digest: ForkDigest,
+    pub finalized_root: Hash256,
+    pub finalized_slot: u64,
+    pub head_root: Hash256,
+    pub head_slot: u64,
+}
+
+impl Encodable for StatusMessage {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.fork_digest);
+        s.append(&self.finalized_root);
+        s.append(&self.finalized_slot);
+        s.append(&self.head_root);
+        s.append(&self.head_slot);
+    }
+}
+
+impl Decodable for StatusMessage {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (fork_digest, i) = ForkDigest::ssz_decode(bytes, i)?;
+        let (finalized_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (finalized_slot, i) = u64::ssz_decode(bytes, i)?;
+        let (head_root, i) = Hash256::ssz_decode(bytes, i)?;
+        let (head_slot, i) = u64::ssz_decode(bytes, i)?;
+        let status = StatusMessage {
+            fork_digest,
+            finalized_root,
+            finalized_slot,
+            head_root,
+            head_slot,
+        };
+        Ok((status, i))
+    }
+}
+
+/// The reason given to a peer when disconnecting from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GoodbyeReason {
+    /// This node is shutting down.
+    ClientShutdown,
+    /// The peer is on a different network or an incompatible chain.
+    IrrelevantNetwork,
+    /// The peer has misbehaved, e.g. by sending invalid messages.
+    Fault,
+    /// This node has reached its maximum number of peers.
+    TooManyPeers,
+    /// The peer has too low a score to remain connected.
+    BadScore,
+    /// The peer is banned.
+    Banned,
+    /// A code not defined by the protocol.
+    Unknown(u64),
+}
+
+impl GoodbyeReason {
+    pub fn as_u64(&self) -> u64 {
+        match self {
+            GoodbyeReason::ClientShutdown => 1,
+            GoodbyeReason::IrrelevantNetwork => 2,
+            GoodbyeReason::Fault => 3,
+            GoodbyeReason::TooManyPeers => 129,
+            GoodbyeReason::BadScore => 250,
+            GoodbyeReason::Banned => 251,
+            GoodbyeReason::Unknown(code) => *code,
+        }
+    }
+}
+
+impl From<u64> for GoodbyeReason {
+    fn from(code: u64) -> Self {
+        match code {
+            1 => GoodbyeReason::ClientShutdown,
+            2 => GoodbyeReason::IrrelevantNetwork,
+            3 => GoodbyeReason::Fault,
+            129 => GoodbyeReason::TooManyPeers,
+            250 => GoodbyeReason::BadScore,
+            251 => GoodbyeReason::Banned,
+            code => GoodbyeReason::Unknown(code),
+        }
+    }
+}
+
+impl Encodable for GoodbyeReason {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.as_u64());
+    }
+}
+
+impl Decodable for GoodbyeReason {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (code, i) = u64::ssz_decode(bytes, i)?;
+        Ok((GoodbyeReason::from(code), i))
+    }
+}
+
+/// Carries the `MetaData` sequence number of the sender, in both a ping and its response.
+///
+/// A peer which sees a sequence number greater than the last it received should request the
+/// sender's `MetaData`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ping {
+    pub seq_number: u64,
+}
+
+impl Encodable for Ping {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.seq_number);
+    }
+}
+
+impl Decodable for Ping {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (seq_number, i) = u64::ssz_decode(bytes, i)?;
+        Ok((Ping { seq_number }, i))
+    }
+}
+
+/// Information about a node which may change during its lifetime.
+///
+/// `seq_number` is incremented whenever any other field changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetaData {
+    pub seq_number: u64,
+    pub attnets: Bitfield,
+}
+
+impl Encodable for MetaData {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.seq_number);
+        s.append(&self.attnets);
+    }
+}
+
+impl Decodable for MetaData {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (seq_number, i) = u64::ssz_decode(bytes, i)?;
+        let (attnets, i) = Bitfield::ssz_decode(bytes, i)?;
+        Ok((
+            MetaData {
+                seq_number,
+                attnets,
+            },
+            i,
+        ))
+    }
+}
+
+/// Requests `count` blocks from the responder's canonical chain, starting at `start_slot` and
+/// including every `step`th slot thereafter.
+///
+/// Slots without a block are skipped, so fewer than `count` blocks may be returned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlocksByRangeRequest {
+    pub start_slot: u64,
+    pub count: u64,
+    pub step: u64,
+}
+
+impl BlocksByRangeRequest {
+    /// Returns `true` if the request is within the limits of the protocol.
+    pub fn is_valid(&self) -> bool {
+        self.count > 0 && self.count <= MAX_REQUEST_BLOCKS && self.step > 0
+    }
+
+    /// Returns `true` if a block at `slot` is within the requested range.
+    pub fn contains_slot(&self, slot: u64) -> bool {
+        slot >= self.start_slot
+            && (slot - self.start_slot) % self.step.max(1) == 0
+            && (slot - self.start_slot) / self.step.max(1) < self.count
+    }
+}
+
+impl Encodable for BlocksByRangeRequest {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.start_slot);
+        s.append(&self.count);
+        s.append(&self.step);
+    }
+}
+
+impl Decodable for BlocksByRangeRequest {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (start_slot, i) = u64::ssz_decode(bytes, i)?;
+        let (count, i) = u64::ssz_decode(bytes, i)?;
+        let (step, i) = u64::ssz_decode(bytes, i)?;
+        let request = BlocksByRangeRequest {
+            start_slot,
+            count,
+            step,
+        };
+        Ok((request, i))
+    }
+}
+
+/// Requests the blocks with the given hashes, typically the unknown parents of blocks received
+/// via gossip.
+///
+/// Blocks unknown to the responder are omitted from the response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlocksByRootRequest {
+    pub block_roots: Vec<Hash256>,
+}
+
+impl BlocksByRootRequest {
+    /// Returns `true` if the request is within the limits of the protocol.
+    pub fn is_valid(&self) -> bool {
+        !self.block_roots.is_empty() && self.block_roots.len() as u64 <= MAX_REQUEST_BLOCKS
+    }
+}
+
+impl Encodable for BlocksByRootRequest {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append_vec(&self.block_roots);
+    }
+}
+
+impl Decodable for BlocksByRootRequest {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (block_roots, i) = Decodable::ssz_decode(bytes, i)?;
+        Ok((BlocksByRootRequest { block_roots }, i))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RPCRequest {
+    Status(StatusMessage),
+    /// Informs the peer that we are disconnecting. No response is expected.
+    Goodbye(GoodbyeReason),
+    Ping(Ping),
+    /// Requests the peer's `MetaData`. The request has an empty body.
+    MetaData,
+    BlocksByRange(BlocksByRangeRequest),
+    BlocksByRoot(BlocksByRootRequest),
+}
+
+impl RPCRequest {
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            RPCRequest::Status(_) => Protocol::Status,
+            RPCRequest::Goodbye(_) => Protocol::Goodbye,
+            RPCRequest::Ping(_) => Protocol::Ping,
+            RPCRequest::MetaData => Protocol::MetaData,
+            RPCRequest::BlocksByRange(_) => Protocol::BeaconBlocksByRange,
+            RPCRequest::BlocksByRoot(_) => Protocol::BeaconBlocksByRoot,
+        }
+    }
+
+    /// The maximum number of response chunks the responder may send.
+    pub fn max_responses(&self) -> u64 {
+        match self {
+            RPCRequest::Status(_) | RPCRequest::Ping(_) | RPCRequest::MetaData => 1,
+            RPCRequest::Goodbye(_) => 0,
+            RPCRequest::BlocksByRange(request) => request.count,
+            RPCRequest::BlocksByRoot(request) => request.block_roots.len() as u64,
+        }
+    }
+
+    pub fn as_ssz(&self) -> Vec<u8> {
+        let mut s = SszStream::new();
+        match self {
+            RPCRequest::Status(status) => s.append(status),
+            RPCRequest::Goodbye(reason) => s.append(reason),
+            RPCRequest::Ping(ping) => s.append(ping),
+            RPCRequest::MetaData => return vec![],
+            RPCRequest::BlocksByRange(request) => s.append(request),
+            RPCRequest::BlocksByRoot(request) => s.append(request),
+        };
+        s.drain()
+    }
+
+    /// Decodes a request received on a stream opened for `protocol`.
+    pub fn from_ssz(protocol: Protocol, ssz: &[u8]) -> Result<Self, DecodeError> {
+        let request = match protocol {
+            Protocol::Status => RPCRequest::Status(decode_exact(ssz)?),
+            Protocol::Goodbye => RPCRequest::Goodbye(decode_exact(ssz)?),
+            Protocol::Ping => RPCRequest::Ping(decode_exact(ssz)?),
+            Protocol::MetaData if ssz.is_empty() => RPCRequest::MetaData,
+            Protocol::MetaData => return Err(DecodeError::TooLong),
+            Protocol::BeaconBlocksByRange => RPCRequest::BlocksByRange(decode_exact(ssz)?),
+            Protocol::BeaconBlocksByRoot => RPCRequest::BlocksByRoot(decode_exact(ssz)?),
+        };
+        Ok(request)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RPCResponse {
+    /// The responder's status, in response to our own.
+    Status(StatusMessage),
+    /// The responder's `MetaData` sequence number, in response to a ping.
+    Pong(Ping),
+    MetaData(MetaData),
+    /// A single block from a `BlocksByRange` response.
+    BlocksByRange(BeaconBlock),
+    /// A single block from a `BlocksByRoot` response.
+    BlocksByRoot(BeaconBlock),
+}
+
+impl RPCResponse {
+    pub fn as_ssz(&self) -> Vec<u8> {
+        let mut s = SszStream::new();
+        match self {
+            RPCResponse::Status(status) => s.append(status),
+            RPCResponse::Pong(ping) => s.append(ping),
+            RPCResponse::MetaData(metadata) => s.append(metadata),
+            RPCResponse::BlocksByRange(block) | RPCResponse::BlocksByRoot(block) => s.append(block),
+        };
+        s.drain()
+    }
+
+    /// Decodes a successful response chunk received for a request on `protocol`.
+    pub fn from_ssz(protocol: Protocol, ssz: &[u8]) -> Result<Self, DecodeError> {
+        let response = match protocol {
+            Protocol::Status => RPCResponse::Status(decode_exact(ssz)?),
+            Protocol::Ping => RPCResponse::Pong(decode_exact(ssz)?),
+            Protocol::MetaData => RPCResponse::MetaData(decode_exact(ssz)?),
+            // There is no response to a goodbye.
+            Protocol::Goodbye => return Err(DecodeError::TooLong),
+            Protocol::BeaconBlocksByRange => RPCResponse::BlocksByRange(decode_exact(ssz)?),
+            Protocol::BeaconBlocksByRoot => RPCResponse::BlocksByRoot(decode_exact(ssz)?),
+        };
+        Ok(response)
+    }
+}
+
+/// Decodes `T`, requiring that all of `ssz` is consumed.
+fn decode_exact<T: Decodable>(ssz: &[u8]) -> Result<T, DecodeError> {
+    let (item, i) = T::ssz_decode(ssz, 0)?;
+    if i != ssz.len() {
+        return Err(DecodeError::TooLong);
+    }
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_by_range_contains_slot() {
+        let request = BlocksByRangeRequest {
+            start_slot: 10,
+            count: 3,
+            step: 2,
+        };
+
+        let contained: Vec<u64> = (0..20)
+            .filter(|slot| request.contains_slot(*slot))
+            .collect();
+        assert_eq!(contained, vec![10, 12, 14]);
+    }
+
+    #[test]
+    fn test_blocks_by_range_validity() {
+        let mut request = BlocksByRangeRequest {
+            start_slot: 0,
+            count: MAX_REQUEST_BLOCKS,
+            step: 1,
+        };
+        assert!(request.is_valid());
+
+        request.count = MAX_REQUEST_BLOCKS + 1;
+        assert!(!request.is_valid());
+
+        request.count = 0;
+        assert!(!request.is_valid());
+
+        request.count = 1;
+        request.step = 0;
+        assert!(!request.is_valid());
+    }
+
+    #[test]
+    fn test_request_ssz_round_trip() {
+        let request = RPCRequest::BlocksByRange(BlocksByRangeRequest {
+            start_slot: 1,
+            count: 2,
+            step: 3,
+        });
+        let ssz = request.as_ssz();
+        assert_eq!(RPCRequest::from_ssz(request.protocol(), &ssz), Ok(request));
+
+        assert!(RPCRequest::from_ssz(Protocol::BeaconBlocksByRange, &ssz[1..]).is_err());
+
+        let request = RPCRequest::BlocksByRoot(BlocksByRootRequest {
+            block_roots: vec![Hash256::from("a".as_bytes()), Hash256::from("b".as_bytes())],
+        });
+        let ssz = request.as_ssz();
+        assert_eq!(RPCRequest::from_ssz(request.protocol(), &ssz), Ok(request));
+    }
+
+    #[test]
+    fn test_status_ssz_round_trip() {
+        let request = RPCRequest::Status(StatusMessage {
+            fork_digest: ForkDigest::new(0, &Hash256::zero()),
+            finalized_root: Hash256::from("finalized".as_bytes()),
+            finalized_slot: 64,
+            head_root: Hash256::from("head".as_bytes()),
+            head_slot: 100,
+        });
+        let ssz = request.as_ssz();
+        assert_eq!(ssz.len(), 4 + 32 + 8 + 32 + 8);
+        assert_eq!(RPCRequest::from_ssz(request.protocol(), &ssz), Ok(request));
+    }
+
+    #[test]
+    fn test_goodbye_ping_metadata_ssz_round_trip() {
+        for request in &[
+            RPCRequest::Goodbye(GoodbyeReason::IrrelevantNetwork),
+            RPCRequest::Goodbye(GoodbyeReason::Unknown(42)),
+            RPCRequest::Ping(Ping { seq_number: 7 }),
+            RPCRequest::MetaData,
+        ] {
+            let ssz = request.as_ssz();
+            assert_eq!(
+                RPCRequest::from_ssz(request.protocol(), &ssz),
+                Ok(request.clone())
+            );
+        }
+        assert!(RPCRequest::from_ssz(Protocol::MetaData, &[0]).is_err());
+
+        let metadata = RPCResponse::MetaData(MetaData {
+            seq_number: 3,
+            attnets: Bitfield::from_elem(64, true),
+        });
+        let ssz = metadata.as_ssz();
+        assert_eq!(
+            RPCResponse::from_ssz(Protocol::MetaData, &ssz),
+            Ok(metadata)
+        );
+    }
+
+    #[test]
+    fn test_goodbye_reason_codes() {
+        for code in &[1, 2, 3, 129, 250, 251, 42] {
+            assert_eq!(GoodbyeReason::from(*code).as_u64(), *code);
+        }
+        assert_eq!(GoodbyeReason::from(3), GoodbyeReason::Fault);
+    }
+
+    #[test]
+    fn test_fork_digest() {
+        let genesis_root = Hash256::from("genesis".as_bytes());
+        let digest = ForkDigest::new(0, &genesis_root);
+
+        assert_eq!(digest, ForkDigest::new(0, &genesis_root));
+        assert_ne!(digest, ForkDigest::new(1, &genesis_root));
+        assert_ne!(digest, ForkDigest::new(0, &Hash256::zero()));
+    }
+
+    #[test]
+    fn test_blocks_by_root_validity() {
+        let mut request = BlocksByRootRequest {
+            block_roots: vec![Hash256::zero(); MAX_REQUEST_BLOCKS as usize],
+        };
+        assert!(request.is_valid());
+
+        request.block_roots.push(Hash256::zero());
+        assert!(!request.is_valid());
+
+        request.block_roots.clear();
+        assert!(!request.is_valid());
+    }
+
+    #[test]
+    fn test_protocol_ids() {
+        for protocol in &[
+            Protocol::Status,
+            Protocol::Goodbye,
+            Protocol::Ping,
+            Protocol::MetaData,
+            Protocol::BeaconBlocksByRange,
+            Protocol::BeaconBlocksByRoot,
+        ] {
+            assert_eq!(Protocol::from_id(protocol.id()), Some(*protocol));
+        }
+        assert_eq!(Protocol::from_id("/eth2/unknown"), None);
+    }
+}
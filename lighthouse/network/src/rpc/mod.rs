@@ -0,0 +1,684 @@
+mod blocks_by_range;
+mod blocks_by_root;
+pub mod codec;
+mod methods;
+
+pub use self::codec::{CodecError, MAX_CHUNK_SIZE};
+pub use self::methods::{
+    BlocksByRangeRequest, BlocksByRootRequest, ForkDigest, GoodbyeReason, MetaData, Ping,
+    Protocol, RPCRequest, RPCResponse, ResponseCode, StatusMessage, MAX_REQUEST_BLOCKS,
+};
+
+use self::codec::{decode_request, encode_request, encode_response_chunk, ResponseDecoder};
+use super::enr::NodeId;
+use super::hashing::canonical_hash;
+use super::types::Hash256;
+use slog::Logger;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Peers are identified by the id of their node record.
+pub type PeerId = NodeId;
+/// Identifies a request stream between two peers, chosen by the requester.
+pub type RequestId = u64;
+
+/// The maximum time to wait for the first response chunk after sending a request.
+pub const TTFB_TIMEOUT: Duration = Duration::from_secs(5);
+/// The maximum time to wait between response chunks.
+pub const RESP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A message on a request stream, to be carried to or from a peer by the transport.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamMessage {
+    /// Opens stream `id`, negotiating `protocol` and carrying the encoded request.
+    Request {
+        id: RequestId,
+        protocol: &'static str,
+        bytes: Vec<u8>,
+    },
+    /// Bytes written by the responder to stream `id`.
+    Response { id: RequestId, bytes: Vec<u8> },
+    /// The responder has finished writing to stream `id`.
+    Close { id: RequestId },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RPCError {
+    /// The responder returned `ResponseCode::InvalidRequest` with the given message.
+    InvalidRequest(String),
+    /// The responder returned `ResponseCode::ServerError` with the given message.
+    ServerError(String),
+    /// The responder returned a code not defined by the protocol.
+    UnknownResponseCode(u8),
+    /// The response stream could not be decoded.
+    Codec(CodecError),
+    /// A response chunk could not be decoded or did not satisfy the request.
+    InvalidResponse,
+    /// The responder sent more response chunks than permitted by the request.
+    TooManyResponses,
+    /// The responder did not respond within `TTFB_TIMEOUT` or `RESP_TIMEOUT`.
+    Timeout,
+    /// The peer disconnected before the response was complete.
+    Disconnected,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RPCEvent {
+    /// A peer has made a request of us. It should be answered with `send_response` and/or
+    /// `send_error`, followed by `end_response`.
+    Request {
+        peer_id: PeerId,
+        id: RequestId,
+        request: RPCRequest,
+    },
+    /// A response chunk was received for one of our requests.
+    Response {
+        peer_id: PeerId,
+        id: RequestId,
+        response: RPCResponse,
+    },
+    /// One of our requests has received all of its responses.
+    ResponseComplete { peer_id: PeerId, id: RequestId },
+    /// One of our requests has failed, no further events will be emitted for it.
+    RequestFailed {
+        peer_id: PeerId,
+        id: RequestId,
+        error: RPCError,
+    },
+}
+
+struct OutboundRequest {
+    request: RPCRequest,
+    decoder: ResponseDecoder,
+    /// The time by which the next response chunk must arrive.
+    deadline: Instant,
+    responses: u64,
+    /// The slot of the last block received, used to ensure blocks arrive in ascending order.
+    last_slot: Option<u64>,
+    /// The roots of blocks received, used to ensure each requested block is sent at most once.
+    received_roots: HashSet<Hash256>,
+}
+
+impl OutboundRequest {
+    fn new(request: RPCRequest, now: Instant) -> Self {
+        Self {
+            request,
+            decoder: ResponseDecoder::new(),
+            deadline: now + TTFB_TIMEOUT,
+            responses: 0,
+            last_slot: None,
+            received_roots: HashSet::new(),
+        }
+    }
+
+    /// Returns `true` if `response` (decoded from `ssz`) satisfies the request, given the
+    /// responses received so far.
+    fn accepts(&mut self, response: &RPCResponse, ssz: &[u8]) -> bool {
+        match (&self.request, response) {
+            (RPCRequest::Status(_), RPCResponse::Status(_))
+            | (RPCRequest::Ping(_), RPCResponse::Pong(_))
+            | (RPCRequest::MetaData, RPCResponse::MetaData(_)) => true,
+            (RPCRequest::BlocksByRange(range), RPCResponse::BlocksByRange(block)) => {
+                let ascending = match self.last_slot {
+                    Some(last_slot) => block.slot > last_slot,
+                    None => true,
+                };
+                self.last_slot = Some(block.slot);
+                ascending && range.contains_slot(block.slot)
+            }
+            (RPCRequest::BlocksByRoot(request), RPCResponse::BlocksByRoot(_)) => {
+                let root = Hash256::from(&canonical_hash(ssz)[..]);
+                request.block_roots.contains(&root) && self.received_roots.insert(root)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The request/response protocols, handling both the requester and responder sides.
+///
+/// Like `Discovery`, `RPC` is a state machine: messages received from peers are supplied via
+/// `on_message`, messages to be sent are collected via `next_outbound` and timeouts are only
+/// checked when `poll` is called.
+pub struct RPC {
+    outbound_requests: HashMap<(PeerId, RequestId), OutboundRequest>,
+    /// Requests made of us which have not yet been ended, with the protocol they were made on.
+    inbound_requests: HashMap<(PeerId, RequestId), Protocol>,
+    next_request_id: RequestId,
+    outbound: VecDeque<(PeerId, StreamMessage)>,
+    events: VecDeque<RPCEvent>,
+    log: Logger,
+}
+
+impl RPC {
+    pub fn new(log: Logger) -> Self {
+        Self {
+            outbound_requests: HashMap::new(),
+            inbound_requests: HashMap::new(),
+            next_request_id: 0,
+            outbound: VecDeque::new(),
+            events: VecDeque::new(),
+            log,
+        }
+    }
+
+    /// Sends a request to `peer_id`. Responses are returned as `RPCEvent`s bearing the returned
+    /// `RequestId`.
+    pub fn send_request(&mut self, peer_id: PeerId, request: RPCRequest, now: Instant) -> RequestId {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+
+        let message = StreamMessage::Request {
+            id,
+            protocol: request.protocol().id(),
+            bytes: encode_request(&request.as_ssz()),
+        };
+        self.outbound.push_back((peer_id, message));
+        self.outbound_requests
+            .insert((peer_id, id), OutboundRequest::new(request, now));
+        id
+    }
+
+    /// Informs `peer_id` that we are disconnecting from it.
+    ///
+    /// The peer closes the stream without responding, so the caller may disconnect once the
+    /// request completes, fails, or after a short delay.
+    pub fn send_goodbye(&mut self, peer_id: PeerId, reason: GoodbyeReason, now: Instant) -> RequestId {
+        debug!(self.log, "Sending goodbye"; "peer_id" => format!("{:?}", peer_id), "reason" => format!("{:?}", reason));
+        self.send_request(peer_id, RPCRequest::Goodbye(reason), now)
+    }
+
+    /// Sends a successful response chunk for an inbound request.
+    pub fn send_response(&mut self, peer_id: PeerId, id: RequestId, response: &RPCResponse) {
+        let bytes = encode_response_chunk(ResponseCode::Success, &response.as_ssz());
+        self.write_response(peer_id, id, bytes);
+    }
+
+    /// Sends an error response chunk for an inbound request.
+    pub fn send_error(&mut self, peer_id: PeerId, id: RequestId, code: ResponseCode, message: &str) {
+        let bytes = encode_response_chunk(code, message.as_bytes());
+        self.write_response(peer_id, id, bytes);
+    }
+
+    /// Closes the stream for an inbound request, indicating that all responses have been sent.
+    pub fn end_response(&mut self, peer_id: PeerId, id: RequestId) {
+        if self.inbound_requests.remove(&(peer_id, id)).is_some() {
+            self.outbound.push_back((peer_id, StreamMessage::Close { id }));
+        }
+    }
+
+    fn write_response(&mut self, peer_id: PeerId, id: RequestId, bytes: Vec<u8>) {
+        if self.inbound_requests.contains_key(&(peer_id, id)) {
+            self.outbound
+                .push_back((peer_id, StreamMessage::Response { id, bytes }));
+        } else {
+            warn!(self.log, "Response to unknown request"; "peer_id" => format!("{:?}", peer_id), "id" => id);
+        }
+    }
+
+    /// Returns the next message to be sent, if any.
+    pub fn next_outbound(&mut self) -> Option<(PeerId, StreamMessage)> {
+        self.outbound.pop_front()
+    }
+
+    /// Fails any requests which have timed out and returns the next event, if any.
+    pub fn poll(&mut self, now: Instant) -> Option<RPCEvent> {
+        let expired: Vec<(PeerId, RequestId)> = self
+            .outbound_requests
+            .iter()
+            .filter(|(_, request)| now >= request.deadline)
+            .map(|(key, _)| *key)
+            .collect();
+        for (peer_id, id) in expired {
+            self.fail_request(peer_id, id, RPCError::Timeout);
+        }
+        self.events.pop_front()
+    }
+
+    /// Fails all outstanding requests to `peer_id` and forgets any requests it made of us.
+    pub fn on_disconnect(&mut self, peer_id: PeerId) {
+        let outstanding: Vec<RequestId> = self
+            .outbound_requests
+            .keys()
+            .filter(|(peer, _)| *peer == peer_id)
+            .map(|(_, id)| *id)
+            .collect();
+        for id in outstanding {
+            self.fail_request(peer_id, id, RPCError::Disconnected);
+        }
+        self.inbound_requests.retain(|(peer, _), _| *peer != peer_id);
+    }
+
+    /// Processes a message received from `peer_id`.
+    pub fn on_message(&mut self, peer_id: PeerId, message: StreamMessage, now: Instant) {
+        match message {
+            StreamMessage::Request {
+                id,
+                protocol,
+                bytes,
+            } => self.on_request(peer_id, id, protocol, &bytes),
+            StreamMessage::Response { id, bytes } => self.on_response(peer_id, id, &bytes, now),
+            StreamMessage::Close { id } => self.on_close(peer_id, id),
+        }
+    }
+
+    fn on_request(&mut self, peer_id: PeerId, id: RequestId, protocol_id: &str, bytes: &[u8]) {
+        let protocol = match Protocol::from_id(protocol_id) {
+            Some(protocol) => protocol,
+            None => {
+                debug!(self.log, "Request for unsupported protocol"; "protocol" => protocol_id);
+                self.outbound.push_back((peer_id, StreamMessage::Close { id }));
+                return;
+            }
+        };
+        if self.inbound_requests.contains_key(&(peer_id, id)) {
+            debug!(self.log, "Duplicate request id"; "peer_id" => format!("{:?}", peer_id), "id" => id);
+            return;
+        }
+        self.inbound_requests.insert((peer_id, id), protocol);
+
+        let request = decode_request(bytes)
+            .ok()
+            .and_then(|ssz| RPCRequest::from_ssz(protocol, &ssz).ok());
+        match request {
+            /*
+             * A goodbye has no response, so the stream is closed immediately. The event is still
+             * emitted so the peer can be disconnected.
+             */
+            Some(RPCRequest::Goodbye(reason)) => {
+                self.end_response(peer_id, id);
+                self.events.push_back(RPCEvent::Request {
+                    peer_id,
+                    id,
+                    request: RPCRequest::Goodbye(reason),
+                });
+            }
+            Some(request) => self.events.push_back(RPCEvent::Request {
+                peer_id,
+                id,
+                request,
+            }),
+            None => {
+                self.send_error(peer_id, id, ResponseCode::InvalidRequest, "Invalid request");
+                self.end_response(peer_id, id);
+            }
+        }
+    }
+
+    fn on_response(&mut self, peer_id: PeerId, id: RequestId, bytes: &[u8], now: Instant) {
+        let result = match self.outbound_requests.get_mut(&(peer_id, id)) {
+            Some(request) => {
+                request.decoder.push(bytes);
+                request.deadline = now + RESP_TIMEOUT;
+                process_chunks(peer_id, id, request, &mut self.events)
+            }
+            None => {
+                debug!(self.log, "Response for unknown request"; "peer_id" => format!("{:?}", peer_id), "id" => id);
+                return;
+            }
+        };
+        if let Err(error) = result {
+            self.fail_request(peer_id, id, error);
+        }
+    }
+
+    fn on_close(&mut self, peer_id: PeerId, id: RequestId) {
+        let request = match self.outbound_requests.remove(&(peer_id, id)) {
+            Some(request) => request,
+            None => return,
+        };
+        if request.decoder.has_partial_chunk() {
+            self.events.push_back(RPCEvent::RequestFailed {
+                peer_id,
+                id,
+                error: RPCError::Codec(CodecError::Incomplete),
+            });
+        } else {
+            self.events
+                .push_back(RPCEvent::ResponseComplete { peer_id, id });
+        }
+    }
+
+    fn fail_request(&mut self, peer_id: PeerId, id: RequestId, error: RPCError) {
+        if self.outbound_requests.remove(&(peer_id, id)).is_some() {
+            debug!(self.log, "RPC request failed";
+                   "peer_id" => format!("{:?}", peer_id),
+                   "id" => id,
+                   "error" => format!("{:?}", error));
+            self.events.push_back(RPCEvent::RequestFailed { peer_id, id, error });
+        }
+    }
+}
+
+/// Emits an event for each complete response chunk buffered for `request`.
+///
+/// Returns an error if a chunk is invalid, after which the request should be failed.
+fn process_chunks(
+    peer_id: PeerId,
+    id: RequestId,
+    request: &mut OutboundRequest,
+    events: &mut VecDeque<RPCEvent>,
+) -> Result<(), RPCError> {
+    while let Some((code, payload)) = request.decoder.next_chunk().map_err(RPCError::Codec)? {
+        let message = || String::from_utf8_lossy(&payload).into_owned();
+        match code {
+            ResponseCode::Success => {}
+            ResponseCode::InvalidRequest => return Err(RPCError::InvalidRequest(message())),
+            ResponseCode::ServerError => return Err(RPCError::ServerError(message())),
+            ResponseCode::Unknown(code) => return Err(RPCError::UnknownResponseCode(code)),
+        }
+
+        request.responses += 1;
+        if request.responses > request.request.max_responses() {
+            return Err(RPCError::TooManyResponses);
+        }
+
+        let response = RPCResponse::from_ssz(request.request.protocol(), &payload)
+            .map_err(|_| RPCError::InvalidResponse)?;
+        if !request.accepts(&response, &payload) {
+            return Err(RPCError::InvalidResponse);
+        }
+        events.push_back(RPCEvent::Response {
+            peer_id,
+            id,
+            response,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::BeaconBlock;
+    use super::*;
+    use slog::Discard;
+
+    fn block_at_slot(slot: u64) -> BeaconBlock {
+        let mut block = BeaconBlock::zero();
+        block.slot = slot;
+        block
+    }
+
+    fn range_request(start_slot: u64, count: u64) -> RPCRequest {
+        RPCRequest::BlocksByRange(BlocksByRangeRequest {
+            start_slot,
+            count,
+            step: 1,
+        })
+    }
+
+    fn rpc() -> RPC {
+        RPC::new(Logger::root(Discard, o!()))
+    }
+
+    /// Delivers all messages between `a` and `b`, returning the events emitted by each.
+    fn exchange(a: &mut RPC, a_id: PeerId, b: &mut RPC, b_id: PeerId, now: Instant) -> (Vec<RPCEvent>, Vec<RPCEvent>) {
+        let mut a_events = vec![];
+        let mut b_events = vec![];
+        loop {
+            let mut delivered = false;
+            while let Some((_, message)) = a.next_outbound() {
+                b.on_message(a_id, message, now);
+                delivered = true;
+            }
+            while let Some((_, message)) = b.next_outbound() {
+                a.on_message(b_id, message, now);
+                delivered = true;
+            }
+            while let Some(event) = a.poll(now) {
+                a_events.push(event);
+            }
+            while let Some(event) = b.poll(now) {
+                b_events.push(event);
+            }
+            if !delivered {
+                return (a_events, b_events);
+            }
+        }
+    }
+
+    #[test]
+    fn test_request_response() {
+        let (mut a, mut b) = (rpc(), rpc());
+        let (a_id, b_id) = (NodeId::random(), NodeId::random());
+        let now = Instant::now();
+
+        let request = range_request(5, 3);
+        let id = a.send_request(b_id, request.clone(), now);
+        let (_, b_events) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            b_events,
+            vec![RPCEvent::Request {
+                peer_id: a_id,
+                id,
+                request,
+            }]
+        );
+
+        b.send_response(a_id, id, &RPCResponse::BlocksByRange(block_at_slot(5)));
+        b.send_response(a_id, id, &RPCResponse::BlocksByRange(block_at_slot(7)));
+        b.end_response(a_id, id);
+        let (a_events, _) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            a_events,
+            vec![
+                RPCEvent::Response {
+                    peer_id: b_id,
+                    id,
+                    response: RPCResponse::BlocksByRange(block_at_slot(5)),
+                },
+                RPCEvent::Response {
+                    peer_id: b_id,
+                    id,
+                    response: RPCResponse::BlocksByRange(block_at_slot(7)),
+                },
+                RPCEvent::ResponseComplete { peer_id: b_id, id },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_response() {
+        let (mut a, mut b) = (rpc(), rpc());
+        let (a_id, b_id) = (NodeId::random(), NodeId::random());
+        let now = Instant::now();
+
+        let id = a.send_request(b_id, range_request(0, 1), now);
+        exchange(&mut a, a_id, &mut b, b_id, now);
+        b.send_error(a_id, id, ResponseCode::ServerError, "unavailable");
+        b.end_response(a_id, id);
+
+        let (a_events, _) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            a_events,
+            vec![RPCEvent::RequestFailed {
+                peer_id: b_id,
+                id,
+                error: RPCError::ServerError("unavailable".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_request_is_rejected() {
+        let (mut a, mut b) = (rpc(), rpc());
+        let (a_id, b_id) = (NodeId::random(), NodeId::random());
+        let now = Instant::now();
+
+        a.outbound_requests
+            .insert((b_id, 0), OutboundRequest::new(range_request(0, 1), now));
+        b.on_message(
+            a_id,
+            StreamMessage::Request {
+                id: 0,
+                protocol: Protocol::BeaconBlocksByRange.id(),
+                bytes: encode_request(&[1, 2, 3]),
+            },
+            now,
+        );
+
+        let (a_events, b_events) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert!(b_events.is_empty());
+        match &a_events[0] {
+            RPCEvent::RequestFailed {
+                error: RPCError::InvalidRequest(_),
+                ..
+            } => {}
+            other => panic!("expected invalid request, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_responses_fail_request() {
+        let (mut a, mut b) = (rpc(), rpc());
+        let (a_id, b_id) = (NodeId::random(), NodeId::random());
+        let now = Instant::now();
+
+        /*
+         * A block outside the requested range.
+         */
+        let id = a.send_request(b_id, range_request(5, 2), now);
+        exchange(&mut a, a_id, &mut b, b_id, now);
+        b.send_response(a_id, id, &RPCResponse::BlocksByRange(block_at_slot(9)));
+        let (a_events, _) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            a_events,
+            vec![RPCEvent::RequestFailed {
+                peer_id: b_id,
+                id,
+                error: RPCError::InvalidResponse,
+            }]
+        );
+
+        /*
+         * Blocks out of order.
+         */
+        let id = a.send_request(b_id, range_request(5, 2), now);
+        exchange(&mut a, a_id, &mut b, b_id, now);
+        b.send_response(a_id, id, &RPCResponse::BlocksByRange(block_at_slot(6)));
+        b.send_response(a_id, id, &RPCResponse::BlocksByRange(block_at_slot(5)));
+        let (a_events, _) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            a_events.last(),
+            Some(&RPCEvent::RequestFailed {
+                peer_id: b_id,
+                id,
+                error: RPCError::InvalidResponse,
+            })
+        );
+    }
+
+    #[test]
+    fn test_blocks_by_root_responses_must_be_requested() {
+        let (mut a, mut b) = (rpc(), rpc());
+        let (a_id, b_id) = (NodeId::random(), NodeId::random());
+        let now = Instant::now();
+
+        let block = block_at_slot(1);
+        let root = Hash256::from(&canonical_hash(&RPCResponse::BlocksByRoot(block.clone()).as_ssz())[..]);
+        let request = RPCRequest::BlocksByRoot(BlocksByRootRequest {
+            block_roots: vec![root],
+        });
+
+        let id = a.send_request(b_id, request.clone(), now);
+        exchange(&mut a, a_id, &mut b, b_id, now);
+        b.send_response(a_id, id, &RPCResponse::BlocksByRoot(block.clone()));
+        b.end_response(a_id, id);
+        let (a_events, _) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            a_events,
+            vec![
+                RPCEvent::Response {
+                    peer_id: b_id,
+                    id,
+                    response: RPCResponse::BlocksByRoot(block.clone()),
+                },
+                RPCEvent::ResponseComplete { peer_id: b_id, id },
+            ]
+        );
+
+        let id = a.send_request(b_id, request, now);
+        exchange(&mut a, a_id, &mut b, b_id, now);
+        b.send_response(a_id, id, &RPCResponse::BlocksByRoot(block_at_slot(2)));
+        let (a_events, _) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            a_events,
+            vec![RPCEvent::RequestFailed {
+                peer_id: b_id,
+                id,
+                error: RPCError::InvalidResponse,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_goodbye() {
+        let (mut a, mut b) = (rpc(), rpc());
+        let (a_id, b_id) = (NodeId::random(), NodeId::random());
+        let now = Instant::now();
+
+        let id = a.send_goodbye(b_id, GoodbyeReason::ClientShutdown, now);
+        let (a_events, b_events) = exchange(&mut a, a_id, &mut b, b_id, now);
+        assert_eq!(
+            b_events,
+            vec![RPCEvent::Request {
+                peer_id: a_id,
+                id,
+                request: RPCRequest::Goodbye(GoodbyeReason::ClientShutdown),
+            }]
+        );
+        assert_eq!(a_events, vec![RPCEvent::ResponseComplete { peer_id: b_id, id }]);
+    }
+
+    #[test]
+    fn test_request_timeouts() {
+        let mut a = rpc();
+        let b_id = NodeId::random();
+        let now = Instant::now();
+
+        let id = a.send_request(b_id, range_request(0, 2), now);
+        assert_eq!(a.poll(now + TTFB_TIMEOUT - Duration::from_millis(1)), None);
+
+        /*
+         * Receiving a chunk extends the deadline by `RESP_TIMEOUT`.
+         */
+        let bytes = encode_response_chunk(
+            ResponseCode::Success,
+            &RPCResponse::BlocksByRange(block_at_slot(0)).as_ssz(),
+        );
+        a.on_message(b_id, StreamMessage::Response { id, bytes }, now + Duration::from_secs(1));
+        match a.poll(now + TTFB_TIMEOUT) {
+            Some(RPCEvent::Response { .. }) => {}
+            other => panic!("expected response, got {:?}", other),
+        }
+        assert_eq!(a.poll(now + TTFB_TIMEOUT), None);
+
+        assert_eq!(
+            a.poll(now + Duration::from_secs(1) + RESP_TIMEOUT),
+            Some(RPCEvent::RequestFailed {
+                peer_id: b_id,
+                id,
+                error: RPCError::Timeout,
+            })
+        );
+    }
+
+    #[test]
+    fn test_disconnect_fails_requests() {
+        let mut a = rpc();
+        let b_id = NodeId::random();
+        let now = Instant::now();
+
+        let id = a.send_request(b_id, range_request(0, 2), now);
+        a.on_disconnect(b_id);
+        assert_eq!(
+            a.poll(now),
+            Some(RPCEvent::RequestFailed {
+                peer_id: b_id,
+                id,
+                error: RPCError::Disconnected,
+            })
+        );
+        assert_eq!(a.poll(now + TTFB_TIMEOUT), None);
+    }
+}
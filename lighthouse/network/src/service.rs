@@ -0,0 +1,571 @@
+use super::boot_node::{load_or_generate_keypair, BootNodeError};
+use super::config::NetworkConfig;
+use super::db::stores::PeerStore;
+use super::db::ClientDB;
+use super::discovery::{DiscoveryEvent, DiscoveryService};
+use super::enr::ATTESTATION_SUBNET_COUNT;
+use super::gossip::{GossipKind, PeerScoreParams, PeerScores};
+use super::local_enr::{LocalEnr, LocalEnrError};
+use super::metrics;
+use super::peer_manager::{
+    duration_as_secs_f64, PeerManager, PeerManagerEvent, PeerPersistenceError,
+};
+use super::rpc::{ForkDigest, PeerId};
+use super::status::HandshakeEvent;
+use super::upnp::{UPnPEvent, UPnPService};
+use lighthouse_metrics::{inc_counter_vec, observe_vec, set_gauge_vec};
+use slog::Logger;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use task_executor::TaskExecutor;
+
+/// The file in the network directory holding the node's secret key.
+const KEY_FILE: &str = "key";
+/// How many slots ahead of an aggregation duty the subnet of the duty is expected to have peers
+/// in its mesh.
+pub const AGGREGATION_MESH_LOOKAHEAD_SLOTS: u64 = 2;
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    /// The key file does not contain a valid secret key.
+    InvalidKey,
+    LocalEnr(LocalEnrError),
+    PeerPersistence(PeerPersistenceError),
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(e: io::Error) -> NetworkError {
+        NetworkError::Io(e)
+    }
+}
+
+impl From<BootNodeError> for NetworkError {
+    fn from(e: BootNodeError) -> NetworkError {
+        match e {
+            BootNodeError::Io(e) => NetworkError::Io(e),
+            BootNodeError::InvalidKey => NetworkError::InvalidKey,
+        }
+    }
+}
+
+impl From<LocalEnrError> for NetworkError {
+    fn from(e: LocalEnrError) -> NetworkError {
+        NetworkError::LocalEnr(e)
+    }
+}
+
+impl From<PeerPersistenceError> for NetworkError {
+    fn from(e: PeerPersistenceError) -> NetworkError {
+        NetworkError::PeerPersistence(e)
+    }
+}
+
+/// Brings up the networking components of a beacon node from a `NetworkConfig`: the node's
+/// identity and record, the peer manager with its persisted peers, discovery, the forwarding of
+/// ports with UPnP and the scoring of gossip peers.
+pub struct NetworkService<T: ClientDB> {
+    local_enr: LocalEnr,
+    /// Shared with the HTTP API, which lists, bans and dials peers.
+    peer_manager: Arc<RwLock<PeerManager>>,
+    gossip_scores: PeerScores,
+    /// `None` if discovery is disabled.
+    discovery: Option<DiscoveryService>,
+    /// `None` if UPnP is disabled.
+    upnp: Option<UPnPService>,
+    /// The mappings made by UPnP, until it fails.
+    upnp_events: Option<Receiver<UPnPEvent>>,
+    /// Addresses the operator asked to dial, awaiting the transport.
+    dials: VecDeque<SocketAddr>,
+    store: PeerStore<T>,
+    log: Logger,
+}
+
+impl<T: ClientDB> NetworkService<T> {
+    /// Starts the service, returning the receiver of discovery events, if discovery is enabled.
+    ///
+    /// The persisted peers are loaded into `peer_manager`, which should have been built from
+    /// `config.peer_manager_config()`.
+    pub fn start(
+        config: &NetworkConfig,
+        peer_manager: Arc<RwLock<PeerManager>>,
+        fork_digest: ForkDigest,
+        gossip_score_params: PeerScoreParams,
+        store: PeerStore<T>,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> Result<(Self, Option<Receiver<DiscoveryEvent>>), NetworkError> {
+        fs::create_dir_all(&config.network_dir)?;
+        let keypair = load_or_generate_keypair(&config.network_dir.join(KEY_FILE))?;
+        let local_enr = LocalEnr::load_or_build(keypair, &config.enr_config(fork_digest), &store)?;
+        if local_enr.enr().ip().is_none() {
+            warn!(log, "Local record has no address"; "help" => "set --enr-address");
+        }
+
+        let now = Instant::now();
+        let known_peers = peer_manager
+            .write()
+            .expect("Peer manager lock poisoned")
+            .load(&store, now)?;
+
+        let (discovery, events) = if config.disable_discovery {
+            info!(log, "Discovery disabled");
+            (None, None)
+        } else {
+            let (discovery, events) = DiscoveryService::start(
+                config.discovery_listen_addr(),
+                local_enr.enr().clone(),
+                config.discovery_config(),
+                executor,
+                log.clone(),
+            )?;
+            /*
+             * Previously known peers seed the routing table alongside the boot nodes.
+             */
+            for enr in known_peers {
+                discovery.add_enr(enr);
+            }
+            (Some(discovery), Some(events))
+        };
+        let (upnp, upnp_events) = if config.disable_upnp {
+            (None, None)
+        } else {
+            let (upnp, events) = UPnPService::start(config.upnp_config(), executor, log.clone());
+            (Some(upnp), Some(events))
+        };
+        info!(log, "Network service started"; "enr" => format!("{}", local_enr.enr()), "target_peers" => config.target_peers);
+
+        let service = Self {
+            local_enr,
+            peer_manager,
+            gossip_scores: PeerScores::new(gossip_score_params, now),
+            discovery,
+            upnp,
+            upnp_events,
+            dials: VecDeque::new(),
+            store,
+            log,
+        };
+        Ok((service, events))
+    }
+
+    pub fn local_enr(&self) -> &LocalEnr {
+        &self.local_enr
+    }
+
+    pub fn peer_manager(&self) -> &Arc<RwLock<PeerManager>> {
+        &self.peer_manager
+    }
+
+    pub fn gossip_scores_mut(&mut self) -> &mut PeerScores {
+        &mut self.gossip_scores
+    }
+
+    /// Decays the gossip scores and applies them to the peer manager, which then prunes the
+    /// lowest scoring peers, and carries out the events of the peer manager. Called once each
+    /// heartbeat interval.
+    pub fn heartbeat(&mut self, now: Instant) {
+        self.gossip_scores.refresh(now);
+        let scores = self.gossip_scores.scores(now);
+        {
+            let mut peer_manager = self
+                .peer_manager
+                .write()
+                .expect("Peer manager lock poisoned");
+            peer_manager.update_gossip_scores(&scores, now);
+            peer_manager.heartbeat(now);
+        }
+        self.process_peer_manager_events(now);
+        if let Err(e) = self.process_upnp_events() {
+            warn!(self.log, "Unable to advertise UPnP mapping"; "error" => format!("{:?}", e));
+        }
+        self.update_mesh_metrics();
+    }
+
+    /// Advertises in the local record the external address and ports forwarded by UPnP.
+    pub fn process_upnp_events(&mut self) -> Result<(), NetworkError> {
+        let events: Vec<UPnPEvent> = match self.upnp_events {
+            Some(ref events) => events.try_iter().collect(),
+            None => return Ok(()),
+        };
+        for event in events {
+            match event {
+                UPnPEvent::Mapped {
+                    external_ip,
+                    tcp_port,
+                    udp_port,
+                } => {
+                    let changed = self.local_enr.set_external_address(
+                        external_ip,
+                        tcp_port,
+                        udp_port,
+                        &self.store,
+                    )?;
+                    if changed {
+                        info!(self.log, "Advertising UPnP mapping"; "enr" => format!("{}", self.local_enr.enr()));
+                        self.push_local_enr();
+                    }
+                }
+                /*
+                 * The service has logged the failure and stopped.
+                 */
+                UPnPEvent::Failed(_) => {
+                    self.upnp = None;
+                    self.upnp_events = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Drains the events of the peer manager, including those caused through the HTTP API.
+    /// Peers which are disconnected or banned leave the gossip meshes, and the addresses to dial
+    /// are queued for the transport.
+    pub fn process_peer_manager_events(&mut self, now: Instant) {
+        loop {
+            let event = self
+                .peer_manager
+                .write()
+                .expect("Peer manager lock poisoned")
+                .poll();
+            match event {
+                Some(PeerManagerEvent::DisconnectPeer { peer_id, reason }) => {
+                    debug!(self.log, "Disconnecting peer"; "peer_id" => format!("{:?}", peer_id), "reason" => format!("{:?}", reason));
+                    self.gossip_scores.disconnect(&peer_id, now);
+                }
+                Some(PeerManagerEvent::Banned { peer_id }) => {
+                    self.gossip_scores.disconnect(&peer_id, now)
+                }
+                Some(PeerManagerEvent::Unbanned { .. }) => {}
+                Some(PeerManagerEvent::Dial { address }) => {
+                    info!(self.log, "Dialing peer"; "address" => format!("{}", address));
+                    self.dials.push_back(address);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Records the outcome of the status handshake with a peer: the chain of a compatible peer
+    /// is tracked to judge whether the node is synced, while an incompatible peer is forgotten
+    /// as it is disconnected.
+    pub fn on_handshake_event(&mut self, event: &HandshakeEvent, now: Instant) {
+        let mut peer_manager = self
+            .peer_manager
+            .write()
+            .expect("Peer manager lock poisoned");
+        match *event {
+            HandshakeEvent::Compatible {
+                ref peer_id,
+                ref status,
+            } => peer_manager.update_sync_status(peer_id, status, now),
+            HandshakeEvent::Disconnect { ref peer_id, .. } => {
+                peer_manager.on_disconnect(peer_id, now);
+                self.gossip_scores.disconnect(peer_id, now);
+            }
+        }
+    }
+
+    /// Returns the next address the transport should dial, if any.
+    pub fn next_dial(&mut self) -> Option<SocketAddr> {
+        self.dials.pop_front()
+    }
+
+    /// Records a valid attestation on `subnet` from `peer_id`, `first` if no other peer delivered
+    /// it before, received `delay` after the start of its slot.
+    pub fn observe_attestation(
+        &mut self,
+        peer_id: PeerId,
+        subnet: u64,
+        first: bool,
+        delay: Duration,
+    ) {
+        self.gossip_scores
+            .deliver(peer_id, GossipKind::Attestation(subnet), first);
+        let label = subnet.to_string();
+        inc_counter_vec(&metrics::SUBNET_MESSAGES, &[&label]);
+        if first {
+            observe_vec(
+                &metrics::SUBNET_FIRST_SEEN_DELAY,
+                &[&label],
+                duration_as_secs_f64(delay),
+            );
+        }
+    }
+
+    /// Returns the number of peers in the mesh of the attestation subnet `subnet`.
+    pub fn subnet_mesh_peers(&self, subnet: u64) -> usize {
+        self.gossip_scores
+            .mesh_peers(GossipKind::Attestation(subnet))
+    }
+
+    /// Sets the mesh peer count of each attestation subnet.
+    pub fn update_mesh_metrics(&self) {
+        for subnet in 0..ATTESTATION_SUBNET_COUNT as u64 {
+            set_gauge_vec(
+                &metrics::SUBNET_MESH_PEERS,
+                &[&subnet.to_string()],
+                self.subnet_mesh_peers(subnet) as i64,
+            );
+        }
+    }
+
+    /// Returns the subnets of `aggregations`, each with the slot of its earliest aggregation
+    /// duty, whose duty is due within `AGGREGATION_MESH_LOOKAHEAD_SLOTS` of `present_slot` but
+    /// which have no peers in their mesh, so that the duty would aggregate few attestations.
+    pub fn unmeshed_aggregation_subnets(
+        &self,
+        aggregations: &BTreeMap<u64, u64>,
+        present_slot: u64,
+    ) -> Vec<(u64, u64)> {
+        aggregations
+            .iter()
+            .filter(|&(_, &slot)| {
+                slot >= present_slot && slot <= present_slot + AGGREGATION_MESH_LOOKAHEAD_SLOTS
+            })
+            .filter(|&(&subnet, _)| self.subnet_mesh_peers(subnet) == 0)
+            .map(|(&subnet, &slot)| (subnet, slot))
+            .collect()
+    }
+
+    /// Advertises in the local record the attestation subnets of `subscriptions`, each with the
+    /// last slot for which it is needed, except those which expired before `present_slot`.
+    ///
+    /// Returns `true` if the record changed, in which case it has been pushed to discovery.
+    pub fn update_subnets(
+        &mut self,
+        subscriptions: &BTreeMap<u64, u64>,
+        present_slot: u64,
+    ) -> Result<bool, NetworkError> {
+        let mut changed = false;
+        for subnet in 0..ATTESTATION_SUBNET_COUNT as u64 {
+            let subscribed = match subscriptions.get(&subnet) {
+                Some(until_slot) => *until_slot >= present_slot,
+                None => false,
+            };
+            changed |= self.local_enr.set_subnet(subnet, subscribed, &self.store)?;
+        }
+        if changed {
+            self.push_local_enr();
+        }
+        Ok(changed)
+    }
+
+    fn push_local_enr(&self) {
+        if let Some(ref discovery) = self.discovery {
+            discovery.set_local_enr(self.local_enr.enr().clone());
+        }
+    }
+
+    pub fn discovery(&self) -> Option<&DiscoveryService> {
+        self.discovery.as_ref()
+    }
+
+    /// Writes the known peers and bans to the store, to be restored on the next start.
+    pub fn persist(&self, now: Instant) -> Result<(), NetworkError> {
+        self.peer_manager
+            .read()
+            .expect("Peer manager lock poisoned")
+            .persist(&self.store, now)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::db::MemoryDB;
+    use super::super::enr::NodeId;
+    use super::super::rand;
+    use super::super::rpc::StatusMessage;
+    use super::super::status::IncompatibleReason;
+    use super::super::types::Hash256;
+    use super::super::upnp::UPnPError;
+    use super::*;
+    use slog::Discard;
+    use std::net::Ipv4Addr;
+
+    fn config() -> NetworkConfig {
+        NetworkConfig {
+            network_dir: std::env::temp_dir()
+                .join(format!("network_service_test_{}", rand::random::<u64>())),
+            listen_address: Ipv4Addr::LOCALHOST.into(),
+            discovery_port: std::net::UdpSocket::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port(),
+            disable_upnp: true,
+            ..NetworkConfig::default()
+        }
+    }
+
+    fn score_params() -> PeerScoreParams {
+        PeerScoreParams::standard(Duration::from_secs(6), 64, 1_024)
+    }
+
+    fn peer_manager(config: &NetworkConfig, log: &Logger) -> Arc<RwLock<PeerManager>> {
+        Arc::new(RwLock::new(PeerManager::new(
+            config.peer_manager_config(),
+            log.clone(),
+        )))
+    }
+
+    #[test]
+    fn test_start_from_config() {
+        let config = config();
+        let db = Arc::new(MemoryDB::open());
+        let fork_digest = ForkDigest([0; 4]);
+        let log = Logger::root(Discard, o!());
+        let (executor, _) = TaskExecutor::new(log.clone());
+
+        let (service, events) = NetworkService::start(
+            &config,
+            peer_manager(&config, &log),
+            fork_digest,
+            score_params(),
+            PeerStore::new(db.clone()),
+            &executor,
+            log.clone(),
+        )
+        .unwrap();
+        assert!(service.discovery().is_some());
+        assert!(events.is_some());
+        let enr = service.local_enr().enr().clone();
+        assert_eq!(enr.ip(), Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(enr.tcp(), Some(config.libp2p_port));
+        drop(service);
+
+        /*
+         * The identity survives a restart, and no UDP port is advertised without discovery.
+         */
+        let config = NetworkConfig {
+            disable_discovery: true,
+            ..config
+        };
+        let peer_manager = peer_manager(&config, &log);
+        let (mut service, events) = NetworkService::start(
+            &config,
+            peer_manager.clone(),
+            fork_digest,
+            score_params(),
+            PeerStore::new(db),
+            &executor,
+            log,
+        )
+        .unwrap();
+        assert!(service.discovery().is_none());
+        assert!(events.is_none());
+        let restarted = service.local_enr().enr().clone();
+        assert_eq!(restarted.node_id(), enr.node_id());
+        assert_eq!(restarted.udp(), None);
+        assert!(restarted.seq() > enr.seq());
+
+        /*
+         * The record advertises the subnets subscribed until their last slot has passed.
+         */
+        let subscriptions = vec![(3, 10), (5, 12)].into_iter().collect();
+        assert!(service.update_subnets(&subscriptions, 10).unwrap());
+        assert!(!service.update_subnets(&subscriptions, 10).unwrap());
+        let enr = service.local_enr().enr();
+        assert!(enr.is_subscribed_to_subnet(3));
+        assert!(enr.is_subscribed_to_subnet(5));
+        assert!(!enr.is_subscribed_to_subnet(4));
+        assert!(enr.seq() > restarted.seq());
+        assert!(service.update_subnets(&subscriptions, 11).unwrap());
+        assert!(!service.local_enr().enr().is_subscribed_to_subnet(3));
+        assert!(service.local_enr().enr().is_subscribed_to_subnet(5));
+
+        /*
+         * Only subnets without mesh peers are reported, and only shortly before their duty.
+         */
+        let aggregations = vec![(3, 12), (5, 12), (6, 20)].into_iter().collect();
+        assert_eq!(
+            service.unmeshed_aggregation_subnets(&aggregations, 11),
+            vec![(3, 12), (5, 12)]
+        );
+        let now = Instant::now();
+        service
+            .gossip_scores_mut()
+            .graft(NodeId::random(), GossipKind::Attestation(5), now);
+        assert_eq!(service.subnet_mesh_peers(5), 1);
+        assert_eq!(
+            service.unmeshed_aggregation_subnets(&aggregations, 11),
+            vec![(3, 12)]
+        );
+        assert!(service
+            .unmeshed_aggregation_subnets(&aggregations, 13)
+            .is_empty());
+        service.observe_attestation(NodeId::random(), 5, true, Duration::from_secs(2));
+        service.update_mesh_metrics();
+
+        /*
+         * Events caused through the shared peer manager are carried out by the service.
+         */
+        let address = "1.2.3.4:9000".parse().unwrap();
+        assert!(peer_manager.write().unwrap().dial(address));
+        assert_eq!(service.next_dial(), None);
+        service.heartbeat(now);
+        assert_eq!(service.next_dial(), Some(address));
+        assert_eq!(service.next_dial(), None);
+        let banned = NodeId::random();
+        service
+            .gossip_scores_mut()
+            .graft(banned, GossipKind::Attestation(3), now);
+        assert!(peer_manager.write().unwrap().on_connect(banned, now));
+        peer_manager.write().unwrap().ban_peer(banned);
+        service.process_peer_manager_events(now);
+        assert_eq!(service.subnet_mesh_peers(3), 0);
+        assert_eq!(peer_manager.write().unwrap().poll(), None);
+
+        /*
+         * The statuses of compatible peers give the best known head, until they disconnect.
+         */
+        let peer_id = NodeId::random();
+        let status = StatusMessage {
+            fork_digest,
+            finalized_root: Hash256::zero(),
+            finalized_slot: 0,
+            head_root: Hash256::zero(),
+            head_slot: 42,
+        };
+        assert!(peer_manager.write().unwrap().on_connect(peer_id, now));
+        service.on_handshake_event(&HandshakeEvent::Compatible { peer_id, status }, now);
+        assert_eq!(peer_manager.read().unwrap().best_peer_head(), Some(42));
+        service.on_handshake_event(
+            &HandshakeEvent::Disconnect {
+                peer_id,
+                reason: IncompatibleReason::HeadSlotInFuture,
+            },
+            now,
+        );
+        assert_eq!(peer_manager.read().unwrap().best_peer_head(), None);
+
+        /*
+         * The ports forwarded by UPnP are advertised, keeping the configured ports which were not
+         * forwarded.
+         */
+        let udp_port = service.local_enr().enr().udp();
+        let (tx, rx) = std::sync::mpsc::channel();
+        service.upnp_events = Some(rx);
+        tx.send(UPnPEvent::Mapped {
+            external_ip: Ipv4Addr::new(203, 0, 113, 7),
+            tcp_port: Some(9100),
+            udp_port: None,
+        })
+        .unwrap();
+        service.process_upnp_events().unwrap();
+        let enr = service.local_enr().enr();
+        assert_eq!(enr.ip(), Some(Ipv4Addr::new(203, 0, 113, 7)));
+        assert_eq!(enr.tcp(), Some(9100));
+        assert_eq!(enr.udp(), udp_port);
+        tx.send(UPnPEvent::Failed(UPnPError::NoGateway)).unwrap();
+        service.process_upnp_events().unwrap();
+        assert!(service.upnp_events.is_none());
+        fs::remove_dir_all(&config.network_dir).unwrap();
+    }
+}
@@ -0,0 +1,414 @@
+use super::db::stores::BeaconBlockStore;
+use super::db::ClientDB;
+use super::rpc::{
+    GoodbyeReason, PeerId, RPCEvent, RPCRequest, RPCResponse, RequestId, StatusMessage, RPC,
+};
+use super::types::Hash256;
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+/// The number of slots a peer's head may be ahead of our clock, allowing for clock disparity.
+pub const HEAD_SLOT_TOLERANCE: u64 = 1;
+
+/// The reasons a peer's chain may be incompatible with our own.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IncompatibleReason {
+    /// The peer is on a different network or fork.
+    ForkDigestMismatch,
+    /// The peer has finalized a block which is not in our canonical chain.
+    FinalizedRootMismatch,
+    /// The peer claims a head slot which is in the future.
+    HeadSlotInFuture,
+    /// The peer did not provide its status.
+    NoStatus,
+}
+
+impl IncompatibleReason {
+    /// The reason to give the peer when disconnecting from it.
+    pub fn goodbye_reason(&self) -> GoodbyeReason {
+        match self {
+            IncompatibleReason::ForkDigestMismatch | IncompatibleReason::FinalizedRootMismatch => {
+                GoodbyeReason::IrrelevantNetwork
+            }
+            IncompatibleReason::HeadSlotInFuture | IncompatibleReason::NoStatus => {
+                GoodbyeReason::Fault
+            }
+        }
+    }
+}
+
+/// Checks that the chain described by `remote` can be reconciled with our own chain, described
+/// by `local`.
+///
+/// A peer which has finalized further than us is assumed compatible, as we cannot yet verify its
+/// finalized block.
+pub fn check_status<T: ClientDB>(
+    local: &StatusMessage,
+    remote: &StatusMessage,
+    current_slot: u64,
+    store: &BeaconBlockStore<T>,
+) -> Result<(), IncompatibleReason> {
+    if local.fork_digest != remote.fork_digest {
+        return Err(IncompatibleReason::ForkDigestMismatch);
+    }
+
+    if remote.head_slot > current_slot + HEAD_SLOT_TOLERANCE {
+        return Err(IncompatibleReason::HeadSlotInFuture);
+    }
+
+    /*
+     * The genesis block is implied by the fork digest, so only later finalized blocks need to be
+     * checked.
+     */
+    if remote.finalized_slot == 0 || remote.finalized_slot > local.finalized_slot {
+        return Ok(());
+    }
+    if remote.finalized_slot == local.finalized_slot {
+        return if remote.finalized_root == local.finalized_root {
+            Ok(())
+        } else {
+            Err(IncompatibleReason::FinalizedRootMismatch)
+        };
+    }
+
+    match store.block_at_slot(&local.finalized_root, remote.finalized_slot) {
+        Ok(Some((hash, _))) if Hash256::from(&hash[..]) == remote.finalized_root => Ok(()),
+        Ok(_) => Err(IncompatibleReason::FinalizedRootMismatch),
+        /*
+         * Our finalized chain could not be read, so the peer cannot be judged. Give it the
+         * benefit of the doubt rather than disconnecting a possibly honest peer.
+         */
+        Err(_) => Ok(()),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HandshakeEvent {
+    /// The peer is on a compatible chain.
+    Compatible {
+        peer_id: PeerId,
+        status: StatusMessage,
+    },
+    /// The peer is on an incompatible chain and should be disconnected.
+    Disconnect {
+        peer_id: PeerId,
+        reason: IncompatibleReason,
+    },
+}
+
+/// Performs the status handshake with each connected peer.
+///
+/// When a peer connects, each side sends a `Status` request containing its own status and
+/// responds to the other's request with the same. The status received from the peer, whether as
+/// a request or a response, is checked for compatibility.
+#[derive(Default)]
+pub struct Handshake {
+    /// Status requests we have sent, and whether a response has been received.
+    requests: HashMap<(PeerId, RequestId), bool>,
+    /// The latest status of each compatible peer.
+    statuses: HashMap<PeerId, StatusMessage>,
+    events: VecDeque<HandshakeEvent>,
+}
+
+impl Handshake {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends our status to a newly connected peer.
+    pub fn on_connect(
+        &mut self,
+        rpc: &mut RPC,
+        peer_id: PeerId,
+        local: &StatusMessage,
+        now: Instant,
+    ) {
+        let id = rpc.send_request(peer_id, RPCRequest::Status(local.clone()), now);
+        self.requests.insert((peer_id, id), false);
+    }
+
+    pub fn on_disconnect(&mut self, peer_id: &PeerId) {
+        self.statuses.remove(peer_id);
+        self.requests.retain(|(peer, _), _| peer != peer_id);
+    }
+
+    /// Returns the last known status of a compatible peer.
+    pub fn peer_status(&self, peer_id: &PeerId) -> Option<&StatusMessage> {
+        self.statuses.get(peer_id)
+    }
+
+    /// Processes the status-related `RPCEvent`s, responding to status requests as required.
+    ///
+    /// Events unrelated to the handshake are returned to the caller.
+    pub fn on_rpc_event<T: ClientDB>(
+        &mut self,
+        rpc: &mut RPC,
+        event: RPCEvent,
+        local: &StatusMessage,
+        current_slot: u64,
+        store: &BeaconBlockStore<T>,
+    ) -> Option<RPCEvent> {
+        match event {
+            RPCEvent::Request {
+                peer_id,
+                id,
+                request: RPCRequest::Status(remote),
+            } => {
+                rpc.send_response(peer_id, id, &RPCResponse::Status(local.clone()));
+                rpc.end_response(peer_id, id);
+                self.process_status(peer_id, remote, local, current_slot, store);
+                None
+            }
+            RPCEvent::Response {
+                peer_id,
+                id,
+                response: RPCResponse::Status(remote),
+            } => {
+                if let Some(answered) = self.requests.get_mut(&(peer_id, id)) {
+                    *answered = true;
+                }
+                self.process_status(peer_id, remote, local, current_slot, store);
+                None
+            }
+            RPCEvent::ResponseComplete { peer_id, id }
+            | RPCEvent::RequestFailed { peer_id, id, .. }
+                if self.requests.contains_key(&(peer_id, id)) =>
+            {
+                if let Some(false) = self.requests.remove(&(peer_id, id)) {
+                    self.disconnect(peer_id, IncompatibleReason::NoStatus);
+                }
+                None
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Returns the next event, if any.
+    pub fn poll(&mut self) -> Option<HandshakeEvent> {
+        self.events.pop_front()
+    }
+
+    fn process_status<T: ClientDB>(
+        &mut self,
+        peer_id: PeerId,
+        remote: StatusMessage,
+        local: &StatusMessage,
+        current_slot: u64,
+        store: &BeaconBlockStore<T>,
+    ) {
+        match check_status(local, &remote, current_slot, store) {
+            Ok(()) => {
+                self.statuses.insert(peer_id, remote.clone());
+                self.events.push_back(HandshakeEvent::Compatible {
+                    peer_id,
+                    status: remote,
+                });
+            }
+            Err(reason) => self.disconnect(peer_id, reason),
+        }
+    }
+
+    fn disconnect(&mut self, peer_id: PeerId, reason: IncompatibleReason) {
+        self.on_disconnect(&peer_id);
+        self.events
+            .push_back(HandshakeEvent::Disconnect { peer_id, reason });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::db::MemoryDB;
+    use super::super::enr::NodeId;
+    use super::super::rpc::ForkDigest;
+    use super::super::ssz::ssz_encode;
+    use super::super::types::BeaconBlock;
+    use super::*;
+    use slog::{Discard, Logger};
+    use std::sync::Arc;
+
+    fn status(finalized_root: Hash256, finalized_slot: u64, head_slot: u64) -> StatusMessage {
+        StatusMessage {
+            fork_digest: ForkDigest::new(0, &Hash256::zero()),
+            finalized_root,
+            finalized_slot,
+            head_root: Hash256::from("head".as_bytes()),
+            head_slot,
+        }
+    }
+
+    /// Stores a chain with a block at each of `slots`, returning the hash of each block.
+    fn build_chain(store: &BeaconBlockStore<MemoryDB>, slots: &[u64]) -> Vec<Hash256> {
+        let mut hashes: Vec<Hash256> = vec![];
+        for slot in slots {
+            let mut block = BeaconBlock::zero();
+            block.slot = *slot;
+            block
+                .ancestor_hashes
+                .push(hashes.last().cloned().unwrap_or_else(Hash256::zero));
+            let hash = Hash256::random();
+            store
+                .put_serialized_block(&hash, &ssz_encode(&block))
+                .unwrap();
+            hashes.push(hash);
+        }
+        hashes
+    }
+
+    #[test]
+    fn test_check_status() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let hashes = build_chain(&store, &[1, 2, 3, 4]);
+        let local = status(hashes[3], 4, 10);
+
+        assert_eq!(check_status(&local, &local, 10, &store), Ok(()));
+
+        let mut remote = local.clone();
+        remote.fork_digest = ForkDigest::new(1, &Hash256::zero());
+        assert_eq!(
+            check_status(&local, &remote, 10, &store),
+            Err(IncompatibleReason::ForkDigestMismatch)
+        );
+
+        let remote = status(hashes[3], 4, 12);
+        assert_eq!(
+            check_status(&local, &remote, 10, &store),
+            Err(IncompatibleReason::HeadSlotInFuture)
+        );
+
+        let remote = status(hashes[1], 2, 8);
+        assert_eq!(check_status(&local, &remote, 10, &store), Ok(()));
+
+        let remote = status(Hash256::from("fork".as_bytes()), 2, 8);
+        assert_eq!(
+            check_status(&local, &remote, 10, &store),
+            Err(IncompatibleReason::FinalizedRootMismatch)
+        );
+
+        let remote = status(Hash256::from("fork".as_bytes()), 4, 8);
+        assert_eq!(
+            check_status(&local, &remote, 10, &store),
+            Err(IncompatibleReason::FinalizedRootMismatch)
+        );
+
+        let remote = status(Hash256::from("unknown".as_bytes()), 6, 10);
+        assert_eq!(check_status(&local, &remote, 10, &store), Ok(()));
+    }
+
+    /// Delivers all messages between two peers, passing events through their handshakes.
+    fn exchange(
+        peers: &mut [(PeerId, RPC, Handshake, StatusMessage)],
+        store: &BeaconBlockStore<MemoryDB>,
+    ) {
+        let now = Instant::now();
+        loop {
+            let mut delivered = false;
+            for i in 0..peers.len() {
+                while let Some((dst, message)) = peers[i].1.next_outbound() {
+                    let src = peers[i].0;
+                    if let Some(peer) = peers.iter_mut().find(|p| p.0 == dst) {
+                        peer.1.on_message(src, message, now);
+                    }
+                    delivered = true;
+                }
+            }
+            for peer in peers.iter_mut() {
+                let (_, ref mut rpc, ref mut handshake, ref local) = *peer;
+                while let Some(event) = rpc.poll(now) {
+                    handshake.on_rpc_event(rpc, event, local, 10, store);
+                }
+            }
+            if !delivered {
+                return;
+            }
+        }
+    }
+
+    fn connect(peer: &mut (PeerId, RPC, Handshake, StatusMessage), remote: PeerId) {
+        let (_, ref mut rpc, ref mut handshake, ref local) = *peer;
+        handshake.on_connect(rpc, remote, local, Instant::now());
+    }
+
+    fn peer(local: StatusMessage) -> (PeerId, RPC, Handshake, StatusMessage) {
+        (
+            NodeId::random(),
+            RPC::new(Logger::root(Discard, o!())),
+            Handshake::new(),
+            local,
+        )
+    }
+
+    #[test]
+    fn test_handshake_compatible() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let mut peers = vec![
+            peer(status(Hash256::zero(), 0, 5)),
+            peer(status(Hash256::zero(), 0, 7)),
+        ];
+        let (a_id, b_id) = (peers[0].0, peers[1].0);
+
+        connect(&mut peers[0], b_id);
+        exchange(&mut peers, &store);
+
+        assert_eq!(peers[0].2.peer_status(&b_id).unwrap().head_slot, 7);
+        assert_eq!(peers[1].2.peer_status(&a_id).unwrap().head_slot, 5);
+        match peers[0].2.poll() {
+            Some(HandshakeEvent::Compatible { peer_id, .. }) => assert_eq!(peer_id, b_id),
+            other => panic!("expected compatible peer, got {:?}", other),
+        }
+        assert_eq!(peers[0].2.poll(), None);
+    }
+
+    #[test]
+    fn test_handshake_incompatible() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let mut remote = status(Hash256::zero(), 0, 5);
+        remote.fork_digest = ForkDigest::new(1, &Hash256::zero());
+        let mut peers = vec![peer(status(Hash256::zero(), 0, 5)), peer(remote)];
+        let (a_id, b_id) = (peers[0].0, peers[1].0);
+
+        connect(&mut peers[0], b_id);
+        exchange(&mut peers, &store);
+
+        assert_eq!(
+            peers[0].2.poll(),
+            Some(HandshakeEvent::Disconnect {
+                peer_id: b_id,
+                reason: IncompatibleReason::ForkDigestMismatch,
+            })
+        );
+        assert_eq!(
+            peers[1].2.poll(),
+            Some(HandshakeEvent::Disconnect {
+                peer_id: a_id,
+                reason: IncompatibleReason::ForkDigestMismatch,
+            })
+        );
+        assert!(peers[0].2.peer_status(&b_id).is_none());
+    }
+
+    #[test]
+    fn test_handshake_no_status() {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let local = status(Hash256::zero(), 0, 5);
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+        let mut handshake = Handshake::new();
+        let peer_id = NodeId::random();
+
+        handshake.on_connect(&mut rpc, peer_id, &local, Instant::now());
+        rpc.on_disconnect(peer_id);
+        while let Some(event) = rpc.poll(Instant::now()) {
+            assert_eq!(
+                handshake.on_rpc_event(&mut rpc, event, &local, 10, &store),
+                None
+            );
+        }
+
+        assert_eq!(
+            handshake.poll(),
+            Some(HandshakeEvent::Disconnect {
+                peer_id,
+                reason: IncompatibleReason::NoStatus,
+            })
+        );
+    }
+}
@@ -0,0 +1,455 @@
+use super::super::db::stores::BeaconBlockStore;
+use super::super::db::ClientDB;
+use super::super::peer_manager::PeerAction;
+use super::super::rpc::{
+    BlocksByRangeRequest, PeerId, RPCEvent, RPCRequest, RPCResponse, RequestId, RPC,
+};
+use super::super::ssz::ssz_encode;
+use super::super::types::{BeaconBlock, Hash256};
+use super::{BLOCKS_PER_BATCH, MAX_DOWNLOAD_ATTEMPTS};
+use slog::Logger;
+use std::collections::{HashSet, VecDeque};
+use std::time::Instant;
+
+#[derive(Debug, PartialEq)]
+pub enum BackfillEvent {
+    /// A peer served blocks which do not chain to the oldest known block.
+    ReportPeer { peer_id: PeerId, action: PeerAction },
+    /// Every block back to genesis is stored.
+    Completed,
+    /// A batch failed too many times. Backfill is paused until `resume` is called.
+    Failed,
+}
+
+struct Download {
+    peer_id: PeerId,
+    request_id: RequestId,
+    blocks: Vec<BeaconBlock>,
+}
+
+/// Downloads the blocks prior to the anchor of a checkpoint-synced node, back to genesis.
+///
+/// Blocks are requested one batch at a time, newest first, and each block is only stored once
+/// it is known to be an ancestor of the anchor: the newest block in a batch must have the root
+/// of the oldest block already stored, and each other block the parent root of the block after
+/// it. As historical blocks are not needed to follow the head, backfill only makes requests
+/// while resumed, and should be paused whenever the node is busy, e.g. during range sync.
+pub struct BackfillSync {
+    peers: Vec<PeerId>,
+    /// Every block at or above this slot is stored.
+    oldest_slot: u64,
+    /// The end of the next batch, below `oldest_slot` if empty batches have been received.
+    request_end: u64,
+    /// The parent root of the oldest stored block.
+    expected_root: Hash256,
+    genesis_root: Hash256,
+    complete: bool,
+    download: Option<Download>,
+    attempts: u8,
+    /// Peers which have served the current batch badly.
+    failed_peers: HashSet<PeerId>,
+    /// Peers which have returned empty batches since a block was last stored.
+    empty_batch_peers: HashSet<PeerId>,
+    paused: bool,
+    events: VecDeque<BackfillEvent>,
+    log: Logger,
+}
+
+impl BackfillSync {
+    /// Starts backfill below the block at `anchor_slot`, whose parent is `anchor_parent_root`.
+    ///
+    /// Backfill starts paused.
+    pub fn new(
+        anchor_slot: u64,
+        anchor_parent_root: Hash256,
+        genesis_root: Hash256,
+        log: Logger,
+    ) -> Self {
+        Self {
+            peers: vec![],
+            oldest_slot: anchor_slot,
+            request_end: anchor_slot,
+            expected_root: anchor_parent_root,
+            genesis_root,
+            complete: false,
+            download: None,
+            attempts: 0,
+            failed_peers: HashSet::new(),
+            empty_batch_peers: HashSet::new(),
+            paused: true,
+            events: VecDeque::new(),
+            log,
+        }
+    }
+
+    /// Returns `true` once the genesis block has been stored.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Every block at or above this slot is stored.
+    pub fn oldest_slot(&self) -> u64 {
+        self.oldest_slot
+    }
+
+    pub fn add_peer(&mut self, rpc: &mut RPC, peer_id: PeerId, now: Instant) {
+        if !self.peers.contains(&peer_id) {
+            self.peers.push(peer_id);
+        }
+        self.request_next(rpc, now);
+    }
+
+    pub fn remove_peer(&mut self, rpc: &mut RPC, peer_id: &PeerId, now: Instant) {
+        self.peers.retain(|peer| peer != peer_id);
+        if self.download.as_ref().map(|d| d.peer_id) == Some(*peer_id) {
+            self.download = None;
+            self.request_next(rpc, now);
+        }
+    }
+
+    /// Stops requesting batches, although a batch already requested is still stored.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self, rpc: &mut RPC, now: Instant) {
+        if self.paused {
+            self.paused = false;
+            self.attempts = 0;
+            self.request_next(rpc, now);
+        }
+    }
+
+    /// Processes the `RPCEvent`s relating to backfill requests, storing verified blocks.
+    ///
+    /// Events unrelated to backfill are returned to the caller.
+    pub fn on_rpc_event<T: ClientDB>(
+        &mut self,
+        rpc: &mut RPC,
+        event: RPCEvent,
+        store: &BeaconBlockStore<T>,
+        now: Instant,
+    ) -> Option<RPCEvent> {
+        match event {
+            RPCEvent::Response {
+                peer_id,
+                id,
+                response: RPCResponse::BlocksByRange(block),
+            } if self.is_active(peer_id, id) => {
+                if let Some(download) = self.download.as_mut() {
+                    download.blocks.push(block);
+                }
+                None
+            }
+            RPCEvent::ResponseComplete { peer_id, id } if self.is_active(peer_id, id) => {
+                if let Some(download) = self.download.take() {
+                    self.on_batch_downloaded(peer_id, download.blocks, store);
+                }
+                self.request_next(rpc, now);
+                None
+            }
+            RPCEvent::RequestFailed { peer_id, id, error } if self.is_active(peer_id, id) => {
+                debug!(self.log, "Backfill batch failed"; "error" => format!("{:?}", error));
+                self.download = None;
+                self.batch_failed(peer_id, PeerAction::HighToleranceError);
+                self.request_next(rpc, now);
+                None
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Returns the next event, if any.
+    pub fn poll(&mut self) -> Option<BackfillEvent> {
+        self.events.pop_front()
+    }
+
+    fn is_active(&self, peer_id: PeerId, id: RequestId) -> bool {
+        match self.download {
+            Some(ref download) => download.peer_id == peer_id && download.request_id == id,
+            None => false,
+        }
+    }
+
+    fn next_request(&self) -> BlocksByRangeRequest {
+        let start_slot = self.request_end.saturating_sub(BLOCKS_PER_BATCH);
+        BlocksByRangeRequest {
+            start_slot,
+            count: self.request_end - start_slot,
+            step: 1,
+        }
+    }
+
+    fn request_next(&mut self, rpc: &mut RPC, now: Instant) {
+        if self.paused || self.download.is_some() || self.is_complete() {
+            return;
+        }
+        if self.request_end == 0 {
+            warn!(
+                self.log,
+                "Backfill reached slot zero without finding genesis"
+            );
+            self.blocks_withheld();
+            if self.paused {
+                return;
+            }
+        }
+        let peer_id = match self
+            .peers
+            .iter()
+            .find(|peer_id| !self.failed_peers.contains(peer_id))
+            .or_else(|| self.peers.first())
+        {
+            Some(peer_id) => *peer_id,
+            None => return,
+        };
+        let request = self.next_request();
+        let request_id = rpc.send_request(peer_id, RPCRequest::BlocksByRange(request), now);
+        self.download = Some(Download {
+            peer_id,
+            request_id,
+            blocks: vec![],
+        });
+    }
+
+    fn on_batch_downloaded<T: ClientDB>(
+        &mut self,
+        peer_id: PeerId,
+        blocks: Vec<BeaconBlock>,
+        store: &BeaconBlockStore<T>,
+    ) {
+        /*
+         * Walk the batch from its newest block, checking each block is the parent of the one
+         * after it. Only the genesis block may be without a parent, and must be the oldest.
+         */
+        let mut expected_root = Some(self.expected_root);
+        let mut roots = Vec::with_capacity(blocks.len());
+        for block in blocks.iter().rev() {
+            let root = block.canonical_root();
+            if Some(root) != expected_root && !self.empty_batch_peers.is_empty() {
+                debug!(self.log, "Backfill batch does not chain after empty batches"; "slot" => block.slot);
+                self.blocks_withheld();
+                return;
+            }
+            if Some(root) != expected_root {
+                warn!(self.log, "Backfill batch does not chain"; "slot" => block.slot);
+                self.batch_failed(peer_id, PeerAction::LowToleranceError);
+                return;
+            }
+            roots.push(root);
+            expected_root = match root == self.genesis_root {
+                true => None,
+                false => block.parent_hash().cloned(),
+            };
+        }
+        let reached_genesis = roots.last() == Some(&self.genesis_root);
+        if expected_root.is_none() && !reached_genesis {
+            warn!(self.log, "Backfilled block has no parent");
+            self.batch_failed(peer_id, PeerAction::LowToleranceError);
+            return;
+        }
+
+        for (block, root) in blocks.iter().rev().zip(roots.iter()) {
+            if let Err(e) = store.put_serialized_block(root, &ssz_encode(block)) {
+                warn!(self.log, "Unable to store backfilled block"; "error" => e.message);
+                self.fail();
+                return;
+            }
+        }
+
+        if let Some(expected_root) = expected_root {
+            self.expected_root = expected_root;
+        }
+        self.complete = reached_genesis;
+        self.request_end = self.next_request().start_slot;
+        match blocks.first() {
+            Some(block) => {
+                self.oldest_slot = block.slot;
+                self.attempts = 0;
+                self.failed_peers.clear();
+                self.empty_batch_peers.clear();
+            }
+            None => {
+                self.empty_batch_peers.insert(peer_id);
+            }
+        }
+
+        if self.is_complete() {
+            info!(self.log, "Backfill complete");
+            self.events.push_back(BackfillEvent::Completed);
+        }
+    }
+
+    /// Handles a gap in the stored chain, which must have been caused by a peer withholding
+    /// blocks by returning an empty batch. Backfill starts again from the oldest stored block,
+    /// avoiding the peers which returned empty batches.
+    fn blocks_withheld(&mut self) {
+        for peer_id in self.empty_batch_peers.drain() {
+            self.failed_peers.insert(peer_id);
+            self.events.push_back(BackfillEvent::ReportPeer {
+                peer_id,
+                action: PeerAction::HighToleranceError,
+            });
+        }
+        self.request_end = self.oldest_slot;
+        self.attempts += 1;
+        if self.attempts >= MAX_DOWNLOAD_ATTEMPTS {
+            self.fail();
+        }
+    }
+
+    fn batch_failed(&mut self, peer_id: PeerId, action: PeerAction) {
+        self.events
+            .push_back(BackfillEvent::ReportPeer { peer_id, action });
+        self.failed_peers.insert(peer_id);
+        self.attempts += 1;
+        if self.attempts >= MAX_DOWNLOAD_ATTEMPTS {
+            self.fail();
+        }
+    }
+
+    fn fail(&mut self) {
+        self.paused = true;
+        self.events.push_back(BackfillEvent::Failed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::db::MemoryDB;
+    use super::super::test_utils::{self, Remote};
+    use super::*;
+    use slog::Discard;
+    use std::sync::Arc;
+
+    /// Builds a chain with a genesis block at slot zero and a block at each of `slots`,
+    /// returning the blocks and their roots.
+    fn chain(genesis_slot_root: u64, slots: &[u64]) -> (Vec<BeaconBlock>, Vec<Hash256>) {
+        let mut genesis = BeaconBlock::zero();
+        genesis.pow_chain_reference = Hash256::from(genesis_slot_root);
+        let mut roots = vec![genesis.canonical_root()];
+        let mut blocks = vec![genesis];
+        for slot in slots {
+            let mut block = BeaconBlock::zero();
+            block.slot = *slot;
+            block.ancestor_hashes.push(*roots.last().unwrap());
+            roots.push(block.canonical_root());
+            blocks.push(block);
+        }
+        (blocks, roots)
+    }
+
+    /// Delivers messages between the local node and `remotes` until there is nothing left to
+    /// do, returning the backfill events.
+    fn run(
+        backfill: &mut BackfillSync,
+        rpc: &mut RPC,
+        store: &BeaconBlockStore<MemoryDB>,
+        remotes: &mut [Remote],
+    ) -> Vec<BackfillEvent> {
+        test_utils::run(rpc, remotes, |rpc, now, events| {
+            let mut progress = false;
+            while let Some(event) = rpc.poll(now) {
+                assert_eq!(backfill.on_rpc_event(rpc, event, store, now), None);
+                progress = true;
+            }
+            while let Some(event) = backfill.poll() {
+                events.push(event);
+                progress = true;
+            }
+            progress
+        })
+    }
+
+    fn start(
+        remotes: &[Remote],
+        anchor: &BeaconBlock,
+        genesis_root: Hash256,
+    ) -> (BackfillSync, RPC) {
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+        let mut backfill = BackfillSync::new(
+            anchor.slot,
+            anchor.ancestor_hashes[0],
+            genesis_root,
+            Logger::root(Discard, o!()),
+        );
+        for remote in remotes {
+            backfill.add_peer(&mut rpc, remote.peer_id, Instant::now());
+        }
+        assert_eq!(rpc.next_outbound(), None);
+        backfill.resume(&mut rpc, Instant::now());
+        (backfill, rpc)
+    }
+
+    fn assert_stored(store: &BeaconBlockStore<MemoryDB>, roots: &[Hash256]) {
+        for root in roots {
+            assert!(store.block_exists(root).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_backfill_to_genesis() {
+        let slots: Vec<u64> = (1..300).filter(|slot| slot % 5 != 0).collect();
+        let (blocks, roots) = chain(0, &slots);
+        let anchor = blocks.last().unwrap().clone();
+        let mut remotes = vec![Remote::new(&blocks)];
+        let (mut backfill, mut rpc) = start(&remotes, &anchor, roots[0]);
+
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let events = run(&mut backfill, &mut rpc, &store, &mut remotes);
+
+        assert_eq!(events, vec![BackfillEvent::Completed]);
+        assert!(backfill.is_complete());
+        assert_eq!(backfill.oldest_slot(), 0);
+        assert_stored(&store, &roots[..roots.len() - 1]);
+        assert!(!store.block_exists(&roots[roots.len() - 1]).unwrap());
+    }
+
+    #[test]
+    fn test_backfill_bad_peers() {
+        let slots: Vec<u64> = (1..150).collect();
+        let (blocks, roots) = chain(0, &slots);
+        let (other_blocks, _) = chain(1, &slots);
+        let anchor = blocks.last().unwrap().clone();
+
+        /*
+         * One peer has no history and one is on another chain. Both are penalised and the
+         * blocks are fetched from the good peer.
+         */
+        let empty = Remote::new(&[]);
+        let fork = Remote::new(&other_blocks);
+        let good = Remote::new(&blocks);
+        let (empty_id, fork_id) = (empty.peer_id, fork.peer_id);
+        let mut remotes = vec![empty, fork, good];
+        let (mut backfill, mut rpc) = start(&remotes, &anchor, roots[0]);
+
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let events = run(&mut backfill, &mut rpc, &store, &mut remotes);
+
+        assert_eq!(events.last(), Some(&BackfillEvent::Completed));
+        for event in &events[..events.len() - 1] {
+            match event {
+                BackfillEvent::ReportPeer { peer_id, .. } => {
+                    assert!(*peer_id == empty_id || *peer_id == fork_id)
+                }
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        assert_stored(&store, &roots[..roots.len() - 1]);
+    }
+
+    #[test]
+    fn test_backfill_fails_without_history() {
+        let (blocks, roots) = chain(0, &(1..100).collect::<Vec<u64>>());
+        let anchor = blocks.last().unwrap().clone();
+        let mut remotes = vec![Remote::new(&[])];
+        let (mut backfill, mut rpc) = start(&remotes, &anchor, roots[0]);
+
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let events = run(&mut backfill, &mut rpc, &store, &mut remotes);
+
+        assert_eq!(events.last(), Some(&BackfillEvent::Failed));
+        assert!(!backfill.is_complete());
+        assert_eq!(backfill.oldest_slot(), anchor.slot);
+    }
+}
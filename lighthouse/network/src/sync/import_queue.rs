@@ -0,0 +1,236 @@
+use super::super::gossip::{SeenCache, SEEN_TTL};
+use super::super::rpc::PeerId;
+use super::super::types::{BeaconBlock, Hash256};
+use slog::Logger;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// How long a block is kept waiting for its parent before it is dropped. A parent which has not
+/// arrived by then is left to range sync.
+pub const PENDING_BLOCK_TTL: Duration = Duration::from_secs(6 * 32);
+/// The maximum number of blocks waiting for their parent. Blocks received beyond this are
+/// dropped.
+pub const MAX_PENDING_BLOCKS: usize = 64;
+
+/// A block whose parent was unknown when it was received.
+struct PendingBlock {
+    peer_id: PeerId,
+    block: BeaconBlock,
+    received: Instant,
+}
+
+/// Sits in front of block import, so that each block is only downloaded and verified once.
+///
+/// - A block received again, from another peer or a parent lookup, while still queued or
+///   recently imported is dropped.
+/// - Blocks already imported are dropped from sync batches before verification.
+/// - A block whose parent is unknown is cached until its parent is imported, at which point it
+///   is released to be imported again, or until `PENDING_BLOCK_TTL` has passed. Only the first
+///   block awaiting a parent needs the parent looked up.
+pub struct ImportQueue {
+    /// The roots of the blocks queued for import.
+    queued: SeenCache<Hash256>,
+    /// The roots of the blocks imported recently.
+    imported: SeenCache<Hash256>,
+    /// The blocks awaiting their parent, by the root of the parent.
+    pending: HashMap<Hash256, Vec<PendingBlock>>,
+    /// The blocks whose parent has been imported, in the order their parents were imported.
+    ready: VecDeque<(PeerId, BeaconBlock)>,
+    log: Logger,
+}
+
+impl ImportQueue {
+    pub fn new(log: Logger) -> Self {
+        Self {
+            queued: SeenCache::new(SEEN_TTL),
+            imported: SeenCache::new(SEEN_TTL),
+            pending: HashMap::new(),
+            ready: VecDeque::new(),
+            log,
+        }
+    }
+
+    /// Records that `block` is about to be queued for import, returning `false` if it has
+    /// already been queued or imported, in which case it should be dropped.
+    pub fn observe(&mut self, block: &BeaconBlock, now: Instant) -> bool {
+        let root = block.canonical_root();
+        self.imported.prune(now);
+        !self.imported.contains(&root) && self.queued.observe(root, now)
+    }
+
+    /// Drops the blocks of a sync batch which have already been imported, returning how many
+    /// were dropped.
+    pub fn retain_unimported(&mut self, blocks: &mut Vec<BeaconBlock>, now: Instant) -> usize {
+        self.imported.prune(now);
+        let len = blocks.len();
+        let imported = &self.imported;
+        blocks.retain(|block| !imported.contains(&block.canonical_root()));
+        len - blocks.len()
+    }
+
+    /// Caches `block`, received from `peer_id`, until its parent is imported.
+    ///
+    /// Returns `true` if no other block was awaiting the same parent, so that the parent should
+    /// be looked up.
+    pub fn on_unknown_parent(&mut self, peer_id: PeerId, block: BeaconBlock, now: Instant) -> bool {
+        self.prune(now);
+        let parent_root = match block.parent_hash() {
+            Some(parent_root) => *parent_root,
+            None => return false,
+        };
+        if self.pending_len() >= MAX_PENDING_BLOCKS {
+            debug!(self.log, "Too many blocks awaiting their parent, dropping block"; "slot" => block.slot);
+            return false;
+        }
+        let root = block.canonical_root();
+        let siblings = self.pending.entry(parent_root).or_default();
+        let first = siblings.is_empty();
+        if siblings
+            .iter()
+            .all(|pending| pending.block.canonical_root() != root)
+        {
+            siblings.push(PendingBlock {
+                peer_id,
+                block,
+                received: now,
+            });
+        }
+        first
+    }
+
+    /// Records the import of the block with root `root`, releasing the blocks awaiting it.
+    pub fn on_imported(&mut self, root: Hash256, now: Instant) {
+        self.imported.observe(root, now);
+        if let Some(children) = self.pending.remove(&root) {
+            debug!(self.log, "Parent imported, releasing blocks"; "blocks" => children.len());
+            self.ready.extend(
+                children
+                    .into_iter()
+                    .map(|pending| (pending.peer_id, pending.block)),
+            );
+        }
+    }
+
+    /// Returns the next block whose parent has been imported, with the peer which sent it.
+    ///
+    /// Blocks imported meanwhile, e.g. as part of a parent chain, are skipped.
+    pub fn next_ready(&mut self) -> Option<(PeerId, BeaconBlock)> {
+        while let Some((peer_id, block)) = self.ready.pop_front() {
+            if !self.imported.contains(&block.canonical_root()) {
+                return Some((peer_id, block));
+            }
+        }
+        None
+    }
+
+    /// Drops the blocks which have awaited their parent for `PENDING_BLOCK_TTL`.
+    pub fn prune(&mut self, now: Instant) {
+        for siblings in self.pending.values_mut() {
+            siblings.retain(|pending| now.duration_since(pending.received) < PENDING_BLOCK_TTL);
+        }
+        self.pending.retain(|_, siblings| !siblings.is_empty());
+        self.queued.prune(now);
+        self.imported.prune(now);
+    }
+
+    /// Returns the number of blocks awaiting their parent.
+    pub fn pending_len(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::enr::NodeId;
+    use super::*;
+    use slog::Discard;
+
+    /// Builds a chain of `len` blocks on an unknown parent.
+    fn chain(len: u64) -> Vec<BeaconBlock> {
+        let mut blocks: Vec<BeaconBlock> = vec![];
+        for slot in 1..=len {
+            let mut block = BeaconBlock::zero();
+            block.slot = slot;
+            let parent_root = match blocks.last() {
+                Some(parent) => parent.canonical_root(),
+                None => Hash256::from(7),
+            };
+            block.ancestor_hashes.push(parent_root);
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    fn queue() -> ImportQueue {
+        ImportQueue::new(Logger::root(Discard, o!()))
+    }
+
+    #[test]
+    fn test_duplicates_are_dropped() {
+        let mut queue = queue();
+        let blocks = chain(3);
+        let now = Instant::now();
+        assert!(queue.observe(&blocks[0], now));
+        assert!(!queue.observe(&blocks[0], now));
+
+        /*
+         * Imported blocks are dropped from batches and gossip alike.
+         */
+        queue.on_imported(blocks[1].canonical_root(), now);
+        assert!(!queue.observe(&blocks[1], now));
+        let mut batch = blocks.clone();
+        assert_eq!(queue.retain_unimported(&mut batch, now), 1);
+        assert_eq!(batch, vec![blocks[0].clone(), blocks[2].clone()]);
+
+        let later = now + SEEN_TTL;
+        assert!(queue.observe(&blocks[0], later));
+        assert!(queue.observe(&blocks[1], later));
+    }
+
+    #[test]
+    fn test_pending_blocks_released_with_parent() {
+        let mut queue = queue();
+        let blocks = chain(3);
+        let (a, b) = (NodeId::random(), NodeId::random());
+        let now = Instant::now();
+
+        /*
+         * Only the first block awaiting a parent needs it looked up.
+         */
+        assert!(queue.on_unknown_parent(a, blocks[1].clone(), now));
+        assert!(!queue.on_unknown_parent(b, blocks[1].clone(), now));
+        assert!(queue.on_unknown_parent(b, blocks[2].clone(), now));
+        assert_eq!(queue.pending_len(), 2);
+        assert_eq!(queue.next_ready(), None);
+
+        queue.on_imported(blocks[0].canonical_root(), now);
+        assert_eq!(queue.pending_len(), 1);
+        assert_eq!(queue.next_ready(), Some((a, blocks[1].clone())));
+        assert_eq!(queue.next_ready(), None);
+
+        /*
+         * A released block imported by other means is not released again.
+         */
+        queue.on_imported(blocks[1].canonical_root(), now);
+        queue.on_imported(blocks[2].canonical_root(), now);
+        assert_eq!(queue.pending_len(), 0);
+        assert_eq!(queue.next_ready(), None);
+    }
+
+    #[test]
+    fn test_pending_blocks_expire() {
+        let mut queue = queue();
+        let blocks = chain(MAX_PENDING_BLOCKS as u64 + 2);
+        let peer_id = NodeId::random();
+        let now = Instant::now();
+        for block in &blocks[1..] {
+            queue.on_unknown_parent(peer_id, block.clone(), now);
+        }
+        assert_eq!(queue.pending_len(), MAX_PENDING_BLOCKS);
+
+        queue.prune(now + PENDING_BLOCK_TTL);
+        assert_eq!(queue.pending_len(), 0);
+        queue.on_imported(blocks[0].canonical_root(), now + PENDING_BLOCK_TTL);
+        assert_eq!(queue.next_ready(), None);
+    }
+}
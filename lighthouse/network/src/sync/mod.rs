@@ -0,0 +1,18 @@
+mod backfill;
+mod import_queue;
+mod parent_lookup;
+mod range;
+mod state;
+#[cfg(test)]
+mod test_utils;
+
+pub use self::backfill::{BackfillEvent, BackfillSync};
+pub use self::import_queue::{ImportQueue, MAX_PENDING_BLOCKS, PENDING_BLOCK_TTL};
+pub use self::parent_lookup::{
+    ParentLookup, ParentLookupEvent, MAX_LOOKUP_ATTEMPTS, MAX_PARENT_DEPTH, MAX_PARENT_LOOKUPS,
+};
+pub use self::range::{
+    BatchId, BatchProcessResult, RangeSync, SyncEvent, BATCH_BUFFER_SIZE, BLOCKS_PER_BATCH,
+    MAX_DOWNLOAD_ATTEMPTS, MAX_PROCESSING_ATTEMPTS,
+};
+pub use self::state::SyncState;
@@ -0,0 +1,381 @@
+use super::super::db::stores::BeaconBlockStore;
+use super::super::db::ClientDB;
+use super::super::peer_manager::PeerAction;
+use super::super::rpc::{
+    BlocksByRootRequest, PeerId, RPCEvent, RPCRequest, RPCResponse, RequestId, RPC,
+};
+use super::super::types::{BeaconBlock, Hash256};
+use slog::Logger;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// The maximum number of ancestors fetched for a single block before giving up.
+pub const MAX_PARENT_DEPTH: usize = 32;
+/// The maximum number of times each ancestor is requested before giving up.
+pub const MAX_LOOKUP_ATTEMPTS: u8 = 3;
+/// The maximum number of lookups in progress at once. Blocks with unknown parents received
+/// beyond this are dropped.
+pub const MAX_PARENT_LOOKUPS: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum ParentLookupEvent {
+    /// A chain of blocks connecting to a known block, oldest first, ready to be imported.
+    ProcessChain { blocks: Vec<BeaconBlock> },
+    /// A peer sent a block whose ancestry is too deep to be worth fetching.
+    ReportPeer { peer_id: PeerId, action: PeerAction },
+    /// The ancestors of the block with the given root could not be found.
+    Failed { root: Hash256 },
+}
+
+struct Lookup {
+    /// The blocks fetched so far, newest first.
+    blocks: Vec<BeaconBlock>,
+    /// The peers which have sent the newest block, and so should know its ancestors.
+    peers: Vec<PeerId>,
+    /// The outstanding request for the parent of the oldest block.
+    request: Option<(PeerId, RequestId)>,
+    /// Whether the outstanding request has returned the parent.
+    received: bool,
+    attempts: u8,
+}
+
+impl Lookup {
+    fn parent_root(&self) -> Hash256 {
+        self.blocks
+            .last()
+            .and_then(|block| block.parent_hash())
+            .cloned()
+            .unwrap_or_else(Hash256::zero)
+    }
+
+    fn contains(&self, root: &Hash256) -> bool {
+        self.blocks
+            .iter()
+            .any(|block| block.canonical_root() == *root)
+    }
+}
+
+/// Fetches the ancestors of blocks received via gossip whose parent is unknown.
+///
+/// Ancestors are requested by root via `BlocksByRoot`, one at a time, until a block is found
+/// whose parent is in the store. The chain is then handed to the caller to import. Chains
+/// deeper than `MAX_PARENT_DEPTH` are abandoned, as a node that far behind should range sync
+/// instead, and a peer could otherwise keep us fetching an invented chain indefinitely.
+pub struct ParentLookup {
+    lookups: Vec<Lookup>,
+    events: VecDeque<ParentLookupEvent>,
+    log: Logger,
+}
+
+impl ParentLookup {
+    pub fn new(log: Logger) -> Self {
+        Self {
+            lookups: vec![],
+            events: VecDeque::new(),
+            log,
+        }
+    }
+
+    /// Returns the number of lookups in progress.
+    pub fn len(&self) -> usize {
+        self.lookups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lookups.is_empty()
+    }
+
+    /// Starts fetching the ancestors of `block`, received from `peer_id`, whose parent is not
+    /// in the store.
+    ///
+    /// If the block is already part of a lookup, `peer_id` is added to the peers of that lookup.
+    pub fn on_unknown_parent(
+        &mut self,
+        rpc: &mut RPC,
+        peer_id: PeerId,
+        block: BeaconBlock,
+        now: Instant,
+    ) {
+        let root = block.canonical_root();
+        if let Some(lookup) = self.lookups.iter_mut().find(|l| l.contains(&root)) {
+            if !lookup.peers.contains(&peer_id) {
+                lookup.peers.push(peer_id);
+            }
+            return;
+        }
+        if self.lookups.len() >= MAX_PARENT_LOOKUPS {
+            debug!(self.log, "Too many parent lookups, dropping block"; "slot" => block.slot);
+            return;
+        }
+        if block.parent_hash().is_none() {
+            return;
+        }
+
+        let mut lookup = Lookup {
+            blocks: vec![block],
+            peers: vec![peer_id],
+            request: None,
+            received: false,
+            attempts: 0,
+        };
+        request_parent(rpc, &mut lookup, now);
+        self.lookups.push(lookup);
+    }
+
+    /// Processes the `RPCEvent`s relating to parent requests.
+    ///
+    /// Events unrelated to parent lookups are returned to the caller.
+    pub fn on_rpc_event<T: ClientDB>(
+        &mut self,
+        rpc: &mut RPC,
+        event: RPCEvent,
+        store: &BeaconBlockStore<T>,
+        now: Instant,
+    ) -> Option<RPCEvent> {
+        match event {
+            RPCEvent::Response {
+                peer_id,
+                id,
+                response: RPCResponse::BlocksByRoot(block),
+            } if self.lookup_index(peer_id, id).is_some() => {
+                /*
+                 * The RPC only accepts blocks with the requested root, so the block is the
+                 * parent of the oldest block in the lookup.
+                 */
+                if let Some(i) = self.lookup_index(peer_id, id) {
+                    self.lookups[i].blocks.push(block);
+                    self.lookups[i].received = true;
+                }
+                None
+            }
+            RPCEvent::ResponseComplete { peer_id, id }
+            | RPCEvent::RequestFailed { peer_id, id, .. }
+                if self.lookup_index(peer_id, id).is_some() =>
+            {
+                if let Some(i) = self.lookup_index(peer_id, id) {
+                    self.on_request_ended(rpc, i, store, now);
+                }
+                None
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Returns the next event, if any.
+    pub fn poll(&mut self) -> Option<ParentLookupEvent> {
+        self.events.pop_front()
+    }
+
+    fn lookup_index(&self, peer_id: PeerId, id: RequestId) -> Option<usize> {
+        self.lookups
+            .iter()
+            .position(|lookup| lookup.request == Some((peer_id, id)))
+    }
+
+    /// Completes lookup `i` if its oldest block connects to the store, otherwise requests the
+    /// next ancestor.
+    fn on_request_ended<T: ClientDB>(
+        &mut self,
+        rpc: &mut RPC,
+        i: usize,
+        store: &BeaconBlockStore<T>,
+        now: Instant,
+    ) {
+        /*
+         * If the request ended without the parent, try again, preferring another peer.
+         */
+        let lookup = &mut self.lookups[i];
+        lookup.request = None;
+        if lookup.received {
+            lookup.received = false;
+            lookup.attempts = 0;
+        } else {
+            lookup.attempts += 1;
+            if lookup.attempts >= MAX_LOOKUP_ATTEMPTS {
+                self.fail(i);
+            } else {
+                request_parent(rpc, lookup, now);
+            }
+            return;
+        }
+
+        let parent_root = self.lookups[i].parent_root();
+        match store.block_exists(&parent_root) {
+            Ok(true) => {
+                let lookup = self.lookups.remove(i);
+                let mut blocks = lookup.blocks;
+                blocks.reverse();
+                debug!(self.log, "Parent lookup complete"; "blocks" => blocks.len());
+                self.events
+                    .push_back(ParentLookupEvent::ProcessChain { blocks });
+            }
+            Ok(false) if parent_root.is_zero() => self.fail(i),
+            Ok(false) if self.lookups[i].blocks.len() > MAX_PARENT_DEPTH => {
+                warn!(self.log, "Parent lookup exceeded maximum depth");
+                let peer_id = self.lookups[i].peers[0];
+                self.events.push_back(ParentLookupEvent::ReportPeer {
+                    peer_id,
+                    action: PeerAction::LowToleranceError,
+                });
+                self.fail(i);
+            }
+            Ok(false) => request_parent(rpc, &mut self.lookups[i], now),
+            Err(e) => {
+                warn!(self.log, "Unable to read block store"; "error" => e.message);
+                self.fail(i);
+            }
+        }
+    }
+
+    fn fail(&mut self, i: usize) {
+        let lookup = self.lookups.remove(i);
+        let root = lookup.blocks[0].canonical_root();
+        self.events.push_back(ParentLookupEvent::Failed { root });
+    }
+}
+
+/// Requests the parent of the oldest block in `lookup`, rotating through its peers on retries.
+fn request_parent(rpc: &mut RPC, lookup: &mut Lookup, now: Instant) {
+    let peer_id = lookup.peers[lookup.attempts as usize % lookup.peers.len()];
+    let request = BlocksByRootRequest {
+        block_roots: vec![lookup.parent_root()],
+    };
+    let id = rpc.send_request(peer_id, RPCRequest::BlocksByRoot(request), now);
+    lookup.request = Some((peer_id, id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::db::MemoryDB;
+    use super::super::super::enr::NodeId;
+    use super::super::super::ssz::ssz_encode;
+    use super::super::test_utils::{self, Remote};
+    use super::*;
+    use slog::Discard;
+    use std::sync::Arc;
+
+    /// Builds a chain of `len` blocks, storing the first `known` of them in `local`.
+    fn chain(len: u64, known: usize, local: &BeaconBlockStore<MemoryDB>) -> Vec<BeaconBlock> {
+        let mut blocks: Vec<BeaconBlock> = vec![];
+        for slot in 0..len {
+            let mut block = BeaconBlock::zero();
+            block.slot = slot;
+            if let Some(parent) = blocks.last() {
+                block.ancestor_hashes.push(parent.canonical_root());
+            }
+            if blocks.len() < known {
+                local
+                    .put_serialized_block(&block.canonical_root(), &ssz_encode(&block))
+                    .unwrap();
+            }
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    /// Serves requests from `remote` until there is nothing left to do, returning the lookup
+    /// events.
+    fn run(
+        lookup: &mut ParentLookup,
+        rpc: &mut RPC,
+        store: &BeaconBlockStore<MemoryDB>,
+        remote: Remote,
+    ) -> Vec<ParentLookupEvent> {
+        test_utils::run(rpc, &mut [remote], |rpc, now, events| {
+            let mut progress = false;
+            while let Some(event) = rpc.poll(now) {
+                assert_eq!(lookup.on_rpc_event(rpc, event, store, now), None);
+                progress = true;
+            }
+            while let Some(event) = lookup.poll() {
+                events.push(event);
+                progress = true;
+            }
+            progress
+        })
+    }
+
+    fn setup() -> (ParentLookup, RPC, BeaconBlockStore<MemoryDB>) {
+        (
+            ParentLookup::new(Logger::root(Discard, o!())),
+            RPC::new(Logger::root(Discard, o!())),
+            BeaconBlockStore::new(Arc::new(MemoryDB::open())),
+        )
+    }
+
+    #[test]
+    fn test_lookup_connects_to_store() {
+        let (mut lookup, mut rpc, store) = setup();
+        let blocks = chain(10, 4, &store);
+        let remote = Remote::new(&blocks);
+        let peer_id = remote.peer_id;
+
+        lookup.on_unknown_parent(&mut rpc, peer_id, blocks[9].clone(), Instant::now());
+        // A second peer sending the same block does not start another lookup.
+        lookup.on_unknown_parent(
+            &mut rpc,
+            NodeId::random(),
+            blocks[9].clone(),
+            Instant::now(),
+        );
+        assert_eq!(lookup.len(), 1);
+
+        let events = run(&mut lookup, &mut rpc, &store, remote);
+        assert_eq!(
+            events,
+            vec![ParentLookupEvent::ProcessChain {
+                blocks: blocks[4..].to_vec()
+            }]
+        );
+        assert!(lookup.is_empty());
+    }
+
+    #[test]
+    fn test_lookup_depth_limit() {
+        let (mut lookup, mut rpc, store) = setup();
+        let len = MAX_PARENT_DEPTH as u64 + 10;
+        let blocks = chain(len, 1, &store);
+        let remote = Remote::new(&blocks);
+        let peer_id = remote.peer_id;
+
+        let head = blocks.last().unwrap().clone();
+        lookup.on_unknown_parent(&mut rpc, peer_id, head.clone(), Instant::now());
+        let events = run(&mut lookup, &mut rpc, &store, remote);
+        assert_eq!(
+            events,
+            vec![
+                ParentLookupEvent::ReportPeer {
+                    peer_id,
+                    action: PeerAction::LowToleranceError,
+                },
+                ParentLookupEvent::Failed {
+                    root: head.canonical_root()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lookup_unknown_to_peer() {
+        let (mut lookup, mut rpc, store) = setup();
+        let blocks = chain(5, 1, &store);
+        let remote = Remote::new(&blocks);
+        let peer_id = remote.peer_id;
+
+        /*
+         * The peer does not have the parent, so each attempt returns nothing.
+         */
+        remote
+            .store
+            .delete_block(&blocks[3].canonical_root())
+            .unwrap();
+        lookup.on_unknown_parent(&mut rpc, peer_id, blocks[4].clone(), Instant::now());
+        let events = run(&mut lookup, &mut rpc, &store, remote);
+        assert_eq!(
+            events,
+            vec![ParentLookupEvent::Failed {
+                root: blocks[4].canonical_root()
+            }]
+        );
+    }
+}
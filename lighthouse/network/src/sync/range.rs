@@ -0,0 +1,637 @@
+use super::super::peer_manager::PeerAction;
+use super::super::rpc::{
+    BlocksByRangeRequest, PeerId, RPCEvent, RPCRequest, RPCResponse, RequestId, StatusMessage, RPC,
+};
+use super::super::types::{BeaconBlock, Hash256};
+use slog::Logger;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::time::Instant;
+
+/// The number of slots requested in each batch.
+pub const BLOCKS_PER_BATCH: u64 = 64;
+/// The maximum number of batches downloaded ahead of the batch being processed.
+pub const BATCH_BUFFER_SIZE: usize = 5;
+/// The number of times a batch may fail to download before sync is abandoned.
+pub const MAX_DOWNLOAD_ATTEMPTS: u8 = 5;
+/// The number of times a batch may fail to process before sync is abandoned.
+pub const MAX_PROCESSING_ATTEMPTS: u8 = 3;
+
+/// Identifies a batch by its position in the range being synced.
+pub type BatchId = u64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BatchProcessResult {
+    /// All blocks in the batch were imported.
+    Success,
+    /// A block in the batch was invalid, so the batch must be downloaded again.
+    Failed,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SyncEvent {
+    /// The blocks of a batch are ready to be imported, in ascending slot order. The result must
+    /// be supplied to `RangeSync::on_batch_processed` before the next batch is emitted.
+    ProcessBatch {
+        batch_id: BatchId,
+        blocks: Vec<BeaconBlock>,
+    },
+    /// A peer served an invalid batch or failed to serve a batch at all.
+    ReportPeer { peer_id: PeerId, action: PeerAction },
+    /// The chain has been imported up to the head of the best known peer.
+    Completed,
+    /// A batch failed too many times, so sync has been abandoned and all peers forgotten. It
+    /// resumes when peers are added again.
+    Failed,
+}
+
+#[derive(Debug, PartialEq)]
+enum BatchState {
+    AwaitingDownload,
+    Downloading {
+        peer_id: PeerId,
+        request_id: RequestId,
+    },
+    AwaitingProcessing {
+        peer_id: PeerId,
+    },
+    Processing {
+        peer_id: PeerId,
+    },
+}
+
+struct Batch {
+    request: BlocksByRangeRequest,
+    state: BatchState,
+    blocks: Vec<BeaconBlock>,
+    download_attempts: u8,
+    processing_attempts: u8,
+    /// Peers which have served this batch badly, avoided when it is requested again.
+    failed_peers: HashSet<PeerId>,
+}
+
+impl Batch {
+    fn new(start_slot: u64) -> Self {
+        Self {
+            request: BlocksByRangeRequest {
+                start_slot,
+                count: BLOCKS_PER_BATCH,
+                step: 1,
+            },
+            state: BatchState::AwaitingDownload,
+            blocks: vec![],
+            download_attempts: 0,
+            processing_attempts: 0,
+            failed_peers: HashSet::new(),
+        }
+    }
+}
+
+/// Downloads and imports the chain from our head to the head of the best known peer.
+///
+/// The slots ahead of our head are split into batches of `BLOCKS_PER_BATCH`, which are
+/// requested via `BlocksByRange` from peers whose head is beyond the start of the batch. A few
+/// batches are downloaded concurrently, but they are handed to the caller for import strictly
+/// in order, one at a time. Batches which fail to download or import are requested again,
+/// preferably from another peer.
+pub struct RangeSync {
+    /// The head slot of each peer ahead of us.
+    peers: HashMap<PeerId, u64>,
+    /// The first slot of batch `0`.
+    start_slot: u64,
+    /// The greatest head slot of any peer.
+    target_slot: u64,
+    batches: BTreeMap<BatchId, Batch>,
+    /// The id of the next batch to be created.
+    next_batch_id: BatchId,
+    /// The id of the next batch to be imported.
+    processing_target: BatchId,
+    /// The root of the last block imported, to which the next batch must connect.
+    last_root: Hash256,
+    requests: HashMap<(PeerId, RequestId), BatchId>,
+    /// Whether a `Completed` event is due once the target is reached.
+    syncing: bool,
+    events: VecDeque<SyncEvent>,
+    log: Logger,
+}
+
+impl RangeSync {
+    pub fn new(head_slot: u64, head_root: Hash256, log: Logger) -> Self {
+        Self {
+            peers: HashMap::new(),
+            start_slot: head_slot + 1,
+            target_slot: head_slot,
+            batches: BTreeMap::new(),
+            next_batch_id: 0,
+            processing_target: 0,
+            last_root: head_root,
+            requests: HashMap::new(),
+            syncing: false,
+            events: VecDeque::new(),
+            log,
+        }
+    }
+
+    /// Returns `true` if every slot up to the best known peer head has been imported.
+    pub fn is_synced(&self) -> bool {
+        self.batch_start(self.processing_target) > self.target_slot
+    }
+
+    /// Adds a peer (or updates its status), syncing towards its head if it is ahead of us.
+    pub fn add_peer(
+        &mut self,
+        rpc: &mut RPC,
+        peer_id: PeerId,
+        status: &StatusMessage,
+        now: Instant,
+    ) {
+        if status.head_slot < self.batch_start(self.processing_target) {
+            return;
+        }
+        if !self.syncing {
+            info!(self.log, "Starting range sync"; "target_slot" => status.head_slot);
+            self.syncing = true;
+        }
+        self.peers.insert(peer_id, status.head_slot);
+        self.target_slot = self.target_slot.max(status.head_slot);
+        self.request_batches(rpc, now);
+    }
+
+    /// Removes a disconnected peer, requesting any batch it was serving from another peer.
+    pub fn remove_peer(&mut self, rpc: &mut RPC, peer_id: &PeerId, now: Instant) {
+        if self.peers.remove(peer_id).is_none() {
+            return;
+        }
+        for batch in self.batches.values_mut() {
+            let downloading = match batch.state {
+                BatchState::Downloading { peer_id: peer, .. } => peer == *peer_id,
+                _ => false,
+            };
+            if downloading {
+                batch.state = BatchState::AwaitingDownload;
+                batch.blocks.clear();
+            }
+        }
+        self.request_batches(rpc, now);
+    }
+
+    /// Processes the `RPCEvent`s relating to batch requests.
+    ///
+    /// Events unrelated to range sync are returned to the caller.
+    pub fn on_rpc_event(
+        &mut self,
+        rpc: &mut RPC,
+        event: RPCEvent,
+        now: Instant,
+    ) -> Option<RPCEvent> {
+        match event {
+            RPCEvent::Response {
+                peer_id,
+                id,
+                response: RPCResponse::BlocksByRange(block),
+            } if self.requests.contains_key(&(peer_id, id)) => {
+                if let Some(batch) = self.active_batch(peer_id, id) {
+                    batch.blocks.push(block);
+                }
+                None
+            }
+            RPCEvent::ResponseComplete { peer_id, id }
+                if self.requests.contains_key(&(peer_id, id)) =>
+            {
+                if let Some(batch_id) = self.active_batch_id(peer_id, id) {
+                    self.on_batch_downloaded(batch_id, peer_id);
+                }
+                self.requests.remove(&(peer_id, id));
+                self.process_next();
+                self.request_batches(rpc, now);
+                None
+            }
+            RPCEvent::RequestFailed { peer_id, id, error }
+                if self.requests.contains_key(&(peer_id, id)) =>
+            {
+                if let Some(batch_id) = self.active_batch_id(peer_id, id) {
+                    debug!(self.log, "Batch download failed"; "batch" => batch_id, "error" => format!("{:?}", error));
+                    self.events.push_back(SyncEvent::ReportPeer {
+                        peer_id,
+                        action: PeerAction::HighToleranceError,
+                    });
+                    self.download_failed(batch_id, peer_id);
+                }
+                self.requests.remove(&(peer_id, id));
+                self.request_batches(rpc, now);
+                None
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Records the result of importing a batch emitted in `SyncEvent::ProcessBatch`.
+    pub fn on_batch_processed(
+        &mut self,
+        rpc: &mut RPC,
+        batch_id: BatchId,
+        result: BatchProcessResult,
+        now: Instant,
+    ) {
+        let peer_id = match self.batches.get(&batch_id).map(|batch| &batch.state) {
+            Some(BatchState::Processing { peer_id }) => *peer_id,
+            _ => return,
+        };
+        match result {
+            BatchProcessResult::Success => {
+                if let Some(batch) = self.batches.remove(&batch_id) {
+                    if let Some(block) = batch.blocks.last() {
+                        self.last_root = block.canonical_root();
+                    }
+                }
+                self.processing_target += 1;
+                self.process_next();
+            }
+            BatchProcessResult::Failed => self.processing_failed(batch_id, peer_id),
+        }
+        self.request_batches(rpc, now);
+    }
+
+    /// Returns the next event, if any.
+    pub fn poll(&mut self) -> Option<SyncEvent> {
+        self.events.pop_front()
+    }
+
+    fn batch_start(&self, batch_id: BatchId) -> u64 {
+        self.start_slot + batch_id * BLOCKS_PER_BATCH
+    }
+
+    /// Returns the id of the batch being downloaded by request `id`, if it is still wanted.
+    fn active_batch_id(&self, peer_id: PeerId, id: RequestId) -> Option<BatchId> {
+        let batch_id = *self.requests.get(&(peer_id, id))?;
+        match self.batches.get(&batch_id)?.state {
+            BatchState::Downloading {
+                peer_id: peer,
+                request_id,
+            } if peer == peer_id && request_id == id => Some(batch_id),
+            _ => None,
+        }
+    }
+
+    fn active_batch(&mut self, peer_id: PeerId, id: RequestId) -> Option<&mut Batch> {
+        let batch_id = self.active_batch_id(peer_id, id)?;
+        self.batches.get_mut(&batch_id)
+    }
+
+    /// Creates batches up to the buffer size and requests each awaiting download from an idle
+    /// peer.
+    fn request_batches(&mut self, rpc: &mut RPC, now: Instant) {
+        while self.batches.len() < BATCH_BUFFER_SIZE
+            && self.batch_start(self.next_batch_id) <= self.target_slot
+        {
+            let start_slot = self.batch_start(self.next_batch_id);
+            self.batches
+                .insert(self.next_batch_id, Batch::new(start_slot));
+            self.next_batch_id += 1;
+        }
+
+        let mut busy: HashSet<PeerId> = self.requests.keys().map(|(peer_id, _)| *peer_id).collect();
+        for batch in self.batches.values_mut() {
+            if batch.state != BatchState::AwaitingDownload {
+                continue;
+            }
+            /*
+             * Peers which have not failed this batch are preferred, even if that means waiting
+             * for one to become idle. Peers which have failed it are only used as a last resort.
+             */
+            let start_slot = batch.request.start_slot;
+            let (preferred, failed): (Vec<PeerId>, Vec<PeerId>) = self
+                .peers
+                .iter()
+                .filter(|&(_, head_slot)| *head_slot >= start_slot)
+                .map(|(peer_id, _)| *peer_id)
+                .partition(|peer_id| !batch.failed_peers.contains(peer_id));
+            let candidates = if preferred.is_empty() {
+                failed
+            } else {
+                preferred
+            };
+            let peer_id = match candidates
+                .into_iter()
+                .find(|peer_id| !busy.contains(peer_id))
+            {
+                Some(peer_id) => peer_id,
+                None => continue,
+            };
+
+            let request_id = rpc.send_request(
+                peer_id,
+                RPCRequest::BlocksByRange(batch.request.clone()),
+                now,
+            );
+            let batch_id = (start_slot - self.start_slot) / BLOCKS_PER_BATCH;
+            self.requests.insert((peer_id, request_id), batch_id);
+            busy.insert(peer_id);
+            batch.blocks.clear();
+            batch.state = BatchState::Downloading {
+                peer_id,
+                request_id,
+            };
+        }
+    }
+
+    /// Verifies that a downloaded batch forms a chain, queueing it for processing if so.
+    fn on_batch_downloaded(&mut self, batch_id: BatchId, peer_id: PeerId) {
+        let linked = self.batches[&batch_id]
+            .blocks
+            .windows(2)
+            .all(|pair| pair[1].parent_hash() == Some(&pair[0].canonical_root()));
+        if linked {
+            if let Some(batch) = self.batches.get_mut(&batch_id) {
+                batch.state = BatchState::AwaitingProcessing { peer_id };
+            }
+        } else {
+            warn!(self.log, "Batch is not a chain"; "batch" => batch_id);
+            self.events.push_back(SyncEvent::ReportPeer {
+                peer_id,
+                action: PeerAction::LowToleranceError,
+            });
+            self.download_failed(batch_id, peer_id);
+        }
+    }
+
+    fn download_failed(&mut self, batch_id: BatchId, peer_id: PeerId) {
+        let abandon = match self.batches.get_mut(&batch_id) {
+            Some(batch) => {
+                batch.download_attempts += 1;
+                batch.failed_peers.insert(peer_id);
+                batch.blocks.clear();
+                batch.state = BatchState::AwaitingDownload;
+                batch.download_attempts >= MAX_DOWNLOAD_ATTEMPTS
+            }
+            None => false,
+        };
+        if abandon {
+            self.abandon(batch_id);
+        }
+    }
+
+    fn processing_failed(&mut self, batch_id: BatchId, peer_id: PeerId) {
+        self.events.push_back(SyncEvent::ReportPeer {
+            peer_id,
+            action: PeerAction::MidToleranceError,
+        });
+        let abandon = match self.batches.get_mut(&batch_id) {
+            Some(batch) => {
+                batch.processing_attempts += 1;
+                batch.failed_peers.insert(peer_id);
+                batch.blocks.clear();
+                batch.state = BatchState::AwaitingDownload;
+                batch.processing_attempts >= MAX_PROCESSING_ATTEMPTS
+            }
+            None => false,
+        };
+        if abandon {
+            self.abandon(batch_id);
+        }
+    }
+
+    /// Forgets all peers and batches, keeping the progress made so far.
+    fn abandon(&mut self, batch_id: BatchId) {
+        warn!(self.log, "Range sync failed"; "batch" => batch_id);
+        self.peers.clear();
+        self.batches.clear();
+        self.next_batch_id = self.processing_target;
+        self.target_slot = self.batch_start(self.processing_target) - 1;
+        self.syncing = false;
+        self.events.push_back(SyncEvent::Failed);
+    }
+
+    /// Emits the next batch for processing, if it has been downloaded and no batch is being
+    /// processed.
+    fn process_next(&mut self) {
+        loop {
+            let batch_id = self.processing_target;
+            let peer_id = match self.batches.get(&batch_id).map(|batch| &batch.state) {
+                Some(BatchState::AwaitingProcessing { peer_id }) => *peer_id,
+                Some(_) => return,
+                None => {
+                    if self.syncing && self.is_synced() {
+                        info!(self.log, "Range sync complete"; "slot" => self.target_slot);
+                        self.syncing = false;
+                        self.events.push_back(SyncEvent::Completed);
+                    }
+                    return;
+                }
+            };
+
+            /*
+             * Slots without blocks are common, so an empty batch is valid and has nothing to
+             * import.
+             */
+            let first_parent = self.batches[&batch_id]
+                .blocks
+                .first()
+                .map(|b| b.parent_hash().cloned());
+            match first_parent {
+                None => {
+                    self.batches.remove(&batch_id);
+                    self.processing_target += 1;
+                }
+                Some(parent) if parent != Some(self.last_root) => {
+                    debug!(self.log, "Batch does not connect to the chain"; "batch" => batch_id);
+                    self.processing_failed(batch_id, peer_id);
+                    return;
+                }
+                Some(_) => {
+                    let batch = self.batches.get_mut(&batch_id).expect("batch exists");
+                    batch.state = BatchState::Processing { peer_id };
+                    self.events.push_back(SyncEvent::ProcessBatch {
+                        batch_id,
+                        blocks: batch.blocks.clone(),
+                    });
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::enr::NodeId;
+    use super::super::super::rpc::ForkDigest;
+    use super::super::test_utils::{self, Remote};
+    use super::*;
+    use slog::Discard;
+
+    /// Builds a remote peer with a block at each of `slots`, descending from `parent`.
+    fn remote(parent: Hash256, slots: &[u64]) -> (Remote, Vec<BeaconBlock>) {
+        let mut head = parent;
+        let mut blocks = vec![];
+        for slot in slots {
+            let mut block = BeaconBlock::zero();
+            block.slot = *slot;
+            block.ancestor_hashes.push(head);
+            head = block.canonical_root();
+            blocks.push(block);
+        }
+        (Remote::new(&blocks), blocks)
+    }
+
+    fn status(head_slot: u64) -> StatusMessage {
+        StatusMessage {
+            fork_digest: ForkDigest([0; 4]),
+            finalized_root: Hash256::zero(),
+            finalized_slot: 0,
+            head_root: Hash256::zero(),
+            head_slot,
+        }
+    }
+
+    /// Delivers messages between the local node and `remotes` until there is nothing left to
+    /// do, importing batches with `process`. Returns the events other than `ProcessBatch`.
+    fn run<F>(
+        sync: &mut RangeSync,
+        rpc: &mut RPC,
+        remotes: &mut [Remote],
+        mut process: F,
+    ) -> Vec<SyncEvent>
+    where
+        F: FnMut(BatchId, &[BeaconBlock]) -> BatchProcessResult,
+    {
+        test_utils::run(rpc, remotes, |rpc, now, events| {
+            let mut progress = false;
+            while let Some(event) = rpc.poll(now) {
+                assert_eq!(sync.on_rpc_event(rpc, event, now), None);
+                progress = true;
+            }
+            while let Some(event) = sync.poll() {
+                match event {
+                    SyncEvent::ProcessBatch { batch_id, blocks } => {
+                        let result = process(batch_id, &blocks);
+                        sync.on_batch_processed(rpc, batch_id, result, now);
+                    }
+                    event => events.push(event),
+                }
+                progress = true;
+            }
+            progress
+        })
+    }
+
+    fn slots(blocks: &[BeaconBlock]) -> Vec<u64> {
+        blocks.iter().map(|block| block.slot).collect()
+    }
+
+    #[test]
+    fn test_sync_to_head() {
+        let genesis = Hash256::from("genesis".as_bytes());
+        let chain_slots: Vec<u64> = (1..300).filter(|slot| slot % 7 != 0).collect();
+        let (a, chain) = remote(genesis, &chain_slots);
+        let (b, _) = remote(genesis, &chain_slots);
+        let mut remotes = vec![a, b];
+
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+        let mut sync = RangeSync::new(0, genesis, Logger::root(Discard, o!()));
+        assert!(sync.is_synced());
+        for remote in &remotes {
+            sync.add_peer(&mut rpc, remote.peer_id, &status(299), Instant::now());
+        }
+        assert!(!sync.is_synced());
+
+        let mut imported = vec![];
+        let events = run(&mut sync, &mut rpc, &mut remotes, |_, blocks| {
+            imported.extend_from_slice(blocks);
+            BatchProcessResult::Success
+        });
+
+        assert_eq!(events, vec![SyncEvent::Completed]);
+        assert_eq!(slots(&imported), slots(&chain));
+        assert!(sync.is_synced());
+    }
+
+    #[test]
+    fn test_peer_on_other_chain() {
+        let genesis = Hash256::from("genesis".as_bytes());
+        let chain_slots: Vec<u64> = (1..150).collect();
+        let (good, chain) = remote(genesis, &chain_slots);
+        let (bad, _) = remote(Hash256::from("fork".as_bytes()), &chain_slots);
+        let bad_id = bad.peer_id;
+        let mut remotes = vec![good, bad];
+
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+        let mut sync = RangeSync::new(0, genesis, Logger::root(Discard, o!()));
+        for remote in &remotes {
+            sync.add_peer(&mut rpc, remote.peer_id, &status(149), Instant::now());
+        }
+
+        let mut imported = vec![];
+        let events = run(&mut sync, &mut rpc, &mut remotes, |_, blocks| {
+            imported.extend_from_slice(blocks);
+            BatchProcessResult::Success
+        });
+
+        /*
+         * Each batch served by the bad peer fails to connect to the chain and is downloaded
+         * again from the good peer.
+         */
+        assert_eq!(events.last(), Some(&SyncEvent::Completed));
+        for event in &events[..events.len() - 1] {
+            assert_eq!(
+                *event,
+                SyncEvent::ReportPeer {
+                    peer_id: bad_id,
+                    action: PeerAction::MidToleranceError,
+                }
+            );
+        }
+        assert_eq!(slots(&imported), slots(&chain));
+    }
+
+    #[test]
+    fn test_processing_retry_and_failure() {
+        let genesis = Hash256::from("genesis".as_bytes());
+        let (peer, chain) = remote(genesis, &(1..100).collect::<Vec<u64>>());
+        let peer_id = peer.peer_id;
+        let mut remotes = vec![peer];
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+
+        /*
+         * A batch which fails once is downloaded and processed again.
+         */
+        let mut sync = RangeSync::new(0, genesis, Logger::root(Discard, o!()));
+        sync.add_peer(&mut rpc, peer_id, &status(99), Instant::now());
+        let mut imported = vec![];
+        let mut failed = false;
+        let events = run(&mut sync, &mut rpc, &mut remotes, |batch_id, blocks| {
+            if batch_id == 1 && !failed {
+                failed = true;
+                return BatchProcessResult::Failed;
+            }
+            imported.extend_from_slice(blocks);
+            BatchProcessResult::Success
+        });
+        let report = SyncEvent::ReportPeer {
+            peer_id,
+            action: PeerAction::MidToleranceError,
+        };
+        assert_eq!(events, vec![report, SyncEvent::Completed]);
+        assert_eq!(slots(&imported), slots(&chain));
+
+        /*
+         * A batch which always fails causes sync to be abandoned.
+         */
+        let mut sync = RangeSync::new(0, genesis, Logger::root(Discard, o!()));
+        sync.add_peer(&mut rpc, peer_id, &status(99), Instant::now());
+        let events = run(&mut sync, &mut rpc, &mut remotes, |_, _| {
+            BatchProcessResult::Failed
+        });
+        assert_eq!(events.len(), MAX_PROCESSING_ATTEMPTS as usize + 1);
+        assert_eq!(events.last(), Some(&SyncEvent::Failed));
+        assert!(sync.is_synced());
+    }
+
+    #[test]
+    fn test_peer_behind() {
+        let mut rpc = RPC::new(Logger::root(Discard, o!()));
+        let mut sync = RangeSync::new(10, Hash256::zero(), Logger::root(Discard, o!()));
+        sync.add_peer(&mut rpc, NodeId::random(), &status(10), Instant::now());
+        assert!(sync.is_synced());
+        assert_eq!(rpc.next_outbound(), None);
+    }
+}
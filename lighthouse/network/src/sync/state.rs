@@ -0,0 +1,86 @@
+/// Whether the head of the node has reached the present slot, and if not, whether it can.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncState {
+    /// The head is no further behind the present slot than the tolerance.
+    Synced,
+    /// The head is `distance` slots behind the present slot, and a peer is ahead of it to sync
+    /// from, or the peers are unknown.
+    Syncing { distance: u64 },
+    /// The head is `distance` slots behind the present slot, but no connected peer is ahead of
+    /// it, so it will not catch up until one connects.
+    Stalled { distance: u64 },
+}
+
+impl SyncState {
+    /// Classifies a head at `head_slot`, which may be `tolerance` slots behind the present slot
+    /// and still be synced.
+    ///
+    /// `best_peer_head` is the greatest head slot advertised by a connected peer, zero if no
+    /// peers are connected, or `None` if the peers are not known.
+    pub fn new(
+        head_slot: u64,
+        present_slot: u64,
+        tolerance: u64,
+        best_peer_head: Option<u64>,
+    ) -> Self {
+        let distance = present_slot.saturating_sub(head_slot);
+        if distance <= tolerance {
+            return SyncState::Synced;
+        }
+        match best_peer_head {
+            Some(best_peer_head) if best_peer_head <= head_slot => SyncState::Stalled { distance },
+            _ => SyncState::Syncing { distance },
+        }
+    }
+
+    pub fn is_synced(&self) -> bool {
+        *self == SyncState::Synced
+    }
+
+    /// Returns the slots by which the head is behind the present slot, zero if synced.
+    pub fn distance(&self) -> u64 {
+        match *self {
+            SyncState::Synced => 0,
+            SyncState::Syncing { distance } | SyncState::Stalled { distance } => distance,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncState::Synced => "synced",
+            SyncState::Syncing { .. } => "syncing",
+            SyncState::Stalled { .. } => "stalled",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_state() {
+        assert_eq!(SyncState::new(98, 100, 2, Some(0)), SyncState::Synced);
+        assert_eq!(SyncState::new(101, 100, 2, None), SyncState::Synced);
+        assert_eq!(
+            SyncState::new(90, 100, 2, None),
+            SyncState::Syncing { distance: 10 }
+        );
+        assert_eq!(
+            SyncState::new(90, 100, 2, Some(95)),
+            SyncState::Syncing { distance: 10 }
+        );
+        assert_eq!(
+            SyncState::new(90, 100, 2, Some(90)),
+            SyncState::Stalled { distance: 10 }
+        );
+        assert_eq!(
+            SyncState::new(90, 100, 2, Some(0)),
+            SyncState::Stalled { distance: 10 }
+        );
+        assert_eq!(SyncState::Stalled { distance: 10 }.distance(), 10);
+        assert_eq!(SyncState::Synced.distance(), 0);
+        assert!(!SyncState::Syncing { distance: 3 }.is_synced());
+        assert_eq!(SyncState::Stalled { distance: 3 }.name(), "stalled");
+    }
+}
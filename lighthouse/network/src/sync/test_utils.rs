@@ -0,0 +1,94 @@
+use super::super::db::stores::BeaconBlockStore;
+use super::super::db::MemoryDB;
+use super::super::enr::NodeId;
+use super::super::rpc::{PeerId, RPCEvent, RPCRequest, RPC};
+use super::super::ssz::ssz_encode;
+use super::super::types::{BeaconBlock, Hash256};
+use slog::{Discard, Logger};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A peer serving blocks from its own store, with its own RPC.
+pub struct Remote {
+    pub peer_id: PeerId,
+    pub rpc: RPC,
+    pub store: BeaconBlockStore<MemoryDB>,
+    pub head: Hash256,
+}
+
+impl Remote {
+    /// Builds a peer storing `blocks`, whose head is the last of them.
+    pub fn new(blocks: &[BeaconBlock]) -> Self {
+        let store = BeaconBlockStore::new(Arc::new(MemoryDB::open()));
+        let mut head = Hash256::zero();
+        for block in blocks {
+            head = block.canonical_root();
+            store
+                .put_serialized_block(&head, &ssz_encode(block))
+                .unwrap();
+        }
+        Self {
+            peer_id: NodeId::random(),
+            rpc: RPC::new(Logger::root(Discard, o!())),
+            store,
+            head,
+        }
+    }
+}
+
+/// Delivers messages between the local node and `remotes` until there is nothing left to do,
+/// returning the events of the component under test.
+///
+/// The remotes answer blocks by range and blocks by root requests. `step` hands the events of the
+/// local RPC to the component and pushes the events it emits, returning whether anything
+/// happened.
+pub fn run<E, F>(rpc: &mut RPC, remotes: &mut [Remote], mut step: F) -> Vec<E>
+where
+    F: FnMut(&mut RPC, Instant, &mut Vec<E>) -> bool,
+{
+    let now = Instant::now();
+    let local_id = NodeId::random();
+    let mut events = vec![];
+    loop {
+        let mut progress = false;
+        while let Some((dst, message)) = rpc.next_outbound() {
+            if let Some(remote) = remotes.iter_mut().find(|r| r.peer_id == dst) {
+                remote.rpc.on_message(local_id, message, now);
+            }
+            progress = true;
+        }
+        for remote in remotes.iter_mut() {
+            let Remote {
+                peer_id,
+                rpc: ref mut remote_rpc,
+                ref store,
+                ref head,
+            } = *remote;
+            while let Some(event) = remote_rpc.poll(now) {
+                match event {
+                    RPCEvent::Request {
+                        id,
+                        request: RPCRequest::BlocksByRange(request),
+                        ..
+                    } => remote_rpc.respond_blocks_by_range(local_id, id, &request, store, head),
+                    RPCEvent::Request {
+                        id,
+                        request: RPCRequest::BlocksByRoot(request),
+                        ..
+                    } => remote_rpc.respond_blocks_by_root(local_id, id, &request, store),
+                    _ => {}
+                }
+            }
+            while let Some((_, message)) = remote_rpc.next_outbound() {
+                rpc.on_message(peer_id, message, now);
+                progress = true;
+            }
+        }
+        if step(rpc, now, &mut events) {
+            progress = true;
+        }
+        if !progress {
+            return events;
+        }
+    }
+}
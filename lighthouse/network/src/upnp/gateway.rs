@@ -0,0 +1,417 @@
+use super::UPnPError;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// The multicast address to which SSDP searches are sent.
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+/// The device type searched for via SSDP.
+const GATEWAY_DEVICE: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+/// The services able to create port mappings, in order of preference.
+const CONNECTION_SERVICES: [&str; 2] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+/// The timeout applied to each HTTP request made of the gateway.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PortMappingProtocol {
+    TCP,
+    UDP,
+}
+
+impl PortMappingProtocol {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PortMappingProtocol::TCP => "TCP",
+            PortMappingProtocol::UDP => "UDP",
+        }
+    }
+}
+
+/// A UPnP internet gateway device, typically a home router, able to forward ports to this host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gateway {
+    /// The address of the gateway's HTTP server.
+    pub addr: SocketAddr,
+    /// The path to which SOAP requests are posted.
+    pub control_path: String,
+    /// The connection service offered by the gateway.
+    pub service_type: String,
+}
+
+/// Searches the local network for an internet gateway device, waiting at most `timeout`.
+pub fn search_gateway(timeout: Duration) -> Result<Gateway, UPnPError> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\n\r\n",
+        SSDP_ADDR, GATEWAY_DEVICE
+    );
+    socket.send_to(request.as_bytes(), SSDP_ADDR)?;
+
+    /*
+     * Other devices may also respond, so keep reading until a response with a location arrives.
+     */
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0; 1_500];
+    let location = loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(UPnPError::NoGateway);
+        }
+        socket.set_read_timeout(Some(deadline - now))?;
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, _)) => len,
+            Err(_) => return Err(UPnPError::NoGateway),
+        };
+        if let Some(location) = parse_ssdp_location(&String::from_utf8_lossy(&buf[..len])) {
+            break location;
+        }
+    };
+
+    let (addr, path) = parse_url(&location)?;
+    let description = http_request(
+        addr,
+        &format!("GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", path, addr),
+    )?;
+    let (service_type, control_url) =
+        find_connection_service(&description).ok_or(UPnPError::NoConnectionService)?;
+    let (addr, control_path) = if control_url.starts_with("http://") {
+        parse_url(&control_url)?
+    } else {
+        (addr, control_url)
+    };
+
+    Ok(Gateway {
+        addr,
+        control_path,
+        service_type,
+    })
+}
+
+impl Gateway {
+    /// Returns the address of this host on the network shared with the gateway.
+    pub fn local_ip(&self) -> Result<Ipv4Addr, UPnPError> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(self.addr)?;
+        match socket.local_addr()? {
+            SocketAddr::V4(addr) => Ok(*addr.ip()),
+            SocketAddr::V6(_) => Err(UPnPError::InvalidResponse("IPv6 gateway".to_string())),
+        }
+    }
+
+    /// Returns the address of the gateway on the internet.
+    pub fn external_ip(&self) -> Result<Ipv4Addr, UPnPError> {
+        let response = self.soap_request("GetExternalIPAddress", "")?;
+        tag_value(&response, "NewExternalIPAddress")
+            .and_then(|ip| ip.trim().parse().ok())
+            .ok_or_else(|| UPnPError::InvalidResponse("No external IP address".to_string()))
+    }
+
+    /// Forwards `external_port` on the gateway to `local_addr`, for `lease_duration` seconds.
+    pub fn add_port_mapping(
+        &self,
+        protocol: PortMappingProtocol,
+        external_port: u16,
+        local_addr: SocketAddrV4,
+        lease_duration: u32,
+        description: &str,
+    ) -> Result<(), UPnPError> {
+        let args = format!(
+            "<NewRemoteHost></NewRemoteHost>\
+             <NewExternalPort>{}</NewExternalPort>\
+             <NewProtocol>{}</NewProtocol>\
+             <NewInternalPort>{}</NewInternalPort>\
+             <NewInternalClient>{}</NewInternalClient>\
+             <NewEnabled>1</NewEnabled>\
+             <NewPortMappingDescription>{}</NewPortMappingDescription>\
+             <NewLeaseDuration>{}</NewLeaseDuration>",
+            external_port,
+            protocol.as_str(),
+            local_addr.port(),
+            local_addr.ip(),
+            description,
+            lease_duration
+        );
+        self.soap_request("AddPortMapping", &args).map(|_| ())
+    }
+
+    pub fn remove_port_mapping(
+        &self,
+        protocol: PortMappingProtocol,
+        external_port: u16,
+    ) -> Result<(), UPnPError> {
+        let args = format!(
+            "<NewRemoteHost></NewRemoteHost>\
+             <NewExternalPort>{}</NewExternalPort>\
+             <NewProtocol>{}</NewProtocol>",
+            external_port,
+            protocol.as_str()
+        );
+        self.soap_request("DeletePortMapping", &args).map(|_| ())
+    }
+
+    fn soap_request(&self, action: &str, args: &str) -> Result<String, UPnPError> {
+        let body = soap_body(&self.service_type, action, args);
+        let request = format!(
+            "POST {} HTTP/1.0\r\n\
+             Host: {}\r\n\
+             Content-Type: text/xml; charset=\"utf-8\"\r\n\
+             SOAPAction: \"{}#{}\"\r\n\
+             Content-Length: {}\r\n\r\n{}",
+            self.control_path,
+            self.addr,
+            self.service_type,
+            action,
+            body.len(),
+            body
+        );
+        http_request(self.addr, &request)
+    }
+}
+
+fn soap_body(service_type: &str, action: &str, args: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service}\">{args}</u:{action}></s:Body>\
+         </s:Envelope>",
+        action = action,
+        service = service_type,
+        args = args
+    )
+}
+
+/// Sends a raw HTTP/1.0 request, returning the body of a successful response.
+///
+/// HTTP/1.0 is used so the response is never chunked and ends when the connection closes.
+fn http_request(addr: SocketAddr, request: &str) -> Result<String, UPnPError> {
+    let mut stream = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = match response.find("\r\n\r\n") {
+        Some(i) => (&response[..i], &response[i + 4..]),
+        None => {
+            return Err(UPnPError::InvalidResponse(
+                "Malformed HTTP response".to_string(),
+            ))
+        }
+    };
+    let status = head.lines().next().unwrap_or("");
+    if status.split_whitespace().nth(1) != Some("200") {
+        let reason = tag_value(body, "errorDescription").unwrap_or(status);
+        return Err(UPnPError::RequestFailed(reason.to_string()));
+    }
+    Ok(body.to_string())
+}
+
+/// Returns the value of the `LOCATION` header of an SSDP response.
+fn parse_ssdp_location(response: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let colon = line.find(':')?;
+        if line[..colon].trim().eq_ignore_ascii_case("location") {
+            Some(line[colon + 1..].trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Splits an `http://host[:port]/path` URL into a socket address and a path.
+fn parse_url(url: &str) -> Result<(SocketAddr, String), UPnPError> {
+    let invalid = || UPnPError::InvalidResponse(format!("Invalid URL: {}", url));
+    if !url.starts_with("http://") {
+        return Err(invalid());
+    }
+    let rest = &url["http://".len()..];
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let host = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let addr = host
+        .to_socket_addrs()
+        .map_err(|_| invalid())?
+        .next()
+        .ok_or_else(invalid)?;
+    Ok((addr, path.to_string()))
+}
+
+/// Finds the first supported connection service in a device description, returning its type
+/// and control URL.
+fn find_connection_service(description: &str) -> Option<(String, String)> {
+    for wanted in &CONNECTION_SERVICES {
+        for service in description.split("<service>").skip(1) {
+            if tag_value(service, "serviceType") == Some(*wanted) {
+                if let Some(control_url) = tag_value(service, "controlURL") {
+                    return Some((wanted.to_string(), control_url.to_string()));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Returns the text of the first `<tag>` element in `xml`, ignoring any namespace prefix.
+fn tag_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("{}>", tag);
+    let mut search = xml;
+    while let Some(i) = search.find(&open) {
+        let before = &search[..i];
+        let is_open_tag = before.ends_with('<') || before.ends_with(':') && before.contains('<');
+        let after = &search[i + open.len()..];
+        if is_open_tag && !before.ends_with("</") {
+            let end = after.find('<')?;
+            return Some(&after[..end]);
+        }
+        search = after;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const DESCRIPTION: &str = "<root><device><serviceList>\
+        <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+        <controlURL>/ctl/L3F</controlURL></service>\
+        <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>\
+        <controlURL>/ctl/IPConn</controlURL></service>\
+        </serviceList></device></root>";
+
+    #[test]
+    fn test_parse_ssdp_location() {
+        let response = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\n\
+                        Location: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        assert_eq!(
+            parse_ssdp_location(response),
+            Some("http://192.168.1.1:5000/rootDesc.xml".to_string())
+        );
+        assert_eq!(parse_ssdp_location("HTTP/1.1 200 OK\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url("http://192.168.1.1:5000/rootDesc.xml"),
+            Ok((
+                "192.168.1.1:5000".parse().unwrap(),
+                "/rootDesc.xml".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_url("http://192.168.1.1"),
+            Ok(("192.168.1.1:80".parse().unwrap(), "/".to_string()))
+        );
+        assert!(parse_url("https://192.168.1.1/").is_err());
+    }
+
+    #[test]
+    fn test_find_connection_service() {
+        assert_eq!(
+            find_connection_service(DESCRIPTION),
+            Some((
+                "urn:schemas-upnp-org:service:WANIPConnection:1".to_string(),
+                "/ctl/IPConn".to_string()
+            ))
+        );
+        assert_eq!(find_connection_service("<root></root>"), None);
+    }
+
+    #[test]
+    fn test_tag_value() {
+        let xml = "<s:Body><u:Response><NewExternalIPAddress>1.2.3.4</NewExternalIPAddress>\
+                   </u:Response></s:Body>";
+        assert_eq!(tag_value(xml, "NewExternalIPAddress"), Some("1.2.3.4"));
+        assert_eq!(
+            tag_value(
+                "<s:errorDescription>Bad</s:errorDescription>",
+                "errorDescription"
+            ),
+            Some("Bad")
+        );
+        assert_eq!(tag_value(xml, "Missing"), None);
+    }
+
+    /// Serves a single HTTP request with `response`, returning the address of the server.
+    fn serve_once(response: &'static str) -> (SocketAddr, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4_096];
+            let len = stream.read(&mut buf).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&buf[..len]).into_owned()
+        });
+        (addr, handle)
+    }
+
+    fn gateway(addr: SocketAddr) -> Gateway {
+        Gateway {
+            addr,
+            control_path: "/ctl/IPConn".to_string(),
+            service_type: CONNECTION_SERVICES[0].to_string(),
+        }
+    }
+
+    #[test]
+    fn test_external_ip() {
+        let (addr, handle) = serve_once(
+            "HTTP/1.0 200 OK\r\nContent-Type: text/xml\r\n\r\n\
+             <s:Envelope><s:Body><u:GetExternalIPAddressResponse>\
+             <NewExternalIPAddress>203.0.113.7</NewExternalIPAddress>\
+             </u:GetExternalIPAddressResponse></s:Body></s:Envelope>",
+        );
+        assert_eq!(
+            gateway(addr).external_ip(),
+            Ok(Ipv4Addr::new(203, 0, 113, 7))
+        );
+
+        let request = handle.join().unwrap();
+        assert!(request.starts_with("POST /ctl/IPConn HTTP/1.0\r\n"));
+        assert!(request.contains(
+            "SOAPAction: \"urn:schemas-upnp-org:service:WANIPConnection:1#GetExternalIPAddress\""
+        ));
+    }
+
+    #[test]
+    fn test_request_failure() {
+        let (addr, handle) = serve_once(
+            "HTTP/1.0 500 Internal Server Error\r\n\r\n\
+             <s:Envelope><s:Body><s:Fault><detail><UPnPError>\
+             <errorCode>718</errorCode><errorDescription>ConflictInMappingEntry</errorDescription>\
+             </UPnPError></detail></s:Fault></s:Body></s:Envelope>",
+        );
+        let local_addr = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 9000);
+        assert_eq!(
+            gateway(addr).add_port_mapping(
+                PortMappingProtocol::TCP,
+                9000,
+                local_addr,
+                3_600,
+                "test"
+            ),
+            Err(UPnPError::RequestFailed(
+                "ConflictInMappingEntry".to_string()
+            ))
+        );
+
+        let request = handle.join().unwrap();
+        assert!(request.contains("<NewInternalClient>192.168.1.2</NewInternalClient>"));
+        assert!(request.contains("<NewProtocol>TCP</NewProtocol>"));
+    }
+}
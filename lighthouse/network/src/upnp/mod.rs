@@ -0,0 +1,26 @@
+mod gateway;
+mod service;
+
+pub use self::gateway::{search_gateway, Gateway, PortMappingProtocol};
+pub use self::service::{UPnPConfig, UPnPEvent, UPnPService};
+
+use std::io;
+
+#[derive(Debug, PartialEq)]
+pub enum UPnPError {
+    Io(String),
+    /// No internet gateway device responded to the search.
+    NoGateway,
+    /// The gateway does not offer a WAN IP or PPP connection service.
+    NoConnectionService,
+    /// The gateway responded with something which could not be understood.
+    InvalidResponse(String),
+    /// The gateway returned an error for a request.
+    RequestFailed(String),
+}
+
+impl From<io::Error> for UPnPError {
+    fn from(error: io::Error) -> Self {
+        UPnPError::Io(format!("{}", error))
+    }
+}
@@ -0,0 +1,312 @@
+use super::{search_gateway, Gateway, PortMappingProtocol, UPnPError};
+use slog::Logger;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use task_executor::TaskExecutor;
+
+/// The description attached to port mappings, shown in router admin pages.
+const MAPPING_DESCRIPTION: &str = "lighthouse";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UPnPConfig {
+    /// The local port accepting libp2p connections.
+    pub tcp_port: u16,
+    /// The local port used for discovery, or `None` if discovery is disabled.
+    pub udp_port: Option<u16>,
+    /// The lease requested for each mapping. Mappings are renewed at half this interval.
+    pub lease_duration: Duration,
+    /// How long to wait for a gateway to respond to the search.
+    pub search_timeout: Duration,
+}
+
+impl Default for UPnPConfig {
+    fn default() -> Self {
+        Self {
+            tcp_port: 9000,
+            udp_port: Some(9000),
+            lease_duration: Duration::from_secs(60 * 60),
+            search_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UPnPEvent {
+    /// The ports are reachable from the internet at `external_ip`, each on the same port as
+    /// locally. A port which could not be mapped is `None`. Emitted on the first mapping and
+    /// whenever the mapping changes.
+    Mapped {
+        external_ip: Ipv4Addr,
+        tcp_port: Option<u16>,
+        udp_port: Option<u16>,
+    },
+    /// Mapping failed and will not be retried.
+    Failed(UPnPError),
+}
+
+/// Maps the local TCP and UDP ports on the network's internet gateway from a background
+/// thread, renewing the leases until the service is dropped, at which point the mappings are
+/// removed.
+///
+/// The external address is delivered on the receiver returned by `UPnPService::start`, to be
+/// reflected into the local ENR.
+pub struct UPnPService {
+    shutdown: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl UPnPService {
+    pub fn start(
+        config: UPnPConfig,
+        executor: &TaskExecutor,
+        log: Logger,
+    ) -> (Self, Receiver<UPnPEvent>) {
+        Self::spawn(config, executor, log, search_gateway)
+    }
+
+    fn spawn<F>(
+        config: UPnPConfig,
+        executor: &TaskExecutor,
+        log: Logger,
+        search: F,
+    ) -> (Self, Receiver<UPnPEvent>)
+    where
+        F: FnOnce(Duration) -> Result<Gateway, UPnPError> + Send + 'static,
+    {
+        let (shutdown_tx, shutdown_rx) = channel();
+        let (event_tx, event_rx) = channel();
+
+        let handle = executor.spawn("upnp", move |_| {
+            info!(log, "Searching for UPnP gateway");
+            match search(config.search_timeout) {
+                Ok(gateway) => run(&gateway, &config, &shutdown_rx, &event_tx, &log),
+                Err(e) => {
+                    warn!(log, "UPnP gateway not found"; "error" => format!("{:?}", e));
+                    let _ = event_tx.send(UPnPEvent::Failed(e));
+                }
+            }
+        });
+
+        let service = Self {
+            shutdown: shutdown_tx,
+            handle: Some(handle),
+        };
+        (service, event_rx)
+    }
+}
+
+impl Drop for UPnPService {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    gateway: &Gateway,
+    config: &UPnPConfig,
+    shutdown: &Receiver<()>,
+    events: &Sender<UPnPEvent>,
+    log: &Logger,
+) {
+    let mut mapping = None;
+    loop {
+        match map_ports(gateway, config, log) {
+            Ok(mapped) => {
+                if mapping != Some(mapped) {
+                    let (external_ip, tcp_port, udp_port) = mapped;
+                    info!(log, "UPnP ports mapped"; "external_ip" => format!("{}", external_ip), "tcp_port" => tcp_port, "udp_port" => udp_port);
+                    mapping = Some(mapped);
+                    let _ = events.send(UPnPEvent::Mapped {
+                        external_ip,
+                        tcp_port,
+                        udp_port,
+                    });
+                }
+            }
+            /*
+             * A failed renewal leaves any existing lease in place, so only give up if the ports
+             * were never mapped.
+             */
+            Err(e) => {
+                warn!(log, "UPnP port mapping failed"; "error" => format!("{:?}", e));
+                if mapping.is_none() {
+                    let _ = events.send(UPnPEvent::Failed(e));
+                    return;
+                }
+            }
+        }
+
+        match shutdown.recv_timeout(config.lease_duration / 2) {
+            Err(RecvTimeoutError::Timeout) => continue,
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    debug!(log, "Removing UPnP port mappings");
+    for &(protocol, port) in &mappings(config) {
+        if let Err(e) = gateway.remove_port_mapping(protocol, port) {
+            debug!(log, "Failed to remove UPnP port mapping"; "port" => port, "error" => format!("{:?}", e));
+        }
+    }
+}
+
+fn mappings(config: &UPnPConfig) -> Vec<(PortMappingProtocol, u16)> {
+    let mut mappings = vec![(PortMappingProtocol::TCP, config.tcp_port)];
+    if let Some(port) = config.udp_port {
+        mappings.push((PortMappingProtocol::UDP, port));
+    }
+    mappings
+}
+
+/// Maps each port to the same port on the gateway, returning the external IP with the ports
+/// which were mapped. The ports are mapped separately, so that a gateway refusing one still
+/// forwards the other.
+fn map_ports(
+    gateway: &Gateway,
+    config: &UPnPConfig,
+    log: &Logger,
+) -> Result<(Ipv4Addr, Option<u16>, Option<u16>), UPnPError> {
+    let local_ip = gateway.local_ip()?;
+    let lease_secs = config.lease_duration.as_secs() as u32;
+    let (mut tcp_port, mut udp_port) = (None, None);
+    let mut error = None;
+    for (protocol, port) in mappings(config) {
+        let local_addr = SocketAddrV4::new(local_ip, port);
+        match gateway.add_port_mapping(protocol, port, local_addr, lease_secs, MAPPING_DESCRIPTION)
+        {
+            Ok(()) => match protocol {
+                PortMappingProtocol::TCP => tcp_port = Some(port),
+                PortMappingProtocol::UDP => udp_port = Some(port),
+            },
+            Err(e) => {
+                debug!(log, "UPnP port not mapped"; "protocol" => format!("{:?}", protocol), "port" => port, "error" => format!("{:?}", e));
+                error = Some(e);
+            }
+        }
+    }
+    match error {
+        Some(e) if tcp_port.is_none() && udp_port.is_none() => Err(e),
+        _ => Ok((gateway.external_ip()?, tcp_port, udp_port)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::Discard;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    const SUCCESS: &str = "HTTP/1.0 200 OK\r\n\r\n\
+                           <s:Envelope><s:Body><u:Response>\
+                           <NewExternalIPAddress>203.0.113.7</NewExternalIPAddress>\
+                           </u:Response></s:Body></s:Envelope>";
+
+    const REFUSED: &str = "HTTP/1.0 500 Internal Server Error\r\n\r\n";
+
+    /// Starts a gateway which accepts every request but mappings of UDP ports if `refuse_udp`,
+    /// returning the SOAP actions received.
+    fn fake_gateway(refuse_udp: bool) -> (Gateway, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let actions = Arc::new(Mutex::new(vec![]));
+        let received = actions.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 4_096];
+                let len = stream.read(&mut buf).unwrap();
+                let request = String::from_utf8_lossy(&buf[..len]).into_owned();
+                let mut action = "";
+                if let Some(line) = request.lines().find(|l| l.starts_with("SOAPAction")) {
+                    action = line.split('#').nth(1).unwrap_or("").trim_end_matches('"');
+                    received.lock().unwrap().push(action.to_string());
+                }
+                let udp = request.contains("<NewProtocol>UDP</NewProtocol>");
+                if refuse_udp && udp && action == "AddPortMapping" {
+                    stream.write_all(REFUSED.as_bytes()).unwrap();
+                } else {
+                    stream.write_all(SUCCESS.as_bytes()).unwrap();
+                }
+            }
+        });
+        let gateway = Gateway {
+            addr,
+            control_path: "/ctl/IPConn".to_string(),
+            service_type: "urn:schemas-upnp-org:service:WANIPConnection:1".to_string(),
+        };
+        (gateway, actions)
+    }
+
+    fn config() -> UPnPConfig {
+        UPnPConfig {
+            tcp_port: 9000,
+            udp_port: Some(9001),
+            ..UPnPConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_map_and_remove_ports() {
+        let (gateway, actions) = fake_gateway(false);
+        let log = Logger::root(Discard, o!());
+        let (executor, _) = TaskExecutor::new(log.clone());
+        let (service, events) = UPnPService::spawn(config(), &executor, log, move |_| Ok(gateway));
+
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(5)),
+            Ok(UPnPEvent::Mapped {
+                external_ip: Ipv4Addr::new(203, 0, 113, 7),
+                tcp_port: Some(9000),
+                udp_port: Some(9001),
+            })
+        );
+
+        drop(service);
+        assert_eq!(
+            *actions.lock().unwrap(),
+            vec![
+                "AddPortMapping",
+                "AddPortMapping",
+                "GetExternalIPAddress",
+                "DeletePortMapping",
+                "DeletePortMapping",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_udp_refused() {
+        let (gateway, _) = fake_gateway(true);
+        let log = Logger::root(Discard, o!());
+        let (executor, _) = TaskExecutor::new(log.clone());
+        let (_service, events) = UPnPService::spawn(config(), &executor, log, move |_| Ok(gateway));
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(5)),
+            Ok(UPnPEvent::Mapped {
+                external_ip: Ipv4Addr::new(203, 0, 113, 7),
+                tcp_port: Some(9000),
+                udp_port: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_gateway() {
+        let log = Logger::root(Discard, o!());
+        let (executor, _) = TaskExecutor::new(log.clone());
+        let (_service, events) =
+            UPnPService::spawn(config(), &executor, log, |_| Err(UPnPError::NoGateway));
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(5)),
+            Ok(UPnPEvent::Failed(UPnPError::NoGateway))
+        );
+    }
+}
@@ -0,0 +1,7 @@
+extern crate futures;
+extern crate grpcio;
+extern crate protobuf;
+
+// The generated code is created by `build.rs` from `services.proto`.
+pub mod services;
+pub mod services_grpc;
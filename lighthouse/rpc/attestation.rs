@@ -0,0 +1,86 @@
+use super::{reply, reply_error};
+use db::DiskDB;
+use grpcio::{RpcContext, RpcStatusCode, UnarySink};
+use http_api::{publish_attestation, Context};
+use protos::services::{
+    AttestationData as AttestationDataProto, ProduceAttestationDataRequest,
+    ProduceAttestationDataResponse, PublishAttestationRequest, PublishAttestationResponse,
+};
+use protos::services_grpc::AttestationService;
+use slog::Logger;
+use ssz::{ssz_encode, Decodable};
+use std::sync::Arc;
+use types::Attestation;
+
+#[derive(Clone)]
+pub struct AttestationServiceInstance {
+    pub ctx: Arc<Context<DiskDB>>,
+    pub log: Logger,
+}
+
+impl AttestationService for AttestationServiceInstance {
+    /// Returns the data to be signed by the committee of the requested shard and slot.
+    fn produce_attestation_data(
+        &mut self,
+        ctx: RpcContext,
+        req: ProduceAttestationDataRequest,
+        sink: UnarySink<ProduceAttestationDataResponse>,
+    ) {
+        let produced = self
+            .ctx
+            .node
+            .read()
+            .expect("Beacon node lock poisoned")
+            .produce_attestation_data(req.get_slot(), req.get_shard());
+        match produced {
+            Ok(data) => {
+                let mut data_proto = AttestationDataProto::new();
+                data_proto.set_ssz(ssz_encode(&data));
+                let mut response = ProduceAttestationDataResponse::new();
+                response.set_attestation_data(data_proto);
+                reply(&ctx, sink, response, &self.log)
+            }
+            Err(e) => {
+                let details = format!("{:?}", e);
+                reply_error(&ctx, sink, RpcStatusCode::NotFound, details, &self.log)
+            }
+        }
+    }
+
+    /// Verifies a signed attestation as if it were received by gossip, then adds it to the pool,
+    /// to be included in a block, and publishes it.
+    fn publish_attestation(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishAttestationRequest,
+        sink: UnarySink<PublishAttestationResponse>,
+    ) {
+        let attestation = match Attestation::ssz_decode(req.get_attestation().get_ssz(), 0) {
+            Ok((attestation, _)) => attestation,
+            Err(_) => {
+                let details = "Invalid attestation SSZ".to_string();
+                return reply_error(
+                    &ctx,
+                    sink,
+                    RpcStatusCode::InvalidArgument,
+                    details,
+                    &self.log,
+                );
+            }
+        };
+
+        let mut response = PublishAttestationResponse::new();
+        match publish_attestation(&self.ctx, attestation) {
+            Ok(()) => {
+                debug!(self.log, "Attestation published");
+                response.set_success(true);
+            }
+            Err(message) => {
+                debug!(self.log, "Published attestation rejected"; "error" => &message);
+                response.set_success(false);
+                response.set_msg(message.into_bytes());
+            }
+        }
+        reply(&ctx, sink, response, &self.log)
+    }
+}
@@ -0,0 +1,108 @@
+use super::{reply, reply_error};
+use beacon_node::{BeaconNode, BlockProcessingOutcome};
+use db::DiskDB;
+use grpcio::{RpcContext, RpcStatusCode, UnarySink};
+use protos::services::{
+    BeaconBlock as BeaconBlockProto, ProduceBeaconBlockRequest, ProduceBeaconBlockResponse,
+    PublishBeaconBlockRequest, PublishBeaconBlockResponse,
+};
+use protos::services_grpc::BeaconBlockService;
+use slog::Logger;
+use ssz::{ssz_encode, Decodable};
+use std::sync::{Arc, RwLock};
+use types::{BeaconBlock, Hash256};
+
+#[derive(Clone)]
+pub struct BeaconBlockServiceInstance {
+    pub node: Arc<RwLock<BeaconNode<DiskDB>>>,
+    pub log: Logger,
+}
+
+impl BeaconBlockService for BeaconBlockServiceInstance {
+    /// Produces an unsigned block for the requested slot, built on the head.
+    fn produce_beacon_block(
+        &mut self,
+        ctx: RpcContext,
+        req: ProduceBeaconBlockRequest,
+        sink: UnarySink<ProduceBeaconBlockResponse>,
+    ) {
+        if req.get_randao_reveal().len() != 32 {
+            let details = "randao_reveal must be 32 bytes".to_string();
+            return reply_error(
+                &ctx,
+                sink,
+                RpcStatusCode::InvalidArgument,
+                details,
+                &self.log,
+            );
+        }
+        let randao_reveal = Hash256::from(req.get_randao_reveal());
+
+        let produced = self
+            .node
+            .read()
+            .expect("Beacon node lock poisoned")
+            .produce_block(req.get_slot(), randao_reveal, Hash256::zero());
+        match produced {
+            Ok(block) => {
+                debug!(self.log, "Produced block"; "slot" => block.slot);
+                let mut block_proto = BeaconBlockProto::new();
+                block_proto.set_ssz(ssz_encode(&block));
+                let mut response = ProduceBeaconBlockResponse::new();
+                response.set_block(block_proto);
+                reply(&ctx, sink, response, &self.log)
+            }
+            Err(e) => {
+                let details = format!("{:?}", e);
+                reply_error(
+                    &ctx,
+                    sink,
+                    RpcStatusCode::FailedPrecondition,
+                    details,
+                    &self.log,
+                )
+            }
+        }
+    }
+
+    /// Imports a block signed by its proposer.
+    fn publish_beacon_block(
+        &mut self,
+        ctx: RpcContext,
+        req: PublishBeaconBlockRequest,
+        sink: UnarySink<PublishBeaconBlockResponse>,
+    ) {
+        let block = match BeaconBlock::ssz_decode(req.get_block().get_ssz(), 0) {
+            Ok((block, _)) => block,
+            Err(_) => {
+                let details = "Invalid block SSZ".to_string();
+                return reply_error(
+                    &ctx,
+                    sink,
+                    RpcStatusCode::InvalidArgument,
+                    details,
+                    &self.log,
+                );
+            }
+        };
+
+        let mut node = self.node.write().expect("Beacon node lock poisoned");
+        let slot = node.present_slot_with_future_tolerance();
+        let mut response = PublishBeaconBlockResponse::new();
+        match node.process_block(&block, slot) {
+            Ok(outcome) => {
+                info!(self.log, "Block published"; "slot" => block.slot, "outcome" => format!("{:?}", outcome));
+                let success = outcome == BlockProcessingOutcome::Imported
+                    || outcome == BlockProcessingOutcome::AlreadyKnown;
+                response.set_success(success);
+                response.set_msg(format!("{:?}", outcome).into_bytes());
+            }
+            Err(e) => {
+                error!(self.log, "Failed to process published block"; "error" => format!("{:?}", e));
+                response.set_success(false);
+                response.set_msg(format!("{:?}", e).into_bytes());
+            }
+        }
+        reply(&ctx, sink, response, &self.log)
+    }
+}
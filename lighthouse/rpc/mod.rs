@@ -0,0 +1,369 @@
+mod attestation;
+mod beacon_block;
+mod validator;
+
+use self::attestation::AttestationServiceInstance;
+use self::beacon_block::BeaconBlockServiceInstance;
+use self::validator::ValidatorServiceInstance;
+use db::DiskDB;
+use futures::Future;
+use grpcio::{Environment, RpcContext, RpcStatus, RpcStatusCode, Server, ServerBuilder, UnarySink};
+use http_api::Context;
+use protos::services_grpc::{
+    create_attestation_service, create_beacon_block_service, create_validator_service,
+};
+use slog::Logger;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+/// The configuration of the gRPC server used by validator clients.
+#[derive(Clone, Debug)]
+pub struct RpcConfig {
+    pub enabled: bool,
+    pub listen_address: IpAddr,
+    pub port: u16,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: Ipv4Addr::LOCALHOST.into(),
+            port: 5051,
+        }
+    }
+}
+
+/// Starts serving the block, validator and attestation services of the node of `ctx`.
+///
+/// Published attestations are verified and pooled as those published to the HTTP API, sharing
+/// the gossip state of `ctx`.
+///
+/// The server stops when the returned `Server` is dropped.
+pub fn start_server(
+    config: &RpcConfig,
+    ctx: Arc<Context<DiskDB>>,
+    log: &Logger,
+) -> Result<Server, grpcio::Error> {
+    let env = Arc::new(Environment::new(1));
+    let node = ctx.node.clone();
+
+    let beacon_block_service = create_beacon_block_service(BeaconBlockServiceInstance {
+        node: node.clone(),
+        log: log.clone(),
+    });
+    let validator_service = create_validator_service(ValidatorServiceInstance {
+        node,
+        log: log.clone(),
+    });
+    let attestation_service = create_attestation_service(AttestationServiceInstance {
+        ctx,
+        log: log.clone(),
+    });
+
+    let mut server = ServerBuilder::new(env)
+        .register_service(beacon_block_service)
+        .register_service(validator_service)
+        .register_service(attestation_service)
+        .bind(config.listen_address.to_string(), config.port)
+        .build()?;
+    server.start();
+    for &(ref host, port) in server.bind_addrs() {
+        info!(log, "gRPC server started"; "host" => host, "port" => port);
+    }
+    Ok(server)
+}
+
+/// Sends `response`, logging if the client has gone away.
+fn reply<T>(ctx: &RpcContext, sink: UnarySink<T>, response: T, log: &Logger) {
+    let log = log.clone();
+    let f = sink
+        .success(response)
+        .map_err(move |e| warn!(log, "Failed to reply to RPC"; "error" => format!("{:?}", e)));
+    ctx.spawn(f)
+}
+
+/// Fails the request with `code`, logging if the client has gone away.
+fn reply_error<T>(
+    ctx: &RpcContext,
+    sink: UnarySink<T>,
+    code: RpcStatusCode,
+    details: String,
+    log: &Logger,
+) {
+    let log = log.clone();
+    let f = sink
+        .fail(RpcStatus::new(code, Some(details)))
+        .map_err(move |e| warn!(log, "Failed to reply to RPC"; "error" => format!("{:?}", e)));
+    ctx.spawn(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use beacon_node::BeaconNode;
+    use bls::{create_proof_of_possession, Keypair, Signature};
+    use db::stores::{BeaconBlockStore, COLUMNS};
+    use db::ColumnCodecs;
+    use grpcio::{Channel, ChannelBuilder};
+    use network::rpc::ForkDigest;
+    use protos::services::{
+        Attestation as AttestationProto, BeaconBlock as BeaconBlockProto,
+        ProduceAttestationDataRequest, ProduceBeaconBlockRequest, PublicKey as PublicKeyRequest,
+        PublishAttestationRequest, PublishBeaconBlockRequest, ValidatorDutiesRequest,
+    };
+    use protos::services_grpc::{
+        AttestationServiceClient, BeaconBlockServiceClient, ValidatorServiceClient,
+    };
+    use slog::Discard;
+    use ssz::{ssz_encode, Decodable};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::RwLock;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::{env, fs, process};
+    use task_executor::TaskExecutor;
+    use types::signing::attestation_signing_root;
+    use types::{
+        Address, Attestation, AttestationData, BeaconBlock, Bitfield, ChainConfig, Hash256,
+        ValidatorRegistration,
+    };
+
+    /// A node with eight validators, its database in a temporary directory, served on an unused
+    /// port of the loopback interface.
+    struct TestServer {
+        node: Arc<RwLock<BeaconNode<DiskDB>>>,
+        /// The keypairs of the validators, by index.
+        keypairs: Vec<Keypair>,
+        channel: Channel,
+        path: PathBuf,
+        _server: Server,
+    }
+
+    impl TestServer {
+        fn start() -> Self {
+            static STARTED: AtomicUsize = AtomicUsize::new(0);
+            let path = env::temp_dir().join(format!(
+                "lighthouse_rpc_test_{}_{}",
+                process::id(),
+                STARTED.fetch_add(1, Ordering::SeqCst)
+            ));
+            let _ = fs::remove_dir_all(&path);
+            let db = DiskDB::open(&path, Some(&COLUMNS)).with_codecs(ColumnCodecs::standard());
+
+            let mut config = ChainConfig::standard();
+            config.cycle_length = 2;
+            config.shard_count = 2;
+            config.min_committee_size = 2;
+            let keypairs: Vec<Keypair> = (0..8).map(|_| Keypair::random()).collect();
+            config.initial_validators = keypairs
+                .iter()
+                .map(|keypair| ValidatorRegistration {
+                    pubkey: keypair.pk.clone(),
+                    withdrawal_shard: 0,
+                    withdrawal_address: Address::random(),
+                    randao_commitment: Hash256::random(),
+                    proof_of_possession: create_proof_of_possession(keypair),
+                })
+                .collect();
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            config.genesis_time = now.as_secs() - 100 * config.slot_duration_millis / 1000;
+            let store = Arc::new(BeaconBlockStore::new(Arc::new(db)));
+            let node = Arc::new(RwLock::new(BeaconNode::new(config, store).unwrap()));
+
+            let rpc_config = RpcConfig {
+                enabled: true,
+                listen_address: Ipv4Addr::LOCALHOST.into(),
+                port: 0,
+            };
+            let log = Logger::root(Discard, o!());
+            let (executor, _) = TaskExecutor::new(log.clone());
+            let ctx = Arc::new(Context::new(node.clone(), None, executor, log.clone()));
+            let server = start_server(&rpc_config, ctx, &log).unwrap();
+            let (_, port) = server.bind_addrs()[0];
+            let channel = ChannelBuilder::new(Arc::new(Environment::new(1)))
+                .connect(&format!("127.0.0.1:{}", port));
+            Self {
+                node,
+                keypairs,
+                channel,
+                path,
+                _server: server,
+            }
+        }
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /// Returns the status code of the reply to a request.
+    fn status<T>(reply: grpcio::Result<T>) -> RpcStatusCode {
+        match reply {
+            Ok(_) => RpcStatusCode::Ok,
+            Err(grpcio::Error::RpcFailure(status)) => status.status,
+            Err(e) => panic!("Request failed: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_validator_service() {
+        let server = TestServer::start();
+        let client = ValidatorServiceClient::new(server.channel.clone());
+        let pubkey = server.node.read().unwrap().validators()[3].pubkey.clone();
+
+        let mut req = PublicKeyRequest::new();
+        req.set_public_key(pubkey.as_bytes());
+        assert_eq!(client.validator_index(&req).unwrap().get_index(), 3);
+        req.set_public_key(Keypair::random().pk.as_bytes());
+        assert_eq!(
+            status(client.validator_index(&req)),
+            RpcStatusCode::NotFound
+        );
+        req.set_public_key(vec![1, 2, 3]);
+        assert_eq!(
+            status(client.validator_index(&req)),
+            RpcStatusCode::InvalidArgument
+        );
+
+        /*
+         * Every validator attests once per cycle.
+         */
+        let mut req = ValidatorDutiesRequest::new();
+        req.set_validator_index(3);
+        req.set_cycle(1);
+        let duties = client.validator_duties(&req).unwrap();
+        let duty = duties.get_attestation_duty();
+        assert!((2..4).contains(&duty.get_slot()));
+        let node = server.node.read().unwrap();
+        let committee = node.committee(duty.get_slot(), duty.get_shard()).unwrap();
+        assert_eq!(committee[duty.get_committee_index() as usize], 3);
+        drop(node);
+
+        req.set_validator_index(8);
+        assert_eq!(
+            status(client.validator_duties(&req)),
+            RpcStatusCode::NotFound
+        );
+        req.set_validator_index(3);
+        req.set_cycle(u64::max_value());
+        assert_eq!(
+            status(client.validator_duties(&req)),
+            RpcStatusCode::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_beacon_block_service() {
+        let server = TestServer::start();
+        let client = BeaconBlockServiceClient::new(server.channel.clone());
+
+        let mut req = ProduceBeaconBlockRequest::new();
+        req.set_slot(1);
+        req.set_randao_reveal(vec![0; 31]);
+        assert_eq!(
+            status(client.produce_beacon_block(&req)),
+            RpcStatusCode::InvalidArgument
+        );
+        req.set_randao_reveal(vec![0; 32]);
+        let produced = client.produce_beacon_block(&req).unwrap();
+        let (block, _) = BeaconBlock::ssz_decode(produced.get_block().get_ssz(), 0).unwrap();
+        assert_eq!(block.slot, 1);
+
+        /*
+         * The block is imported once, and is then already known.
+         */
+        let mut req = PublishBeaconBlockRequest::new();
+        req.set_block(produced.get_block().clone());
+        let published = client.publish_beacon_block(&req).unwrap();
+        assert!(published.get_success());
+        assert_eq!(server.node.read().unwrap().head().0, 1);
+        let published = client.publish_beacon_block(&req).unwrap();
+        assert!(published.get_success());
+        assert_eq!(published.get_msg(), b"AlreadyKnown");
+
+        let mut invalid = BeaconBlockProto::new();
+        invalid.set_ssz(vec![1, 2, 3]);
+        req.set_block(invalid);
+        assert_eq!(
+            status(client.publish_beacon_block(&req)),
+            RpcStatusCode::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_attestation_service() {
+        let server = TestServer::start();
+        let client = AttestationServiceClient::new(server.channel.clone());
+        let (slot, shard, committee, fork_digest) = {
+            let node = server.node.read().unwrap();
+            let slot = node.present_slot();
+            let committee = &node.committees(slot)[0];
+            let fork_digest = ForkDigest::new(http_api::GENESIS_FORK_VERSION, &node.genesis_root());
+            (
+                slot,
+                u64::from(committee.shard),
+                committee.committee.clone(),
+                fork_digest,
+            )
+        };
+
+        let mut req = ProduceAttestationDataRequest::new();
+        req.set_slot(slot);
+        req.set_shard(shard);
+        let produced = client.produce_attestation_data(&req).unwrap();
+        let (data, _) =
+            AttestationData::ssz_decode(produced.get_attestation_data().get_ssz(), 0).unwrap();
+        assert_eq!((data.slot, data.shard), (slot, shard));
+        req.set_shard(u64::from(u16::max_value()));
+        assert_eq!(
+            status(client.produce_attestation_data(&req)),
+            RpcStatusCode::NotFound
+        );
+
+        /*
+         * An attestation signed by its participant is pooled, while one signed by another member
+         * of the committee, or from outside the committee, is not.
+         */
+        let root = attestation_signing_root(&data, fork_digest.0);
+        let mut attestation = Attestation::zero();
+        attestation.data = data;
+        attestation.aggregate_sig.add(&Signature::new(
+            &root[..],
+            &server.keypairs[committee[0]].sk,
+        ));
+        let publish = |participant: usize, len: usize| {
+            let mut attestation = attestation.clone();
+            attestation.participation_bitfield = Bitfield::from_elem(len, false);
+            attestation.participation_bitfield.set(participant, true);
+            let mut proto = AttestationProto::new();
+            proto.set_ssz(ssz_encode(&attestation));
+            let mut req = PublishAttestationRequest::new();
+            req.set_attestation(proto);
+            client.publish_attestation(&req).unwrap()
+        };
+        let published = publish(1, committee.len());
+        assert!(!published.get_success());
+        assert_eq!(published.get_msg(), b"Invalid signature");
+        assert!(server.node.read().unwrap().pooled_attestations().is_empty());
+
+        assert!(publish(0, committee.len()).get_success());
+        assert_eq!(server.node.read().unwrap().pooled_attestations().len(), 1);
+
+        let published = publish(committee.len(), committee.len() + 1);
+        assert!(!published.get_success());
+        assert_eq!(published.get_msg(), b"Participant is not in the committee");
+
+        let mut req = PublishAttestationRequest::new();
+
+        let mut invalid = AttestationProto::new();
+        invalid.set_ssz(vec![1, 2, 3]);
+        req.set_attestation(invalid);
+        assert_eq!(
+            status(client.publish_attestation(&req)),
+            RpcStatusCode::InvalidArgument
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use super::{reply, reply_error};
+use beacon_node::{BeaconNode, BeaconNodeError};
+use bls::PublicKey;
+use db::DiskDB;
+use grpcio::{RpcContext, RpcStatusCode, UnarySink};
+use protos::services::{
+    AttestationDuty, IndexResponse, PublicKey as PublicKeyRequest, ValidatorDutiesRequest,
+    ValidatorDutiesResponse,
+};
+use protos::services_grpc::ValidatorService;
+use slog::Logger;
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone)]
+pub struct ValidatorServiceInstance {
+    pub node: Arc<RwLock<BeaconNode<DiskDB>>>,
+    pub log: Logger,
+}
+
+impl ValidatorService for ValidatorServiceInstance {
+    /// Returns the index of the validator with the requested public key.
+    fn validator_index(
+        &mut self,
+        ctx: RpcContext,
+        req: PublicKeyRequest,
+        sink: UnarySink<IndexResponse>,
+    ) {
+        let pubkey = match PublicKey::from_bytes(req.get_public_key()) {
+            Ok(pubkey) => pubkey,
+            Err(_) => {
+                let details = "Invalid public key".to_string();
+                return reply_error(
+                    &ctx,
+                    sink,
+                    RpcStatusCode::InvalidArgument,
+                    details,
+                    &self.log,
+                );
+            }
+        };
+
+        let index = self
+            .node
+            .read()
+            .expect("Beacon node lock poisoned")
+            .validator_index(&pubkey);
+        match index {
+            Some(index) => {
+                let mut response = IndexResponse::new();
+                response.set_index(index as u64);
+                reply(&ctx, sink, response, &self.log)
+            }
+            None => {
+                let details = "Unknown validator".to_string();
+                reply_error(&ctx, sink, RpcStatusCode::NotFound, details, &self.log)
+            }
+        }
+    }
+
+    /// Returns the blocks and attestation the validator must produce during the requested cycle.
+    fn validator_duties(
+        &mut self,
+        ctx: RpcContext,
+        req: ValidatorDutiesRequest,
+        sink: UnarySink<ValidatorDutiesResponse>,
+    ) {
+        let duties = self
+            .node
+            .read()
+            .expect("Beacon node lock poisoned")
+            .validator_duties(req.get_validator_index() as usize, req.get_cycle());
+        let duties = match duties {
+            Ok(duties) => duties,
+            Err(e) => {
+                let code = match e {
+                    BeaconNodeError::CycleOutOfRange => RpcStatusCode::InvalidArgument,
+                    _ => RpcStatusCode::NotFound,
+                };
+                let details = format!("{:?}", e);
+                return reply_error(&ctx, sink, code, details, &self.log);
+            }
+        };
+
+        let mut response = ValidatorDutiesResponse::new();
+        response.set_block_production_slots(duties.block_production_slots);
+        if let (Some(slot), Some(shard), Some(committee_index)) = (
+            duties.attestation_slot,
+            duties.attestation_shard,
+            duties.committee_index,
+        ) {
+            let mut attestation_duty = AttestationDuty::new();
+            attestation_duty.set_slot(slot);
+            attestation_duty.set_shard(shard);
+            attestation_duty.set_committee_index(committee_index as u64);
+            response.set_attestation_duty(attestation_duty);
+        }
+        reply(&ctx, sink, response, &self.log)
+    }
+}
@@ -0,0 +1,37 @@
+use ctrlc;
+use slog::Logger;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use task_executor::{ShutdownReason, TaskExecutor};
+
+/// How long the background services are given to stop once shutdown begins.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Asks `executor` to shut the node down on the first SIGINT or SIGTERM.
+///
+/// A second signal exits the process at once, for a shutdown which hangs.
+pub fn handle_signals(executor: TaskExecutor, log: &Logger) -> Result<(), ctrlc::Error> {
+    let signalled = AtomicBool::new(false);
+    let log = log.clone();
+    ctrlc::set_handler(move || {
+        if signalled.swap(true, Ordering::SeqCst) {
+            warn!(log, "Signalled again, exiting without shutting down");
+            process::exit(1);
+        }
+        executor.shut_down(ShutdownReason::Signal);
+    })?;
+    Ok(())
+}
+
+/// Runs `stop` on its own thread, returning `false` if it did not finish within `timeout`.
+pub fn stop_within<F: FnOnce() + Send + 'static>(stop: F, timeout: Duration) -> bool {
+    let (done_tx, done) = mpsc::channel();
+    thread::spawn(move || {
+        stop();
+        let _ = done_tx.send(());
+    });
+    done.recv_timeout(timeout).is_ok()
+}
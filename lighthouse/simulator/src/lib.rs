@@ -0,0 +1,15 @@
+extern crate beacon_node;
+extern crate db;
+extern crate network;
+#[macro_use]
+extern crate slog;
+extern crate ssz;
+extern crate types;
+
+mod node;
+mod simulator;
+mod transport;
+
+pub use node::{Error, SimNode};
+pub use simulator::{Simulator, SimulatorConfig};
+pub use transport::{Payload, Transport};
@@ -0,0 +1,684 @@
+use super::transport::Payload;
+use beacon_node::{
+    AttestationOutcome, BeaconNode, BeaconNodeBuilder, BeaconNodeError, BlockProcessingOutcome,
+    TestingSlotClock,
+};
+use db::MemoryDB;
+use network::gossip::{self, DuplicateFilter, GossipKind};
+use network::rpc::{ForkDigest, PeerId, StatusMessage};
+use network::{
+    BatchProcessResult, BeaconProcessor, BeaconProcessorConfig, Handshake, HandshakeEvent,
+    ImportQueue, NodeId, ParentLookup, ParentLookupEvent, RPCEvent, RPCRequest, RangeSync,
+    SyncEvent, Work, RPC,
+};
+use slog::Logger;
+use ssz::{ssz_encode, Decodable};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use types::{Attestation, BeaconBlock, Bitfield, ChainConfig, Hash256};
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    BeaconNodeError(BeaconNodeError),
+}
+
+impl From<BeaconNodeError> for Error {
+    fn from(e: BeaconNodeError) -> Error {
+        Error::BeaconNodeError(e)
+    }
+}
+
+/// A beacon node, wired to its peers by a `Transport`.
+///
+/// Each node keeps the chain in a `BeaconNode` of its own, storing blocks in a `MemoryDB`, and
+/// drives the same state machines as a real node: the status handshake, range sync, parent
+/// lookups, gossip deduplication, the import queue and the beacon processor, which runs with a
+/// single worker. The slot of the node is set by the simulator, rather than read from the system
+/// clock.
+pub struct SimNode {
+    pub peer_id: PeerId,
+    node: BeaconNode<MemoryDB>,
+    clock: Arc<TestingSlotClock>,
+    fork_digest: ForkDigest,
+    peers: HashSet<PeerId>,
+    rpc: RPC,
+    handshake: Handshake,
+    range_sync: RangeSync,
+    parent_lookup: ParentLookup,
+    import_queue: ImportQueue,
+    duplicates: DuplicateFilter,
+    processor: BeaconProcessor,
+    outbound: VecDeque<(PeerId, Payload)>,
+    /// Peers found to be incompatible, which should be disconnected.
+    disconnects: VecDeque<PeerId>,
+    log: Logger,
+}
+
+impl SimNode {
+    /// Starts a node from the genesis of `config`, at slot zero.
+    pub fn new(config: &ChainConfig, log: Logger) -> Result<Self, Error> {
+        let peer_id = NodeId::random();
+        let log = log.new(o!("node" => format!("{:?}", peer_id)));
+        let clock = Arc::new(
+            TestingSlotClock::new(config.genesis_time, config.slot_duration_millis)
+                .ok_or(BeaconNodeError::InvalidSlotDuration)?,
+        );
+        let node = BeaconNodeBuilder::new(config.clone())
+            .memory_store()
+            .slot_clock(clock.clone())
+            .build()?;
+        let genesis_root = node.genesis_root();
+
+        Ok(Self {
+            peer_id,
+            node,
+            clock,
+            fork_digest: ForkDigest::new(0, &genesis_root),
+            peers: HashSet::new(),
+            rpc: RPC::new(log.clone()),
+            handshake: Handshake::new(),
+            range_sync: RangeSync::new(0, genesis_root, log.clone()),
+            parent_lookup: ParentLookup::new(log.clone()),
+            import_queue: ImportQueue::new(log.clone()),
+            duplicates: DuplicateFilter::default(),
+            /*
+             * The simulated clock stands still while the network runs, so attestations are
+             * released as they arrive rather than held for a batch.
+             */
+            processor: BeaconProcessor::new(
+                &BeaconProcessorConfig {
+                    max_workers: 1,
+                    max_attestation_batch_size: 1,
+                    ..BeaconProcessorConfig::default()
+                },
+                log.clone(),
+            ),
+            outbound: VecDeque::new(),
+            disconnects: VecDeque::new(),
+            log,
+        })
+    }
+
+    /// Returns the slot and root of the canonical head.
+    pub fn head(&self) -> (u64, Hash256) {
+        self.node.head()
+    }
+
+    pub fn beacon_node(&self) -> &BeaconNode<MemoryDB> {
+        &self.node
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Moves the clock of the node to the start of `slot`.
+    pub fn set_slot(&mut self, slot: u64) {
+        self.clock.set_slot(slot);
+    }
+
+    pub fn status(&self) -> StatusMessage {
+        let (finalized_slot, finalized_root) = self.finalized();
+        let (head_slot, head_root) = self.node.head();
+        StatusMessage {
+            fork_digest: self.fork_digest,
+            finalized_root,
+            finalized_slot,
+            head_root,
+            head_slot,
+        }
+    }
+
+    pub fn on_connect(&mut self, peer_id: PeerId, now: Instant) {
+        if self.peers.insert(peer_id) {
+            let status = self.status();
+            self.handshake
+                .on_connect(&mut self.rpc, peer_id, &status, now);
+        }
+    }
+
+    pub fn on_disconnect(&mut self, peer_id: PeerId, now: Instant) {
+        if self.peers.remove(&peer_id) {
+            self.rpc.on_disconnect(peer_id);
+            self.handshake.on_disconnect(&peer_id);
+            self.range_sync.remove_peer(&mut self.rpc, &peer_id, now);
+        }
+    }
+
+    /// Builds a block for `slot` on the canonical head, including the pooled attestations.
+    ///
+    /// RANDAO is not simulated, so the proposer index is revealed in its place. This ensures that
+    /// blocks from different proposers differ.
+    pub fn produce_block(&self, slot: u64, proposer_index: usize) -> Result<BeaconBlock, Error> {
+        let randao_reveal = Hash256::from(proposer_index as u64);
+        Ok(self
+            .node
+            .produce_block(slot, randao_reveal, Hash256::zero())?)
+    }
+
+    /// Imports a block produced locally and gossips it to all peers.
+    pub fn publish(
+        &mut self,
+        block: &BeaconBlock,
+        now: Instant,
+    ) -> Result<BlockProcessingOutcome, Error> {
+        let outcome = self.import_block(block)?;
+        if outcome == BlockProcessingOutcome::Imported {
+            self.import_queue.on_imported(block.canonical_root(), now);
+            self.publish_gossip(GossipKind::BeaconBlock, &ssz_encode(block), now);
+        }
+        Ok(outcome)
+    }
+
+    /// Has each of `validators` which is in a committee at `slot` attest to the canonical head.
+    /// The votes of each committee are aggregated into one attestation, which is pooled and
+    /// gossiped to all peers.
+    ///
+    /// The validators have no keys, so attestations are not signed.
+    pub fn attest(&mut self, slot: u64, validators: &[usize], now: Instant) -> Result<(), Error> {
+        let committees: Vec<(u64, Vec<usize>)> = self
+            .node
+            .committees(slot)
+            .iter()
+            .map(|c| (u64::from(c.shard), c.committee.clone()))
+            .collect();
+        for (shard, committee) in committees {
+            let mut participation_bitfield = Bitfield::from_elem(committee.len(), false);
+            for (i, index) in committee.iter().enumerate() {
+                if validators.contains(index) {
+                    participation_bitfield.set(i, true);
+                }
+            }
+            if participation_bitfield.highest_set_bit().is_none() {
+                continue;
+            }
+            let mut attestation = Attestation::zero();
+            attestation.data = self.node.produce_attestation_data(slot, shard)?;
+            attestation.participation_bitfield = participation_bitfield;
+            let outcome = self.node.process_attestation(attestation.clone(), slot)?;
+            if outcome == AttestationOutcome::Pooled {
+                self.publish_gossip(
+                    GossipKind::Attestation(shard),
+                    &ssz_encode(&attestation),
+                    now,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Imports `block` at the present slot of the node.
+    pub fn import_block(&mut self, block: &BeaconBlock) -> Result<BlockProcessingOutcome, Error> {
+        let present_slot = self.node.present_slot();
+        Ok(self.node.process_block(block, present_slot)?)
+    }
+
+    /// Processes a message received from `peer_id`.
+    pub fn on_message(&mut self, peer_id: PeerId, payload: Payload, now: Instant) {
+        match payload {
+            Payload::Rpc(message) => self.rpc.on_message(peer_id, message, now),
+            Payload::Gossip(kind, bytes) => self.on_gossip(peer_id, kind, &bytes, now),
+        }
+    }
+
+    /// Processes all pending events, returning `true` if there were any.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        let mut progress = false;
+        while let Some(event) = self.rpc.poll(now) {
+            self.on_rpc_event(event, now);
+            progress = true;
+        }
+        while let Some(event) = self.handshake.poll() {
+            self.on_handshake_event(event, now);
+            progress = true;
+        }
+        while let Some(event) = self.range_sync.poll() {
+            self.on_sync_event(event);
+            progress = true;
+        }
+        while let Some(event) = self.parent_lookup.poll() {
+            self.on_parent_lookup_event(event);
+            progress = true;
+        }
+        self.import_queue.prune(now);
+        while let Some((peer_id, block)) = self.import_queue.next_ready() {
+            self.queue(Work::GossipBlock { peer_id, block });
+            progress = true;
+        }
+        while let Some(work) = self.processor.next_work(now) {
+            self.process(work, now);
+            self.processor.on_work_complete();
+            progress = true;
+        }
+        while let Some((peer_id, message)) = self.rpc.next_outbound() {
+            self.outbound.push_back((peer_id, Payload::Rpc(message)));
+            progress = true;
+        }
+        progress
+    }
+
+    /// Returns the next message to be sent, if any.
+    pub fn next_outbound(&mut self) -> Option<(PeerId, Payload)> {
+        self.outbound.pop_front()
+    }
+
+    /// Returns the next peer which should be disconnected, if any.
+    pub fn next_disconnect(&mut self) -> Option<PeerId> {
+        self.disconnects.pop_front()
+    }
+
+    fn on_gossip(&mut self, peer_id: PeerId, kind: GossipKind, bytes: &[u8], now: Instant) {
+        if !self.duplicates.observe_message(bytes, now) {
+            return;
+        }
+        let ssz = match gossip::decode(bytes) {
+            Ok(ssz) => ssz,
+            Err(_) => {
+                debug!(self.log, "Invalid gossip"; "peer_id" => format!("{:?}", peer_id));
+                return;
+            }
+        };
+        match kind {
+            GossipKind::BeaconBlock => match BeaconBlock::ssz_decode(&ssz, 0) {
+                Ok((block, _)) => {
+                    if self.import_queue.observe(&block, now) {
+                        self.queue(Work::GossipBlock { peer_id, block });
+                    }
+                }
+                Err(_) => {
+                    debug!(self.log, "Invalid gossip block"; "peer_id" => format!("{:?}", peer_id))
+                }
+            },
+            GossipKind::Attestation(_) => match Attestation::ssz_decode(&ssz, 0) {
+                Ok((attestation, _)) => self.queue(Work::GossipAttestation {
+                    peer_id,
+                    attestation,
+                }),
+                Err(_) => {
+                    debug!(self.log, "Invalid gossip attestation"; "peer_id" => format!("{:?}", peer_id))
+                }
+            },
+            /*
+             * No other topics are simulated.
+             */
+            _ => {}
+        }
+    }
+
+    /// Hands `work` to the beacon processor, closing the stream of any request it drops.
+    fn queue(&mut self, work: Work) {
+        match self.processor.push(work) {
+            Some(Work::BlocksByRangeRequest { peer_id, id, .. })
+            | Some(Work::BlocksByRootRequest { peer_id, id, .. }) => {
+                self.rpc.end_response(peer_id, id)
+            }
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, work: Work, now: Instant) {
+        match work {
+            Work::GossipBlock { peer_id, block } => self.process_gossip_block(peer_id, block, now),
+            Work::ParentChain { blocks } => {
+                self.import_chain(&blocks, now);
+            }
+            Work::ChainSegment {
+                batch_id,
+                mut blocks,
+            } => {
+                /*
+                 * Blocks imported from gossip meanwhile are skipped, but the rest of the batch
+                 * must still connect to them.
+                 */
+                self.import_queue.retain_unimported(&mut blocks, now);
+                let result = if self.import_chain(&blocks, now) {
+                    BatchProcessResult::Success
+                } else {
+                    BatchProcessResult::Failed
+                };
+                self.range_sync
+                    .on_batch_processed(&mut self.rpc, batch_id, result, now);
+            }
+            Work::BlocksByRangeRequest {
+                peer_id,
+                id,
+                request,
+            } => {
+                let (_, head_root) = self.node.head();
+                self.rpc.respond_blocks_by_range(
+                    peer_id,
+                    id,
+                    &request,
+                    self.node.store(),
+                    &head_root,
+                )
+            }
+            Work::BlocksByRootRequest {
+                peer_id,
+                id,
+                request,
+            } => self
+                .rpc
+                .respond_blocks_by_root(peer_id, id, &request, self.node.store()),
+            Work::GossipAttestation {
+                peer_id,
+                attestation,
+            } => self.process_gossip_attestation(peer_id, attestation),
+            Work::GossipAttestationBatch { attestations } => {
+                for (peer_id, attestation) in attestations {
+                    self.process_gossip_attestation(peer_id, attestation);
+                }
+            }
+            /*
+             * Validators attest directly, without aggregators, so no aggregates are gossiped.
+             */
+            Work::GossipAggregate { .. } => {}
+        }
+    }
+
+    fn process_gossip_block(&mut self, peer_id: PeerId, block: BeaconBlock, now: Instant) {
+        match self.import_block(&block) {
+            Ok(BlockProcessingOutcome::Imported) => {
+                self.import_queue.on_imported(block.canonical_root(), now);
+                self.gossip(GossipKind::BeaconBlock, &ssz_encode(&block), Some(peer_id));
+            }
+            /*
+             * The block is held until its parent is imported, and the parent is looked up unless
+             * another block is already awaiting it.
+             */
+            Ok(BlockProcessingOutcome::UnknownParent) => {
+                if self
+                    .import_queue
+                    .on_unknown_parent(peer_id, block.clone(), now)
+                {
+                    self.parent_lookup
+                        .on_unknown_parent(&mut self.rpc, peer_id, block, now)
+                }
+            }
+            Ok(BlockProcessingOutcome::AlreadyKnown) => {}
+            Ok(outcome) => {
+                debug!(self.log, "Gossip block not imported"; "outcome" => format!("{:?}", outcome))
+            }
+            Err(e) => {
+                warn!(self.log, "Unable to import gossip block"; "error" => format!("{:?}", e))
+            }
+        }
+    }
+
+    fn process_gossip_attestation(&mut self, peer_id: PeerId, attestation: Attestation) {
+        let present_slot = self.node.present_slot();
+        let kind = GossipKind::Attestation(attestation.data.shard);
+        let ssz = ssz_encode(&attestation);
+        match self.node.process_attestation(attestation, present_slot) {
+            Ok(AttestationOutcome::Pooled) => self.gossip(kind, &ssz, Some(peer_id)),
+            Ok(AttestationOutcome::AlreadyKnown) => {}
+            Ok(outcome) => {
+                debug!(self.log, "Gossip attestation not pooled"; "outcome" => format!("{:?}", outcome))
+            }
+            Err(e) => {
+                warn!(self.log, "Unable to process gossip attestation"; "error" => format!("{:?}", e))
+            }
+        }
+    }
+
+    /// Gossips a message of our own to all peers, marking it as seen so that it is not processed
+    /// again when echoed back.
+    fn publish_gossip(&mut self, kind: GossipKind, ssz: &[u8], now: Instant) {
+        match gossip::encode(ssz) {
+            Ok(bytes) => {
+                self.duplicates.observe_message(&bytes, now);
+                self.forward(kind, &bytes, None);
+            }
+            Err(e) => warn!(self.log, "Unable to encode gossip"; "error" => format!("{:?}", e)),
+        }
+    }
+
+    /// Encodes and forwards a gossip message to all peers except the one it came from.
+    fn gossip(&mut self, kind: GossipKind, ssz: &[u8], source: Option<PeerId>) {
+        match gossip::encode(ssz) {
+            Ok(bytes) => self.forward(kind, &bytes, source),
+            Err(e) => warn!(self.log, "Unable to encode gossip"; "error" => format!("{:?}", e)),
+        }
+    }
+
+    /// Sends an encoded gossip message to all peers except `source`.
+    fn forward(&mut self, kind: GossipKind, bytes: &[u8], source: Option<PeerId>) {
+        for peer_id in &self.peers {
+            if Some(*peer_id) != source {
+                self.outbound
+                    .push_back((*peer_id, Payload::Gossip(kind, bytes.to_vec())));
+            }
+        }
+    }
+
+    fn on_rpc_event(&mut self, event: RPCEvent, now: Instant) {
+        let status = self.status();
+        let present_slot = self.node.present_slot();
+        let event = self.handshake.on_rpc_event(
+            &mut self.rpc,
+            event,
+            &status,
+            present_slot,
+            self.node.store(),
+        );
+        let event = event.and_then(|event| self.range_sync.on_rpc_event(&mut self.rpc, event, now));
+        let event = event.and_then(|event| {
+            self.parent_lookup
+                .on_rpc_event(&mut self.rpc, event, self.node.store(), now)
+        });
+
+        match event {
+            Some(RPCEvent::Request {
+                peer_id,
+                id,
+                request: RPCRequest::BlocksByRange(request),
+            }) => self.queue(Work::BlocksByRangeRequest {
+                peer_id,
+                id,
+                request,
+            }),
+            Some(RPCEvent::Request {
+                peer_id,
+                id,
+                request: RPCRequest::BlocksByRoot(request),
+            }) => self.queue(Work::BlocksByRootRequest {
+                peer_id,
+                id,
+                request,
+            }),
+            /*
+             * The remaining protocols are not simulated, but the stream must still be closed.
+             */
+            Some(RPCEvent::Request { peer_id, id, .. }) => self.rpc.end_response(peer_id, id),
+            _ => {}
+        }
+    }
+
+    fn on_handshake_event(&mut self, event: HandshakeEvent, now: Instant) {
+        match event {
+            HandshakeEvent::Compatible { peer_id, status } => {
+                /*
+                 * Range sync starts from the finalized block rather than our head, so that a node
+                 * which has built on a fork can still reach the chain of its peers. Blocks it
+                 * already has are skipped on import.
+                 */
+                if self.range_sync.is_synced() && status.head_slot > self.node.head().0 {
+                    let (finalized_slot, finalized_root) = self.finalized();
+                    self.range_sync =
+                        RangeSync::new(finalized_slot, finalized_root, self.log.clone());
+                }
+                self.range_sync
+                    .add_peer(&mut self.rpc, peer_id, &status, now);
+            }
+            HandshakeEvent::Disconnect { peer_id, reason } => {
+                debug!(self.log, "Incompatible peer"; "peer_id" => format!("{:?}", peer_id), "reason" => format!("{:?}", reason));
+                self.disconnects.push_back(peer_id);
+            }
+        }
+    }
+
+    fn on_sync_event(&mut self, event: SyncEvent) {
+        match event {
+            SyncEvent::ProcessBatch { batch_id, blocks } => {
+                self.queue(Work::ChainSegment { batch_id, blocks })
+            }
+            SyncEvent::ReportPeer { peer_id, action } => {
+                debug!(self.log, "Peer reported by sync"; "peer_id" => format!("{:?}", peer_id), "action" => format!("{:?}", action));
+            }
+            SyncEvent::Completed => info!(self.log, "Synced"; "slot" => self.node.head().0),
+            SyncEvent::Failed => warn!(self.log, "Range sync failed"),
+        }
+    }
+
+    fn on_parent_lookup_event(&mut self, event: ParentLookupEvent) {
+        match event {
+            ParentLookupEvent::ProcessChain { blocks } => self.queue(Work::ParentChain { blocks }),
+            ParentLookupEvent::ReportPeer { peer_id, action } => {
+                debug!(self.log, "Peer reported by parent lookup"; "peer_id" => format!("{:?}", peer_id), "action" => format!("{:?}", action));
+            }
+            ParentLookupEvent::Failed { root } => {
+                debug!(self.log, "Parent lookup failed"; "root" => format!("{:?}", root));
+            }
+        }
+    }
+
+    /// Imports `blocks` in order, returning `false` if any could not be imported.
+    fn import_chain(&mut self, blocks: &[BeaconBlock], now: Instant) -> bool {
+        for block in blocks {
+            match self.import_block(block) {
+                Ok(BlockProcessingOutcome::Imported) => {
+                    self.import_queue.on_imported(block.canonical_root(), now)
+                }
+                Ok(BlockProcessingOutcome::AlreadyKnown) => {}
+                Ok(_) => return false,
+                Err(e) => {
+                    warn!(self.log, "Unable to import block"; "error" => format!("{:?}", e));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the slot and root of the finalized block.
+    fn finalized(&self) -> (u64, Hash256) {
+        let root = self.node.finalized_root();
+        let block = self
+            .node
+            .block(&root)
+            .expect("The finalized block is stored");
+        (block.slot, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::simulator::SimulatorConfig;
+    use super::*;
+    use slog::Discard;
+
+    fn node() -> SimNode {
+        let config = SimulatorConfig::default().chain_config();
+        SimNode::new(&config, Logger::root(Discard, o!())).unwrap()
+    }
+
+    /// Connects two peers to `node`, discarding the handshake.
+    fn connect_peers(node: &mut SimNode, now: Instant) -> (PeerId, PeerId) {
+        let (a, b) = (NodeId::random(), NodeId::random());
+        node.on_connect(a, now);
+        node.on_connect(b, now);
+        while node.next_outbound().is_some() {}
+        node.poll(now);
+        while node.next_outbound().is_some() {}
+        (a, b)
+    }
+
+    /// Returns the peers to which gossip was sent, echoing each message back from `echo`.
+    fn gossip_recipients(node: &mut SimNode, echo: PeerId, now: Instant) -> Vec<PeerId> {
+        let mut recipients = vec![];
+        while let Some((peer_id, payload)) = node.next_outbound() {
+            if let Payload::Gossip(..) = payload {
+                recipients.push(peer_id);
+                node.on_message(echo, payload, now);
+                node.poll(now);
+            }
+        }
+        recipients.sort();
+        recipients
+    }
+
+    #[test]
+    fn test_import_and_fork_choice() {
+        let mut node = node();
+        node.set_slot(3);
+        let (_, genesis_root) = node.head();
+
+        let a = node.produce_block(1, 0).unwrap();
+        assert_eq!(node.import_block(&a), Ok(BlockProcessingOutcome::Imported));
+        assert_eq!(
+            node.import_block(&a),
+            Ok(BlockProcessingOutcome::AlreadyKnown)
+        );
+        assert_eq!(node.head(), (1, a.canonical_root()));
+
+        /*
+         * Without votes, a later block on a fork becomes the head.
+         */
+        let mut b = BeaconBlock::zero();
+        b.slot = 2;
+        b.ancestor_hashes.push(genesis_root);
+        assert_eq!(node.import_block(&b), Ok(BlockProcessingOutcome::Imported));
+        assert_eq!(node.head(), (2, b.canonical_root()));
+
+        let mut orphan = BeaconBlock::zero();
+        orphan.slot = 3;
+        orphan.ancestor_hashes.push(Hash256::from(7));
+        assert_eq!(
+            node.import_block(&orphan),
+            Ok(BlockProcessingOutcome::UnknownParent)
+        );
+        assert_eq!(node.head(), (2, b.canonical_root()));
+    }
+
+    #[test]
+    fn test_publish_gossips_once() {
+        let mut node = node();
+        let now = Instant::now();
+        let (a, b) = connect_peers(&mut node, now);
+        node.set_slot(1);
+
+        /*
+         * The node's own message is not imported or forwarded again when echoed back.
+         */
+        let block = node.produce_block(1, 0).unwrap();
+        assert_eq!(
+            node.publish(&block, now),
+            Ok(BlockProcessingOutcome::Imported)
+        );
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(gossip_recipients(&mut node, a, now), expected);
+        assert!(node.next_outbound().is_none());
+    }
+
+    #[test]
+    fn test_attest() {
+        let mut node = node();
+        let now = Instant::now();
+        let (a, b) = connect_peers(&mut node, now);
+        node.set_slot(1);
+
+        /*
+         * The votes of the validators of the node in the committee are pooled as one attestation,
+         * which is gossiped once.
+         */
+        let committee = node.beacon_node().committees(1)[0].committee.clone();
+        node.attest(1, &committee, now).unwrap();
+        assert_eq!(node.beacon_node().pooled_attestations().len(), 1);
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(gossip_recipients(&mut node, a, now), expected);
+        assert_eq!(node.beacon_node().pooled_attestations().len(), 1);
+    }
+}
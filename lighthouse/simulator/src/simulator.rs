@@ -0,0 +1,356 @@
+use super::node::{Error, SimNode};
+use super::transport::Transport;
+use network::rpc::PeerId;
+use slog::Logger;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use types::{ChainConfig, ValidatorRegistration};
+
+pub struct SimulatorConfig {
+    pub node_count: usize,
+    /// Validators are attached to nodes in turn, so some nodes may have none.
+    pub validator_count: usize,
+    pub slot_duration: Duration,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            node_count: 4,
+            validator_count: 8,
+            slot_duration: Duration::from_secs(6),
+        }
+    }
+}
+
+impl SimulatorConfig {
+    /// A chain with two slots per cycle and one committee per slot, so that a few validators
+    /// fill every committee and finality is reached within a few cycles.
+    pub fn chain_config(&self) -> ChainConfig {
+        let mut config = ChainConfig::standard();
+        config.cycle_length = 2;
+        config.shard_count = 2;
+        config.min_committee_size = 2;
+        config.min_attestation_inclusion_delay = 1;
+        config.initial_validators = (0..self.validator_count)
+            .map(|_| ValidatorRegistration::random())
+            .collect();
+        config
+    }
+}
+
+/// Runs a network of `SimNode`s in a single thread, with a simulated clock.
+///
+/// Each slot, the proposer of the slot produces a block on the head of its node, which gossips
+/// it to the rest of the network. Once it has been delivered, each validator in a committee of
+/// the slot attests to the head of its node. All messages are delivered until the network is
+/// idle after each step, so the outcome of a simulation is independent of timing.
+pub struct Simulator {
+    nodes: Vec<SimNode>,
+    /// The index of each node, by peer id.
+    indices: HashMap<PeerId, usize>,
+    /// The index of the node each validator is attached to.
+    validators: Vec<usize>,
+    transport: Transport,
+    genesis_time: Instant,
+    slot_duration: Duration,
+    slot: u64,
+    log: Logger,
+}
+
+impl Simulator {
+    /// Starts all nodes from a common genesis. No nodes are connected.
+    pub fn new(config: &SimulatorConfig, log: Logger) -> Result<Self, Error> {
+        let chain_config = config.chain_config();
+        let mut nodes = vec![];
+        for _ in 0..config.node_count {
+            nodes.push(SimNode::new(&chain_config, log.clone())?);
+        }
+        let indices = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.peer_id, i))
+            .collect();
+        let validators = (0..config.validator_count)
+            .map(|v| v % config.node_count.max(1))
+            .collect();
+
+        Ok(Self {
+            nodes,
+            indices,
+            validators,
+            transport: Transport::new(),
+            genesis_time: Instant::now(),
+            slot_duration: config.slot_duration,
+            slot: 0,
+            log,
+        })
+    }
+
+    pub fn node(&self, i: usize) -> &SimNode {
+        &self.nodes[i]
+    }
+
+    pub fn nodes(&self) -> &[SimNode] {
+        &self.nodes
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    /// The simulated time at the start of the current slot.
+    pub fn now(&self) -> Instant {
+        self.genesis_time + self.slot_duration * self.slot as u32
+    }
+
+    /// Connects nodes `a` and `b`, running the network until the handshake and any resulting
+    /// sync are complete.
+    pub fn connect(&mut self, a: usize, b: usize) {
+        let (peer_a, peer_b) = (self.nodes[a].peer_id, self.nodes[b].peer_id);
+        if a != b && self.transport.connect(peer_a, peer_b) {
+            let now = self.now();
+            self.nodes[a].on_connect(peer_b, now);
+            self.nodes[b].on_connect(peer_a, now);
+            self.run_until_idle();
+        }
+    }
+
+    /// Connects every node to every other node.
+    pub fn connect_all(&mut self) {
+        for a in 0..self.nodes.len() {
+            for b in a + 1..self.nodes.len() {
+                self.connect(a, b);
+            }
+        }
+    }
+
+    pub fn disconnect(&mut self, a: usize, b: usize) {
+        let (peer_a, peer_b) = (self.nodes[a].peer_id, self.nodes[b].peer_id);
+        if self.transport.disconnect(peer_a, peer_b) {
+            let now = self.now();
+            self.nodes[a].on_disconnect(peer_b, now);
+            self.nodes[b].on_disconnect(peer_a, now);
+        }
+    }
+
+    /// Disconnects node `i` from all other nodes.
+    pub fn isolate(&mut self, i: usize) {
+        for j in 0..self.nodes.len() {
+            self.disconnect(i, j);
+        }
+    }
+
+    /// Drops the next `count` gossip messages addressed to node `i`.
+    pub fn drop_gossip_to(&mut self, i: usize, count: usize) {
+        let peer_id = self.nodes[i].peer_id;
+        self.transport.drop_gossip_to(peer_id, count);
+    }
+
+    /// Returns the index of the validator which proposes at `slot`. All nodes share a genesis, so
+    /// agree on the committees.
+    pub fn proposer(&self, slot: u64) -> Option<usize> {
+        self.nodes.first()?.beacon_node().block_proposer(slot)
+    }
+
+    /// Returns the index of the node to which the proposer of `slot` is attached.
+    pub fn proposer_node(&self, slot: u64) -> Option<usize> {
+        self.validators.get(self.proposer(slot)?).cloned()
+    }
+
+    /// Advances to the next slot, producing a block and having the committees of the slot attest,
+    /// running the network until it is idle after each.
+    pub fn run_slot(&mut self) -> Result<(), Error> {
+        self.slot += 1;
+        let now = self.now();
+        for node in &mut self.nodes {
+            node.set_slot(self.slot);
+        }
+        if let Some(proposer_index) = self.proposer(self.slot) {
+            let node = &mut self.nodes[self.validators[proposer_index]];
+            let block = node.produce_block(self.slot, proposer_index)?;
+            node.publish(&block, now)?;
+        }
+        self.run_until_idle();
+
+        for i in 0..self.nodes.len() {
+            let validators: Vec<usize> = (0..self.validators.len())
+                .filter(|v| self.validators[*v] == i)
+                .collect();
+            self.nodes[i].attest(self.slot, &validators, now)?;
+        }
+        self.run_until_idle();
+        Ok(())
+    }
+
+    pub fn run_slots(&mut self, count: u64) -> Result<(), Error> {
+        for _ in 0..count {
+            self.run_slot()?;
+        }
+        Ok(())
+    }
+
+    /// Delivers messages between nodes until none have anything left to do.
+    fn run_until_idle(&mut self) {
+        let now = self.now();
+        loop {
+            let mut progress = false;
+            let mut disconnects = vec![];
+            for (i, node) in self.nodes.iter_mut().enumerate() {
+                progress |= node.poll(now);
+                while let Some((dst, payload)) = node.next_outbound() {
+                    self.transport.send(node.peer_id, dst, payload);
+                    progress = true;
+                }
+                while let Some(peer_id) = node.next_disconnect() {
+                    if let Some(j) = self.indices.get(&peer_id) {
+                        disconnects.push((i, *j));
+                    }
+                }
+            }
+            while let Some((src, dst, payload)) = self.transport.next() {
+                if let Some(i) = self.indices.get(&dst) {
+                    self.nodes[*i].on_message(src, payload, now);
+                    progress = true;
+                }
+            }
+            for (a, b) in disconnects {
+                debug!(self.log, "Disconnecting incompatible nodes"; "a" => a, "b" => b);
+                self.disconnect(a, b);
+                progress = true;
+            }
+            if !progress {
+                return;
+            }
+        }
+    }
+
+    /// Returns `true` if all nodes have the same head and finalized checkpoint.
+    pub fn is_converged(&self) -> bool {
+        self.nodes.windows(2).all(|pair| {
+            let (a, b) = (pair[0].beacon_node(), pair[1].beacon_node());
+            a.head() == b.head()
+                && a.finalized_cycle() == b.finalized_cycle()
+                && a.finalized_root() == b.finalized_root()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::Discard;
+
+    fn simulator(node_count: usize, validator_count: usize) -> Simulator {
+        let config = SimulatorConfig {
+            node_count,
+            validator_count,
+            ..SimulatorConfig::default()
+        };
+        Simulator::new(&config, Logger::root(Discard, o!())).unwrap()
+    }
+
+    #[test]
+    fn test_connected_nodes_converge() {
+        let mut sim = simulator(4, 8);
+        sim.connect_all();
+        let mut finalized_cycles = vec![];
+        for _ in 0..2 {
+            for _ in 0..20 {
+                sim.run_slot().unwrap();
+                assert!(sim.is_converged());
+                assert_eq!(sim.node(0).head().0, sim.slot());
+            }
+            finalized_cycles.push(sim.node(0).beacon_node().finalized_cycle());
+        }
+
+        /*
+         * Every validator attests, so the chain keeps finalizing.
+         */
+        assert!(finalized_cycles[0] > 0);
+        assert!(finalized_cycles[1] > finalized_cycles[0]);
+    }
+
+    #[test]
+    fn test_late_joiner_range_syncs() {
+        /*
+         * The fourth node has no validators, so builds nothing while it is offline.
+         */
+        let mut sim = simulator(4, 3);
+        sim.connect(0, 1);
+        sim.connect(0, 2);
+        sim.connect(1, 2);
+        sim.run_slots(150).unwrap();
+        assert_eq!(sim.node(3).head().0, 0);
+        assert!(sim.node(0).beacon_node().finalized_cycle() > 0);
+        assert!(!sim.is_converged());
+
+        /*
+         * The finalized checkpoint is reached again as the synced blocks are imported.
+         */
+        sim.connect(3, 0);
+        assert!(sim.is_converged());
+        assert_eq!(sim.node(3).head().0, 150);
+
+        sim.run_slots(3).unwrap();
+        assert!(sim.is_converged());
+    }
+
+    #[test]
+    fn test_missed_gossip_recovered_by_parent_lookup() {
+        let mut sim = simulator(3, 3);
+        sim.connect_all();
+        sim.run_slots(5).unwrap();
+
+        /*
+         * At most two validators propose the next two blocks, so some node proposes neither. It
+         * misses the first, which is sent to it by both of the others.
+         */
+        let i = (0..3)
+            .find(|i| (6..8).all(|slot| sim.proposer_node(slot) != Some(*i)))
+            .unwrap();
+        sim.drop_gossip_to(i, 2);
+        sim.run_slot().unwrap();
+        assert_eq!(sim.node(i).head().0, 5);
+        assert!(!sim.is_converged());
+
+        sim.run_slot().unwrap();
+        assert!(sim.is_converged());
+        assert_eq!(sim.node(i).head().0, 7);
+    }
+
+    #[test]
+    fn test_partitioned_fork_resolves() {
+        let mut sim = simulator(4, 4);
+        sim.connect_all();
+        sim.run_slots(4).unwrap();
+
+        /*
+         * Each slot has one committee of two validators, one of which proposes at every odd slot
+         * and the other at every even slot. The node of the odd proposer is isolated, so builds
+         * its own chain while the others finalize without it.
+         */
+        let isolated = sim.proposer_node(11).unwrap();
+        let connected = (isolated + 1) % 4;
+        sim.isolate(isolated);
+        sim.run_slots(8).unwrap();
+        assert_eq!(sim.node(isolated).head().0, 11);
+        assert_eq!(sim.node(connected).head().0, 12);
+        assert!(
+            sim.node(connected).beacon_node().finalized_cycle()
+                > sim.node(isolated).beacon_node().finalized_cycle()
+        );
+
+        /*
+         * The isolated node syncs the chain of the others, which has the votes of more
+         * validators. Finality is accounted from the blocks of both chains, so it only agrees
+         * once the cycles after the partition are accounted.
+         */
+        sim.connect_all();
+        assert_eq!(sim.node(isolated).head(), sim.node(connected).head());
+
+        sim.run_slots(4).unwrap();
+        assert!(sim.is_converged());
+        assert_eq!(sim.node(isolated).head().0, 16);
+    }
+}
@@ -0,0 +1,132 @@
+use network::gossip::GossipKind;
+use network::rpc::{PeerId, StreamMessage};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A message carried between two nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Payload {
+    /// A message on a request stream.
+    Rpc(StreamMessage),
+    /// A gossip message on the topic of its kind, as encoded on the wire.
+    Gossip(GossipKind, Vec<u8>),
+}
+
+/// Carries messages between nodes in memory, in the order they were sent.
+///
+/// Messages are only delivered between connected nodes; any sent over a link which does not
+/// exist (or no longer exists by the time it would be delivered) are dropped, as they would be by
+/// a real transport.
+#[derive(Default)]
+pub struct Transport {
+    links: HashSet<(PeerId, PeerId)>,
+    queue: VecDeque<(PeerId, PeerId, Payload)>,
+    /// The number of further gossip messages to drop before they reach each node.
+    gossip_losses: HashMap<PeerId, usize>,
+}
+
+impl Transport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Links `a` and `b`. Returns `false` if they were already connected.
+    pub fn connect(&mut self, a: PeerId, b: PeerId) -> bool {
+        self.links.insert(link(a, b))
+    }
+
+    /// Unlinks `a` and `b`. Returns `false` if they were not connected.
+    pub fn disconnect(&mut self, a: PeerId, b: PeerId) -> bool {
+        self.links.remove(&link(a, b))
+    }
+
+    pub fn is_connected(&self, a: PeerId, b: PeerId) -> bool {
+        self.links.contains(&link(a, b))
+    }
+
+    /// Drops the next `count` gossip messages addressed to `dst`, simulating missed gossip.
+    pub fn drop_gossip_to(&mut self, dst: PeerId, count: usize) {
+        *self.gossip_losses.entry(dst).or_insert(0) += count;
+    }
+
+    pub fn send(&mut self, src: PeerId, dst: PeerId, payload: Payload) {
+        if self.is_connected(src, dst) {
+            self.queue.push_back((src, dst, payload));
+        }
+    }
+
+    /// Returns the next message to be delivered as `(src, dst, payload)`, if any.
+    pub fn next(&mut self) -> Option<(PeerId, PeerId, Payload)> {
+        while let Some((src, dst, payload)) = self.queue.pop_front() {
+            if !self.is_connected(src, dst) {
+                continue;
+            }
+            if let Payload::Gossip(..) = payload {
+                if let Some(losses) = self.gossip_losses.get_mut(&dst) {
+                    if *losses > 0 {
+                        *losses -= 1;
+                        continue;
+                    }
+                }
+            }
+            return Some((src, dst, payload));
+        }
+        None
+    }
+}
+
+/// Links are undirected, so are stored with the lesser id first.
+fn link(a: PeerId, b: PeerId) -> (PeerId, PeerId) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network::NodeId;
+
+    fn gossip(byte: u8) -> Payload {
+        Payload::Gossip(GossipKind::BeaconBlock, vec![byte])
+    }
+
+    #[test]
+    fn test_delivery() {
+        let (a, b, c) = (NodeId::random(), NodeId::random(), NodeId::random());
+        let mut transport = Transport::new();
+        assert!(transport.connect(a, b));
+        assert!(!transport.connect(b, a));
+        assert!(transport.is_connected(b, a));
+
+        transport.send(a, b, gossip(1));
+        transport.send(b, a, gossip(2));
+        transport.send(a, c, gossip(3));
+        assert_eq!(transport.next(), Some((a, b, gossip(1))));
+        assert_eq!(transport.next(), Some((b, a, gossip(2))));
+        assert_eq!(transport.next(), None);
+
+        /*
+         * Messages in flight are lost when the link goes down.
+         */
+        transport.send(a, b, gossip(4));
+        assert!(transport.disconnect(a, b));
+        assert_eq!(transport.next(), None);
+    }
+
+    #[test]
+    fn test_gossip_loss() {
+        let (a, b) = (NodeId::random(), NodeId::random());
+        let mut transport = Transport::new();
+        transport.connect(a, b);
+        transport.drop_gossip_to(b, 1);
+
+        let close = Payload::Rpc(StreamMessage::Close { id: 0 });
+        transport.send(a, b, close.clone());
+        transport.send(a, b, gossip(1));
+        transport.send(a, b, gossip(2));
+        assert_eq!(transport.next(), Some((a, b, close)));
+        assert_eq!(transport.next(), Some((a, b, gossip(2))));
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use config::Flags;
+use db::stores::COLUMNS;
+use db::DiskDB;
+use serde_json::{self, Value};
+use slog::Logger;
+use validator_client::{
+    interop_keypairs, parse_graffiti, parse_interop_range, InitializedValidators,
+    SlashingProtection, ValidatorClient, ValidatorClientConfig,
+};
+
+/// The directory within the validators dir holding the slashing protection database.
+pub const SLASHING_PROTECTION_DIR: &str = "slashing_protection";
+
+/// Runs the validator client until the process is killed or a doppelgänger is detected, or
+/// imports or exports its slashing protection.
+pub fn run(flags: &Flags, validators_dir: &Path, log: &Logger) {
+    let db = DiskDB::open(
+        &validators_dir.join(SLASHING_PROTECTION_DIR),
+        Some(&COLUMNS),
+    );
+    let slashing_protection = Arc::new(SlashingProtection::new(Arc::new(db)));
+    let matches = flags.matches();
+    if let Some(matches) = matches.subcommand_matches("import_slashing_protection") {
+        let path = Path::new(matches.value_of("FILE").expect("FILE is required"));
+        import_interchange(&slashing_protection, path, log);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("export_slashing_protection") {
+        let path = Path::new(matches.value_of("FILE").expect("FILE is required"));
+        export_interchange(&slashing_protection, path, log);
+        return;
+    }
+
+    let doppelganger_cycles = match flags.parse::<u64>("doppelganger-cycles") {
+        Ok(Some(cycles)) => cycles,
+        Ok(None) => ValidatorClientConfig::default().doppelganger_cycles,
+        Err(e) => {
+            error!(log, "Invalid doppelganger cycles"; "error" => e);
+            return;
+        }
+    };
+    let graffiti = flags.value_of("graffiti").map(|value| {
+        parse_graffiti(value).map_err(|e| format!("{} ({:?})", flags.invalid("graffiti", value), e))
+    });
+    let graffiti = match graffiti {
+        Some(Ok(graffiti)) => Some(graffiti),
+        Some(Err(e)) => {
+            error!(log, "Invalid graffiti"; "error" => e);
+            return;
+        }
+        None => None,
+    };
+    let metrics_address = if flags.is_present("metrics") {
+        match (
+            flags.parse::<IpAddr>("metrics-address"),
+            flags.parse::<u16>("metrics-port"),
+        ) {
+            (Ok(Some(address)), Ok(Some(port))) => Some(SocketAddr::new(address, port)),
+            (Err(e), _) | (_, Err(e)) => {
+                error!(log, "Invalid metrics address or port"; "error" => e);
+                return;
+            }
+            _ => {
+                error!(log, "Invalid metrics address or port");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+    let config = ValidatorClientConfig {
+        beacon_nodes: flags
+            .value_of("beacon-nodes")
+            .map(|urls| {
+                urls.split(',')
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty())
+                    .collect()
+            })
+            .unwrap_or_else(|| ValidatorClientConfig::default().beacon_nodes),
+        validators_dir: validators_dir.to_path_buf(),
+        doppelganger_cycles,
+        graffiti,
+        graffiti_file: flags.value_of("graffiti-file").map(PathBuf::from),
+        metrics_address,
+        ..ValidatorClientConfig::default()
+    };
+
+    /*
+     * Interop keys are derived in memory, so the validators dir is not read.
+     */
+    let interop_indices = flags.value_of("interop-validators").map(|value| {
+        parse_interop_range(value)
+            .map_err(|e| format!("{} ({})", flags.invalid("interop-validators", value), e))
+    });
+    let validators = match interop_indices {
+        Some(Ok(indices)) => {
+            warn!(log, "Using interop keys, which are publicly known";
+                  "indices" => format!("{:?}", indices));
+            InitializedValidators::from_keypairs(interop_keypairs(indices))
+        }
+        Some(Err(e)) => {
+            error!(log, "Invalid interop validators"; "error" => e);
+            return;
+        }
+        None => match InitializedValidators::from_dir(&config.validators_dir) {
+            Ok(validators) => validators,
+            Err(e) => {
+                error!(log, "Unable to load validator keys";
+                       "dir" => format!("{}", config.validators_dir.display()),
+                       "error" => format!("{:?}", e));
+                return;
+            }
+        },
+    };
+    info!(log, "Loaded validator keys"; "count" => validators.len());
+
+    let started = ValidatorClient::start(&config, validators, slashing_protection, log.clone());
+    let mut client = match started {
+        Ok(client) => client,
+        Err(e) => {
+            error!(log, "Unable to start validator client"; "error" => format!("{:?}", e));
+            return;
+        }
+    };
+
+    /*
+     * The client performs its duties on its own thread until the process is killed, stopping only
+     * should another client sign for its validators.
+     */
+    if let Err(e) = client.wait() {
+        crit!(log, "Validator client stopped"; "error" => format!("{:?}", e));
+    }
+}
+
+/// Imports the slashing protection of an EIP-3076 interchange file.
+fn import_interchange(slashing_protection: &SlashingProtection<DiskDB>, path: &Path, log: &Logger) {
+    let interchange = fs::read(path)
+        .map_err(|e| format!("{}", e))
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).map_err(|e| format!("{}", e)));
+    let interchange = match interchange {
+        Ok(interchange) => interchange,
+        Err(e) => {
+            error!(log, "Unable to read interchange file";
+                   "path" => format!("{}", path.display()),
+                   "error" => e);
+            return;
+        }
+    };
+    match slashing_protection.import_interchange(&interchange) {
+        Ok(count) => info!(log, "Imported slashing protection"; "validators" => count),
+        Err(e) => {
+            error!(log, "Unable to import slashing protection"; "error" => format!("{:?}", e))
+        }
+    }
+}
+
+/// Exports the slashing protection to an EIP-3076 interchange file.
+fn export_interchange(slashing_protection: &SlashingProtection<DiskDB>, path: &Path, log: &Logger) {
+    let interchange = match slashing_protection.export_interchange() {
+        Ok(interchange) => interchange,
+        Err(e) => {
+            error!(log, "Unable to export slashing protection"; "error" => format!("{:?}", e));
+            return;
+        }
+    };
+    match fs::write(path, interchange.to_string()) {
+        Ok(()) => info!(log, "Exported slashing protection";
+                        "path" => format!("{}", path.display()),
+                        "validators" => interchange["data"].as_array().map_or(0, |d| d.len())),
+        Err(e) => error!(log, "Unable to write interchange file";
+                         "path" => format!("{}", path.display()),
+                         "error" => format!("{}", e)),
+    }
+}
@@ -0,0 +1,701 @@
+use super::runtime::new_runtime;
+use bls::{PublicKey, Signature};
+use futures::sync::oneshot;
+use futures::{Future, Stream};
+use hex;
+use hyper::client::connect::Connect;
+use hyper::client::HttpConnector;
+use hyper::header::{ACCEPT, CONTENT_TYPE};
+use hyper::{Body, Client, Method, Request, StatusCode, Uri};
+use serde_json::{self, Value};
+use ssz::Decodable;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::timer::Timeout;
+use types::{AggregateAndProof, Attestation, AttestationData, BeaconBlock, Hash256};
+
+#[derive(Debug, PartialEq)]
+pub enum ApiClientError {
+    InvalidUrl(String),
+    /// The request could not be sent, or the connection failed.
+    Request(String),
+    /// No response arrived within the request timeout.
+    Timeout,
+    /// The beacon node responded with an error status and message.
+    Status(u16, String),
+    /// The response could not be understood.
+    InvalidResponse(String),
+}
+
+/// The genesis of the beacon node's chain.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Genesis {
+    pub genesis_time: u64,
+    pub genesis_block_root: Hash256,
+    pub fork_digest: [u8; 4],
+}
+
+/// The sync status of the beacon node.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyncStatus {
+    pub head_slot: u64,
+    /// The number of slots by which the head is behind the present slot.
+    pub sync_distance: u64,
+    pub is_syncing: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProposerDuty {
+    pub validator_index: usize,
+    pub slot: u64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AttesterDuty {
+    pub validator_index: usize,
+    pub slot: u64,
+    pub shard: u64,
+    /// The validator's position in its committee.
+    pub committee_index: usize,
+    pub committee_length: usize,
+}
+
+/// The duties of a cycle, together with the root of the last block before the cycle. The duties
+/// may change if the chain reorganises past that block.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Duties<T> {
+    pub dependent_root: Hash256,
+    pub duties: Vec<T>,
+}
+
+/// A blocking client of a beacon node's HTTP API.
+///
+/// Requests run on the client's own runtime, so the client may be shared between threads.
+pub struct BeaconNodeClient {
+    base: String,
+    client: Client<HttpConnector>,
+    timeout: Duration,
+    runtime: Runtime,
+}
+
+impl BeaconNodeClient {
+    /// Creates a client of the API at `url`, e.g. `http://localhost:5052`.
+    pub fn new(url: &str, timeout: Duration) -> Result<Self, ApiClientError> {
+        let base = url.trim_end_matches('/').to_string();
+        base.parse::<Uri>()
+            .map_err(|_| ApiClientError::InvalidUrl(url.to_string()))?;
+        let runtime = new_runtime("beacon-node-client")
+            .map_err(|e| ApiClientError::Request(format!("{}", e)))?;
+        Ok(Self {
+            base,
+            client: Client::new(),
+            timeout,
+            runtime,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.base
+    }
+
+    /// `GET /eth/v1/beacon/genesis`
+    pub fn genesis(&self) -> Result<Genesis, ApiClientError> {
+        let data = self.get_json("/eth/v1/beacon/genesis")?;
+        let fork_digest = parse_hex(&data["fork_digest"])?;
+        if fork_digest.len() != 4 {
+            return Err(invalid("fork_digest"));
+        }
+        Ok(Genesis {
+            genesis_time: parse_u64(&data["genesis_time"])?,
+            genesis_block_root: parse_hash(&data["genesis_block_root"])?,
+            fork_digest: [
+                fork_digest[0],
+                fork_digest[1],
+                fork_digest[2],
+                fork_digest[3],
+            ],
+        })
+    }
+
+    /// `GET /eth/v1/config/spec`, returning each value as a string.
+    pub fn spec(&self) -> Result<Value, ApiClientError> {
+        self.get_json("/eth/v1/config/spec")
+    }
+
+    /// `GET /eth/v1/node/syncing`
+    pub fn syncing(&self) -> Result<SyncStatus, ApiClientError> {
+        let data = self.get_json("/eth/v1/node/syncing")?;
+        Ok(SyncStatus {
+            head_slot: parse_u64(&data["head_slot"])?,
+            sync_distance: parse_u64(&data["sync_distance"])?,
+            is_syncing: data["is_syncing"]
+                .as_bool()
+                .ok_or_else(|| invalid("is_syncing"))?,
+        })
+    }
+
+    /// Returns the index of the validator with `pubkey`, or `None` if it is unknown.
+    pub fn validator_index(&self, pubkey: &PublicKey) -> Result<Option<usize>, ApiClientError> {
+        let path = format!(
+            "/eth/v1/beacon/states/head/validators/0x{}",
+            hex::encode(pubkey.as_bytes())
+        );
+        match self.get_json(&path) {
+            Ok(data) => Ok(Some(parse_u64(&data["index"])? as usize)),
+            Err(ApiClientError::Status(404, _)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `GET /eth/v1/validator/duties/proposer/{cycle}`
+    pub fn proposer_duties(&self, cycle: u64) -> Result<Duties<ProposerDuty>, ApiClientError> {
+        let path = format!("/eth/v1/validator/duties/proposer/{}", cycle);
+        duties(&self.get(&path)?, |duty| {
+            Ok(ProposerDuty {
+                validator_index: parse_u64(&duty["validator_index"])? as usize,
+                slot: parse_u64(&duty["slot"])?,
+            })
+        })
+    }
+
+    /// `POST /eth/v1/validator/duties/attester/{cycle}`
+    pub fn attester_duties(
+        &self,
+        cycle: u64,
+        indices: &[usize],
+    ) -> Result<Duties<AttesterDuty>, ApiClientError> {
+        let body: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+        let path = format!("/eth/v1/validator/duties/attester/{}", cycle);
+        duties(&self.post(&path, &json!(body))?, |duty| {
+            Ok(AttesterDuty {
+                validator_index: parse_u64(&duty["validator_index"])? as usize,
+                slot: parse_u64(&duty["slot"])?,
+                shard: parse_u64(&duty["shard"])?,
+                committee_index: parse_u64(&duty["committee_index"])? as usize,
+                committee_length: parse_u64(&duty["committee_length"])? as usize,
+            })
+        })
+    }
+
+    /// `POST /eth/v1/validator/beacon_committee_subscriptions`
+    ///
+    /// Asks the beacon node to join the subnets of the `duties`, so that it can aggregate their
+    /// attestations.
+    pub fn subscribe(&self, duties: &[AttesterDuty]) -> Result<(), ApiClientError> {
+        let body: Vec<Value> = duties
+            .iter()
+            .map(|duty| {
+                json!({
+                    "validator_index": duty.validator_index.to_string(),
+                    "slot": duty.slot.to_string(),
+                    "shard": duty.shard.to_string(),
+                })
+            })
+            .collect();
+        self.post_json(
+            "/eth/v1/validator/beacon_committee_subscriptions",
+            &Value::Array(body),
+        )?;
+        Ok(())
+    }
+
+    /// `POST /eth/v1/validator/liveness/{cycle}`, returning those of `indices` seen attesting in
+    /// `cycle`.
+    pub fn live_validators(
+        &self,
+        cycle: u64,
+        indices: &[usize],
+    ) -> Result<Vec<usize>, ApiClientError> {
+        let body: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+        let path = format!("/eth/v1/validator/liveness/{}", cycle);
+        let mut live = vec![];
+        for item in array(&self.post_json(&path, &json!(body))?)? {
+            let index = parse_u64(&item["index"])? as usize;
+            match item["is_live"].as_bool() {
+                Some(true) => live.push(index),
+                Some(false) => {}
+                None => return Err(invalid("is_live")),
+            }
+        }
+        Ok(live)
+    }
+
+    /// `GET /eth/v1/validator/blocks/{slot}?randao_reveal,graffiti`, returning an unsigned block
+    /// on the beacon node's head.
+    ///
+    /// The block is requested as SSZ, so that its root is computed from exactly the bytes the
+    /// beacon node will import.
+    pub fn produce_block(
+        &self,
+        slot: u64,
+        randao_reveal: &Hash256,
+        graffiti: &Hash256,
+    ) -> Result<BeaconBlock, ApiClientError> {
+        let path = format!(
+            "/eth/v1/validator/blocks/{}?randao_reveal={}&graffiti={}",
+            slot,
+            hex_hash(randao_reveal),
+            hex_hash(graffiti)
+        );
+        self.get_ssz(&path)
+    }
+
+    /// `POST /eth/v1/beacon/blocks`
+    pub fn publish_block(
+        &self,
+        block: &BeaconBlock,
+        signature: &Signature,
+    ) -> Result<(), ApiClientError> {
+        let body = json!({
+            "message": block_json(block),
+            "signature": format!("0x{}", hex::encode(signature.as_bytes())),
+        });
+        self.post_json("/eth/v1/beacon/blocks", &body)?;
+        Ok(())
+    }
+
+    /// `GET /eth/v1/validator/attestation_data?slot,shard`, requested as SSZ.
+    pub fn attestation_data(
+        &self,
+        slot: u64,
+        shard: u64,
+    ) -> Result<AttestationData, ApiClientError> {
+        let path = format!(
+            "/eth/v1/validator/attestation_data?slot={}&shard={}",
+            slot, shard
+        );
+        self.get_ssz(&path)
+    }
+
+    /// `POST /eth/v1/beacon/pool/attestations`
+    pub fn publish_attestations(&self, attestations: &[Attestation]) -> Result<(), ApiClientError> {
+        let body: Vec<Value> = attestations.iter().map(attestation_json).collect();
+        self.post_json("/eth/v1/beacon/pool/attestations", &Value::Array(body))?;
+        Ok(())
+    }
+
+    /// `GET /eth/v1/validator/aggregate_attestation?slot,attestation_data_root`, requested as
+    /// SSZ. Returns `None` if the beacon node has no attestations to the data.
+    pub fn aggregate_attestation(
+        &self,
+        slot: u64,
+        attestation_data_root: &Hash256,
+    ) -> Result<Option<Attestation>, ApiClientError> {
+        let path = format!(
+            "/eth/v1/validator/aggregate_attestation?slot={}&attestation_data_root={}",
+            slot,
+            hex_hash(attestation_data_root)
+        );
+        match self.get_ssz(&path) {
+            Ok(aggregate) => Ok(Some(aggregate)),
+            Err(ApiClientError::Status(404, _)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// `POST /eth/v1/validator/aggregate_and_proofs`
+    pub fn publish_aggregate_and_proofs(
+        &self,
+        signed: &[(AggregateAndProof, Signature)],
+    ) -> Result<(), ApiClientError> {
+        let body: Vec<Value> = signed
+            .iter()
+            .map(|(message, signature)| {
+                json!({
+                    "message": aggregate_and_proof_json(message),
+                    "signature": format!("0x{}", hex::encode(signature.as_bytes())),
+                })
+            })
+            .collect();
+        self.post_json(
+            "/eth/v1/validator/aggregate_and_proofs",
+            &Value::Array(body),
+        )?;
+        Ok(())
+    }
+
+    /// Returns the `data` of the JSON response to a `GET` of `path`.
+    pub fn get_json(&self, path: &str) -> Result<Value, ApiClientError> {
+        data(&self.get(path)?)
+    }
+
+    /// Returns the `data` of the JSON response to a `POST` of `body` to `path`, or `Null` if the
+    /// response is empty.
+    pub fn post_json(&self, path: &str, body: &Value) -> Result<Value, ApiClientError> {
+        let response = self.post(path, body)?;
+        if response.is_empty() {
+            Ok(Value::Null)
+        } else {
+            data(&response)
+        }
+    }
+
+    fn get(&self, path: &str) -> Result<Vec<u8>, ApiClientError> {
+        let req = self
+            .request(Method::GET, path)?
+            .header(ACCEPT, "application/json")
+            .body(Body::empty())
+            .map_err(|e| ApiClientError::Request(format!("{}", e)))?;
+        self.send(req)
+    }
+
+    /// Returns the object decoded from the SSZ response to a `GET` of `path`.
+    fn get_ssz<T: Decodable>(&self, path: &str) -> Result<T, ApiClientError> {
+        let req = self
+            .request(Method::GET, path)?
+            .header(ACCEPT, "application/octet-stream")
+            .body(Body::empty())
+            .map_err(|e| ApiClientError::Request(format!("{}", e)))?;
+        let ssz = self.send(req)?;
+        match T::ssz_decode(&ssz, 0) {
+            Ok((object, i)) if i == ssz.len() => Ok(object),
+            _ => Err(ApiClientError::InvalidResponse("Invalid SSZ".to_string())),
+        }
+    }
+
+    fn post(&self, path: &str, body: &Value) -> Result<Vec<u8>, ApiClientError> {
+        let req = self
+            .request(Method::POST, path)?
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .map_err(|e| ApiClientError::Request(format!("{}", e)))?;
+        self.send(req)
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        path: &str,
+    ) -> Result<::hyper::http::request::Builder, ApiClientError> {
+        let uri: Uri = format!("{}{}", self.base, path)
+            .parse()
+            .map_err(|_| ApiClientError::InvalidUrl(format!("{}{}", self.base, path)))?;
+        let mut builder = Request::builder();
+        builder.method(method).uri(uri);
+        Ok(builder)
+    }
+
+    /// Sends `req`, returning the body of a successful response.
+    fn send(&self, req: Request<Body>) -> Result<Vec<u8>, ApiClientError> {
+        send_request(&self.client, &self.runtime, self.timeout, req)
+    }
+}
+
+/// Sends `req` with `client` on `runtime`, returning the body of a successful response which
+/// arrives within `timeout`.
+pub fn send_request<C>(
+    client: &Client<C>,
+    runtime: &Runtime,
+    timeout: Duration,
+    req: Request<Body>,
+) -> Result<Vec<u8>, ApiClientError>
+where
+    C: Connect + Sync + 'static,
+    C::Transport: 'static,
+    C::Future: 'static,
+{
+    let future = client.request(req).and_then(|response| {
+        let status = response.status();
+        response
+            .into_body()
+            .concat2()
+            .map(move |body| (status, body.to_vec()))
+    });
+    let future = Timeout::new(future, timeout).map_err(|e| {
+        if e.is_elapsed() {
+            ApiClientError::Timeout
+        } else {
+            match e.into_inner() {
+                Some(e) => ApiClientError::Request(format!("{}", e)),
+                None => ApiClientError::Request("Timer failed".to_string()),
+            }
+        }
+    });
+    let (status, body) = oneshot::spawn(future, &runtime.executor()).wait()?;
+    if status.is_success() {
+        Ok(body)
+    } else {
+        Err(status_error(status, &body))
+    }
+}
+
+/// Returns the error of a failed response, with the message of its JSON body, if any.
+fn status_error(status: StatusCode, body: &[u8]) -> ApiClientError {
+    let message = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|body| body["message"].as_str().map(|m| m.to_string()))
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or("").to_string());
+    ApiClientError::Status(status.as_u16(), message)
+}
+
+fn data(body: &[u8]) -> Result<Value, ApiClientError> {
+    let mut body: Value = serde_json::from_slice(body)
+        .map_err(|_| ApiClientError::InvalidResponse("Invalid JSON".to_string()))?;
+    match body.get_mut("data") {
+        Some(data) => Ok(data.take()),
+        None => Err(invalid("data")),
+    }
+}
+
+/*
+ * Objects are encoded as the beacon node decodes them, with quoted integers.
+ */
+
+pub fn block_json(block: &BeaconBlock) -> Value {
+    json!({
+        "slot": block.slot.to_string(),
+        "randao_reveal": hex_hash(&block.randao_reveal),
+        "pow_chain_reference": hex_hash(&block.pow_chain_reference),
+        "ancestor_hashes": block.ancestor_hashes.iter().map(hex_hash).collect::<Vec<String>>(),
+        "active_state_root": hex_hash(&block.active_state_root),
+        "crystallized_state_root": hex_hash(&block.crystallized_state_root),
+        "attestations": block.attestations.iter().map(attestation_json).collect::<Vec<Value>>(),
+        "specials": block
+            .specials
+            .iter()
+            .map(|special| {
+                json!({
+                    "kind": special.kind,
+                    "data": format!("0x{}", hex::encode(&special.data)),
+                })
+            })
+            .collect::<Vec<Value>>(),
+        "graffiti": hex_hash(&block.graffiti),
+    })
+}
+
+fn attestation_json(attestation: &Attestation) -> Value {
+    json!({
+        "data": attestation_data_json(&attestation.data),
+        "participation_bitfield": format!("0x{}", hex::encode(attestation.participation_bitfield.to_bytes())),
+        "custody_bitfield": format!("0x{}", hex::encode(attestation.custody_bitfield.to_bytes())),
+        "aggregate_sig": format!("0x{}", hex::encode(attestation.aggregate_sig.as_bytes())),
+    })
+}
+
+pub fn attestation_data_json(data: &AttestationData) -> Value {
+    json!({
+        "slot": data.slot.to_string(),
+        "shard": data.shard.to_string(),
+        "beacon_block_hash": hex_hash(&data.beacon_block_hash),
+        "epoch_boundary_hash": hex_hash(&data.epoch_boundary_hash),
+        "shard_block_hash": hex_hash(&data.shard_block_hash),
+        "latest_crosslink_hash": hex_hash(&data.latest_crosslink_hash),
+        "justified_slot": data.justified_slot.to_string(),
+        "justified_block_hash": hex_hash(&data.justified_block_hash),
+    })
+}
+
+pub fn aggregate_and_proof_json(message: &AggregateAndProof) -> Value {
+    json!({
+        "aggregator_index": message.aggregator_index.to_string(),
+        "aggregate": attestation_json(&message.aggregate),
+        "selection_proof": format!("0x{}", hex::encode(message.selection_proof.as_bytes())),
+    })
+}
+
+pub fn hex_hash(hash: &Hash256) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+/// Parses a duties response, `{"dependent_root": root, "data": [duty]}`.
+fn duties<T, F>(body: &[u8], parse: F) -> Result<Duties<T>, ApiClientError>
+where
+    F: Fn(&Value) -> Result<T, ApiClientError>,
+{
+    let body: Value = serde_json::from_slice(body)
+        .map_err(|_| ApiClientError::InvalidResponse("Invalid JSON".to_string()))?;
+    Ok(Duties {
+        dependent_root: parse_hash(&body["dependent_root"])?,
+        duties: array(&body["data"])?
+            .iter()
+            .map(parse)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn invalid(field: &str) -> ApiClientError {
+    ApiClientError::InvalidResponse(format!("Invalid field: {}", field))
+}
+
+fn array(value: &Value) -> Result<&Vec<Value>, ApiClientError> {
+    value.as_array().ok_or_else(|| invalid("data"))
+}
+
+/// Parses a quoted integer.
+pub fn parse_u64(value: &Value) -> Result<u64, ApiClientError> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid(&value.to_string()))
+}
+
+/// Parses `0x`-prefixed hex.
+pub fn parse_hex(value: &Value) -> Result<Vec<u8>, ApiClientError> {
+    value
+        .as_str()
+        .filter(|s| s.starts_with("0x"))
+        .and_then(|s| hex::decode(&s[2..]).ok())
+        .ok_or_else(|| invalid(&value.to_string()))
+}
+
+pub fn parse_hash(value: &Value) -> Result<Hash256, ApiClientError> {
+    let bytes = parse_hex(value)?;
+    if bytes.len() != 32 {
+        return Err(invalid(&value.to_string()));
+    }
+    Ok(Hash256::from(&bytes[..]))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use beacon_node::{BeaconNode, TestingSlotClock};
+    use bls::{create_proof_of_possession, Keypair};
+    use db::stores::BeaconBlockStore;
+    use db::MemoryDB;
+    use http_api::{ApiConfig, ApiServer, Context};
+    use slog::{Discard, Logger};
+    use std::net::Ipv4Addr;
+    use std::sync::{Arc, RwLock};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use task_executor::TaskExecutor;
+    use types::{Address, ChainConfig, ValidatorRegistration};
+
+    /// Returns an API server for a beacon node with the validators of `keypairs`, where the
+    /// present slot is 100.
+    pub fn beacon_node(keypairs: &[Keypair]) -> (ApiServer, Arc<Context<MemoryDB>>) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        beacon_node_from(keypairs, now.as_secs() - 100)
+    }
+
+    /// Returns an API server for a beacon node as `beacon_node`, but synced, so that it serves
+    /// duties: its clock is stopped at the present slot, and its head is a block two slots
+    /// before, on genesis.
+    pub fn synced_beacon_node(keypairs: &[Keypair]) -> (ApiServer, Arc<Context<MemoryDB>>) {
+        let (server, ctx) = beacon_node(keypairs);
+        {
+            let mut node = ctx.node.write().unwrap();
+            let slot = node.present_slot();
+            let config = node.config().clone();
+            let clock =
+                TestingSlotClock::new(config.genesis_time, config.slot_duration_millis).unwrap();
+            clock.set_slot(slot);
+            node.set_slot_clock(Arc::new(clock));
+            let mut block = BeaconBlock::zero();
+            block.slot = slot - 2;
+            block.ancestor_hashes.push(node.genesis_root());
+            node.process_block(&block, slot).unwrap();
+        }
+        (server, ctx)
+    }
+
+    /// Returns an API server for a beacon node with the validators of `keypairs`, and one-second
+    /// slots from `genesis_time`.
+    pub fn beacon_node_from(
+        keypairs: &[Keypair],
+        genesis_time: u64,
+    ) -> (ApiServer, Arc<Context<MemoryDB>>) {
+        let mut config = ChainConfig::standard();
+        config.cycle_length = 2;
+        config.shard_count = 2;
+        config.min_committee_size = 2;
+        config.slot_duration_millis = 1_000;
+        config.initial_validators = keypairs
+            .iter()
+            .map(|keypair| ValidatorRegistration {
+                pubkey: keypair.pk.clone(),
+                withdrawal_shard: 0,
+                withdrawal_address: Address::zero(),
+                randao_commitment: Hash256::zero(),
+                proof_of_possession: create_proof_of_possession(keypair),
+            })
+            .collect();
+        config.genesis_time = genesis_time;
+
+        let store = Arc::new(BeaconBlockStore::new(Arc::new(MemoryDB::open())));
+        let node = Arc::new(RwLock::new(BeaconNode::new(config, store).unwrap()));
+        let (executor, _) = TaskExecutor::new(Logger::root(Discard, o!()));
+        let ctx = Arc::new(Context::new(
+            node,
+            None,
+            executor.clone(),
+            Logger::root(Discard, o!()),
+        ));
+        let api_config = ApiConfig {
+            enabled: true,
+            listen_address: Ipv4Addr::LOCALHOST.into(),
+            port: 0,
+            ..ApiConfig::default()
+        };
+        (
+            ApiServer::start(&api_config, ctx.clone(), &executor).unwrap(),
+            ctx,
+        )
+    }
+
+    pub fn client(server: &ApiServer) -> BeaconNodeClient {
+        let url = format!("http://{}", server.local_addr());
+        BeaconNodeClient::new(&url, Duration::from_secs(2)).unwrap()
+    }
+
+    /// Returns the fork digest of the chain of `server`, in which attestations must be signed.
+    pub fn fork_digest(server: &ApiServer) -> [u8; 4] {
+        client(server).genesis().unwrap().fork_digest
+    }
+
+    #[test]
+    fn test_client() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, ctx) = synced_beacon_node(&keypairs);
+        let client = client(&server);
+
+        let genesis = client.genesis().unwrap();
+        let node = ctx.node.read().unwrap();
+        assert_eq!(genesis.genesis_time, node.config().genesis_time);
+        assert_eq!(genesis.genesis_block_root, node.genesis_root());
+        assert_eq!(client.spec().unwrap()["CYCLE_LENGTH"], "2");
+        let syncing = client.syncing().unwrap();
+        assert_eq!(syncing.head_slot, node.present_slot() - 2);
+        assert_eq!(syncing.sync_distance, 2);
+
+        assert_eq!(client.validator_index(&keypairs[2].pk), Ok(Some(2)));
+        assert_eq!(client.validator_index(&Keypair::random().pk), Ok(None));
+
+        let proposers = client.proposer_duties(3).unwrap();
+        assert_eq!(proposers.dependent_root, node.genesis_root());
+        assert_eq!(proposers.duties.len(), 2);
+        assert_eq!(proposers.duties[0].slot, 6);
+        let attesters = client.attester_duties(3, &[0, 1, 2, 3]).unwrap();
+        assert_eq!(attesters.dependent_root, node.genesis_root());
+        assert_eq!(attesters.duties.len(), 4);
+        assert!(attesters.duties.iter().all(|d| d.slot == 6 || d.slot == 7));
+        drop(node);
+        let future_duties: Vec<AttesterDuty> = client.attester_duties(60, &[0, 1]).unwrap().duties;
+        assert_eq!(client.subscribe(&future_duties), Ok(()));
+        assert!(!ctx.subnet_subscriptions.lock().unwrap().is_empty());
+        assert_eq!(client.live_validators(3, &[0, 1]), Ok(vec![]));
+
+        match client.attester_duties(3, &[4]) {
+            Err(ApiClientError::Status(400, message)) => assert!(message.contains("Unknown")),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let present_slot = ctx.node.read().unwrap().present_slot();
+        let block = client
+            .produce_block(present_slot, &Hash256::from(9), &Hash256::from(5))
+            .unwrap();
+        assert_eq!(block.slot, present_slot);
+        assert_eq!(block.randao_reveal, Hash256::from(9));
+        assert_eq!(block.graffiti, Hash256::from(5));
+        let signature = Signature::new(&[1], &keypairs[0].sk);
+        assert_eq!(client.publish_block(&block, &signature), Ok(()));
+        assert_eq!(
+            ctx.node.read().unwrap().head(),
+            (present_slot, block.canonical_root())
+        );
+        drop(server);
+        assert!(client.genesis().is_err());
+        assert!(BeaconNodeClient::new("not a url", Duration::from_secs(1)).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use super::api_client::{ApiClientError, AttesterDuty};
+use super::beacon_node_fallback::BeaconNodeFallback;
+use super::error::DutyError;
+use super::metrics;
+use super::signer::Signer;
+use super::signing::{
+    attestation_data_root, attestation_signing_root, selection_proof, sign_aggregate_and_proof,
+    sign_attestation_data,
+};
+use super::slashing_protection::SlashingProtection;
+use bls::AggregateSignature;
+use db::ClientDB;
+use lighthouse_metrics::{inc_counter, start_timer, stop_timer};
+use slog::Logger;
+use types::{is_aggregator, AggregateAndProof, Attestation, AttestationData, Bitfield};
+
+/*
+ * Attestations are produced a third of the way through their slot, giving the block of the slot
+ * time to arrive. Aggregates are produced two thirds of the way through, once the attestations of
+ * the committee have reached the aggregator's beacon node.
+ */
+
+/// Attests to the head of the beacon node for `duty`, if slashing protection allows it, returning
+/// the data attested to.
+pub fn attest<T: ClientDB>(
+    beacon_nodes: &BeaconNodeFallback,
+    slashing_protection: &SlashingProtection<T>,
+    signer: &Signer,
+    duty: &AttesterDuty,
+    cycle_length: u64,
+    fork_digest: [u8; 4],
+    log: &Logger,
+) -> Result<AttestationData, DutyError> {
+    let timer = start_timer(&metrics::ATTESTATION_PRODUCE_TIMES);
+    let data =
+        beacon_nodes.first_success(|client| client.attestation_data(duty.slot, duty.shard))?;
+    stop_timer(timer);
+    if data.slot != duty.slot || data.shard != duty.shard {
+        return Err(ApiClientError::InvalidResponse(
+            "Attestation data does not match the request".to_string(),
+        )
+        .into());
+    }
+
+    slashing_protection.check_and_insert_attestation(
+        signer.pubkey(),
+        data.justified_slot / cycle_length,
+        data.slot / cycle_length,
+        &attestation_signing_root(&data, fork_digest),
+    )?;
+
+    let timer = start_timer(&metrics::ATTESTATION_SIGN_TIMES);
+    let signature = sign_attestation_data(signer, &data, fork_digest).map_err(DutyError::Signer)?;
+    stop_timer(timer);
+    let mut participation_bitfield = Bitfield::from_elem(duty.committee_length, false);
+    participation_bitfield.set(duty.committee_index, true);
+    let mut aggregate_sig = AggregateSignature::new();
+    aggregate_sig.add(&signature);
+    let attestation = Attestation {
+        data: data.clone(),
+        participation_bitfield,
+        custody_bitfield: Bitfield::from_elem(duty.committee_length, false),
+        aggregate_sig,
+    };
+
+    let attestations = [attestation];
+    let timer = start_timer(&metrics::ATTESTATION_PUBLISH_TIMES);
+    beacon_nodes.first_success(|client| client.publish_attestations(&attestations))?;
+    stop_timer(timer);
+
+    inc_counter(&metrics::ATTESTATIONS);
+    debug!(log, "Published attestation";
+           "slot" => duty.slot,
+           "shard" => duty.shard,
+           "validator_index" => duty.validator_index,
+           "head" => format!("{:?}", data.beacon_block_hash));
+    Ok(data)
+}
+
+/// Returns whether the validator of `duty` is selected to aggregate its committee's attestations.
+pub fn is_selected(
+    signer: &Signer,
+    duty: &AttesterDuty,
+    fork_digest: [u8; 4],
+) -> Result<bool, DutyError> {
+    let proof = selection_proof(signer, duty.slot, fork_digest).map_err(DutyError::Signer)?;
+    Ok(is_aggregator(duty.committee_length, &proof))
+}
+
+/// Publishes the aggregate of the attestations to `data` by the committee of `duty`, for a
+/// validator selected as an aggregator.
+///
+/// Returns `false` if the beacon node has no attestations to aggregate.
+pub fn aggregate(
+    beacon_nodes: &BeaconNodeFallback,
+    signer: &Signer,
+    duty: &AttesterDuty,
+    data: &AttestationData,
+    fork_digest: [u8; 4],
+    log: &Logger,
+) -> Result<bool, DutyError> {
+    let root = attestation_data_root(data);
+    let aggregate = match beacon_nodes
+        .first_success(|client| client.aggregate_attestation(data.slot, &root))?
+    {
+        Some(aggregate) => aggregate,
+        None => return Ok(false),
+    };
+    if aggregate.data != *data {
+        return Err(ApiClientError::InvalidResponse(
+            "Aggregate does not match the request".to_string(),
+        )
+        .into());
+    }
+    let participants = aggregate.participation_bitfield.num_set_bits();
+    let timer = start_timer(&metrics::AGGREGATE_SIGN_TIMES);
+    let message = AggregateAndProof {
+        aggregator_index: duty.validator_index as u64,
+        aggregate,
+        selection_proof: selection_proof(signer, duty.slot, fork_digest)
+            .map_err(DutyError::Signer)?,
+    };
+    let signature =
+        sign_aggregate_and_proof(signer, &message, fork_digest).map_err(DutyError::Signer)?;
+    stop_timer(timer);
+
+    let signed = [(message, signature)];
+    let timer = start_timer(&metrics::AGGREGATE_PUBLISH_TIMES);
+    beacon_nodes.first_success(|client| client.publish_aggregate_and_proofs(&signed))?;
+    stop_timer(timer);
+
+    inc_counter(&metrics::AGGREGATES);
+    info!(log, "Published aggregate";
+          "slot" => duty.slot,
+          "shard" => duty.shard,
+          "validator_index" => duty.validator_index,
+          "participants" => participants);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::api_client::tests::{client, fork_digest, synced_beacon_node};
+    use super::super::beacon_node_fallback::tests::fallback;
+    use super::super::signer::tests::remote_signer;
+    use super::super::signer::{RemoteSigner, SignerClient};
+    use super::*;
+    use bls::Keypair;
+    use db::MemoryDB;
+    use serde_json::Value;
+    use slog::Discard;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_attest_and_aggregate() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, ctx) = synced_beacon_node(&keypairs);
+        let client = client(&server);
+        let beacon_nodes = fallback(&server);
+        let digest = fork_digest(&server);
+        let log = Logger::root(Discard, o!());
+        let protection = SlashingProtection::new(Arc::new(MemoryDB::open()));
+        let slot = ctx.node.read().unwrap().present_slot();
+        let cycle = slot / 2;
+        let duties: Vec<AttesterDuty> = client
+            .attester_duties(cycle, &[0, 1, 2, 3])
+            .unwrap()
+            .duties
+            .into_iter()
+            .filter(|duty| duty.slot == slot)
+            .collect();
+        assert_eq!(duties.len(), 2);
+
+        /*
+         * The first validator's keys are held by a remote signer.
+         */
+        let remote_keypair = &keypairs[duties[0].validator_index];
+        let (url, requests, _runtime) = remote_signer(remote_keypair);
+        let signer_client = Arc::new(SignerClient::new().unwrap());
+        let timeout = std::time::Duration::from_secs(2);
+        let remote = RemoteSigner::new(remote_keypair.pk.clone(), &url, timeout, signer_client);
+        let signers = [
+            Signer::Remote(remote.unwrap()),
+            Signer::Local(keypairs[duties[1].validator_index].clone()),
+        ];
+
+        let mut attested = vec![];
+        for (duty, signer) in duties.iter().zip(signers.iter()) {
+            let data = attest(&beacon_nodes, &protection, signer, duty, 2, digest, &log).unwrap();
+            attested.push(data);
+        }
+        assert_eq!(attested[0], attested[1]);
+        assert_eq!(ctx.node.read().unwrap().pooled_attestations().len(), 2);
+        assert_eq!(requests.lock().unwrap()[0]["type"], "ATTESTATION");
+        let duty = &duties[0];
+        let signer = &signers[0];
+        assert_eq!(
+            attest(&beacon_nodes, &protection, signer, duty, 2, digest, &log),
+            Ok(attested[0].clone())
+        );
+
+        /*
+         * Every member of a committee smaller than the target number of aggregators is selected.
+         */
+        assert_eq!(is_selected(signer, duty, digest), Ok(true));
+        assert_eq!(
+            aggregate(&beacon_nodes, signer, duty, &attested[0], digest, &log),
+            Ok(true)
+        );
+        let node = ctx.node.read().unwrap();
+        assert_eq!(node.pooled_attestations().len(), 3);
+        assert!(node
+            .pooled_attestations()
+            .iter()
+            .any(|a| a.participation_bitfield.num_set_bits() == 2));
+        drop(node);
+
+        let mut unknown = attested[0].clone();
+        unknown.beacon_block_hash = types::Hash256::from(7);
+        assert_eq!(
+            aggregate(&beacon_nodes, signer, duty, &unknown, digest, &log),
+            Ok(false)
+        );
+        let types: Vec<Value> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request["type"].clone())
+            .collect();
+        assert!(types.contains(&json!("AGGREGATE_AND_PROOF")));
+    }
+}
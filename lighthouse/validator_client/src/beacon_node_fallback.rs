@@ -0,0 +1,224 @@
+use super::api_client::{ApiClientError, BeaconNodeClient};
+use slog::Logger;
+use std::sync::RwLock;
+use types::Hash256;
+
+/// The health of a beacon node when it was last checked, ordered from best to worst.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Health {
+    Synced,
+    /// Syncing, with the number of slots by which the node's head is behind.
+    Syncing(u64),
+    /// Not yet checked, or the node did not respond.
+    Offline,
+    /// The node follows another chain, so is never used.
+    WrongChain,
+}
+
+struct Candidate {
+    client: BeaconNodeClient,
+    health: RwLock<Health>,
+}
+
+/// A set of beacon nodes, of which the healthiest available is used for each request.
+///
+/// Requests are sent to the nodes in order of their health, falling back to the next node when a
+/// request fails. Nodes of equal health are used in the order they were given, so the first node
+/// is preferred while it is healthy.
+pub struct BeaconNodeFallback {
+    candidates: Vec<Candidate>,
+    genesis_root: Hash256,
+    log: Logger,
+}
+
+impl BeaconNodeFallback {
+    /// Creates a fallback of `clients`, which must follow the chain with `genesis_root`.
+    ///
+    /// The nodes are considered offline until their health is checked.
+    pub fn new(clients: Vec<BeaconNodeClient>, genesis_root: Hash256, log: Logger) -> Self {
+        Self {
+            candidates: clients
+                .into_iter()
+                .map(|client| Candidate {
+                    client,
+                    health: RwLock::new(Health::Offline),
+                })
+                .collect(),
+            genesis_root,
+            log,
+        }
+    }
+
+    /// Returns the URL and health of each node, in the order they were given.
+    pub fn health(&self) -> Vec<(String, Health)> {
+        self.candidates
+            .iter()
+            .map(|c| (c.client.url().to_string(), read(&c.health)))
+            .collect()
+    }
+
+    /// Returns the number of nodes which are synced or syncing.
+    pub fn num_available(&self) -> usize {
+        self.candidates
+            .iter()
+            .filter(|c| read(&c.health) < Health::Offline)
+            .count()
+    }
+
+    /// Checks the chain and sync status of every node.
+    pub fn update_health(&self) {
+        for candidate in &self.candidates {
+            let health = check_health(&candidate.client, &self.genesis_root);
+            let previous = {
+                let mut current = candidate.health.write().expect("Health lock poisoned");
+                ::std::mem::replace(&mut *current, health)
+            };
+            if health == Health::WrongChain && previous != Health::WrongChain {
+                error!(self.log, "Beacon node is on another chain";
+                       "url" => candidate.client.url());
+            } else if (health < Health::Offline) != (previous < Health::Offline) {
+                info!(self.log, "Beacon node health changed";
+                      "url" => candidate.client.url(),
+                      "health" => format!("{:?}", health));
+            }
+        }
+    }
+
+    /// Returns the result of `request` from the first node to answer it, trying the nodes in
+    /// order of health. A node which cannot be reached is marked offline until its next check.
+    ///
+    /// Returns the error of the last node tried if none succeeds.
+    pub fn first_success<T, F>(&self, request: F) -> Result<T, ApiClientError>
+    where
+        F: Fn(&BeaconNodeClient) -> Result<T, ApiClientError>,
+    {
+        let mut candidates: Vec<(Health, &Candidate)> = self
+            .candidates
+            .iter()
+            .map(|c| (read(&c.health), c))
+            .filter(|(health, _)| *health != Health::WrongChain)
+            .collect();
+        candidates.sort_by_key(|(health, _)| *health);
+
+        let mut last_error = ApiClientError::Request("No beacon nodes available".to_string());
+        for (_, candidate) in candidates {
+            match request(&candidate.client) {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    debug!(self.log, "Beacon node request failed";
+                           "url" => candidate.client.url(),
+                           "error" => format!("{:?}", e));
+                    match e {
+                        ApiClientError::Request(_) | ApiClientError::Timeout => {
+                            *candidate.health.write().expect("Health lock poisoned") =
+                                Health::Offline;
+                        }
+                        _ => {}
+                    }
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+fn read(health: &RwLock<Health>) -> Health {
+    *health.read().expect("Health lock poisoned")
+}
+
+fn check_health(client: &BeaconNodeClient, genesis_root: &Hash256) -> Health {
+    match client.genesis() {
+        Ok(ref genesis) if genesis.genesis_block_root != *genesis_root => {
+            return Health::WrongChain
+        }
+        Ok(_) => {}
+        Err(_) => return Health::Offline,
+    }
+    match client.syncing() {
+        Ok(ref status) if status.is_syncing => Health::Syncing(status.sync_distance),
+        Ok(_) => Health::Synced,
+        Err(_) => Health::Offline,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::api_client::tests::{beacon_node, client};
+    use super::*;
+    use bls::Keypair;
+    use http_api::ApiServer;
+    use slog::Discard;
+    use std::time::Duration;
+
+    /// Returns a fallback of the single beacon node `server`, with its health checked.
+    pub fn fallback(server: &ApiServer) -> BeaconNodeFallback {
+        let client = client(server);
+        let genesis_root = client.genesis().unwrap().genesis_block_root;
+        let fallback =
+            BeaconNodeFallback::new(vec![client], genesis_root, Logger::root(Discard, o!()));
+        fallback.update_health();
+        fallback
+    }
+
+    #[test]
+    fn test_fallback() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, ctx) = beacon_node(&keypairs);
+        let offline = BeaconNodeClient::new("http://127.0.0.1:1", Duration::from_secs(1)).unwrap();
+        let genesis_root = ctx.node.read().unwrap().genesis_root();
+        let log = Logger::root(Discard, o!());
+        let fallback = BeaconNodeFallback::new(vec![offline, client(&server)], genesis_root, log);
+        assert_eq!(fallback.num_available(), 0);
+
+        /*
+         * Unchecked nodes are tried in order, until one answers.
+         */
+        let genesis = fallback.first_success(|c| c.genesis()).unwrap();
+        assert_eq!(genesis.genesis_block_root, genesis_root);
+
+        fallback.update_health();
+        let health: Vec<Health> = fallback.health().into_iter().map(|(_, h)| h).collect();
+        assert_eq!(health[0], Health::Offline);
+        match health[1] {
+            Health::Syncing(distance) => assert!(distance >= 100),
+            other => panic!("Unexpected health: {:?}", other),
+        }
+        assert_eq!(fallback.num_available(), 1);
+
+        /*
+         * A node which fails is marked offline, and the error of the last node is returned.
+         */
+        drop(server);
+        match fallback.first_success(|c| c.genesis()) {
+            Err(ApiClientError::Request(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert_eq!(fallback.num_available(), 0);
+    }
+
+    #[test]
+    fn test_wrong_chain() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, _) = beacon_node(&keypairs);
+        let log = Logger::root(Discard, o!());
+        let fallback = BeaconNodeFallback::new(vec![client(&server)], Hash256::from(1), log);
+        assert!(fallback.first_success(|c| c.genesis()).is_ok());
+
+        fallback.update_health();
+        assert_eq!(fallback.health()[0].1, Health::WrongChain);
+        assert_eq!(fallback.num_available(), 0);
+        match fallback.first_success(|c| c.genesis()) {
+            Err(ApiClientError::Request(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_health_order() {
+        assert!(Health::Synced < Health::Syncing(0));
+        assert!(Health::Syncing(1) < Health::Syncing(2));
+        assert!(Health::Syncing(1 << 63) < Health::Offline);
+        assert!(Health::Offline < Health::WrongChain);
+    }
+}
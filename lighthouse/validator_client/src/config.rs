@@ -0,0 +1,38 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use types::Hash256;
+
+/// The configuration of the validator client.
+#[derive(Clone, Debug)]
+pub struct ValidatorClientConfig {
+    /// The URLs of the HTTP APIs of the beacon nodes, in order of preference.
+    pub beacon_nodes: Vec<String>,
+    /// The directory holding the validators' keys.
+    pub validators_dir: PathBuf,
+    /// How long to wait for each response from a beacon node.
+    pub request_timeout: Duration,
+    /// The number of cycles each validator is watched for attestations by another client before
+    /// it signs anything, or zero to sign at once.
+    pub doppelganger_cycles: u64,
+    /// The graffiti of blocks proposed by validators without their own.
+    pub graffiti: Option<Hash256>,
+    /// A file of graffiti for each validator, read before each proposal.
+    pub graffiti_file: Option<PathBuf>,
+    /// The address of the server of the client's metrics and performance summary, if enabled.
+    pub metrics_address: Option<SocketAddr>,
+}
+
+impl Default for ValidatorClientConfig {
+    fn default() -> Self {
+        Self {
+            beacon_nodes: vec!["http://localhost:5052".to_string()],
+            validators_dir: PathBuf::from("validators"),
+            request_timeout: Duration::from_secs(4),
+            doppelganger_cycles: 2,
+            graffiti: None,
+            graffiti_file: None,
+            metrics_address: None,
+        }
+    }
+}
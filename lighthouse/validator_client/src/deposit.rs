@@ -0,0 +1,208 @@
+use bls::{Keypair, PublicKey, Signature};
+use hex;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use types::Hash256;
+
+/// The domain type of deposits, which are signed in the same domain on every fork.
+pub const DOMAIN_DEPOSIT: [u8; 4] = [3, 0, 0, 0];
+/// The prefix of withdrawal credentials committing to a BLS withdrawal key.
+pub const BLS_WITHDRAWAL_PREFIX: u8 = 0;
+/// The deposit of a full validator, in Gwei.
+pub const MAX_EFFECTIVE_BALANCE: u64 = 32_000_000_000;
+/// The version of the deposit CLI whose deposit data format is written, which the launchpad
+/// requires.
+pub const DEPOSIT_CLI_VERSION: &str = "2.3.0";
+
+/*
+ * Deposits are made on the PoW chain, whose deposit contract and the launchpad compute the roots
+ * of SSZ containers with SHA-256, unlike the beacon chain's canonical hash.
+ */
+
+/// The deposit of a validator, signed by its voting key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepositData {
+    pub pubkey: PublicKey,
+    pub withdrawal_credentials: Hash256,
+    /// The amount deposited, in Gwei.
+    pub amount: u64,
+    pub signature: Signature,
+}
+
+impl DepositData {
+    /// Returns the deposit of `amount` by `voting_keypair`, withdrawable by `withdrawal_pubkey`,
+    /// signed for the chain of `fork_version`.
+    pub fn new(
+        voting_keypair: &Keypair,
+        withdrawal_pubkey: &PublicKey,
+        amount: u64,
+        fork_version: [u8; 4],
+    ) -> Self {
+        let withdrawal_credentials = withdrawal_credentials(withdrawal_pubkey);
+        let message_root =
+            deposit_message_root(&voting_keypair.pk, &withdrawal_credentials, amount);
+        let signing_root = sha256(&[&message_root, &deposit_domain(fork_version)]);
+        Self {
+            pubkey: voting_keypair.pk.clone(),
+            withdrawal_credentials,
+            amount,
+            signature: Signature::new(&signing_root[..], &voting_keypair.sk),
+        }
+    }
+
+    /// Returns the root of the message signed by the deposit.
+    pub fn message_root(&self) -> Hash256 {
+        deposit_message_root(&self.pubkey, &self.withdrawal_credentials, self.amount)
+    }
+
+    /// Returns the root of the deposit, which the deposit contract checks.
+    pub fn root(&self) -> Hash256 {
+        sha256(&[
+            &sha256(&[
+                &bytes_root(&self.pubkey.as_bytes()),
+                &self.withdrawal_credentials,
+            ]),
+            &sha256(&[
+                &uint_root(self.amount),
+                &bytes_root(&self.signature.as_bytes()),
+            ]),
+        ])
+    }
+
+    /// Returns the deposit in the format of the deposit data file read by the launchpad.
+    pub fn to_launchpad_json(&self, fork_version: [u8; 4], network_name: &str) -> Value {
+        json!({
+            "pubkey": hex::encode(self.pubkey.as_bytes()),
+            "withdrawal_credentials": hex::encode(self.withdrawal_credentials),
+            "amount": self.amount,
+            "signature": hex::encode(self.signature.as_bytes()),
+            "deposit_message_root": hex::encode(self.message_root()),
+            "deposit_data_root": hex::encode(self.root()),
+            "fork_version": hex::encode(fork_version),
+            "network_name": network_name,
+            "deposit_cli_version": DEPOSIT_CLI_VERSION,
+        })
+    }
+}
+
+/// Returns the withdrawal credentials committing to `withdrawal_pubkey`: the BLS withdrawal prefix
+/// followed by the last 31 bytes of the hash of the key.
+pub fn withdrawal_credentials(withdrawal_pubkey: &PublicKey) -> Hash256 {
+    let mut credentials = sha256(&[&withdrawal_pubkey.as_bytes()]);
+    credentials[0] = BLS_WITHDRAWAL_PREFIX;
+    credentials
+}
+
+/// Returns the domain in which deposits are signed for the chain of `fork_version`.
+///
+/// Deposits may be made before genesis, so the domain commits to a zero genesis validators root.
+pub fn deposit_domain(fork_version: [u8; 4]) -> [u8; 32] {
+    let fork_data_root = sha256(&[&pad_to_chunk(&fork_version), &[0; 32]]);
+    let mut domain = [0; 32];
+    domain[..4].copy_from_slice(&DOMAIN_DEPOSIT);
+    domain[4..].copy_from_slice(&fork_data_root[..28]);
+    domain
+}
+
+fn deposit_message_root(
+    pubkey: &PublicKey,
+    withdrawal_credentials: &Hash256,
+    amount: u64,
+) -> Hash256 {
+    sha256(&[
+        &sha256(&[&bytes_root(&pubkey.as_bytes()), withdrawal_credentials]),
+        &sha256(&[&uint_root(amount), &[0; 32]]),
+    ])
+}
+
+fn sha256(parts: &[&[u8]]) -> Hash256 {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    Hash256::from(&hasher.finalize()[..])
+}
+
+/// Returns `bytes` zero-padded to 32 bytes.
+fn pad_to_chunk(bytes: &[u8]) -> [u8; 32] {
+    let mut padded = [0; 32];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    padded
+}
+
+fn uint_root(value: u64) -> Hash256 {
+    Hash256::from(&pad_to_chunk(&value.to_le_bytes())[..])
+}
+
+/// Returns the root of a fixed-length byte vector: the merkle root of its 32-byte chunks, padded
+/// with zero chunks to a power of two.
+fn bytes_root(bytes: &[u8]) -> Hash256 {
+    let mut layer: Vec<Hash256> = bytes
+        .chunks(32)
+        .map(|chunk| Hash256::from(&pad_to_chunk(chunk)[..]))
+        .collect();
+    while layer.len() > 1 {
+        if layer.len() % 2 == 1 {
+            layer.push(Hash256::zero());
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| sha256(&[&pair[0], &pair[1]]))
+            .collect();
+    }
+    layer.pop().unwrap_or_else(Hash256::zero)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interop::interop_keypair;
+    use super::*;
+
+    #[test]
+    fn test_deposit_domain() {
+        assert_eq!(
+            hex::encode(&deposit_domain([0; 4])[..]),
+            "03000000f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a9"
+        );
+        assert_ne!(deposit_domain([0; 4]), deposit_domain([0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn test_bytes_root() {
+        let chunk = Hash256::from(&[7; 32][..]);
+        assert_eq!(bytes_root(&[7; 32]), chunk);
+        assert_eq!(
+            bytes_root(&[7; 48]),
+            sha256(&[&chunk, &pad_to_chunk(&[7; 16])])
+        );
+        assert_eq!(
+            bytes_root(&[7; 96]),
+            sha256(&[&sha256(&[&chunk, &chunk]), &sha256(&[&chunk, &[0; 32]])])
+        );
+    }
+
+    #[test]
+    fn test_deposit_data() {
+        let voting = interop_keypair(0);
+        let withdrawal = interop_keypair(1);
+        let deposit = DepositData::new(&voting, &withdrawal.pk, MAX_EFFECTIVE_BALANCE, [0; 4]);
+        assert_eq!(deposit.pubkey, voting.pk);
+        assert_eq!(deposit.withdrawal_credentials[0], BLS_WITHDRAWAL_PREFIX);
+        assert_eq!(
+            deposit.withdrawal_credentials[1..],
+            sha256(&[&withdrawal.pk.as_bytes()])[1..]
+        );
+        let signing_root = sha256(&[&deposit.message_root(), &deposit_domain([0; 4])]);
+        assert!(deposit.signature.verify(&signing_root[..], &voting.pk));
+
+        let json = deposit.to_launchpad_json([0; 4], "mainnet");
+        assert_eq!(json["amount"], MAX_EFFECTIVE_BALANCE);
+        assert_eq!(json["fork_version"], "00000000");
+        assert_eq!(json["deposit_data_root"], hex::encode(deposit.root()));
+        assert_eq!(
+            json["deposit_message_root"],
+            hex::encode(deposit.message_root())
+        );
+        assert_ne!(deposit.root(), deposit.message_root());
+    }
+}
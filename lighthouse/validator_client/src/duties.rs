@@ -0,0 +1,193 @@
+use super::api_client::{ApiClientError, AttesterDuty, ProposerDuty};
+use super::beacon_node_fallback::BeaconNodeFallback;
+use slog::Logger;
+use std::collections::BTreeMap;
+use types::Hash256;
+
+/// The duties of the validators in one cycle.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CycleDuties {
+    /// The root of the last block before the cycle, on which the duties depend.
+    pub dependent_root: Hash256,
+    /// The validator indices for which the attester duties were fetched.
+    pub indices: Vec<usize>,
+    pub proposers: Vec<ProposerDuty>,
+    pub attesters: Vec<AttesterDuty>,
+}
+
+/// Keeps the duties of the validators for the present and next cycles.
+///
+/// Duties are polled every slot. The proposer duties of a cycle are fetched each time, as their
+/// `dependent_root` reveals whether the chain has reorganised since the attester duties were
+/// fetched. Attester duties are fetched again only after a reorg, or once more validators are
+/// known, and the beacon node is then asked to join their subnets.
+pub struct DutiesService {
+    cycle_length: u64,
+    cycles: BTreeMap<u64, CycleDuties>,
+    log: Logger,
+}
+
+impl DutiesService {
+    pub fn new(cycle_length: u64, log: Logger) -> Self {
+        Self {
+            cycle_length: cycle_length.max(1),
+            cycles: BTreeMap::new(),
+            log,
+        }
+    }
+
+    /// Updates the duties of the validators of `indices` for the cycle of `slot` and the next,
+    /// and forgets the duties of earlier cycles.
+    pub fn poll(
+        &mut self,
+        beacon_nodes: &BeaconNodeFallback,
+        slot: u64,
+        indices: &[usize],
+    ) -> Result<(), ApiClientError> {
+        let cycle = slot / self.cycle_length;
+        self.cycles = self.cycles.split_off(&cycle);
+        self.poll_cycle(beacon_nodes, cycle, slot, indices)?;
+        self.poll_cycle(beacon_nodes, cycle + 1, slot, indices)
+    }
+
+    fn poll_cycle(
+        &mut self,
+        beacon_nodes: &BeaconNodeFallback,
+        cycle: u64,
+        slot: u64,
+        indices: &[usize],
+    ) -> Result<(), ApiClientError> {
+        let proposers = beacon_nodes.first_success(|client| client.proposer_duties(cycle))?;
+        let proposer_duties: Vec<ProposerDuty> = proposers
+            .duties
+            .into_iter()
+            .filter(|duty| indices.contains(&duty.validator_index))
+            .collect();
+
+        if let Some(known) = self.cycles.get_mut(&cycle) {
+            if known.dependent_root == proposers.dependent_root && known.indices == indices {
+                known.proposers = proposer_duties;
+                return Ok(());
+            }
+            if known.dependent_root != proposers.dependent_root {
+                info!(self.log, "Duties changed by reorg"; "cycle" => cycle);
+            }
+        }
+
+        let attesters = if indices.is_empty() {
+            vec![]
+        } else {
+            let attesters =
+                beacon_nodes.first_success(|client| client.attester_duties(cycle, indices))?;
+            if attesters.dependent_root != proposers.dependent_root {
+                /*
+                 * The chain reorganised between the requests, so the duties may be inconsistent.
+                 * They are fetched again at the next poll.
+                 */
+                self.cycles.remove(&cycle);
+                return Ok(());
+            }
+            attesters.duties
+        };
+        let upcoming: Vec<AttesterDuty> = attesters
+            .iter()
+            .filter(|duty| duty.slot >= slot)
+            .cloned()
+            .collect();
+        if !upcoming.is_empty() {
+            beacon_nodes.first_success(|client| client.subscribe(&upcoming))?;
+        }
+        debug!(self.log, "Fetched duties";
+               "cycle" => cycle,
+               "proposals" => proposer_duties.len(),
+               "attestations" => attesters.len());
+
+        self.cycles.insert(
+            cycle,
+            CycleDuties {
+                dependent_root: proposers.dependent_root,
+                indices: indices.to_vec(),
+                proposers: proposer_duties,
+                attesters,
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns the duties of the cycle, if known.
+    pub fn cycle(&self, cycle: u64) -> Option<&CycleDuties> {
+        self.cycles.get(&cycle)
+    }
+
+    /// Returns the proposer duties at `slot`.
+    pub fn proposers_at(&self, slot: u64) -> Vec<&ProposerDuty> {
+        self.cycle(slot / self.cycle_length)
+            .map(|duties| duties.proposers.iter().filter(|d| d.slot == slot).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the attester duties at `slot`.
+    pub fn attesters_at(&self, slot: u64) -> Vec<&AttesterDuty> {
+        self.cycle(slot / self.cycle_length)
+            .map(|duties| duties.attesters.iter().filter(|d| d.slot == slot).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::api_client::tests::synced_beacon_node;
+    use super::super::beacon_node_fallback::tests::fallback;
+    use super::*;
+    use bls::Keypair;
+    use slog::Discard;
+    use types::BeaconBlock;
+
+    #[test]
+    fn test_poll() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, ctx) = synced_beacon_node(&keypairs);
+        let beacon_nodes = fallback(&server);
+        let mut duties = DutiesService::new(2, Logger::root(Discard, o!()));
+        let present_slot = ctx.node.read().unwrap().present_slot();
+        let slot = present_slot - present_slot % 2;
+
+        duties.poll(&beacon_nodes, slot, &[1, 2]).unwrap();
+        let head_root = ctx.node.read().unwrap().head().1;
+        for cycle in &[slot / 2, slot / 2 + 1] {
+            let cycle = duties.cycle(*cycle).unwrap();
+            assert_eq!(cycle.dependent_root, head_root);
+            assert_eq!(cycle.attesters.len(), 2);
+            assert!(cycle
+                .proposers
+                .iter()
+                .all(|d| d.validator_index == 1 || d.validator_index == 2));
+        }
+        let attesters = duties.attesters_at(slot).len() + duties.attesters_at(slot + 1).len();
+        assert_eq!(attesters, 2);
+        assert!(!ctx.subnet_subscriptions.lock().unwrap().is_empty());
+
+        /*
+         * A block in the last slot of the present cycle changes the dependent root of the next.
+         */
+        let mut block = BeaconBlock::zero();
+        block.slot = slot + 1;
+        block.ancestor_hashes.push(head_root);
+        ctx.node
+            .write()
+            .unwrap()
+            .process_block(&block, present_slot + 1)
+            .unwrap();
+        duties.poll(&beacon_nodes, slot, &[1, 2]).unwrap();
+        assert_eq!(duties.cycle(slot / 2).unwrap().dependent_root, head_root);
+        assert_eq!(
+            duties.cycle(slot / 2 + 1).unwrap().dependent_root,
+            block.canonical_root()
+        );
+
+        duties.poll(&beacon_nodes, slot + 2, &[0, 1, 2, 3]).unwrap();
+        assert!(duties.cycle(slot / 2).is_none());
+        assert_eq!(duties.cycle(slot / 2 + 1).unwrap().attesters.len(), 4);
+        assert!(duties.cycle(slot / 2 + 2).is_some());
+    }
+}
@@ -0,0 +1,24 @@
+use super::api_client::ApiClientError;
+use super::slashing_protection::NotSafe;
+
+/// The reason a validator's duty was not performed.
+#[derive(Debug, PartialEq)]
+pub enum DutyError {
+    Api(ApiClientError),
+    /// A remote signer did not sign the message.
+    Signer(ApiClientError),
+    /// Signing the message could get the validator slashed.
+    NotSafe(NotSafe),
+}
+
+impl From<ApiClientError> for DutyError {
+    fn from(e: ApiClientError) -> DutyError {
+        DutyError::Api(e)
+    }
+}
+
+impl From<NotSafe> for DutyError {
+    fn from(e: NotSafe) -> DutyError {
+        DutyError::NotSafe(e)
+    }
+}
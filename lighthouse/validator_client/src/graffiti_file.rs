@@ -0,0 +1,139 @@
+use bls::PublicKey;
+use hex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use types::Hash256;
+
+/// The most bytes of text which fit in the graffiti of a block.
+pub const GRAFFITI_BYTES: usize = 32;
+
+#[derive(Debug)]
+pub enum GraffitiError {
+    /// The graffiti is longer than `GRAFFITI_BYTES`.
+    TooLong(String),
+    Io(io::Error),
+    /// The line, counted from one, is not `default: <graffiti>` or `0x<pubkey>: <graffiti>`.
+    InvalidLine(usize),
+}
+
+impl From<io::Error> for GraffitiError {
+    fn from(e: io::Error) -> GraffitiError {
+        GraffitiError::Io(e)
+    }
+}
+
+/// Returns the graffiti holding the UTF-8 bytes of `text`, padded with zeros.
+pub fn parse_graffiti(text: &str) -> Result<Hash256, GraffitiError> {
+    let bytes = text.as_bytes();
+    if bytes.len() > GRAFFITI_BYTES {
+        return Err(GraffitiError::TooLong(text.to_string()));
+    }
+    let mut graffiti = [0; GRAFFITI_BYTES];
+    graffiti[..bytes.len()].copy_from_slice(bytes);
+    Ok(Hash256::from(&graffiti[..]))
+}
+
+/// A file of graffiti for each validator, read again on every lookup so it may be edited while
+/// the validator client runs.
+///
+/// Each line is either `default: <graffiti>` or `0x<pubkey>: <graffiti>`, and blank lines are
+/// ignored.
+#[derive(Debug, Clone)]
+pub struct GraffitiFile {
+    path: PathBuf,
+}
+
+impl GraffitiFile {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Returns the graffiti of `pubkey` if the file has a line for it, or else the default graffiti
+    /// if the file has one.
+    pub fn load_graffiti(&self, pubkey: &PublicKey) -> Result<Option<Hash256>, GraffitiError> {
+        let contents = fs::read_to_string(&self.path)?;
+        let key = format!("0x{}", hex::encode(pubkey.as_bytes()));
+        let mut default = None;
+        for (i, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, ':');
+            let (name, text) = match (parts.next(), parts.next()) {
+                (Some(name), Some(text)) => (name.trim(), text.trim()),
+                _ => return Err(GraffitiError::InvalidLine(i + 1)),
+            };
+            if name == "default" {
+                default = Some(parse_graffiti(text)?);
+            } else if !name.starts_with("0x") {
+                return Err(GraffitiError::InvalidLine(i + 1));
+            } else if name.eq_ignore_ascii_case(&key) {
+                return parse_graffiti(text).map(Some);
+            }
+        }
+        Ok(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::validator_definitions::tests::test_dir;
+    use super::*;
+    use bls::Keypair;
+
+    #[test]
+    fn test_parse_graffiti() {
+        let graffiti = parse_graffiti("lighthouse").unwrap();
+        assert_eq!(&graffiti[..10], b"lighthouse");
+        assert!(graffiti[10..].iter().all(|b| *b == 0));
+        assert_eq!(parse_graffiti("").unwrap(), Hash256::zero());
+        assert!(parse_graffiti(&"a".repeat(GRAFFITI_BYTES)).is_ok());
+        match parse_graffiti(&"a".repeat(GRAFFITI_BYTES + 1)) {
+            Err(GraffitiError::TooLong(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_graffiti_file() {
+        let dir = test_dir("graffiti");
+        let path = dir.join("graffiti.txt");
+        let file = GraffitiFile::new(&path);
+        let keypairs = [Keypair::random(), Keypair::random()];
+        match file.load_graffiti(&keypairs[0].pk) {
+            Err(GraffitiError::Io(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let line = format!("0x{}: mine\n", hex::encode(keypairs[0].pk.as_bytes()));
+        fs::write(&path, &line).unwrap();
+        assert_eq!(
+            file.load_graffiti(&keypairs[0].pk).unwrap(),
+            Some(parse_graffiti("mine").unwrap())
+        );
+        assert_eq!(file.load_graffiti(&keypairs[1].pk).unwrap(), None);
+
+        /*
+         * The file is read again on each lookup.
+         */
+        fs::write(&path, format!("default: all: of us\n\n{}", line)).unwrap();
+        assert_eq!(
+            file.load_graffiti(&keypairs[0].pk).unwrap(),
+            Some(parse_graffiti("mine").unwrap())
+        );
+        assert_eq!(
+            file.load_graffiti(&keypairs[1].pk).unwrap(),
+            Some(parse_graffiti("all: of us").unwrap())
+        );
+
+        fs::write(&path, "default\n").unwrap();
+        match file.load_graffiti(&keypairs[0].pk) {
+            Err(GraffitiError::InvalidLine(1)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+use super::api_client::ApiClientError;
+use super::graffiti_file::parse_graffiti;
+use super::keys::{load_keypairs, KeyError};
+use super::signer::{RemoteSigner, Signer, SignerClient, REMOTE_SIGNER_TIMEOUT};
+use super::validator_definitions::{
+    decrypt_keystore, SigningDefinition, ValidatorDefinition, ValidatorDefinitions,
+    ValidatorDefinitionsError, CONFIG_FILENAME,
+};
+use bls::{Keypair, PublicKey};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use types::Hash256;
+
+#[derive(Debug)]
+pub enum InitializedValidatorsError {
+    Keys(KeyError),
+    Definitions(ValidatorDefinitionsError),
+    /// The client of the remote signers could not be created, or a signer URL is invalid.
+    RemoteSigner(ApiClientError),
+}
+
+impl From<KeyError> for InitializedValidatorsError {
+    fn from(e: KeyError) -> InitializedValidatorsError {
+        InitializedValidatorsError::Keys(e)
+    }
+}
+
+impl From<ValidatorDefinitionsError> for InitializedValidatorsError {
+    fn from(e: ValidatorDefinitionsError) -> InitializedValidatorsError {
+        InitializedValidatorsError::Definitions(e)
+    }
+}
+
+impl From<io::Error> for InitializedValidatorsError {
+    fn from(e: io::Error) -> InitializedValidatorsError {
+        InitializedValidatorsError::Definitions(ValidatorDefinitionsError::Io(e))
+    }
+}
+
+impl From<ApiClientError> for InitializedValidatorsError {
+    fn from(e: ApiClientError) -> InitializedValidatorsError {
+        InitializedValidatorsError::RemoteSigner(e)
+    }
+}
+
+/// The signers of the validators the validator client performs the duties of: those of the
+/// `.key` files of the validators dir, followed by those of its enabled validator definitions.
+///
+/// The definitions are reloaded whenever their file changes.
+pub struct InitializedValidators {
+    validators_dir: Option<PathBuf>,
+    /// The modification time of the definitions when they were last loaded.
+    modified: Option<SystemTime>,
+    key_file_signers: Vec<Arc<Signer>>,
+    /// The signers of the enabled definitions, with the definitions they were created from.
+    definition_signers: Vec<(SigningDefinition, Arc<Signer>)>,
+    /// The graffiti of the enabled definitions which have their own.
+    definition_graffiti: Vec<(PublicKey, Hash256)>,
+    /// The client shared by the remote signers, created once one is defined.
+    signer_client: Option<Arc<SignerClient>>,
+}
+
+impl InitializedValidators {
+    /// A fixed set of keypairs, with no validators dir to reload.
+    pub fn from_keypairs(keypairs: Vec<Keypair>) -> Self {
+        Self {
+            validators_dir: None,
+            modified: None,
+            key_file_signers: keypairs
+                .into_iter()
+                .map(|keypair| Arc::new(Signer::Local(keypair)))
+                .collect(),
+            definition_signers: vec![],
+            definition_graffiti: vec![],
+            signer_client: None,
+        }
+    }
+
+    /// Loads the signers of `validators_dir`, decrypting the keystores of its definitions.
+    pub fn from_dir(validators_dir: &Path) -> Result<Self, InitializedValidatorsError> {
+        fs::create_dir_all(validators_dir)?;
+        let mut validators = Self::from_keypairs(load_keypairs(validators_dir)?);
+        validators.validators_dir = Some(validators_dir.to_path_buf());
+        validators.load_definitions()?;
+        Ok(validators)
+    }
+
+    pub fn signers(&self) -> Vec<&Arc<Signer>> {
+        self.key_file_signers
+            .iter()
+            .chain(self.definition_signers.iter().map(|(_, signer)| signer))
+            .collect()
+    }
+
+    /// Returns the graffiti of the definition of `pubkey`, if it has its own.
+    pub fn graffiti(&self, pubkey: &PublicKey) -> Option<Hash256> {
+        self.definition_graffiti
+            .iter()
+            .find(|(key, _)| key == pubkey)
+            .map(|(_, graffiti)| *graffiti)
+    }
+
+    pub fn len(&self) -> usize {
+        self.key_file_signers.len() + self.definition_signers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reloads the definitions if their file changed since they were last loaded, returning
+    /// whether it did.
+    ///
+    /// Only the keystores of validators not already loaded are decrypted. If any fails, the
+    /// signers are unchanged and the reload is retried on the next refresh.
+    pub fn refresh(&mut self) -> Result<bool, InitializedValidatorsError> {
+        let modified = match self.validators_dir {
+            Some(ref dir) => modified_time(dir),
+            None => return Ok(false),
+        };
+        if modified == self.modified {
+            return Ok(false);
+        }
+        self.load_definitions()?;
+        Ok(true)
+    }
+
+    fn load_definitions(&mut self) -> Result<(), InitializedValidatorsError> {
+        let dir = match self.validators_dir {
+            Some(ref dir) => dir.clone(),
+            None => return Ok(()),
+        };
+        /*
+         * The time is read first, so a change made while loading is loaded on the next refresh.
+         */
+        let modified = modified_time(&dir);
+        let definitions = ValidatorDefinitions::open(&dir)?;
+
+        let mut signers = vec![];
+        let mut graffiti = vec![];
+        for definition in definitions.0.iter().filter(|d| d.enabled) {
+            let loaded = self.definition_signers.iter().find(|(signing, signer)| {
+                *signing == definition.signing_definition
+                    && *signer.pubkey() == definition.voting_public_key
+            });
+            let signer = match loaded {
+                Some((_, signer)) => signer.clone(),
+                None => Arc::new(self.signer(definition)?),
+            };
+            signers.push((definition.signing_definition.clone(), signer));
+            /*
+             * The graffiti was checked when the definitions were read.
+             */
+            let own_graffiti = definition
+                .graffiti
+                .as_ref()
+                .and_then(|text| parse_graffiti(text).ok());
+            if let Some(own_graffiti) = own_graffiti {
+                graffiti.push((definition.voting_public_key.clone(), own_graffiti));
+            }
+        }
+        self.definition_signers = signers;
+        self.definition_graffiti = graffiti;
+        self.modified = modified;
+        Ok(())
+    }
+
+    fn signer(
+        &mut self,
+        definition: &ValidatorDefinition,
+    ) -> Result<Signer, InitializedValidatorsError> {
+        let pubkey = &definition.voting_public_key;
+        match definition.signing_definition {
+            SigningDefinition::LocalKeystore {
+                ref voting_keystore_path,
+                ref voting_keystore_password,
+            } => {
+                let keypair =
+                    decrypt_keystore(voting_keystore_path, voting_keystore_password, pubkey)?;
+                Ok(Signer::Local(keypair))
+            }
+            SigningDefinition::RemoteSigner {
+                ref url,
+                request_timeout_ms,
+            } => {
+                let client = match self.signer_client {
+                    Some(ref client) => client.clone(),
+                    None => Arc::new(SignerClient::new()?),
+                };
+                self.signer_client = Some(client.clone());
+                let timeout =
+                    request_timeout_ms.map_or(REMOTE_SIGNER_TIMEOUT, Duration::from_millis);
+                Ok(Signer::Remote(RemoteSigner::new(
+                    pubkey.clone(),
+                    url,
+                    timeout,
+                    client,
+                )?))
+            }
+        }
+    }
+}
+
+fn modified_time(validators_dir: &Path) -> Option<SystemTime> {
+    fs::metadata(validators_dir.join(CONFIG_FILENAME))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::validator_definitions::tests::{test_dir, write_keystore};
+    use super::super::validator_definitions::KeystorePassword;
+    use super::*;
+    use hex;
+
+    #[test]
+    fn test_from_dir_and_refresh() {
+        let dir = test_dir("initialized");
+        let validators_dir = dir.join("validators");
+        let key_file_keypair = Keypair::random();
+        fs::create_dir_all(&validators_dir).unwrap();
+        fs::write(
+            validators_dir.join("0.key"),
+            hex::encode(key_file_keypair.sk.as_bytes()),
+        )
+        .unwrap();
+
+        let mut validators = InitializedValidators::from_dir(&validators_dir).unwrap();
+        assert_eq!(validators.len(), 1);
+        assert!(!validators.refresh().unwrap());
+
+        let keypairs = [Keypair::random(), Keypair::random()];
+        let mut definitions = ValidatorDefinitions::default();
+        for (i, keypair) in keypairs.iter().enumerate() {
+            let path = dir.join(format!("keystore-{}.json", i));
+            write_keystore(&path, keypair, "password");
+            let password = KeystorePassword::Inline("password".to_string());
+            definitions
+                .import_keystore(&validators_dir, &path, password)
+                .unwrap();
+        }
+        definitions.save(&validators_dir).unwrap();
+        assert!(validators.refresh().unwrap());
+        assert_eq!(
+            validators
+                .signers()
+                .iter()
+                .map(|signer| signer.pubkey())
+                .collect::<Vec<_>>(),
+            vec![&key_file_keypair.pk, &keypairs[0].pk, &keypairs[1].pk]
+        );
+        assert!(!validators.refresh().unwrap());
+        assert_eq!(validators.graffiti(&keypairs[0].pk), None);
+
+        /*
+         * Graffiti is reloaded with the definitions.
+         */
+        definitions.0[0].graffiti = Some("mine".to_string());
+        std::thread::sleep(Duration::from_millis(20));
+        definitions.save(&validators_dir).unwrap();
+        assert!(validators.refresh().unwrap());
+        assert_eq!(
+            validators.graffiti(&keypairs[0].pk),
+            Some(parse_graffiti("mine").unwrap())
+        );
+        assert_eq!(validators.graffiti(&keypairs[1].pk), None);
+
+        /*
+         * A disabled validator is removed, once the change is seen.
+         */
+        definitions.0[0].enabled = false;
+        std::thread::sleep(Duration::from_millis(20));
+        definitions.save(&validators_dir).unwrap();
+        assert!(validators.refresh().unwrap());
+        assert_eq!(validators.len(), 2);
+        assert_eq!(*validators.signers()[1].pubkey(), keypairs[1].pk);
+
+        /*
+         * A validator which cannot be decrypted leaves the keypairs unchanged.
+         */
+        definitions.0[0].enabled = true;
+        definitions.0[0].signing_definition = SigningDefinition::LocalKeystore {
+            voting_keystore_path: dir.join("keystore-0.json"),
+            voting_keystore_password: KeystorePassword::Inline("wrong".to_string()),
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        definitions.save(&validators_dir).unwrap();
+        assert!(validators.refresh().is_err());
+        assert_eq!(validators.len(), 2);
+
+        /*
+         * A validator moved to a remote signer is signed for remotely.
+         */
+        definitions.0[0].signing_definition = SigningDefinition::RemoteSigner {
+            url: "http://127.0.0.1:1".to_string(),
+            request_timeout_ms: None,
+        };
+        definitions.0[1].signing_definition = SigningDefinition::RemoteSigner {
+            url: "http://127.0.0.1:1".to_string(),
+            request_timeout_ms: Some(100),
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        definitions.save(&validators_dir).unwrap();
+        assert!(validators.refresh().unwrap());
+        assert_eq!(validators.len(), 3);
+        let signers = validators.signers();
+        assert_eq!(*signers[1].pubkey(), keypairs[0].pk);
+        match **signers[2] {
+            Signer::Remote(_) => {}
+            Signer::Local(_) => panic!("Expected a remote signer"),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
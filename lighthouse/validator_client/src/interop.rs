@@ -0,0 +1,82 @@
+use super::key_derivation::{keypair_from_scalar, reduce_mod_r};
+use bls::{Keypair, SecretKey};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+/// Returns the standard interop keypair of the validator with `index`, whose secret key is the
+/// SHA-256 hash of the index as 32 little-endian bytes, read as a little-endian integer modulo the
+/// curve order.
+///
+/// These keys are public, so are only for test networks.
+pub fn interop_keypair(index: usize) -> Keypair {
+    let mut preimage = [0; 32];
+    preimage[..8].copy_from_slice(&(index as u64).to_le_bytes());
+    let mut key = Sha256::digest(&preimage).to_vec();
+    key.reverse();
+    keypair_from_scalar(&reduce_mod_r(&key))
+}
+
+pub fn interop_secret_key(index: usize) -> SecretKey {
+    interop_keypair(index).sk
+}
+
+/// Returns the interop keypairs of the validators with indices in `indices`.
+pub fn interop_keypairs(indices: Range<usize>) -> Vec<Keypair> {
+    indices.map(interop_keypair).collect()
+}
+
+/// Parses a range of validator indices of the form `i..j`, which excludes `j`.
+pub fn parse_interop_range(range: &str) -> Result<Range<usize>, String> {
+    let invalid = || format!("Expected a range of indices such as 0..8, not {}", range);
+    let mut parts = range.splitn(2, "..");
+    let (start, end) = match (parts.next(), parts.next()) {
+        (Some(start), Some(end)) => (start.trim(), end.trim()),
+        _ => return Err(invalid()),
+    };
+    match (start.parse::<usize>(), end.parse::<usize>()) {
+        (Ok(start), Ok(end)) if start < end => Ok(start..end),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::PublicKey;
+    use hex;
+
+    #[test]
+    fn test_interop_secret_key() {
+        /*
+         * The keys of the interop specification.
+         */
+        let expected = [
+            "25295f0d1d592a90b333e26e85149708208e9f8e8bc18f6c77bd62f8ad7a6866",
+            "51d0b65185db6989ab0b560d6deed19c7ead0e24b9b6372cbecb1f26bdfad000",
+            "315ed405fafe339603932eebe8dbfd650ce5dafa561f6928664c75db85f97857",
+        ];
+        for (index, key) in expected.iter().enumerate() {
+            let bytes = interop_secret_key(index).as_bytes();
+            assert_eq!(bytes[..16], [0; 16]);
+            assert_eq!(hex::encode(&bytes[16..]), *key);
+        }
+
+        let keypairs = interop_keypairs(1..3);
+        assert_eq!(keypairs.len(), 2);
+        assert_eq!(keypairs[0].sk.as_bytes(), interop_secret_key(1).as_bytes());
+        assert_eq!(
+            keypairs[1].pk,
+            PublicKey::from_secret_key(&interop_secret_key(2))
+        );
+    }
+
+    #[test]
+    fn test_parse_interop_range() {
+        assert_eq!(parse_interop_range("0..8"), Ok(0..8));
+        assert_eq!(parse_interop_range(" 4 .. 6"), Ok(4..6));
+        assert!(parse_interop_range("8").is_err());
+        assert!(parse_interop_range("4..4").is_err());
+        assert!(parse_interop_range("a..4").is_err());
+        assert!(parse_interop_range("0..-1").is_err());
+    }
+}
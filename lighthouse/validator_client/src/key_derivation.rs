@@ -0,0 +1,231 @@
+use bls::{Keypair, PublicKey, SecretKey};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+
+/// The order of the BLS12-381 curve, big-endian.
+pub const CURVE_ORDER: [u8; 32] = [
+    0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08, 0x09, 0xa1, 0xd8, 0x05,
+    0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+];
+/// The purpose of EIP-2334 paths.
+pub const PURPOSE: u32 = 12_381;
+/// The coin type of EIP-2334 paths.
+pub const COIN_TYPE: u32 = 3600;
+
+/// The length of a serialized secret key, which is big-endian and zero-padded.
+const SECRET_KEY_BYTES: usize = 48;
+/// The length of the key material reduced to each secret key.
+const OKM_BYTES: usize = 48;
+const KEYGEN_SALT: &[u8] = b"BLS-SIG-KEYGEN-SALT-";
+/// The number of 32-byte chunks of a Lamport secret key.
+const LAMPORT_CHUNKS: usize = 255;
+
+/// The smallest seed from which keys are derived.
+pub const MIN_SEED_BYTES: usize = 32;
+
+#[derive(Debug, PartialEq)]
+pub enum KeyDerivationError {
+    SeedTooShort,
+    /// The path is not of the form `m/<index>/<index>/...`.
+    InvalidPath(String),
+    InvalidMnemonic(String),
+}
+
+/// Returns the master secret key of `seed`, as per EIP-2333.
+pub fn derive_master_sk(seed: &[u8]) -> Result<[u8; 32], KeyDerivationError> {
+    if seed.len() < MIN_SEED_BYTES {
+        return Err(KeyDerivationError::SeedTooShort);
+    }
+    Ok(hkdf_mod_r(seed))
+}
+
+/// Returns the child with `index` of the secret key `parent`, as per EIP-2333.
+///
+/// The child is derived from the Lamport public key of the parent, so a child key does not
+/// reveal its parent.
+pub fn derive_child_sk(parent: &[u8; 32], index: u32) -> [u8; 32] {
+    let salt = index.to_be_bytes();
+    let not_parent: Vec<u8> = parent.iter().map(|byte| !byte).collect();
+    let mut lamport_pk = Sha256::new();
+    for ikm in &[&parent[..], &not_parent[..]] {
+        let lamport_sk = hkdf_expand(&hkdf_extract(&salt, ikm), &[], LAMPORT_CHUNKS * 32);
+        for chunk in lamport_sk.chunks(32) {
+            lamport_pk.update(Sha256::digest(chunk));
+        }
+    }
+    hkdf_mod_r(&lamport_pk.finalize())
+}
+
+/// Returns the secret key at the EIP-2334 `path` of `seed`, e.g. `m/12381/3600/0/0/0`.
+pub fn derive_path(seed: &[u8], path: &str) -> Result<[u8; 32], KeyDerivationError> {
+    let invalid = || KeyDerivationError::InvalidPath(path.to_string());
+    let mut nodes = path.split('/');
+    if nodes.next() != Some("m") {
+        return Err(invalid());
+    }
+    let mut sk = derive_master_sk(seed)?;
+    for node in nodes {
+        let index = node.parse::<u32>().map_err(|_| invalid())?;
+        sk = derive_child_sk(&sk, index);
+    }
+    Ok(sk)
+}
+
+/// Returns the EIP-2334 path of the withdrawal key of the validator with `index`.
+pub fn withdrawal_key_path(index: u32) -> String {
+    format!("m/{}/{}/{}/0", PURPOSE, COIN_TYPE, index)
+}
+
+/// Returns the EIP-2334 path of the voting key of the validator with `index`.
+pub fn voting_key_path(index: u32) -> String {
+    format!("{}/0", withdrawal_key_path(index))
+}
+
+/// Returns the keypair of the secret key `sk`, a big-endian scalar less than the curve order.
+pub fn keypair_from_scalar(sk: &[u8; 32]) -> Keypair {
+    let mut bytes = [0; SECRET_KEY_BYTES];
+    bytes[SECRET_KEY_BYTES - 32..].copy_from_slice(sk);
+    let sk = SecretKey::from_bytes(&bytes).expect("Reduced scalar is a valid secret key");
+    Keypair {
+        pk: PublicKey::from_secret_key(&sk),
+        sk,
+    }
+}
+
+/// Returns the big-endian integer `bytes` modulo the curve order.
+pub fn reduce_mod_r(bytes: &[u8]) -> [u8; 32] {
+    let mut remainder = [0; 32];
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            /*
+             * The remainder is less than the order, which is less than 2^255, so doubling it
+             * does not overflow and one subtraction reduces it again.
+             */
+            let mut carry = (byte >> bit) & 1;
+            for digit in remainder.iter_mut().rev() {
+                let shifted = (u16::from(*digit) << 1) | u16::from(carry);
+                *digit = shifted as u8;
+                carry = (shifted >> 8) as u8;
+            }
+            if remainder >= CURVE_ORDER {
+                subtract(&mut remainder, &CURVE_ORDER);
+            }
+        }
+    }
+    remainder
+}
+
+fn subtract(a: &mut [u8; 32], b: &[u8; 32]) {
+    let mut borrow = 0;
+    for i in (0..32).rev() {
+        let difference = i16::from(a[i]) - i16::from(b[i]) - borrow;
+        borrow = if difference < 0 { 1 } else { 0 };
+        a[i] = (difference + (borrow << 8)) as u8;
+    }
+}
+
+/// Derives a non-zero secret key from the key material `ikm`.
+fn hkdf_mod_r(ikm: &[u8]) -> [u8; 32] {
+    let mut salt = KEYGEN_SALT.to_vec();
+    let mut ikm = ikm.to_vec();
+    ikm.push(0);
+    loop {
+        salt = Sha256::digest(&salt).to_vec();
+        let okm = hkdf_expand(&hkdf_extract(&salt, &ikm), &[0, OKM_BYTES as u8], OKM_BYTES);
+        let sk = reduce_mod_r(&okm);
+        if sk != [0; 32] {
+            return sk;
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    hmac_sha256(salt, &[ikm])
+}
+
+/// Expands `prk` to `length` bytes, at most 255 blocks of 32 bytes.
+fn hkdf_expand(prk: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+    let mut okm = Vec::with_capacity(length);
+    let mut block = vec![];
+    for counter in 1..=255u8 {
+        if okm.len() >= length {
+            break;
+        }
+        block = hmac_sha256(prk, &[&block, info, &[counter]]);
+        okm.extend_from_slice(&block);
+    }
+    okm.truncate(length);
+    okm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex;
+
+    /// The seed of the first test case of EIP-2333, that of the BIP-39 mnemonic of eleven
+    /// "abandon" and "about", with the passphrase "TREZOR".
+    const SEED: &str = "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04";
+
+    #[test]
+    fn test_derive_master_and_child() {
+        let seed = hex::decode(SEED).unwrap();
+        let master = derive_master_sk(&seed).unwrap();
+        assert_eq!(
+            hex::encode(master),
+            "0d7359d57963ab8fbbde1852dcf553fedbc31f464d80ee7d40ae683122b45070"
+        );
+        assert_eq!(
+            hex::encode(derive_child_sk(&master, 0)),
+            "2d18bd6c14e6d15bf8b5085c9b74f3daae3b03cc2014770a599d8c1539e50f8e"
+        );
+        assert_eq!(
+            derive_master_sk(&seed[..31]),
+            Err(KeyDerivationError::SeedTooShort)
+        );
+    }
+
+    #[test]
+    fn test_derive_path() {
+        let seed = hex::decode(SEED).unwrap();
+        assert_eq!(voting_key_path(0), "m/12381/3600/0/0/0");
+        assert_eq!(withdrawal_key_path(7), "m/12381/3600/7/0");
+        assert_eq!(
+            hex::encode(derive_path(&seed, &voting_key_path(0)).unwrap()),
+            "032e6c3c7359223e127e9479afc521c4342f8903bc29ae01b671bcbcc98be0f6"
+        );
+        assert_eq!(
+            derive_path(&seed, "m").unwrap(),
+            derive_master_sk(&seed).unwrap()
+        );
+        for path in &["12381/0", "m/12381/x", "m//0"] {
+            match derive_path(&seed, path) {
+                Err(KeyDerivationError::InvalidPath(_)) => {}
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_reduce_mod_r() {
+        assert_eq!(reduce_mod_r(&CURVE_ORDER), [0; 32]);
+        let mut order_plus_one = CURVE_ORDER;
+        order_plus_one[31] += 1;
+        let mut one = [0; 32];
+        one[31] = 1;
+        assert_eq!(reduce_mod_r(&order_plus_one), one);
+        assert_eq!(reduce_mod_r(&one), one);
+
+        let keypair = keypair_from_scalar(&one);
+        assert_eq!(keypair.sk.as_bytes()[..47], [0; 47][..]);
+        assert_eq!(keypair.sk.as_bytes()[47], 1);
+    }
+}
@@ -0,0 +1,78 @@
+use bls::{Keypair, PublicKey, SecretKey};
+use hex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The extension of files holding a hex-encoded secret key.
+pub const KEY_FILE_EXTENSION: &str = "key";
+
+#[derive(Debug)]
+pub enum KeyError {
+    Io(io::Error),
+    /// The file does not hold a valid secret key.
+    InvalidKey(PathBuf),
+}
+
+impl From<io::Error> for KeyError {
+    fn from(e: io::Error) -> KeyError {
+        KeyError::Io(e)
+    }
+}
+
+/// Loads the keypair of every `.key` file in `dir`, in order of file name.
+pub fn load_keypairs(dir: &Path) -> Result<Vec<Keypair>, KeyError> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(KEY_FILE_EXTENSION) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut keypairs = vec![];
+    for path in paths {
+        let contents = fs::read_to_string(&path)?;
+        let contents = contents.trim();
+        let contents = contents.trim_start_matches("0x");
+        let sk = hex::decode(contents)
+            .ok()
+            .and_then(|bytes| SecretKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| KeyError::InvalidKey(path.clone()))?;
+        keypairs.push(Keypair {
+            pk: PublicKey::from_secret_key(&sk),
+            sk,
+        });
+    }
+    Ok(keypairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_keypairs() {
+        let dir = std::env::temp_dir().join(format!("validator_keys_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let keypairs = [Keypair::random(), Keypair::random()];
+        for (i, keypair) in keypairs.iter().enumerate() {
+            let contents = format!("0x{}\n", hex::encode(keypair.sk.as_bytes()));
+            fs::write(dir.join(format!("{}.key", i)), contents).unwrap();
+        }
+        fs::write(dir.join("README"), "not a key").unwrap();
+
+        let loaded = load_keypairs(&dir).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].pk, keypairs[0].pk);
+        assert_eq!(loaded[1].pk, keypairs[1].pk);
+
+        fs::write(dir.join("2.key"), "0x1234").unwrap();
+        match load_keypairs(&dir) {
+            Err(KeyError::InvalidKey(path)) => assert_eq!(path, dir.join("2.key")),
+            other => panic!("Unexpected result: {:?}", other.map(|k| k.len())),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,395 @@
+use aes_ctr::cipher::stream::{NewStreamCipher, SyncStreamCipher};
+use aes_ctr::Aes128Ctr;
+use bls::{Keypair, PublicKey, SecretKey};
+use hex;
+use hmac::Hmac;
+use pbkdf2::pbkdf2;
+use rand::{thread_rng, Rng};
+use scrypt::{scrypt, ScryptParams};
+use serde_json::{self, Value};
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use unicode_normalization::UnicodeNormalization;
+
+/// The version of the EIP-2335 keystore format.
+pub const KEYSTORE_VERSION: u64 = 4;
+/// The length of the key derived from the password.
+const DKLEN: u32 = 32;
+/// The scrypt cost recommended by EIP-2335.
+const SCRYPT_N: u32 = 262_144;
+
+#[derive(Debug, PartialEq)]
+pub enum KeystoreError {
+    Io(String),
+    InvalidJson(String),
+    /// The keystore uses a function which is not supported.
+    UnsupportedFunction(String),
+    InvalidParams(String),
+    /// The checksum does not match, so the password is incorrect.
+    IncorrectPassword,
+    InvalidSecretKey,
+    /// The secret key does not match the public key of the keystore.
+    PublicKeyMismatch,
+}
+
+/// The function which derives the decryption key from a password, with its parameters.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Kdf {
+    Scrypt {
+        n: u32,
+        r: u32,
+        p: u32,
+        salt: Vec<u8>,
+    },
+    /// PBKDF2 with HMAC-SHA256.
+    Pbkdf2 { c: u32, salt: Vec<u8> },
+}
+
+impl Kdf {
+    /// Returns scrypt with the parameters recommended by EIP-2335 and a random salt.
+    pub fn scrypt() -> Self {
+        let salt: [u8; 32] = thread_rng().gen();
+        Kdf::Scrypt {
+            n: SCRYPT_N,
+            r: 8,
+            p: 1,
+            salt: salt.to_vec(),
+        }
+    }
+}
+
+/// A secret key encrypted with a password in the EIP-2335 format.
+///
+/// The secret key is encrypted with AES-128-CTR by the first half of the key derived from the
+/// password, and the second half is checked against a SHA-256 checksum before decryption.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Keystore {
+    pub kdf: Kdf,
+    checksum: Vec<u8>,
+    iv: Vec<u8>,
+    cipher_message: Vec<u8>,
+    pub pubkey: PublicKey,
+    /// The EIP-2334 derivation path of the key, which may be empty.
+    pub path: String,
+    pub uuid: String,
+    pub description: String,
+}
+
+impl Keystore {
+    /// Encrypts the secret key of `keypair` with `password`.
+    pub fn encrypt(
+        keypair: &Keypair,
+        password: &str,
+        kdf: Kdf,
+        iv: &[u8],
+        uuid: &str,
+    ) -> Result<Self, KeystoreError> {
+        if iv.len() != 16 {
+            return Err(KeystoreError::InvalidParams("iv".to_string()));
+        }
+        let key = derive_key(password, &kdf)?;
+        let mut cipher_message = keypair.sk.as_bytes();
+        aes_128_ctr(&key[..16], iv, &mut cipher_message);
+        Ok(Self {
+            checksum: checksum(&key, &cipher_message),
+            kdf,
+            iv: iv.to_vec(),
+            cipher_message,
+            pubkey: keypair.pk.clone(),
+            path: String::new(),
+            uuid: uuid.to_string(),
+            description: String::new(),
+        })
+    }
+
+    /// Decrypts the keypair with `password`.
+    pub fn decrypt(&self, password: &str) -> Result<Keypair, KeystoreError> {
+        let key = derive_key(password, &self.kdf)?;
+        if checksum(&key, &self.cipher_message) != self.checksum {
+            return Err(KeystoreError::IncorrectPassword);
+        }
+        let mut secret = self.cipher_message.clone();
+        aes_128_ctr(&key[..16], &self.iv, &mut secret);
+        let sk = SecretKey::from_bytes(&secret).map_err(|_| KeystoreError::InvalidSecretKey)?;
+        let pk = PublicKey::from_secret_key(&sk);
+        if pk != self.pubkey {
+            return Err(KeystoreError::PublicKeyMismatch);
+        }
+        Ok(Keypair { sk, pk })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, KeystoreError> {
+        let bytes = fs::read(path).map_err(|e| KeystoreError::Io(format!("{}", e)))?;
+        let value: Value = serde_json::from_slice(&bytes)
+            .map_err(|_| KeystoreError::InvalidJson("Invalid JSON".to_string()))?;
+        Self::from_json(&value)
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self, KeystoreError> {
+        if value["version"].as_u64() != Some(KEYSTORE_VERSION) {
+            return Err(invalid("version"));
+        }
+        let crypto = &value["crypto"];
+        let kdf = &crypto["kdf"];
+        let params = &kdf["params"];
+        if params["dklen"].as_u64() != Some(u64::from(DKLEN)) {
+            return Err(KeystoreError::InvalidParams("dklen".to_string()));
+        }
+        let kdf = match kdf["function"].as_str() {
+            Some("scrypt") => Kdf::Scrypt {
+                n: u32_field(params, "n")?,
+                r: u32_field(params, "r")?,
+                p: u32_field(params, "p")?,
+                salt: hex_field(params, "salt")?,
+            },
+            Some("pbkdf2") => {
+                if params["prf"].as_str() != Some("hmac-sha256") {
+                    return Err(KeystoreError::UnsupportedFunction(
+                        params["prf"].to_string(),
+                    ));
+                }
+                Kdf::Pbkdf2 {
+                    c: u32_field(params, "c")?,
+                    salt: hex_field(params, "salt")?,
+                }
+            }
+            _ => {
+                return Err(KeystoreError::UnsupportedFunction(
+                    kdf["function"].to_string(),
+                ))
+            }
+        };
+        if crypto["checksum"]["function"].as_str() != Some("sha256") {
+            return Err(KeystoreError::UnsupportedFunction(
+                crypto["checksum"]["function"].to_string(),
+            ));
+        }
+        let cipher = &crypto["cipher"];
+        if cipher["function"].as_str() != Some("aes-128-ctr") {
+            return Err(KeystoreError::UnsupportedFunction(
+                cipher["function"].to_string(),
+            ));
+        }
+        let iv = hex_field(&cipher["params"], "iv")?;
+        if iv.len() != 16 {
+            return Err(KeystoreError::InvalidParams("iv".to_string()));
+        }
+        let pubkey = hex_field(value, "pubkey")
+            .ok()
+            .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| invalid("pubkey"))?;
+
+        Ok(Self {
+            kdf,
+            checksum: hex_field(&crypto["checksum"], "message")?,
+            iv,
+            cipher_message: hex_field(cipher, "message")?,
+            pubkey,
+            path: value["path"].as_str().unwrap_or("").to_string(),
+            uuid: value["uuid"]
+                .as_str()
+                .ok_or_else(|| invalid("uuid"))?
+                .to_string(),
+            description: value["description"].as_str().unwrap_or("").to_string(),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let kdf = match self.kdf {
+            Kdf::Scrypt { n, r, p, ref salt } => json!({
+                "function": "scrypt",
+                "params": { "dklen": DKLEN, "n": n, "r": r, "p": p, "salt": hex::encode(salt) },
+                "message": "",
+            }),
+            Kdf::Pbkdf2 { c, ref salt } => json!({
+                "function": "pbkdf2",
+                "params": { "dklen": DKLEN, "c": c, "prf": "hmac-sha256", "salt": hex::encode(salt) },
+                "message": "",
+            }),
+        };
+        json!({
+            "crypto": {
+                "kdf": kdf,
+                "checksum": {
+                    "function": "sha256",
+                    "params": {},
+                    "message": hex::encode(&self.checksum),
+                },
+                "cipher": {
+                    "function": "aes-128-ctr",
+                    "params": { "iv": hex::encode(&self.iv) },
+                    "message": hex::encode(&self.cipher_message),
+                },
+            },
+            "description": self.description,
+            "pubkey": hex::encode(self.pubkey.as_bytes()),
+            "path": self.path,
+            "uuid": self.uuid,
+            "version": KEYSTORE_VERSION,
+        })
+    }
+}
+
+/// Returns a random version 4 UUID, which identifies a new keystore.
+pub fn random_uuid() -> String {
+    let mut bytes: [u8; 16] = thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Derives the key of `kdf` from `password`, after normalising it to NFKD and removing control
+/// codes as EIP-2335 requires.
+fn derive_key(password: &str, kdf: &Kdf) -> Result<Vec<u8>, KeystoreError> {
+    let password: String = password.nfkd().filter(|c| !c.is_control()).collect();
+    let mut key = vec![0; DKLEN as usize];
+    match kdf {
+        Kdf::Scrypt { n, r, p, salt } => {
+            if *n < 2 || !n.is_power_of_two() {
+                return Err(KeystoreError::InvalidParams("n".to_string()));
+            }
+            let log_n = n.trailing_zeros() as u8;
+            let params = ScryptParams::new(log_n, *r, *p)
+                .map_err(|_| KeystoreError::InvalidParams("scrypt".to_string()))?;
+            scrypt(password.as_bytes(), salt, &params, &mut key)
+                .map_err(|_| KeystoreError::InvalidParams("scrypt".to_string()))?;
+        }
+        Kdf::Pbkdf2 { c, salt } => pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, *c, &mut key),
+    }
+    Ok(key)
+}
+
+fn checksum(key: &[u8], cipher_message: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(&key[16..32]);
+    hasher.update(cipher_message);
+    hasher.finalize().to_vec()
+}
+
+/// Encrypts or decrypts `data` in place.
+fn aes_128_ctr(key: &[u8], iv: &[u8], data: &mut [u8]) {
+    let mut cipher = Aes128Ctr::new_var(key, iv).expect("Key and IV are 16 bytes");
+    cipher.apply_keystream(data);
+}
+
+fn invalid(name: &str) -> KeystoreError {
+    KeystoreError::InvalidJson(format!("Invalid field: {}", name))
+}
+
+fn u32_field(value: &Value, name: &str) -> Result<u32, KeystoreError> {
+    value[name]
+        .as_u64()
+        .and_then(|n| u32::try_from(n).ok())
+        .ok_or_else(|| KeystoreError::InvalidParams(name.to_string()))
+}
+
+/// Reads a hex field, which has no `0x` prefix in EIP-2335.
+fn hex_field(value: &Value, name: &str) -> Result<Vec<u8>, KeystoreError> {
+    value[name]
+        .as_str()
+        .and_then(|s| hex::decode(s).ok())
+        .ok_or_else(|| invalid(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pbkdf2_kdf() -> Kdf {
+        Kdf::Pbkdf2 {
+            c: 16,
+            salt: vec![1; 32],
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let keypair = Keypair::random();
+        let scrypt_kdf = Kdf::Scrypt {
+            n: 16,
+            r: 8,
+            p: 1,
+            salt: vec![2; 32],
+        };
+        for kdf in &[pbkdf2_kdf(), scrypt_kdf] {
+            let keystore =
+                Keystore::encrypt(&keypair, "password", kdf.clone(), &[3; 16], "id").unwrap();
+            assert_ne!(keystore.cipher_message, keypair.sk.as_bytes());
+            assert_eq!(keystore.decrypt("password").unwrap().pk, keypair.pk);
+            assert_eq!(
+                keystore.decrypt("Password"),
+                Err(KeystoreError::IncorrectPassword)
+            );
+            /*
+             * Control codes are removed from passwords.
+             */
+            assert_eq!(keystore.decrypt("pass\u{7f}word\n").unwrap().pk, keypair.pk);
+        }
+
+        let kdf = Kdf::Scrypt {
+            n: 15,
+            r: 8,
+            p: 1,
+            salt: vec![],
+        };
+        assert!(Keystore::encrypt(&keypair, "password", kdf, &[3; 16], "id").is_err());
+        assert!(Keystore::encrypt(&keypair, "password", pbkdf2_kdf(), &[3; 8], "id").is_err());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let keypair = Keypair::random();
+        let mut keystore =
+            Keystore::encrypt(&keypair, "password", pbkdf2_kdf(), &[3; 16], "id").unwrap();
+        keystore.path = "m/12381/3600/0/0/0".to_string();
+        let value = keystore.to_json();
+        assert_eq!(value["version"], 4);
+        assert_eq!(value["crypto"]["kdf"]["params"]["prf"], "hmac-sha256");
+        assert_eq!(Keystore::from_json(&value), Ok(keystore.clone()));
+
+        let mut other_pubkey = value.clone();
+        other_pubkey["pubkey"] = json!(hex::encode(Keypair::random().pk.as_bytes()));
+        let other_pubkey = Keystore::from_json(&other_pubkey).unwrap();
+        assert_eq!(
+            other_pubkey.decrypt("password"),
+            Err(KeystoreError::PublicKeyMismatch)
+        );
+
+        let mut unsupported = value.clone();
+        unsupported["crypto"]["cipher"]["function"] = json!("aes-256-gcm");
+        match Keystore::from_json(&unsupported) {
+            Err(KeystoreError::UnsupportedFunction(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        let mut old_version = value.clone();
+        old_version["version"] = json!(3);
+        assert!(Keystore::from_json(&old_version).is_err());
+    }
+
+    #[test]
+    fn test_random_uuid() {
+        let uuid = random_uuid();
+        let groups: Vec<usize> = uuid.split('-').map(str::len).collect();
+        assert_eq!(groups, vec![8, 4, 4, 4, 12]);
+        assert_eq!(&uuid[14..15], "4");
+        assert_ne!(uuid, random_uuid());
+
+        match (Kdf::scrypt(), Kdf::scrypt()) {
+            (Kdf::Scrypt { n, salt, .. }, Kdf::Scrypt { salt: other, .. }) => {
+                assert_eq!(n, SCRYPT_N);
+                assert_ne!(salt, other);
+            }
+            other => panic!("Unexpected KDFs: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! A validator client, which performs the duties of its validators using a beacon node's HTTP API.
+extern crate aes_ctr;
+extern crate bip39;
+extern crate bls;
+extern crate db;
+extern crate futures;
+extern crate hashing;
+extern crate hex;
+extern crate hmac;
+extern crate hyper;
+extern crate hyper_tls;
+#[macro_use]
+extern crate lazy_static;
+extern crate lighthouse_metrics;
+extern crate pbkdf2;
+extern crate process_metrics;
+extern crate rand;
+extern crate scrypt;
+#[macro_use]
+extern crate serde_json;
+extern crate sha2;
+#[macro_use]
+extern crate slog;
+extern crate slot_clock;
+extern crate ssz;
+extern crate tokio;
+extern crate types;
+extern crate unicode_normalization;
+extern crate yaml_rust;
+
+#[cfg(test)]
+extern crate beacon_node;
+#[cfg(test)]
+extern crate http_api;
+#[cfg(test)]
+extern crate task_executor;
+
+mod api_client;
+mod attester;
+mod beacon_node_fallback;
+mod config;
+mod deposit;
+mod duties;
+mod error;
+mod graffiti_file;
+mod initialized_validators;
+mod interop;
+mod key_derivation;
+mod keys;
+mod keystore;
+mod metrics;
+mod metrics_server;
+mod performance;
+mod proposer;
+mod runtime;
+mod service;
+mod signer;
+mod signing;
+mod slashing_protection;
+mod validator_definitions;
+mod validator_keys;
+
+pub use api_client::{
+    ApiClientError, AttesterDuty, BeaconNodeClient, Duties, Genesis, ProposerDuty, SyncStatus,
+};
+pub use beacon_node_fallback::{BeaconNodeFallback, Health};
+pub use config::ValidatorClientConfig;
+pub use deposit::{
+    deposit_domain, withdrawal_credentials, DepositData, DEPOSIT_CLI_VERSION, MAX_EFFECTIVE_BALANCE,
+};
+pub use duties::{CycleDuties, DutiesService};
+pub use error::DutyError;
+pub use graffiti_file::{parse_graffiti, GraffitiError, GraffitiFile, GRAFFITI_BYTES};
+pub use initialized_validators::{InitializedValidators, InitializedValidatorsError};
+pub use interop::{interop_keypair, interop_keypairs, interop_secret_key, parse_interop_range};
+pub use key_derivation::{
+    derive_path, keypair_from_scalar, voting_key_path, withdrawal_key_path, KeyDerivationError,
+};
+pub use keys::{load_keypairs, KeyError};
+pub use keystore::{random_uuid, Kdf, Keystore, KeystoreError};
+pub use metrics_server::{MetricsServer, MetricsServerError};
+pub use performance::{Duty, DutyCounts, Performance, ValidatorPerformance};
+pub use service::{ValidatorClient, ValidatorClientError};
+pub use signer::{RemoteSigner, SignableMessage, Signer, SignerClient, REMOTE_SIGNER_TIMEOUT};
+pub use slashing_protection::{
+    NotSafe, Safe, SignedAttestation, SignedBlock, SlashingProtection, SlashingProtectionError,
+    INTERCHANGE_FORMAT_VERSION,
+};
+pub use validator_definitions::{
+    decrypt_keystore, find_keystores, KeystorePassword, SigningDefinition, ValidatorDefinition,
+    ValidatorDefinitions, ValidatorDefinitionsError,
+};
+pub use validator_keys::{generate_mnemonic, mnemonic_seed, ValidatorKeys};
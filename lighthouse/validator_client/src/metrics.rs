@@ -0,0 +1,94 @@
+use lighthouse_metrics::{
+    try_create_histogram, try_create_int_counter, try_create_int_gauge, Histogram, IntCounter,
+    IntGauge, Result,
+};
+
+lazy_static! {
+    /*
+     * Validators and beacon nodes
+     */
+    pub static ref ACTIVE_VALIDATORS: Result<IntGauge> = try_create_int_gauge(
+        "vc_active_validators",
+        "Count of validators known to the beacon node and not watched for doppelgangers"
+    );
+    pub static ref AVAILABLE_BEACON_NODES: Result<IntGauge> = try_create_int_gauge(
+        "vc_available_beacon_nodes",
+        "Count of beacon nodes which are synced or syncing"
+    );
+    /*
+     * Block proposals
+     */
+    pub static ref BLOCK_PROPOSALS: Result<IntCounter> = try_create_int_counter(
+        "vc_block_proposals_total",
+        "Count of blocks proposed and published"
+    );
+    pub static ref BLOCK_PROPOSAL_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "vc_block_proposal_failures_total",
+        "Count of block proposals which failed"
+    );
+    pub static ref BLOCK_PROPOSALS_MISSED: Result<IntCounter> = try_create_int_counter(
+        "vc_block_proposals_missed_total",
+        "Count of block proposals which failed or were refused by slashing protection"
+    );
+    pub static ref BLOCK_PRODUCE_TIMES: Result<Histogram> = try_create_histogram(
+        "vc_block_produce_seconds",
+        "Time taken by the beacon node to produce a block"
+    );
+    pub static ref BLOCK_SIGN_TIMES: Result<Histogram> =
+        try_create_histogram("vc_block_sign_seconds", "Time taken to sign a block");
+    pub static ref BLOCK_PUBLISH_TIMES: Result<Histogram> = try_create_histogram(
+        "vc_block_publish_seconds",
+        "Time taken by the beacon node to import and publish a block"
+    );
+    /*
+     * Attestations
+     */
+    pub static ref ATTESTATIONS: Result<IntCounter> = try_create_int_counter(
+        "vc_attestations_total",
+        "Count of attestations produced and published"
+    );
+    pub static ref ATTESTATION_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "vc_attestation_failures_total",
+        "Count of attestations which failed"
+    );
+    pub static ref ATTESTATIONS_MISSED: Result<IntCounter> = try_create_int_counter(
+        "vc_attestations_missed_total",
+        "Count of attestations which failed or were refused by slashing protection"
+    );
+    pub static ref ATTESTATION_PRODUCE_TIMES: Result<Histogram> = try_create_histogram(
+        "vc_attestation_produce_seconds",
+        "Time taken by the beacon node to produce attestation data"
+    );
+    pub static ref ATTESTATION_SIGN_TIMES: Result<Histogram> =
+        try_create_histogram("vc_attestation_sign_seconds", "Time taken to sign an attestation");
+    pub static ref ATTESTATION_PUBLISH_TIMES: Result<Histogram> = try_create_histogram(
+        "vc_attestation_publish_seconds",
+        "Time taken by the beacon node to pool and publish an attestation"
+    );
+    /*
+     * Aggregates
+     */
+    pub static ref AGGREGATES: Result<IntCounter> = try_create_int_counter(
+        "vc_aggregates_total",
+        "Count of aggregates produced and published"
+    );
+    pub static ref AGGREGATE_FAILURES: Result<IntCounter> = try_create_int_counter(
+        "vc_aggregate_failures_total",
+        "Count of aggregations which failed"
+    );
+    pub static ref AGGREGATE_SIGN_TIMES: Result<Histogram> =
+        try_create_histogram("vc_aggregate_sign_seconds", "Time taken to sign an aggregate");
+    pub static ref AGGREGATE_PUBLISH_TIMES: Result<Histogram> = try_create_histogram(
+        "vc_aggregate_publish_seconds",
+        "Time taken by the beacon node to pool and publish an aggregate"
+    );
+    /*
+     * Tokio runtimes
+     */
+    pub static ref TOKIO_RUNTIMES: Result<IntCounter> =
+        try_create_int_counter("vc_tokio_runtimes_total", "Count of tokio runtimes started");
+    pub static ref TOKIO_WORKER_THREADS: Result<IntGauge> = try_create_int_gauge(
+        "vc_tokio_worker_threads",
+        "Count of running worker threads of the tokio runtimes"
+    );
+}
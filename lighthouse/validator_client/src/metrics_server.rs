@@ -0,0 +1,194 @@
+use super::beacon_node_fallback::{BeaconNodeFallback, Health};
+use super::performance::Performance;
+use futures::sync::oneshot;
+use futures::Future;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::service_fn_ok;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use lighthouse_metrics::{encode_text, TEXT_CONTENT_TYPE};
+use process_metrics;
+use serde_json::Value;
+use slog::Logger;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+#[derive(Debug)]
+pub enum MetricsServerError {
+    Hyper(hyper::Error),
+}
+
+impl From<hyper::Error> for MetricsServerError {
+    fn from(e: hyper::Error) -> MetricsServerError {
+        MetricsServerError::Hyper(e)
+    }
+}
+
+/// Serves the metrics of the validator client, and a summary of its validators' performance, on
+/// a background thread, so they may be watched without the beacon node.
+///
+/// The server stops when dropped.
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    pub fn start(
+        addr: &SocketAddr,
+        performance: Arc<Performance>,
+        beacon_nodes: Arc<BeaconNodeFallback>,
+        log: Logger,
+    ) -> Result<Self, MetricsServerError> {
+        let new_service = move || {
+            let (performance, beacon_nodes) = (performance.clone(), beacon_nodes.clone());
+            service_fn_ok(move |req: Request<Body>| {
+                handle(&performance, &beacon_nodes, &req).map(Body::from)
+            })
+        };
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let server = Server::try_bind(addr)?.serve(new_service);
+        let local_addr = server.local_addr();
+        let server = {
+            let log = log.clone();
+            server
+                .with_graceful_shutdown(shutdown_rx)
+                .map_err(move |e| error!(log, "Metrics server failed"; "error" => format!("{}", e)))
+        };
+        if !local_addr.ip().is_loopback() {
+            warn!(log, "Metrics server exposed beyond localhost"; "address" => format!("{}", local_addr));
+        }
+        info!(log, "Metrics server started"; "address" => format!("{}", local_addr));
+        let handle = thread::spawn(move || hyper::rt::run(server));
+
+        Ok(Self {
+            local_addr,
+            shutdown: Some(shutdown),
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the address on which the server is listening.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Serves `GET /metrics`, for Prometheus to scrape, and `GET /summary`, the performance of each
+/// validator with duties and the health of each beacon node as JSON.
+fn handle<T>(
+    performance: &Performance,
+    beacon_nodes: &BeaconNodeFallback,
+    req: &Request<T>,
+) -> Response<Vec<u8>> {
+    let path = req.uri().path().trim_matches('/');
+    let mut response = Response::builder();
+    let response = match (req.method(), path) {
+        (&Method::GET, "metrics") => {
+            process_metrics::scrape();
+            response
+                .header(CONTENT_TYPE, TEXT_CONTENT_TYPE)
+                .body(encode_text())
+        }
+        (&Method::GET, "summary") => {
+            let summary = json!({
+                "data": {
+                    "validators": performance.to_json(),
+                    "beacon_nodes": beacon_nodes
+                        .health()
+                        .into_iter()
+                        .map(|(url, health)| health_json(&url, health))
+                        .collect::<Vec<Value>>(),
+                }
+            });
+            response
+                .header(CONTENT_TYPE, "application/json")
+                .body(summary.to_string().into_bytes())
+        }
+        _ => response
+            .status(StatusCode::NOT_FOUND)
+            .body(b"Not found".to_vec()),
+    };
+    response.expect("Metrics response is valid")
+}
+
+fn health_json(url: &str, health: Health) -> Value {
+    let (status, sync_distance) = match health {
+        Health::Synced => ("synced", Some(0)),
+        Health::Syncing(distance) => ("syncing", Some(distance)),
+        Health::Offline => ("offline", None),
+        Health::WrongChain => ("wrong_chain", None),
+    };
+    json!({
+        "url": url,
+        "health": status,
+        "sync_distance": sync_distance.map(|distance| distance.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::api_client::BeaconNodeClient;
+    use super::super::performance::Duty;
+    use super::*;
+    use bls::Keypair;
+    use slog::Discard;
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, TcpStream};
+    use std::time::Duration;
+    use types::Hash256;
+
+    fn get(server: &MetricsServer, path: &str) -> String {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        );
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_metrics_server() {
+        let log = Logger::root(Discard, o!());
+        let offline = BeaconNodeClient::new("http://127.0.0.1:1", Duration::from_secs(1)).unwrap();
+        let beacon_nodes = Arc::new(BeaconNodeFallback::new(
+            vec![offline],
+            Hash256::zero(),
+            log.clone(),
+        ));
+        let performance = Arc::new(Performance::new());
+        performance.record(2, &Keypair::random().pk, Duty::Proposal, 5, false);
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+        let server = MetricsServer::start(&addr, performance, beacon_nodes, log).unwrap();
+
+        let response = get(&server, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(TEXT_CONTENT_TYPE));
+        assert!(response.contains("process_resident_memory_bytes"));
+        assert!(response.contains("vc_tokio_worker_threads"));
+
+        let response = get(&server, "/summary");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"index\":\"2\""));
+        assert!(response.contains("\"last_missed_slot\":\"5\""));
+        assert!(response.contains("\"health\":\"offline\""));
+
+        assert!(get(&server, "/eth/v1/node/health").starts_with("HTTP/1.1 404 Not Found"));
+    }
+}
@@ -0,0 +1,136 @@
+use bls::PublicKey;
+use hex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// A kind of duty performed by a validator.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Duty {
+    Proposal,
+    Attestation,
+    Aggregation,
+}
+
+/// The number of a validator's duties of one kind which were performed and missed.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct DutyCounts {
+    pub performed: u64,
+    /// Duties which failed, or which slashing protection refused.
+    pub missed: u64,
+    /// The slot of the latest duty missed.
+    pub last_missed_slot: Option<u64>,
+}
+
+impl DutyCounts {
+    fn to_json(self) -> Value {
+        json!({
+            "performed": self.performed.to_string(),
+            "missed": self.missed.to_string(),
+            "last_missed_slot": self.last_missed_slot.map(|slot| slot.to_string()),
+        })
+    }
+}
+
+/// The duties of a validator since the validator client started.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ValidatorPerformance {
+    pub proposals: DutyCounts,
+    pub attestations: DutyCounts,
+    pub aggregations: DutyCounts,
+}
+
+/// The performance of each validator with duties, by validator index.
+#[derive(Default)]
+pub struct Performance {
+    validators: Mutex<BTreeMap<usize, (PublicKey, ValidatorPerformance)>>,
+}
+
+impl Performance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records whether the validator with `index` and `pubkey` performed its `duty` at `slot`.
+    pub fn record(&self, index: usize, pubkey: &PublicKey, duty: Duty, slot: u64, performed: bool) {
+        let mut validators = self.validators.lock().expect("Performance lock poisoned");
+        let (_, performance) = validators
+            .entry(index)
+            .or_insert_with(|| (pubkey.clone(), ValidatorPerformance::default()));
+        let counts = match duty {
+            Duty::Proposal => &mut performance.proposals,
+            Duty::Attestation => &mut performance.attestations,
+            Duty::Aggregation => &mut performance.aggregations,
+        };
+        if performed {
+            counts.performed += 1;
+        } else {
+            counts.missed += 1;
+            counts.last_missed_slot = Some(slot);
+        }
+    }
+
+    /// Returns the performance of the validator with `index`, if it has had duties.
+    pub fn validator(&self, index: usize) -> Option<ValidatorPerformance> {
+        self.validators
+            .lock()
+            .expect("Performance lock poisoned")
+            .get(&index)
+            .map(|(_, performance)| performance.clone())
+    }
+
+    /// Returns the performance of every validator with duties, in order of index.
+    pub fn to_json(&self) -> Value {
+        let validators = self.validators.lock().expect("Performance lock poisoned");
+        Value::Array(
+            validators
+                .iter()
+                .map(|(index, (pubkey, performance))| {
+                    json!({
+                        "index": index.to_string(),
+                        "pubkey": format!("0x{}", hex::encode(pubkey.as_bytes())),
+                        "proposals": performance.proposals.to_json(),
+                        "attestations": performance.attestations.to_json(),
+                        "aggregations": performance.aggregations.to_json(),
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+
+    #[test]
+    fn test_record() {
+        let performance = Performance::new();
+        let keypair = Keypair::random();
+        assert_eq!(performance.validator(3), None);
+
+        performance.record(3, &keypair.pk, Duty::Attestation, 10, true);
+        performance.record(3, &keypair.pk, Duty::Attestation, 12, false);
+        performance.record(3, &keypair.pk, Duty::Proposal, 13, true);
+        performance.record(1, &keypair.pk, Duty::Aggregation, 14, false);
+        let validator = performance.validator(3).unwrap();
+        assert_eq!(
+            validator.attestations,
+            DutyCounts {
+                performed: 1,
+                missed: 1,
+                last_missed_slot: Some(12),
+            }
+        );
+        assert_eq!(validator.proposals.performed, 1);
+        assert_eq!(validator.aggregations, DutyCounts::default());
+
+        let json = performance.to_json();
+        assert_eq!(json[0]["index"], "1");
+        assert_eq!(json[0]["aggregations"]["missed"], "1");
+        assert_eq!(json[0]["aggregations"]["last_missed_slot"], "14");
+        assert_eq!(json[1]["attestations"]["performed"], "1");
+        assert_eq!(json[1]["proposals"]["last_missed_slot"], Value::Null);
+    }
+}
@@ -0,0 +1,148 @@
+use super::api_client::ApiClientError;
+use super::beacon_node_fallback::BeaconNodeFallback;
+use super::error::DutyError;
+use super::metrics;
+use super::signer::Signer;
+use super::signing::{block_signing_root, randao_reveal, sign_block};
+use super::slashing_protection::SlashingProtection;
+use db::ClientDB;
+use lighthouse_metrics::{inc_counter, start_timer, stop_timer};
+use slog::Logger;
+use types::Hash256;
+
+/// Proposes the block of `signer` at `slot`: the beacon node produces an unsigned block bearing
+/// `graffiti`, which is signed and returned to the beacon node to be imported and published.
+///
+/// The block is signed only if slashing protection allows it. Returns the root of the published
+/// block.
+#[allow(clippy::too_many_arguments)]
+pub fn propose_block<T: ClientDB>(
+    beacon_nodes: &BeaconNodeFallback,
+    slashing_protection: &SlashingProtection<T>,
+    signer: &Signer,
+    slot: u64,
+    graffiti: &Hash256,
+    cycle_length: u64,
+    fork_digest: [u8; 4],
+    log: &Logger,
+) -> Result<Hash256, DutyError> {
+    let reveal =
+        randao_reveal(signer, slot / cycle_length, fork_digest).map_err(DutyError::Signer)?;
+
+    let timer = start_timer(&metrics::BLOCK_PRODUCE_TIMES);
+    let block =
+        beacon_nodes.first_success(|client| client.produce_block(slot, &reveal, graffiti))?;
+    stop_timer(timer);
+    if block.slot != slot || block.randao_reveal != reveal || block.graffiti != *graffiti {
+        return Err(ApiClientError::InvalidResponse(
+            "Produced block does not match the request".to_string(),
+        )
+        .into());
+    }
+
+    slashing_protection.check_and_insert_block(
+        signer.pubkey(),
+        slot,
+        &block_signing_root(&block, fork_digest),
+    )?;
+    let timer = start_timer(&metrics::BLOCK_SIGN_TIMES);
+    let signature = sign_block(signer, &block, fork_digest).map_err(DutyError::Signer)?;
+    stop_timer(timer);
+
+    let timer = start_timer(&metrics::BLOCK_PUBLISH_TIMES);
+    beacon_nodes.first_success(|client| client.publish_block(&block, &signature))?;
+    stop_timer(timer);
+
+    inc_counter(&metrics::BLOCK_PROPOSALS);
+    let root = block.canonical_root();
+    info!(log, "Published block";
+          "slot" => slot,
+          "root" => format!("{:?}", root),
+          "attestations" => block.attestations.len());
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::api_client::tests::beacon_node;
+    use super::super::beacon_node_fallback::tests::fallback;
+    use super::super::slashing_protection::NotSafe;
+    use super::*;
+    use bls::Keypair;
+    use db::MemoryDB;
+    use slog::Discard;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_propose_block() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, ctx) = beacon_node(&keypairs);
+        let beacon_nodes = fallback(&server);
+        let log = Logger::root(Discard, o!());
+        let protection = SlashingProtection::new(Arc::new(MemoryDB::open()));
+        let (slot, proposer) = {
+            let node = ctx.node.read().unwrap();
+            let slot = node.present_slot();
+            (slot, node.block_proposer(slot).unwrap())
+        };
+
+        let keypair = &keypairs[proposer];
+        let signer = Signer::Local(keypair.clone());
+
+        /*
+         * A block which conflicts with one already signed is refused without being published.
+         */
+        let conflicting = SlashingProtection::new(Arc::new(MemoryDB::open()));
+        conflicting
+            .check_and_insert_block(&keypair.pk, slot, &Hash256::from(1))
+            .unwrap();
+        let graffiti = Hash256::from(7);
+        match propose_block(
+            &beacon_nodes,
+            &conflicting,
+            &signer,
+            slot,
+            &graffiti,
+            2,
+            [0; 4],
+            &log,
+        ) {
+            Err(DutyError::NotSafe(NotSafe::DoubleBlockProposal(_))) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert!(ctx.node.read().unwrap().head().0 < slot);
+
+        let root = propose_block(
+            &beacon_nodes,
+            &protection,
+            &signer,
+            slot,
+            &graffiti,
+            2,
+            [0; 4],
+            &log,
+        )
+        .unwrap();
+        assert_eq!(ctx.node.read().unwrap().head(), (slot, root));
+        assert_eq!(
+            ctx.node.read().unwrap().block(&root).unwrap().graffiti,
+            graffiti
+        );
+        /*
+         * A second block at the same slot is not after the head.
+         */
+        match propose_block(
+            &beacon_nodes,
+            &protection,
+            &signer,
+            slot,
+            &graffiti,
+            2,
+            [0; 4],
+            &log,
+        ) {
+            Err(DutyError::Api(ApiClientError::Status(400, _))) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use super::metrics::{TOKIO_RUNTIMES, TOKIO_WORKER_THREADS};
+use lighthouse_metrics::{dec_gauge, inc_counter, inc_gauge};
+use std::io;
+use tokio::runtime::{Builder, Runtime};
+
+/// Returns a runtime whose worker threads are named after `name` and counted in the metrics, as
+/// each beacon node client and signer client has its own runtime.
+pub fn new_runtime(name: &str) -> io::Result<Runtime> {
+    let runtime = Builder::new()
+        .name_prefix(format!("{}-", name))
+        .after_start(|| inc_gauge(&TOKIO_WORKER_THREADS))
+        .before_stop(|| dec_gauge(&TOKIO_WORKER_THREADS))
+        .build()?;
+    inc_counter(&TOKIO_RUNTIMES);
+    Ok(runtime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use std::thread;
+
+    #[test]
+    fn test_new_runtime() {
+        let runtimes = TOKIO_RUNTIMES.as_ref().unwrap().get();
+        let mut runtime = new_runtime("test").unwrap();
+        assert!(TOKIO_RUNTIMES.as_ref().unwrap().get() > runtimes);
+
+        let name = runtime
+            .block_on(future::lazy(|| {
+                Ok::<_, ()>(thread::current().name().map(String::from))
+            }))
+            .unwrap();
+        assert!(name.unwrap().starts_with("test-"));
+        assert!(TOKIO_WORKER_THREADS.as_ref().unwrap().get() > 0);
+    }
+}
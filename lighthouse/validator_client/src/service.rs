@@ -0,0 +1,1025 @@
+use super::api_client::{parse_u64, ApiClientError, AttesterDuty, BeaconNodeClient};
+use super::attester::{aggregate, attest, is_selected};
+use super::beacon_node_fallback::BeaconNodeFallback;
+use super::config::ValidatorClientConfig;
+use super::duties::DutiesService;
+use super::error::DutyError;
+use super::graffiti_file::GraffitiFile;
+use super::initialized_validators::InitializedValidators;
+use super::metrics;
+use super::metrics_server::MetricsServer;
+use super::performance::{Duty, Performance};
+use super::proposer::propose_block;
+use super::signer::Signer;
+use super::slashing_protection::{SlashingProtection, SlashingProtectionError};
+use db::ClientDB;
+use hex;
+use lighthouse_metrics::{inc_counter, set_gauge};
+use slog::Logger;
+use slot_clock::{SlotClock, SystemTimeSlotClock};
+use std::net::SocketAddr;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use types::{AttestationData, Hash256};
+
+/// The interval at which the time to genesis is logged while waiting for it.
+const GENESIS_COUNTDOWN_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, PartialEq)]
+pub enum ValidatorClientError {
+    NoValidators,
+    Api(ApiClientError),
+    /// The beacon node's spec lacks a value, or has an invalid one.
+    InvalidSpec(String),
+    SlashingProtection(SlashingProtectionError),
+    /// Another client attested for the validators with these indices, so the client stopped.
+    DoppelgangerDetected(Vec<usize>),
+    /// The metrics server could not be started.
+    MetricsServer(String),
+}
+
+impl From<ApiClientError> for ValidatorClientError {
+    fn from(e: ApiClientError) -> ValidatorClientError {
+        ValidatorClientError::Api(e)
+    }
+}
+
+impl From<SlashingProtectionError> for ValidatorClientError {
+    fn from(e: SlashingProtectionError) -> ValidatorClientError {
+        ValidatorClientError::SlashingProtection(e)
+    }
+}
+
+/// A validator's signer, with its index once the beacon node knows of it.
+struct Validator {
+    signer: Arc<Signer>,
+    index: Option<usize>,
+    /// The cycle in which the validator was added, while it is watched for doppelgängers. It
+    /// signs nothing until the watch ends.
+    watched_from: Option<u64>,
+}
+
+/// Performs the duties of a set of validators on a background thread, waking at the start of
+/// every slot to propose, a third of the way through to attest, and two thirds of the way through
+/// to aggregate.
+///
+/// Each validator is first watched for `doppelganger_cycles` whole cycles, signing nothing. Should
+/// the beacon nodes see it attest meanwhile, another client holds its keys, and the client stops
+/// rather than risk a slashing. Validators added before genesis cannot have attested, so are not
+/// watched.
+///
+/// Started before genesis, the client waits for it, logging a countdown, and performs the duties
+/// of the first slot from genesis.
+///
+/// The health of the beacon nodes is checked every slot on another thread.
+pub struct ValidatorClient {
+    shutdown: Sender<()>,
+    handle: Option<JoinHandle<Result<(), ValidatorClientError>>>,
+    health_shutdown: Sender<()>,
+    health_handle: Option<JoinHandle<()>>,
+    metrics_server: Option<MetricsServer>,
+}
+
+impl ValidatorClient {
+    /// Connects to the beacon nodes of `config` and starts performing the duties of `validators`,
+    /// signing only what `slashing_protection` allows.
+    ///
+    /// The chain is that of the first beacon node to respond; nodes on any other chain are not
+    /// used. The slashing protection must protect that chain, or no chain yet.
+    pub fn start<T: ClientDB + 'static>(
+        config: &ValidatorClientConfig,
+        validators: InitializedValidators,
+        slashing_protection: Arc<SlashingProtection<T>>,
+        log: Logger,
+    ) -> Result<Self, ValidatorClientError> {
+        if validators.is_empty() {
+            return Err(ValidatorClientError::NoValidators);
+        }
+        let clients = config
+            .beacon_nodes
+            .iter()
+            .map(|url| BeaconNodeClient::new(url, config.request_timeout))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut genesis = Err(ApiClientError::Request("No beacon nodes".to_string()));
+        for client in &clients {
+            genesis = client.genesis();
+            if genesis.is_ok() {
+                break;
+            }
+        }
+        let genesis = genesis?;
+        slashing_protection.check_genesis_root(&genesis.genesis_block_root)?;
+        let beacon_nodes = Arc::new(BeaconNodeFallback::new(
+            clients,
+            genesis.genesis_block_root,
+            log.clone(),
+        ));
+        beacon_nodes.update_health();
+        let spec = beacon_nodes.first_success(|client| client.spec())?;
+        let spec_value = |name: &str| {
+            parse_u64(&spec[name]).map_err(|_| ValidatorClientError::InvalidSpec(name.to_string()))
+        };
+        let cycle_length = spec_value("CYCLE_LENGTH")?.max(1);
+        let clock =
+            SystemTimeSlotClock::new(genesis.genesis_time, spec_value("SLOT_DURATION_MILLIS")?)
+                .ok_or_else(|| {
+                    ValidatorClientError::InvalidSpec("SLOT_DURATION_MILLIS".to_string())
+                })?;
+        info!(log, "Connected to beacon nodes";
+              "available" => beacon_nodes.num_available(),
+              "beacon_nodes" => config.beacon_nodes.len(),
+              "genesis_time" => genesis.genesis_time,
+              "fork_digest" => format!("0x{}", hex::encode(genesis.fork_digest)),
+              "validators" => validators.len());
+
+        let performance = Arc::new(Performance::new());
+        let metrics_server = match config.metrics_address {
+            Some(ref addr) => Some(
+                MetricsServer::start(addr, performance.clone(), beacon_nodes.clone(), log.clone())
+                    .map_err(|e| ValidatorClientError::MetricsServer(format!("{:?}", e)))?,
+            ),
+            None => None,
+        };
+
+        let (health_shutdown, health_shutdown_rx) = channel();
+        let health_handle = {
+            let (beacon_nodes, interval) = (beacon_nodes.clone(), clock.slot_duration());
+            thread::spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = health_shutdown_rx.recv_timeout(interval)
+                {
+                    beacon_nodes.update_health();
+                }
+            })
+        };
+
+        let mut duty_loop = DutyLoop {
+            beacon_nodes,
+            clock,
+            cycle_length,
+            doppelganger_cycles: config.doppelganger_cycles,
+            fork_digest: genesis.fork_digest,
+            graffiti: config.graffiti,
+            graffiti_file: config
+                .graffiti_file
+                .as_ref()
+                .map(|path| GraffitiFile::new(path)),
+            validators: vec![],
+            initialized_validators: validators,
+            slashing_protection,
+            lookup_cycle: None,
+            duties: DutiesService::new(cycle_length, log.clone()),
+            performance,
+            log,
+        };
+        duty_loop.sync_validators(clock.now().unwrap_or(0) / cycle_length);
+        let (shutdown, shutdown_rx) = channel();
+        let handle = thread::spawn(move || duty_loop.run(shutdown_rx));
+        Ok(Self {
+            shutdown,
+            handle: Some(handle),
+            health_shutdown,
+            health_handle: Some(health_handle),
+            metrics_server,
+        })
+    }
+
+    /// Returns the address of the metrics server, if it was started.
+    pub fn metrics_address(&self) -> Option<SocketAddr> {
+        self.metrics_server.as_ref().map(MetricsServer::local_addr)
+    }
+
+    /// Blocks until the client stops performing duties, which it does only on detecting a
+    /// doppelgänger.
+    pub fn wait(&mut self) -> Result<(), ValidatorClientError> {
+        match self.handle.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) | None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ValidatorClient {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(());
+        let _ = self.health_shutdown.send(());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        if let Some(handle) = self.health_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+struct DutyLoop<T: ClientDB> {
+    beacon_nodes: Arc<BeaconNodeFallback>,
+    clock: SystemTimeSlotClock,
+    cycle_length: u64,
+    /// The number of whole cycles new validators are watched for doppelgängers.
+    doppelganger_cycles: u64,
+    fork_digest: [u8; 4],
+    /// The graffiti of validators without their own.
+    graffiti: Option<Hash256>,
+    /// The graffiti of each validator, which takes precedence over any other.
+    graffiti_file: Option<GraffitiFile>,
+    validators: Vec<Validator>,
+    /// The signers `validators` are kept in line with.
+    initialized_validators: InitializedValidators,
+    slashing_protection: Arc<SlashingProtection<T>>,
+    /// The cycle in which unknown validators were last looked up.
+    lookup_cycle: Option<u64>,
+    duties: DutiesService,
+    /// The duties performed and missed by each validator.
+    performance: Arc<Performance>,
+    log: Logger,
+}
+
+impl<T: ClientDB> DutyLoop<T> {
+    fn run(&mut self, shutdown: Receiver<()>) -> Result<(), ValidatorClientError> {
+        if !self.wait_for_genesis(&shutdown) {
+            return Ok(());
+        }
+        loop {
+            if !self.wait(&shutdown, self.clock.duration_to_next_slot()) {
+                return Ok(());
+            }
+            let slot = match self.clock.now() {
+                Some(slot) => slot,
+                None => continue,
+            };
+            self.on_slot(slot)?;
+
+            let start = self.clock.start_of(slot);
+            let third = self.clock.slot_duration() / 3;
+            let until_attest = self.clock.duration_to(start + third);
+            if !self.wait(&shutdown, until_attest.unwrap_or_default()) {
+                return Ok(());
+            }
+            let attested = self.attest(slot);
+
+            let until_aggregate = self.clock.duration_to(start + third * 2);
+            if !self.wait(&shutdown, until_aggregate.unwrap_or_default()) {
+                return Ok(());
+            }
+            self.aggregate(&attested);
+        }
+    }
+
+    /// Logs the time to genesis every `GENESIS_COUNTDOWN_INTERVAL` until less than that remains,
+    /// returning `false` if the client shut down meanwhile.
+    ///
+    /// The rest of the wait is for the start of the first slot, like that for any other slot.
+    fn wait_for_genesis(&self, shutdown: &Receiver<()>) -> bool {
+        while let Some(remaining) = self.clock.duration_to_slot(0) {
+            info!(self.log, "Waiting for genesis"; "seconds" => remaining.as_secs());
+            if remaining <= GENESIS_COUNTDOWN_INTERVAL {
+                break;
+            }
+            if !self.wait(shutdown, GENESIS_COUNTDOWN_INTERVAL) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Waits for `duration`, returning `false` if the client shut down meanwhile.
+    fn wait(&self, shutdown: &Receiver<()>, duration: Duration) -> bool {
+        match shutdown.recv_timeout(duration) {
+            Err(RecvTimeoutError::Timeout) => true,
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => {
+                debug!(self.log, "Validator client shutting down");
+                false
+            }
+        }
+    }
+
+    fn on_slot(&mut self, slot: u64) -> Result<(), ValidatorClientError> {
+        let cycle = slot / self.cycle_length;
+        match self.initialized_validators.refresh() {
+            Ok(true) => self.sync_validators(cycle),
+            Ok(false) => {}
+            Err(e) => {
+                warn!(self.log, "Unable to reload validator definitions"; "error" => format!("{:?}", e))
+            }
+        }
+        if self.lookup_cycle != Some(cycle) {
+            self.resolve_indices();
+            self.lookup_cycle = Some(cycle);
+        }
+        self.check_doppelgangers(cycle)?;
+        let indices: Vec<usize> = self.validators.iter().filter_map(|v| v.index).collect();
+        if let Err(e) = self.duties.poll(&self.beacon_nodes, slot, &indices) {
+            warn!(self.log, "Unable to fetch duties"; "slot" => slot, "error" => format!("{:?}", e));
+        }
+
+        set_gauge(
+            &metrics::ACTIVE_VALIDATORS,
+            self.validators
+                .iter()
+                .filter(|v| v.index.is_some() && v.watched_from.is_none())
+                .count() as i64,
+        );
+        set_gauge(
+            &metrics::AVAILABLE_BEACON_NODES,
+            self.beacon_nodes.num_available() as i64,
+        );
+        info!(self.log, "Slot";
+              "slot" => slot,
+              "active_validators" => indices.len(),
+              "beacon_nodes" => self.beacon_nodes.num_available(),
+              "proposals" => self.duties.proposers_at(slot).len(),
+              "attestations" => self.duties.attesters_at(slot).len());
+
+        for duty in self.duties.proposers_at(slot) {
+            let signer = match self.signer(duty.validator_index) {
+                Some(signer) => signer,
+                None => continue,
+            };
+            let result = propose_block(
+                &self.beacon_nodes,
+                &self.slashing_protection,
+                signer,
+                slot,
+                &self.graffiti(signer),
+                self.cycle_length,
+                self.fork_digest,
+                &self.log,
+            );
+            self.performance.record(
+                duty.validator_index,
+                signer.pubkey(),
+                Duty::Proposal,
+                slot,
+                result.is_ok(),
+            );
+            match result {
+                Ok(_) => {}
+                Err(DutyError::NotSafe(e)) => {
+                    inc_counter(&metrics::BLOCK_PROPOSALS_MISSED);
+                    crit!(self.log, "Refused to sign slashable block";
+                          "slot" => slot,
+                          "validator_index" => duty.validator_index,
+                          "reason" => format!("{:?}", e));
+                }
+                Err(e) => {
+                    inc_counter(&metrics::BLOCK_PROPOSALS_MISSED);
+                    inc_counter(&metrics::BLOCK_PROPOSAL_FAILURES);
+                    error!(self.log, "Block proposal failed";
+                           "slot" => slot,
+                           "validator_index" => duty.validator_index,
+                           "error" => format!("{:?}", e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Publishes the attestations of the validators with duties at `slot`, returning the duties
+    /// of those selected as aggregators, with the data they attested to.
+    fn attest(&self, slot: u64) -> Vec<(AttesterDuty, AttestationData)> {
+        let mut aggregators = vec![];
+        for duty in self.duties.attesters_at(slot) {
+            let signer = match self.signer(duty.validator_index) {
+                Some(signer) => signer,
+                None => continue,
+            };
+            let result = attest(
+                &self.beacon_nodes,
+                &self.slashing_protection,
+                signer,
+                duty,
+                self.cycle_length,
+                self.fork_digest,
+                &self.log,
+            );
+            self.performance.record(
+                duty.validator_index,
+                signer.pubkey(),
+                Duty::Attestation,
+                slot,
+                result.is_ok(),
+            );
+            match result {
+                Ok(data) => match is_selected(signer, duty, self.fork_digest) {
+                    Ok(true) => aggregators.push((duty.clone(), data)),
+                    Ok(false) => {}
+                    Err(e) => {
+                        warn!(self.log, "Unable to sign selection proof";
+                              "slot" => slot,
+                              "validator_index" => duty.validator_index,
+                              "error" => format!("{:?}", e));
+                    }
+                },
+                Err(DutyError::NotSafe(e)) => {
+                    inc_counter(&metrics::ATTESTATIONS_MISSED);
+                    crit!(self.log, "Refused to sign slashable attestation";
+                          "slot" => slot,
+                          "validator_index" => duty.validator_index,
+                          "reason" => format!("{:?}", e));
+                }
+                Err(e) => {
+                    inc_counter(&metrics::ATTESTATIONS_MISSED);
+                    inc_counter(&metrics::ATTESTATION_FAILURES);
+                    error!(self.log, "Attestation failed";
+                           "slot" => slot,
+                           "validator_index" => duty.validator_index,
+                           "error" => format!("{:?}", e));
+                }
+            }
+        }
+        aggregators
+    }
+
+    fn aggregate(&self, aggregators: &[(AttesterDuty, AttestationData)]) {
+        for (duty, data) in aggregators {
+            let signer = match self.signer(duty.validator_index) {
+                Some(signer) => signer,
+                None => continue,
+            };
+            let result = aggregate(
+                &self.beacon_nodes,
+                signer,
+                duty,
+                data,
+                self.fork_digest,
+                &self.log,
+            );
+            let record = |performed| {
+                self.performance.record(
+                    duty.validator_index,
+                    signer.pubkey(),
+                    Duty::Aggregation,
+                    duty.slot,
+                    performed,
+                )
+            };
+            match result {
+                Ok(true) => record(true),
+                Ok(false) => {
+                    debug!(self.log, "No attestations to aggregate"; "slot" => duty.slot)
+                }
+                Err(e) => {
+                    record(false);
+                    inc_counter(&metrics::AGGREGATE_FAILURES);
+                    error!(self.log, "Aggregation failed";
+                           "slot" => duty.slot,
+                           "validator_index" => duty.validator_index,
+                           "error" => format!("{:?}", e));
+                }
+            }
+        }
+    }
+
+    /// Returns the graffiti of `signer`'s blocks: that of the graffiti file, then of its
+    /// definition, then of the client, or else none.
+    fn graffiti(&self, signer: &Signer) -> Hash256 {
+        let pubkey = signer.pubkey();
+        let from_file = self.graffiti_file.as_ref().and_then(|file| {
+            file.load_graffiti(pubkey)
+                .map_err(|e| {
+                    warn!(self.log, "Unable to read graffiti file"; "error" => format!("{:?}", e))
+                })
+                .ok()
+                .and_then(|graffiti| graffiti)
+        });
+        from_file
+            .or_else(|| self.initialized_validators.graffiti(pubkey))
+            .or(self.graffiti)
+            .unwrap_or_else(Hash256::zero)
+    }
+
+    /// Returns the signer of the validator with `index`, unless it is watched for doppelgängers.
+    fn signer(&self, index: usize) -> Option<&Signer> {
+        self.validators
+            .iter()
+            .find(|v| v.index == Some(index) && v.watched_from.is_none())
+            .map(|v| &*v.signer)
+    }
+
+    /// Asks the beacon nodes whether any watched validator attested in the previous or present
+    /// cycle, ending the watch of those watched for `doppelganger_cycles` whole cycles.
+    ///
+    /// The cycle in which a validator was added is not checked, as it may hold the attestations
+    /// of this client before it restarted.
+    fn check_doppelgangers(&mut self, cycle: u64) -> Result<(), ValidatorClientError> {
+        let mut checked = true;
+        for check_cycle in cycle.saturating_sub(1)..=cycle {
+            let indices: Vec<usize> = self
+                .validators
+                .iter()
+                .filter(|v| match v.watched_from {
+                    Some(from) => from < check_cycle,
+                    None => false,
+                })
+                .filter_map(|v| v.index)
+                .collect();
+            if indices.is_empty() {
+                continue;
+            }
+            match self
+                .beacon_nodes
+                .first_success(|client| client.live_validators(check_cycle, &indices))
+            {
+                Ok(ref live) if !live.is_empty() => {
+                    crit!(self.log, "Doppelganger detected, shutting down";
+                          "msg" => "another client is signing for these validators",
+                          "cycle" => check_cycle,
+                          "validator_indices" => format!("{:?}", live));
+                    return Err(ValidatorClientError::DoppelgangerDetected(live.clone()));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    checked = false;
+                    warn!(self.log, "Unable to check for doppelgangers"; "error" => format!("{:?}", e));
+                }
+            }
+        }
+        if !checked {
+            return Ok(());
+        }
+        for validator in &mut self.validators {
+            let (index, watched_from) = match (validator.index, validator.watched_from) {
+                (Some(index), Some(watched_from)) => (index, watched_from),
+                _ => continue,
+            };
+            if watched_from + self.doppelganger_cycles < cycle {
+                validator.watched_from = None;
+                info!(self.log, "No doppelganger detected, validator may sign";
+                      "validator_index" => index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the initialized validators not yet performing duties, watching them for doppelgängers
+    /// from `cycle`, and removes those no longer initialized.
+    fn sync_validators(&mut self, cycle: u64) {
+        let signers = self.initialized_validators.signers();
+        let before = self.validators.len();
+        self.validators
+            .retain(|v| signers.iter().any(|signer| Arc::ptr_eq(signer, &v.signer)));
+        let removed = before - self.validators.len();
+        let mut added = 0;
+        for signer in signers {
+            if !self
+                .validators
+                .iter()
+                .any(|v| Arc::ptr_eq(signer, &v.signer))
+            {
+                let watched_from = if self.doppelganger_cycles > 0 && self.clock.now().is_some() {
+                    Some(cycle)
+                } else {
+                    None
+                };
+                self.validators.push(Validator {
+                    signer: signer.clone(),
+                    index: None,
+                    watched_from,
+                });
+                added += 1;
+            }
+        }
+        if added > 0 {
+            /*
+             * The indices of the new validators are looked up on the next slot.
+             */
+            self.lookup_cycle = None;
+        }
+        if added > 0 || removed > 0 {
+            info!(self.log, "Updated validators";
+                  "added" => added,
+                  "removed" => removed,
+                  "validators" => self.validators.len());
+        }
+    }
+
+    /// Looks up the index of each validator not yet known to the beacon node.
+    fn resolve_indices(&mut self) {
+        for validator in self.validators.iter_mut().filter(|v| v.index.is_none()) {
+            let pubkey = validator.signer.pubkey();
+            match self
+                .beacon_nodes
+                .first_success(|client| client.validator_index(pubkey))
+            {
+                Ok(Some(index)) => {
+                    info!(self.log, "Validator known to beacon node"; "index" => index);
+                    validator.index = Some(index);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(self.log, "Unable to look up validator"; "error" => format!("{:?}", e))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::api_client::tests::{
+        beacon_node, beacon_node_from, fork_digest, synced_beacon_node,
+    };
+    use super::super::beacon_node_fallback::tests::fallback;
+    use super::super::graffiti_file::parse_graffiti;
+    use super::super::performance::ValidatorPerformance;
+    use super::super::validator_definitions::tests::test_dir;
+    use super::super::validator_definitions::{
+        SigningDefinition, ValidatorDefinition, ValidatorDefinitions,
+    };
+    use super::*;
+    use bls::Keypair;
+    use db::MemoryDB;
+    use slog::Discard;
+    use std::fs;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_start() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, _) = beacon_node(&keypairs);
+        let config = ValidatorClientConfig {
+            beacon_nodes: vec![format!("http://{}", server.local_addr())],
+            request_timeout: Duration::from_secs(2),
+            ..ValidatorClientConfig::default()
+        };
+        let log = Logger::root(Discard, o!());
+        let protection = Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open())));
+
+        assert_eq!(
+            ValidatorClient::start(
+                &config,
+                InitializedValidators::from_keypairs(vec![]),
+                protection.clone(),
+                log.clone()
+            )
+            .err(),
+            Some(ValidatorClientError::NoValidators)
+        );
+        let validators = InitializedValidators::from_keypairs(keypairs.clone());
+        let client =
+            ValidatorClient::start(&config, validators, protection.clone(), log.clone()).unwrap();
+        assert_eq!(client.metrics_address(), None);
+        drop(client);
+
+        let metrics_config = ValidatorClientConfig {
+            metrics_address: Some("127.0.0.1:0".parse().unwrap()),
+            ..config.clone()
+        };
+        let validators = InitializedValidators::from_keypairs(keypairs.clone());
+        let client =
+            ValidatorClient::start(&metrics_config, validators, protection.clone(), log.clone())
+                .unwrap();
+        assert_ne!(client.metrics_address().map(|addr| addr.port()), Some(0));
+        drop(client);
+
+        /*
+         * Slashing protection for another chain is refused.
+         */
+        let other_chain = Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open())));
+        other_chain.check_genesis_root(&Hash256::from(1)).unwrap();
+        let validators = InitializedValidators::from_keypairs(keypairs);
+        match ValidatorClient::start(&config, validators, other_chain, log.clone()) {
+            Err(ValidatorClientError::SlashingProtection(
+                SlashingProtectionError::GenesisMismatch(_),
+            )) => {}
+            other => panic!("Unexpected result: {:?}", other.err()),
+        }
+
+        /*
+         * An unreachable beacon node is passed over for the next.
+         */
+        let offline = "http://127.0.0.1:1".to_string();
+        let fallback_config = ValidatorClientConfig {
+            beacon_nodes: vec![offline.clone(), config.beacon_nodes[0].clone()],
+            ..config.clone()
+        };
+        let validators = InitializedValidators::from_keypairs(vec![Keypair::random()]);
+        let client = ValidatorClient::start(
+            &fallback_config,
+            validators,
+            protection.clone(),
+            log.clone(),
+        )
+        .unwrap();
+        drop(client);
+
+        let config = ValidatorClientConfig {
+            beacon_nodes: vec![offline],
+            ..config
+        };
+        let validators = InitializedValidators::from_keypairs(vec![Keypair::random()]);
+        match ValidatorClient::start(&config, validators, protection, log) {
+            Err(ValidatorClientError::Api(_)) => {}
+            other => panic!("Unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_start_before_genesis() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let genesis_time = now.as_secs() + 2;
+        let (server, ctx) = beacon_node_from(&keypairs, genesis_time);
+        let config = ValidatorClientConfig {
+            beacon_nodes: vec![format!("http://{}", server.local_addr())],
+            request_timeout: Duration::from_secs(2),
+            doppelganger_cycles: 1,
+            ..ValidatorClientConfig::default()
+        };
+        let protection = Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open())));
+        let validators = InitializedValidators::from_keypairs(keypairs);
+        let client =
+            ValidatorClient::start(&config, validators, protection, Logger::root(Discard, o!()))
+                .unwrap();
+        assert!(ctx.node.read().unwrap().pooled_attestations().is_empty());
+
+        /*
+         * The validators attest in the first slot, though watched for doppelgängers had they
+         * been added after genesis.
+         */
+        let after_attesting = Duration::from_secs(genesis_time) + Duration::from_millis(900);
+        thread::sleep(after_attesting - SystemTime::now().duration_since(UNIX_EPOCH).unwrap());
+        let attestations = ctx.node.read().unwrap().pooled_attestations().to_vec();
+        assert!(!attestations.is_empty());
+        assert!(attestations.iter().all(|a| a.data.slot == 0));
+        drop(client);
+    }
+
+    #[test]
+    fn test_on_slot() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, _) = synced_beacon_node(&keypairs);
+        let mut duty_loop = DutyLoop {
+            beacon_nodes: Arc::new(fallback(&server)),
+            clock: SystemTimeSlotClock::new(0, 1_000).unwrap(),
+            cycle_length: 2,
+            doppelganger_cycles: 0,
+            fork_digest: [0; 4],
+            graffiti: None,
+            graffiti_file: None,
+            validators: vec![
+                Validator {
+                    signer: Arc::new(Signer::Local(keypairs[1].clone())),
+                    index: None,
+                    watched_from: None,
+                },
+                Validator {
+                    signer: Arc::new(Signer::Local(Keypair::random())),
+                    index: None,
+                    watched_from: None,
+                },
+            ],
+            initialized_validators: InitializedValidators::from_keypairs(vec![]),
+            slashing_protection: Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open()))),
+            lookup_cycle: None,
+            duties: DutiesService::new(2, Logger::root(Discard, o!())),
+            performance: Arc::new(Performance::new()),
+            log: Logger::root(Discard, o!()),
+        };
+
+        duty_loop.on_slot(6).unwrap();
+        assert_eq!(duty_loop.lookup_cycle, Some(3));
+        assert_eq!(duty_loop.validators[0].index, Some(1));
+        assert_eq!(duty_loop.validators[1].index, None);
+        let duties = duty_loop.duties.cycle(3).unwrap();
+        assert_eq!(duties.attesters.len(), 1);
+        assert_eq!(duties.attesters[0].validator_index, 1);
+        assert!(duties
+            .proposers
+            .iter()
+            .all(|duty| duty.validator_index == 1));
+        assert!(duty_loop.duties.cycle(4).is_some());
+    }
+
+    #[test]
+    fn test_sync_validators() {
+        let keypairs: Vec<Keypair> = (0..3).map(|_| Keypair::random()).collect();
+        let initialized_validators = InitializedValidators::from_keypairs(keypairs[..2].to_vec());
+        let retained = initialized_validators.signers()[0].clone();
+        let mut duty_loop = DutyLoop {
+            beacon_nodes: Arc::new(BeaconNodeFallback::new(
+                vec![BeaconNodeClient::new("http://127.0.0.1:1", Duration::from_secs(1)).unwrap()],
+                Hash256::zero(),
+                Logger::root(Discard, o!()),
+            )),
+            clock: SystemTimeSlotClock::new(0, 1_000).unwrap(),
+            cycle_length: 2,
+            doppelganger_cycles: 2,
+            fork_digest: [0; 4],
+            graffiti: None,
+            graffiti_file: None,
+            validators: vec![
+                Validator {
+                    signer: retained,
+                    index: Some(5),
+                    watched_from: None,
+                },
+                Validator {
+                    signer: Arc::new(Signer::Local(keypairs[2].clone())),
+                    index: Some(6),
+                    watched_from: None,
+                },
+            ],
+            initialized_validators,
+            slashing_protection: Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open()))),
+            lookup_cycle: Some(3),
+            duties: DutiesService::new(2, Logger::root(Discard, o!())),
+            performance: Arc::new(Performance::new()),
+            log: Logger::root(Discard, o!()),
+        };
+
+        duty_loop.sync_validators(3);
+        assert_eq!(duty_loop.validators.len(), 2);
+        assert_eq!(duty_loop.validators[0].index, Some(5));
+        assert_eq!(*duty_loop.validators[1].signer.pubkey(), keypairs[1].pk);
+        assert_eq!(duty_loop.validators[1].index, None);
+        assert_eq!(duty_loop.validators[0].watched_from, None);
+        assert_eq!(duty_loop.validators[1].watched_from, Some(3));
+        assert_eq!(duty_loop.lookup_cycle, None);
+    }
+
+    #[test]
+    fn test_graffiti() {
+        let dir = test_dir("service_graffiti");
+        let validators_dir = dir.join("validators");
+        let keypairs: Vec<Keypair> = (0..2).map(|_| Keypair::random()).collect();
+        let definitions = keypairs
+            .iter()
+            .enumerate()
+            .map(|(i, keypair)| ValidatorDefinition {
+                enabled: true,
+                voting_public_key: keypair.pk.clone(),
+                description: String::new(),
+                graffiti: if i == 0 {
+                    Some("definition".to_string())
+                } else {
+                    None
+                },
+                signing_definition: SigningDefinition::RemoteSigner {
+                    url: "http://127.0.0.1:1".to_string(),
+                    request_timeout_ms: None,
+                },
+            })
+            .collect();
+        ValidatorDefinitions(definitions)
+            .save(&validators_dir)
+            .unwrap();
+        let graffiti_path = dir.join("graffiti.txt");
+        let mut duty_loop = DutyLoop {
+            beacon_nodes: Arc::new(BeaconNodeFallback::new(
+                vec![],
+                Hash256::zero(),
+                Logger::root(Discard, o!()),
+            )),
+            clock: SystemTimeSlotClock::new(0, 1_000).unwrap(),
+            cycle_length: 2,
+            doppelganger_cycles: 0,
+            fork_digest: [0; 4],
+            graffiti: Some(parse_graffiti("client").unwrap()),
+            graffiti_file: Some(GraffitiFile::new(&graffiti_path)),
+            validators: vec![],
+            initialized_validators: InitializedValidators::from_dir(&validators_dir).unwrap(),
+            slashing_protection: Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open()))),
+            lookup_cycle: None,
+            duties: DutiesService::new(2, Logger::root(Discard, o!())),
+            performance: Arc::new(Performance::new()),
+            log: Logger::root(Discard, o!()),
+        };
+        let signers: Vec<Arc<Signer>> = duty_loop
+            .initialized_validators
+            .signers()
+            .into_iter()
+            .cloned()
+            .collect();
+
+        /*
+         * A missing graffiti file is passed over for the definitions, then the client's graffiti.
+         */
+        assert_eq!(
+            duty_loop.graffiti(&signers[0]),
+            parse_graffiti("definition").unwrap()
+        );
+        assert_eq!(
+            duty_loop.graffiti(&signers[1]),
+            parse_graffiti("client").unwrap()
+        );
+
+        fs::write(&graffiti_path, "default: file\n").unwrap();
+        assert_eq!(
+            duty_loop.graffiti(&signers[0]),
+            parse_graffiti("file").unwrap()
+        );
+        fs::remove_file(&graffiti_path).unwrap();
+        duty_loop.graffiti = None;
+        assert_eq!(duty_loop.graffiti(&signers[1]), Hash256::zero());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_attest_and_aggregate() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, ctx) = synced_beacon_node(&keypairs);
+        let slot = ctx.node.read().unwrap().present_slot();
+        let digest = fork_digest(&server);
+        let mut duty_loop = DutyLoop {
+            beacon_nodes: Arc::new(fallback(&server)),
+            clock: SystemTimeSlotClock::new(0, 1_000).unwrap(),
+            cycle_length: 2,
+            doppelganger_cycles: 0,
+            fork_digest: digest,
+            graffiti: None,
+            graffiti_file: None,
+            validators: keypairs
+                .iter()
+                .map(|keypair| Validator {
+                    signer: Arc::new(Signer::Local(keypair.clone())),
+                    index: None,
+                    watched_from: None,
+                })
+                .collect(),
+            initialized_validators: InitializedValidators::from_keypairs(vec![]),
+            slashing_protection: Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open()))),
+            lookup_cycle: None,
+            duties: DutiesService::new(2, Logger::root(Discard, o!())),
+            performance: Arc::new(Performance::new()),
+            log: Logger::root(Discard, o!()),
+        };
+
+        duty_loop.on_slot(slot).unwrap();
+        let aggregators = duty_loop.attest(slot);
+        assert_eq!(aggregators.len(), 2);
+        assert_eq!(ctx.node.read().unwrap().pooled_attestations().len(), 2);
+        duty_loop.aggregate(&aggregators);
+        assert_eq!(ctx.node.read().unwrap().pooled_attestations().len(), 3);
+        let performance: Vec<ValidatorPerformance> = (0..4)
+            .filter_map(|index| duty_loop.performance.validator(index))
+            .collect();
+        assert_eq!(performance.len(), 2);
+        assert!(performance.iter().all(|p| p.attestations.performed == 1
+            && p.attestations.missed == 0
+            && p.aggregations.performed == 1));
+    }
+
+    #[test]
+    fn test_doppelganger() {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::random()).collect();
+        let (server, ctx) = synced_beacon_node(&keypairs);
+        let slot = ctx.node.read().unwrap().present_slot();
+        let cycle = slot / 2;
+        let digest = fork_digest(&server);
+        let duty_loop = |watched_from: Option<u64>| DutyLoop {
+            beacon_nodes: Arc::new(fallback(&server)),
+            clock: SystemTimeSlotClock::new(0, 1_000).unwrap(),
+            cycle_length: 2,
+            doppelganger_cycles: 1,
+            fork_digest: digest,
+            graffiti: None,
+            graffiti_file: None,
+            validators: keypairs
+                .iter()
+                .map(|keypair| Validator {
+                    signer: Arc::new(Signer::Local(keypair.clone())),
+                    index: None,
+                    watched_from,
+                })
+                .collect(),
+            initialized_validators: InitializedValidators::from_keypairs(vec![]),
+            slashing_protection: Arc::new(SlashingProtection::new(Arc::new(MemoryDB::open()))),
+            lookup_cycle: None,
+            duties: DutiesService::new(2, Logger::root(Discard, o!())),
+            performance: Arc::new(Performance::new()),
+            log: Logger::root(Discard, o!()),
+        };
+
+        /*
+         * Validators watched for a whole cycle without being seen may sign.
+         */
+        let mut watched = duty_loop(Some(cycle - 2));
+        watched.on_slot(slot).unwrap();
+        assert!(watched.validators.iter().all(|v| v.watched_from.is_none()));
+        assert_eq!(watched.attest(slot).len(), 2);
+
+        /*
+         * Validators seen attesting while watched stop the client, unless only seen in the cycle
+         * they were added, which may hold their attestations from before a restart.
+         */
+        let mut restarted = duty_loop(Some(cycle));
+        restarted.on_slot(slot).unwrap();
+        assert!(restarted.attest(slot).is_empty());
+        assert!(restarted
+            .validators
+            .iter()
+            .all(|v| v.watched_from.is_some()));
+        assert_eq!(ctx.node.read().unwrap().pooled_attestations().len(), 2);
+
+        let mut doppelganger = duty_loop(Some(cycle - 1));
+        match doppelganger.on_slot(slot) {
+            Err(ValidatorClientError::DoppelgangerDetected(indices)) => {
+                assert_eq!(indices.len(), 2)
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
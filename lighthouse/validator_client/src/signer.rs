@@ -0,0 +1,256 @@
+use super::api_client::{
+    aggregate_and_proof_json, attestation_data_json, block_json, hex_hash, parse_hex, send_request,
+    ApiClientError,
+};
+use super::runtime::new_runtime;
+use bls::{Keypair, PublicKey, Signature};
+use hex;
+use hyper::client::HttpConnector;
+use hyper::header::{ACCEPT, CONTENT_TYPE};
+use hyper::{Body, Client, Method, Request, Uri};
+use hyper_tls::HttpsConnector;
+use serde_json::{self, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use types::{AggregateAndProof, AttestationData, BeaconBlock, Hash256};
+
+/// The time allowed for each request to a remote signer, unless its definition gives another.
+pub const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(12);
+
+/// A message to be signed by a validator.
+///
+/// A remote signer is sent the message with its signing root, so that it can apply its own
+/// checks, such as slashing protection, before signing.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SignableMessage<'a> {
+    Block(&'a BeaconBlock),
+    Attestation(&'a AttestationData),
+    /// The cycle of a randao reveal.
+    RandaoReveal(u64),
+    /// The slot of a selection proof.
+    SelectionProof(u64),
+    AggregateAndProof(&'a AggregateAndProof),
+}
+
+impl<'a> SignableMessage<'a> {
+    /// Returns the type of the message in a signing request, and the field holding the message.
+    fn request_type(&self) -> (&'static str, &'static str, Value) {
+        match self {
+            SignableMessage::Block(block) => ("BLOCK", "block", block_json(block)),
+            SignableMessage::Attestation(data) => {
+                ("ATTESTATION", "attestation", attestation_data_json(data))
+            }
+            SignableMessage::RandaoReveal(cycle) => (
+                "RANDAO_REVEAL",
+                "randao_reveal",
+                json!({ "epoch": cycle.to_string() }),
+            ),
+            SignableMessage::SelectionProof(slot) => (
+                "AGGREGATION_SLOT",
+                "aggregation_slot",
+                json!({ "slot": slot.to_string() }),
+            ),
+            SignableMessage::AggregateAndProof(message) => (
+                "AGGREGATE_AND_PROOF",
+                "aggregate_and_proof",
+                aggregate_and_proof_json(message),
+            ),
+        }
+    }
+}
+
+/// Signs the messages of a validator, with its keypair or by a remote signer holding its keys.
+pub enum Signer {
+    Local(Keypair),
+    Remote(RemoteSigner),
+}
+
+impl Signer {
+    pub fn pubkey(&self) -> &PublicKey {
+        match self {
+            Signer::Local(keypair) => &keypair.pk,
+            Signer::Remote(remote) => &remote.pubkey,
+        }
+    }
+
+    /// Signs `signing_root`, the root of `message` in its domain on the chain of `fork_digest`.
+    pub fn sign(
+        &self,
+        message: SignableMessage,
+        signing_root: &Hash256,
+        fork_digest: [u8; 4],
+    ) -> Result<Signature, ApiClientError> {
+        match self {
+            Signer::Local(keypair) => Ok(Signature::new(signing_root, &keypair.sk)),
+            Signer::Remote(remote) => remote.sign(message, signing_root, fork_digest),
+        }
+    }
+}
+
+/// The HTTP(S) client and runtime shared by the remote signers.
+pub struct SignerClient {
+    client: Client<HttpsConnector<HttpConnector>>,
+    runtime: Runtime,
+}
+
+impl SignerClient {
+    pub fn new() -> Result<Self, ApiClientError> {
+        let connector =
+            HttpsConnector::new(2).map_err(|e| ApiClientError::Request(format!("{}", e)))?;
+        let runtime =
+            new_runtime("signer-client").map_err(|e| ApiClientError::Request(format!("{}", e)))?;
+        Ok(Self {
+            client: Client::builder().build(connector),
+            runtime,
+        })
+    }
+}
+
+/// A signer which holds the keys of a validator on another host, implementing the standard
+/// `POST /api/v1/eth2/sign/{pubkey}` API.
+pub struct RemoteSigner {
+    pubkey: PublicKey,
+    url: String,
+    timeout: Duration,
+    client: Arc<SignerClient>,
+}
+
+impl RemoteSigner {
+    /// Creates a signer of `pubkey` at `url`, e.g. `https://signer:9000`, which must answer each
+    /// request within `timeout`.
+    pub fn new(
+        pubkey: PublicKey,
+        url: &str,
+        timeout: Duration,
+        client: Arc<SignerClient>,
+    ) -> Result<Self, ApiClientError> {
+        let url = format!(
+            "{}/api/v1/eth2/sign/0x{}",
+            url.trim_end_matches('/'),
+            hex::encode(pubkey.as_bytes())
+        );
+        url.parse::<Uri>()
+            .map_err(|_| ApiClientError::InvalidUrl(url.clone()))?;
+        Ok(Self {
+            pubkey,
+            url,
+            timeout,
+            client,
+        })
+    }
+
+    fn sign(
+        &self,
+        message: SignableMessage,
+        signing_root: &Hash256,
+        fork_digest: [u8; 4],
+    ) -> Result<Signature, ApiClientError> {
+        let (request_type, field, value) = message.request_type();
+        let mut body = json!({
+            "type": request_type,
+            "fork_info": { "fork_digest": format!("0x{}", hex::encode(fork_digest)) },
+            "signingRoot": hex_hash(signing_root),
+        });
+        body[field] = value;
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(self.url.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json")
+            .body(Body::from(body.to_string()))
+            .map_err(|e| ApiClientError::Request(format!("{}", e)))?;
+
+        let response = send_request(&self.client.client, &self.client.runtime, self.timeout, req)?;
+        let response: Value = serde_json::from_slice(&response)
+            .map_err(|_| ApiClientError::InvalidResponse("Invalid JSON".to_string()))?;
+        parse_hex(&response["signature"])
+            .ok()
+            .and_then(|bytes| Signature::from_bytes(&bytes).ok())
+            .ok_or_else(|| ApiClientError::InvalidResponse("Invalid signature".to_string()))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::api_client::parse_hash;
+    use super::*;
+    use futures::{Future, Stream};
+    use hyper::service::service_fn;
+    use hyper::{Response, Server};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    /// Returns the URL of a remote signer which signs with `keypair`, recording the body of each
+    /// request. The signer runs until the runtime is dropped.
+    pub fn remote_signer(keypair: &Keypair) -> (String, Arc<Mutex<Vec<Value>>>, Runtime) {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let (sk, recorded) = (keypair.sk.clone(), requests.clone());
+        let new_service = move || {
+            let (sk, recorded) = (sk.clone(), recorded.clone());
+            service_fn(move |req: Request<Body>| {
+                let (sk, recorded) = (sk.clone(), recorded.clone());
+                req.into_body().concat2().map(move |body| {
+                    let body: Value = serde_json::from_slice(&body).unwrap();
+                    let root = parse_hash(&body["signingRoot"]).unwrap();
+                    recorded.lock().unwrap().push(body);
+                    let signature = Signature::new(&root, &sk);
+                    let response = json!({
+                        "signature": format!("0x{}", hex::encode(signature.as_bytes())),
+                    });
+                    Response::new(Body::from(response.to_string()))
+                })
+            })
+        };
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(new_service);
+        let url = format!("http://{}", server.local_addr());
+        let mut runtime = Runtime::new().unwrap();
+        runtime.spawn(server.map_err(|_| ()));
+        (url, requests, runtime)
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let keypair = Keypair::random();
+        let (url, requests, _runtime) = remote_signer(&keypair);
+        let client = Arc::new(SignerClient::new().unwrap());
+        let timeout = Duration::from_secs(2);
+        let local = Signer::Local(keypair.clone());
+        let remote = Signer::Remote(
+            RemoteSigner::new(keypair.pk.clone(), &url, timeout, client.clone()).unwrap(),
+        );
+        assert_eq!(remote.pubkey(), local.pubkey());
+
+        let block = BeaconBlock::zero();
+        let root = Hash256::from(3);
+        let message = SignableMessage::Block(&block);
+        assert_eq!(
+            remote.sign(message, &root, [1, 2, 3, 4]),
+            local.sign(message, &root, [1, 2, 3, 4])
+        );
+        remote
+            .sign(SignableMessage::RandaoReveal(5), &root, [0; 4])
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["type"], "BLOCK");
+        assert_eq!(requests[0]["fork_info"]["fork_digest"], "0x01020304");
+        assert_eq!(requests[0]["signingRoot"], hex_hash(&root));
+        assert_eq!(requests[0]["block"]["slot"], "0");
+        assert_eq!(requests[1]["type"], "RANDAO_REVEAL");
+        assert_eq!(requests[1]["randao_reveal"]["epoch"], "5");
+
+        /*
+         * A signer which accepts the connection but never answers times out.
+         */
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let timeout = Duration::from_millis(100);
+        let silent = RemoteSigner::new(keypair.pk.clone(), &url, timeout, client).unwrap();
+        assert_eq!(
+            Signer::Remote(silent).sign(message, &root, [0; 4]),
+            Err(ApiClientError::Timeout)
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use super::api_client::ApiClientError;
+use super::signer::{SignableMessage, Signer};
+use bls::Signature;
+use hashing::canonical_hash;
+use ssz::ssz_encode;
+pub use types::signing::{
+    attestation_data_root, attestation_signing_root, domain, signing_root,
+    DOMAIN_AGGREGATE_AND_PROOF, DOMAIN_ATTESTATION, DOMAIN_PROPOSAL, DOMAIN_RANDAO,
+    DOMAIN_SELECTION_PROOF,
+};
+use types::{AggregateAndProof, AttestationData, BeaconBlock, Hash256};
+
+/// Returns the root which is signed for `block`, and recorded by slashing protection.
+pub fn block_signing_root(block: &BeaconBlock, fork_digest: [u8; 4]) -> Hash256 {
+    signing_root(
+        &block.canonical_root(),
+        domain(DOMAIN_PROPOSAL, fork_digest),
+    )
+}
+
+pub fn sign_block(
+    signer: &Signer,
+    block: &BeaconBlock,
+    fork_digest: [u8; 4],
+) -> Result<Signature, ApiClientError> {
+    let root = block_signing_root(block, fork_digest);
+    signer.sign(SignableMessage::Block(block), &root, fork_digest)
+}
+
+pub fn sign_attestation_data(
+    signer: &Signer,
+    data: &AttestationData,
+    fork_digest: [u8; 4],
+) -> Result<Signature, ApiClientError> {
+    let root = attestation_signing_root(data, fork_digest);
+    signer.sign(SignableMessage::Attestation(data), &root, fork_digest)
+}
+
+/// Returns the proof of `signer` for `slot`, which selects the validator as an aggregator if it
+/// satisfies `types::is_aggregator`.
+pub fn selection_proof(
+    signer: &Signer,
+    slot: u64,
+    fork_digest: [u8; 4],
+) -> Result<Signature, ApiClientError> {
+    let root = signing_root(
+        &Hash256::from(slot),
+        domain(DOMAIN_SELECTION_PROOF, fork_digest),
+    );
+    signer.sign(SignableMessage::SelectionProof(slot), &root, fork_digest)
+}
+
+pub fn sign_aggregate_and_proof(
+    signer: &Signer,
+    aggregate_and_proof: &AggregateAndProof,
+    fork_digest: [u8; 4],
+) -> Result<Signature, ApiClientError> {
+    let object_root = Hash256::from(&canonical_hash(&ssz_encode(aggregate_and_proof))[..]);
+    let root = signing_root(
+        &object_root,
+        domain(DOMAIN_AGGREGATE_AND_PROOF, fork_digest),
+    );
+    let message = SignableMessage::AggregateAndProof(aggregate_and_proof);
+    signer.sign(message, &root, fork_digest)
+}
+
+/// Returns the randao reveal of `signer` for `cycle`.
+///
+/// Blocks carry a 32-byte reveal, so the reveal is the hash of the signature over the cycle.
+pub fn randao_reveal(
+    signer: &Signer,
+    cycle: u64,
+    fork_digest: [u8; 4],
+) -> Result<Hash256, ApiClientError> {
+    let root = signing_root(&Hash256::from(cycle), domain(DOMAIN_RANDAO, fork_digest));
+    let signature = signer.sign(SignableMessage::RandaoReveal(cycle), &root, fork_digest)?;
+    Ok(Hash256::from(&canonical_hash(&signature.as_bytes())[..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+
+    #[test]
+    fn test_signing() {
+        assert_eq!(domain(0x0102, [5, 6, 7, 8]), [2, 1, 0, 0, 5, 6, 7, 8]);
+
+        let keypair = Keypair::random();
+        let signer = Signer::Local(keypair.clone());
+        let mut block = BeaconBlock::zero();
+        block.slot = 3;
+        let signature = sign_block(&signer, &block, [0; 4]).unwrap();
+        let root = signing_root(&block.canonical_root(), domain(DOMAIN_PROPOSAL, [0; 4]));
+        assert!(signature.verify(&root, &keypair.pk));
+        let other_fork = signing_root(&block.canonical_root(), domain(DOMAIN_PROPOSAL, [1; 4]));
+        assert!(!signature.verify(&other_fork, &keypair.pk));
+
+        assert_eq!(
+            randao_reveal(&signer, 1, [0; 4]),
+            randao_reveal(&signer, 1, [0; 4])
+        );
+        assert_ne!(
+            randao_reveal(&signer, 1, [0; 4]),
+            randao_reveal(&signer, 2, [0; 4])
+        );
+
+        let mut data = AttestationData::zero();
+        data.slot = 3;
+        let signature = sign_attestation_data(&signer, &data, [0; 4]).unwrap();
+        let root = signing_root(
+            &attestation_data_root(&data),
+            domain(DOMAIN_ATTESTATION, [0; 4]),
+        );
+        assert!(signature.verify(&root, &keypair.pk));
+        assert_ne!(
+            selection_proof(&signer, 3, [0; 4]),
+            selection_proof(&signer, 4, [0; 4])
+        );
+    }
+}
@@ -0,0 +1,619 @@
+use bls::PublicKey;
+use db::stores::SlashingProtectionStore;
+use db::{ClientDB, DBError};
+use hex;
+use serde_json::Value;
+use ssz::{decode_ssz_list, Decodable, DecodeError, Encodable, SszStream};
+use std::sync::{Arc, Mutex};
+use types::Hash256;
+
+/// The version of the EIP-3076 interchange format which is imported and exported.
+pub const INTERCHANGE_FORMAT_VERSION: &str = "5";
+
+/*
+ * The history of each validator is checked with the conservative rules of EIP-3076. Besides
+ * refusing double votes and surround votes, nothing is signed before the earliest message in the
+ * history, as an imported history may omit older messages.
+ *
+ * The `epoch`s of the interchange format are cycles.
+ */
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignedBlock {
+    pub slot: u64,
+    /// Zero if unknown, as for blocks imported without a signing root.
+    pub signing_root: Hash256,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignedAttestation {
+    pub source_cycle: u64,
+    pub target_cycle: u64,
+    /// Zero if unknown, as for attestations imported without a signing root.
+    pub signing_root: Hash256,
+}
+
+/// The messages signed by a validator.
+#[derive(Debug, PartialEq, Clone, Default)]
+struct History {
+    blocks: Vec<SignedBlock>,
+    attestations: Vec<SignedAttestation>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Safe {
+    Valid,
+    /// The same message was signed before, so signing it again is harmless.
+    SameData,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NotSafe {
+    /// Another block was signed at the slot.
+    DoubleBlockProposal(SignedBlock),
+    /// The slot is not after that of the earliest block in the history.
+    BlockSlotTooLow(u64),
+    /// Another attestation was signed with the same target.
+    DoubleVote(SignedAttestation),
+    /// The attestation surrounds an attestation in the history.
+    SurroundingVote(SignedAttestation),
+    /// The attestation is surrounded by an attestation in the history.
+    SurroundedVote(SignedAttestation),
+    /// The source of the attestation is after its target.
+    InvalidAttestation,
+    /// The source or target of the attestation is before that of the earliest attestation in the
+    /// history.
+    AttestationCycleTooLow,
+    Error(SlashingProtectionError),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SlashingProtectionError {
+    DBError(String),
+    /// A stored history could not be decoded.
+    InvalidHistory,
+    /// The histories protect validators of the chain with the given genesis root.
+    GenesisMismatch(Hash256),
+    /// No chain has been recorded, so there is nothing to export.
+    UnknownGenesis,
+    InvalidInterchange(String),
+}
+
+impl From<DBError> for SlashingProtectionError {
+    fn from(e: DBError) -> SlashingProtectionError {
+        SlashingProtectionError::DBError(e.message)
+    }
+}
+
+impl From<DecodeError> for SlashingProtectionError {
+    fn from(_: DecodeError) -> SlashingProtectionError {
+        SlashingProtectionError::InvalidHistory
+    }
+}
+
+impl From<SlashingProtectionError> for NotSafe {
+    fn from(e: SlashingProtectionError) -> NotSafe {
+        NotSafe::Error(e)
+    }
+}
+
+/// Refuses to sign any block or attestation which conflicts with one its validator signed
+/// before, recording each message allowed to be signed.
+pub struct SlashingProtection<T: ClientDB> {
+    store: SlashingProtectionStore<T>,
+    /// Held while a history is checked and updated, so that two conflicting messages are never
+    /// both checked against the same history.
+    lock: Mutex<()>,
+}
+
+impl<T: ClientDB> SlashingProtection<T> {
+    pub fn new(db: Arc<T>) -> Self {
+        Self {
+            store: SlashingProtectionStore::new(db),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Records `genesis_root` as the root of the protected chain, or checks that it is the
+    /// recorded root.
+    pub fn check_genesis_root(
+        &self,
+        genesis_root: &Hash256,
+    ) -> Result<(), SlashingProtectionError> {
+        let _lock = self.lock.lock().expect("Slashing protection lock poisoned");
+        match self.genesis_root()? {
+            Some(ref stored) if stored != genesis_root => {
+                Err(SlashingProtectionError::GenesisMismatch(*stored))
+            }
+            Some(_) => Ok(()),
+            None => Ok(self.store.put_genesis_root(genesis_root)?),
+        }
+    }
+
+    /// Checks that the validator with `pubkey` may sign the block at `slot` with `signing_root`,
+    /// recording the block if so.
+    pub fn check_and_insert_block(
+        &self,
+        pubkey: &PublicKey,
+        slot: u64,
+        signing_root: &Hash256,
+    ) -> Result<Safe, NotSafe> {
+        let _lock = self.lock.lock().expect("Slashing protection lock poisoned");
+        let mut history = self.history(&pubkey.as_bytes())?;
+
+        if let Some(block) = history.blocks.iter().find(|b| b.slot == slot) {
+            if block.signing_root == *signing_root && !signing_root.is_zero() {
+                return Ok(Safe::SameData);
+            }
+            return Err(NotSafe::DoubleBlockProposal(block.clone()));
+        }
+        if let Some(min_slot) = history.blocks.iter().map(|b| b.slot).min() {
+            if slot <= min_slot {
+                return Err(NotSafe::BlockSlotTooLow(min_slot));
+            }
+        }
+
+        history.blocks.push(SignedBlock {
+            slot,
+            signing_root: *signing_root,
+        });
+        self.put_history(&pubkey.as_bytes(), &history)?;
+        Ok(Safe::Valid)
+    }
+
+    /// Checks that the validator with `pubkey` may sign the attestation from `source_cycle` to
+    /// `target_cycle` with `signing_root`, recording the attestation if so.
+    pub fn check_and_insert_attestation(
+        &self,
+        pubkey: &PublicKey,
+        source_cycle: u64,
+        target_cycle: u64,
+        signing_root: &Hash256,
+    ) -> Result<Safe, NotSafe> {
+        if source_cycle > target_cycle {
+            return Err(NotSafe::InvalidAttestation);
+        }
+        let _lock = self.lock.lock().expect("Slashing protection lock poisoned");
+        let mut history = self.history(&pubkey.as_bytes())?;
+
+        for previous in &history.attestations {
+            if previous.target_cycle == target_cycle {
+                if previous.signing_root == *signing_root && !signing_root.is_zero() {
+                    return Ok(Safe::SameData);
+                }
+                return Err(NotSafe::DoubleVote(previous.clone()));
+            }
+            if previous.source_cycle < source_cycle && target_cycle < previous.target_cycle {
+                return Err(NotSafe::SurroundedVote(previous.clone()));
+            }
+            if source_cycle < previous.source_cycle && previous.target_cycle < target_cycle {
+                return Err(NotSafe::SurroundingVote(previous.clone()));
+            }
+        }
+        let min_source = history.attestations.iter().map(|a| a.source_cycle).min();
+        let min_target = history.attestations.iter().map(|a| a.target_cycle).min();
+        match (min_source, min_target) {
+            (Some(min_source), Some(min_target))
+                if source_cycle < min_source || target_cycle <= min_target =>
+            {
+                return Err(NotSafe::AttestationCycleTooLow)
+            }
+            _ => {}
+        }
+
+        history.attestations.push(SignedAttestation {
+            source_cycle,
+            target_cycle,
+            signing_root: *signing_root,
+        });
+        self.put_history(&pubkey.as_bytes(), &history)?;
+        Ok(Safe::Valid)
+    }
+
+    /// Merges the histories of an EIP-3076 interchange into the stored histories, returning the
+    /// number of validators in the interchange.
+    pub fn import_interchange(
+        &self,
+        interchange: &Value,
+    ) -> Result<usize, SlashingProtectionError> {
+        let invalid = |message: &str| SlashingProtectionError::InvalidInterchange(message.into());
+        let metadata = &interchange["metadata"];
+        if metadata["interchange_format_version"].as_str() != Some(INTERCHANGE_FORMAT_VERSION) {
+            return Err(invalid("Unsupported interchange_format_version"));
+        }
+        let genesis_root = parse_hash(&metadata["genesis_validators_root"])
+            .ok_or_else(|| invalid("Invalid genesis_validators_root"))?;
+        let entries = interchange["data"]
+            .as_array()
+            .ok_or_else(|| invalid("Invalid data"))?;
+
+        /*
+         * Every entry is parsed before any is imported, so that an invalid interchange changes
+         * nothing.
+         */
+        let mut imported = vec![];
+        for entry in entries {
+            let pubkey = parse_hex(&entry["pubkey"])
+                .filter(|bytes| PublicKey::from_bytes(bytes).is_ok())
+                .ok_or_else(|| invalid("Invalid pubkey"))?;
+            imported.push((pubkey, history_from_json(entry).map_err(|e| invalid(&e))?));
+        }
+
+        self.check_genesis_root(&genesis_root)?;
+        let _lock = self.lock.lock().expect("Slashing protection lock poisoned");
+        for (pubkey, imported) in &imported {
+            let mut history = self.history(pubkey)?;
+            for block in &imported.blocks {
+                if !history.blocks.contains(block) {
+                    history.blocks.push(block.clone());
+                }
+            }
+            for attestation in &imported.attestations {
+                if !history.attestations.contains(attestation) {
+                    history.attestations.push(attestation.clone());
+                }
+            }
+            self.put_history(pubkey, &history)?;
+        }
+        Ok(imported.len())
+    }
+
+    /// Returns the stored histories as an EIP-3076 interchange.
+    pub fn export_interchange(&self) -> Result<Value, SlashingProtectionError> {
+        let _lock = self.lock.lock().expect("Slashing protection lock poisoned");
+        let genesis_root = self
+            .genesis_root()?
+            .ok_or(SlashingProtectionError::UnknownGenesis)?;
+        let mut data = vec![];
+        for pubkey in self.pubkeys()? {
+            let history = self.history(&pubkey)?;
+            data.push(json!({
+                "pubkey": format!("0x{}", hex::encode(&pubkey)),
+                "signed_blocks": history.blocks.iter().map(|block| {
+                    let mut value = json!({ "slot": block.slot.to_string() });
+                    if !block.signing_root.is_zero() {
+                        value["signing_root"] = json!(hex_hash(&block.signing_root));
+                    }
+                    value
+                }).collect::<Vec<Value>>(),
+                "signed_attestations": history.attestations.iter().map(|attestation| {
+                    let mut value = json!({
+                        "source_epoch": attestation.source_cycle.to_string(),
+                        "target_epoch": attestation.target_cycle.to_string(),
+                    });
+                    if !attestation.signing_root.is_zero() {
+                        value["signing_root"] = json!(hex_hash(&attestation.signing_root));
+                    }
+                    value
+                }).collect::<Vec<Value>>(),
+            }));
+        }
+        Ok(json!({
+            "metadata": {
+                "interchange_format_version": INTERCHANGE_FORMAT_VERSION,
+                "genesis_validators_root": hex_hash(&genesis_root),
+            },
+            "data": data,
+        }))
+    }
+
+    fn genesis_root(&self) -> Result<Option<Hash256>, SlashingProtectionError> {
+        match self.store.get_genesis_root()? {
+            Some(ref root) if root.len() == 32 => Ok(Some(Hash256::from(&root[..]))),
+            Some(_) => Err(SlashingProtectionError::InvalidHistory),
+            None => Ok(None),
+        }
+    }
+
+    fn history(&self, pubkey: &[u8]) -> Result<History, SlashingProtectionError> {
+        match self.store.get_serialized_history(pubkey)? {
+            Some(ssz) => Ok(History::ssz_decode(&ssz, 0)?.0),
+            None => Ok(History::default()),
+        }
+    }
+
+    /// Stores `history`, adding `pubkey` to the protected validators if it is new.
+    fn put_history(&self, pubkey: &[u8], history: &History) -> Result<(), SlashingProtectionError> {
+        let mut pubkeys = self.pubkeys()?;
+        if !pubkeys.iter().any(|known| known[..] == pubkey[..]) {
+            pubkeys.push(pubkey.to_vec());
+            let mut list = SszStream::new();
+            for pubkey in &pubkeys {
+                list.append_encoded_val(pubkey);
+            }
+            let mut s = SszStream::new();
+            s.append_encoded_val(&list.drain());
+            self.store.put_serialized_pubkeys(&s.drain())?;
+        }
+        let mut s = SszStream::new();
+        s.append(history);
+        Ok(self.store.put_serialized_history(pubkey, &s.drain())?)
+    }
+
+    fn pubkeys(&self) -> Result<Vec<Vec<u8>>, SlashingProtectionError> {
+        match self.store.get_serialized_pubkeys()? {
+            Some(ssz) => Ok(decode_ssz_list(&ssz, 0)?.0),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+impl Encodable for SignedBlock {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.slot);
+        s.append(&self.signing_root);
+    }
+}
+
+impl Decodable for SignedBlock {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (slot, i) = u64::ssz_decode(bytes, i)?;
+        let (signing_root, i) = Hash256::ssz_decode(bytes, i)?;
+        Ok((Self { slot, signing_root }, i))
+    }
+}
+
+impl Encodable for SignedAttestation {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append(&self.source_cycle);
+        s.append(&self.target_cycle);
+        s.append(&self.signing_root);
+    }
+}
+
+impl Decodable for SignedAttestation {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (source_cycle, i) = u64::ssz_decode(bytes, i)?;
+        let (target_cycle, i) = u64::ssz_decode(bytes, i)?;
+        let (signing_root, i) = Hash256::ssz_decode(bytes, i)?;
+        Ok((
+            Self {
+                source_cycle,
+                target_cycle,
+                signing_root,
+            },
+            i,
+        ))
+    }
+}
+
+impl Encodable for History {
+    fn ssz_append(&self, s: &mut SszStream) {
+        s.append_vec(&self.blocks);
+        s.append_vec(&self.attestations);
+    }
+}
+
+impl Decodable for History {
+    fn ssz_decode(bytes: &[u8], i: usize) -> Result<(Self, usize), DecodeError> {
+        let (blocks, i) = decode_ssz_list(bytes, i)?;
+        let (attestations, i) = decode_ssz_list(bytes, i)?;
+        Ok((
+            Self {
+                blocks,
+                attestations,
+            },
+            i,
+        ))
+    }
+}
+
+/// Parses the `signed_blocks` and `signed_attestations` of an interchange entry.
+fn history_from_json(entry: &Value) -> Result<History, String> {
+    let signing_root = |value: &Value| match value.get("signing_root") {
+        Some(root) => parse_hash(root).ok_or_else(|| "Invalid signing_root".to_string()),
+        None => Ok(Hash256::zero()),
+    };
+    let mut history = History::default();
+    let blocks = entry["signed_blocks"]
+        .as_array()
+        .ok_or("Invalid signed_blocks")?;
+    for block in blocks {
+        history.blocks.push(SignedBlock {
+            slot: parse_u64(&block["slot"]).ok_or("Invalid slot")?,
+            signing_root: signing_root(block)?,
+        });
+    }
+    let attestations = entry["signed_attestations"]
+        .as_array()
+        .ok_or("Invalid signed_attestations")?;
+    for attestation in attestations {
+        history.attestations.push(SignedAttestation {
+            source_cycle: parse_u64(&attestation["source_epoch"]).ok_or("Invalid source_epoch")?,
+            target_cycle: parse_u64(&attestation["target_epoch"]).ok_or("Invalid target_epoch")?,
+            signing_root: signing_root(attestation)?,
+        });
+    }
+    Ok(history)
+}
+
+fn hex_hash(hash: &Hash256) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+fn parse_u64(value: &Value) -> Option<u64> {
+    value.as_str().and_then(|s| s.parse().ok())
+}
+
+fn parse_hex(value: &Value) -> Option<Vec<u8>> {
+    value
+        .as_str()
+        .filter(|s| s.starts_with("0x"))
+        .and_then(|s| hex::decode(&s[2..]).ok())
+}
+
+fn parse_hash(value: &Value) -> Option<Hash256> {
+    parse_hex(value)
+        .filter(|bytes| bytes.len() == 32)
+        .map(|bytes| Hash256::from(&bytes[..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::Keypair;
+    use db::MemoryDB;
+
+    fn protection() -> SlashingProtection<MemoryDB> {
+        SlashingProtection::new(Arc::new(MemoryDB::open()))
+    }
+
+    #[test]
+    fn test_blocks() {
+        let protection = protection();
+        let pk = Keypair::random().pk;
+        let root = Hash256::from(1);
+
+        assert_eq!(
+            protection.check_and_insert_block(&pk, 5, &root),
+            Ok(Safe::Valid)
+        );
+        assert_eq!(
+            protection.check_and_insert_block(&pk, 5, &root),
+            Ok(Safe::SameData)
+        );
+        assert_eq!(
+            protection.check_and_insert_block(&pk, 5, &Hash256::from(2)),
+            Err(NotSafe::DoubleBlockProposal(SignedBlock {
+                slot: 5,
+                signing_root: root
+            }))
+        );
+        assert_eq!(
+            protection.check_and_insert_block(&pk, 4, &root),
+            Err(NotSafe::BlockSlotTooLow(5))
+        );
+        assert_eq!(
+            protection.check_and_insert_block(&pk, 6, &Hash256::from(2)),
+            Ok(Safe::Valid)
+        );
+        assert_eq!(
+            protection.check_and_insert_block(&Keypair::random().pk, 5, &Hash256::from(2)),
+            Ok(Safe::Valid)
+        );
+    }
+
+    #[test]
+    fn test_attestations() {
+        let protection = protection();
+        let pk = Keypair::random().pk;
+        let root = Hash256::from(1);
+        let other = Hash256::from(2);
+
+        assert_eq!(
+            protection.check_and_insert_attestation(&pk, 2, 3, &root),
+            Ok(Safe::Valid)
+        );
+        assert_eq!(
+            protection.check_and_insert_attestation(&pk, 2, 3, &root),
+            Ok(Safe::SameData)
+        );
+        match protection.check_and_insert_attestation(&pk, 2, 3, &other) {
+            Err(NotSafe::DoubleVote(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            protection.check_and_insert_attestation(&pk, 4, 3, &other),
+            Err(NotSafe::InvalidAttestation)
+        );
+        assert_eq!(
+            protection.check_and_insert_attestation(&pk, 3, 6, &other),
+            Ok(Safe::Valid)
+        );
+        match protection.check_and_insert_attestation(&pk, 4, 5, &other) {
+            Err(NotSafe::SurroundedVote(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match protection.check_and_insert_attestation(&pk, 2, 7, &other) {
+            Err(NotSafe::SurroundingVote(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            protection.check_and_insert_attestation(&pk, 1, 8, &other),
+            Err(NotSafe::SurroundingVote(SignedAttestation {
+                source_cycle: 2,
+                target_cycle: 3,
+                signing_root: root,
+            }))
+        );
+        assert_eq!(
+            protection.check_and_insert_attestation(&pk, 6, 7, &other),
+            Ok(Safe::Valid)
+        );
+    }
+
+    #[test]
+    fn test_genesis_root() {
+        let protection = protection();
+        assert_eq!(
+            protection.export_interchange(),
+            Err(SlashingProtectionError::UnknownGenesis)
+        );
+        assert_eq!(protection.check_genesis_root(&Hash256::from(1)), Ok(()));
+        assert_eq!(protection.check_genesis_root(&Hash256::from(1)), Ok(()));
+        assert_eq!(
+            protection.check_genesis_root(&Hash256::from(2)),
+            Err(SlashingProtectionError::GenesisMismatch(Hash256::from(1)))
+        );
+    }
+
+    #[test]
+    fn test_interchange() {
+        let original = protection();
+        let pks = [Keypair::random().pk, Keypair::random().pk];
+        original.check_genesis_root(&Hash256::from(9)).unwrap();
+        original
+            .check_and_insert_block(&pks[0], 4, &Hash256::from(1))
+            .unwrap();
+        original
+            .check_and_insert_attestation(&pks[0], 1, 2, &Hash256::from(2))
+            .unwrap();
+        original
+            .check_and_insert_attestation(&pks[1], 0, 1, &Hash256::from(3))
+            .unwrap();
+
+        let interchange = original.export_interchange().unwrap();
+        assert_eq!(interchange["metadata"]["interchange_format_version"], "5");
+        assert_eq!(interchange["data"].as_array().unwrap().len(), 2);
+        assert_eq!(interchange["data"][0]["signed_blocks"][0]["slot"], "4");
+
+        let imported = protection();
+        assert_eq!(imported.import_interchange(&interchange), Ok(2));
+        assert_eq!(imported.export_interchange(), Ok(interchange.clone()));
+        assert!(imported
+            .check_and_insert_block(&pks[0], 4, &Hash256::from(5))
+            .is_err());
+        assert!(imported
+            .check_and_insert_attestation(&pks[1], 0, 1, &Hash256::from(5))
+            .is_err());
+
+        /*
+         * Blocks imported without a signing root may not be signed again.
+         */
+        let minimal = json!({
+            "metadata": {
+                "interchange_format_version": "5",
+                "genesis_validators_root": hex_hash(&Hash256::from(9)),
+            },
+            "data": [{
+                "pubkey": format!("0x{}", hex::encode(pks[1].as_bytes())),
+                "signed_blocks": [{ "slot": "7" }],
+                "signed_attestations": [],
+            }],
+        });
+        assert_eq!(imported.import_interchange(&minimal), Ok(1));
+        assert!(imported
+            .check_and_insert_block(&pks[1], 7, &Hash256::zero())
+            .is_err());
+
+        let mut other_chain = minimal.clone();
+        other_chain["metadata"]["genesis_validators_root"] = json!(hex_hash(&Hash256::from(8)));
+        assert_eq!(
+            imported.import_interchange(&other_chain),
+            Err(SlashingProtectionError::GenesisMismatch(Hash256::from(9)))
+        );
+        let mut old_version = minimal.clone();
+        old_version["metadata"]["interchange_format_version"] = json!("4");
+        assert!(imported.import_interchange(&old_version).is_err());
+    }
+}
@@ -0,0 +1,498 @@
+use super::graffiti_file::parse_graffiti;
+use super::keystore::{Keystore, KeystoreError};
+use bls::{Keypair, PublicKey};
+use hex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use yaml_rust::yaml::Hash;
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+
+/// The file within the validators dir which defines the validators.
+pub const CONFIG_FILENAME: &str = "validator_definitions.yml";
+/// The file within a validator's dir holding its voting keystore.
+pub const VOTING_KEYSTORE_FILE: &str = "voting-keystore.json";
+
+#[derive(Debug)]
+pub enum ValidatorDefinitionsError {
+    Io(io::Error),
+    InvalidYaml(String),
+    /// The definition at the index lacks a field, or has an invalid one.
+    InvalidDefinition(usize, String),
+    Keystore(PathBuf, KeystoreError),
+    /// The validator is already defined.
+    DuplicateValidator(PublicKey),
+}
+
+impl From<io::Error> for ValidatorDefinitionsError {
+    fn from(e: io::Error) -> ValidatorDefinitionsError {
+        ValidatorDefinitionsError::Io(e)
+    }
+}
+
+/// The password of a voting keystore.
+#[derive(Debug, PartialEq, Clone)]
+pub enum KeystorePassword {
+    /// A file holding the password, followed by any newlines.
+    File(PathBuf),
+    Inline(String),
+}
+
+impl KeystorePassword {
+    pub fn read(&self) -> Result<String, io::Error> {
+        match self {
+            KeystorePassword::File(path) => {
+                let password = fs::read_to_string(path)?;
+                Ok(password.trim_end_matches(&['\r', '\n'][..]).to_string())
+            }
+            KeystorePassword::Inline(password) => Ok(password.clone()),
+        }
+    }
+}
+
+/// How the messages of a validator are signed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SigningDefinition {
+    /// By the voting keypair, decrypted from a keystore.
+    LocalKeystore {
+        voting_keystore_path: PathBuf,
+        voting_keystore_password: KeystorePassword,
+    },
+    /// By a remote signer holding the voting keypair.
+    RemoteSigner {
+        url: String,
+        /// The time allowed for each request, if not the default.
+        request_timeout_ms: Option<u64>,
+    },
+}
+
+/// A validator, with how its messages are signed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidatorDefinition {
+    /// Whether the validator client should perform the validator's duties.
+    pub enabled: bool,
+    pub voting_public_key: PublicKey,
+    pub description: String,
+    /// The graffiti of the validator's blocks, if not the validator client's.
+    pub graffiti: Option<String>,
+    pub signing_definition: SigningDefinition,
+}
+
+impl ValidatorDefinition {
+    fn from_yaml(i: usize, yaml: &Yaml) -> Result<Self, ValidatorDefinitionsError> {
+        let invalid =
+            |name: &str| ValidatorDefinitionsError::InvalidDefinition(i, name.to_string());
+        let voting_public_key = yaml["voting_public_key"]
+            .as_str()
+            .and_then(|s| hex::decode(s.trim_start_matches("0x")).ok())
+            .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| invalid("voting_public_key"))?;
+
+        /*
+         * Definitions without a type are of local keystores.
+         */
+        let signing_definition = match yaml["type"].as_str() {
+            None | Some("local_keystore") => {
+                let voting_keystore_password = match (
+                    yaml["voting_keystore_password_path"].as_str(),
+                    yaml["voting_keystore_password"].as_str(),
+                ) {
+                    (Some(path), None) => KeystorePassword::File(PathBuf::from(path)),
+                    (None, Some(password)) => KeystorePassword::Inline(password.to_string()),
+                    _ => return Err(invalid("voting_keystore_password")),
+                };
+                SigningDefinition::LocalKeystore {
+                    voting_keystore_path: yaml["voting_keystore_path"]
+                        .as_str()
+                        .map(PathBuf::from)
+                        .ok_or_else(|| invalid("voting_keystore_path"))?,
+                    voting_keystore_password,
+                }
+            }
+            Some("remote_signer") => {
+                let request_timeout_ms = match yaml["request_timeout_ms"] {
+                    Yaml::BadValue => None,
+                    ref timeout => Some(
+                        timeout
+                            .as_i64()
+                            .filter(|ms| *ms > 0)
+                            .ok_or_else(|| invalid("request_timeout_ms"))?
+                            as u64,
+                    ),
+                };
+                SigningDefinition::RemoteSigner {
+                    url: yaml["url"]
+                        .as_str()
+                        .ok_or_else(|| invalid("url"))?
+                        .to_string(),
+                    request_timeout_ms,
+                }
+            }
+            Some(_) => return Err(invalid("type")),
+        };
+        let graffiti = match yaml["graffiti"] {
+            Yaml::BadValue => None,
+            ref graffiti => Some(
+                graffiti
+                    .as_str()
+                    .filter(|text| parse_graffiti(text).is_ok())
+                    .ok_or_else(|| invalid("graffiti"))?
+                    .to_string(),
+            ),
+        };
+
+        Ok(Self {
+            enabled: yaml["enabled"]
+                .as_bool()
+                .ok_or_else(|| invalid("enabled"))?,
+            voting_public_key,
+            description: yaml["description"].as_str().unwrap_or("").to_string(),
+            graffiti,
+            signing_definition,
+        })
+    }
+
+    fn to_yaml(&self) -> Yaml {
+        let string = |s: &str| Yaml::String(s.to_string());
+        let path = |p: &Path| string(&p.to_string_lossy());
+        let mut hash = Hash::new();
+        hash.insert(string("enabled"), Yaml::Boolean(self.enabled));
+        hash.insert(
+            string("voting_public_key"),
+            string(&format!(
+                "0x{}",
+                hex::encode(self.voting_public_key.as_bytes())
+            )),
+        );
+        hash.insert(string("description"), string(&self.description));
+        if let Some(graffiti) = &self.graffiti {
+            hash.insert(string("graffiti"), string(graffiti));
+        }
+        match &self.signing_definition {
+            SigningDefinition::LocalKeystore {
+                voting_keystore_path,
+                voting_keystore_password,
+            } => {
+                hash.insert(string("type"), string("local_keystore"));
+                hash.insert(string("voting_keystore_path"), path(voting_keystore_path));
+                match voting_keystore_password {
+                    KeystorePassword::File(p) => {
+                        hash.insert(string("voting_keystore_password_path"), path(p))
+                    }
+                    KeystorePassword::Inline(password) => {
+                        hash.insert(string("voting_keystore_password"), string(password))
+                    }
+                };
+            }
+            SigningDefinition::RemoteSigner {
+                url,
+                request_timeout_ms,
+            } => {
+                hash.insert(string("type"), string("remote_signer"));
+                hash.insert(string("url"), string(url));
+                if let Some(ms) = request_timeout_ms {
+                    hash.insert(string("request_timeout_ms"), Yaml::Integer(*ms as i64));
+                }
+            }
+        }
+        Yaml::Hash(hash)
+    }
+}
+
+/// Decrypts the voting keypair of `pubkey` from the keystore at `path`.
+pub fn decrypt_keystore(
+    path: &Path,
+    password: &KeystorePassword,
+    pubkey: &PublicKey,
+) -> Result<Keypair, ValidatorDefinitionsError> {
+    let keystore_error = |e| ValidatorDefinitionsError::Keystore(path.to_path_buf(), e);
+    let keystore = Keystore::from_file(path).map_err(keystore_error)?;
+    if keystore.pubkey != *pubkey {
+        return Err(keystore_error(KeystoreError::PublicKeyMismatch));
+    }
+    keystore.decrypt(&password.read()?).map_err(keystore_error)
+}
+
+/// The validators defined in the `validator_definitions.yml` of a validators dir.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ValidatorDefinitions(pub Vec<ValidatorDefinition>);
+
+impl ValidatorDefinitions {
+    /// Reads the definitions of `validators_dir`, which are empty if it has none.
+    pub fn open(validators_dir: &Path) -> Result<Self, ValidatorDefinitionsError> {
+        let contents = match fs::read_to_string(validators_dir.join(CONFIG_FILENAME)) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let docs = YamlLoader::load_from_str(&contents)
+            .map_err(|e| ValidatorDefinitionsError::InvalidYaml(format!("{}", e)))?;
+        let definitions = match docs.first() {
+            Some(Yaml::Array(definitions)) => definitions
+                .iter()
+                .enumerate()
+                .map(|(i, yaml)| ValidatorDefinition::from_yaml(i, yaml))
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(Yaml::Null) | None => vec![],
+            Some(_) => {
+                return Err(ValidatorDefinitionsError::InvalidYaml(
+                    "Expected a list of validators".to_string(),
+                ))
+            }
+        };
+        Ok(ValidatorDefinitions(definitions))
+    }
+
+    /// Writes the definitions to `validators_dir`, replacing any already there.
+    pub fn save(&self, validators_dir: &Path) -> Result<(), ValidatorDefinitionsError> {
+        let yaml = Yaml::Array(self.0.iter().map(ValidatorDefinition::to_yaml).collect());
+        let mut contents = String::new();
+        YamlEmitter::new(&mut contents)
+            .dump(&yaml)
+            .map_err(|e| ValidatorDefinitionsError::InvalidYaml(format!("{:?}", e)))?;
+        contents.push('\n');
+
+        /*
+         * The validator client may read the file at any time, so it is replaced in one rename.
+         */
+        fs::create_dir_all(validators_dir)?;
+        let temp_path = validators_dir.join(format!("{}.tmp", CONFIG_FILENAME));
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, validators_dir.join(CONFIG_FILENAME))?;
+        Ok(())
+    }
+
+    /// Copies the keystore at `keystore_path` into its own dir within `validators_dir` and adds an
+    /// enabled definition of it, once `password` has been shown to decrypt it.
+    pub fn import_keystore(
+        &mut self,
+        validators_dir: &Path,
+        keystore_path: &Path,
+        password: KeystorePassword,
+    ) -> Result<PublicKey, ValidatorDefinitionsError> {
+        let keystore_error =
+            |e| ValidatorDefinitionsError::Keystore(keystore_path.to_path_buf(), e);
+        let keystore = Keystore::from_file(keystore_path).map_err(keystore_error)?;
+        if self
+            .0
+            .iter()
+            .any(|d| d.voting_public_key == keystore.pubkey)
+        {
+            return Err(ValidatorDefinitionsError::DuplicateValidator(
+                keystore.pubkey,
+            ));
+        }
+        keystore
+            .decrypt(&password.read()?)
+            .map_err(keystore_error)?;
+
+        let dir = validators_dir.join(format!("0x{}", hex::encode(keystore.pubkey.as_bytes())));
+        fs::create_dir_all(&dir)?;
+        let voting_keystore_path = dir.join(VOTING_KEYSTORE_FILE);
+        fs::copy(keystore_path, &voting_keystore_path)?;
+
+        self.0.push(ValidatorDefinition {
+            enabled: true,
+            voting_public_key: keystore.pubkey.clone(),
+            description: keystore.description,
+            graffiti: None,
+            signing_definition: SigningDefinition::LocalKeystore {
+                voting_keystore_path,
+                voting_keystore_password: password,
+            },
+        });
+        Ok(keystore.pubkey)
+    }
+}
+
+/// Finds the `keystore*.json` files within `dir` and its subdirs, in order of path.
+pub fn find_keystores(dir: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut keystores = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            keystores.append(&mut find_keystores(&path)?);
+            continue;
+        }
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if name.starts_with("keystore") && name.ends_with(".json") {
+            keystores.push(path);
+        }
+    }
+    keystores.sort();
+    Ok(keystores)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::super::keystore::Kdf;
+    use super::*;
+
+    /// Returns a new empty dir for the test `name`.
+    pub fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("validator_client_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a keystore of `keypair` encrypted with `password` to `path`.
+    pub fn write_keystore(path: &Path, keypair: &Keypair, password: &str) {
+        let kdf = Kdf::Pbkdf2 {
+            c: 16,
+            salt: vec![1; 32],
+        };
+        let keystore = Keystore::encrypt(keypair, password, kdf, &[2; 16], "id").unwrap();
+        fs::write(path, keystore.to_json().to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_open() {
+        let dir = test_dir("definitions");
+        assert_eq!(
+            ValidatorDefinitions::open(&dir).unwrap(),
+            ValidatorDefinitions(vec![])
+        );
+
+        let definitions = ValidatorDefinitions(vec![
+            ValidatorDefinition {
+                enabled: true,
+                voting_public_key: Keypair::random().pk,
+                description: "first".to_string(),
+                graffiti: Some("first: graffiti".to_string()),
+                signing_definition: SigningDefinition::LocalKeystore {
+                    voting_keystore_path: dir.join("a/voting-keystore.json"),
+                    voting_keystore_password: KeystorePassword::File(dir.join("a.pass")),
+                },
+            },
+            ValidatorDefinition {
+                enabled: false,
+                voting_public_key: Keypair::random().pk,
+                description: String::new(),
+                graffiti: None,
+                signing_definition: SigningDefinition::LocalKeystore {
+                    voting_keystore_path: dir.join("b/voting-keystore.json"),
+                    voting_keystore_password: KeystorePassword::Inline("pass: word".to_string()),
+                },
+            },
+            ValidatorDefinition {
+                enabled: true,
+                voting_public_key: Keypair::random().pk,
+                description: String::new(),
+                graffiti: None,
+                signing_definition: SigningDefinition::RemoteSigner {
+                    url: "https://signer:9000".to_string(),
+                    request_timeout_ms: Some(500),
+                },
+            },
+        ]);
+        definitions.save(&dir).unwrap();
+        assert_eq!(ValidatorDefinitions::open(&dir).unwrap(), definitions);
+
+        fs::write(dir.join(CONFIG_FILENAME), "- enabled: true\n").unwrap();
+        match ValidatorDefinitions::open(&dir) {
+            Err(ValidatorDefinitionsError::InvalidDefinition(0, _)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        let remote = format!(
+            "- enabled: true\n  voting_public_key: \"0x{}\"\n  type: remote_signer\n",
+            hex::encode(Keypair::random().pk.as_bytes())
+        );
+        fs::write(dir.join(CONFIG_FILENAME), remote).unwrap();
+        match ValidatorDefinitions::open(&dir) {
+            Err(ValidatorDefinitionsError::InvalidDefinition(0, ref field)) if field == "url" => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        let long_graffiti = format!(
+            "- enabled: true\n  voting_public_key: \"0x{}\"\n  type: remote_signer\n  \
+             url: \"https://signer\"\n  graffiti: {}\n",
+            hex::encode(Keypair::random().pk.as_bytes()),
+            "a".repeat(33)
+        );
+        fs::write(dir.join(CONFIG_FILENAME), long_graffiti).unwrap();
+        match ValidatorDefinitions::open(&dir) {
+            Err(ValidatorDefinitionsError::InvalidDefinition(0, ref field))
+                if field == "graffiti" => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_keystore() {
+        let dir = test_dir("import");
+        let validators_dir = dir.join("validators");
+        let keypairs = [Keypair::random(), Keypair::random()];
+        fs::create_dir_all(dir.join("keys/nested")).unwrap();
+        write_keystore(&dir.join("keys/keystore-0.json"), &keypairs[0], "password");
+        write_keystore(
+            &dir.join("keys/nested/keystore-1.json"),
+            &keypairs[1],
+            "other",
+        );
+        fs::write(dir.join("keys/deposit_data.json"), "[]").unwrap();
+        fs::write(dir.join("password.txt"), "password\n").unwrap();
+
+        let keystores = find_keystores(&dir.join("keys")).unwrap();
+        assert_eq!(
+            keystores,
+            vec![
+                dir.join("keys/keystore-0.json"),
+                dir.join("keys/nested/keystore-1.json")
+            ]
+        );
+
+        let mut definitions = ValidatorDefinitions::default();
+        let password_file = KeystorePassword::File(dir.join("password.txt"));
+        let pubkey = definitions
+            .import_keystore(&validators_dir, &keystores[0], password_file.clone())
+            .unwrap();
+        assert_eq!(pubkey, keypairs[0].pk);
+        match definitions.import_keystore(&validators_dir, &keystores[0], password_file.clone()) {
+            Err(ValidatorDefinitionsError::DuplicateValidator(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        match definitions.import_keystore(&validators_dir, &keystores[1], password_file) {
+            Err(ValidatorDefinitionsError::Keystore(_, KeystoreError::IncorrectPassword)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        let inline = KeystorePassword::Inline("other".to_string());
+        definitions
+            .import_keystore(&validators_dir, &keystores[1], inline)
+            .unwrap();
+
+        assert_eq!(definitions.0.len(), 2);
+        assert!(definitions.0.iter().all(|d| d.enabled));
+        let expected_path = validators_dir
+            .join(format!("0x{}", hex::encode(keypairs[0].pk.as_bytes())))
+            .join(VOTING_KEYSTORE_FILE);
+        match definitions.0[0].signing_definition {
+            SigningDefinition::LocalKeystore {
+                ref voting_keystore_path,
+                ref voting_keystore_password,
+            } => {
+                assert_eq!(*voting_keystore_path, expected_path);
+                let keypair =
+                    decrypt_keystore(voting_keystore_path, voting_keystore_password, &pubkey);
+                assert_eq!(keypair.unwrap().pk, pubkey);
+                match decrypt_keystore(
+                    voting_keystore_path,
+                    voting_keystore_password,
+                    &keypairs[1].pk,
+                ) {
+                    Err(ValidatorDefinitionsError::Keystore(
+                        _,
+                        KeystoreError::PublicKeyMismatch,
+                    )) => {}
+                    other => panic!("Unexpected result: {:?}", other),
+                }
+            }
+            ref other => panic!("Unexpected definition: {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
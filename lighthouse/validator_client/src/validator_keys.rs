@@ -0,0 +1,130 @@
+use super::deposit::DepositData;
+use super::key_derivation::{
+    derive_path, keypair_from_scalar, voting_key_path, withdrawal_key_path, KeyDerivationError,
+};
+use super::keystore::{random_uuid, Kdf, Keystore, KeystoreError};
+use bip39::{Language, Mnemonic, MnemonicType, Seed};
+use bls::Keypair;
+use rand::{thread_rng, Rng};
+
+/// Returns a new random 24-word BIP-39 mnemonic, from which validator keys may be derived.
+pub fn generate_mnemonic() -> String {
+    Mnemonic::new(MnemonicType::Words24, Language::English).into_phrase()
+}
+
+/// Returns the seed of the English BIP-39 mnemonic `phrase` with `passphrase`, which is usually
+/// empty.
+pub fn mnemonic_seed(phrase: &str, passphrase: &str) -> Result<Vec<u8>, KeyDerivationError> {
+    let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mnemonic = Mnemonic::from_phrase(&phrase, Language::English)
+        .map_err(|e| KeyDerivationError::InvalidMnemonic(format!("{}", e)))?;
+    Ok(Seed::new(&mnemonic, passphrase).as_bytes().to_vec())
+}
+
+/// The keys of the validator with `index` in a seed, at the paths of EIP-2334.
+pub struct ValidatorKeys {
+    pub index: u32,
+    pub voting: Keypair,
+    pub withdrawal: Keypair,
+}
+
+impl ValidatorKeys {
+    pub fn derive(seed: &[u8], index: u32) -> Result<Self, KeyDerivationError> {
+        Ok(Self {
+            index,
+            voting: keypair_from_scalar(&derive_path(seed, &voting_key_path(index))?),
+            withdrawal: keypair_from_scalar(&derive_path(seed, &withdrawal_key_path(index))?),
+        })
+    }
+
+    /// Returns the deposit of `amount` Gwei for the validator on the chain of `fork_version`.
+    pub fn deposit_data(&self, amount: u64, fork_version: [u8; 4]) -> DepositData {
+        DepositData::new(&self.voting, &self.withdrawal.pk, amount, fork_version)
+    }
+
+    /// Encrypts the voting key with `password`, with a random IV and UUID.
+    ///
+    /// The withdrawal key is not stored, as it is only needed after exit and can be derived again
+    /// from the mnemonic.
+    pub fn voting_keystore(&self, password: &str, kdf: Kdf) -> Result<Keystore, KeystoreError> {
+        let iv: [u8; 16] = thread_rng().gen();
+        let mut keystore = Keystore::encrypt(&self.voting, password, kdf, &iv, &random_uuid())?;
+        keystore.path = voting_key_path(self.index);
+        Ok(keystore)
+    }
+
+    /// Returns the name of the voting keystore file, as written by the deposit CLI.
+    pub fn keystore_file_name(&self) -> String {
+        format!(
+            "keystore-{}.json",
+            voting_key_path(self.index).replace('/', "_")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::deposit::MAX_EFFECTIVE_BALANCE;
+    use super::*;
+    use hex;
+
+    const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                          abandon abandon abandon about";
+
+    #[test]
+    fn test_mnemonic_seed() {
+        /*
+         * The seed of the first test case of EIP-2333.
+         */
+        assert_eq!(
+            hex::encode(mnemonic_seed(PHRASE, "TREZOR").unwrap()),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+        assert_eq!(
+            mnemonic_seed(&format!("  {}\n", PHRASE), "").unwrap(),
+            mnemonic_seed(PHRASE, "").unwrap()
+        );
+        match mnemonic_seed(&PHRASE.replace("about", "abandon"), "") {
+            Err(KeyDerivationError::InvalidMnemonic(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let phrase = generate_mnemonic();
+        assert_eq!(phrase.split(' ').count(), 24);
+        assert!(mnemonic_seed(&phrase, "").is_ok());
+    }
+
+    #[test]
+    fn test_validator_keys() {
+        let seed = mnemonic_seed(PHRASE, "TREZOR").unwrap();
+        let keys = ValidatorKeys::derive(&seed, 0).unwrap();
+        assert_eq!(
+            hex::encode(&keys.voting.sk.as_bytes()[16..]),
+            "032e6c3c7359223e127e9479afc521c4342f8903bc29ae01b671bcbcc98be0f6"
+        );
+        assert_ne!(keys.withdrawal.pk, keys.voting.pk);
+        assert_ne!(
+            ValidatorKeys::derive(&seed, 1).unwrap().voting.pk,
+            keys.voting.pk
+        );
+        assert_eq!(
+            keys.keystore_file_name(),
+            "keystore-m_12381_3600_0_0_0.json"
+        );
+
+        let deposit = keys.deposit_data(MAX_EFFECTIVE_BALANCE, [0; 4]);
+        assert_eq!(deposit.pubkey, keys.voting.pk);
+
+        let kdf = Kdf::Pbkdf2 {
+            c: 16,
+            salt: vec![1; 32],
+        };
+        let keystore = keys.voting_keystore("password", kdf.clone()).unwrap();
+        assert_eq!(keystore.path, "m/12381/3600/0/0/0");
+        assert_eq!(keystore.decrypt("password").unwrap().pk, keys.voting.pk);
+        assert_ne!(
+            keys.voting_keystore("password", kdf).unwrap().uuid,
+            keystore.uuid
+        );
+    }
+}